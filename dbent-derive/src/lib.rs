@@ -12,14 +12,109 @@ use quote::quote;
 #[cfg(test)]
 mod tests;
 
-/// This macro generates an implementation of the `Keyed` trait for structs
-/// that have a single `Key<T>` defined
-#[proc_macro_derive(Entity)]
+/// This macro generates an implementation of the `Keyed`, `KeyedMut`,
+/// `KeyedInfallible` and `hooks::Lifecycle` traits for structs that have a
+/// single `Key<T>` defined
+///
+/// The struct can be marked with `#[entity(...)]` to generate more:
+///
+/// - `timestamps`: implements `Timestamps` over the `created_at` and `updated_at` fields,
+///   touched by repositories on insert and update
+/// - `hooks`: makes repositories call the `hooks::EntityHooks` implemented for the struct
+/// - `fields`: implements `sql::Columns` and generates a module named after the struct in
+///   snake case with a `fields` submodule holding typed column tokens; only for structs
+///   defined at module level
+/// - `table = "name"`: sets the table name, which defaults to the struct name in snake case;
+///   needs `fields`
+/// - `id_prefix = "usr"`: sets the prefix of the `external_id::ExternalId` of the entity,
+///   which defaults to the table name; needs `fields`
+/// - `unique("a", "b")`: adds a UNIQUE constraint over the columns to the schema; can be
+///   repeated and needs `fields`
+/// - `version = 3`: implements `format::FormatVersion` with the format version, and
+///   `migrate = "path"` sets its migration function
+/// - `accessors`: generates `{field}_key` methods for the `Entity`, `EntityLabel` and
+///   `Parent` fields, and `{field}_label` methods for the `EntityLabel` fields
+/// - `queries`: generates `exists(conn, &key)`, `count(conn)` and `count_where(conn, &filter)`
+///   functions over the table of the entity with the `rusqlite` feature, and `select()`
+///   starting a `sqlite::Select` for entities implementing `FromRow`; needs `fields`
+/// - `track_reads`: generates a getter named after each column field, recording
+///   the reads of the column with the installed `reads::ReadTracker`; needs `fields`
+/// - `builder`: generates a `builder()` function returning a `{Struct}Builder` with a
+///   setter per field and `build()`, starting from an unset key, `Many` fields
+///   `NotFetched` and the other fields to their default, for tests building entities
+///   with only the fields they care about
+/// - `seeded`: implements `testing::Seeded` with the `testing` feature, building each
+///   field from the seed, or setting it to its default for types that aren't `Seeded`
+/// - `default_fetch = "not_fetched"`: implements `Default` with `Many` fields set to
+///   `NotFetched`, or `None` with `"none"`, and the other fields to their default
+/// - `key_type = "i64"`: sets the key type, for a first field whose type the macro
+///   can't see is a `Key`, like an alias of `Key<i64>`
+///
+/// Fields can be marked with `#[entity(...)]` too:
+///
+/// - `column = "name"`: sets the column name, which defaults to the field name; like
+///   `table`, it must be made of ASCII letters, digits and underscores
+/// - `default_fetch = "none"`: overrides the default of a `Many` field, needs
+///   `default_fetch` on the struct
+/// - `optional`: declares an `Entity`, `EntityLabel` or `Parent` field as a relation
+///   that may be None, with a nullable column; they are required otherwise
+/// - `on_delete = "cascade"`: sets what deleting the referenced entity does to the
+///   entity with `sqlite::delete_cascading`, deleting it with `"cascade"`, clearing the
+///   relation with `"set_null"`, which needs `optional`, or failing the delete with
+///   `"restrict"`; only for `Entity`, `EntityLabel` and `Parent` fields
+/// - `encrypt`: encrypts the column with the installed `cipher::FieldCipher`, and
+///   declares it without a type; not for relation fields
+/// - `cfg(feature = "full")`: keeps the field in the struct but leaves it out of the
+///   generated columns, schema, graph relations and accessors unless the predicate
+///   holds, with `FromRow` setting it to its default; a field marked with
+///   `#[cfg(...)]` itself is left out of all the generated items when removed
+///
+/// A `Version` field marked with `#[version]` implements `Versioned`, guarding
+/// the updates of repositories with the version, and an
+/// `i64` field marked with `#[position]` implements `position::Positioned`.
+#[proc_macro_derive(Entity, attributes(entity, version, position))]
 pub fn derive_entity(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     impl_entity(&input).unwrap_or_else(|err| err.to_compile_error()).into()
 }
 
+/// The same as `#[derive(Entity)]`, for invoking as `#[derive(dbent::Keyed)]`
+/// in crates where `Entity` names another derive
+#[proc_macro_derive(Keyed, attributes(entity, version, position))]
+pub fn derive_keyed(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    impl_entity(&input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+/// This macro generates an implementation of the `sqlite::FromRow` trait for
+/// structs with named fields, reading each field from the column of the same name,
+/// or with a prefix for `FromRow::from_row_prefixed`
+///
+/// `Many` fields are left `NotFetched`, and `Entity` and `Parent` fields are
+/// read from their key column. Fields can be marked with `#[entity(...)]`:
+///
+/// - `column = "name"`: sets the column name, which defaults to the field name
+/// - `join(label = "name")`: reads an `EntityLabel` from its key column and the
+///   label column of a join; required for `EntityLabel` fields
+/// - `encrypt`: decrypts the column with the installed `cipher::FieldCipher`
+#[proc_macro_derive(FromRow, attributes(entity))]
+pub fn derive_from_row(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    impl_from_row(&input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+/// This macro generates the implementations for keyless structs, like the
+/// rows of a SELECT-only projection or a view
+///
+/// It implements `sql::Columns`, `sqlite::FromRow` with the `rusqlite` feature,
+/// and `Label` if a field is marked with `#[label]`, but not `Keyed`. Fields
+/// take the `#[entity(...)]` options of `#[derive(FromRow)]`.
+#[proc_macro_derive(Projection, attributes(entity, label))]
+pub fn derive_projection(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    impl_projection(&input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
 /// This macro generates an implementation of the `Label` trait for structs
 /// that have a field marked with `#[label]` attribute
 #[proc_macro_derive(Label, attributes(label))]
@@ -28,39 +123,1545 @@ pub fn derive_label(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     impl_label(&input).unwrap_or_else(|err| err.to_compile_error()).into()
 }
 
-/// Returns the implementation of the `Keyed` trait
-fn impl_entity(input: &DeriveInput) -> Result<TokenStream, Error> {
+/// The same as `#[derive(Label)]`, for invoking as `#[derive(dbent::Labeled)]`
+/// in crates where `Label` names another derive
+#[proc_macro_derive(Labeled, attributes(label))]
+pub fn derive_labeled(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    impl_label(&input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+/// This macro generates an implementation of the `Validate` trait for structs
+/// with fields marked with `#[validate(...)]` attributes
+#[proc_macro_derive(Validate, attributes(validate))]
+pub fn derive_validate(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    impl_validate(&input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+/// This macro generates an implementation of the `sql::DbEnum` trait for enums
+/// of unit variants, with `schema::ColumnType`, `From<Enum>` for `sql::Value`,
+/// and with the `rusqlite` feature `ToSql` and `FromSql`, so they can be used
+/// as the type of entity fields
+///
+/// Variants are stored as TEXT, by default as their name in snake case, or
+/// as INTEGER with `#[db_enum(integer)]` on the enum, where each variant needs
+/// an explicit discriminant like `Active = 1`. The stored value of a variant
+/// can be set with `#[db_enum(value = "...")]`, or `#[db_enum(value = 1)]`
+/// for integers, to keep it when the variant is renamed.
+#[proc_macro_derive(DbEnum, attributes(db_enum))]
+pub fn derive_db_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    impl_db_enum(&input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+/// This macro generates a `{Struct}Dto` struct with the fields of a struct
+/// flattened to types without dbent enums, and an implementation of the
+/// `dto::IntoDto` trait with `From` conversions both ways
+///
+/// `Key` fields become an `Option` of the key, `Entity` and `Parent` fields
+/// an `Option` of their key, `EntityLabel` fields an `Option` of their key and
+/// a `{field}_label` field with an `Option` of their label, and `Many` fields a
+/// `Vec` of the DTOs of their entities. The struct can be marked with
+/// `#[dto(...)]`:
+///
+/// - `name = "Name"`: sets the name of the DTO, which defaults to `{Struct}Dto`
+/// - `derive(...)`: adds derives to the DTO, like `derive(Clone, Serialize)`
+///
+/// Fields marked with `#[dto(skip)]` are left out of the DTO, and set to their
+/// default when converting back.
+#[proc_macro_derive(IntoDto, attributes(dto))]
+pub fn derive_into_dto(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    impl_into_dto(&input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+/// This macro generates an implementation of the `denormalized::Denormalized`
+/// trait for read models, from the source entities declared with
+/// `#[denormalized(...)]` on the struct:
+///
+/// - `source(Path)`: declares an entity implementing `schema::Schema` the rows
+///   are derived from; can be repeated
+/// - `source(Path, sql = "...")`: also sets the SQL recomputing the rows derived
+///   from a row of the source, whose key is bound to `?1`
+#[proc_macro_derive(Denormalized, attributes(denormalized))]
+pub fn derive_denormalized(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    impl_denormalized(&input).unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+/// This attribute adds serde defaults to the relation fields of a struct, so
+/// they don't need to be repeated on each of them
+///
+/// It must come before `#[derive(Serialize, Deserialize)]`. `Many` fields are
+/// skipped when `NotFetched` and default to `NotFetched` when missing, and
+/// `Entity`, `EntityLabel` and `Parent` fields are skipped when `None` and
+/// default to `None`. Options already set on a field with `#[serde(...)]` are
+/// kept.
+///
+/// ```
+/// use dbent::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[serde_relations]
+/// #[derive(Entity, Serialize, Deserialize)]
+/// struct Order {
+///     id: Key<Int>,
+///     customer: Entity<Int, String>,
+///     lines: Many<String>,
+/// }
+///
+/// let order = Order { id: Key::new(1), customer: Entity::None, lines: Many::NotFetched };
+/// assert_eq!(serde_json::to_string(&order).unwrap(), r#"{"id":1}"#);
+///
+/// let order: Order = serde_json::from_str(r#"{"id":1}"#).unwrap();
+/// assert!(order.lines.is_not_fetched());
+/// ```
+#[proc_macro_attribute]
+pub fn serde_relations(args: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    let args = TokenStream::from(args);
+    let result = if args.is_empty() {
+        impl_serde_relations(&mut input)
+    } else {
+        Err(Error::new(args.span(), "#[serde_relations] takes no options"))
+    };
+    match result {
+        Ok(()) => quote!(#input).into(),
+        Err(err) => {
+            let err = err.to_compile_error();
+            quote!(#err #input).into()
+        },
+    }
+}
+
+/// Adds the serde defaults of `#[serde_relations]` to the relation fields
+fn impl_serde_relations(input: &mut DeriveInput) -> Result<(), Error> {
+    let span = input.span();
+    let fields = match &mut input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(fields), .. }) => fields,
+        _ => return Err(Error::new(span, "#[serde_relations] can only be used on structs with named fields")),
+    };
+
+    for field in fields.named.iter_mut() {
+        let Some(ident) = last_segment(&field.ty).map(|seg| seg.ident.to_string()) else {
+            continue;
+        };
+        let (default, skip) = match ident.as_str() {
+            "Many" => (quote!(default = "::dbent::serde::not_fetched"), "::dbent::Many::is_not_fetched"),
+            "Entity" => (quote!(default), "::dbent::Entity::is_none"),
+            "EntityLabel" => (quote!(default), "::dbent::EntityLabel::is_none"),
+            "Parent" => (quote!(default), "::dbent::serde::parent_is_none"),
+            _ => continue,
+        };
+
+        let set = serde_options(&field.attrs)?;
+        if !set.iter().any(|option| option == "default") {
+            field.attrs.push(syn::parse_quote!(#[serde(#default)]));
+        }
+        if !set.iter().any(|option| option.starts_with("skip")) {
+            field.attrs.push(syn::parse_quote!(#[serde(skip_serializing_if = #skip)]));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the names of the options set with `#[serde(...)]`
+fn serde_options(attrs: &[syn::Attribute]) -> Result<Vec<String>, Error> {
+    let mut options = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+        if let syn::Meta::List(list) = attr.parse_meta()? {
+            for nested in list.nested {
+                let path = match nested {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) => path,
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) => value.path,
+                    syn::NestedMeta::Meta(syn::Meta::List(list)) => list.path,
+                    syn::NestedMeta::Lit(_) => continue,
+                };
+                if let Some(ident) = path.get_ident() {
+                    options.push(ident.to_string());
+                }
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Returns the implementation of the `Keyed` and `KeyedMut` traits
+fn impl_entity(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let attrs = EntityAttrs::parse(&input.attrs)?;
+
+    let (key_type, key_ident) = match input.data {
+        syn::Data::Struct(ref body) => single_key(&body.fields, attrs.key_type.as_ref())?,
+        _ => panic!("#[derive(Entity)] can only be used on structs"),
+    };
+
+    let timestamps = if attrs.timestamps {
+        impl_timestamps(input)?
+    } else {
+        TokenStream::new()
+    };
+
+    let versioned = impl_versioned(input)?;
+
+    let lifecycle = impl_lifecycle(input, &attrs, !versioned.is_empty());
+
+    let positioned = impl_positioned(input)?;
+
+    let format_version = match (&attrs.version, &attrs.migrate) {
+        (Some(version), migrate) => impl_format_version(input, version, migrate.as_ref()),
+        (None, Some(migrate)) => return Err(Error::new(migrate.span(), "#[entity(migrate = \"...\")] needs #[entity(version = ...)]")),
+        (None, None) => TokenStream::new(),
+    };
+
+    let accessors = if attrs.accessors {
+        impl_accessors(input)?
+    } else {
+        TokenStream::new()
+    };
+
+    let queries = if attrs.queries {
+        impl_queries(input)
+    } else {
+        TokenStream::new()
+    };
+
+    let builder = if attrs.builder {
+        impl_builder(input)?
+    } else {
+        TokenStream::new()
+    };
+
+    let seeded = if attrs.seeded {
+        impl_seeded(input)?
+    } else {
+        TokenStream::new()
+    };
+
+    let default = impl_default(input, attrs.default_fetch)?;
+
+    let fields = if attrs.fields {
+        impl_fields(input, &attrs)?
+    } else if attrs.table.is_some() {
+        return Err(Error::new(input.span(), "#[entity(table = \"...\")] needs #[entity(fields)]"));
+    } else if attrs.id_prefix.is_some() {
+        return Err(Error::new(input.span(), "#[entity(id_prefix = \"...\")] needs #[entity(fields)]"));
+    } else if !attrs.unique.is_empty() {
+        return Err(Error::new(input.span(), "#[entity(unique(...))] needs #[entity(fields)]"));
+    } else if attrs.track_reads {
+        return Err(Error::new(input.span(), "#[entity(track_reads)] needs #[entity(fields)]"));
+    } else if attrs.queries {
+        return Err(Error::new(input.span(), "#[entity(queries)] needs #[entity(fields)]"));
+    } else {
+        TokenStream::new()
+    };
+
+    Ok(
+        quote!{
+            #[automatically_derived]
+            impl #impl_generics ::dbent::Keyed for #name #ty_generics #where_clause {
+                type KeyType = #key_type;
+
+                #[inline]
+                fn key(&self) -> ::dbent::Result<&::dbent::Key<Self::KeyType>> {
+                    Ok(&self.#key_ident)
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics ::dbent::KeyedMut for #name #ty_generics #where_clause {
+                #[inline]
+                fn key_mut(&mut self) -> ::dbent::Result<&mut ::dbent::Key<Self::KeyType>> {
+                    Ok(&mut self.#key_ident)
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics ::dbent::KeyedInfallible for #name #ty_generics #where_clause {
+                #[inline]
+                fn key_infallible(&self) -> &::dbent::Key<Self::KeyType> {
+                    &self.#key_ident
+                }
+            }
+
+            #timestamps
+
+            #versioned
+
+            #lifecycle
+
+            #positioned
+
+            #accessors
+
+            #queries
+
+            #builder
+
+            #seeded
+
+            #default
+
+            #format_version
+
+            #fields
+        }
+    )
+}
+
+/// Options set with `#[entity(...)]` on the struct
+#[derive(Default)]
+struct EntityAttrs {
+    timestamps: bool,
+    hooks: bool,
+    fields: bool,
+    accessors: bool,
+    queries: bool,
+    track_reads: bool,
+    builder: bool,
+    seeded: bool,
+    version: Option<syn::LitInt>,
+    migrate: Option<syn::Path>,
+    default_fetch: Option<DefaultFetch>,
+    key_type: Option<syn::Type>,
+    table: Option<String>,
+    id_prefix: Option<String>,
+    unique: Vec<(Vec<String>, proc_macro2::Span)>,
+}
+
+impl EntityAttrs {
+    /// Parses all the `#[entity(...)]` attributes
+    fn parse(attrs: &[syn::Attribute]) -> Result<Self, Error> {
+        let mut result = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("entity")) {
+            let list = match attr.parse_meta()? {
+                syn::Meta::List(list) => list,
+                meta => return Err(Error::new(meta.span(), "#[derive(Entity)] expects a list of options like #[entity(timestamps)]")),
+            };
+
+            for nested in &list.nested {
+                match nested {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("timestamps") => result.timestamps = true,
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("hooks") => result.hooks = true,
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("fields") => result.fields = true,
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("accessors") => result.accessors = true,
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("queries") => result.queries = true,
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("track_reads") => result.track_reads = true,
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("builder") => result.builder = true,
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("seeded") => result.seeded = true,
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("version") => match &value.lit {
+                        syn::Lit::Int(lit) => result.version = Some(lit.clone()),
+                        lit => return Err(Error::new(lit.span(), "#[entity(version = ...)] expects an integer literal")),
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("migrate") => match &value.lit {
+                        syn::Lit::Str(lit) => result.migrate = Some(lit.parse()?),
+                        lit => return Err(Error::new(lit.span(), "#[derive(Entity)] expects a string literal")),
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("table") => {
+                        result.table = Some(sql_ident(&value.lit)?);
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("id_prefix") => {
+                        result.id_prefix = Some(lit_string(&value.lit)?);
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("default_fetch") => {
+                        result.default_fetch = Some(DefaultFetch::parse(&value.lit)?);
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("key_type") => match &value.lit {
+                        syn::Lit::Str(lit) => result.key_type = Some(lit.parse()?),
+                        lit => return Err(Error::new(lit.span(), "#[entity(key_type = \"...\")] expects the key type as a string literal")),
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("unique") => {
+                        let columns = list
+                            .nested
+                            .iter()
+                            .map(|nested| match nested {
+                                syn::NestedMeta::Lit(lit) => lit_string(lit),
+                                nested => Err(Error::new(nested.span(), "#[entity(unique(...))] expects column names")),
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        if columns.is_empty() {
+                            return Err(Error::new(list.span(), "#[entity(unique(...))] expects column names"));
+                        }
+                        result.unique.push((columns, list.span()));
+                    },
+                    nested => return Err(Error::new(nested.span(), "#[derive(Entity)] unknown entity option")),
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Options set with `#[entity(...)]` on a field
+#[derive(Default)]
+struct FieldAttrs {
+    column: Option<String>,
+    join_label: Option<String>,
+    default_fetch: Option<(DefaultFetch, proc_macro2::Span)>,
+    optional: Option<proc_macro2::Span>,
+    encrypt: Option<proc_macro2::Span>,
+    on_delete: Option<(syn::Ident, proc_macro2::Span)>,
+    cfg: Option<syn::NestedMeta>,
+}
+
+impl FieldAttrs {
+    /// Parses all the `#[entity(...)]` attributes of a field
+    fn parse(field: &syn::Field) -> Result<Self, Error> {
+        let mut result = Self::default();
+
+        for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("entity")) {
+            let list = match attr.parse_meta()? {
+                syn::Meta::List(list) => list,
+                meta => return Err(Error::new(meta.span(), "#[derive(Entity)] expects a list of options like #[entity(column = \"name\")]")),
+            };
+
+            for nested in &list.nested {
+                match nested {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("column") => {
+                        result.column = Some(sql_ident(&value.lit)?);
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("default_fetch") => {
+                        result.default_fetch = Some((DefaultFetch::parse(&value.lit)?, value.span()));
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("optional") => {
+                        result.optional = Some(path.span());
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("encrypt") => {
+                        result.encrypt = Some(path.span());
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("on_delete") => {
+                        let variant = match lit_string(&value.lit)?.as_str() {
+                            "cascade" => "Cascade",
+                            "set_null" => "SetNull",
+                            "restrict" => "Restrict",
+                            _ => return Err(Error::new(value.lit.span(), "#[entity(on_delete = \"...\")] expects \"cascade\", \"set_null\" or \"restrict\"")),
+                        };
+                        result.on_delete = Some((syn::Ident::new(variant, value.lit.span()), value.span()));
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("cfg") => match list.nested.first() {
+                        Some(predicate) if list.nested.len() == 1 => result.cfg = Some(predicate.clone()),
+                        _ => return Err(Error::new(list.span(), "#[entity(cfg(...))] expects a single predicate like cfg(feature = \"full\")")),
+                    },
+                    syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("join") => {
+                        for nested in &list.nested {
+                            match nested {
+                                syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("label") => {
+                                    result.join_label = Some(sql_ident(&value.lit)?);
+                                },
+                                nested => return Err(Error::new(nested.span(), "#[entity(join(...))] expects label = \"column\"")),
+                            }
+                        }
+                        if result.join_label.is_none() {
+                            return Err(Error::new(list.span(), "#[entity(join(...))] expects label = \"column\""));
+                        }
+                    },
+                    nested => return Err(Error::new(nested.span(), "#[derive(Entity)] unknown field option")),
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the `#[cfg(...)]` attribute gating the items generated for the
+    /// field, if set
+    fn cfg_attr(&self) -> TokenStream {
+        match &self.cfg {
+            Some(predicate) => quote! { #[cfg(#predicate)] },
+            None => TokenStream::new(),
+        }
+    }
+}
+
+/// The initial state of `Many` fields set with `default_fetch = "..."`
+#[derive(Clone, Copy)]
+enum DefaultFetch {
+    NotFetched,
+    None,
+}
+
+impl DefaultFetch {
+    /// Parses `"not_fetched"` or `"none"`
+    fn parse(lit: &syn::Lit) -> Result<Self, Error> {
+        match lit_string(lit)?.as_str() {
+            "not_fetched" => Ok(DefaultFetch::NotFetched),
+            "none" => Ok(DefaultFetch::None),
+            _ => Err(Error::new(lit.span(), "#[entity(default_fetch = \"...\")] expects \"not_fetched\" or \"none\"")),
+        }
+    }
+}
+
+/// Returns the value of a string literal
+fn lit_string(lit: &syn::Lit) -> Result<String, Error> {
+    match lit {
+        syn::Lit::Str(lit) => Ok(lit.value()),
+        lit => Err(Error::new(lit.span(), "#[derive(Entity)] expects a string literal")),
+    }
+}
+
+/// Returns the value of a string literal naming a table or column, which must
+/// be made of ASCII letters, digits and underscores, not starting with a digit
+fn sql_ident(lit: &syn::Lit) -> Result<String, Error> {
+    let name = lit_string(lit)?;
+    let mut chars = name.chars();
+    let plain = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !plain {
+        return Err(Error::new(lit.span(), format!("#[derive(Entity)] {name:?} is not a valid SQL identifier, use letters, digits and underscores")));
+    }
+    Ok(name)
+}
+
+/// Returns the name of a field without the raw identifier prefix
+fn field_name(field: &syn::Field) -> String {
+    field
+        .ident
+        .as_ref()
+        .map(|ident| ident.to_string().trim_start_matches("r#").to_owned())
+        .unwrap_or_default()
+}
+
+/// Returns the last path segment of a type, if it is a path
+fn last_segment(ty: &syn::Type) -> Option<&syn::PathSegment> {
+    match ty {
+        syn::Type::Path(typepath) => typepath.path.segments.last(),
+        _ => None,
+    }
+}
+
+/// Returns true if the type looks like a `Many`
+fn is_many(ty: &syn::Type) -> bool {
+    last_segment(ty).map(|seg| seg.ident == "Many").unwrap_or(false)
+}
+
+/// The names of `Entity` and its aliases
+const ENTITY_TYPES: &[&str] = &["Entity", "EntityInt", "EntityString", "EntityI64"];
+
+/// The names of `EntityLabel` and its aliases
+const ENTITY_LABEL_TYPES: &[&str] = &["EntityLabel", "EntityLabelInt", "EntityLabelString", "EntityLabelI64"];
+
+/// Returns true if the type looks like a reference to another entity,
+/// like `Entity`, its aliases, `EntityLabel` or `Parent`
+fn is_relation(ty: &syn::Type) -> bool {
+    last_segment(ty)
+        .map(|seg| ENTITY_TYPES.iter().any(|name| seg.ident == name) || is_entity_label(ty) || seg.ident == "Parent")
+        .unwrap_or(false)
+}
+
+/// Returns true if the type looks like a `PolyEntity`
+fn is_poly(ty: &syn::Type) -> bool {
+    last_segment(ty).map(|seg| seg.ident == "PolyEntity").unwrap_or(false)
+}
+
+/// Returns true if the type looks like a `Parent`
+fn is_parent(ty: &syn::Type) -> bool {
+    last_segment(ty).map(|seg| seg.ident == "Parent").unwrap_or(false)
+}
+
+/// Returns true if the type looks like an `EntityLabel` or one of its aliases
+fn is_entity_label(ty: &syn::Type) -> bool {
+    last_segment(ty)
+        .map(|seg| ENTITY_LABEL_TYPES.iter().any(|name| seg.ident == name))
+        .unwrap_or(false)
+}
+
+/// Converts a CamelCase name to snake_case
+fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut result = String::new();
+
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let prev_lower = i > 0 && (chars[i - 1].is_lowercase() || chars[i - 1].is_ascii_digit());
+            let next_lower = i > 0 && chars.get(i + 1).map(|c| c.is_lowercase()).unwrap_or(false) && chars[i - 1].is_uppercase();
+            if prev_lower || next_lower {
+                result.push('_');
+            }
+            result.extend(c.to_lowercase());
+        } else {
+            result.push(*c);
+        }
+    }
+
+    result
+}
+
+/// Returns the column value, encrypted for `#[entity(encrypt)]` fields
+fn encrypted_value(column: &str, encrypt: bool, value: TokenStream) -> TokenStream {
+    if encrypt {
+        quote! { ::dbent::cipher::__private::encrypted(#column, #value)? }
+    } else {
+        value
+    }
+}
+
+/// Returns the module with the typed column tokens and the `Columns` and
+/// `Schema` implementations
+fn impl_fields(input: &DeriveInput, attrs: &EntityAttrs) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(input.generics.span(), "#[entity(fields)] can't be used on generic structs"));
+    }
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Err(Error::new(input.span(), "#[entity(fields)] can only be used on structs with named fields")),
+    };
+
+    let (module_vis, fields_vis, item_vis) = match &input.vis {
+        syn::Visibility::Inherited => (quote! {}, quote! { pub(super) }, quote! { pub(in super::super) }),
+        vis => (quote! { #vis }, quote! { pub }, quote! { pub }),
+    };
+
+    let module = syn::Ident::new(&snake_case(&name.to_string()), name.span());
+    let table = attrs.table.clone().unwrap_or_else(|| module.to_string());
+    let id_prefix = match &attrs.id_prefix {
+        Some(prefix) => quote! { ::core::option::Option::Some(#prefix.into()) },
+        None => quote! { ::core::option::Option::None },
+    };
+    let id_prefix_value = attrs.id_prefix.clone().unwrap_or_else(|| table.clone());
+    let (vis, track_reads) = (&input.vis, attrs.track_reads);
+
+    let mut tokens = Vec::new();
+    let mut getters = Vec::new();
+    let mut values = Vec::new();
+    let mut columns = Vec::new();
+    let mut column_cfgs = Vec::new();
+    let mut metas = Vec::new();
+    let mut many = Vec::new();
+    let mut relations = Vec::new();
+    let mut clear_keys = Vec::new();
+    for (index, field) in fields.named.iter().enumerate() {
+        let attrs = FieldAttrs::parse(field)?;
+        if let (Some(predicate), 0) = (&attrs.cfg, index) {
+            return Err(Error::new(predicate.span(), "#[entity(cfg(...))] can't be used on the key field"));
+        }
+        let cfg = attrs.cfg_attr();
+        if let (Some(span), false) = (attrs.optional, is_relation(&field.ty) || is_poly(&field.ty)) {
+            return Err(Error::new(span, "#[entity(optional)] can only be used on Entity, EntityLabel, Parent and PolyEntity fields"));
+        }
+        if let (Some(span), true) = (attrs.encrypt, is_many(&field.ty) || is_relation(&field.ty) || is_poly(&field.ty)) {
+            return Err(Error::new(span, "#[entity(encrypt)] can't be used on relation fields"));
+        }
+        match (&attrs.on_delete, attrs.optional) {
+            (Some((_, span)), _) if !is_relation(&field.ty) => {
+                return Err(Error::new(*span, "#[entity(on_delete = \"...\")] can only be used on Entity, EntityLabel and Parent fields"));
+            },
+            (Some((variant, span)), None) if variant == "SetNull" => {
+                return Err(Error::new(*span, "#[entity(on_delete = \"set_null\")] needs #[entity(optional)]"));
+            },
+            _ => {},
+        }
+
+        if is_many(&field.ty) || is_relation(&field.ty) || is_poly(&field.ty) {
+            let ident = &field.ident;
+            let name = field_name(field);
+            let (kind, cardinality) = match (is_many(&field.ty), attrs.optional) {
+                (true, _) => (quote! { Many }, quote! { Many }),
+                (false, Some(_)) => (quote! { One }, quote! { OptionalOne }),
+                (false, None) => (quote! { One }, quote! { RequiredOne }),
+            };
+            relations.push(quote! {
+                #cfg
+                ::dbent::graph::GraphRelation {
+                    field: #name,
+                    kind: ::dbent::graph::RelationKind::#kind,
+                    cardinality: ::dbent::schema::Cardinality::#cardinality,
+                    state: (&NodesOf(&self.#ident)).state(),
+                    nodes: (&NodesOf(&self.#ident)).nodes(),
+                },
+            });
+            clear_keys.push(if is_many(&field.ty) {
+                quote! { (&mut ChildrenOf(&mut self.#ident)).clear_keys(); }
+            } else if is_parent(&field.ty) {
+                quote! { if child { self.#ident = ::core::default::Default::default(); } }
+            } else {
+                TokenStream::new()
+            });
+        }
+
+        if is_many(&field.ty) {
+            let ty = &field.ty;
+            let field = field_name(field);
+            many.push(quote! {
+                #cfg
+                (&TableOf::<<#ty as ::dbent::schema::Relation>::Target>(PhantomData))
+                    .table()
+                    .map(|table| ::dbent::schema::ManyMeta { field: #field.into(), table }),
+            });
+            continue;
+        }
+
+        let optional = attrs.optional.is_some();
+        let encrypt = attrs.encrypt.is_some();
+        let column = attrs.column.unwrap_or_else(|| field_name(field));
+        let const_name = syn::Ident::new(&field_name(field).to_uppercase(), field.span());
+        let ty = &field.ty;
+        let ident = &field.ident;
+
+        if is_poly(ty) {
+            let tag_column = format!("{column}_type");
+            let tag_const = syn::Ident::new(&format!("{const_name}_TYPE"), field.span());
+            let (doc, tag_doc) = (format!("Column `{column}`"), format!("Column `{tag_column}`"));
+            tokens.push(quote! {
+                #[doc = #doc]
+                #cfg
+                #item_vis const #const_name: ::dbent::sql::Field<super::super::#name, ::dbent::Key<<#ty as ::dbent::Keyed>::KeyType>> = ::dbent::sql::Field::new(#column);
+                #[doc = #tag_doc]
+                #cfg
+                #item_vis const #tag_const: ::dbent::sql::Field<super::super::#name, ::std::string::String> = ::dbent::sql::Field::new(#tag_column);
+            });
+            if track_reads {
+                let doc = format!("Returns `{}`, recording the read of columns `{column}` and `{tag_column}`", field_name(field));
+                getters.push(quote! {
+                    #[doc = #doc]
+                    #cfg
+                    #[inline]
+                    #vis fn #ident(&self) -> &#ty {
+                        ::dbent::reads::__private::record(#table, #column);
+                        ::dbent::reads::__private::record(#table, #tag_column);
+                        &self.#ident
+                    }
+                });
+            }
+            values.push(quote! {
+                #cfg #column => ::dbent::Keyed::key(&self.#ident).ok().and_then(|key| (&ColumnRef(key)).to_value()),
+                #cfg #tag_column => self.#ident.tag().map(|tag| ::dbent::sql::Value::Text(tag.into_owned())),
+            });
+            metas.push(quote! {
+                #cfg
+                {
+                    let (sql_type, nullable) = (&ColumnOf::<::dbent::Key<<#ty as ::dbent::Keyed>::KeyType>>(PhantomData)).sql_type();
+                    ::dbent::schema::ColumnMeta { name: #column.into(), sql_type, nullable: nullable || #optional, primary_key: false, references: None, on_delete: None }
+                },
+                #cfg
+                ::dbent::schema::ColumnMeta {
+                    name: #tag_column.into(),
+                    sql_type: ::dbent::schema::SqlType::Text,
+                    nullable: #optional,
+                    primary_key: false,
+                    references: None,
+                    on_delete: None,
+                },
+            });
+            columns.extend([column, tag_column]);
+            column_cfgs.extend([attrs.cfg.clone(), attrs.cfg]);
+            continue;
+        }
+        let (value_type, value) = if is_relation(ty) {
+            (
+                quote! { ::dbent::Key<<#ty as ::dbent::Keyed>::KeyType> },
+                quote! { ::dbent::Keyed::key(&self.#ident).ok().and_then(|key| (&ColumnRef(key)).to_value()) },
+            )
+        } else {
+            (quote! { #ty }, encrypted_value(&column, encrypt, quote! { (&ColumnRef(&self.#ident)).to_value() }))
+        };
+        let references = if is_relation(ty) {
+            quote! { (&TableOf::<<#ty as ::dbent::schema::Relation>::Target>(PhantomData)).table() }
+        } else {
+            quote! { None }
+        };
+        let on_delete = match &attrs.on_delete {
+            Some((variant, _)) => quote! { ::core::option::Option::Some(::dbent::schema::OnDelete::#variant) },
+            None => quote! { ::core::option::Option::None },
+        };
+        let doc = format!("Column `{column}`");
+
+        tokens.push(quote! {
+            #[doc = #doc]
+            #cfg
+            #item_vis const #const_name: ::dbent::sql::Field<super::super::#name, #value_type> = ::dbent::sql::Field::new(#column);
+        });
+        if track_reads {
+            let doc = format!("Returns `{}`, recording the read of column `{column}`", field_name(field));
+            getters.push(quote! {
+                #[doc = #doc]
+                #cfg
+                #[inline]
+                #vis fn #ident(&self) -> &#ty {
+                    ::dbent::reads::__private::record(#table, #column);
+                    &self.#ident
+                }
+            });
+        }
+        values.push(quote! { #cfg #column => #value, });
+        metas.push(quote! {
+            #cfg
+            {
+                let (sql_type, nullable) = (&ColumnOf::<#value_type>(PhantomData)).sql_type();
+                let sql_type = if #encrypt { ::dbent::schema::SqlType::Any } else { sql_type };
+                ::dbent::schema::ColumnMeta { name: #column.into(), sql_type, nullable: nullable || #optional, primary_key: #index == 0, references: #references, on_delete: #on_delete }
+            },
+        });
+        columns.push(column);
+        column_cfgs.push(attrs.cfg);
+    }
+
+    let mut unique = Vec::new();
+    for (names, span) in &attrs.unique {
+        if let Some(name) = names.iter().find(|name| !columns.contains(name)) {
+            return Err(Error::new(*span, format!("#[entity(unique(...))] unknown column `{name}`")));
+        }
+        let predicates = names
+            .iter()
+            .filter_map(|name| columns.iter().position(|column| column == name))
+            .filter_map(|index| column_cfgs[index].as_ref())
+            .collect::<Vec<_>>();
+        let cfg = if predicates.is_empty() { TokenStream::new() } else { quote! { #[cfg(all(#(#predicates),*))] } };
+        unique.push(quote! { #cfg ::std::vec![#(#names.into()),*], });
+    }
+    let column_names = columns.iter().zip(&column_cfgs).map(|(column, predicate)| match predicate {
+        Some(predicate) => quote! { #[cfg(#predicate)] #column },
+        None => quote! { #column },
+    });
+    let module_doc = format!("Items generated for [`{name}`]");
+    let fields_doc = format!("Typed column tokens for [`{name}`]");
+
+    Ok(
+        quote!{
+            #[doc = #module_doc]
+            #[allow(dead_code)]
+            #module_vis mod #module {
+                #[doc = #fields_doc]
+                #fields_vis mod fields {
+                    #[allow(unused_imports)]
+                    use super::super::*;
+
+                    #(#tokens)*
+                }
+            }
+
+            #[automatically_derived]
+            impl #name {
+                #(#getters)*
+            }
+
+            #[automatically_derived]
+            impl ::dbent::sql::Columns for #name {
+                fn column_value(&self, column: &str) -> Option<::dbent::sql::Value> {
+                    self.try_column_value(column).unwrap_or_default()
+                }
+
+                fn try_column_value(&self, column: &str) -> ::dbent::Result<Option<::dbent::sql::Value>> {
+                    #[allow(unused_imports)]
+                    use ::dbent::sql::__private::{ColumnRef, NoValue, ToValue};
+
+                    Ok(match column {
+                        #(#values)*
+                        _ => None,
+                    })
+                }
+
+                fn columns(&self) -> &'static [&'static str] {
+                    &[#(#column_names),*]
+                }
+            }
+
+            #[automatically_derived]
+            impl ::dbent::schema::Schema for #name {
+                fn entity_meta() -> ::dbent::schema::EntityMeta {
+                    #[allow(unused_imports)]
+                    use ::core::marker::PhantomData;
+                    #[allow(unused_imports)]
+                    use ::dbent::schema::__private::{ColumnOf, KnownColumn, KnownTable, TableOf, UnknownColumn, UnknownTable};
+
+                    ::dbent::schema::EntityMeta {
+                        table: #table.into(),
+                        columns: ::std::vec![#(#metas)*],
+                        many: ::std::vec::Vec::<::core::option::Option<::dbent::schema::ManyMeta>>::from([#(#many)*])
+                            .into_iter()
+                            .flatten()
+                            .collect(),
+                        unique: ::std::vec![#(#unique)*],
+                        id_prefix: #id_prefix,
+                    }
+                }
+
+                fn table() -> ::std::borrow::Cow<'static, str> {
+                    #table.into()
+                }
+
+                fn id_prefix() -> ::std::borrow::Cow<'static, str> {
+                    #id_prefix_value.into()
+                }
+            }
+
+            #[automatically_derived]
+            impl ::dbent::graph::Graph for #name {
+                fn graph_table(&self) -> ::std::borrow::Cow<'static, str> {
+                    #table.into()
+                }
+
+                fn graph_relations(&self) -> ::std::vec::Vec<::dbent::graph::GraphRelation<'_>> {
+                    #[allow(unused_imports)]
+                    use ::dbent::graph::__private::{KnownNodes, KnownState, NodesOf, UnknownNodes, UnknownState};
+
+                    ::std::vec![#(#relations)*]
+                }
+
+                #[allow(unused_variables)]
+                fn graph_clear_keys(&mut self, child: bool) {
+                    #[allow(unused_imports)]
+                    use ::dbent::graph::__private::{ChildrenOf, KnownChildren, UnknownChildren};
+
+                    if let Ok(key) = ::dbent::KeyedMut::key_mut(self) {
+                        *key = ::dbent::Key(None);
+                    }
+                    #(#clear_keys)*
+                }
+            }
+        }
+    )
+}
+
+/// Returns the implementation of the `FormatVersion` trait
+fn impl_format_version(input: &DeriveInput, version: &syn::LitInt, migrate: Option<&syn::Path>) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let migrate = migrate.map(|migrate| quote! {
+        #[inline]
+        fn migrate(version: u32, value: ::dbent::format::__private::Value) -> ::dbent::format::__private::Value {
+            #migrate(version, value)
+        }
+    });
+
+    quote!{
+        #[automatically_derived]
+        impl #impl_generics ::dbent::format::FormatVersion for #name #ty_generics #where_clause {
+            const FORMAT_VERSION: u32 = #version;
+
+            #migrate
+        }
+    }
+}
+
+/// Returns the methods reaching the keys and labels of the relation fields
+fn impl_accessors(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Err(Error::new(input.span(), "#[entity(accessors)] can only be used on structs with named fields")),
+    };
+
+    let mut methods = Vec::new();
+    for field in fields.named.iter().filter(|field| is_relation(&field.ty)) {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let name = field_name(field);
+        let cfg = FieldAttrs::parse(field)?.cfg_attr();
+
+        let key_method = syn::Ident::new(&format!("{name}_key"), field.span());
+        let doc = format!("Returns the key of `{name}`");
+        methods.push(quote! {
+            #[doc = #doc]
+            #cfg
+            #[inline]
+            #vis fn #key_method(&self) -> ::dbent::Result<&::dbent::Key<<#ty as ::dbent::Keyed>::KeyType>> {
+                ::dbent::Keyed::key(&self.#ident)
+            }
+        });
+
+        if is_entity_label(ty) {
+            let label_method = syn::Ident::new(&format!("{name}_label"), field.span());
+            let doc = format!("Returns the label of `{name}`");
+            methods.push(quote! {
+                #[doc = #doc]
+                #cfg
+                #[inline]
+                #vis fn #label_method(&self) -> ::dbent::Result<&<#ty as ::dbent::Label>::LabelType> {
+                    ::dbent::Label::label(&self.#ident)
+                }
+            });
+        }
+    }
+
+    Ok(
+        quote!{
+            #[automatically_derived]
+            impl #impl_generics #name #ty_generics #where_clause {
+                #(#methods)*
+            }
+        }
+    )
+}
+
+/// Returns the `exists`, `count` and `count_where` functions over the table
+fn impl_queries(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // The key type is a parameter so the ToSql bound is checked where the
+    // functions are called, not for every entity with a key rusqlite can't bind
+    quote!{
+        ::dbent::__with_rusqlite! {
+            #[automatically_derived]
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Returns whether the table has a row with the key
+                #vis fn exists<K>(conn: &::dbent::sqlite::__private::Connection, key: &K) -> ::dbent::Result<bool>
+                where
+                    Self: ::dbent::Keyed<KeyType = K>,
+                    K: ::dbent::sqlite::__private::ToSql,
+                {
+                    ::dbent::sqlite::exists::<Self>(conn, key)
+                }
+
+                /// Returns the number of rows of the table
+                #vis fn count(conn: &::dbent::sqlite::__private::Connection) -> ::dbent::Result<usize> {
+                    ::dbent::sqlite::count::<Self>(conn)
+                }
+
+                /// Returns the number of rows of the table matching the filter
+                #vis fn count_where(conn: &::dbent::sqlite::__private::Connection, filter: &::dbent::sql::Filter) -> ::dbent::Result<usize> {
+                    ::dbent::sqlite::count_where::<Self>(conn, filter)
+                }
+
+                /// Starts a SELECT of the rows of the table
+                #vis fn select() -> ::dbent::sqlite::Select<Self> {
+                    ::dbent::sqlite::Select::new()
+                }
+            }
+        }
+    }
+}
+
+/// Returns the `{Struct}Builder` type and the `builder()` function starting it
+fn impl_builder(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Err(Error::new(input.span(), "#[entity(builder)] can only be used on structs with named fields")),
+    };
+
+    let builder = syn::Ident::new(&format!("{name}Builder"), name.span());
+    let mut values = Vec::new();
+    let mut setters = Vec::new();
+    for field in fields.named.iter() {
+        let ident = &field.ident;
+        let ty = &field.ty;
+
+        let value = if is_many(ty) {
+            quote! { ::dbent::Many::NotFetched }
+        } else {
+            quote! { ::core::default::Default::default() }
+        };
+        values.push(quote! { #ident: #value, });
+
+        let doc = format!("Sets `{}`", field_name(field));
+        setters.push(quote! {
+            #[doc = #doc]
+            #[inline]
+            #vis fn #ident(mut self, value: impl ::core::convert::Into<#ty>) -> Self {
+                self.0.#ident = value.into();
+                self
+            }
+        });
+    }
+
+    let builder_doc = format!("Builds a `{name}` field by field, see `{name}::builder`");
+    let start_doc = format!(
+        "Returns a builder of `{name}` with an unset key, `Many` fields `NotFetched` and the other fields to their default"
+    );
+    Ok(
+        quote!{
+            #[doc = #builder_doc]
+            #vis struct #builder #generics (#name #ty_generics) #where_clause;
+
+            #[automatically_derived]
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #start_doc]
+                #vis fn builder() -> #builder #ty_generics {
+                    #builder(Self { #(#values)* })
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics #builder #ty_generics #where_clause {
+                #(#setters)*
+
+                /// Returns the built entity
+                #[inline]
+                #vis fn build(self) -> #name #ty_generics {
+                    self.0
+                }
+            }
+        }
+    )
+}
+
+/// Returns the implementation of `testing::Seeded`, for the `testing` feature
+fn impl_seeded(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Err(Error::new(input.span(), "#[entity(seeded)] can only be used on structs with named fields")),
+    };
+
+    let values = fields.named.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        quote! { #ident: (&SeedOf::<#ty>(PhantomData)).seeded(rng, depth), }
+    });
+
+    Ok(
+        quote!{
+            ::dbent::__with_testing! {
+                #[automatically_derived]
+                impl #impl_generics ::dbent::testing::Seeded for #name #ty_generics #where_clause {
+                    fn seeded(rng: &mut ::dbent::testing::SeedRng, depth: usize) -> Self {
+                        #[allow(unused_imports)]
+                        use ::core::marker::PhantomData;
+                        #[allow(unused_imports)]
+                        use ::dbent::testing::__private::{DefaultSeed, KnownSeed, SeedOf};
+
+                        Self { #(#values)* }
+                    }
+                }
+            }
+        }
+    )
+}
+
+/// Returns the implementation of `Default` with the `Many` fields starting in
+/// the state set by `default_fetch`, if set on the struct
+fn impl_default(input: &DeriveInput, default_fetch: Option<DefaultFetch>) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Err(Error::new(input.span(), "#[derive(Entity)] can only be used on structs with named fields")),
+    };
+
+    let mut values = Vec::new();
+    for field in fields.named.iter() {
+        let field_fetch = FieldAttrs::parse(field)?.default_fetch;
+        let fetch = match (default_fetch, field_fetch) {
+            (None, Some((_, span))) => return Err(Error::new(span, "#[entity(default_fetch = \"...\")] on a field needs it on the struct")),
+            (Some(_), Some((_, span))) if !is_many(&field.ty) => {
+                return Err(Error::new(span, "#[entity(default_fetch = \"...\")] can only be used on Many fields"));
+            },
+            (Some(fetch), field_fetch) => field_fetch.map_or(fetch, |(fetch, _)| fetch),
+            (None, None) => continue,
+        };
+
+        let ident = &field.ident;
+        let value = match fetch {
+            DefaultFetch::NotFetched if is_many(&field.ty) => quote! { ::dbent::Many::NotFetched },
+            DefaultFetch::None if is_many(&field.ty) => quote! { ::dbent::Many::None },
+            _ => quote! { ::core::default::Default::default() },
+        };
+        values.push(quote! { #ident: #value, });
+    }
+
+    if default_fetch.is_none() {
+        return Ok(TokenStream::new());
+    }
+
+    Ok(
+        quote!{
+            #[automatically_derived]
+            impl #impl_generics ::core::default::Default for #name #ty_generics #where_clause {
+                fn default() -> Self {
+                    Self { #(#values)* }
+                }
+            }
+        }
+    )
+}
+
+/// Returns the implementation of the `sqlite::FromRow` trait
+fn impl_from_row(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Err(Error::new(input.span(), "#[derive(FromRow)] can only be used on structs with named fields")),
+    };
+
+    let mut values = Vec::new();
+    for field in fields.named.iter() {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        if is_many(ty) {
+            values.push(quote! { #ident: ::dbent::Many::NotFetched, });
+            continue;
+        }
+
+        let attrs = FieldAttrs::parse(field)?;
+        let column = attrs.column.unwrap_or_else(|| field_name(field));
+        let is_label = is_entity_label(ty);
+        if let (Some(span), true) = (attrs.encrypt, is_relation(ty) || is_poly(ty)) {
+            return Err(Error::new(span, "#[entity(encrypt)] can't be used on relation fields"));
+        }
+        let value = match attrs.join_label {
+            Some(label) if is_label => quote! { <#ty>::from_columns(row.get(&*column(#column))?, row.get(&*column(#label))?) },
+            Some(_) => return Err(Error::new(field.span(), "#[entity(join(...))] can only be used on EntityLabel fields")),
+            None if is_label => {
+                return Err(Error::new(field.span(), "#[derive(FromRow)] needs #[entity(join(label = \"...\"))] on EntityLabel fields"));
+            },
+            None if is_poly(ty) => {
+                let tag_column = format!("{column}_type");
+                quote! { ::dbent::sqlite::__private::poly_entity(row, &column(#column), &column(#tag_column))? }
+            },
+            None if is_relation(ty) => quote! { <#ty as FromKeyColumn>::from_key_column(row.get(&*column(#column))?) },
+            None if attrs.encrypt.is_some() => quote! { ::dbent::sqlite::__private::decrypted(row, &column(#column))? },
+            None => quote! { row.get(&*column(#column))? },
+        };
+        values.push(match &attrs.cfg {
+            Some(predicate) => quote! {
+                #[cfg(#predicate)]
+                #ident: #value,
+                #[cfg(not(#predicate))]
+                #ident: ::core::default::Default::default(),
+            },
+            None => quote! { #ident: #value, },
+        });
+    }
+
+    Ok(
+        quote!{
+            #[automatically_derived]
+            impl #impl_generics ::dbent::sqlite::FromRow for #name #ty_generics #where_clause {
+                fn from_row(row: &::dbent::sqlite::__private::Row<'_>) -> ::dbent::sqlite::__private::Result<Self> {
+                    Self::from_row_prefixed(row, "")
+                }
+
+                fn from_row_prefixed(row: &::dbent::sqlite::__private::Row<'_>, prefix: &str) -> ::dbent::sqlite::__private::Result<Self> {
+                    #[allow(unused_imports)]
+                    use ::dbent::sqlite::__private::FromKeyColumn;
+
+                    #[allow(unused_variables)]
+                    let column = |name: &'static str| ::dbent::sqlite::__private::prefixed(prefix, name);
+                    Ok(Self { #(#values)* })
+                }
+            }
+        }
+    )
+}
+
+/// Returns the implementations of `sql::Columns`, `sqlite::FromRow` and
+/// `Label` for a keyless struct
+fn impl_projection(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    if let Some(attr) = input.attrs.iter().find(|attr| attr.path.is_ident("entity")) {
+        return Err(Error::new_spanned(attr, "#[derive(Projection)] takes no struct options"));
+    }
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Err(Error::new(input.span(), "#[derive(Projection)] can only be used on structs with named fields")),
+    };
+
+    let mut values = Vec::new();
+    let mut columns = Vec::new();
+    for field in fields.named.iter().filter(|field| !is_many(&field.ty)) {
+        let attrs = FieldAttrs::parse(field)?;
+        let cfg = attrs.cfg_attr();
+        let column = attrs.column.unwrap_or_else(|| field_name(field));
+        let ident = &field.ident;
+        let value = if is_relation(&field.ty) {
+            quote! { ::dbent::Keyed::key(&self.#ident).ok().and_then(|key| (&ColumnRef(key)).to_value()) }
+        } else {
+            encrypted_value(&column, attrs.encrypt.is_some(), quote! { (&ColumnRef(&self.#ident)).to_value() })
+        };
+        values.push(quote! { #cfg #column => #value, });
+        columns.push(quote! { #cfg #column });
+    }
+
+    let from_row = impl_from_row(input)?;
+
+    let label = if fields.named.iter().any(|field| marked_with_label(&field)) {
+        impl_label(input)?
+    } else {
+        TokenStream::new()
+    };
+
+    Ok(
+        quote!{
+            #[automatically_derived]
+            impl #impl_generics ::dbent::sql::Columns for #name #ty_generics #where_clause {
+                fn column_value(&self, column: &str) -> Option<::dbent::sql::Value> {
+                    self.try_column_value(column).unwrap_or_default()
+                }
+
+                fn try_column_value(&self, column: &str) -> ::dbent::Result<Option<::dbent::sql::Value>> {
+                    #[allow(unused_imports)]
+                    use ::dbent::sql::__private::{ColumnRef, NoValue, ToValue};
+
+                    Ok(match column {
+                        #(#values)*
+                        _ => None,
+                    })
+                }
+
+                fn columns(&self) -> &'static [&'static str] {
+                    &[#(#columns),*]
+                }
+            }
+
+            ::dbent::__with_rusqlite! {
+                #from_row
+            }
+
+            #label
+        }
+    )
+}
+
+/// Returns the implementation of the `Timestamps` trait
+fn impl_timestamps(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Err(Error::new(input.span(), "#[entity(timestamps)] can only be used on structs with named fields")),
+    };
+
+    let timestamp_type = |field_name: &str| {
+        let field = fields
+            .named
+            .iter()
+            .find(|field| field.ident.as_ref().map(|ident| ident == field_name).unwrap_or(false))
+            .ok_or_else(|| Error::new(fields.span(), format!("#[entity(timestamps)] needs the `{field_name}` field")))?;
+
+        option_type(&field.ty)
+            .ok_or_else(|| Error::new(field.ty.span(), format!("#[entity(timestamps)] needs `{field_name}` to be an Option")))
+    };
+
+    let created_type = timestamp_type("created_at")?;
+    let updated_type = timestamp_type("updated_at")?;
+
+    Ok(
+        quote!{
+            #[automatically_derived]
+            impl #impl_generics ::dbent::timestamps::Timestamps for #name #ty_generics #where_clause {
+                type Timestamp = #created_type;
+
+                #[inline]
+                fn created_at(&self) -> ::core::option::Option<&Self::Timestamp> {
+                    self.created_at.as_ref()
+                }
+
+                #[inline]
+                fn updated_at(&self) -> ::core::option::Option<&#updated_type> {
+                    self.updated_at.as_ref()
+                }
+
+                #[inline]
+                fn set_created_at(&mut self, timestamp: ::core::option::Option<Self::Timestamp>) {
+                    self.created_at = timestamp;
+                }
+
+                #[inline]
+                fn set_updated_at(&mut self, timestamp: ::core::option::Option<#updated_type>) {
+                    self.updated_at = timestamp;
+                }
+            }
+        }
+    )
+}
+
+/// Returns the implementation of the `hooks::Lifecycle` trait, touching the
+/// timestamps with `#[entity(timestamps)]`, calling the `EntityHooks` with
+/// `#[entity(hooks)]` and guarding updates with the `#[version]` field if `versioned`
+fn impl_lifecycle(input: &DeriveInput, attrs: &EntityAttrs, versioned: bool) -> TokenStream {
     let name = &input.ident;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let (key_type, key_expr) = match input.data {
-        syn::Data::Struct(ref body) => {
-            let (ty, ident) = single_key(&body.fields)?;
-            (
-                ty,
-                quote! {
-                    Ok(&self.#ident)
+    let (touch_insert, touch_update) = if attrs.timestamps {
+        (
+            quote!(::dbent::timestamps::Timestamps::touch_insert(self);),
+            quote!(::dbent::timestamps::Timestamps::touch_update(self);),
+        )
+    } else {
+        (TokenStream::new(), TokenStream::new())
+    };
+
+    let hooks = if attrs.hooks {
+        quote!{
+            const FETCH_BEFORE_DELETE: bool = true;
+
+            fn after_fetch(&mut self) -> ::dbent::Result<()> {
+                ::dbent::hooks::EntityHooks::after_fetch(self)
+            }
+
+            fn before_delete(&self) -> ::dbent::Result<()> {
+                ::dbent::hooks::EntityHooks::before_delete(self)
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
+    let before_save = if attrs.hooks {
+        quote!(::dbent::hooks::EntityHooks::before_save(self)?;)
+    } else {
+        TokenStream::new()
+    };
+
+    let versioned = if versioned {
+        quote!{
+            fn versioned(&mut self) -> ::core::option::Option<&mut dyn ::dbent::version::Versioned> {
+                ::core::option::Option::Some(self)
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
+
+    quote!{
+        #[automatically_derived]
+        impl #impl_generics ::dbent::hooks::Lifecycle for #name #ty_generics #where_clause {
+            fn before_insert(&mut self) -> ::dbent::Result<()> {
+                #touch_insert
+                #before_save
+                Ok(())
+            }
+
+            fn before_update(&mut self) -> ::dbent::Result<()> {
+                #touch_update
+                #before_save
+                Ok(())
+            }
+
+            #versioned
+
+            #hooks
+        }
+    }
+}
+
+/// Returns the implementation of the `Versioned` trait if a field is marked with `#[version]`
+fn impl_versioned(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Ok(TokenStream::new()),
+    };
+
+    let version_fields = fields
+        .named
+        .iter()
+        .filter(|field| field.attrs.iter().any(|attr| attr.path.is_ident("version")))
+        .collect::<Vec<_>>();
+
+    let field = match version_fields.as_slice() {
+        [] => return Ok(TokenStream::new()),
+        [field] => field,
+        [_, field, ..] => return Err(Error::new(field.span(), "#[derive(Entity)] can have only 1 field marked with #[version]")),
+    };
+    let ident = &field.ident;
+    let column = FieldAttrs::parse(field)?.column.unwrap_or_else(|| field_name(field));
+
+    Ok(
+        quote!{
+            #[automatically_derived]
+            impl #impl_generics ::dbent::version::Versioned for #name #ty_generics #where_clause {
+                #[inline]
+                fn version(&self) -> ::dbent::version::Version {
+                    self.#ident
                 }
-            )
-        },
-        _ => panic!("#[derive(Entity)] can only be used on structs"),
+
+                #[inline]
+                fn set_version(&mut self, version: ::dbent::version::Version) {
+                    self.#ident = version;
+                }
+
+                #[inline]
+                fn version_column(&self) -> &'static str {
+                    #column
+                }
+            }
+        }
+    )
+}
+
+/// Returns the implementation of the `Positioned` trait if a field is marked with `#[position]`
+fn impl_positioned(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Ok(TokenStream::new()),
+    };
+
+    let position_fields = fields
+        .named
+        .iter()
+        .filter(|field| field.attrs.iter().any(|attr| attr.path.is_ident("position")))
+        .collect::<Vec<_>>();
+
+    let field = match position_fields.as_slice() {
+        [] => return Ok(TokenStream::new()),
+        [field] => field,
+        [_, field, ..] => return Err(Error::new(field.span(), "#[derive(Entity)] can have only 1 field marked with #[position]")),
     };
+    let ident = &field.ident;
+    let column = FieldAttrs::parse(field)?.column.unwrap_or_else(|| field_name(field));
 
     Ok(
         quote!{
             #[automatically_derived]
-            impl #impl_generics ::dbent::Keyed for #name #ty_generics #where_clause {
-                type KeyType = #key_type;
+            impl #impl_generics ::dbent::position::Positioned for #name #ty_generics #where_clause {
+                const POSITION_COLUMN: &'static str = #column;
+
+                #[inline]
+                fn position(&self) -> i64 {
+                    self.#ident
+                }
 
                 #[inline]
-                fn key(&self) -> ::dbent::Result<&Key<Self::KeyType>> {
-                    #key_expr
+                fn set_position(&mut self, position: i64) {
+                    self.#ident = position;
                 }
             }
         }
     )
 }
 
+/// Returns the type `T` if `ty` is an `Option<T>`
+fn option_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(typepath) => typepath.path.segments.last()?,
+        _ => return None,
+    };
+
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 /// Returns the implementation of the `Label` trait
 fn impl_label(input: &DeriveInput) -> Result<TokenStream, Error> {
     let name = &input.ident;
@@ -94,8 +1695,9 @@ fn impl_label(input: &DeriveInput) -> Result<TokenStream, Error> {
     )
 }
 
-/// Returns the key type and name if the first field found in the struct is a `Key<T>`
-fn single_key(fields: &syn::Fields) -> Result<(TokenStream, TokenStream), Error> {
+/// Returns the key type and name if the first field found in the struct is a `Key<T>`,
+/// or of any type with the key type set by `#[entity(key_type = "...")]`
+fn single_key(fields: &syn::Fields, key_type: Option<&syn::Type>) -> Result<(TokenStream, TokenStream), Error> {
     let (ty, ident) = match fields {
         syn::Fields::Named(fields) => {
             let field = fields
@@ -104,6 +1706,7 @@ fn single_key(fields: &syn::Fields) -> Result<(TokenStream, TokenStream), Error>
                 .ok_or_else(|| Error::new(fields.span(), "#[derive(Entity)] needs at least a single Key field defined"))?;
 
             match &field.ty {
+                _ if key_type.is_some() => (quote! { #key_type }, &field.ident),
                 syn::Type::Path(typepath) => {
                     let seg = typepath
                         .path
@@ -112,7 +1715,7 @@ fn single_key(fields: &syn::Fields) -> Result<(TokenStream, TokenStream), Error>
                         .ok_or_else(|| Error::new(field.span(), "#[derive(Entity)] needs at least a single Key field defined; no segments found"))?;
 
                     if seg.ident != "Key" {
-                        return Err(Error::new(field.span(), "#[derive(Entity)] needs the first field to be a Key; aliasing the Key to something else breaks the macro"));
+                        return Err(Error::new(field.span(), "#[derive(Entity)] needs the first field to be a Key; set the key type with #[entity(key_type = \"...\")] for an alias of Key"));
                     }
 
                     (argument_type(field, &seg.arguments)?, &field.ident)
@@ -181,3 +1784,490 @@ fn marked_with_label(field: &&syn::Field) -> bool {
 
     false
 }
+
+/// Returns the implementation of the `Validate` trait
+fn impl_validate(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        syn::Data::Struct(ref body) => return Err(Error::new(body.fields.span(), "#[derive(Validate)] can only be used on structs with named fields")),
+        _ => return Err(Error::new(input.span(), "#[derive(Validate)] can only be used on structs")),
+    };
+
+    let mut checks = Vec::new();
+    for field in &fields.named {
+        for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("validate")) {
+            checks.extend(validate_checks(field, attr)?);
+        }
+    }
+
+    Ok(
+        quote!{
+            #[automatically_derived]
+            impl #impl_generics ::dbent::validate::Validate for #name #ty_generics #where_clause {
+                fn validate(&self) -> ::core::result::Result<(), ::dbent::validate::ValidationErrors> {
+                    let mut errors = ::dbent::validate::ValidationErrors::new();
+                    #(#checks)*
+                    errors.into_result()
+                }
+            }
+        }
+    )
+}
+
+/// Returns the checks for a single `#[validate(...)]` attribute of a field
+fn validate_checks(field: &syn::Field, attr: &syn::Attribute) -> Result<Vec<TokenStream>, Error> {
+    let ident = &field.ident;
+    let name = ident.as_ref().map(ToString::to_string).unwrap_or_default();
+
+    let list = match attr.parse_meta()? {
+        syn::Meta::List(list) => list,
+        meta => return Err(Error::new(meta.span(), "#[derive(Validate)] expects a list of validations like #[validate(required)]")),
+    };
+
+    let mut checks = Vec::new();
+    for nested in &list.nested {
+        let check = match nested {
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("required") => quote! {
+                if !::dbent::validate::Required::is_present(&self.#ident) {
+                    errors.add(#name, ::dbent::validate::FieldErrorKind::Required);
+                }
+            },
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("required_key") => quote! {
+                if !::dbent::Keyed::key(&self.#ident).map(|key| key.is_some()).unwrap_or(false) {
+                    errors.add(#name, ::dbent::validate::FieldErrorKind::RequiredKey);
+                }
+            },
+            syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("length") => {
+                let (min, max) = validate_bounds(list)?;
+                let condition = match (&min, &max) {
+                    (Some(min), Some(max)) => quote! { !(#min..=#max).contains(&length) },
+                    (Some(min), None) => quote! { length < #min },
+                    (None, Some(max)) => quote! { length > #max },
+                    (None, None) => return Err(Error::new(list.span(), "#[validate(length(...))] needs a min or max bound")),
+                };
+                let min = option_tokens(&min, quote! { usize });
+                let max = option_tokens(&max, quote! { usize });
+                quote! {
+                    if let Some(length) = ::dbent::validate::HasLength::length(&self.#ident) {
+                        if #condition {
+                            errors.add(#name, ::dbent::validate::FieldErrorKind::Length { min: #min, max: #max, actual: length });
+                        }
+                    }
+                }
+            },
+            syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("range") => {
+                let (min, max) = validate_bounds(list)?;
+                let condition = match (&min, &max) {
+                    (Some(min), Some(max)) => quote! { !(#min..=#max).contains(&self.#ident) },
+                    (Some(min), None) => quote! { self.#ident < #min },
+                    (None, Some(max)) => quote! { self.#ident > #max },
+                    (None, None) => return Err(Error::new(list.span(), "#[validate(range(...))] needs a min or max bound")),
+                };
+                let min = option_tokens(&min, quote! { f64 });
+                let max = option_tokens(&max, quote! { f64 });
+                quote! {
+                    if #condition {
+                        errors.add(#name, ::dbent::validate::FieldErrorKind::Range { min: #min, max: #max });
+                    }
+                }
+            },
+            syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("custom") => {
+                let function = match &value.lit {
+                    syn::Lit::Str(lit) => lit.parse::<syn::Path>()?,
+                    lit => return Err(Error::new(lit.span(), "#[validate(custom = \"...\")] expects the path of a function as a string")),
+                };
+                quote! {
+                    if let Err(message) = #function(&self.#ident) {
+                        errors.add(#name, ::dbent::validate::FieldErrorKind::Custom(message.to_string()));
+                    }
+                }
+            },
+            nested => return Err(Error::new(nested.span(), "#[derive(Validate)] unknown validation; expected one of length, range, required, required_key or custom")),
+        };
+        checks.push(check);
+    }
+
+    Ok(checks)
+}
+
+/// Returns the `min` and `max` literals of a bounded validation
+fn validate_bounds(list: &syn::MetaList) -> Result<(Option<syn::Lit>, Option<syn::Lit>), Error> {
+    let mut min = None;
+    let mut max = None;
+
+    for nested in &list.nested {
+        match nested {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("min") => min = Some(value.lit.clone()),
+            syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("max") => max = Some(value.lit.clone()),
+            nested => return Err(Error::new(nested.span(), "#[derive(Validate)] bounds must be defined as min = ... or max = ...")),
+        }
+    }
+
+    Ok((min, max))
+}
+
+/// Returns the tokens of an `Option` holding the literal cast to `ty`
+fn option_tokens(lit: &Option<syn::Lit>, ty: TokenStream) -> TokenStream {
+    match lit {
+        Some(lit) => quote! { ::core::option::Option::Some(#lit as #ty) },
+        None => quote! { ::core::option::Option::None },
+    }
+}
+
+/// Returns the implementations of `sql::DbEnum`, `schema::ColumnType`,
+/// `From<Enum>` for `sql::Value`, and the rusqlite `ToSql` and `FromSql`
+fn impl_db_enum(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let variants = match input.data {
+        syn::Data::Enum(ref body) => &body.variants,
+        _ => return Err(Error::new(input.span(), "#[derive(DbEnum)] can only be used on enums")),
+    };
+
+    let mut integer = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("db_enum")) {
+        match attr.parse_meta()? {
+            syn::Meta::List(list) if list.nested.len() == 1 => match &list.nested[0] {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("integer") => integer = true,
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("text") => integer = false,
+                nested => return Err(Error::new(nested.span(), "#[derive(DbEnum)] expects #[db_enum(text)] or #[db_enum(integer)] on the enum")),
+            },
+            meta => return Err(Error::new(meta.span(), "#[derive(DbEnum)] expects #[db_enum(text)] or #[db_enum(integer)] on the enum")),
+        }
+    }
+
+    let mut values = Vec::new();
+    for variant in variants {
+        if !matches!(variant.fields, syn::Fields::Unit) {
+            return Err(Error::new(variant.span(), "#[derive(DbEnum)] can only be used on enums of unit variants"));
+        }
+
+        let mut value = None;
+        for attr in variant.attrs.iter().filter(|attr| attr.path.is_ident("db_enum")) {
+            match attr.parse_meta()? {
+                syn::Meta::List(list) if list.nested.len() == 1 => match &list.nested[0] {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(name_value)) if name_value.path.is_ident("value") => {
+                        value = Some(name_value.lit.clone());
+                    },
+                    nested => return Err(Error::new(nested.span(), "#[derive(DbEnum)] expects #[db_enum(value = ...)] on variants")),
+                },
+                meta => return Err(Error::new(meta.span(), "#[derive(DbEnum)] expects #[db_enum(value = ...)] on variants")),
+            }
+        }
+
+        let value = match (integer, value, &variant.discriminant) {
+            (false, Some(syn::Lit::Str(lit)), _) => DbEnumValue::Text(lit.value()),
+            (false, None, _) => DbEnumValue::Text(snake_case(&variant.ident.to_string())),
+            (true, Some(syn::Lit::Int(lit)), _) => DbEnumValue::Integer(lit.base10_parse()?),
+            (true, None, Some((_, expr))) => DbEnumValue::Integer(discriminant(expr)?),
+            (true, None, None) => {
+                return Err(Error::new(variant.span(), "#[db_enum(integer)] needs a discriminant like `Active = 1` or #[db_enum(value = 1)] on each variant"));
+            },
+            (false, Some(lit), _) => return Err(Error::new(lit.span(), "#[db_enum(value = \"...\")] expects a string literal")),
+            (true, Some(lit), _) => return Err(Error::new(lit.span(), "#[db_enum(value = ...)] expects an integer literal with #[db_enum(integer)]")),
+        };
+
+        if values.iter().any(|(_, other)| other == &value) {
+            return Err(Error::new(variant.span(), "#[derive(DbEnum)] needs a distinct value for each variant"));
+        }
+        values.push((&variant.ident, value));
+    }
+
+    let sql_type = if integer { quote! { Integer } } else { quote! { Text } };
+    let to_values = values.iter().map(|(ident, value)| match value {
+        DbEnumValue::Text(text) => quote! { Self::#ident => ::dbent::sql::Value::Text(::std::string::String::from(#text)), },
+        DbEnumValue::Integer(number) => quote! { Self::#ident => ::dbent::sql::Value::Integer(#number), },
+    });
+    let from_values = values.iter().map(|(ident, value)| match value {
+        DbEnumValue::Text(text) => quote! { ::dbent::sql::Value::Text(value) if value == #text => Some(Self::#ident), },
+        DbEnumValue::Integer(number) => quote! { ::dbent::sql::Value::Integer(#number) => Some(Self::#ident), },
+    });
+    let enum_name = name.to_string();
+
+    Ok(
+        quote!{
+            #[automatically_derived]
+            impl #impl_generics ::dbent::sql::DbEnum for #name #ty_generics #where_clause {
+                const NAME: &'static str = #enum_name;
+
+                fn db_value(&self) -> ::dbent::sql::Value {
+                    match self {
+                        #(#to_values)*
+                    }
+                }
+
+                fn from_db_value(value: &::dbent::sql::Value) -> ::core::option::Option<Self> {
+                    match value {
+                        #(#from_values)*
+                        _ => None,
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #impl_generics ::dbent::schema::ColumnType for #name #ty_generics #where_clause {
+                const SQL_TYPE: ::dbent::schema::SqlType = ::dbent::schema::SqlType::#sql_type;
+            }
+
+            #[automatically_derived]
+            impl #impl_generics ::core::convert::From<#name #ty_generics> for ::dbent::sql::Value #where_clause {
+                #[inline]
+                fn from(variant: #name #ty_generics) -> Self {
+                    ::dbent::sql::DbEnum::db_value(&variant)
+                }
+            }
+
+            ::dbent::__with_rusqlite! {
+                #[automatically_derived]
+                impl #impl_generics ::dbent::sqlite::__private::ToSql for #name #ty_generics #where_clause {
+                    #[inline]
+                    fn to_sql(&self) -> ::dbent::sqlite::__private::Result<::dbent::sqlite::__private::ToSqlOutput<'_>> {
+                        ::dbent::sqlite::__private::db_enum_to_sql(self)
+                    }
+                }
+
+                #[automatically_derived]
+                impl #impl_generics ::dbent::sqlite::__private::FromSql for #name #ty_generics #where_clause {
+                    #[inline]
+                    fn column_result(value: ::dbent::sqlite::__private::ValueRef<'_>) -> ::dbent::sqlite::__private::FromSqlResult<Self> {
+                        ::dbent::sqlite::__private::db_enum_column_result(value)
+                    }
+                }
+            }
+        }
+    )
+}
+
+/// The value stored for a variant by `#[derive(DbEnum)]`
+#[derive(PartialEq)]
+enum DbEnumValue {
+    Text(String),
+    Integer(i64),
+}
+
+/// Returns the value of an integer discriminant, like `1` or `-1`
+fn discriminant(expr: &syn::Expr) -> Result<i64, Error> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit), .. }) => lit.base10_parse(),
+        syn::Expr::Unary(syn::ExprUnary { op: syn::UnOp::Neg(_), expr, .. }) => discriminant(expr).map(|value| -value),
+        expr => Err(Error::new(expr.span(), "#[db_enum(integer)] expects integer literals as discriminants")),
+    }
+}
+
+/// Returns the `{Struct}Dto` struct, the implementation of `dto::IntoDto` and
+/// the `From` conversions between them
+fn impl_into_dto(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let vis = &input.vis;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(input.generics.span(), "#[derive(IntoDto)] can't be used on generic structs"));
+    }
+
+    let fields = match input.data {
+        syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(ref fields), .. }) => fields,
+        _ => return Err(Error::new(input.span(), "#[derive(IntoDto)] can only be used on structs with named fields")),
+    };
+
+    let mut dto = syn::Ident::new(&format!("{name}Dto"), name.span());
+    let mut derives = Vec::new();
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("dto")) {
+        let list = match attr.parse_meta()? {
+            syn::Meta::List(list) => list,
+            meta => return Err(Error::new(meta.span(), "#[derive(IntoDto)] expects a list of options like #[dto(derive(Debug))]")),
+        };
+
+        for nested in &list.nested {
+            match nested {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("name") => {
+                    dto = syn::Ident::new(&lit_string(&value.lit)?, value.lit.span());
+                },
+                syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("derive") => {
+                    derives.extend(list.nested.iter().cloned());
+                },
+                nested => return Err(Error::new(nested.span(), "#[derive(IntoDto)] unknown dto option; expected name or derive")),
+            }
+        }
+    }
+
+    let mut dto_fields = Vec::new();
+    let mut into_values = Vec::new();
+    let mut from_values = Vec::new();
+    for field in fields.named.iter() {
+        let ident = &field.ident;
+        let ty = &field.ty;
+
+        let mut skip = false;
+        for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("dto")) {
+            let list = match attr.parse_meta()? {
+                syn::Meta::List(list) => list,
+                meta => return Err(Error::new(meta.span(), "#[derive(IntoDto)] expects #[dto(skip)] on fields")),
+            };
+
+            for nested in &list.nested {
+                match nested {
+                    syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("skip") => skip = true,
+                    nested => return Err(Error::new(nested.span(), "#[derive(IntoDto)] unknown dto field option; expected skip")),
+                }
+            }
+        }
+        if skip {
+            from_values.push(quote! { #ident: ::core::default::Default::default(), });
+            continue;
+        }
+
+        let key = quote! { ::dbent::Keyed::key(&entity.#ident).ok().and_then(|key| key.0.clone()) };
+        match last_segment(ty) {
+            Some(segment) if segment.ident == "Key" => {
+                let key_type = argument_type(field, &segment.arguments)?;
+                dto_fields.push(quote! { #vis #ident: ::core::option::Option<#key_type>, });
+                into_values.push(quote! { #ident: entity.#ident.0, });
+                from_values.push(quote! { #ident: ::dbent::Key(dto.#ident), });
+            },
+            _ if is_many(ty) => {
+                dto_fields.push(quote! {
+                    #vis #ident: ::std::vec::Vec<<<#ty as ::dbent::schema::Relation>::Target as ::dbent::dto::IntoDto>::Dto>,
+                });
+                into_values.push(quote! { #ident: ::dbent::dto::__private::many_into_dtos(entity.#ident), });
+                from_values.push(quote! { #ident: ::dbent::dto::__private::many_from_dtos(dto.#ident), });
+            },
+            _ if is_entity_label(ty) => {
+                let label = syn::Ident::new(&format!("{}_label", field_name(field)), field.span());
+                dto_fields.push(quote! {
+                    #vis #ident: ::core::option::Option<<#ty as ::dbent::Keyed>::KeyType>,
+                    #vis #label: ::core::option::Option<<#ty as ::dbent::Label>::LabelType>,
+                });
+                into_values.push(quote! {
+                    #ident: #key,
+                    #label: ::dbent::Label::label(&entity.#ident).ok().cloned(),
+                });
+                from_values.push(quote! { #ident: <#ty>::from_columns(::dbent::Key(dto.#ident), dto.#label), });
+            },
+            _ if is_relation(ty) => {
+                dto_fields.push(quote! { #vis #ident: ::core::option::Option<<#ty as ::dbent::Keyed>::KeyType>, });
+                into_values.push(quote! { #ident: #key, });
+                from_values.push(if is_parent(ty) {
+                    quote! { #ident: ::dbent::Parent(::dbent::Entity::from_key_nullable(::dbent::Key(dto.#ident))), }
+                } else {
+                    quote! { #ident: <#ty>::from_key_nullable(::dbent::Key(dto.#ident)), }
+                });
+            },
+            _ => {
+                dto_fields.push(quote! { #vis #ident: #ty, });
+                into_values.push(quote! { #ident: entity.#ident, });
+                from_values.push(quote! { #ident: dto.#ident, });
+            },
+        }
+    }
+
+    let derives = if derives.is_empty() { TokenStream::new() } else { quote! { #[derive(#(#derives),*)] } };
+    let dto_doc = format!("Flat data transfer object of [`{name}`]");
+    Ok(
+        quote!{
+            #[doc = #dto_doc]
+            #derives
+            #vis struct #dto {
+                #(#dto_fields)*
+            }
+
+            #[automatically_derived]
+            impl ::dbent::dto::IntoDto for #name {
+                type Dto = #dto;
+
+                #[allow(unused_variables)]
+                fn into_dto(self) -> #dto {
+                    let entity = self;
+                    #dto { #(#into_values)* }
+                }
+
+                #[allow(unused_variables)]
+                fn from_dto(dto: #dto) -> Self {
+                    Self { #(#from_values)* }
+                }
+            }
+
+            #[automatically_derived]
+            impl ::core::convert::From<#name> for #dto {
+                #[inline]
+                fn from(entity: #name) -> Self {
+                    ::dbent::dto::IntoDto::into_dto(entity)
+                }
+            }
+
+            #[automatically_derived]
+            impl ::core::convert::From<#dto> for #name {
+                #[inline]
+                fn from(dto: #dto) -> Self {
+                    ::dbent::dto::IntoDto::from_dto(dto)
+                }
+            }
+        }
+    )
+}
+
+fn impl_denormalized(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut sources = Vec::new();
+    let mut refresh_sql = Vec::new();
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("denormalized")) {
+        let list = match attr.parse_meta()? {
+            syn::Meta::List(list) => list,
+            meta => return Err(Error::new(meta.span(), "#[derive(Denormalized)] expects a list of options like #[denormalized(source(User))]")),
+        };
+
+        for nested in &list.nested {
+            let source = match nested {
+                syn::NestedMeta::Meta(syn::Meta::List(source)) if source.path.is_ident("source") => source,
+                nested => return Err(Error::new(nested.span(), "#[derive(Denormalized)] unknown denormalized option; expected source")),
+            };
+
+            let mut options = source.nested.iter();
+            let path = match options.next() {
+                Some(syn::NestedMeta::Meta(syn::Meta::Path(path))) => path,
+                _ => return Err(Error::new(source.span(), "#[derive(Denormalized)] expects the path of an entity like source(User)")),
+            };
+            let table = quote! { <#path as ::dbent::schema::Schema>::table() };
+            for option in options {
+                match option {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(value)) if value.path.is_ident("sql") => {
+                        let sql = lit_string(&value.lit)?;
+                        refresh_sql.push(quote! {
+                            if source == #table {
+                                return ::core::option::Option::Some(::std::borrow::Cow::Borrowed(#sql));
+                            }
+                        });
+                    },
+                    option => return Err(Error::new(option.span(), "#[derive(Denormalized)] unknown source option; expected sql")),
+                }
+            }
+            sources.push(table);
+        }
+    }
+
+    if sources.is_empty() {
+        return Err(Error::new(input.span(), "#[derive(Denormalized)] needs at least one #[denormalized(source(...))]"));
+    }
+
+    let refresh_sql = (!refresh_sql.is_empty()).then(|| quote! {
+        fn refresh_sql(source: &str) -> ::core::option::Option<::std::borrow::Cow<'static, str>> {
+            #(#refresh_sql)*
+            ::core::option::Option::None
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::dbent::denormalized::Denormalized for #name #ty_generics #where_clause {
+            fn sources() -> ::std::vec::Vec<::std::borrow::Cow<'static, str>> {
+                ::std::vec![#(#sources),*]
+            }
+
+            #refresh_sql
+        }
+    })
+}
@@ -19,12 +19,194 @@ fn test_key() -> Result {
 
     let model = Model { id: Key::new(1), data: "Data".to_owned() };
     assert_eq!(model.key()?, &Key::new(1));
+    assert_eq!(model.key_infallible(), &Key::new(1));
+    assert_eq!(Box::new(&model).key_infallible(), &Key::new(1));
     let model = Model::default();
     assert!(model.key()?.is_none());
 
     Ok(())
 }
 
+type UserId = Key<i64>;
+
+#[derive(Entity)]
+#[entity(fields, key_type = "i64")]
+struct AliasedUser {
+    id: UserId,
+    name: String,
+}
+
+#[test]
+fn test_key_type() -> Result {
+    use dbent::schema::Schema;
+    use dbent::Key as Id;
+
+    #[derive(Entity)]
+    #[entity(key_type = "String")]
+    struct Country {
+        code: Id<String>,
+    }
+
+    let mut user = AliasedUser { id: UserId::new(1), name: "Ann".to_owned() };
+    assert_eq!(user.key()?, &Key::new(1));
+    *user.key_mut()? = Key::new(2);
+    assert_eq!(user.id, Key::new(2));
+    assert!(AliasedUser::entity_meta().column("id").unwrap().primary_key);
+
+    let country = Country { code: Id::new("BR".to_owned()) };
+    assert_eq!(country.key()?.as_deref(), Some("BR"));
+    Ok(())
+}
+
+#[test]
+fn test_keyed_labeled() -> Result {
+    mod other {
+        // stands in for another crate's derives taking the names
+        #[allow(unused_imports)]
+        pub use std::fmt::Debug as Entity;
+    }
+
+    #[allow(unused_imports)]
+    use other::Entity;
+
+    #[derive(dbent::Keyed, dbent::Labeled)]
+    struct Model {
+        id: dbent::Key<i64>,
+        #[label]
+        name: String,
+    }
+
+    let model = Model { id: dbent::Key::new(1), name: "Ann".to_owned() };
+    assert_eq!(dbent::Keyed::key(&model)?, &dbent::Key::new(1));
+    assert_eq!(dbent::Label::label(&model)?, "Ann");
+    Ok(())
+}
+
+#[test]
+fn test_accessors() -> Result {
+    #[derive(Entity, Label)]
+    struct User {
+        id: Key<Int>,
+        #[label]
+        name: String,
+    }
+
+    #[derive(Entity)]
+    #[entity(accessors)]
+    struct Post {
+        id: Key<Int>,
+        author: EntityLabelInt<User>,
+        editor: EntityInt<User>,
+        parent: Parent<Int, User>,
+    }
+
+    let post = Post {
+        id: Key::new(1),
+        author: EntityLabel::KeyLabel(Key::new(2), "Ann".to_owned()),
+        editor: User { id: Key::new(3), name: "Bob".to_owned() }.into(),
+        parent: Parent::default(),
+    };
+    assert_eq!(post.author_key()?, &Key::new(2));
+    assert_eq!(post.author_label()?, "Ann");
+    assert_eq!(post.editor_key()?, &Key::new(3));
+    assert!(matches!(post.parent_key(), Err(dbent::Error::EntityEmpty)));
+    Ok(())
+}
+
+#[test]
+fn test_builder() -> Result {
+    #[derive(Entity, Debug, PartialEq)]
+    struct Comment {
+        id: Key<Int>,
+    }
+
+    #[derive(Entity, Debug, PartialEq)]
+    #[entity(builder)]
+    struct Post {
+        id: Key<Int>,
+        title: String,
+        author: EntityInt<Comment>,
+        comments: Many<Comment>,
+        score: Option<i64>,
+    }
+
+    let post = Post::builder().build();
+    assert_eq!(post, Post { id: Key(None), title: String::new(), author: Entity::None, comments: Many::NotFetched, score: None });
+
+    let post = Post::builder()
+        .id(1)
+        .title("Hello")
+        .author(Key::new(2))
+        .comments(vec![Comment { id: Key::new(3) }])
+        .score(4)
+        .build();
+    assert_eq!(post.id, Key::new(1));
+    assert_eq!(post.title, "Hello");
+    assert_eq!(post.author.key()?, &Key::new(2));
+    assert_eq!(post.comments.data()?.len(), 1);
+    assert_eq!(post.score, Some(4));
+    Ok(())
+}
+
+#[test]
+fn test_seeded() {
+    use dbent::testing::{seeded, seeded_with_depth};
+
+    #[derive(Default, PartialEq, Debug)]
+    struct Color(u8, u8, u8);
+
+    #[derive(Entity, PartialEq, Debug)]
+    #[entity(seeded)]
+    struct Folder {
+        id: Key<Int>,
+        name: String,
+        color: Color,
+        parent: Parent<Int, Folder>,
+        owner: EntityLabel<Int, Folder, String>,
+        children: Many<Folder>,
+    }
+
+    let folder = seeded::<Folder>(5);
+    assert_eq!(folder, seeded::<Folder>(5));
+    assert_ne!(folder, seeded::<Folder>(6));
+    assert_eq!(folder.color, Color::default());
+    assert!(folder.parent.is_key() && folder.owner.is_data());
+
+    let children = folder.children.data().unwrap();
+    assert!(children.iter().all(|child| child.children.data().is_ok()));
+    assert!(children.iter().flat_map(|child| child.children.data().unwrap()).all(|child| child.children.is_not_fetched()));
+
+    let folder = seeded_with_depth::<Folder>(5, 0);
+    assert!(matches!(folder.owner, EntityLabel::KeyLabel(Key(Some(_)), _)));
+    assert!(folder.children.is_not_fetched());
+}
+
+#[test]
+fn test_default_fetch() {
+    #[derive(Entity)]
+    struct Comment {
+        id: Key<Int>,
+    }
+
+    #[derive(Entity)]
+    #[entity(default_fetch = "not_fetched")]
+    struct Post {
+        id: Key<Int>,
+        title: String,
+        author: EntityInt<Comment>,
+        comments: Many<Comment>,
+        #[entity(default_fetch = "none")]
+        drafts: Many<Comment>,
+    }
+
+    let post = Post::default();
+    assert!(post.id.is_none());
+    assert!(post.title.is_empty());
+    assert!(post.author.is_none());
+    assert!(post.comments.is_not_fetched());
+    assert!(post.drafts.is_none());
+}
+
 #[test]
 fn test_key_on_entity() -> Result {
     #[derive(Clone, Entity)]
@@ -98,3 +280,1704 @@ fn test_label_on_entity_label() -> Result {
 
     Ok(())
 }
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><========================  VALIDATE  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_validate() {
+    use dbent::validate::FieldErrorKind;
+
+    #[derive(Entity, Label)]
+    struct Model1 {
+        id: Key<Int>,
+        #[label] data: String,
+    }
+
+    fn not_admin(value: &str) -> core::result::Result<(), String> {
+        if value == "admin" { Err("reserved name".to_owned()) } else { Ok(()) }
+    }
+
+    #[derive(Entity, Validate)]
+    struct Model2 {
+        id: Key<Int>,
+        #[validate(length(min = 1, max = 5), custom = "not_admin")]
+        name: String,
+        #[validate(range(min = 0, max = 150))]
+        age: i32,
+        #[validate(required)]
+        model1: EntityLabelInt<Model1>,
+        #[validate(required_key)]
+        owner: EntityInt<Model1>,
+        #[validate(length(max = 1))]
+        children: Many<Model1>,
+    }
+
+    let mut model = Model2 {
+        id: Key(None),
+        name: "Name".to_owned(),
+        age: 30,
+        model1: EntityLabel::KeyLabel(Key::new(1), "Label".to_owned()),
+        owner: Key::new(1).into_entity(),
+        children: Many::NotFetched,
+    };
+    assert!(model.validate().is_ok());
+
+    model.name = "Too long".to_owned();
+    model.age = -1;
+    model.model1 = EntityLabel::None;
+    model.owner = Key(None).into_entity();
+    model.children = Many::Data(vec![]);
+    let errors = model.validate().unwrap_err();
+    let kinds = errors.errors().iter().map(|e| (e.field, e.kind.clone())).collect::<Vec<_>>();
+    assert_eq!(kinds, vec![
+        ("name", FieldErrorKind::Length { min: Some(1), max: Some(5), actual: 8 }),
+        ("age", FieldErrorKind::Range { min: Some(0.0), max: Some(150.0) }),
+        ("model1", FieldErrorKind::Required),
+        ("owner", FieldErrorKind::RequiredKey),
+    ]);
+
+    model.name = "admin".to_owned();
+    let errors = model.validate().unwrap_err();
+    assert_eq!(errors.field("name").next().unwrap().kind, FieldErrorKind::Custom("reserved name".to_owned()));
+
+    let error: dbent::Error = errors.into();
+    assert!(matches!(error, dbent::Error::Invalid(_)));
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=======================  TIMESTAMPS  =========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_timestamps() {
+    use std::time::SystemTime;
+
+    #[derive(Default, Entity)]
+    #[entity(timestamps)]
+    struct Model {
+        id: Key<Int>,
+        data: String,
+        created_at: Option<SystemTime>,
+        updated_at: Option<SystemTime>,
+    }
+
+    let mut model = Model::default();
+    assert!(model.created_at().is_none());
+    assert!(model.updated_at().is_none());
+
+    model.touch_insert();
+    let created_at = *model.created_at().unwrap();
+    assert_eq!(model.updated_at(), Some(&created_at));
+
+    model.touch_update();
+    assert_eq!(model.created_at(), Some(&created_at));
+    assert!(*model.updated_at().unwrap() >= created_at);
+
+    model.set_created_at(None);
+    model.touch_update();
+    assert!(model.created_at().is_some());
+}
+
+#[test]
+fn test_timestamps_repository() -> Result {
+    use std::time::SystemTime;
+    use dbent::repository::MemoryRepository;
+
+    #[derive(Clone, Default, Entity)]
+    #[entity(timestamps)]
+    struct Model {
+        id: Key<Int>,
+        created_at: Option<SystemTime>,
+        updated_at: Option<SystemTime>,
+    }
+
+    let repo = MemoryRepository::new();
+    let mut model = Model::default();
+    repo.insert(&mut model)?;
+    let created_at = model.created_at.unwrap();
+    assert_eq!(repo.get(&1)?.created_at, Some(created_at));
+    assert_eq!(repo.get(&1)?.updated_at, Some(created_at));
+
+    model.updated_at = None;
+    repo.update(&mut model)?;
+    assert_eq!(model.created_at, Some(created_at));
+    assert!(model.updated_at.unwrap() >= created_at);
+    assert_eq!(repo.get(&1)?.updated_at, model.updated_at);
+    Ok(())
+}
+
+/// A timestamp counting calls to `now`, storable in SQLite
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Tick(i64);
+
+impl dbent::timestamps::Timestamp for Tick {
+    fn now() -> Self {
+        use std::sync::atomic::{AtomicI64, Ordering};
+
+        static TICKS: AtomicI64 = AtomicI64::new(0);
+        Tick(TICKS.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+}
+
+impl From<Tick> for dbent::sql::Value {
+    fn from(tick: Tick) -> Self {
+        tick.0.into()
+    }
+}
+
+impl rusqlite::types::FromSql for Tick {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        i64::column_result(value).map(Tick)
+    }
+}
+
+#[derive(Entity, FromRow)]
+#[entity(fields, timestamps, unique("name"))]
+struct Stamped {
+    id: Key<Int>,
+    name: String,
+    created_at: Option<Tick>,
+    updated_at: Option<Tick>,
+}
+
+#[test]
+fn test_timestamps_upsert() -> Result {
+    use dbent::schema::Schema;
+    use dbent::sqlite::FromRow;
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute(&Stamped::entity_meta().create_table_sql(), [])?;
+    let mut model = Stamped { id: Key(None), name: "a".to_owned(), created_at: None, updated_at: None };
+    dbent::sqlite::insert(&conn, &mut model)?;
+    let created_at = model.created_at.unwrap();
+
+    let mut keyless = Stamped { id: Key(None), name: "a".to_owned(), created_at: None, updated_at: None };
+    dbent::sqlite::upsert(&conn, &mut keyless)?;
+    let mut keyed = Stamped { id: Key::new(1), name: "b".to_owned(), created_at: None, updated_at: None };
+    dbent::sqlite::upsert(&conn, &mut keyed)?;
+
+    let mut statement = conn.prepare("SELECT * FROM stamped WHERE id = 1")?;
+    let stored = statement.query_row([], Stamped::from_row)?;
+    assert_eq!((stored.name.as_str(), stored.created_at), ("b", Some(created_at)));
+    assert_eq!(stored.updated_at, keyed.updated_at);
+
+    let mut revision = Revision { id: Key::new(1), text: "a".to_owned(), rev: Version(1) };
+    assert!(matches!(dbent::sqlite::upsert(&conn, &mut revision), Err(dbent::Error::VersionedUpsert)));
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  VERSION  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_versioned() {
+    #[derive(Entity)]
+    struct Model {
+        id: Key<Int>,
+        #[version] revision: Version,
+    }
+
+    let mut model = Model { id: Key::new(1), revision: Version(3) };
+    assert_eq!(model.version(), Version(3));
+    assert_eq!(model.bump_version(), Version(4));
+    assert_eq!(model.revision, Version(4));
+}
+
+#[derive(Clone, Entity, FromRow)]
+#[entity(fields)]
+struct Revision {
+    id: Key<Int>,
+    text: String,
+    #[version]
+    #[entity(column = "rev")]
+    rev: Version,
+}
+
+#[test]
+fn test_versioned_repository() -> Result {
+    use dbent::repository::MemoryRepository;
+    use dbent::schema::Schema;
+    use dbent::sqlite::TxContext;
+
+    let memory = MemoryRepository::new();
+    let mut conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute(&Revision::entity_meta().create_table_sql(), [])?;
+    let tx = TxContext::begin(&mut conn)?;
+
+    let repos: [&dyn Repository<Revision>; 2] = [&memory, &tx];
+    for repo in repos {
+        let mut revision = Revision { id: Key(None), text: "a".to_owned(), rev: Version(1) };
+        repo.insert(&mut revision)?;
+        assert_eq!(revision.version_column(), "rev");
+
+        let mut stale = revision.clone();
+        revision.text = "b".to_owned();
+        repo.update(&mut revision)?;
+        assert_eq!(revision.rev, Version(2));
+        assert_eq!(repo.get(&1)?.rev, Version(2));
+
+        stale.text = "c".to_owned();
+        assert!(matches!(
+            repo.update(&mut stale),
+            Err(dbent::Error::StaleVersion { expected: Version(1), found: Version(2) })
+        ));
+        assert!(matches!(repo.upsert(&mut stale), Err(dbent::Error::StaleVersion { .. })));
+        assert_eq!(stale.rev, Version(1));
+        assert_eq!(repo.get(&1)?.text, "b");
+    }
+    Ok(())
+}
+
+#[derive(Clone, Entity, FromRow)]
+#[entity(fields, timestamps)]
+struct StampedRevision {
+    id: Key<Int>,
+    #[version]
+    version: Version,
+    created_at: Option<Tick>,
+    updated_at: Option<Tick>,
+}
+
+#[test]
+fn test_versioned_stale_untouched() -> Result {
+    use dbent::repository::MemoryRepository;
+    use dbent::schema::Schema;
+    use dbent::sqlite::TxContext;
+
+    let memory = MemoryRepository::new();
+    let mut conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute(&StampedRevision::entity_meta().create_table_sql(), [])?;
+    let tx = TxContext::begin(&mut conn)?;
+
+    let repos: [&dyn Repository<StampedRevision>; 2] = [&memory, &tx];
+    for repo in repos {
+        let mut revision = StampedRevision { id: Key(None), version: Version(1), created_at: None, updated_at: None };
+        repo.insert(&mut revision)?;
+        let mut stale = revision.clone();
+        repo.update(&mut revision)?;
+
+        assert!(matches!(repo.update(&mut stale), Err(dbent::Error::StaleVersion { .. })));
+        assert_eq!((stale.version, stale.updated_at), (Version(1), stale.created_at));
+    }
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  POSITION  =========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_positioned() -> Result {
+    use dbent::position::Positioned;
+
+    #[derive(Entity)]
+    struct Model {
+        id: Key<Int>,
+        #[position]
+        #[entity(column = "sort_order")]
+        order: i64,
+    }
+
+    let mut many: Many<Model> = vec![Model { id: Key::new(1), order: 4 }, Model { id: Key::new(2), order: 1 }].into();
+    many.sort_by_position()?;
+    assert_eq!(many[0].id, Key::new(2));
+    assert_eq!(many.reindex()?, [0, 1]);
+    assert_eq!(many[1].order, 1);
+    assert_eq!(Model::POSITION_COLUMN, "sort_order");
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><==========================  HOOKS  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+thread_local! {
+    static HOOK_CALLS: std::cell::RefCell<Vec<&'static str>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+#[derive(Clone, Entity, FromRow)]
+#[entity(fields, hooks)]
+struct Hooked {
+    id: Key<Int>,
+    name: String,
+}
+
+impl dbent::hooks::EntityHooks for Hooked {
+    fn before_save(&mut self) -> dbent::Result<()> {
+        HOOK_CALLS.with(|calls| calls.borrow_mut().push("before_save"));
+        self.name = self.name.trim().to_owned();
+        Ok(())
+    }
+
+    fn after_fetch(&mut self) -> dbent::Result<()> {
+        HOOK_CALLS.with(|calls| calls.borrow_mut().push("after_fetch"));
+        Ok(())
+    }
+
+    fn before_delete(&self) -> dbent::Result<()> {
+        HOOK_CALLS.with(|calls| calls.borrow_mut().push("before_delete"));
+        match self.name.as_str() {
+            "locked" => Err(dbent::Error::Aborted("locked".to_owned())),
+            _ => Ok(()),
+        }
+    }
+}
+
+struct Everyone;
+
+impl dbent::sql::ScopePolicy<Hooked> for Everyone {
+    fn filter(&self) -> Option<dbent::sql::Filter> {
+        None
+    }
+}
+
+#[test]
+fn test_hooks_repository() -> Result {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use dbent::repository::{CachedRepository, MemoryRepository};
+    use dbent::schema::Schema;
+    use dbent::sqlite::{PooledContext, ScopedRepository, TxContext};
+
+    let connection = || -> dbent::Result<rusqlite::Connection> {
+        let conn = rusqlite::Connection::open_in_memory()?;
+        conn.execute(&Hooked::entity_meta().create_table_sql(), [])?;
+        Ok(conn)
+    };
+    let calls = || HOOK_CALLS.with(|calls| calls.take());
+
+    let memory = MemoryRepository::new();
+    let cached = CachedRepository::new(MemoryRepository::new(), Duration::from_secs(60));
+    let mut tx_conn = connection()?;
+    let mut tx = TxContext::begin(&mut tx_conn)?;
+    let savepoint = tx.savepoint()?;
+    let pooled = PooledContext::new(Arc::new(Mutex::new(connection()?)));
+    let scoped_conn = connection()?;
+    let scoped = ScopedRepository::new(&scoped_conn, Everyone);
+
+    let repos: [&dyn Repository<Hooked>; 5] = [&memory, &cached, &savepoint, &pooled, &scoped];
+    for repo in repos {
+        calls();
+        let mut hooked = Hooked { id: Key(None), name: " a ".to_owned() };
+        repo.insert(&mut hooked)?;
+        assert_eq!(hooked.name, "a");
+        assert_eq!(calls(), ["before_save"]);
+
+        hooked.name = " b ".to_owned();
+        repo.update(&mut hooked)?;
+        assert_eq!(calls(), ["before_save"]);
+
+        assert_eq!(repo.get(&1)?.name, "b");
+        assert_eq!(repo.list()?.len(), 1);
+        assert_eq!(calls(), ["after_fetch", "after_fetch"]);
+
+        repo.insert(&mut Hooked { id: Key(None), name: "locked".to_owned() })?;
+        calls();
+        assert!(matches!(repo.delete(&2), Err(dbent::Error::Aborted(_))));
+        assert!(repo.get(&2).is_ok());
+        calls();
+
+        repo.delete(&1)?;
+        assert!(calls().ends_with(&["before_delete"]));
+        assert!(matches!(repo.get(&1), Err(dbent::Error::NotFound)));
+    }
+    drop(savepoint);
+
+    calls();
+    tx.insert(&mut Hooked { id: Key(None), name: "c".to_owned() })?;
+    Repository::<Hooked>::delete(&tx, &1)?;
+    assert_eq!(calls(), ["before_save", "after_fetch", "before_delete"]);
+    drop(tx);
+
+    let mut registry = dbent::schema::Registry::new();
+    registry.register::<Hooked>();
+    let tx = TxContext::begin(&mut tx_conn)?.with_registry(registry);
+    tx.insert(&mut Hooked { id: Key(None), name: "locked".to_owned() })?;
+    tx.insert(&mut Hooked { id: Key(None), name: "d".to_owned() })?;
+    calls();
+    assert!(matches!(Repository::<Hooked>::delete(&tx, &1), Err(dbent::Error::Aborted(_))));
+    assert!(matches!(Repository::<Hooked>::delete_many(&tx, &[&2, &1]), Err(dbent::Error::Aborted(_))));
+    assert_eq!(Repository::<Hooked>::list(&tx)?.len(), 2);
+    calls();
+    Repository::<Hooked>::delete(&tx, &2)?;
+    assert_eq!(calls(), ["after_fetch", "before_delete"]);
+    Ok(())
+}
+
+#[derive(Clone, Entity, FromRow)]
+#[entity(fields, hooks)]
+struct HookedRevision {
+    id: Key<Int>,
+    #[version]
+    version: Version,
+}
+
+impl dbent::hooks::EntityHooks for HookedRevision {
+    fn before_save(&mut self) -> dbent::Result<()> {
+        HOOK_CALLS.with(|calls| calls.borrow_mut().push("before_save"));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_hooks_versioned_upsert() -> Result {
+    use dbent::repository::MemoryRepository;
+    use dbent::schema::Schema;
+    use dbent::sqlite::TxContext;
+
+    let calls = || HOOK_CALLS.with(|calls| calls.take());
+    let memory = MemoryRepository::manual();
+    let mut conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute(&HookedRevision::entity_meta().create_table_sql(), [])?;
+    let tx = TxContext::begin(&mut conn)?;
+
+    let repos: [&dyn Repository<HookedRevision>; 2] = [&memory, &tx];
+    for repo in repos {
+        calls();
+        let mut revision = HookedRevision { id: Key::new(1), version: Version(1) };
+        repo.upsert(&mut revision)?;
+        assert_eq!(calls(), ["before_save"]);
+
+        repo.upsert(&mut revision)?;
+        assert_eq!(calls(), ["before_save"]);
+        assert_eq!(repo.get(&1)?.version, Version(2));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_hooks_duplicate_insert() -> Result {
+    use dbent::repository::MemoryRepository;
+
+    let calls = || HOOK_CALLS.with(|calls| calls.take());
+    let repo = MemoryRepository::new();
+    repo.insert(&mut Hooked { id: Key::new(1), name: "a".to_owned() })?;
+    calls();
+
+    let mut duplicate = Hooked { id: Key::new(1), name: " b ".to_owned() };
+    assert!(matches!(repo.insert(&mut duplicate), Err(dbent::Error::DuplicateKey { .. })));
+    assert_eq!(duplicate.name, " b ");
+    assert!(calls().is_empty());
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><==========================  LINK  ============================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_link_entity() -> Result {
+    #[derive(Entity)]
+    struct UserRole {
+        id: Key<LinkKey<Int, String>>,
+        granted_by: String,
+    }
+
+    let roles: Many<UserRole> = vec![
+        UserRole { id: Key::new(LinkKey::new(1, "admin".to_owned())), granted_by: "root".to_owned() },
+        UserRole { id: Key::new(LinkKey::new(1, "editor".to_owned())), granted_by: "root".to_owned() },
+    ].into();
+    assert_eq!(roles.data()?[0].key()?.to_string(), "1:admin");
+    assert_eq!(roles.right_keys()?.len(), 2);
+
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><==========================  FIELDS  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[derive(Entity)]
+#[entity(fields)]
+struct BlogPost {
+    id: Key<Int>,
+    #[entity(column = "post_title")]
+    title: String,
+    r#type: Option<String>,
+    author: Entity<Int, Author>,
+    comments: Many<Author>,
+}
+
+#[derive(Entity)]
+struct Author {
+    id: Key<Int>,
+}
+
+#[test]
+fn test_fields() {
+    use dbent::sql::Value;
+
+    assert_eq!(blog_post::fields::ID.column(), "id");
+    assert_eq!(blog_post::fields::TITLE.column(), "post_title");
+    assert_eq!(blog_post::fields::TYPE.column(), "type");
+
+    let filter = blog_post::fields::AUTHOR.eq(Key::new(7)) & blog_post::fields::TYPE.eq(None);
+    assert_eq!(filter.sql(), "(author = ?) AND (type IS NULL)");
+    assert_eq!(filter.params(), &[Value::Integer(7)]);
+}
+
+#[test]
+fn test_fields_columns() -> Result {
+    use dbent::sql::{Columns, Value};
+
+    let mut posts: Many<BlogPost> = vec![
+        BlogPost { id: Key::new(1), title: "b".to_owned(), r#type: None, author: Entity::Key(Key::new(2)), comments: Many::None },
+        BlogPost { id: Key::new(2), title: "a".to_owned(), r#type: None, author: Entity::Key(Key::new(1)), comments: Many::None },
+    ].into();
+    assert_eq!(posts.data()?[0].column_value("post_title"), Some(Value::Text("b".to_owned())));
+    assert_eq!(posts.data()?[0].column_value("author"), Some(Value::Integer(2)));
+    assert_eq!(posts.data()?[0].column_value("comments"), None);
+    assert_eq!(posts.data()?[0].columns(), &["id", "post_title", "type", "author"]);
+
+    posts.sort_by_order(&blog_post::fields::AUTHOR.asc())?;
+    assert_eq!(posts.data()?[0].title, "a");
+    Ok(())
+}
+
+#[derive(Entity)]
+#[entity(fields, table = "people", unique("name"), unique("name", "author"))]
+struct Person {
+    id: Key<Int>,
+    name: String,
+    score: Option<f64>,
+    author: Entity<Int, Author>,
+    comments: Many<Author>,
+    unknown: std::time::Duration,
+}
+
+#[test]
+fn test_fields_schema() {
+    use dbent::schema::{Schema, SqlType};
+
+    let meta = Person::entity_meta();
+    assert_eq!(meta.table, "people");
+    assert_eq!(meta.key().map(|column| &*column.name), Some("id"));
+    assert_eq!(meta.columns.iter().map(|column| (&*column.name, column.sql_type, column.nullable)).collect::<Vec<_>>(), vec![
+        ("id", SqlType::Integer, false),
+        ("name", SqlType::Text, false),
+        ("score", SqlType::Real, true),
+        ("author", SqlType::Integer, false),
+        ("unknown", SqlType::Any, true),
+    ]);
+    assert_eq!(meta.unique, vec![vec!["name"], vec!["name", "author"]]);
+    assert_eq!(meta.create_table_sql(), "CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL, author INTEGER NOT NULL, unknown, UNIQUE (name), UNIQUE (name, author))");
+    assert_eq!(BlogPost::entity_meta().table, "blog_post");
+    assert!(BlogPost::entity_meta().unique.is_empty());
+    assert_eq!(meta.column("author").unwrap().references, None);
+}
+
+#[derive(Entity, Clone)]
+#[entity(fields, table = "threads")]
+struct Thread {
+    id: Key<Int>,
+    replies: Many<Reply>,
+}
+
+#[derive(Entity, Clone)]
+#[entity(fields)]
+struct Reply {
+    id: Key<Int>,
+    thread: Parent<Int, Thread>,
+    quoted: EntityLabel<Int, Reply, String>,
+}
+
+#[test]
+fn test_fields_schema_relations() {
+    use dbent::schema::Schema;
+
+    let thread = Thread::entity_meta();
+    assert_eq!(thread.many.len(), 1);
+    assert_eq!((&*thread.many[0].field, &*thread.many[0].table), ("replies", "reply"));
+
+    let reply = Reply::entity_meta();
+    assert_eq!(reply.column("thread").unwrap().references.as_deref(), Some("threads"));
+    assert_eq!(reply.column("quoted").unwrap().references.as_deref(), Some("reply"));
+}
+
+#[derive(Entity, Clone)]
+#[entity(fields)]
+struct Customer {
+    id: Key<Int>,
+}
+
+#[derive(Entity, Clone)]
+#[entity(fields, table = "invoices")]
+struct Invoice {
+    id: Key<Int>,
+    customer: Entity<Int, Customer>,
+    #[entity(optional)]
+    approver: Entity<Int, Customer>,
+    lines: Many<InvoiceLine>,
+}
+
+#[derive(Entity, Clone)]
+#[entity(fields)]
+struct InvoiceLine {
+    id: Key<Int>,
+    product: EntityLabel<Int, Customer, String>,
+}
+
+#[test]
+fn test_fields_integrity() {
+    use dbent::graph::{check_integrity, Graph};
+    use dbent::schema::{Cardinality, Schema};
+
+    let meta = Invoice::entity_meta();
+    assert_eq!(meta.column("customer").unwrap().cardinality(), Some(Cardinality::RequiredOne));
+    assert_eq!(meta.column("approver").unwrap().cardinality(), Some(Cardinality::OptionalOne));
+    assert_eq!(meta.column("id").unwrap().cardinality(), None);
+    assert_eq!(meta.create_table_sql(), "CREATE TABLE invoices (id INTEGER PRIMARY KEY, customer INTEGER NOT NULL, approver INTEGER)");
+
+    let line = |id, product| InvoiceLine { id: Key::new(id), product };
+    let mut invoice = Invoice {
+        id: Key::new(1),
+        customer: Entity::None,
+        approver: Entity::None,
+        lines: vec![line(1, EntityLabel::KeyLabel(Key::new(4), "Tea".to_owned())), line(2, EntityLabel::None)].into(),
+    };
+    let cardinalities = invoice.graph_relations().iter().map(|relation| relation.cardinality).collect::<Vec<_>>();
+    assert_eq!(cardinalities, [Cardinality::RequiredOne, Cardinality::OptionalOne, Cardinality::Many]);
+
+    let issues = check_integrity(&invoice);
+    assert_eq!(issues.iter().map(ToString::to_string).collect::<Vec<_>>(), [
+        "invoices(1).customer is required but None",
+        "invoice_line(2).product is required but None",
+    ]);
+
+    invoice.customer = Entity::Key(Key::new(3));
+    invoice.lines = Many::NotFetched;
+    assert!(check_integrity(&invoice).is_empty());
+}
+
+#[test]
+fn test_fields_graph_diff() {
+    use dbent::graph::{diff_graph, Graph, RelationKind};
+    use dbent::sql::Value;
+
+    let reply = |id: Option<Int>, quoted: EntityLabel<Int, Reply, String>| Reply {
+        id: Key(id),
+        thread: Parent::from(Key::new(1)),
+        quoted,
+    };
+    let old = Thread {
+        id: Key::new(1),
+        replies: vec![reply(Some(1), EntityLabel::None), reply(Some(2), EntityLabel::None)].into(),
+    };
+
+    let relations = old.graph_relations();
+    assert_eq!((relations[0].field, relations[0].kind), ("replies", RelationKind::Many));
+    assert_eq!(relations[0].nodes.as_ref().unwrap().len(), 2);
+    assert_eq!(old.graph_table(), "threads");
+    assert_eq!(old.graph_key(), Value::Integer(1));
+    assert!(diff_graph(&old, &old.clone()).is_empty());
+
+    let mut new = old.clone();
+    new.replies = vec![
+        reply(Some(1), EntityLabel::KeyLabel(Key::new(2), "quote".to_owned())),
+        reply(None, EntityLabel::None),
+    ].into();
+
+    let diff = diff_graph(&old, &new);
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!((&*diff.changed[0].table, &diff.changed[0].key), ("reply", &Value::Integer(1)));
+    assert_eq!(diff.changed[0].changes[0].column, "quoted");
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!((diff.added[0].key.clone(), diff.added[0].field), (Value::Null, "replies"));
+    assert_eq!((&*diff.added[0].parent_table, &diff.added[0].parent_key), ("threads", &Value::Integer(1)));
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(diff.removed[0].key, Value::Integer(2));
+
+    new.replies = Many::NotFetched;
+    assert!(diff_graph(&old, &new).is_empty());
+}
+
+#[test]
+fn test_fields_debug_graph() {
+    use dbent::graph::{debug_graph, Graph, RelationState};
+
+    let reply = |id, quoted| Reply { id: Key::new(id), thread: Parent::from(Key::new(1)), quoted };
+    let quoted = reply(3, EntityLabel::KeyLabel(Key::new(1), "first".to_owned()));
+    let mut thread = Thread {
+        id: Key::new(1),
+        replies: vec![reply(1, EntityLabel::None), reply(2, EntityLabel::Data(Box::new(quoted)))].into(),
+    };
+    assert_eq!(thread.graph_name(), "Thread");
+    assert_eq!(thread.graph_relations()[0].state, RelationState::Fetched);
+    assert_eq!(debug_graph(&thread), "\
+Thread(1)
+└─ replies: [2]
+   ├─ Reply(1)
+   │  ├─ thread: Key(1)
+   │  └─ quoted: None
+   └─ Reply(2)
+      ├─ thread: Key(1)
+      └─ quoted: Reply(3)
+         ├─ thread: Key(1)
+         └─ quoted: Key(1)
+");
+
+    thread.replies = Many::NotFetched;
+    assert_eq!(debug_graph(&thread), "Thread(1)\n└─ replies: NotFetched\n");
+}
+
+#[test]
+fn test_fields_graph_stats() {
+    use dbent::graph::graph_stats;
+
+    let reply = |id, quoted| Reply { id: Key::new(id), thread: Parent::from(Key::new(1)), quoted };
+    let quoted = reply(3, EntityLabel::KeyLabel(Key::new(1), "first".to_owned()));
+    let mut thread = Thread {
+        id: Key::new(1),
+        replies: vec![reply(1, EntityLabel::None), reply(2, EntityLabel::Data(Box::new(quoted)))].into(),
+    };
+
+    let stats = graph_stats(&thread);
+    assert_eq!((stats.entities, stats.tables["threads"], stats.tables["reply"]), (4, 1, 3));
+    assert_eq!((stats.fetched, stats.not_fetched, stats.none), (2, 4, 1));
+    assert_eq!((stats.many_elements, stats.depth), (2, 2));
+    assert_eq!(stats.approx_bytes, core::mem::size_of::<Thread>() + 3 * core::mem::size_of::<Reply>());
+
+    thread.replies = Many::NotFetched;
+    let stats = graph_stats(&thread);
+    assert_eq!((stats.entities, stats.fetched, stats.not_fetched, stats.many_elements, stats.depth), (1, 0, 1, 0, 0));
+
+    let person = Person {
+        id: Key::new(1),
+        name: "Ann".to_owned(),
+        score: None,
+        author: Entity::None,
+        comments: Many::None,
+        unknown: std::time::Duration::ZERO,
+    };
+    let stats = graph_stats(&person);
+    assert_eq!((stats.none, stats.approx_bytes), (2, core::mem::size_of::<Person>() + 3));
+}
+
+#[test]
+fn test_fields_clone_as_new() {
+    use dbent::graph::Graph;
+
+    let quoted = Reply { id: Key::new(3), thread: Parent::from(Key::new(2)), quoted: EntityLabel::None };
+    let reply = |id, quoted| Reply { id: Key::new(id), thread: Parent::from(Key::new(1)), quoted };
+    let thread = Thread {
+        id: Key::new(1),
+        replies: vec![reply(1, EntityLabel::None), reply(2, EntityLabel::Data(Box::new(quoted)))].into(),
+    };
+
+    let copy = thread.clone_as_new();
+    assert_eq!(copy.id, Key(None));
+    let replies = copy.replies.data().unwrap();
+    assert_eq!(replies.len(), 2);
+    assert!(replies.iter().all(|reply| reply.id == Key(None) && matches!(reply.thread.0, Entity::None)));
+    let EntityLabel::Data(quoted) = &replies[1].quoted else { panic!("quoted reply not copied") };
+    assert_eq!((&quoted.id, quoted.thread.key().unwrap()), (&Key::new(3), &Key::new(2)));
+    assert_eq!(thread.id, Key::new(1));
+
+    let reply = reply(4, EntityLabel::None).clone_as_new();
+    assert_eq!((&reply.id, reply.thread.key().unwrap()), (&Key(None), &Key::new(1)));
+}
+
+#[derive(Entity)]
+#[entity(fields, track_reads, table = "wide")]
+struct Wide {
+    id: Key<Int>,
+    name: String,
+    #[entity(column = "body_text")]
+    body: String,
+    owner: Entity<Int, Customer>,
+    tags: Many<Customer>,
+}
+
+#[test]
+fn test_fields_track_reads() {
+    use std::sync::Arc;
+    use dbent::reads::{self, ReadTracker};
+
+    let wide = Wide { id: Key::new(1), name: "a".to_owned(), body: "b".to_owned(), owner: Entity::None, tags: Many::None };
+    assert_eq!(wide.name(), "a");
+
+    let tracker = Arc::new(ReadTracker::new());
+    assert_eq!(tracker.projection::<Wide>(), ["id"]);
+    reads::set_tracker(tracker.clone());
+    assert_eq!(wide.body(), "b");
+    assert!(wide.owner().is_none());
+    reads::clear_tracker();
+    assert_eq!(wide.name(), "a");
+
+    assert!(tracker.was_read::<Wide>("body_text"));
+    assert!(!tracker.was_read::<Wide>("name"));
+    assert_eq!(tracker.projection::<Wide>(), ["id", "body_text", "owner"]);
+    assert_eq!(tracker.unread::<Wide>(), ["name"]);
+    assert_eq!(tracker.select_sql::<Wide>(), "SELECT id, body_text, owner FROM wide");
+    assert_eq!(tracker.reads()["wide"].len(), 2);
+
+    tracker.reset();
+    assert!(tracker.reads().is_empty());
+}
+
+/// Maps its relations only on the server, as with `cfg(feature = "full")`
+/// where `any()` stands for a disabled feature and `all()` for an enabled one
+#[derive(Entity, FromRow)]
+#[entity(fields, accessors, unique("name", "owner"))]
+struct Account {
+    id: Key<Int>,
+    #[entity(cfg(all()))]
+    name: String,
+    #[entity(cfg(any()))]
+    owner: Entity<Int, Customer>,
+    #[entity(cfg(any()))]
+    invoices: Many<Invoice>,
+    #[cfg(any())]
+    secret: String,
+}
+
+#[test]
+fn test_fields_cfg() -> Result {
+    use dbent::graph::Graph;
+    use dbent::schema::Schema;
+    use dbent::sql::Columns;
+    use dbent::sqlite::FromRow;
+
+    let meta = Account::entity_meta();
+    assert_eq!(meta.create_table_sql(), "CREATE TABLE account (id INTEGER PRIMARY KEY, name TEXT NOT NULL)");
+    assert!(meta.many.is_empty());
+
+    let account = Account { id: Key::new(1), name: "Ann".to_owned(), owner: Entity::Key(Key::new(2)), invoices: Many::NotFetched };
+    assert_eq!(account.columns(), ["id", "name"]);
+    assert_eq!(account.column_value("owner"), None);
+    assert!(account.graph_relations().is_empty());
+    assert_eq!(account::fields::NAME.column(), "name");
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch("CREATE TABLE account (id INTEGER PRIMARY KEY, name TEXT NOT NULL); INSERT INTO account VALUES (1, 'Ann');")?;
+    let account = conn.query_row("SELECT * FROM account", [], Account::from_row)?;
+    assert_eq!(account.name, "Ann");
+    assert!(account.owner.is_none() && account.invoices.is_not_fetched());
+    Ok(())
+}
+
+#[derive(Entity, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[entity(fields)]
+struct Photo {
+    id: Key<Int>,
+    url: String,
+}
+
+#[derive(Entity, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[entity(fields, table = "videos", id_prefix = "vid")]
+struct Video {
+    id: Key<Int>,
+}
+
+#[derive(Entity, FromRow, serde::Serialize, serde::Deserialize)]
+#[entity(fields)]
+struct Remark {
+    id: Key<Int>,
+    #[entity(optional)]
+    subject: PolyEntity<(Photo, Video)>,
+}
+
+#[test]
+fn test_fields_poly_entity() -> Result {
+    use dbent::graph::{Graph, RelationState};
+    use dbent::poly::OneOf2;
+    use dbent::schema::Schema;
+    use dbent::sql::{Columns, Value};
+    use dbent::sqlite::FromRow;
+
+    assert_eq!((Video::entity_meta().id_prefix(), Video::id_prefix()), ("vid", "vid".into()));
+    assert_eq!((Remark::entity_meta().id_prefix, Remark::id_prefix()), (None, "remark".into()));
+
+    let meta = Remark::entity_meta();
+    assert_eq!(meta.create_table_sql(), "CREATE TABLE remark (id INTEGER PRIMARY KEY, subject INTEGER, subject_type TEXT)");
+    assert_eq!(remark::fields::SUBJECT_TYPE.column(), "subject_type");
+
+    let photo = Photo { id: Key::new(2), url: "a.png".to_owned() };
+    let remark = Remark { id: Key::new(1), subject: OneOf2::A(photo).into() };
+    assert_eq!(remark.columns(), ["id", "subject", "subject_type"]);
+    assert_eq!(remark.column_value("subject"), Some(Value::Integer(2)));
+    assert_eq!(remark.column_value("subject_type"), Some(Value::Text("photo".to_owned())));
+    let relations = remark.graph_relations();
+    assert_eq!(relations[0].state, RelationState::Fetched);
+    assert_eq!(relations[0].nodes.as_ref().unwrap()[0].graph_table(), "photo");
+
+    let json = serde_json::to_string(&remark).unwrap();
+    assert_eq!(json, r#"{"id":1,"subject":{"photo":{"Data":{"id":2,"url":"a.png"}}}}"#);
+    let remark: Remark = serde_json::from_str(&json).unwrap();
+    assert_eq!(remark.subject.data()?, &OneOf2::A(Photo { id: Key::new(2), url: "a.png".to_owned() }));
+
+    let remark: Remark = serde_json::from_str(r#"{"id":1,"subject":{"videos":{"Key":3}}}"#).unwrap();
+    assert_eq!(remark.subject, PolyEntity::from_key::<Video>(Key::new(3)));
+    assert_eq!(serde_json::to_string(&remark.subject).unwrap(), r#"{"videos":{"Key":3}}"#);
+    assert!(serde_json::from_str::<Remark>(r#"{"id":1,"subject":{"audio":{"Key":3}}}"#).is_err());
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch(&meta.create_table_sql())?;
+    conn.execute_batch("INSERT INTO remark VALUES (1, 3, 'videos'), (2, NULL, NULL), (3, 4, 'audio');")?;
+    let mut statement = conn.prepare("SELECT * FROM remark WHERE id < 3 ORDER BY id")?;
+    let remarks = statement.query_map([], Remark::from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    assert_eq!(remarks[0].subject.tag().unwrap(), "videos");
+    assert_eq!(remarks[0].subject.key()?, &Key::new(3));
+    assert!(remarks[1].subject.is_none());
+    assert_eq!(serde_json::to_string(&remarks[1].subject).unwrap(), r#""None""#);
+    assert!(conn.query_row("SELECT * FROM remark WHERE id = 3", [], Remark::from_row).is_err());
+    Ok(())
+}
+
+#[serde_relations]
+#[derive(Entity, serde::Serialize, serde::Deserialize)]
+struct Shipment {
+    id: Key<Int>,
+    order: Entity<Int, String>,
+    carrier: EntityLabel<Int, String, String>,
+    parent: Parent<Int, Author>,
+    items: Many<String>,
+    #[serde(default)]
+    notes: Many<String>,
+}
+
+#[test]
+fn test_serde_relations() {
+    let shipment = Shipment {
+        id: Key::new(1),
+        order: Entity::None,
+        carrier: EntityLabel::None,
+        parent: Parent::default(),
+        items: Many::NotFetched,
+        notes: Many::None,
+    };
+    assert_eq!(serde_json::to_string(&shipment).unwrap(), r#"{"id":1,"notes":"None"}"#);
+
+    let shipment = Shipment { order: Entity::Key(Key::new(2)), items: vec!["a".to_owned()].into(), ..shipment };
+    assert_eq!(
+        serde_json::to_string(&shipment).unwrap(),
+        r#"{"id":1,"order":{"Key":2},"items":{"Data":["a"]},"notes":"None"}"#,
+    );
+
+    let shipment: Shipment = serde_json::from_str(r#"{"id":1}"#).unwrap();
+    assert!(shipment.order.is_none() && shipment.carrier.is_none() && shipment.parent.is_none());
+    assert!(shipment.items.is_not_fetched());
+    assert!(shipment.notes.is_none());
+}
+
+#[test]
+fn test_serde_relations_wire() -> Result {
+    use dbent::wire::{from_bytes, to_bytes};
+
+    let shipment = Shipment {
+        id: Key::new(1),
+        order: Entity::None,
+        carrier: EntityLabel::KeyLabel(Key::new(3), "Post".to_owned()),
+        parent: Parent::default(),
+        items: Many::NotFetched,
+        notes: vec!["fragile".to_owned()].into(),
+    };
+    let decoded = from_bytes::<Shipment>(&to_bytes(&shipment)?)?;
+    assert_eq!(decoded.id, Key::new(1));
+    assert!(decoded.order.is_none() && decoded.parent.is_none());
+    assert!(matches!(&decoded.carrier, EntityLabel::KeyLabel(key, label) if *key == Key::new(3) && label == "Post"));
+    assert!(decoded.items.is_not_fetched());
+    assert_eq!(decoded.notes.data()?, &["fragile".to_owned()]);
+
+    let shipment = Shipment { order: Entity::Key(Key::new(2)), items: Many::None, notes: Many::None, ..decoded };
+    let decoded = from_bytes::<Shipment>(&to_bytes(&shipment)?)?;
+    assert!(matches!(decoded.order, Entity::Key(key) if key == Key::new(2)));
+    assert!(decoded.items.is_none());
+    assert!(decoded.notes.is_none());
+    Ok(())
+}
+
+#[derive(Entity)]
+#[entity(fields, queries, table = "bookmarks")]
+struct Bookmark {
+    id: Key<Int>,
+    url: String,
+    visits: i64,
+}
+
+#[test]
+fn test_fields_queries() -> Result {
+    use dbent::schema::Schema;
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch(&Bookmark::entity_meta().create_table_sql())?;
+    assert_eq!(Bookmark::count(&conn)?, 0);
+    assert!(!Bookmark::exists(&conn, &1)?);
+
+    conn.execute_batch("INSERT INTO bookmarks (url, visits) VALUES ('a', 1), ('b', 5), ('c', 9);")?;
+    assert!(Bookmark::exists(&conn, &1)? && !Bookmark::exists(&conn, &4)?);
+    assert_eq!(Bookmark::count(&conn)?, 3);
+    assert_eq!(Bookmark::count_where(&conn, &bookmark::fields::VISITS.gt(1))?, 2);
+    Ok(())
+}
+
+#[derive(Entity, FromRow)]
+#[entity(fields, queries, table = "listings")]
+struct Listing {
+    id: Key<Int>,
+    title: String,
+    price: i64,
+}
+
+#[test]
+fn test_fields_queries_select() -> Result {
+    use dbent::schema::Schema;
+    use listing::fields;
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch(&Listing::entity_meta().create_table_sql())?;
+    conn.execute_batch("INSERT INTO listings (title, price) VALUES ('Lamp', 40), ('Desk', 250), ('Chair', 90), ('Rug', 120);")?;
+
+    let listings = Listing::select()
+        .filter(fields::PRICE.gt(50))
+        .order(fields::PRICE.desc())
+        .limit(2)
+        .fetch(&conn)?;
+    assert_eq!(listings.iter().map(|listing| listing.title.as_str()).collect::<Vec<_>>(), ["Desk", "Rug"]);
+
+    let cheapest = Listing::select().order(fields::PRICE.asc()).first(&conn)?;
+    assert_eq!(cheapest.map(|listing| listing.title).as_deref(), Some("Lamp"));
+    assert_eq!(Listing::select().filter(fields::TITLE.like("%r%")).count(&conn)?, 2);
+    Ok(())
+}
+
+#[derive(Entity, FromRow)]
+#[entity(fields)]
+struct Drive {
+    id: Key<Int>,
+}
+
+#[derive(Entity, FromRow)]
+#[entity(fields)]
+struct Doc {
+    id: Key<Int>,
+    #[entity(on_delete = "cascade")]
+    drive: Entity<Int, Drive>,
+    #[entity(optional, on_delete = "set_null")]
+    template: Entity<Int, Doc>,
+}
+
+#[derive(Entity, FromRow)]
+#[entity(fields)]
+struct DocShare {
+    id: Key<Int>,
+    #[entity(on_delete = "restrict")]
+    doc: Entity<Int, Doc>,
+}
+
+#[test]
+fn test_fields_on_delete() -> Result {
+    use dbent::repository::Repository;
+    use dbent::schema::{OnDelete, Registry, Schema};
+    use dbent::sql::Value;
+    use dbent::sqlite::{self, DeleteEffect, TxContext};
+    use dbent::Error;
+
+    assert_eq!(Doc::entity_meta().column("drive").unwrap().on_delete, Some(OnDelete::Cascade));
+    assert_eq!(Doc::entity_meta().column("template").unwrap().on_delete, Some(OnDelete::SetNull));
+    assert_eq!(Drive::entity_meta().column("id").unwrap().on_delete, None);
+
+    let mut registry = Registry::new();
+    registry.register::<Drive>().register::<Doc>().register::<DocShare>();
+    let mut conn = rusqlite::Connection::open_in_memory()?;
+    for sql in registry.create_tables_sql() {
+        conn.execute_batch(&sql)?;
+    }
+    conn.execute_batch(
+        "INSERT INTO drive VALUES (1), (2);
+         INSERT INTO doc VALUES (1, 1, NULL), (2, 1, 1), (3, 2, 1);
+         INSERT INTO doc_share VALUES (1, 3);",
+    )?;
+
+    let delete = |table: &str, key| DeleteEffect::Delete { table: table.to_owned(), key: Value::Integer(key) };
+    let set_null = |key| DeleteEffect::SetNull { table: "doc".to_owned(), column: "template".to_owned(), key: Value::Integer(key) };
+    assert_eq!(
+        sqlite::delete_plan::<Drive>(&conn, &registry, &1)?,
+        [set_null(2), set_null(3), delete("doc", 1), delete("doc", 2), delete("drive", 1)],
+    );
+    let err = sqlite::delete_plan::<Drive>(&conn, &registry, &2).unwrap_err();
+    assert_eq!(err.to_string(), "can't delete from doc while referenced by doc_share.doc");
+    assert!(matches!(sqlite::delete_plan::<Drive>(&conn, &registry, &3), Err(Error::NotFound)));
+
+    let tx = TxContext::begin(&mut conn)?.with_registry(registry);
+    Repository::<Drive>::delete(&tx, &1)?;
+    assert!(matches!(Repository::<Drive>::delete(&tx, &2), Err(Error::DeleteRestricted { .. })));
+    assert_eq!(Repository::<Doc>::list(&tx)?.iter().map(|doc| (doc.id.unwrap(), doc.template.is_none())).collect::<Vec<_>>(), [(3, true)]);
+    assert_eq!(Repository::<Drive>::list(&tx)?.len(), 1);
+    tx.commit()?;
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch(
+        "PRAGMA foreign_keys = ON;
+         CREATE TABLE drive (id INTEGER PRIMARY KEY);
+         CREATE TABLE doc (id INTEGER PRIMARY KEY, drive INTEGER NOT NULL REFERENCES drive, template INTEGER REFERENCES doc);
+         CREATE TABLE doc_share (id INTEGER PRIMARY KEY, doc INTEGER NOT NULL REFERENCES doc);
+         INSERT INTO drive VALUES (1), (2);
+         INSERT INTO doc VALUES (1, 1, NULL), (2, 1, 1), (3, 2, 1);",
+    )?;
+    let mut registry = Registry::new();
+    registry.register::<Drive>().register::<Doc>().register::<DocShare>();
+    assert_eq!(sqlite::delete_cascading::<Drive>(&conn, &registry, &1)?.len(), 5);
+    assert_eq!(conn.query_row("SELECT COUNT(*) FROM doc WHERE template IS NULL", [], |row| row.get::<_, i64>(0))?, 1);
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  FROM ROW  =========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_from_row() -> Result {
+    use dbent::sqlite::FromRow;
+
+    #[derive(Entity, Label)]
+    struct User {
+        id: Key<Int>,
+        #[label]
+        name: String,
+    }
+
+    #[derive(Entity, FromRow)]
+    struct Comment {
+        id: Key<Int>,
+        #[entity(column = "body")]
+        text: String,
+        post: EntityInt<User>,
+        parent: Parent<Int, User>,
+        #[entity(column = "author_id", join(label = "author_label"))]
+        author: EntityLabelInt<User>,
+        replies: Many<User>,
+    }
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch("
+        CREATE TABLE user (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE comment (id INTEGER PRIMARY KEY, body TEXT NOT NULL, post INTEGER, parent INTEGER, author_id INTEGER);
+        INSERT INTO user VALUES (1, 'Ann');
+        INSERT INTO comment VALUES (1, 'First', 7, 3, 1), (2, 'Second', NULL, NULL, 2);
+    ")?;
+
+    let mut statement = conn.prepare("
+        SELECT comment.*, user.name AS author_label
+        FROM comment LEFT JOIN user ON user.id = comment.author_id
+        ORDER BY comment.id
+    ")?;
+    let comments = statement.query_map([], Comment::from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+    assert_eq!(comments[0].text, "First");
+    assert_eq!(comments[0].post.key()?, &Key::new(7));
+    assert_eq!(comments[0].parent.key()?, &Key::new(3));
+    assert_eq!(comments[0].author.key()?, &Key::new(1));
+    assert_eq!(comments[0].author.label()?, "Ann");
+    assert!(comments[0].replies.is_not_fetched());
+
+    assert!(comments[1].post.is_none());
+    assert!(comments[1].parent.is_none());
+    assert!(comments[1].author.is_none());
+    Ok(())
+}
+
+#[derive(Entity, FromRow)]
+#[entity(fields)]
+struct Line {
+    id: Key<Int>,
+    #[entity(column = "name")]
+    product: String,
+    quantity: i64,
+}
+
+#[derive(Entity, FromRow)]
+struct Order {
+    id: Key<Int>,
+    customer: String,
+    lines: Many<Line>,
+}
+
+#[test]
+fn test_query_with_many() -> Result {
+    use dbent::sqlite::{self, FromRow};
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch("
+        CREATE TABLE orders (id INTEGER PRIMARY KEY, customer TEXT NOT NULL);
+        CREATE TABLE line (id INTEGER PRIMARY KEY, order_id INTEGER NOT NULL, name TEXT NOT NULL, quantity INTEGER NOT NULL);
+        INSERT INTO orders VALUES (1, 'Ann'), (2, 'Bob'), (3, 'Cid');
+        INSERT INTO line VALUES (1, 1, 'Tea', 2), (2, 3, 'Jam', 1), (3, 1, 'Milk', 1);
+    ")?;
+
+    let line = conn.query_row("SELECT 5 AS l_id, 'Salt' AS l_name, 3 AS l_quantity", [], |row| Line::from_row_prefixed(row, "l_"))?;
+    assert_eq!((line.id, line.product.as_str(), line.quantity), (Key::new(5), "Salt", 3));
+
+    let orders: Vec<Order> = sqlite::query_with_many(
+        &conn,
+        "SELECT orders.*, line.id AS line_id, line.name AS line_name, line.quantity AS line_quantity
+         FROM orders LEFT JOIN line ON line.order_id = orders.id
+         WHERE orders.id <> ? ORDER BY orders.id, line.id",
+        [9],
+        "line_",
+        |order: &mut Order| &mut order.lines,
+    )?;
+
+    assert_eq!(orders.iter().map(|order| order.customer.as_str()).collect::<Vec<_>>(), ["Ann", "Bob", "Cid"]);
+    assert_eq!(orders[0].lines.data()?.iter().map(|line| line.product.as_str()).collect::<Vec<_>>(), ["Tea", "Milk"]);
+    assert_eq!(orders[0].lines[1].quantity, 1);
+    assert_eq!(orders[1].lines.is_empty(), Some(true));
+    assert_eq!(orders[2].lines[0].id, Key::new(2));
+    Ok(())
+}
+
+#[derive(Entity)]
+#[entity(fields)]
+struct Tagv {
+    id: Key<Int>,
+    name: dbent::intern::InternedStr,
+    initial: char,
+    title: std::borrow::Cow<'static, str>,
+}
+
+#[test]
+fn test_text_column_types() -> Result {
+    use dbent::schema::{Schema, SqlType};
+    use dbent::sqlite;
+
+    let meta = Tagv::entity_meta();
+    for column in ["name", "initial", "title"] {
+        assert_eq!(meta.column(column).unwrap().sql_type, SqlType::Text);
+        assert!(!meta.column(column).unwrap().nullable);
+    }
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute(&meta.create_table_sql(), [])?;
+    let mut tag = Tagv { id: Key(None), name: "rust".into(), initial: 'r', title: "Rust".into() };
+    sqlite::insert(&conn, &mut tag)?;
+    let row = conn.query_row("SELECT name, initial, title FROM tagv", [], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })?;
+    assert_eq!(row, ("rust".to_owned(), "r".to_owned(), "Rust".to_owned()));
+
+    tag.initial = 'R';
+    sqlite::upsert(&conn, &mut tag)?;
+    let initial: String = conn.query_row("SELECT initial FROM tagv", [], |row| row.get(0))?;
+    assert_eq!(initial, "R");
+    Ok(())
+}
+
+#[derive(Entity, FromRow)]
+#[entity(fields)]
+struct Patient {
+    id: Key<Int>,
+    #[entity(encrypt)]
+    ssn: String,
+    #[entity(encrypt, column = "notes")]
+    note: Option<String>,
+}
+
+/// Stores text as blobs of reversed bytes
+struct Reverse;
+
+impl dbent::cipher::FieldCipher for Reverse {
+    fn encrypt(&self, _column: &str, value: dbent::sql::Value) -> dbent::Result<dbent::sql::Value> {
+        match value {
+            dbent::sql::Value::Text(text) => Ok(dbent::sql::Value::Blob(text.into_bytes().into_iter().rev().collect())),
+            value => Err(dbent::Error::Cipher(format!("{value:?} is not text"))),
+        }
+    }
+
+    fn decrypt(&self, _column: &str, value: dbent::sql::Value) -> dbent::Result<dbent::sql::Value> {
+        match value {
+            dbent::sql::Value::Blob(bytes) => Ok(dbent::sql::Value::Text(String::from_utf8(bytes.into_iter().rev().collect()).unwrap())),
+            value => Err(dbent::Error::Cipher(format!("{value:?} is not a blob"))),
+        }
+    }
+}
+
+#[test]
+fn test_encrypt() -> Result {
+    use dbent::cipher;
+    use dbent::schema::{Schema, SqlType};
+    use dbent::sql::{Columns, Value};
+    use dbent::sqlite::{self, FromRow};
+
+    let meta = Patient::entity_meta();
+    assert_eq!(meta.column("ssn").unwrap().sql_type, SqlType::Any);
+    assert!(!meta.column("ssn").unwrap().nullable);
+    assert!(meta.column("notes").unwrap().nullable);
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute(&meta.create_table_sql(), [])?;
+    let mut patient = Patient { id: Key(None), ssn: "123".to_owned(), note: None };
+    assert_eq!(patient.column_value("ssn"), None);
+    assert!(matches!(patient.try_column_value("ssn"), Err(dbent::Error::CipherMissing(column)) if column == "ssn"));
+    assert!(matches!(sqlite::insert(&conn, &mut patient), Err(dbent::Error::CipherMissing(_))));
+    assert!(matches!(patient.named_params(), Err(dbent::Error::CipherMissing(_))));
+    assert_eq!(conn.query_row("SELECT COUNT(*) FROM patient", [], |row| row.get::<_, i64>(0))?, 0);
+
+    cipher::set_cipher(Reverse);
+    assert_eq!(patient.column_value("ssn"), Some(Value::Blob(b"321".to_vec())));
+    assert_eq!(patient.column_value("notes"), Some(Value::Null));
+    sqlite::insert(&conn, &mut patient)?;
+    sqlite::insert(&conn, &mut Patient { id: Key(None), ssn: "456".to_owned(), note: Some("ok".to_owned()) })?;
+
+    let stored: Vec<u8> = conn.query_row("SELECT ssn FROM patient WHERE id = 1", [], |row| row.get(0))?;
+    assert_eq!(stored, b"321");
+    let read = |id: Int| conn.query_row("SELECT * FROM patient WHERE id = ?", [id], Patient::from_row);
+    let patient = read(2)?;
+    assert_eq!((patient.ssn.as_str(), patient.note.as_deref()), ("456", Some("ok")));
+    assert_eq!(read(1)?.note, None);
+
+    conn.execute("UPDATE patient SET ssn = 'plain' WHERE id = 1", [])?;
+    assert!(matches!(read(1), Err(rusqlite::Error::FromSqlConversionFailure(1, _, _))));
+
+    cipher::clear_cipher();
+    assert!(matches!(read(2), Err(rusqlite::Error::FromSqlConversionFailure(1, _, _))));
+    Ok(())
+}
+
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><========================  PROJECTION  ========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_projection() -> Result {
+    use dbent::sql::{Columns, Value};
+    use dbent::sqlite::FromRow;
+
+    #[derive(Entity)]
+    struct User {
+        id: Key<Int>,
+        name: String,
+    }
+
+    #[derive(Projection)]
+    struct UserTotal {
+        user: EntityInt<User>,
+        #[label]
+        #[entity(column = "user_name")]
+        name: String,
+        total: i64,
+    }
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch("
+        CREATE TABLE user (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE purchase (user_id INTEGER NOT NULL, amount INTEGER NOT NULL);
+        INSERT INTO user VALUES (1, 'Ann'), (2, 'Bob');
+        INSERT INTO purchase VALUES (1, 10), (1, 5), (2, 7);
+    ")?;
+
+    let mut statement = conn.prepare("
+        SELECT user.id AS user, user.name AS user_name, SUM(amount) AS total
+        FROM purchase JOIN user ON user.id = purchase.user_id
+        GROUP BY user.id ORDER BY user.id
+    ")?;
+    let totals = statement.query_map([], UserTotal::from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+    assert_eq!(totals.len(), 2);
+    assert_eq!(totals[0].label()?, "Ann");
+    assert_eq!(totals[0].total, 15);
+    assert_eq!(totals[1].user.key()?, &Key::new(2));
+    assert_eq!(totals[1].columns(), ["user", "user_name", "total"]);
+    assert_eq!(totals[1].column_value("user"), Some(Value::Integer(2)));
+    assert_eq!(totals[1].column_value("total"), Some(Value::Integer(7)));
+    Ok(())
+}
+
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  DB ENUM  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[derive(DbEnum, Clone, Copy, PartialEq, Debug)]
+enum IssueStatus {
+    Open,
+    #[db_enum(value = "done")]
+    InProgress,
+    ClosedForGood,
+}
+
+#[derive(DbEnum, Clone, Copy, PartialEq, Debug)]
+#[db_enum(integer)]
+enum Priority {
+    Low = -1,
+    #[db_enum(value = 5)]
+    High = 2,
+}
+
+#[derive(Entity, FromRow)]
+#[entity(fields)]
+struct Issue {
+    id: Key<Int>,
+    status: IssueStatus,
+    priority: Option<Priority>,
+}
+
+#[test]
+fn test_db_enum() -> Result {
+    use dbent::schema::Schema;
+    use dbent::sql::{Columns, DbEnum, Value};
+    use dbent::sqlite::FromRow;
+
+    assert_eq!(IssueStatus::Open.db_value(), Value::Text("open".to_owned()));
+    assert_eq!(IssueStatus::InProgress.db_value(), Value::Text("done".to_owned()));
+    assert_eq!(Value::from(IssueStatus::ClosedForGood), Value::Text("closed_for_good".to_owned()));
+    assert_eq!(IssueStatus::from_db_value(&Value::Text("done".to_owned())), Some(IssueStatus::InProgress));
+    assert_eq!(IssueStatus::from_db_value(&Value::Text("in_progress".to_owned())), None);
+    assert_eq!(IssueStatus::from_db_value(&Value::Integer(0)), None);
+    assert_eq!((Priority::Low.db_value(), Priority::High.db_value()), (Value::Integer(-1), Value::Integer(5)));
+    assert_eq!(Priority::from_db_value(&Value::Integer(2)), None);
+    assert!(matches!(
+        Priority::try_from_db_value(Value::Integer(2)),
+        Err(dbent::Error::UnknownVariant { name: "Priority", value: Value::Integer(2) }),
+    ));
+
+    let meta = Issue::entity_meta();
+    assert_eq!(meta.create_table_sql(), "CREATE TABLE issue (id INTEGER PRIMARY KEY, status TEXT NOT NULL, priority INTEGER)");
+
+    let issue = Issue { id: Key::new(1), status: IssueStatus::InProgress, priority: Some(Priority::High) };
+    assert_eq!(issue.column_value("status"), Some(Value::Text("done".to_owned())));
+    assert_eq!(issue.column_value("priority"), Some(Value::Integer(5)));
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute(&meta.create_table_sql(), [])?;
+    conn.execute("INSERT INTO issue VALUES (?, ?, ?)", rusqlite::params![1, issue.status, issue.priority])?;
+    conn.execute("INSERT INTO issue VALUES (2, 'open', NULL), (3, 'lost', NULL)", [])?;
+    let mut statement = conn.prepare("SELECT * FROM issue WHERE id = ?")?;
+    let issue = statement.query_row([1], Issue::from_row)?;
+    assert_eq!((issue.status, issue.priority), (IssueStatus::InProgress, Some(Priority::High)));
+    let issue = statement.query_row([2], Issue::from_row)?;
+    assert_eq!((issue.status, issue.priority), (IssueStatus::Open, None));
+    assert!(matches!(statement.query_row([3], Issue::from_row), Err(rusqlite::Error::FromSqlConversionFailure(..))));
+    Ok(())
+}
+
+#[derive(DbEnum, Clone, Copy, PartialEq, Debug)]
+enum EntityKind {
+    Person,
+    Company,
+}
+
+#[derive(Entity)]
+#[entity(fields)]
+struct Contact {
+    id: Key<Int>,
+    kind: EntityKind,
+}
+
+#[test]
+fn test_entity_named_field() {
+    use dbent::graph::Graph;
+    use dbent::schema::Schema;
+    use dbent::sql::{Columns, Value};
+
+    let meta = Contact::entity_meta();
+    assert_eq!(meta.create_table_sql(), "CREATE TABLE contact (id INTEGER PRIMARY KEY, kind TEXT NOT NULL)");
+
+    let contact = Contact { id: Key::new(1), kind: EntityKind::Company };
+    assert_eq!(contact.column_value("kind"), Some(Value::Text("company".to_owned())));
+    assert!(contact.graph_relations().is_empty());
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><===========================  DTO  ============================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_into_dto() {
+    use dbent::dto::IntoDto;
+
+    #[derive(Entity, Label, IntoDto)]
+    struct Tag {
+        id: Key<Int>,
+        #[label]
+        name: String,
+    }
+
+    #[derive(Entity, IntoDto)]
+    #[dto(derive(Debug, PartialEq))]
+    struct Comment {
+        id: Key<Int>,
+        post: Parent<Int, Article>,
+        text: String,
+    }
+
+    #[derive(Entity, IntoDto)]
+    #[dto(name = "ArticleBody", derive(Debug, PartialEq))]
+    struct Article {
+        id: Key<Int>,
+        author: Entity<Int, Tag>,
+        tag: EntityLabel<Int, Tag, String>,
+        comments: Many<Comment>,
+        #[dto(skip)]
+        views: u64,
+    }
+
+    let article = Article {
+        id: Key::new(1),
+        author: Entity::from(Tag { id: Key::new(7), name: "Ann".to_owned() }),
+        tag: EntityLabel::Data(Box::new(Tag { id: Key::new(3), name: "rust".to_owned() })),
+        comments: vec![Comment { id: Key::new(2), post: Parent::from(Key::new(1)), text: "Nice".to_owned() }].into(),
+        views: 10,
+    };
+    let body = article.into_dto();
+    assert_eq!(body, ArticleBody {
+        id: Some(1),
+        author: Some(7),
+        tag: Some(3),
+        tag_label: Some("rust".to_owned()),
+        comments: vec![CommentDto { id: Some(2), post: Some(1), text: "Nice".to_owned() }],
+    });
+
+    let article = Article::from(body);
+    assert_eq!((&article.id, article.author.key().unwrap()), (&Key::new(1), &Key::new(7)));
+    assert!(matches!(&article.tag, EntityLabel::KeyLabel(Key(Some(3)), label) if label == "rust"));
+    assert_eq!(article.comments.data().unwrap()[0].post.key().unwrap(), &Key::new(1));
+    assert_eq!(article.views, 0);
+
+    let article = Article::from_dto(ArticleBody { id: None, author: None, tag: Some(3), tag_label: None, comments: Vec::new() });
+    assert!(article.author.is_none() && article.tag.is_none());
+    assert_eq!(article.comments.data().map(Vec::len).ok(), Some(0));
+
+    let comment = CommentDto::from(Comment { id: Key(None), post: Parent::default(), text: String::new() });
+    assert_eq!((comment.id, comment.post), (None, None));
+    let _ = TagDto::from(Tag { id: Key::new(1), name: "a".to_owned() }).name;
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><===========================  CSV  ============================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[derive(Entity, serde::Deserialize, Debug)]
+#[entity(fields)]
+struct Ticket {
+    id: Key<Int>,
+    title: String,
+    note: Option<String>,
+    open: bool,
+    score: f64,
+    hash: Vec<u8>,
+    author: Entity<Int, Ticket>,
+    #[entity(optional)]
+    assignee: Parent<Int, Ticket>,
+    #[serde(skip)]
+    replies: Many<Ticket>,
+}
+
+#[test]
+fn test_csv() -> Result {
+    use dbent::csv::{read_csv, write_csv};
+
+    let ticket = |id, title: &str, note: Option<&str>| Ticket {
+        id: Key(id),
+        title: title.to_owned(),
+        note: note.map(str::to_owned),
+        open: true,
+        score: 1.5,
+        hash: vec![0xca, 0xfe],
+        author: Entity::Key(Key::new(3)),
+        assignee: Parent(Entity::None),
+        replies: Many::None,
+    };
+    let tickets = [ticket(Some(1), "Broken, \"again\"\nreally", None), ticket(Some(2), " ", Some(""))];
+
+    let mut csv = Vec::new();
+    write_csv(&mut csv, &tickets)?;
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(
+        csv,
+        "id,title,note,open,score,hash,author,assignee\r\n\
+         1,\"Broken, \"\"again\"\"\nreally\",,1,1.5,cafe,3,\r\n\
+         2,\" \",\"\",1,1.5,cafe,3,\r\n"
+    );
+
+    let read: Vec<Ticket> = read_csv(csv.as_bytes())?;
+    assert_eq!(read.len(), 2);
+    assert_eq!((&read[0].id, read[0].title.as_str(), read[0].note.as_deref()), (&Key::new(1), tickets[0].title.as_str(), None));
+    assert_eq!((read[1].title.as_str(), read[1].note.as_deref()), (" ", Some("")));
+    assert!(read[0].open && read[0].score == 1.5 && read[0].hash == [0xca, 0xfe]);
+    assert!(matches!(read[0].author, Entity::Key(Key(Some(3)))));
+    assert!(matches!(read[0].assignee, Parent(Entity::None)));
+
+    let read: Vec<Ticket> = read_csv("title,open,score,hash,author,assignee\nNew,false,2,\"\",4,5\n\n".as_bytes())?;
+    assert!(read[0].id.is_none() && !read[0].open && read[0].hash.is_empty());
+    assert_eq!(read[0].assignee.key()?, &Key::new(5));
+
+    assert!(read_csv::<Ticket, _>("title\n\"x".as_bytes()).is_err());
+    let err = read_csv::<Ticket, _>("title,open\nA,yes\n".as_bytes()).unwrap_err().to_string();
+    assert!(err.starts_with("invalid CSV: line 2:"), "{err}");
+    let err = read_csv::<Ticket, _>("title,open\nA\n".as_bytes()).unwrap_err().to_string();
+    assert_eq!(err, "invalid CSV: line 2: expected 2 fields, found 1");
+    Ok(())
+}
+
+#[derive(Entity)]
+#[entity(fields)]
+struct Team {
+    id: Key<Int>,
+    name: String,
+}
+
+#[derive(Entity)]
+#[entity(fields)]
+struct Member {
+    id: Key<Int>,
+    team: Entity<Int, Team>,
+}
+
+#[derive(Entity, Denormalized)]
+#[entity(fields)]
+#[denormalized(
+    source(Team, sql = "UPDATE team_stats SET name = (SELECT name FROM team WHERE id = ?1) WHERE id = ?1"),
+    source(Member)
+)]
+struct TeamStats {
+    id: Key<Int>,
+    name: String,
+    members: i64,
+}
+
+#[test]
+fn test_denormalized() -> Result {
+    use dbent::denormalized::{refresh, refresh_with_sql, Denormalized, SourceChanges};
+
+    assert_eq!(TeamStats::sources(), ["team", "member"]);
+    assert!(TeamStats::refresh_sql("team").is_some());
+    assert!(TeamStats::refresh_sql("member").is_none());
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch(
+        "CREATE TABLE team (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE member (id INTEGER PRIMARY KEY, team INTEGER);
+         CREATE TABLE team_stats (id INTEGER PRIMARY KEY, name TEXT, members INTEGER);
+         INSERT INTO team VALUES (1, 'Core'), (2, 'Docs');
+         INSERT INTO team_stats VALUES (1, 'Core', 0), (2, 'Docs', 0);
+         UPDATE team SET name = 'Platform' WHERE id = 1;
+         INSERT INTO member VALUES (1, 2), (2, 2);",
+    )?;
+
+    let mut changes = SourceChanges::new();
+    changes.record::<Team>(1);
+    changes.record::<Member>(1);
+    changes.record::<Member>(2);
+    assert_eq!(refresh_with_sql::<TeamStats>(&conn, &changes)?, 1);
+
+    let rows = refresh::<TeamStats>(&changes, |source, keys| match source {
+        "member" => {
+            let sql = "UPDATE team_stats SET members = (SELECT COUNT(*) FROM member WHERE team = team_stats.id) \
+                       WHERE id IN (SELECT team FROM member WHERE id = ?1)";
+            keys.iter().try_fold(0, |rows, key| Ok(rows + conn.execute(sql, [key])?))
+        },
+        _ => Ok(0),
+    })?;
+    assert_eq!(rows, 2);
+
+    let stats: Vec<(String, i64)> = conn
+        .prepare("SELECT name, members FROM team_stats ORDER BY id")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    assert_eq!(stats, [("Platform".to_owned(), 0), ("Docs".to_owned(), 2)]);
+    Ok(())
+}
@@ -0,0 +1,10 @@
+use dbent::prelude::*;
+
+#[derive(DbEnum)]
+#[db_enum(integer)]
+enum Priority {
+    Low = 1,
+    High,
+}
+
+fn main() {}
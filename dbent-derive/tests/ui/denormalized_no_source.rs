@@ -0,0 +1,8 @@
+use dbent::prelude::*;
+
+#[derive(Denormalized)]
+struct Totals {
+    total: i64,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+#[entity(fields)]
+struct User {
+    #[entity(cfg(feature = "full"))]
+    id: Key<Int>,
+    name: String,
+}
+
+fn main() {}
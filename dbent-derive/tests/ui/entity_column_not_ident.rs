@@ -0,0 +1,11 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+#[entity(fields)]
+struct User {
+    id: Key<Int>,
+    #[entity(column = "name\" TEXT); DROP TABLE user; --")]
+    name: String,
+}
+
+fn main() {}
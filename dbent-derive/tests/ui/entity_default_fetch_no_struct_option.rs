@@ -0,0 +1,10 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+struct Post {
+    id: Key<Int>,
+    #[entity(default_fetch = "none")]
+    comments: Many<String>,
+}
+
+fn main() {}
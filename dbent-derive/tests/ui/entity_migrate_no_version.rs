@@ -0,0 +1,9 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+#[entity(migrate = "upgrade")]
+struct Test {
+    id: Key<Int>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+struct Test {
+    id: Key<Int>,
+    #[position] position: i64,
+    #[position] other: i64,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+struct Test {
+    id: Key<Int>,
+    #[version] version: Version,
+    #[version] other: Version,
+}
+
+fn main() {}
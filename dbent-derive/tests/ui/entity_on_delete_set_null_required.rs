@@ -0,0 +1,17 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+#[entity(fields)]
+pub struct Author {
+    id: Key<Int>,
+}
+
+#[derive(Entity)]
+#[entity(fields)]
+pub struct Post {
+    id: Key<Int>,
+    #[entity(on_delete = "set_null")]
+    author: Entity<Int, Author>,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+#[entity(fields)]
+pub struct Post {
+    id: Key<Int>,
+    #[entity(optional)]
+    title: String,
+}
+
+fn main() {}
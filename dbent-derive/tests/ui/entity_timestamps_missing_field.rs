@@ -0,0 +1,10 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+#[entity(timestamps)]
+struct Test {
+    id: Key<Int>,
+    created_at: Option<std::time::SystemTime>,
+}
+
+fn main() {}
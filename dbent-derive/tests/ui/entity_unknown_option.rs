@@ -0,0 +1,9 @@
+use dbent::prelude::*;
+
+#[derive(Entity)]
+#[entity(timestampz)]
+struct Test {
+    id: Key<Int>,
+}
+
+fn main() {}
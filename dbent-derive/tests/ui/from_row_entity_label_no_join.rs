@@ -0,0 +1,16 @@
+use dbent::prelude::*;
+
+#[derive(Entity, Label)]
+struct User {
+    id: Key<Int>,
+    #[label]
+    name: String,
+}
+
+#[derive(FromRow)]
+struct Post {
+    id: Key<Int>,
+    author: EntityLabelInt<User>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use dbent::prelude::*;
+
+#[derive(Projection)]
+#[entity(fields)]
+struct Total {
+    name: String,
+    total: i64,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use dbent::prelude::*;
+
+#[serde_relations]
+enum Test {
+    A,
+}
+
+fn main() {}
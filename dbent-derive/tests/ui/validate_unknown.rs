@@ -0,0 +1,9 @@
+use dbent_derive::Validate;
+
+#[derive(Validate)]
+struct Test {
+    #[validate(email)]
+    data: String,
+}
+
+fn main() {}
@@ -0,0 +1,348 @@
+//! Audit trail of entity writes
+//!
+//! [`AuditedRepository`] wraps another [`Repository`] and records an
+//! [`AuditEntry`] for every insert, update, upsert and delete: the entity
+//! table, key, action, actor, time and the [`Changeset`] of the columns it
+//! changed. Entries go to an [`AuditSink`]: the in-memory [`AuditLog`], or
+//! with the `rusqlite` feature, [`SqliteAuditSink`] writing them to an
+//! `audit_log` table.
+//!
+//! Updates and deletes read the stored entity first to know the old values.
+//! Entries are recorded after the write succeeds; when the sink fails the
+//! error is returned, so writing both in one transaction keeps them in sync.
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::prelude::*;
+//! use dbent::audit::{AuditAction, AuditLog, AuditedRepository};
+//! use dbent::repository::MemoryRepository;
+//!
+//! #[derive(Entity, Clone)]
+//! #[entity(fields)]
+//! pub struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! # pub fn main() {
+//! let log = AuditLog::new();
+//! let users = AuditedRepository::new(MemoryRepository::new(), &log, "admin");
+//!
+//! let mut user = User { id: Key(None), name: "Ann".to_owned() };
+//! users.insert(&mut user).unwrap();
+//! user.name = "Anne".to_owned();
+//! users.update(&mut user).unwrap();
+//!
+//! let entries = log.entries();
+//! assert_eq!(entries[1].action, AuditAction::Update);
+//! assert_eq!(entries[1].key.as_deref(), Some("1"));
+//! assert_eq!(entries[1].actor, "admin");
+//! assert_eq!(entries[1].changes.columns().collect::<Vec<_>>(), ["name"]);
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+
+use core::fmt;
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::changeset::Changeset;
+use crate::repository::Repository;
+use crate::schema::Schema;
+use crate::sql::Columns;
+use crate::{Error, Keyed, Result};
+
+/// The kind of write an entry records
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AuditAction {
+    /// A new entity was inserted
+    Insert,
+    /// A stored entity was changed
+    Update,
+    /// A stored entity was deleted
+    Delete,
+}
+
+impl AuditAction {
+    /// Returns the action name in lowercase, as stored by the sinks
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Insert => "insert",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A recorded write of an entity
+#[derive(Clone, PartialEq, Debug)]
+pub struct AuditEntry {
+    /// The table of the entity
+    pub entity: Cow<'static, str>,
+    /// The key of the entity as text, if set
+    pub key: Option<String>,
+    /// The kind of write
+    pub action: AuditAction,
+    /// The changed columns, from NULL for inserts and to NULL for deletes
+    pub changes: Changeset,
+    /// Who made the change
+    pub actor: String,
+    /// When the change was made
+    pub at: SystemTime,
+}
+
+/// Destination of audit entries
+pub trait AuditSink {
+    /// Stores the entry
+    fn record(&self, entry: AuditEntry) -> Result<()>;
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for &S {
+    fn record(&self, entry: AuditEntry) -> Result<()> {
+        (**self).record(entry)
+    }
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for Arc<S> {
+    fn record(&self, entry: AuditEntry) -> Result<()> {
+        (**self).record(entry)
+    }
+}
+
+/// An [`AuditSink`] keeping the entries in memory
+#[derive(Default, Debug)]
+pub struct AuditLog {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl AuditLog {
+    /// Creates an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the recorded entries, oldest first
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.lock().clone()
+    }
+
+    /// Removes and returns the recorded entries, oldest first
+    pub fn take(&self) -> Vec<AuditEntry> {
+        core::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<AuditEntry>> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl AuditSink for AuditLog {
+    fn record(&self, entry: AuditEntry) -> Result<()> {
+        self.lock().push(entry);
+        Ok(())
+    }
+}
+
+/// A [`Repository`] decorator recording its writes to an [`AuditSink`]
+pub struct AuditedRepository<R, S> {
+    inner: R,
+    sink: S,
+    actor: String,
+}
+
+impl<R, S: AuditSink> AuditedRepository<R, S> {
+    /// Wraps `inner`, recording the writes of `actor` to `sink`
+    pub fn new(inner: R, sink: S, actor: impl Into<String>) -> Self {
+        Self { inner, sink, actor: actor.into() }
+    }
+
+    /// Returns the wrapped repository
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the sink the entries are recorded to
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Returns the actor recorded in the entries
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    /// Sets the actor recorded in the next entries
+    pub fn set_actor(&mut self, actor: impl Into<String>) {
+        self.actor = actor.into();
+    }
+
+    /// Returns the wrapped repository, dropping the sink
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn record<T>(&self, action: AuditAction, key: Option<&T::KeyType>, changes: Changeset) -> Result<()>
+    where
+        T: Keyed + Schema,
+        T::KeyType: fmt::Display,
+    {
+        self.sink.record(AuditEntry {
+            entity: T::table(),
+            key: key.map(ToString::to_string),
+            action,
+            changes,
+            actor: self.actor.clone(),
+            at: SystemTime::now(),
+        })
+    }
+}
+
+impl<R, S, T> Repository<T> for AuditedRepository<R, S>
+where
+    R: Repository<T>,
+    S: AuditSink,
+    T: Keyed + Schema + Columns,
+    T::KeyType: fmt::Display,
+{
+    fn insert(&self, entity: &mut T) -> Result<()> {
+        self.inner.insert(entity)?;
+        self.record::<T>(AuditAction::Insert, entity.key()?.as_ref(), Changeset::inserted(entity))
+    }
+
+    fn get(&self, key: &T::KeyType) -> Result<T> {
+        self.inner.get(key)
+    }
+
+    fn get_many(&self, keys: &[&T::KeyType]) -> Result<Vec<T>> {
+        self.inner.get_many(keys)
+    }
+
+    fn get_with(&self, key: &T::KeyType, columns: &[&str]) -> Result<T> {
+        self.inner.get_with(key, columns)
+    }
+
+    fn list(&self) -> Result<Vec<T>> {
+        self.inner.list()
+    }
+
+    fn list_with(&self, columns: &[&str]) -> Result<Vec<T>> {
+        self.inner.list_with(columns)
+    }
+
+    fn update(&self, entity: &mut T) -> Result<()> {
+        let key = entity.key()?.as_ref().ok_or(Error::KeyMissing)?;
+        let old = self.inner.get(key)?;
+        self.inner.update(entity)?;
+        self.record::<T>(AuditAction::Update, entity.key()?.as_ref(), Changeset::between(&old, entity))
+    }
+
+    fn upsert(&self, entity: &mut T) -> Result<()> {
+        let old = match entity.key()?.as_ref() {
+            Some(key) => match self.inner.get(key) {
+                Ok(old) => Some(old),
+                Err(Error::NotFound) => None,
+                Err(err) => return Err(err),
+            },
+            None => None,
+        };
+        self.inner.upsert(entity)?;
+
+        let (action, changes) = match old {
+            Some(old) => (AuditAction::Update, Changeset::between(&old, entity)),
+            None => (AuditAction::Insert, Changeset::inserted(entity)),
+        };
+        self.record::<T>(action, entity.key()?.as_ref(), changes)
+    }
+
+    fn delete(&self, key: &T::KeyType) -> Result<()> {
+        let old = self.inner.get(key)?;
+        self.inner.delete(key)?;
+        self.record::<T>(AuditAction::Delete, Some(key), Changeset::deleted(&old))
+    }
+}
+
+/// An [`AuditSink`] writing the entries to a table, with the `rusqlite` feature
+///
+/// Each changed column is a row holding the entity, key, action, actor,
+/// time in milliseconds since the Unix epoch, and the column with its old
+/// and new values. Entries without changes are a single row with a NULL column.
+#[cfg(feature = "rusqlite")]
+pub struct SqliteAuditSink<'c> {
+    conn: &'c rusqlite::Connection,
+    table: Cow<'static, str>,
+}
+
+#[cfg(feature = "rusqlite")]
+impl<'c> SqliteAuditSink<'c> {
+    /// Creates a sink writing to the `audit_log` table
+    pub fn new(conn: &'c rusqlite::Connection) -> Self {
+        Self::with_table(conn, "audit_log")
+    }
+
+    /// Creates a sink writing to `table`
+    pub fn with_table(conn: &'c rusqlite::Connection, table: impl Into<Cow<'static, str>>) -> Self {
+        Self { conn, table: table.into() }
+    }
+
+    /// Returns the name of the table
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Creates the table if it doesn't exist
+    pub fn create_table(&self) -> Result<()> {
+        self.conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (\
+                    id INTEGER PRIMARY KEY, \
+                    entity TEXT NOT NULL, \
+                    key TEXT, \
+                    action TEXT NOT NULL, \
+                    actor TEXT NOT NULL, \
+                    at INTEGER NOT NULL, \
+                    field TEXT, \
+                    old, \
+                    new\
+                )",
+                crate::sql::quote_ident(&self.table),
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl AuditSink for SqliteAuditSink<'_> {
+    fn record(&self, entry: AuditEntry) -> Result<()> {
+        use crate::sql::Value;
+
+        let sql = format!(
+            "INSERT INTO {} (entity, key, action, actor, at, field, old, new) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            crate::sql::quote_ident(&self.table),
+        );
+        let mut statement = self.conn.prepare_cached(&sql)?;
+        let at = entry.at.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as i64);
+        let mut insert = |field: Option<&str>, old: &Value, new: &Value| {
+            statement.execute(rusqlite::params![entry.entity.as_ref(), entry.key, entry.action.as_str(), entry.actor, at, field, old, new])
+        };
+
+        if entry.changes.is_empty() {
+            insert(None, &Value::Null, &Value::Null)?;
+        }
+        for change in &entry.changes {
+            insert(Some(change.column), &change.old, &change.new)?;
+        }
+        Ok(())
+    }
+}
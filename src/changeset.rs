@@ -0,0 +1,183 @@
+//! Changed column values, ready to be written
+//!
+//! A [`Changeset`] lists the columns of an entity whose values changed, with
+//! the old and new values as bindable [`Value`]s. Snapshots and graph diffs
+//! produce them, and they render to the UPDATE statement writing them:
+//!
+//! ```
+//! use dbent::changeset::Changeset;
+//!
+//! let mut changeset = Changeset::new();
+//! changeset.push("name", "Bob", "Rob");
+//! changeset.push("age", 30, 31);
+//!
+//! let sql = changeset.to_update_sql("users", "id").unwrap();
+//! assert_eq!(sql, "UPDATE users SET name = ?, age = ? WHERE id = ?");
+//! assert_eq!(changeset.params(7).len(), 3);
+//! ```
+
+use core::ops::Deref;
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::types::ToSql;
+
+use crate::sql::{quote_ident, Columns, Value};
+
+/// A column whose value changed
+#[derive(Clone, PartialEq, Debug)]
+pub struct Change {
+    /// The column name
+    pub column: &'static str,
+    /// The previous value
+    pub old: Value,
+    /// The current value
+    pub new: Value,
+}
+
+/// The changed columns of an entity, in column order
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Changeset {
+    changes: Vec<Change>,
+}
+
+impl Changeset {
+    /// Creates an empty changeset
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the columns of `old` whose values differ in `new`
+    pub fn between<T: Columns + ?Sized>(old: &T, new: &T) -> Self {
+        old.columns()
+            .iter()
+            .filter_map(|&column| {
+                let old = old.column_value(column).unwrap_or_default();
+                let new = new.column_value(column).unwrap_or_default();
+                (old != new).then_some(Change { column, old, new })
+            })
+            .collect()
+    }
+
+    /// Returns the non-NULL columns of a new entity, changed from NULL
+    pub fn inserted<T: Columns + ?Sized>(entity: &T) -> Self {
+        Self::against_null(entity, |value| (Value::Null, value))
+    }
+
+    /// Returns the non-NULL columns of a deleted entity, changed to NULL
+    pub fn deleted<T: Columns + ?Sized>(entity: &T) -> Self {
+        Self::against_null(entity, |value| (value, Value::Null))
+    }
+
+    fn against_null<T: Columns + ?Sized>(entity: &T, change: impl Fn(Value) -> (Value, Value)) -> Self {
+        entity
+            .columns()
+            .iter()
+            .filter_map(|&column| {
+                let (old, new) = change(entity.column_value(column).filter(|value| *value != Value::Null)?);
+                Some(Change { column, old, new })
+            })
+            .collect()
+    }
+
+    /// Records a change of `column`, keeping the first old value if it was
+    /// already changed
+    pub fn push(&mut self, column: &'static str, old: impl Into<Value>, new: impl Into<Value>) {
+        let new = new.into();
+        match self.changes.iter_mut().find(|change| change.column == column) {
+            Some(change) => change.new = new,
+            None => self.changes.push(Change { column, old: old.into(), new }),
+        }
+    }
+
+    /// Returns the change of `column`, if it changed
+    pub fn get(&self, column: &str) -> Option<&Change> {
+        self.changes.iter().find(|change| change.column == column)
+    }
+
+    /// Returns the names of the changed columns
+    pub fn columns(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.changes.iter().map(|change| change.column)
+    }
+
+    /// Returns `UPDATE table SET col = ?, ... WHERE key_column = ?`, or None
+    /// if nothing changed
+    ///
+    /// The placeholders are bound by [`Changeset::params`].
+    pub fn to_update_sql(&self, table: &str, key_column: &str) -> Option<String> {
+        if self.changes.is_empty() {
+            return None;
+        }
+
+        let assignments = self.columns().map(|column| format!("{} = ?", quote_ident(column))).collect::<Vec<_>>();
+        Some(format!("UPDATE {} SET {} WHERE {} = ?", quote_ident(table), assignments.join(", "), quote_ident(key_column)))
+    }
+
+    /// Returns the new values followed by `key`, the parameters of
+    /// [`Changeset::to_update_sql`]
+    pub fn params(&self, key: impl Into<Value>) -> Vec<Value> {
+        let mut params = self.changes.iter().map(|change| change.new.clone()).collect::<Vec<_>>();
+        params.push(key.into());
+        params
+    }
+
+    /// Returns the changed columns with their new values, as taken by
+    /// [`update_versioned`](crate::version::update_versioned)
+    #[cfg(feature = "rusqlite")]
+    pub fn assignments(&self) -> Vec<(&str, &dyn ToSql)> {
+        self.changes.iter().map(|change| (change.column, &change.new as &dyn ToSql)).collect()
+    }
+
+    /// Writes the changes to the row with `key`, or fails with
+    /// `Error::NotFound` if there is none
+    ///
+    /// Does nothing if nothing changed.
+    #[cfg(feature = "rusqlite")]
+    pub fn update(&self, conn: &rusqlite::Connection, table: &str, key_column: &str, key: impl Into<Value>) -> crate::Result<()> {
+        let Some(sql) = self.to_update_sql(table, key_column) else {
+            return Ok(());
+        };
+
+        match conn.execute(&sql, rusqlite::params_from_iter(self.params(key)))? {
+            0 => Err(crate::Error::NotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Deref for Changeset {
+    type Target = [Change];
+
+    fn deref(&self) -> &Self::Target {
+        &self.changes
+    }
+}
+
+impl From<Vec<Change>> for Changeset {
+    fn from(changes: Vec<Change>) -> Self {
+        Self { changes }
+    }
+}
+
+impl FromIterator<Change> for Changeset {
+    fn from_iter<I: IntoIterator<Item = Change>>(iter: I) -> Self {
+        Self { changes: iter.into_iter().collect() }
+    }
+}
+
+impl IntoIterator for Changeset {
+    type Item = Change;
+    type IntoIter = std::vec::IntoIter<Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Changeset {
+    type Item = &'a Change;
+    type IntoIter = core::slice::Iter<'a, Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.iter()
+    }
+}
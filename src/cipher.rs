@@ -0,0 +1,144 @@
+//! Column-level encryption
+//!
+//! [`set_cipher`] installs a process-wide [`FieldCipher`] that encrypts the
+//! values of the fields marked with `#[entity(encrypt)]` when
+//! [`Columns::try_column_value`](crate::sql::Columns::try_column_value) reads them, so
+//! every SQL helper writes them encrypted, and decrypts them when
+//! [`FromRow`](crate::sqlite::FromRow) reads them back. Encrypted columns are
+//! declared without a type, so the cipher can store them as text or blobs.
+//!
+//! NULLs are neither encrypted nor decrypted. Without a cipher installed,
+//! encrypted columns fail to be written and read with `Error::CipherMissing`,
+//! so they are never stored in clear, and the errors of the cipher are
+//! returned by the SQL helpers as well.
+//!
+//! ```
+//! # #[cfg(all(feature = "derive", feature = "rusqlite"))] {
+//! # mod example {
+//! use dbent::cipher::{self, FieldCipher};
+//! use dbent::prelude::*;
+//! use dbent::schema::Schema;
+//! use dbent::sql::Value;
+//! use dbent::sqlite::FromRow;
+//!
+//! /// Not a real cipher: it only reverses the bytes of text values
+//! struct Reverse;
+//!
+//! impl FieldCipher for Reverse {
+//!     fn encrypt(&self, _column: &str, value: Value) -> dbent::Result<Value> {
+//!         match value {
+//!             Value::Text(text) => Ok(Value::Blob(text.into_bytes().into_iter().rev().collect())),
+//!             value => Ok(value),
+//!         }
+//!     }
+//!
+//!     fn decrypt(&self, _column: &str, value: Value) -> dbent::Result<Value> {
+//!         match value {
+//!             Value::Blob(bytes) => String::from_utf8(bytes.into_iter().rev().collect())
+//!                 .map(Value::Text)
+//!                 .map_err(|err| dbent::Error::Cipher(err.to_string())),
+//!             value => Ok(value),
+//!         }
+//!     }
+//! }
+//!
+//! #[derive(Entity, FromRow)]
+//! #[entity(fields)]
+//! pub struct Patient {
+//!     id: Key<Int>,
+//!     #[entity(encrypt)]
+//!     ssn: String,
+//! }
+//!
+//! # pub fn main() -> dbent::Result<()> {
+//! cipher::set_cipher(Reverse);
+//!
+//! let conn = rusqlite::Connection::open_in_memory()?;
+//! conn.execute(&Patient::entity_meta().create_table_sql(), [])?;
+//! dbent::sqlite::insert(&conn, &mut Patient { id: Key(None), ssn: "123".to_owned() })?;
+//!
+//! let stored: Vec<u8> = conn.query_row("SELECT ssn FROM patient", [], |row| row.get(0))?;
+//! assert_eq!(stored, b"321");
+//! let patient = conn.query_row("SELECT id, ssn FROM patient", [], Patient::from_row)?;
+//! assert_eq!(patient.ssn, "123");
+//!
+//! cipher::clear_cipher();
+//! # Ok(())
+//! # }
+//! # }
+//! # example::main().unwrap();
+//! # }
+//! ```
+
+use std::sync::{Arc, RwLock};
+
+use crate::sql::Value;
+use crate::{Error, Result};
+
+/// Encrypts and decrypts the values of the columns marked with
+/// `#[entity(encrypt)]` while it is installed with [`set_cipher`]
+///
+/// The column name is given so keys can differ per column.
+pub trait FieldCipher: Send + Sync {
+    /// Returns the value to store for the plain `value` of `column`
+    fn encrypt(&self, column: &str, value: Value) -> Result<Value>;
+
+    /// Returns the plain value of `column` from the stored `value`
+    fn decrypt(&self, column: &str, value: Value) -> Result<Value>;
+}
+
+impl<C: FieldCipher + ?Sized> FieldCipher for Arc<C> {
+    fn encrypt(&self, column: &str, value: Value) -> Result<Value> {
+        (**self).encrypt(column, value)
+    }
+
+    fn decrypt(&self, column: &str, value: Value) -> Result<Value> {
+        (**self).decrypt(column, value)
+    }
+}
+
+static CIPHER: RwLock<Option<Box<dyn FieldCipher>>> = RwLock::new(None);
+
+/// Installs the cipher, replacing the previous one
+pub fn set_cipher(cipher: impl FieldCipher + 'static) {
+    *CIPHER.write().unwrap_or_else(|err| err.into_inner()) = Some(Box::new(cipher));
+}
+
+/// Removes the cipher, if any
+pub fn clear_cipher() {
+    *CIPHER.write().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+/// Runs `f` with the installed cipher, passing NULLs through
+fn with_cipher(column: &str, value: Value, f: impl FnOnce(&dyn FieldCipher, Value) -> Result<Value>) -> Result<Value> {
+    if value == Value::Null {
+        return Ok(value);
+    }
+    match &*CIPHER.read().unwrap_or_else(|err| err.into_inner()) {
+        Some(cipher) => f(&**cipher, value),
+        None => Err(Error::CipherMissing(column.to_owned())),
+    }
+}
+
+/// Encrypts the value of `column` with the installed cipher
+pub fn encrypt(column: &str, value: Value) -> Result<Value> {
+    with_cipher(column, value, |cipher, value| cipher.encrypt(column, value))
+}
+
+/// Decrypts the stored value of `column` with the installed cipher
+pub fn decrypt(column: &str, value: Value) -> Result<Value> {
+    with_cipher(column, value, |cipher, value| cipher.decrypt(column, value))
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the code generated by `#[derive(Entity)]`
+
+    use crate::sql::Value;
+    use crate::Result;
+
+    /// Returns the encrypted value of an `#[entity(encrypt)]` column
+    pub fn encrypted(column: &str, value: Option<Value>) -> Result<Option<Value>> {
+        value.map(|value| super::encrypt(column, value)).transpose()
+    }
+}
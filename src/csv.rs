@@ -0,0 +1,457 @@
+//! CSV import and export of entities
+//!
+//! [`write_csv`] writes a header with the column names of the [`Schema`] and
+//! a record per entity with the values of its [`Columns`], and [`read_csv`]
+//! deserializes the records back into entities, for bulk import and export:
+//!
+//! - NULLs are written as empty fields and empty strings as `""`, so they
+//!   read back the same
+//! - blobs are written in lowercase hex
+//! - relation columns hold the key, read back as an `Entity::Key` or
+//!   `Parent`; `EntityLabel` fields can't be read, as the label is missing
+//! - `Many` fields are read as `NotFetched`
+//! - columns missing from the header are read as missing fields, so keys are
+//!   optional: entities without them are read with an empty Key
+//!
+//! The fields are matched to the columns by name, so fields renamed with
+//! `#[entity(column = "...")]` need the same `#[serde(rename = "...")]`.
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::prelude::*;
+//! use serde::Deserialize;
+//!
+//! #[derive(Entity, Deserialize)]
+//! #[entity(fields)]
+//! pub struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//!     nickname: Option<String>,
+//! }
+//!
+//! # pub fn main() -> dbent::Result<()> {
+//! let users: Many<User> = vec![
+//!     User { id: Key::new(1), name: "Ann, the first".to_owned(), nickname: None },
+//!     User { id: Key::new(2), name: "Bob".to_owned(), nickname: Some("".to_owned()) },
+//! ].into();
+//!
+//! let mut csv = Vec::new();
+//! dbent::csv::write_csv(&mut csv, users.data()?)?;
+//! assert_eq!(csv, b"id,name,nickname\r\n1,\"Ann, the first\",\r\n2,Bob,\"\"\r\n");
+//!
+//! let users: Vec<User> = dbent::csv::read_csv("name\nCid\n".as_bytes())?;
+//! assert!(users[0].id.is_none());
+//! assert_eq!(users[0].name, "Cid");
+//! # Ok(())
+//! # }
+//! # }
+//! # example::main().unwrap();
+//! # }
+//! ```
+
+use core::fmt;
+use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use serde::de::{
+    self,
+    DeserializeOwned,
+    DeserializeSeed,
+    EnumAccess,
+    IntoDeserializer,
+    Unexpected,
+    VariantAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::export::TabularExport;
+use crate::schema::{EntityMeta, Schema, SqlType};
+use crate::sql::{Columns, Value};
+use crate::{Error, Key, Result};
+
+/// Writes the header and a record for each entity
+///
+/// See [`CsvExport`] to write several tables, or other types of entities.
+pub fn write_csv<'a, T, W>(writer: W, entities: impl IntoIterator<Item = &'a T>) -> Result<()>
+where
+    T: Schema + Columns + 'a,
+    W: Write,
+{
+    CsvExport::new(writer).export(entities)
+}
+
+/// A [`TabularExport`] writing CSV
+///
+/// Each table is written as a header followed by its records, so writing
+/// several tables to the same writer concatenates them.
+#[derive(Debug)]
+pub struct CsvExport<W> {
+    writer: W,
+}
+
+impl<W: Write> CsvExport<W> {
+    /// Writes to `writer`
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Returns the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> TabularExport for CsvExport<W> {
+    type Error = Error;
+
+    fn begin(&mut self, meta: &EntityMeta) -> Result<()> {
+        let header = meta.columns.iter().map(|column| quoted(&column.name)).collect::<Vec<_>>();
+        write!(self.writer, "{}\r\n", header.join(","))?;
+        Ok(())
+    }
+
+    fn row(&mut self, cells: &[Value]) -> Result<()> {
+        let record = cells.iter().map(field).collect::<Vec<_>>();
+        write!(self.writer, "{}\r\n", record.join(","))?;
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Reads the entities of the records, matching the fields to the columns
+/// named in the header
+///
+/// Fails with `Error::Csv` for malformed CSV or records that don't
+/// deserialize, giving the line of the record.
+pub fn read_csv<T, R>(mut reader: R) -> Result<Vec<T>>
+where
+    T: Schema + DeserializeOwned,
+    R: Read,
+{
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut records = Records { chars: text.chars().peekable(), line: 1 };
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+
+    let meta = T::entity_meta();
+    let header = header?
+        .1
+        .into_iter()
+        .map(|name| {
+            let name = name.unwrap_or_default();
+            let sql_type = meta.column(&name).map_or(SqlType::Text, |column| column.sql_type);
+            (name, sql_type)
+        })
+        .collect::<Vec<_>>();
+    let many = meta.many.iter().map(|many| many.field.to_string()).collect::<Vec<_>>();
+
+    let mut entities = Vec::new();
+    for record in records {
+        let (line, fields) = record?;
+        if fields.len() != header.len() {
+            return Err(Error::Csv(format!("line {line}: expected {} fields, found {}", header.len(), fields.len())));
+        }
+
+        let columns = header.iter().zip(fields).map(|((name, sql_type), text)| (name.clone(), Cell::Column(text, *sql_type)));
+        let many = many.iter().map(|name| (name.clone(), Cell::Many));
+        let row = de::value::MapDeserializer::new(columns.chain(many));
+        entities.push(T::deserialize(row).map_err(|err: DeError| Error::Csv(format!("line {line}: {err}")))?);
+    }
+    Ok(entities)
+}
+
+/// Returns the text of a header field, quoted if needed
+fn quoted(text: &str) -> Cow<'_, str> {
+    if text.is_empty() {
+        Cow::Borrowed("\"\"")
+    } else if text.contains([',', '"', '\r', '\n']) || text.trim() != text {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Returns the text of a field holding `value`
+fn field(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::Null => Cow::Borrowed(""),
+        Value::Integer(value) => Cow::Owned(value.to_string()),
+        Value::Unsigned(value) => Cow::Owned(value.to_string()),
+        Value::Real(value) => Cow::Owned(value.to_string()),
+        Value::Text(text) => quoted(text),
+        Value::Blob(bytes) if bytes.is_empty() => Cow::Borrowed("\"\""),
+        Value::Blob(bytes) => Cow::Owned(Key::new(bytes).hex().to_string()),
+    }
+}
+
+/// The records of CSV text, with the line each starts at
+///
+/// Fields are None when empty and unquoted, for NULLs.
+struct Records<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Records<'_> {
+    /// Reads a field up to its separator
+    fn field(&mut self) -> Result<Option<String>> {
+        if self.chars.next_if_eq(&'"').is_none() {
+            let mut text = String::new();
+            while let Some(c) = self.chars.next_if(|&c| c != ',' && c != '\r' && c != '\n') {
+                text.push(c);
+            }
+            return Ok((!text.is_empty()).then_some(text));
+        }
+
+        let start = self.line;
+        let mut text = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') if self.chars.next_if_eq(&'"').is_some() => text.push('"'),
+                Some('"') => break,
+                Some(c) => {
+                    self.line += usize::from(c == '\n');
+                    text.push(c);
+                },
+                None => return Err(Error::Csv(format!("line {start}: unterminated quoted field"))),
+            }
+        }
+        match self.chars.peek() {
+            None | Some(',' | '\r' | '\n') => Ok(Some(text)),
+            Some(c) => Err(Error::Csv(format!("line {}: unexpected {c:?} after a quoted field", self.line))),
+        }
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = Result<(usize, Vec<Option<String>>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Blank lines hold no records
+        while let Some(c) = self.chars.next_if(|&c| c == '\r' || c == '\n') {
+            self.line += usize::from(c == '\n');
+        }
+        self.chars.peek()?;
+
+        let start = self.line;
+        let mut fields = Vec::new();
+        loop {
+            match self.field() {
+                Ok(field) => fields.push(field),
+                Err(err) => return Some(Err(err)),
+            }
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('\r') => {
+                    self.chars.next_if_eq(&'\n');
+                    self.line += 1;
+                },
+                Some('\n') => self.line += 1,
+                _ => (),
+            }
+            return Some(Ok((start, fields)));
+        }
+    }
+}
+
+/// The error of deserializing a record, wrapped in `Error::Csv`
+#[derive(Debug)]
+struct DeError(String);
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        DeError(msg.to_string())
+    }
+}
+
+/// A field of a record, read as the type of its column, or a `Many` field
+enum Cell {
+    Column(Option<String>, SqlType),
+    Many,
+}
+
+impl Cell {
+    /// Returns the text of a non-NULL column
+    fn text(&self) -> Option<&str> {
+        match self {
+            Cell::Column(text, _) => text.as_deref(),
+            Cell::Many => None,
+        }
+    }
+
+    /// Returns the bytes of a non-NULL column in hex
+    fn bytes(&self) -> Option<Vec<u8>> {
+        Key::<Vec<u8>>::from_hex(self.text()?).ok()?.0
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> core::result::Result<V::Value, DeError> {
+                match self.text().map(str::parse) {
+                    Some(Ok(value)) => visitor.$visit(value),
+                    Some(Err(_)) => Err(de::Error::invalid_value(Unexpected::Str(self.text().unwrap_or_default()), &visitor)),
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Cell {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> core::result::Result<V::Value, DeError> {
+        match self {
+            Cell::Column(None, _) | Cell::Many => visitor.visit_unit(),
+            Cell::Column(Some(text), SqlType::Integer) => match text.parse() {
+                Ok(value) => visitor.visit_i64(value),
+                Err(_) => visitor.visit_string(text),
+            },
+            Cell::Column(Some(text), SqlType::Real) => match text.parse() {
+                Ok(value) => visitor.visit_f64(value),
+                Err(_) => visitor.visit_string(text),
+            },
+            cell @ Cell::Column(Some(_), SqlType::Blob) => match cell.bytes() {
+                Some(bytes) => visitor.visit_byte_buf(bytes),
+                None => visitor.visit_string(cell.text().unwrap_or_default().to_owned()),
+            },
+            Cell::Column(Some(text), _) => visitor.visit_string(text),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> core::result::Result<V::Value, DeError> {
+        match self.text() {
+            Some("1" | "true") => visitor.visit_bool(true),
+            Some("0" | "false") => visitor.visit_bool(false),
+            Some(text) => Err(de::Error::invalid_value(Unexpected::Str(text), &visitor)),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> core::result::Result<V::Value, DeError> {
+        match self {
+            Cell::Column(Some(text), _) => visitor.visit_string(text),
+            cell => cell.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> core::result::Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> core::result::Result<V::Value, DeError> {
+        match self.bytes() {
+            Some(bytes) => visitor.visit_byte_buf(bytes),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> core::result::Result<V::Value, DeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> core::result::Result<V::Value, DeError> {
+        match self.bytes() {
+            Some(bytes) => visitor.visit_seq(de::value::SeqDeserializer::new(bytes.into_iter())),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> core::result::Result<V::Value, DeError> {
+        match self {
+            Cell::Column(None, _) | Cell::Many => visitor.visit_none(),
+            cell => visitor.visit_some(cell),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> core::result::Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> core::result::Result<V::Value, DeError> {
+        match (name, self) {
+            ("Many", Cell::Many) => visitor.visit_enum("NotFetched".into_deserializer()),
+            ("Entity", Cell::Column(None, _)) if variants.contains(&"None") => visitor.visit_enum("None".into_deserializer()),
+            ("Entity", cell) if variants.contains(&"Key") => visitor.visit_enum(cell),
+            (_, Cell::Column(Some(text), _)) => visitor.visit_enum(text.into_deserializer()),
+            (_, cell) => cell.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        char unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for Cell {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// The key of a relation column, read as the Key variant of an Entity
+impl<'de> EnumAccess<'de> for Cell {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> core::result::Result<(S::Value, Self), DeError> {
+        let variant = seed.deserialize(de::value::StrDeserializer::<DeError>::new("Key"))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Cell {
+    type Error = DeError;
+
+    fn unit_variant(self) -> core::result::Result<(), DeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> core::result::Result<S::Value, DeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> core::result::Result<V::Value, DeError> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> core::result::Result<V::Value, DeError> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
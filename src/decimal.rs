@@ -0,0 +1,90 @@
+//! Exact decimal keys and fields
+//!
+//! [`Decimal`] wraps `rust_decimal::Decimal` so it can implement the rusqlite
+//! conversions, storing values as TEXT to keep them exact. It dereferences to
+//! the wrapped value for arithmetic and formatting options.
+//!
+//! ```
+//! use dbent::Key;
+//! use dbent::decimal::Decimal;
+//!
+//! let price: Key<Decimal> = Key::new("19.90".parse().unwrap());
+//! assert_eq!(price.to_string(), "19.90");
+//! ```
+
+use core::{fmt, ops::Deref, str::FromStr};
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// An exact decimal number
+///
+/// Serializes as a string and deserializes from strings or numbers.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Decimal(pub rust_decimal::Decimal);
+
+impl Decimal {
+    /// Returns the wrapped `rust_decimal::Decimal`
+    pub fn into_inner(self) -> rust_decimal::Decimal {
+        self.0
+    }
+}
+
+impl Deref for Decimal {
+    type Target = rust_decimal::Decimal;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<rust_decimal::Decimal> for Decimal {
+    fn from(value: rust_decimal::Decimal) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Decimal> for rust_decimal::Decimal {
+    fn from(value: Decimal) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = rust_decimal::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        rust_decimal::Decimal::from_str(value).map(Self)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl FromSql for Decimal {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Text(_) => value.as_str()?.parse().map_err(|err| FromSqlError::Other(Box::new(err))),
+            ValueRef::Integer(value) => Ok(Self(value.into())),
+            ValueRef::Real(value) => rust_decimal::Decimal::try_from(value)
+                .map(Self)
+                .map_err(|err| FromSqlError::Other(Box::new(err))),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl ToSql for Decimal {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0.to_string()))
+    }
+}
@@ -0,0 +1,156 @@
+//! Read models kept in sync with the entities they are derived from
+//!
+//! A [`Denormalized`] entity copies or aggregates columns of other entities,
+//! the way an `EntityLabel` caches the label of the entity it points to, and
+//! declares the tables of those source entities. When sources change, the
+//! keys written are recorded in [`SourceChanges`] and [`refresh`] hands the
+//! ones of each source the entity derives from to a closure recomputing the
+//! affected rows. With the `rusqlite` feature, [`refresh_with_sql`] runs the
+//! SQL declared for each source instead, once per changed key bound to `?1`.
+//!
+//! `#[derive(Denormalized)]` declares the sources with
+//! `#[denormalized(source(Path), source(Path, sql = "..."))]`, where `Path`
+//! is an entity implementing `schema::Schema`:
+//!
+//! ```
+//! # #[cfg(all(feature = "derive", feature = "rusqlite"))] {
+//! # mod example {
+//! use dbent::prelude::*;
+//! use dbent::denormalized::{self, SourceChanges};
+//!
+//! #[derive(Entity)]
+//! #[entity(fields)]
+//! pub struct Customer {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! #[derive(Entity, Denormalized)]
+//! #[entity(fields)]
+//! #[denormalized(source(
+//!     Customer,
+//!     sql = "UPDATE order_summary SET customer_name = (SELECT name FROM customer WHERE id = ?1) WHERE customer_id = ?1"
+//! ))]
+//! pub struct OrderSummary {
+//!     id: Key<Int>,
+//!     customer_id: Int,
+//!     customer_name: String,
+//! }
+//!
+//! # pub fn main() -> dbent::Result<()> {
+//! let conn = rusqlite::Connection::open_in_memory()?;
+//! conn.execute_batch(
+//!     "CREATE TABLE customer (id INTEGER PRIMARY KEY, name TEXT);
+//!      CREATE TABLE order_summary (id INTEGER PRIMARY KEY, customer_id INTEGER, customer_name TEXT);
+//!      INSERT INTO customer VALUES (1, 'Ann');
+//!      INSERT INTO order_summary VALUES (1, 1, 'Ann'), (2, 1, 'Ann');
+//!      UPDATE customer SET name = 'Anne' WHERE id = 1;",
+//! )?;
+//!
+//! let mut changes = SourceChanges::new();
+//! changes.record::<Customer>(1);
+//! assert_eq!(denormalized::refresh_with_sql::<OrderSummary>(&conn, &changes)?, 2);
+//! # Ok(())
+//! # }
+//! # }
+//! # example::main().unwrap();
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::schema::Schema;
+use crate::sql::Value;
+use crate::Result;
+
+/// Entities whose rows are derived from the rows of other entities
+pub trait Denormalized {
+    /// Returns the tables of the entities the rows are derived from
+    fn sources() -> Vec<Cow<'static, str>>;
+
+    /// Returns the SQL recomputing the rows derived from a row of the
+    /// `source` table, whose key is bound to `?1`, if declared
+    fn refresh_sql(source: &str) -> Option<Cow<'static, str>> {
+        let _ = source;
+        None
+    }
+}
+
+/// The keys of the source rows written since the last refresh, by table
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct SourceChanges {
+    keys: BTreeMap<Cow<'static, str>, Vec<Value>>,
+}
+
+impl SourceChanges {
+    /// Creates SourceChanges without keys
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a write to the row of the table of `S` with the key
+    pub fn record<S: Schema>(&mut self, key: impl Into<Value>) {
+        self.record_table(S::table(), key);
+    }
+
+    /// Records a write to the row of the table with the key
+    pub fn record_table(&mut self, table: impl Into<Cow<'static, str>>, key: impl Into<Value>) {
+        let keys = self.keys.entry(table.into()).or_default();
+        let key = key.into();
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    /// Returns the keys recorded for the table, in the order they were recorded
+    pub fn keys(&self, table: &str) -> &[Value] {
+        self.keys.get(table).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the tables with keys recorded
+    pub fn tables(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(|table| &**table)
+    }
+
+    /// Returns true if no keys were recorded
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+/// Recomputes the rows of `T` derived from the changed source rows, calling
+/// `recompute` with each source of `T` that has changed keys and the keys,
+/// and returns the sum of the rows it reports
+pub fn refresh<T: Denormalized>(
+    changes: &SourceChanges,
+    mut recompute: impl FnMut(&str, &[Value]) -> Result<usize>,
+) -> Result<usize> {
+    let mut rows = 0;
+    for source in T::sources() {
+        let keys = changes.keys(&source);
+        if !keys.is_empty() {
+            rows += recompute(&source, keys)?;
+        }
+    }
+    Ok(rows)
+}
+
+/// Recomputes the rows of `T` derived from the changed source rows with the
+/// `refresh_sql` of each source, run once per changed key, and returns the
+/// number of rows changed
+///
+/// Sources without SQL are left to [`refresh`].
+#[cfg(feature = "rusqlite")]
+pub fn refresh_with_sql<T: Denormalized>(conn: &rusqlite::Connection, changes: &SourceChanges) -> Result<usize> {
+    let run = || {
+        refresh::<T>(changes, |source, keys| {
+            let Some(sql) = T::refresh_sql(source) else {
+                return Ok(0);
+            };
+            let mut statement = conn.prepare_cached(&sql)?;
+            keys.iter().try_fold(0, |rows, key| Ok(rows + statement.execute([key])?))
+        })
+    };
+    crate::trace::traced("refresh", core::any::type_name::<T>(), None, run, |&rows| rows)
+}
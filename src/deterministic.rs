@@ -0,0 +1,145 @@
+//! Serialized output that is the same on every run
+//!
+//! The map-like types of the crate serialize in a fixed order: a
+//! `LocalizedLabel` in the order its locales were added, the tables of a
+//! `SchemaSet` by name, and the maps returned by `ReadTracker::reads` and
+//! `FetchCounter::counts` by key. A `HashMap` or
+//! `HashSet` however iterates in an order seeded per process, which makes
+//! snapshots of values holding one change from run to run.
+//!
+//! [`DeterministicSerialize`] marks the types whose serialized form doesn't
+//! depend on such an order. It is implemented for the serializable types of
+//! the crate, for the standard types that keep their order, and for the
+//! containers of them, but not for `HashMap` and `HashSet`, so a snapshot
+//! helper bounded by it refuses at compile time the values that would flake:
+//!
+//! ```
+//! use dbent::deterministic::DeterministicSerialize;
+//! use dbent::localized::LocalizedLabel;
+//! use dbent::prelude::*;
+//!
+//! fn snapshot<T: DeterministicSerialize + ?Sized>(value: &T) -> String {
+//!     serde_json::to_string(value).unwrap()
+//! }
+//!
+//! let label = LocalizedLabel::new().with("pt", "Vermelho").with("en", "Red");
+//! assert_eq!(snapshot(&label), r#"{"pt":"Vermelho","en":"Red"}"#);
+//! assert_eq!(snapshot(&vec![Key::new(1), Key(None)]), "[1,null]");
+//! ```
+//!
+//! ```compile_fail
+//! # use dbent::deterministic::DeterministicSerialize;
+//! # fn snapshot<T: DeterministicSerialize + ?Sized>(value: &T) {}
+//! snapshot(&std::collections::HashMap::<String, i64>::new());
+//! ```
+//!
+//! Structs and enums of the application implement it by hand once all their
+//! fields do, as `impl DeterministicSerialize for User {}`.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::Serialize;
+
+use crate::schema::{ColumnMeta, EntityMeta, ManyMeta, OnDelete, SchemaSet, SqlType};
+use crate::{Entity, EntityLabel, Key, Keyed, Many, Parent, SharedMany, Tag, TagExt};
+
+/// Types whose serialized form is the same on every run, without maps or
+/// sets iterated in a per-process order
+pub trait DeterministicSerialize: Serialize {}
+
+macro_rules! impl_deterministic {
+    ($($ty:ty),* $(,)?) => {
+        $(impl DeterministicSerialize for $ty {})*
+    };
+}
+
+impl_deterministic!(bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, str, String, ());
+
+impl_deterministic!(
+    Tag,
+    crate::CiString,
+    crate::key_value::KeyValue,
+    crate::localized::LocalizedLabel,
+    crate::version::Version,
+    ColumnMeta,
+    EntityMeta,
+    ManyMeta,
+    OnDelete,
+    SchemaSet,
+    SqlType,
+);
+
+#[cfg(feature = "decimal")]
+impl_deterministic!(crate::decimal::Decimal);
+
+#[cfg(feature = "intern")]
+impl_deterministic!(crate::intern::InternedStr);
+
+#[cfg(feature = "uuid")]
+impl_deterministic!(uuid::Uuid);
+
+#[cfg(feature = "chrono")]
+impl_deterministic!(chrono::NaiveDate, chrono::NaiveDateTime, chrono::DateTime<chrono::Utc>);
+
+#[cfg(feature = "time")]
+impl_deterministic!(time::Date, time::PrimitiveDateTime, time::OffsetDateTime);
+
+impl<T: DeterministicSerialize + ?Sized> DeterministicSerialize for &T {}
+impl<T: DeterministicSerialize + ?Sized> DeterministicSerialize for Box<T> {}
+impl<T: DeterministicSerialize + ToOwned + ?Sized> DeterministicSerialize for Cow<'_, T> {}
+impl<T: DeterministicSerialize> DeterministicSerialize for Option<T> {}
+impl<T: DeterministicSerialize> DeterministicSerialize for [T] {}
+impl<T: DeterministicSerialize> DeterministicSerialize for Vec<T> {}
+impl<T: DeterministicSerialize> DeterministicSerialize for VecDeque<T> {}
+impl<T: DeterministicSerialize> DeterministicSerialize for BTreeSet<T> {}
+impl<K: DeterministicSerialize, V: DeterministicSerialize> DeterministicSerialize for BTreeMap<K, V> {}
+
+macro_rules! impl_deterministic_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: DeterministicSerialize),+> DeterministicSerialize for ($($name,)+) {}
+    };
+}
+
+impl_deterministic_tuple!(A);
+impl_deterministic_tuple!(A, B);
+impl_deterministic_tuple!(A, B, C);
+impl_deterministic_tuple!(A, B, C, D);
+
+impl<K: DeterministicSerialize> DeterministicSerialize for Key<K> {}
+impl<K: DeterministicSerialize> DeterministicSerialize for crate::Sensitive<K> {}
+impl<S: DeterministicSerialize, K: DeterministicSerialize> DeterministicSerialize for crate::ScopedKey<S, K> {}
+impl<P: DeterministicSerialize> DeterministicSerialize for TagExt<P> {}
+impl<K: DeterministicSerialize, T: DeterministicSerialize> DeterministicSerialize for Entity<K, T> {}
+impl<K: DeterministicSerialize, T: Keyed<KeyType = K>> DeterministicSerialize for Parent<K, T> {}
+impl<K: DeterministicSerialize, T> DeterministicSerialize for crate::WeakEntity<K, T> {}
+impl<K, T, L> DeterministicSerialize for EntityLabel<K, T, L>
+where
+    K: DeterministicSerialize,
+    T: DeterministicSerialize,
+    L: DeterministicSerialize,
+{
+}
+impl<T: DeterministicSerialize> DeterministicSerialize for Many<T> {}
+impl<T: DeterministicSerialize> DeterministicSerialize for SharedMany<T> {}
+impl<A: DeterministicSerialize, B: DeterministicSerialize> DeterministicSerialize for crate::LinkKey<A, B> {}
+impl<A, B, P> DeterministicSerialize for crate::Link<A, B, P>
+where
+    A: DeterministicSerialize,
+    B: DeterministicSerialize,
+    P: DeterministicSerialize,
+{
+}
+impl<T: DeterministicSerialize> DeterministicSerialize for crate::readonly::ReadOnly<T> {}
+impl<const N: usize> DeterministicSerialize for crate::fixed_str::FixedStr<N> {}
+impl<T: DeterministicSerialize> DeterministicSerialize for crate::serde::Redacted<T> {}
+impl<T: DeterministicSerialize + ?Sized> DeterministicSerialize for crate::serde::Redacting<'_, T> {}
+impl<T: DeterministicSerialize + ?Sized> DeterministicSerialize for crate::serde::DepthLimited<'_, T> {}
+#[cfg(feature = "json")]
+impl<T: DeterministicSerialize + crate::format::FormatVersion> DeterministicSerialize for crate::format::WithVersion<T> {}
+impl<T> DeterministicSerialize for crate::external_id::ExternalId<T>
+where
+    T: Keyed + crate::schema::Schema,
+    T::KeyType: core::fmt::Display,
+{
+}
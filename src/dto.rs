@@ -0,0 +1,93 @@
+//! Plain data transfer objects of entities
+//!
+//! [`IntoDto`] converts an entity to and from a flat struct holding no dbent
+//! types, for the types of an API boundary. `#[derive(IntoDto)]` generates
+//! the `{Struct}Dto` from the fields of the entity, so both stay in sync:
+//!
+//! - `Key` fields become an `Option` of the key
+//! - `Entity` and `Parent` fields become an `Option` of their key
+//! - `EntityLabel` fields become an `Option` of their key, with their label
+//!   inlined in an `Option` of the label named `{field}_label`
+//! - `Many` fields become a `Vec` of the DTOs of their entities, empty when
+//!   they are `NotFetched` or `None`, and converted back to `Many::Data`
+//! - other fields are kept as they are
+//!
+//! Fetched relations only keep their key, so a DTO converted back holds
+//! the keys of its `Entity`, `EntityLabel` and `Parent` fields. The keys and
+//! labels of relations must be `Clone`.
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::dto::IntoDto;
+//! use dbent::prelude::*;
+//!
+//! #[derive(Entity, Label, IntoDto)]
+//! #[dto(derive(Debug, PartialEq))]
+//! pub struct Country {
+//!     id: Key<Int>,
+//!     #[label]
+//!     name: String,
+//! }
+//!
+//! #[derive(Entity, IntoDto)]
+//! #[dto(derive(Debug, PartialEq))]
+//! pub struct City {
+//!     id: Key<Int>,
+//!     name: String,
+//!     country: EntityLabel<Int, Country, String>,
+//! }
+//!
+//! # pub fn main() {
+//! let city = City {
+//!     id: Key::new(1),
+//!     name: "Lisbon".to_owned(),
+//!     country: EntityLabel::KeyLabel(Key::new(351), "Portugal".to_owned()),
+//! };
+//! let dto = city.into_dto();
+//! assert_eq!(dto, CityDto {
+//!     id: Some(1),
+//!     name: "Lisbon".to_owned(),
+//!     country: Some(351),
+//!     country_label: Some("Portugal".to_owned()),
+//! });
+//!
+//! let city = City::from(dto);
+//! assert_eq!(city.country.label().unwrap(), "Portugal");
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+
+/// Trait for entities converted to and from a flat DTO, implemented by
+/// `#[derive(IntoDto)]`
+pub trait IntoDto: Sized {
+    /// The DTO of the entity
+    type Dto;
+
+    /// Converts the entity to its DTO
+    fn into_dto(self) -> Self::Dto;
+
+    /// Converts the DTO back to an entity
+    fn from_dto(dto: Self::Dto) -> Self;
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the code generated by `#[derive(IntoDto)]`
+
+    use super::IntoDto;
+    use crate::Many;
+
+    pub fn many_into_dtos<T: IntoDto>(many: Many<T>) -> Vec<T::Dto> {
+        match many {
+            Many::Data(data) => data.into_iter().map(T::into_dto).collect(),
+            Many::NotFetched | Many::None => Vec::new(),
+        }
+    }
+
+    pub fn many_from_dtos<T: IntoDto>(dtos: Vec<T::Dto>) -> Many<T> {
+        Many::Data(dtos.into_iter().map(T::from_dto).collect())
+    }
+}
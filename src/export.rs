@@ -0,0 +1,121 @@
+//! Tabular exports of entities
+//!
+//! [`TabularExport`] is implemented by the writers of tabular reports, like
+//! CSV or spreadsheet files. [`export`](TabularExport::export) walks the
+//! entities with their [`Schema`] and [`Columns`], so a writer only handles a
+//! table described by its [`EntityMeta`] and the rows of cell [`Value`]s.
+//! Writing several tables, like the sheets of a workbook, begins and ends
+//! each one in turn.
+//!
+//! With the `csv` feature, [`csv::CsvExport`](crate::csv::CsvExport) writes
+//! CSV. A spreadsheet writer could look like this:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::export::TabularExport;
+//! use dbent::prelude::*;
+//! use dbent::schema::EntityMeta;
+//! use dbent::sql::Value;
+//!
+//! #[derive(Default)]
+//! pub struct Workbook {
+//!     sheets: Vec<(String, Vec<Vec<String>>)>,
+//! }
+//!
+//! impl TabularExport for Workbook {
+//!     type Error = std::convert::Infallible;
+//!
+//!     fn begin(&mut self, meta: &EntityMeta) -> Result<(), Self::Error> {
+//!         let header = meta.columns.iter().map(|column| column.name.to_string()).collect();
+//!         self.sheets.push((meta.table.to_string(), vec![header]));
+//!         Ok(())
+//!     }
+//!
+//!     fn row(&mut self, cells: &[Value]) -> Result<(), Self::Error> {
+//!         let row = cells.iter().map(|cell| match cell {
+//!             Value::Null => String::new(),
+//!             Value::Integer(value) => value.to_string(),
+//!             Value::Unsigned(value) => value.to_string(),
+//!             Value::Real(value) => value.to_string(),
+//!             Value::Text(text) => text.clone(),
+//!             Value::Blob(bytes) => format!("{} bytes", bytes.len()),
+//!         });
+//!         self.sheets.last_mut().expect("a sheet was begun").1.push(row.collect());
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[derive(Entity)]
+//! #[entity(fields)]
+//! pub struct Country {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! # pub fn main() {
+//! let mut workbook = Workbook::default();
+//! workbook.export(&[Country { id: Key::new(1), name: "Chile".to_owned() }]).unwrap();
+//! assert_eq!(workbook.sheets[0].0, "country");
+//! assert_eq!(workbook.sheets[0].1, [["id", "name"], ["1", "Chile"]]);
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+
+use crate::schema::{EntityMeta, Schema};
+use crate::sql::{Columns, Value};
+
+/// A writer of tables of entities, like a CSV or spreadsheet writer
+pub trait TabularExport {
+    /// The error of the writer
+    type Error;
+
+    /// Starts a table with the columns of `meta`, like writing a header or
+    /// adding a sheet
+    fn begin(&mut self, meta: &EntityMeta) -> Result<(), Self::Error>;
+
+    /// Writes a row with a cell for each column of the table, in order
+    fn row(&mut self, cells: &[Value]) -> Result<(), Self::Error>;
+
+    /// Ends the table, like flushing it; does nothing by default
+    fn end(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Writes a table with a row for each entity, reading their columns as
+    /// NULL when they have no value
+    fn export<'a, T>(&mut self, entities: impl IntoIterator<Item = &'a T>) -> Result<(), Self::Error>
+    where
+        T: Schema + Columns + 'a,
+        Self: Sized,
+    {
+        let meta = T::entity_meta();
+        self.begin(&meta)?;
+
+        let mut cells = Vec::with_capacity(meta.columns.len());
+        for entity in entities {
+            cells.clear();
+            cells.extend(meta.columns.iter().map(|column| entity.column_value(&column.name).unwrap_or_default()));
+            self.row(&cells)?;
+        }
+        self.end()
+    }
+}
+
+impl<E: TabularExport + ?Sized> TabularExport for &mut E {
+    type Error = E::Error;
+
+    fn begin(&mut self, meta: &EntityMeta) -> Result<(), Self::Error> {
+        (**self).begin(meta)
+    }
+
+    fn row(&mut self, cells: &[Value]) -> Result<(), Self::Error> {
+        (**self).row(cells)
+    }
+
+    fn end(&mut self) -> Result<(), Self::Error> {
+        (**self).end()
+    }
+}
@@ -0,0 +1,191 @@
+//! Prefixed ids for public APIs
+//!
+//! An [`ExternalId`] renders the key of an entity with the prefix of its
+//! type, like `user_123`, so the ids handed out by an API tell what they
+//! point at while the database keeps plain integer keys. The prefix is the
+//! `id_prefix` of the [`EntityMeta`](crate::schema::EntityMeta) of the
+//! entity, set with `#[entity(id_prefix = "...")]`, or its table name.
+//!
+//! Parsing checks the prefix, so the id of one entity type can't be passed
+//! where another is expected. With serde, ExternalIds are strings:
+//!
+//! ```
+//! # #[cfg(all(feature = "derive", feature = "serde"))] {
+//! # mod example {
+//! use dbent::external_id::ExternalId;
+//! use dbent::prelude::*;
+//!
+//! #[derive(Entity)]
+//! #[entity(fields)]
+//! pub struct User {
+//!     id: Key<Int>,
+//! }
+//!
+//! #[derive(Entity)]
+//! #[entity(fields, table = "invoices", id_prefix = "in")]
+//! pub struct Invoice {
+//!     id: Key<Int>,
+//! }
+//!
+//! # pub fn main() {
+//! let user = User { id: Key::new(123) };
+//! let id = ExternalId::of(&user).unwrap();
+//! assert_eq!(id.to_string(), "user_123");
+//!
+//! let id: ExternalId<Invoice> = "in_42".parse().unwrap();
+//! assert_eq!(id.into_key(), Key::new(42));
+//! assert!("user_42".parse::<ExternalId<Invoice>>().is_err());
+//!
+//! assert_eq!(serde_json::to_string(&ExternalId::<Invoice>::new(7)).unwrap(), r#""in_7""#);
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::str::FromStr;
+
+use crate::schema::Schema;
+use crate::{Error, Key, Keyed, Result};
+
+/// The key of an entity of type `T` rendered with the prefix of the type
+pub struct ExternalId<T: Keyed> {
+    key: T::KeyType,
+    entity: PhantomData<fn() -> T>,
+}
+
+impl<T: Keyed> ExternalId<T> {
+    /// Creates the ExternalId of the key value
+    pub fn new(key: T::KeyType) -> Self {
+        Self { key, entity: PhantomData }
+    }
+
+    /// Creates the ExternalId of the Key, failing with `Error::KeyMissing`
+    /// for an unset Key
+    pub fn from_key(key: Key<T::KeyType>) -> Result<Self> {
+        key.0.map(Self::new).ok_or(Error::KeyMissing)
+    }
+
+    /// Creates the ExternalId of the key of the entity
+    pub fn of(entity: &T) -> Result<Self>
+    where
+        T::KeyType: Clone,
+    {
+        Self::from_key(entity.key()?.clone())
+    }
+
+    /// Returns the key value
+    pub fn key(&self) -> &T::KeyType {
+        &self.key
+    }
+
+    /// Converts into the Key of the entity
+    pub fn into_key(self) -> Key<T::KeyType> {
+        Key(Some(self.key))
+    }
+}
+
+impl<T: Keyed> Clone for ExternalId<T>
+where
+    T::KeyType: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.key.clone())
+    }
+}
+
+impl<T: Keyed> Copy for ExternalId<T> where T::KeyType: Copy {}
+
+impl<T: Keyed> PartialEq for ExternalId<T>
+where
+    T::KeyType: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<T: Keyed> Eq for ExternalId<T> where T::KeyType: Eq {}
+
+impl<T: Keyed> Hash for ExternalId<T>
+where
+    T::KeyType: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl<T: Keyed + Schema> fmt::Display for ExternalId<T>
+where
+    T::KeyType: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", T::id_prefix(), self.key)
+    }
+}
+
+impl<T: Keyed + Schema> fmt::Debug for ExternalId<T>
+where
+    T::KeyType: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ExternalId").field(&format_args!("{self}")).finish()
+    }
+}
+
+impl<T: Keyed + Schema> FromStr for ExternalId<T>
+where
+    T::KeyType: FromStr,
+{
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        value
+            .strip_prefix(&*T::id_prefix())
+            .and_then(|rest| rest.strip_prefix('_'))
+            .and_then(|key| key.parse().ok())
+            .map(Self::new)
+            .ok_or_else(|| Error::InvalidExternalId(value.to_owned()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Keyed + Schema> serde::Serialize for ExternalId<T>
+where
+    T::KeyType: fmt::Display,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Keyed + Schema> serde::Deserialize<'de> for ExternalId<T>
+where
+    T::KeyType: FromStr,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        struct Visitor<T>(PhantomData<fn() -> T>);
+
+        impl<T: Keyed + Schema> serde::de::Visitor<'_> for Visitor<T>
+        where
+            T::KeyType: FromStr,
+        {
+            type Value = ExternalId<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "an id prefixed with {}_", T::id_prefix())
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> core::result::Result<Self::Value, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(Visitor(PhantomData))
+    }
+}
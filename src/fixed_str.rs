@@ -0,0 +1,202 @@
+//! Fixed capacity strings for short keys
+//!
+//! A [`FixedStr<N>`] holds up to `N` bytes of UTF-8 inline, so code-style
+//! keys like currency or locale codes, i.e. `Key<FixedStr<8>>`, are copied
+//! and compared without heap allocations in hot lookup paths. It converts to
+//! and from SQL text and serde strings like `String` does.
+//!
+//! ```
+//! use dbent::prelude::*;
+//! use dbent::fixed_str::FixedStr;
+//!
+//! let usd = Key::new(FixedStr::<3>::new("USD").unwrap());
+//! assert_eq!(usd.to_string(), "USD");
+//! assert!(FixedStr::<3>::new("en-US").is_err());
+//! ```
+
+use core::{borrow::Borrow, fmt, hash, ops::Deref, str::FromStr};
+use std::borrow::Cow;
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+use crate::{normalize_label, Error, NormalizedLabel, Result};
+
+/// A string of up to `N` bytes stored inline, with `N` at most 255
+#[derive(Clone, Copy)]
+pub struct FixedStr<const N: usize> {
+    len: u8,
+    bytes: [u8; N],
+}
+
+impl<const N: usize> FixedStr<N> {
+    const CAPACITY_FITS: () = assert!(N <= u8::MAX as usize, "FixedStr capacity must be at most 255");
+
+    /// The maximum length in bytes
+    pub const CAPACITY: usize = N;
+
+    /// Copies `value`, failing with `Error::CapacityExceeded` if it is longer than `N` bytes
+    pub fn new(value: &str) -> Result<Self> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CAPACITY_FITS;
+
+        if value.len() > N {
+            return Err(Error::CapacityExceeded { len: value.len(), capacity: N });
+        }
+        let mut bytes = [0; N];
+        bytes[..value.len()].copy_from_slice(value.as_bytes());
+        Ok(Self { len: value.len() as u8, bytes })
+    }
+
+    /// Returns the string slice
+    pub fn as_str(&self) -> &str {
+        // only whole str values are ever copied in
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> Default for FixedStr<N> {
+    fn default() -> Self {
+        Self { len: 0, bytes: [0; N] }
+    }
+}
+
+impl<const N: usize> Deref for FixedStr<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> AsRef<str> for FixedStr<N> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> Borrow<str> for FixedStr<N> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> PartialEq for FixedStr<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const N: usize> Eq for FixedStr<N> {}
+
+impl<const N: usize> PartialEq<str> for FixedStr<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for FixedStr<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<const N: usize> PartialOrd for FixedStr<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const N: usize> Ord for FixedStr<N> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl<const N: usize> hash::Hash for FixedStr<N> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl<const N: usize> NormalizedLabel for FixedStr<N> {
+    fn normalized(&self) -> Cow<'_, str> {
+        normalize_label(self.as_str())
+    }
+}
+
+impl<const N: usize> fmt::Display for FixedStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> fmt::Debug for FixedStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> FromStr for FixedStr<N> {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for FixedStr<N> {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl<const N: usize> From<FixedStr<N>> for String {
+    fn from(value: FixedStr<N>) -> Self {
+        value.as_str().to_owned()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const N: usize> serde::Serialize for FixedStr<N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> serde::Deserialize<'de> for FixedStr<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        struct Visitor<const N: usize>;
+
+        impl<const N: usize> serde::de::Visitor<'_> for Visitor<N> {
+            type Value = FixedStr<N>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a string of at most {N} bytes")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> core::result::Result<Self::Value, E> {
+                FixedStr::new(value).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl<const N: usize> FromSql for FixedStr<N> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Self::new(value.as_str()?).map_err(|err| FromSqlError::Other(Box::new(err)))
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl<const N: usize> ToSql for FixedStr<N> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
@@ -0,0 +1,116 @@
+//! Versioned serialization formats, with the `json` feature
+//!
+//! Apps persisting entity snapshots to disk need to read the ones written by
+//! older releases. Types implementing [`FormatVersion`] are serialized inside
+//! [`WithVersion`] along with their current format version, and upgraded on
+//! deserialization by their [`FormatVersion::migrate`] hook, one version at a
+//! time, before being read. `#[derive(Entity)]` implements [`FormatVersion`]
+//! with `#[entity(version = 3)]`, and `#[entity(version = 3, migrate = "path")]`
+//! for a migration function.
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use dbent::prelude::*;
+//! use dbent::format::WithVersion;
+//! use serde::{Deserialize, Serialize};
+//! use serde_json::{json, Value};
+//!
+//! #[derive(Entity, Serialize, Deserialize)]
+//! #[entity(version = 2, migrate = "upgrade_user")]
+//! struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! // version 1 stored the name as `login`
+//! fn upgrade_user(version: u32, mut value: Value) -> Value {
+//!     if version == 1 {
+//!         if let Some(login) = value.as_object_mut().and_then(|user| user.remove("login")) {
+//!             value["name"] = login;
+//!         }
+//!     }
+//!     value
+//! }
+//!
+//! let user = User { id: Key::new(1), name: "ann".to_owned() };
+//! let stored = serde_json::to_value(WithVersion(&user)).unwrap();
+//! assert_eq!(stored, json!({ "version": 2, "entity": { "id": 1, "name": "ann" } }));
+//!
+//! let old = json!({ "version": 1, "entity": { "id": 1, "login": "bob" } });
+//! let WithVersion(user) = serde_json::from_value::<WithVersion<User>>(old).unwrap();
+//! assert_eq!(user.name, "bob");
+//! # }
+//! ```
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{Error, Result};
+
+/// Trait for types whose serialized form carries a format version
+pub trait FormatVersion {
+    /// The current format version
+    const FORMAT_VERSION: u32;
+
+    /// Upgrades `value`, serialized with format `version`, to `version + 1`
+    ///
+    /// Returns the value as is by default, for versions that only added
+    /// fields with serde defaults.
+    fn migrate(version: u32, value: Value) -> Value {
+        let _ = version;
+        value
+    }
+}
+
+impl<T: FormatVersion + ?Sized> FormatVersion for &T {
+    const FORMAT_VERSION: u32 = T::FORMAT_VERSION;
+
+    fn migrate(version: u32, value: Value) -> Value {
+        T::migrate(version, value)
+    }
+}
+
+/// Reads `T` from a `value` serialized with format `version`, migrating it
+/// to the current version first
+///
+/// Fails with `Error::UnsupportedFormatVersion` for versions newer than the current one.
+pub fn upgrade<T: DeserializeOwned + FormatVersion>(version: u32, value: Value) -> Result<T> {
+    if version > T::FORMAT_VERSION {
+        return Err(Error::UnsupportedFormatVersion { found: version, supported: T::FORMAT_VERSION });
+    }
+    let value = (version..T::FORMAT_VERSION).fold(value, |value, version| T::migrate(version, value));
+    Ok(serde_json::from_value(value)?)
+}
+
+/// The serialized form of a [`WithVersion`]
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    entity: T,
+}
+
+/// A wrapper serializing a value as `{ "version": ..., "entity": ... }`, and
+/// migrating it to the current format version when deserializing
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct WithVersion<T>(pub T);
+
+impl<T: Serialize + FormatVersion> Serialize for WithVersion<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        Envelope { version: T::FORMAT_VERSION, entity: &self.0 }.serialize(serializer)
+    }
+}
+
+impl<'de, T: DeserializeOwned + FormatVersion> Deserialize<'de> for WithVersion<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        let envelope = Envelope::<Value>::deserialize(deserializer)?;
+        upgrade(envelope.version, envelope.entity).map(WithVersion).map_err(D::Error::custom)
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the code generated by `#[entity(version = ...)]`
+
+    pub use serde_json::Value;
+}
@@ -0,0 +1,591 @@
+//! Diffing entity graphs for syncing
+//!
+//! [`diff_graph`] compares two versions of an entity and the related entities
+//! loaded into it, e.g. the state last synced to a server and the state after
+//! local edits. It reports the column changes of every entity found in both,
+//! and the entities of `Many` relations that were added or removed:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::prelude::*;
+//! use dbent::graph::diff_graph;
+//!
+//! #[derive(Entity, Clone)]
+//! #[entity(fields)]
+//! pub struct Order {
+//!     id: Key<Int>,
+//!     note: String,
+//!     lines: Many<Line>,
+//! }
+//!
+//! #[derive(Entity, Clone)]
+//! #[entity(fields)]
+//! pub struct Line {
+//!     id: Key<Int>,
+//!     quantity: i32,
+//! }
+//!
+//! # pub fn main() {
+//! let line = |id, quantity| Line { id: Key::new(id), quantity };
+//! let old = Order { id: Key::new(1), note: "".to_owned(), lines: vec![line(1, 1), line(2, 1)].into() };
+//! let mut new = old.clone();
+//! new.note = "rush".to_owned();
+//! new.lines = vec![line(1, 3), line(3, 1)].into();
+//!
+//! let diff = diff_graph(&old, &new);
+//! assert_eq!(diff.changed.len(), 2);
+//! assert_eq!(diff.added[0].key, 3.into());
+//! assert_eq!(diff.removed[0].key, 2.into());
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+//!
+//! `#[derive(Entity)]` implements [`Graph`] with `#[entity(fields)]`. Entities
+//! are matched by their key, the value of their first column. Relations not
+//! loaded on either side are skipped, and entities of `Entity` relations are
+//! only compared, as they are referenced rather than owned; a changed
+//! reference shows as a change of the referencing column.
+//!
+//! [`Graph::clone_as_new`] duplicates an entity with its children, unsetting
+//! their keys so they are inserted as new entities.
+//!
+//! [`debug_graph`] renders which relations of a graph are loaded, for
+//! debugging hydration, and [`check_integrity`] finds the required relations
+//! left as None before saving a graph. [`graph_stats`] counts the entities
+//! and relations of a graph and estimates its size, to log before sending a
+//! response and catch graphs fetched deeper than needed.
+
+use core::any::type_name;
+use core::fmt::Write;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::changeset::Changeset;
+use crate::schema::Cardinality;
+use crate::sql::{Columns, Value};
+
+/// Trait for entities whose loaded relations can be walked at runtime
+///
+/// This is object safe so graphs of different entity types can be walked.
+pub trait Graph: Columns {
+    /// Returns the table of the entity
+    fn graph_table(&self) -> Cow<'static, str>;
+
+    /// Returns the relations of the entity with the related entities loaded
+    fn graph_relations(&self) -> Vec<GraphRelation<'_>>;
+
+    /// Returns the key of the entity, the value of its first column
+    fn graph_key(&self) -> Value {
+        self.columns()
+            .first()
+            .and_then(|column| self.column_value(column))
+            .unwrap_or_default()
+    }
+
+    /// Returns the name of the entity type, without its path
+    fn graph_name(&self) -> &'static str {
+        let name = type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
+    /// Returns the size of the entity itself, without the data it owns
+    fn graph_size(&self) -> usize {
+        core::mem::size_of_val(self)
+    }
+
+    /// Unsets the key of the entity and of the entities of its loaded `Many`
+    /// relations, and with `child` clears its `Parent` relations, which point
+    /// at the entity holding it
+    fn graph_clear_keys(&mut self, child: bool);
+
+    /// Returns a copy of the entity and of the entities of its loaded `Many`
+    /// relations with their keys unset, to insert as new entities
+    ///
+    /// The `Parent` relations of the copied children are cleared, as the new
+    /// entity holding them has no key yet. Entities of `Entity` and
+    /// `EntityLabel` relations are referenced rather than owned, so they are
+    /// copied with their keys.
+    fn clone_as_new(&self) -> Self
+    where
+        Self: Clone + Sized,
+    {
+        let mut entity = self.clone();
+        entity.graph_clear_keys(false);
+        entity
+    }
+}
+
+/// The kind of a relation between entities
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RelationKind {
+    /// A reference to one entity, like `Entity` or `Parent`
+    One,
+    /// A collection of owned entities, like `Many`
+    Many,
+}
+
+/// The fetch state of a relation
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RelationState {
+    /// The related data is loaded, like `Entity::Data` or `Many::Data`
+    Fetched,
+    /// Only the key is known, like `Entity::Key` or `Many::NotFetched`
+    NotFetched,
+    /// There is no related data, like `Entity::None` or `Many::None`
+    None,
+    /// The field isn't one of the relation types
+    Unknown,
+}
+
+/// A relation of an entity with the related entities loaded
+pub struct GraphRelation<'a> {
+    /// The field holding the relation
+    pub field: &'static str,
+    /// The kind of relation
+    pub kind: RelationKind,
+    /// The declared cardinality of the relation
+    pub cardinality: Cardinality,
+    /// The fetch state of the relation
+    pub state: RelationState,
+    /// The related entities, or None if not loaded or not walkable
+    pub nodes: Option<Vec<&'a dyn Graph>>,
+}
+
+/// The column changes of an entity found in both graphs
+#[derive(Clone, PartialEq, Debug)]
+pub struct EntityDiff {
+    /// The table of the entity
+    pub table: Cow<'static, str>,
+    /// The key of the entity
+    pub key: Value,
+    /// The changed columns
+    pub changes: Changeset,
+}
+
+/// An entity found in only one of the graphs
+#[derive(Clone, PartialEq, Debug)]
+pub struct GraphChild {
+    /// The table of the entity
+    pub table: Cow<'static, str>,
+    /// The key of the entity, Null if not saved yet
+    pub key: Value,
+    /// The table of the entity holding the relation
+    pub parent_table: Cow<'static, str>,
+    /// The key of the entity holding the relation
+    pub parent_key: Value,
+    /// The field holding the relation
+    pub field: &'static str,
+    /// The values of the columns of the entity
+    pub values: Vec<(&'static str, Value)>,
+}
+
+/// The differences between two versions of an entity graph
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GraphDiff {
+    /// Column changes of the entities found in both graphs
+    pub changed: Vec<EntityDiff>,
+    /// Entities found only in the new graph, parents before children
+    pub added: Vec<GraphChild>,
+    /// Entities found only in the old graph, parents before children
+    pub removed: Vec<GraphChild>,
+}
+
+impl GraphDiff {
+    /// Returns true if the graphs are the same
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Returns the differences between two versions of an entity graph
+pub fn diff_graph<T: Graph>(old: &T, new: &T) -> GraphDiff {
+    let mut diff = GraphDiff::default();
+    diff_nodes(old, new, &mut diff);
+    diff
+}
+
+fn diff_nodes(old: &dyn Graph, new: &dyn Graph, diff: &mut GraphDiff) {
+    let changes = Changeset::between(old, new);
+    if !changes.is_empty() {
+        diff.changed.push(EntityDiff { table: new.graph_table(), key: new.graph_key(), changes });
+    }
+
+    let old_relations = old.graph_relations();
+    for relation in new.graph_relations() {
+        let old_nodes = old_relations
+            .iter()
+            .find(|old| old.field == relation.field)
+            .and_then(|old| old.nodes.as_ref());
+        let (Some(old_nodes), Some(new_nodes)) = (old_nodes, relation.nodes.as_ref()) else {
+            continue;
+        };
+
+        let matching = |node: &dyn Graph, nodes: &[&'_ dyn Graph]| -> Option<usize> {
+            let key = node.graph_key();
+            (key != Value::Null).then(|| nodes.iter().position(|other| other.graph_key() == key)).flatten()
+        };
+
+        for &node in new_nodes {
+            match matching(node, old_nodes) {
+                Some(i) => diff_nodes(old_nodes[i], node, diff),
+                None if relation.kind == RelationKind::Many => push_child(new, relation.field, node, &mut diff.added),
+                None => {},
+            }
+        }
+        if relation.kind == RelationKind::Many {
+            for &node in old_nodes {
+                if matching(node, new_nodes).is_none() {
+                    push_child(old, relation.field, node, &mut diff.removed);
+                }
+            }
+        }
+    }
+}
+
+/// Pushes `node` and the entities it owns to `children`
+fn push_child(parent: &dyn Graph, field: &'static str, node: &dyn Graph, children: &mut Vec<GraphChild>) {
+    children.push(GraphChild {
+        table: node.graph_table(),
+        key: node.graph_key(),
+        parent_table: parent.graph_table(),
+        parent_key: parent.graph_key(),
+        field,
+        values: node
+            .columns()
+            .iter()
+            .map(|&column| (column, node.column_value(column).unwrap_or_default()))
+            .collect(),
+    });
+
+    for relation in node.graph_relations() {
+        if relation.kind == RelationKind::Many {
+            for child in relation.nodes.into_iter().flatten() {
+                push_child(node, relation.field, child, children);
+            }
+        }
+    }
+}
+
+/// Renders the entity and its relations as a tree showing what is loaded
+///
+/// Fetched relations are expanded, the others show their state, with the
+/// key of `Entity` relations not fetched yet:
+///
+/// ```text
+/// User(5)
+/// ├─ orders: NotFetched
+/// ├─ org: Key(3)
+/// └─ posts: [1]
+///    └─ Post(7)
+///       └─ tags: None
+/// ```
+pub fn debug_graph<T: Graph>(entity: &T) -> String {
+    let mut out = String::new();
+    write_node(&mut out, entity, "");
+    out
+}
+
+/// Returns the branch and the indent of its children for item `i` of `count`
+fn branch(i: usize, count: usize) -> (&'static str, &'static str) {
+    if i + 1 == count {
+        ("└─ ", "   ")
+    } else {
+        ("├─ ", "│  ")
+    }
+}
+
+fn write_node(out: &mut String, node: &dyn Graph, prefix: &str) {
+    let _ = writeln!(out, "{}({})", node.graph_name(), display_value(&node.graph_key()));
+
+    let relations = node.graph_relations();
+    let count = relations.len();
+    for (i, relation) in relations.into_iter().enumerate() {
+        let (branch_str, indent) = branch(i, count);
+        let _ = write!(out, "{prefix}{branch_str}{}: ", relation.field);
+        let prefix = format!("{prefix}{indent}");
+
+        match (relation.kind, relation.state, relation.nodes) {
+            (RelationKind::One, _, Some(nodes)) if nodes.len() == 1 => write_node(out, nodes[0], &prefix),
+            (RelationKind::Many, _, Some(nodes)) => {
+                let _ = writeln!(out, "[{}]", nodes.len());
+                for (i, &child) in nodes.iter().enumerate() {
+                    let (branch_str, indent) = branch(i, nodes.len());
+                    let _ = write!(out, "{prefix}{branch_str}");
+                    write_node(out, child, &format!("{prefix}{indent}"));
+                }
+            },
+            (RelationKind::One, RelationState::NotFetched, _) => {
+                let key = node.column_value(relation.field).unwrap_or_default();
+                let _ = writeln!(out, "Key({})", display_value(&key));
+            },
+            (_, state, _) => {
+                let _ = writeln!(out, "{state:?}");
+            },
+        }
+    }
+}
+
+/// A required relation left as None, found by [`check_integrity`]
+#[derive(Clone, PartialEq, Debug)]
+pub struct IntegrityIssue {
+    /// The table of the entity holding the relation
+    pub table: Cow<'static, str>,
+    /// The key of the entity holding the relation
+    pub key: Value,
+    /// The field holding the relation
+    pub field: &'static str,
+}
+
+impl core::fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}({}).{} is required but None", self.table, display_value(&self.key), self.field)
+    }
+}
+
+/// Returns the relations with [`Cardinality::RequiredOne`] left as None in
+/// the entity and the related entities loaded into it
+pub fn check_integrity<T: Graph>(entity: &T) -> Vec<IntegrityIssue> {
+    let mut issues = Vec::new();
+    check_node(entity, &mut issues);
+    issues
+}
+
+fn check_node(node: &dyn Graph, issues: &mut Vec<IntegrityIssue>) {
+    for relation in node.graph_relations() {
+        if relation.cardinality == Cardinality::RequiredOne && relation.state == RelationState::None {
+            issues.push(IntegrityIssue { table: node.graph_table(), key: node.graph_key(), field: relation.field });
+        }
+        for child in relation.nodes.into_iter().flatten() {
+            check_node(child, issues);
+        }
+    }
+}
+
+/// The size of an entity graph, returned by [`graph_stats`]
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct GraphStats {
+    /// Entities in the graph, including the root
+    pub entities: usize,
+    /// Entities of each table
+    pub tables: BTreeMap<Cow<'static, str>, usize>,
+    /// Relations with their data loaded
+    pub fetched: usize,
+    /// Relations holding only a key, or `Many` relations not fetched
+    pub not_fetched: usize,
+    /// Relations with no data
+    pub none: usize,
+    /// Entities in the loaded `Many` relations
+    pub many_elements: usize,
+    /// Levels of relations below the root holding loaded entities
+    pub depth: usize,
+    /// Approximate bytes taken by the entities and their text and blob columns
+    pub approx_bytes: usize,
+}
+
+/// Returns the counts of the entities and relations in the entity and the
+/// related entities loaded into it, with an estimate of their size
+///
+/// The size adds the size of each entity to the length of its text and blob
+/// column values, so it leaves out the capacity of strings and vectors and
+/// the fields that aren't columns.
+pub fn graph_stats<T: Graph>(entity: &T) -> GraphStats {
+    let mut stats = GraphStats::default();
+    stats_node(entity, 0, &mut stats);
+    stats
+}
+
+fn stats_node(node: &dyn Graph, depth: usize, stats: &mut GraphStats) {
+    stats.entities += 1;
+    stats.depth = stats.depth.max(depth);
+    *stats.tables.entry(node.graph_table()).or_default() += 1;
+    stats.approx_bytes += node.graph_size();
+    for column in node.columns() {
+        match node.column_value(column) {
+            Some(Value::Text(text)) => stats.approx_bytes += text.len(),
+            Some(Value::Blob(blob)) => stats.approx_bytes += blob.len(),
+            _ => {},
+        }
+    }
+
+    for relation in node.graph_relations() {
+        match relation.state {
+            RelationState::Fetched => stats.fetched += 1,
+            RelationState::NotFetched => stats.not_fetched += 1,
+            RelationState::None => stats.none += 1,
+            RelationState::Unknown => {},
+        }
+        let nodes = relation.nodes.unwrap_or_default();
+        if relation.kind == RelationKind::Many {
+            stats.many_elements += nodes.len();
+        }
+        for child in nodes {
+            stats_node(child, depth + 1, stats);
+        }
+    }
+}
+
+/// Formats a key for [`debug_graph`]
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "None".to_owned(),
+        Value::Integer(value) => value.to_string(),
+        Value::Unsigned(value) => value.to_string(),
+        Value::Real(value) => value.to_string(),
+        Value::Text(value) => format!("{value:?}"),
+        Value::Blob(value) => value.iter().map(|byte| format!("{byte:02x}")).collect(),
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the code generated by `#[entity(fields)]`
+
+    use crate::poly::{PolyEntity, PolyTargets, __private::DataGraph};
+    use crate::{Entity, EntityLabel, Many, Parent};
+
+    use super::{Graph, RelationState};
+
+    pub struct NodesOf<'a, R: ?Sized>(pub &'a R);
+
+    pub trait KnownNodes<'a> {
+        fn nodes(&self) -> Option<Vec<&'a dyn Graph>>;
+    }
+
+    impl<'a, K, T: Graph> KnownNodes<'a> for NodesOf<'a, Entity<K, T>> {
+        fn nodes(&self) -> Option<Vec<&'a dyn Graph>> {
+            match self.0 {
+                Entity::Data(data) => Some(vec![&**data as &dyn Graph]),
+                _ => None,
+            }
+        }
+    }
+
+    impl<'a, K, T: Graph> KnownNodes<'a> for NodesOf<'a, Parent<K, T>> {
+        fn nodes(&self) -> Option<Vec<&'a dyn Graph>> {
+            NodesOf(&self.0.0).nodes()
+        }
+    }
+
+    impl<'a, K, T: Graph, L> KnownNodes<'a> for NodesOf<'a, EntityLabel<K, T, L>> {
+        fn nodes(&self) -> Option<Vec<&'a dyn Graph>> {
+            match self.0 {
+                EntityLabel::Data(data) => Some(vec![&**data as &dyn Graph]),
+                _ => None,
+            }
+        }
+    }
+
+    impl<'a, T: DataGraph> KnownNodes<'a> for NodesOf<'a, PolyEntity<T>> {
+        fn nodes(&self) -> Option<Vec<&'a dyn Graph>> {
+            match self.0 {
+                PolyEntity::Data(data) => Some(vec![T::data_graph(data)]),
+                _ => None,
+            }
+        }
+    }
+
+    impl<'a, T: Graph> KnownNodes<'a> for NodesOf<'a, Many<T>> {
+        fn nodes(&self) -> Option<Vec<&'a dyn Graph>> {
+            match self.0 {
+                Many::Data(data) => Some(data.iter().map(|node| node as &dyn Graph).collect()),
+                _ => None,
+            }
+        }
+    }
+
+    pub trait KnownState {
+        fn state(&self) -> RelationState;
+    }
+
+    impl<K, T> KnownState for NodesOf<'_, Entity<K, T>> {
+        fn state(&self) -> RelationState {
+            match self.0 {
+                Entity::Data(_) => RelationState::Fetched,
+                Entity::Key(_) => RelationState::NotFetched,
+                Entity::None => RelationState::None,
+            }
+        }
+    }
+
+    impl<K, T> KnownState for NodesOf<'_, Parent<K, T>> {
+        fn state(&self) -> RelationState {
+            NodesOf(&self.0.0).state()
+        }
+    }
+
+    impl<K, T, L> KnownState for NodesOf<'_, EntityLabel<K, T, L>> {
+        fn state(&self) -> RelationState {
+            match self.0 {
+                EntityLabel::Data(_) => RelationState::Fetched,
+                EntityLabel::KeyLabel(..) => RelationState::NotFetched,
+                EntityLabel::None => RelationState::None,
+            }
+        }
+    }
+
+    impl<T: PolyTargets> KnownState for NodesOf<'_, PolyEntity<T>> {
+        fn state(&self) -> RelationState {
+            match self.0 {
+                PolyEntity::Data(_) => RelationState::Fetched,
+                PolyEntity::Key(..) => RelationState::NotFetched,
+                PolyEntity::None => RelationState::None,
+            }
+        }
+    }
+
+    impl<T> KnownState for NodesOf<'_, Many<T>> {
+        fn state(&self) -> RelationState {
+            match self.0 {
+                Many::Data(_) => RelationState::Fetched,
+                Many::NotFetched => RelationState::NotFetched,
+                Many::None => RelationState::None,
+            }
+        }
+    }
+
+    pub trait UnknownState {
+        fn state(&self) -> RelationState;
+    }
+
+    impl<R: ?Sized> UnknownState for &NodesOf<'_, R> {
+        fn state(&self) -> RelationState {
+            RelationState::Unknown
+        }
+    }
+
+    pub struct ChildrenOf<'a, R: ?Sized>(pub &'a mut R);
+
+    pub trait KnownChildren {
+        fn clear_keys(&mut self);
+    }
+
+    impl<T: Graph> KnownChildren for ChildrenOf<'_, Many<T>> {
+        fn clear_keys(&mut self) {
+            if let Many::Data(data) = self.0 {
+                data.iter_mut().for_each(|node| node.graph_clear_keys(true));
+            }
+        }
+    }
+
+    pub trait UnknownChildren {
+        fn clear_keys(&mut self);
+    }
+
+    impl<R: ?Sized> UnknownChildren for &mut ChildrenOf<'_, R> {
+        fn clear_keys(&mut self) {}
+    }
+
+    pub trait UnknownNodes<'a> {
+        fn nodes(&self) -> Option<Vec<&'a dyn Graph>>;
+    }
+
+    impl<'a, R: ?Sized> UnknownNodes<'a> for &NodesOf<'a, R> {
+        fn nodes(&self) -> Option<Vec<&'a dyn Graph>> {
+            None
+        }
+    }
+}
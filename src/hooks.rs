@@ -0,0 +1,156 @@
+//! Lifecycle hooks for entities
+//!
+//! Implement [`EntityHooks`] to run code when an entity is saved, fetched or
+//! deleted, such as normalizing fields, invalidating caches or keeping
+//! denormalized labels up to date. All the callbacks default to doing nothing,
+//! so an empty `impl EntityHooks for User {}` is enough to opt in.
+//!
+//! Repositories call the hooks of entities deriving `Entity` with
+//! `#[entity(hooks)]`, through the [`Lifecycle`] the derive implements.
+//! `Entity`, `EntityLabel` and `Many` forward the hooks to their fetched data.
+
+use crate::version::Versioned;
+use crate::{Entity, EntityLabel, Many, Result};
+
+/// What repositories do to an entity around reading and writing it
+///
+/// Repositories call [`before_insert`](Lifecycle::before_insert) and
+/// [`before_update`](Lifecycle::before_update) right before writing the
+/// entity, [`after_fetch`](Lifecycle::after_fetch) on every entity they read
+/// and [`before_delete`](Lifecycle::before_delete) with the stored entity
+/// before deleting it. `#[derive(Entity)]` implements it for every entity,
+/// touching the timestamps of the ones marked with `#[entity(timestamps)]`,
+/// checking the `#[version]` of versioned ones and calling the
+/// [`EntityHooks`] of the ones marked with `#[entity(hooks)]`; entities
+/// implementing `Keyed` by hand opt in with an empty `impl Lifecycle for User {}`.
+pub trait Lifecycle {
+    /// Whether repositories deleting by key read the entity first to call
+    /// [`before_delete`](Lifecycle::before_delete), which backends that can
+    /// delete without reading it skip otherwise
+    const FETCH_BEFORE_DELETE: bool = false;
+
+    /// Called before the entity is inserted
+    ///
+    /// Returning an error aborts the insert.
+    fn before_insert(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before the entity is updated
+    ///
+    /// Returning an error aborts the update.
+    fn before_update(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns the entity as `Versioned` if its updates are guarded by its version
+    ///
+    /// Repositories then only update the stored entity when it holds the same
+    /// version, bumping it, and fail with `Error::StaleVersion` otherwise.
+    fn versioned(&mut self) -> Option<&mut dyn Versioned> {
+        None
+    }
+
+    /// Called after the entity was read
+    fn after_fetch(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with the stored entity before it is deleted
+    ///
+    /// Returning an error aborts the delete.
+    fn before_delete(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Callbacks invoked around the persistence of an entity
+pub trait EntityHooks {
+    /// Called before the entity is inserted or updated
+    ///
+    /// Returning an error aborts the save.
+    fn before_save(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after the entity was fetched from the database
+    fn after_fetch(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before the entity is deleted
+    ///
+    /// Returning an error aborts the delete.
+    fn before_delete(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<K, T: EntityHooks> EntityHooks for Entity<K, T> {
+    fn before_save(&mut self) -> Result<()> {
+        match self {
+            Entity::Data(data) => data.before_save(),
+            _ => Ok(()),
+        }
+    }
+
+    fn after_fetch(&mut self) -> Result<()> {
+        match self {
+            Entity::Data(data) => data.after_fetch(),
+            _ => Ok(()),
+        }
+    }
+
+    fn before_delete(&self) -> Result<()> {
+        match self {
+            Entity::Data(data) => data.before_delete(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<K, T: EntityHooks, L> EntityHooks for EntityLabel<K, T, L> {
+    fn before_save(&mut self) -> Result<()> {
+        match self {
+            EntityLabel::Data(data) => data.before_save(),
+            _ => Ok(()),
+        }
+    }
+
+    fn after_fetch(&mut self) -> Result<()> {
+        match self {
+            EntityLabel::Data(data) => data.after_fetch(),
+            _ => Ok(()),
+        }
+    }
+
+    fn before_delete(&self) -> Result<()> {
+        match self {
+            EntityLabel::Data(data) => data.before_delete(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T: EntityHooks> EntityHooks for Many<T> {
+    fn before_save(&mut self) -> Result<()> {
+        match self {
+            Many::Data(data) => data.iter_mut().try_for_each(EntityHooks::before_save),
+            _ => Ok(()),
+        }
+    }
+
+    fn after_fetch(&mut self) -> Result<()> {
+        match self {
+            Many::Data(data) => data.iter_mut().try_for_each(EntityHooks::after_fetch),
+            _ => Ok(()),
+        }
+    }
+
+    fn before_delete(&self) -> Result<()> {
+        match self {
+            Many::Data(data) => data.iter().try_for_each(EntityHooks::before_delete),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Interned strings for labels repeated across many entities
+//!
+//! An [`InternedStr`] is a reference counted string shared through a global
+//! pool, so the same label loaded into thousands of `EntityLabel`s is stored
+//! only once. It implements `Display`, so `Label` and `Tagged` work over it
+//! like they do over `String`.
+//!
+//! ```
+//! use dbent::intern::InternedStr;
+//!
+//! let a = InternedStr::new("Brazil");
+//! let b = InternedStr::from(String::from("Brazil"));
+//! assert!(InternedStr::ptr_eq(&a, &b));
+//! ```
+
+use core::{borrow::Borrow, fmt, hash, ops::Deref};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+use crate::{normalize_label, NormalizedLabel};
+
+fn pool() -> &'static Mutex<HashSet<Arc<str>>> {
+    static POOL: OnceLock<Mutex<HashSet<Arc<str>>>> = OnceLock::new();
+    POOL.get_or_init(Default::default)
+}
+
+/// A string shared through the global intern pool
+#[derive(Clone)]
+pub struct InternedStr(Arc<str>);
+
+impl InternedStr {
+    /// Returns the interned version of `value`, adding it to the pool if needed
+    pub fn new(value: &str) -> Self {
+        let mut pool = pool().lock().unwrap_or_else(|err| err.into_inner());
+        match pool.get(value) {
+            Some(interned) => Self(interned.clone()),
+            None => {
+                let interned: Arc<str> = Arc::from(value);
+                pool.insert(interned.clone());
+                Self(interned)
+            },
+        }
+    }
+
+    /// Returns the string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true if both point to the same pooled string
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+
+    /// Returns how many strings are in the pool
+    pub fn pool_size() -> usize {
+        pool().lock().unwrap_or_else(|err| err.into_inner()).len()
+    }
+
+    /// Removes the strings that are no longer used from the pool
+    pub fn purge() {
+        pool()
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .retain(|interned| Arc::strong_count(interned) > 1);
+    }
+}
+
+impl Deref for InternedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for InternedStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for InternedStr {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for InternedStr {
+    fn eq(&self, other: &Self) -> bool {
+        Self::ptr_eq(self, other) || self.0 == other.0
+    }
+}
+
+impl Eq for InternedStr {}
+
+impl PartialEq<str> for InternedStr {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for InternedStr {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialOrd for InternedStr {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InternedStr {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl hash::Hash for InternedStr {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl Default for InternedStr {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl NormalizedLabel for InternedStr {
+    fn normalized(&self) -> Cow<'_, str> {
+        normalize_label(&self.0)
+    }
+}
+
+impl fmt::Display for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Debug for InternedStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl From<&str> for InternedStr {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for InternedStr {
+    fn from(value: String) -> Self {
+        Self::new(&value)
+    }
+}
+
+impl From<InternedStr> for String {
+    fn from(value: InternedStr) -> Self {
+        value.0.to_string()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for InternedStr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for InternedStr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = InternedStr;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                Ok(InternedStr::new(value))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl FromSql for InternedStr {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_str().map(InternedStr::new)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl ToSql for InternedStr {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(&*self.0))
+    }
+}
@@ -0,0 +1,175 @@
+//! Hydrating entity graphs from JSON trees
+//!
+//! REST APIs usually send related entities inline, as objects, or by their
+//! keys, as scalars. [`from_json_tree`] deserializes such a tree, reading the
+//! relation fields by their shape instead of their tagged representation:
+//!
+//! - for `Entity` fields, objects become Data, scalars become Keys and `null`
+//!   becomes None
+//! - for `Many` fields, arrays become Data and `null` becomes None
+//!
+//! ```
+//! use dbent::prelude::*;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Author {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Book {
+//!     id: Key<Int>,
+//!     author: Entity<Int, Author>,
+//!     editor: Entity<Int, Author>,
+//! }
+//!
+//! let json = serde_json::json!({ "id": 1, "author": { "id": 2, "name": "Ann" }, "editor": 3 });
+//! let book: Book = dbent::json::from_json_tree(json).unwrap();
+//! assert_eq!(book.author.data().unwrap().name, "Ann");
+//! assert!(matches!(book.editor, Entity::Key(Key(Some(3)))));
+//! ```
+
+use serde::de::{
+    self,
+    DeserializeOwned,
+    DeserializeSeed,
+    EnumAccess,
+    IntoDeserializer,
+    Unexpected,
+    VariantAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+use serde_json::{Error, Map, Value};
+
+/// Deserializes `T` from a JSON tree, reading relations by their shape
+pub fn from_json_tree<T: DeserializeOwned>(value: Value) -> crate::Result<T> {
+    Ok(T::deserialize(Tree(value))?)
+}
+
+const ENTITY_VARIANTS: &[&str] = &["Key", "Data", "None"];
+const MANY_VARIANTS: &[&str] = &["Data", "NotFetched", "None"];
+
+/// A JSON value whose nested relations are read by their shape
+struct Tree(Value);
+
+impl Tree {
+    /// Returns the variant of an Entity or Many given by the shape of the value
+    fn relation_variant(&self, name: &str, variants: &[&str]) -> Option<&'static str> {
+        match (name, variants, &self.0) {
+            ("Entity", ENTITY_VARIANTS, Value::Null) | ("Many", MANY_VARIANTS, Value::Null) => Some("None"),
+            ("Entity", ENTITY_VARIANTS, Value::Object(_)) => Some("Data"),
+            ("Entity", ENTITY_VARIANTS, _) => Some("Key"),
+            ("Many", MANY_VARIANTS, Value::Array(_)) => Some("Data"),
+            _ => None,
+        }
+    }
+}
+
+fn unexpected(value: &Value) -> Unexpected<'_> {
+    match value {
+        Value::Null => Unexpected::Unit,
+        Value::Bool(value) => Unexpected::Bool(*value),
+        Value::Number(_) => Unexpected::Other("number"),
+        Value::String(value) => Unexpected::Str(value),
+        Value::Array(_) => Unexpected::Seq,
+        Value::Object(_) => Unexpected::Map,
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Tree {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Array(values) => visitor.visit_seq(de::value::SeqDeserializer::new(values.into_iter().map(Tree))),
+            Value::Object(map) => visitor.visit_map(de::value::MapDeserializer::new(map.into_iter().map(|(key, value)| (key, Tree(value))))),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        if let Some(variant) = self.relation_variant(name, variants) {
+            let value = (variant != "None").then_some(self);
+            return visitor.visit_enum(TreeEnum { variant: variant.to_owned(), value });
+        }
+
+        match self.0 {
+            Value::String(variant) => visitor.visit_enum(TreeEnum { variant, value: None }),
+            Value::Object(map) if map.len() == 1 => {
+                let (variant, value) = map.into_iter().next().expect("map has one entry");
+                visitor.visit_enum(TreeEnum { variant, value: Some(Tree(value)) })
+            },
+            value => Err(de::Error::invalid_type(unexpected(&value), &"a string or a map with a single key")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Tree {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// An enum variant with its content, if any
+struct TreeEnum {
+    variant: String,
+    value: Option<Tree>,
+}
+
+impl<'de> EnumAccess<'de> for TreeEnum {
+    type Error = Error;
+    type Variant = TreeVariant;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, TreeVariant(self.value)))
+    }
+}
+
+/// The content of an enum variant, if any
+struct TreeVariant(Option<Tree>);
+
+impl<'de> VariantAccess<'de> for TreeVariant {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.0 {
+            None | Some(Tree(Value::Null)) => Ok(()),
+            Some(Tree(value)) => Err(de::Error::invalid_type(unexpected(&value), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(self.0.unwrap_or(Tree(Value::Null)))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self.0.unwrap_or(Tree(Value::Array(Vec::new()))), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self.0.unwrap_or(Tree(Value::Object(Map::new()))), visitor)
+    }
+}
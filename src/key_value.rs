@@ -0,0 +1,166 @@
+//! Runtime-typed keys
+//!
+//! [`KeyValue`] carries a key of any of the supported key types, for generic
+//! code like admin screens that handles entities of unknown types. Typed
+//! `Key`s convert into it and back, failing with `Error::KeyTypeMismatch`
+//! when the value is of another type.
+//!
+//! ```
+//! use dbent::Key;
+//! use dbent::key_value::KeyValue;
+//!
+//! let value = KeyValue::from(Key::new(7i64));
+//! assert_eq!(value, KeyValue::Int(7));
+//! assert_eq!(Key::<i64>::try_from(value).unwrap(), Key::new(7));
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use crate::{Error, Key, Result};
+
+/// A key value of a type only known at runtime
+///
+/// Serializes as the inner value, with Null as `null`. When deserializing,
+/// strings always become Text, so UUIDs and text keys can't be told apart.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(untagged))]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub enum KeyValue {
+    /// An integer key
+    Int(i64),
+    /// A text key
+    Text(String),
+    /// A UUID key
+    #[cfg(feature = "uuid")]
+    Uuid(uuid::Uuid),
+    /// A binary key
+    Bytes(Vec<u8>),
+    /// An unset key
+    #[default]
+    Null,
+}
+
+impl KeyValue {
+    /// Is this a Null variant?
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+}
+
+/// Writes keys the way `Key` does, with binary keys in lowercase hex
+impl fmt::Display for KeyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyValue::Int(value) => write!(f, "{value}"),
+            KeyValue::Text(value) => write!(f, "{value}"),
+            #[cfg(feature = "uuid")]
+            KeyValue::Uuid(value) => write!(f, "{value}"),
+            KeyValue::Bytes(value) => write!(f, "{}", Key::new(value).hex()),
+            KeyValue::Null => write!(f, "None"),
+        }
+    }
+}
+
+/// Implements the conversions between KeyValue and Keys of types that map to
+/// a variant without loss
+macro_rules! impl_key_value {
+    ($variant:ident: $($ty:ty),*) => {
+        $(
+            impl From<Key<$ty>> for KeyValue {
+                fn from(key: Key<$ty>) -> Self {
+                    key.0.map_or(KeyValue::Null, |value| KeyValue::$variant(value.into()))
+                }
+            }
+
+            impl TryFrom<KeyValue> for Key<$ty> {
+                type Error = Error;
+
+                fn try_from(value: KeyValue) -> Result<Self> {
+                    match value {
+                        KeyValue::$variant(value) => value.try_into().map(Key::new).map_err(|_| Error::KeyOutOfRange),
+                        KeyValue::Null => Ok(Key(None)),
+                        _ => Err(Error::KeyTypeMismatch),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_key_value!(Int: i8, i16, i32, i64, u8, u16, u32);
+impl_key_value!(Text: String);
+impl_key_value!(Bytes: Vec<u8>);
+#[cfg(feature = "uuid")]
+impl_key_value!(Uuid: uuid::Uuid);
+
+/// Implements the conversions between KeyValue and Keys of integer types that
+/// may not fit in an `i64`, like `Int`
+macro_rules! impl_key_value_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl TryFrom<Key<$ty>> for KeyValue {
+                type Error = Error;
+
+                fn try_from(key: Key<$ty>) -> Result<Self> {
+                    match key.0 {
+                        Some(value) => i64::try_from(value).map(KeyValue::Int).map_err(|_| Error::KeyOutOfRange),
+                        None => Ok(KeyValue::Null),
+                    }
+                }
+            }
+
+            impl TryFrom<KeyValue> for Key<$ty> {
+                type Error = Error;
+
+                fn try_from(value: KeyValue) -> Result<Self> {
+                    match value {
+                        KeyValue::Int(value) => <$ty>::try_from(value).map(Key::new).map_err(|_| Error::KeyOutOfRange),
+                        KeyValue::Null => Ok(Key(None)),
+                        _ => Err(Error::KeyTypeMismatch),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_key_value_unsigned!(u64, usize);
+
+impl From<&str> for KeyValue {
+    fn from(value: &str) -> Self {
+        KeyValue::Text(value.to_owned())
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl ToSql for KeyValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        match self {
+            KeyValue::Int(value) => value.to_sql(),
+            KeyValue::Text(value) => value.to_sql(),
+            #[cfg(feature = "uuid")]
+            KeyValue::Uuid(value) => value.to_sql(),
+            KeyValue::Bytes(value) => value.to_sql(),
+            KeyValue::Null => rusqlite::types::Null.to_sql(),
+        }
+    }
+}
+
+/// Reads integer, text and blob columns, so UUIDs are read as Bytes
+#[cfg(feature = "rusqlite")]
+impl FromSql for KeyValue {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Integer(value) => Ok(KeyValue::Int(value)),
+            ValueRef::Text(_) => value.as_str().map(|text| KeyValue::Text(text.to_owned())),
+            ValueRef::Blob(value) => Ok(KeyValue::Bytes(value.to_vec())),
+            ValueRef::Null => Ok(KeyValue::Null),
+            ValueRef::Real(_) => Err(FromSqlError::InvalidType),
+        }
+    }
+}
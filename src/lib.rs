@@ -2,6 +2,10 @@
 #![deny(missing_docs)]
 
 use core::fmt;
+use core::hash::Hash;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
 use thiserror::Error;
 
 #[cfg(feature = "rusqlite")]
@@ -14,11 +18,105 @@ use rusqlite::types::{
 };
 
 #[cfg(feature = "serde")]
-use serde::{Serialize, Deserialize};
+use ::serde::{Serialize, Deserialize};
+
+pub mod audit;
+pub mod changeset;
+pub mod cipher;
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "decimal")]
+pub mod decimal;
+pub mod denormalized;
+#[cfg(feature = "deterministic")]
+pub mod deterministic;
+pub mod dto;
+pub mod export;
+pub mod external_id;
+pub mod fixed_str;
+#[cfg(feature = "json")]
+pub mod format;
+pub mod graph;
+pub mod hooks;
+#[cfg(feature = "intern")]
+pub mod intern;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod key_value;
+pub mod limits;
+pub mod localized;
+pub mod observe;
+pub mod once;
+pub mod outbox;
+pub mod poly;
+pub mod position;
+pub mod readonly;
+pub mod reads;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod repository;
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod snapshot;
+pub mod snowflake;
+pub mod stable_hash;
+pub mod sql;
+#[cfg(feature = "rusqlite")]
+pub mod sqlite;
+#[cfg(feature = "async")]
+pub mod stream;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod timestamps;
+mod trace;
+pub mod version;
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "wire")]
+pub mod wire;
 
 #[cfg(test)]
 mod tests;
 
+/// Derives `Keyed` and `Label` under names that don't clash with other
+/// crates, invoked as `#[derive(dbent::Keyed, dbent::Labeled)]`
+#[cfg(feature = "derive")]
+pub use dbent_derive::{Keyed, Labeled};
+
+/// Expands to the items with the `rusqlite` feature, for derived code
+#[cfg(feature = "rusqlite")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_rusqlite {
+    ($($item:item)*) => { $($item)* };
+}
+
+/// Expands to the items with the `rusqlite` feature, for derived code
+#[cfg(not(feature = "rusqlite"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_rusqlite {
+    ($($item:item)*) => {};
+}
+
+/// Expands to the items with the `testing` feature, for derived code
+#[cfg(feature = "testing")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_testing {
+    ($($item:item)*) => { $($item)* };
+}
+
+/// Expands to the items with the `testing` feature, for derived code
+#[cfg(not(feature = "testing"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_testing {
+    ($($item:item)*) => {};
+}
+
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
 //<<>><=========================  TRAITS  ===========================><<>>//
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
@@ -32,6 +130,26 @@ pub trait Keyed {
     fn key(&self) -> Result<&Key<Self::KeyType>>;
 }
 
+/// Trait for entities whose key can be changed, e.g. to write back the key
+/// assigned by the database on insert
+pub trait KeyedMut: Keyed {
+    /// Returns the mutable Key for the Entity
+    fn key_mut(&mut self) -> Result<&mut Key<Self::KeyType>>;
+}
+
+/// Trait for entities whose key is always reachable, like the structs
+/// deriving `Entity`, so reading it needs no `?`
+pub trait KeyedInfallible: Keyed {
+    /// Returns the Key for the Entity
+    fn key_infallible(&self) -> &Key<Self::KeyType>;
+}
+
+impl<T: KeyedMut + ?Sized> KeyedMut for Box<T> {
+    fn key_mut(&mut self) -> Result<&mut Key<Self::KeyType>> {
+        (**self).key_mut()
+    }
+}
+
 /// Trait for entities that optionally have a label defined
 ///
 /// This is needed for using EntityLabels.
@@ -41,16 +159,166 @@ pub trait Label {
 
     /// Returns the Label for the Entity
     fn label(&self) -> Result<&Self::LabelType>;
+
+    /// Returns the label text used by the `Tagged` methods, the displayed
+    /// Label by default
+    ///
+    /// `Tagged` is implemented for all `Keyed` and `Label` entities, so it
+    /// can't be implemented by hand; override this instead to customize the
+    /// label of their Tags, e.g. composing it with a code, or borrowing a
+    /// `&'static str` label instead of allocating.
+    fn tag_label(&self) -> Result<Cow<'static, str>>
+    where
+        Self::LabelType: fmt::Display,
+    {
+        self.label().map(|label| Cow::Owned(label.to_string()))
+    }
+}
+
+macro_rules! impl_keyed_label_deref {
+    ($($ty:ty),*) => {
+        $(
+            impl<T: Keyed + ?Sized> Keyed for $ty {
+                type KeyType = T::KeyType;
+
+                fn key(&self) -> Result<&Key<Self::KeyType>> {
+                    (**self).key()
+                }
+            }
+
+            impl<T: KeyedInfallible + ?Sized> KeyedInfallible for $ty {
+                fn key_infallible(&self) -> &Key<Self::KeyType> {
+                    (**self).key_infallible()
+                }
+            }
+
+            impl<T: Label + ?Sized> Label for $ty {
+                type LabelType = T::LabelType;
+
+                fn label(&self) -> Result<&Self::LabelType> {
+                    (**self).label()
+                }
+
+                fn tag_label(&self) -> Result<Cow<'static, str>>
+                where
+                    Self::LabelType: fmt::Display,
+                {
+                    (**self).tag_label()
+                }
+            }
+        )*
+    };
+}
+
+impl_keyed_label_deref!(&T, Box<T>, std::rc::Rc<T>, std::sync::Arc<T>);
+
+/// Trait for labels compared in a normalized form, for lookups and search
+///
+/// It is implemented for the string types and, through their label, for
+/// entities whose label type implements it. Entities whose label can't be
+/// retrieved normalize to an empty string.
+pub trait NormalizedLabel {
+    /// Returns the label trimmed and case folded, and without accents with
+    /// the `unaccent` feature
+    fn normalized(&self) -> Cow<'_, str>;
+}
+
+/// Returns `text` trimmed and case folded, and without accents with the
+/// `unaccent` feature, borrowing it when already normalized
+pub fn normalize_label(text: &str) -> Cow<'_, str> {
+    let text = text.trim();
+
+    #[cfg(feature = "unaccent")]
+    {
+        use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+        if !text.is_ascii() {
+            return Cow::Owned(text.nfd().filter(|&c| !is_combining_mark(c)).flat_map(char::to_lowercase).collect());
+        }
+    }
+
+    if text.chars().any(char::is_uppercase) {
+        Cow::Owned(text.to_lowercase())
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+impl NormalizedLabel for str {
+    fn normalized(&self) -> Cow<'_, str> {
+        normalize_label(self)
+    }
+}
+
+impl NormalizedLabel for String {
+    fn normalized(&self) -> Cow<'_, str> {
+        normalize_label(self)
+    }
+}
+
+impl NormalizedLabel for Cow<'_, str> {
+    fn normalized(&self) -> Cow<'_, str> {
+        normalize_label(self)
+    }
+}
+
+impl<T> NormalizedLabel for T
+where
+    T: Label,
+    T::LabelType: NormalizedLabel,
+{
+    fn normalized(&self) -> Cow<'_, str> {
+        self.label().map(NormalizedLabel::normalized).unwrap_or_default()
+    }
 }
 
 /// Struct that holds both key and label for convenience
+///
+/// Both are `Cow`s so tags built from `&'static str` labels, like those of
+/// lookup tables backed by enums overriding [`Label::tag_label`], borrow
+/// them instead of allocating.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
 pub struct Tag {
     /// Entity key
-    pub key: String,
+    pub key: Cow<'static, str>,
     /// Entity label
-    pub label: String,
+    pub label: Cow<'static, str>,
+}
+
+impl Tag {
+    /// Creates a new Tag
+    pub fn new(key: impl Into<Cow<'static, str>>, label: impl Into<Cow<'static, str>>) -> Self {
+        Self { key: key.into(), label: label.into() }
+    }
+}
+
+/// Tags are looked up by their normalized label
+impl NormalizedLabel for Tag {
+    fn normalized(&self) -> Cow<'_, str> {
+        normalize_label(&self.label)
+    }
+}
+
+/// Returns the text of a Tag key, or `Error::KeyMissing` if unset
+fn key_text<K: fmt::Display>(key: &Key<K>) -> Result<Cow<'static, str>> {
+    key.0.as_ref().map(|key| Cow::Owned(key.to_string())).ok_or(Error::KeyMissing)
+}
+
+/// Writes the text of a Tag key into `text`, reusing its buffer when owned
+/// instead of allocating a new one, or fails with `Error::KeyMissing` if unset
+fn write_key_text<K: fmt::Display>(text: &mut Cow<'static, str>, key: &Key<K>) -> Result<()> {
+    let value = key.0.as_ref().ok_or(Error::KeyMissing)?;
+    match text {
+        Cow::Owned(buffer) => {
+            use fmt::Write;
+
+            buffer.clear();
+            write!(buffer, "{value}").expect("a Display implementation returned an error unexpectedly");
+        }
+        text => *text = Cow::Owned(value.to_string()),
+    }
+    Ok(())
 }
 
 /// Convenience trait for returning both key and label as a Tag
@@ -58,10 +326,55 @@ pub struct Tag {
 /// There is a blanket implementation for all entities that
 /// implement both Keyed and Label
 pub trait Tagged {
-    /// Returns the Tag for the Entity
+    /// Returns the Tag for the Entity, failing with `Error::KeyMissing` when
+    /// the Key is unset, so no placeholder key ends up in stored tags or URLs
     fn tag(&self) -> Result<Tag>;
+    /// Returns the Tag for the Entity using `fallback_label` when the label
+    /// can't be retrieved, e.g. for partially fetched entities
+    fn tag_or(&self, fallback_label: &str) -> Result<Tag> {
+        self.tag().or_else(|err| match self.tag_parts().0 {
+            Some(key) => Ok(Tag::new(key, fallback_label.to_owned())),
+            None => Err(err),
+        })
+    }
+    /// Returns the Tag for the Entity using the key as label when the label
+    /// can't be retrieved
+    fn tag_lossy(&self) -> Result<Tag> {
+        self.tag().or_else(|err| match self.tag_parts().0 {
+            Some(key) => Ok(Tag::new(key.clone(), key)),
+            None => Err(err),
+        })
+    }
     /// The entity does not have a valid tag if it doesn't have a Key
     fn has_tag(&self) -> bool;
+    /// Returns whether the label can be retrieved, even without a Key
+    fn has_label(&self) -> bool {
+        self.tag_parts().1.is_some()
+    }
+    /// Returns the key and label texts, each None when unset or unavailable,
+    /// for rendering entities that only have one of them
+    ///
+    /// The default takes both from `tag`, so it has neither when `tag` fails.
+    fn tag_parts(&self) -> (Option<String>, Option<String>) {
+        match self.tag() {
+            Ok(tag) => (Some(tag.key.into_owned()), Some(tag.label.into_owned())),
+            Err(_) => (None, None),
+        }
+    }
+    /// Overwrites `tag` with the Tag for the Entity, writing the key into its
+    /// existing buffer, so refreshing a list of Tags doesn't allocate them anew
+    ///
+    /// The default replaces `tag` with the result of `tag`.
+    fn write_tag(&self, tag: &mut Tag) -> Result<()> {
+        *tag = self.tag()?;
+        Ok(())
+    }
+    /// Returns the Tag for the Entity, failing with `Error::KeyMissing` when
+    /// the Key is unset, like `tag`
+    #[deprecated(note = "`tag` fails with `Error::KeyMissing` for unset keys too")]
+    fn tag_strict(&self) -> Result<Tag> {
+        self.tag()
+    }
 }
 
 impl<K, T, L> Tagged for T
@@ -73,15 +386,248 @@ where
     fn tag(&self) -> Result<Tag> {
         Ok(
             Tag {
-                key: self.key()?.to_string(),
-                label: self.label()?.to_string(),
+                key: key_text(self.key()?)?,
+                label: self.tag_label()?,
             }
         )
     }
 
+    fn tag_or(&self, fallback_label: &str) -> Result<Tag> {
+        Ok(
+            Tag {
+                key: key_text(self.key()?)?,
+                label: self.tag_label().unwrap_or_else(|_| Cow::Owned(fallback_label.to_owned())),
+            }
+        )
+    }
+
+    fn tag_lossy(&self) -> Result<Tag> {
+        let key = key_text(self.key()?)?;
+        let label = self.tag_label().unwrap_or_else(|_| key.clone());
+        Ok(Tag { key, label })
+    }
+
+    fn write_tag(&self, tag: &mut Tag) -> Result<()> {
+        let key = self.key()?;
+        tag.label = self.tag_label()?;
+        write_key_text(&mut tag.key, key)
+    }
+
     fn has_tag(&self) -> bool {
         self.key().map(|v| v.is_some()).unwrap_or(false)
     }
+
+    fn has_label(&self) -> bool {
+        self.label().is_ok()
+    }
+
+    fn tag_parts(&self) -> (Option<String>, Option<String>) {
+        let key = self.key().ok().and_then(|key| key.as_ref().map(ToString::to_string));
+        let label = self.tag_label().ok().map(Cow::into_owned);
+        (key, label)
+    }
+}
+
+/// Convenience trait for collecting the Tags of many entities at once
+///
+/// There is a blanket implementation for slices of entities that
+/// implement Tagged, which also covers `Vec`s through deref
+pub trait Tags {
+    /// Returns the Tags for all entities, failing on the first error
+    fn tags(&self) -> Result<Vec<Tag>>;
+
+    /// Replaces the contents of `tags` with the Tags for all entities,
+    /// reusing the buffers of the Tags already in it
+    ///
+    /// Rebuilding a large picklist this way only allocates for the labels.
+    /// On error, `tags` holds the Tags written so far.
+    fn tags_into(&self, tags: &mut Vec<Tag>) -> Result<()>;
+}
+
+impl<T: Tagged> Tags for [T] {
+    fn tags(&self) -> Result<Vec<Tag>> {
+        self.iter().map(Tagged::tag).collect()
+    }
+
+    fn tags_into(&self, tags: &mut Vec<Tag>) -> Result<()> {
+        tags.truncate(self.len());
+        tags.resize_with(self.len(), Tag::default);
+        for (index, (entity, tag)) in self.iter().zip(tags.iter_mut()).enumerate() {
+            if let Err(err) = entity.write_tag(tag) {
+                tags.truncate(index);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A Tag with extra data for the UI, like a color, an icon or a sort rank
+///
+/// Returned by [`TagMeta::tag_ext`], it dereferences to the Tag, and is
+/// serialized as the Tag's fields with a `payload` field, `null` when unset.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct TagExt<P> {
+    /// The key and label
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub tag: Tag,
+    /// The extra data, if the entity has any
+    pub payload: Option<P>,
+}
+
+impl<P> TagExt<P> {
+    /// Creates a TagExt from a Tag and its payload
+    pub fn new(tag: Tag, payload: Option<P>) -> Self {
+        Self { tag, payload }
+    }
+
+    /// Drops the payload, returning the Tag
+    pub fn into_tag(self) -> Tag {
+        self.tag
+    }
+}
+
+impl<P> core::ops::Deref for TagExt<P> {
+    type Target = Tag;
+
+    fn deref(&self) -> &Tag {
+        &self.tag
+    }
+}
+
+impl<P> From<Tag> for TagExt<P> {
+    fn from(tag: Tag) -> Self {
+        Self { tag, payload: None }
+    }
+}
+
+/// Trait for entities giving extra data with their Tags, for dropdowns that
+/// need more than the key and label
+///
+/// ```
+/// use dbent::{Key, Keyed, Label, TagMeta};
+///
+/// struct Status {
+///     id: Key<u32>,
+///     name: String,
+///     color: &'static str,
+/// }
+///
+/// impl Keyed for Status {
+///     type KeyType = u32;
+///
+///     fn key(&self) -> dbent::Result<&Key<u32>> {
+///         Ok(&self.id)
+///     }
+/// }
+///
+/// impl Label for Status {
+///     type LabelType = String;
+///
+///     fn label(&self) -> dbent::Result<&String> {
+///         Ok(&self.name)
+///     }
+/// }
+///
+/// impl TagMeta for Status {
+///     type Payload = &'static str;
+///
+///     fn tag_payload(&self) -> Option<&'static str> {
+///         Some(self.color)
+///     }
+/// }
+///
+/// let status = Status { id: Key::new(1), name: "Open".to_owned(), color: "green" };
+/// let tag = status.tag_ext().unwrap();
+/// assert_eq!((&*tag.label, tag.payload), ("Open", Some("green")));
+/// ```
+pub trait TagMeta: Tagged {
+    /// The extra data
+    type Payload;
+
+    /// Returns the extra data, if the entity has any
+    fn tag_payload(&self) -> Option<Self::Payload>;
+
+    /// Returns the Tag with the extra data
+    fn tag_ext(&self) -> Result<TagExt<Self::Payload>> {
+        Ok(TagExt { tag: self.tag()?, payload: self.tag_payload() })
+    }
+}
+
+/// Object-safe mirror of Keyed for holding different entity types together
+///
+/// Keyed can't be made into an object because of its associated type, so
+/// this returns the key as a `Display` instead. There is a blanket
+/// implementation for all entities whose key type implements Display.
+pub trait AnyKeyed {
+    /// Returns the Key value for the Entity, or None if unset
+    fn any_key(&self) -> Result<Option<&dyn fmt::Display>>;
+}
+
+impl<T> AnyKeyed for T
+where
+    T: Keyed,
+    T::KeyType: fmt::Display,
+{
+    fn any_key(&self) -> Result<Option<&dyn fmt::Display>> {
+        Ok(self.key()?.0.as_ref().map(|key| key as &dyn fmt::Display))
+    }
+}
+
+/// Object-safe trait for entities with both a key and a Tag, e.g. for rows
+/// of generic UI tables holding different entity types
+///
+/// The concrete entity can be recovered with the downcast methods on
+/// `dyn AnyTagged`. There is a blanket implementation for all entities that
+/// implement both AnyKeyed and Tagged.
+pub trait AnyTagged: AnyKeyed + Tagged {
+    /// Returns the entity as Any for downcasting
+    fn as_any(&self) -> &dyn core::any::Any;
+    /// Returns the mutable entity as Any for downcasting
+    fn as_any_mut(&mut self) -> &mut dyn core::any::Any;
+    /// Converts the boxed entity into a boxed Any for downcasting
+    fn into_any(self: Box<Self>) -> Box<dyn core::any::Any>;
+}
+
+impl<T: AnyKeyed + Tagged + 'static> AnyTagged for T {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn core::any::Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn core::any::Any> {
+        self
+    }
+}
+
+impl dyn AnyTagged {
+    /// Is the entity of type `T`?
+    pub fn is<T: 'static>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    /// Returns the entity as `T` if it is of that type
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    /// Returns the mutable entity as `T` if it is of that type
+    pub fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+
+    /// Converts the boxed entity into `T`, or gives it back if it is of another type
+    pub fn downcast<T: 'static>(self: Box<Self>) -> core::result::Result<Box<T>, Box<Self>> {
+        if self.is::<T>() {
+            Ok(self.into_any().downcast().expect("type was checked"))
+        } else {
+            Err(self)
+        }
+    }
 }
 
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
@@ -90,12 +636,15 @@ where
 
 /// A newtype for defining a Key on entities
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
-#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
 pub struct Key<K>(pub Option<K>);
 
 impl<K> Key<K> {
-    /// Creates a new Key from a value `K`
-    pub fn new(value: K) -> Self {
+    /// The unset Key, for `const` and `static` entities
+    pub const NONE: Self = Self(None);
+
+    /// Creates a new Key from a value `K`, also in `const` contexts
+    pub const fn new(value: K) -> Self {
         Self(Some(value))
     }
 
@@ -108,6 +657,45 @@ impl<K> Key<K> {
     pub fn to_entity<T>(&self) -> Entity<K, T> where K: Clone {
         Entity::Key(self.clone())
     }
+
+    /// Returns the key value, panicking with `msg` and the key type if unset
+    #[track_caller]
+    pub fn expect(self, msg: &str) -> K {
+        match self.0 {
+            Some(value) => value,
+            None => panic!("{msg}: Key<{}> is unset", core::any::type_name::<K>()),
+        }
+    }
+
+    /// Returns the key value as a `Display`, or None if unset, for callers
+    /// that must not print the "None" of `Key`'s own `Display`
+    pub fn try_display(&self) -> Option<impl fmt::Display + '_>
+    where
+        K: fmt::Display,
+    {
+        self.0.as_ref()
+    }
+
+    /// Returns an adapter displaying the key value, or `placeholder` if unset
+    /// instead of "None"
+    pub fn display_or<'a>(&'a self, placeholder: &'a str) -> KeyDisplay<'a, K> {
+        KeyDisplay { key: self, placeholder }
+    }
+
+    /// Returns a hash of the key value that is the same across processes,
+    /// platforms and versions, for cache sharding and consistent hashing
+    ///
+    /// See [`stable_hash`] for the algorithm and the encoding of the values.
+    pub fn stable_hash(&self) -> u64
+    where
+        K: stable_hash::StableHash,
+    {
+        let mut hasher = stable_hash::StableHasher::new();
+        if let Some(value) = &self.0 {
+            value.stable_hash(&mut hasher);
+        }
+        hasher.finish()
+    }
 }
 
 impl<K> core::ops::Deref for Key<K> {
@@ -124,6 +712,125 @@ impl<K> core::ops::DerefMut for Key<K> {
     }
 }
 
+impl Key<Int> {
+    /// Returns the Key that follows this one
+    ///
+    /// An empty Key is followed by `1`, the first key handed out by
+    /// SQLite's rowid and AUTOINCREMENT columns. Fails with
+    /// `Error::KeyOverflow` for a Key of `Int::MAX`.
+    pub fn next(&self) -> Result<Self> {
+        match self.0 {
+            Some(value) => value.checked_add(1).map(Key::new).ok_or(Error::KeyOverflow),
+            None => Ok(Key::new(1)),
+        }
+    }
+}
+
+impl<K: AsRef<[u8]>> Key<K> {
+    /// Returns a Display adapter writing binary key values in lowercase hex
+    ///
+    /// Binary keys like `Key<Vec<u8>>` or `Key<[u8; 16]>` are stored as
+    /// BLOBs; this is their text form for URLs and logs.
+    pub fn hex(&self) -> HexKey<'_> {
+        HexKey(self.0.as_ref().map(AsRef::as_ref))
+    }
+}
+
+impl<K: TryFrom<Vec<u8>>> Key<K> {
+    /// Parses a binary Key from hex text, in either case
+    pub fn from_hex(text: &str) -> Result<Self> {
+        let invalid = || Error::InvalidHex(text.to_owned());
+        let text = text.trim();
+        if !text.len().is_multiple_of(2) {
+            return Err(invalid());
+        }
+
+        let bytes = (0..text.len())
+            .step_by(2)
+            .map(|i| text.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+        K::try_from(bytes).map(Key::new).map_err(|_| invalid())
+    }
+}
+
+/// Hex representation of a binary Key, returned by [`Key::hex`]
+#[derive(Clone, Copy, Debug)]
+pub struct HexKey<'a>(Option<&'a [u8]>);
+
+impl fmt::Display for HexKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(bytes) => bytes.iter().try_for_each(|byte| write!(f, "{byte:02x}")),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+/// A thread safe counter that hands out sequential `Int` Keys
+///
+/// Meant for in-memory and test backends that need to allocate keys
+/// the way the database would.
+#[derive(Debug)]
+pub struct KeyAllocator {
+    next: std::sync::atomic::AtomicUsize,
+}
+
+impl KeyAllocator {
+    /// Creates an allocator whose first Key will be `1`
+    pub const fn new() -> Self {
+        Self::starting_at(1)
+    }
+
+    /// Creates an allocator whose first Key will be `first`
+    pub const fn starting_at(first: Int) -> Self {
+        Self { next: std::sync::atomic::AtomicUsize::new(first) }
+    }
+
+    /// Allocates the next Key
+    ///
+    /// Fails with `Error::KeyOverflow` once the keys up to `Int::MAX` are
+    /// used up, without allocating `Int::MAX` itself.
+    pub fn next_key(&self) -> Result<Key<Int>> {
+        use std::sync::atomic::Ordering::Relaxed;
+        self.next
+            .fetch_update(Relaxed, Relaxed, |next| next.checked_add(1))
+            .map(Key::new)
+            .map_err(|_| Error::KeyOverflow)
+    }
+
+    /// Returns the Key that will be allocated next without allocating it
+    ///
+    /// Returns `None` once the allocator is exhausted and `next_key` would
+    /// fail with `Error::KeyOverflow`.
+    pub fn peek(&self) -> Option<Key<Int>> {
+        let next = self.next.load(std::sync::atomic::Ordering::Relaxed);
+        (next < Int::MAX).then(|| Key::new(next))
+    }
+
+    /// Makes sure keys allocated from now on come after `key`
+    ///
+    /// Useful when loading entities that already have keys assigned.
+    pub fn observe(&self, key: &Key<Int>) {
+        if let Some(value) = key.0 {
+            self.next.fetch_max(value.saturating_add(1), std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for KeyAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for KeyAllocator {
+    /// Returns an allocator that continues from where this one is
+    fn clone(&self) -> Self {
+        Self { next: std::sync::atomic::AtomicUsize::new(self.next.load(std::sync::atomic::Ordering::Relaxed)) }
+    }
+}
+
 impl<K: fmt::Display> fmt::Display for Key<K> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.0 {
@@ -133,6 +840,46 @@ impl<K: fmt::Display> fmt::Display for Key<K> {
     }
 }
 
+/// Displays a Key with a placeholder for unset keys, returned by `Key::display_or`
+#[derive(Clone, Copy, Debug)]
+pub struct KeyDisplay<'a, K> {
+    key: &'a Key<K>,
+    placeholder: &'a str,
+}
+
+impl<'a, K> KeyDisplay<'a, K> {
+    /// Sets the placeholder displayed for unset keys
+    pub fn placeholder(self, placeholder: &'a str) -> Self {
+        Self { placeholder, ..self }
+    }
+}
+
+impl<K: fmt::Display> fmt::Display for KeyDisplay<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.key.0 {
+            Some(value) => value.fmt(f),
+            None => f.write_str(self.placeholder),
+        }
+    }
+}
+
+/// Allows looking up `Key`s in maps and sets by their raw `Option` value,
+/// e.g. `map.get(&Some(5))`
+///
+/// The derived `Hash`, `Eq` and `Ord` of the newtype only take the inner
+/// `Option` into account, so they are consistent with it as required by `Borrow`.
+impl<K> core::borrow::Borrow<Option<K>> for Key<K> {
+    fn borrow(&self) -> &Option<K> {
+        &self.0
+    }
+}
+
+impl<K> From<K> for Key<K> {
+    fn from(value: K) -> Self {
+        Self::new(value)
+    }
+}
+
 impl<K> From<Option<K>> for Key<K> {
     fn from(value: Option<K>) -> Self {
         Self(value)
@@ -145,6 +892,32 @@ impl<K: Clone> From<&Option<K>> for Key<K> {
     }
 }
 
+/// Implements fallible conversions between Keys of integer types,
+/// e.g. from SQLite's `i64` rowids to `Int`
+macro_rules! impl_key_try_from {
+    ($from:ty => $($to:ty),*) => {
+        $(
+            impl TryFrom<Key<$from>> for Key<$to> {
+                type Error = Error;
+
+                fn try_from(key: Key<$from>) -> Result<Self> {
+                    key.0
+                        .map(<$to>::try_from)
+                        .transpose()
+                        .map(Key)
+                        .map_err(|_| Error::KeyOutOfRange)
+                }
+            }
+        )*
+    };
+}
+
+impl_key_try_from!(i32 => u32, i64, u64, usize);
+impl_key_try_from!(u32 => i32, i64, u64, usize);
+impl_key_try_from!(i64 => i32, u32, u64, usize);
+impl_key_try_from!(u64 => i32, u32, i64, usize);
+impl_key_try_from!(usize => i32, u32, i64, u64);
+
 #[cfg(feature = "rusqlite")]
 impl<K: FromSql> FromSql for Key<K> {
     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
@@ -163,30 +936,344 @@ impl<K: ToSql> ToSql for Key<K> {
 }
 
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
-//<<>><=========================  ENTITY  ===========================><<>>//
+//<<>><======================  SCOPED KEY  ==========================><<>>//
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
 
-/// Enum for defining a simple entity that will hold a Key
-/// or the created/fetched data
+/// A key value paired with the scope it belongs to, such as a tenant id
+///
+/// It is meant to be used as the type of a Key, i.e. `Key<ScopedKey<S, K>>`,
+/// so entities keep working with `Keyed` and `Entity` while the scope must be
+/// checked explicitly with [`Key::in_scope`] before resolving the row key.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
-pub enum Entity<K, T> {
-    /// Key of the entity
-    Key(Key<K>),
-    /// Created/Fetched data for the entity
-    Data(Box<T>),
-    #[default]
-    /// For when you have no data to fill or null from database
-    None,
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct ScopedKey<S, K> {
+    /// The scope the key belongs to
+    pub scope: S,
+    /// The row key within the scope
+    pub key: K,
 }
 
-impl<K, T> Keyed for Entity<K, T>
-where
-    T: Keyed<KeyType = K>,
-{
-    type KeyType = K;
+impl<S, K> ScopedKey<S, K> {
+    /// Creates a new ScopedKey
+    pub fn new(scope: S, key: K) -> Self {
+        Self { scope, key }
+    }
+}
 
-    fn key(&self) -> Result<&Key<Self::KeyType>> {
+impl<S: fmt::Display, K: fmt::Display> fmt::Display for ScopedKey<S, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.scope, self.key)
+    }
+}
+
+impl<S, K> Key<ScopedKey<S, K>> {
+    /// Creates a new Key from a scope and a row key
+    pub fn scoped(scope: S, key: K) -> Self {
+        Self::new(ScopedKey::new(scope, key))
+    }
+
+    /// Returns the scope of this Key if it is set
+    pub fn scope(&self) -> Option<&S> {
+        self.0.as_ref().map(|scoped| &scoped.scope)
+    }
+
+    /// Returns the row key if this Key belongs to `scope`
+    pub fn in_scope(&self, scope: &S) -> Result<&K> where S: PartialEq {
+        match &self.0 {
+            Some(scoped) if scoped.scope == *scope => Ok(&scoped.key),
+            Some(_) => Err(Error::ScopeMismatch),
+            None => Err(Error::KeyEmpty),
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl<S: ToSql, K: ToSql> Key<ScopedKey<S, K>> {
+    /// Returns the scope and key as parameters for two SQL columns
+    pub fn to_sql_pair(&self) -> [&dyn ToSql; 2] {
+        match &self.0 {
+            Some(scoped) => [&scoped.scope, &scoped.key],
+            None => [&rusqlite::types::Null, &rusqlite::types::Null],
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl<S: FromSql, K: FromSql> Key<ScopedKey<S, K>> {
+    /// Reads the Key from the scope and key columns of a row
+    ///
+    /// The Key is empty if any of the columns is NULL.
+    pub fn from_sql_pair<I: rusqlite::RowIndex>(row: &rusqlite::Row<'_>, scope: I, key: I) -> rusqlite::Result<Self> {
+        let scope: Option<S> = row.get(scope)?;
+        let key: Option<K> = row.get(key)?;
+        Ok(Self(scope.zip(key).map(|(scope, key)| ScopedKey::new(scope, key))))
+    }
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=======================  CI STRING  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+/// A string key value that ignores case and surrounding whitespace
+///
+/// Meant for natural keys like country or currency codes, i.e.
+/// `Key<CiString>`, where `"us"` and `" US "` must resolve to the same row.
+/// The trimmed text is kept as given, so it displays and serializes as
+/// written, but it is written to SQL in lowercase, the form `Eq` compares,
+/// so lookups in the database match the same values as in memory. Values
+/// read back from SQL are the lowercase ones.
+#[derive(Clone, Default, Debug)]
+pub struct CiString(String);
+
+impl CiString {
+    /// Creates a new CiString, trimming surrounding whitespace
+    pub fn new(value: impl AsRef<str>) -> Self {
+        Self(value.as_ref().trim().to_owned())
+    }
+
+    /// Returns the trimmed text as given
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the text in lowercase, the form used for comparisons and
+    /// written to SQL
+    pub fn to_lowercase(&self) -> String {
+        self.folded().collect()
+    }
+
+    /// Returns the characters compared by `Eq`, `Ord` and `Hash`
+    fn folded(&self) -> impl Iterator<Item = char> + '_ {
+        self.0.chars().flat_map(char::to_lowercase)
+    }
+}
+
+impl PartialEq for CiString {
+    fn eq(&self, other: &Self) -> bool {
+        self.folded().eq(other.folded())
+    }
+}
+
+impl Eq for CiString {}
+
+impl PartialEq<str> for CiString {
+    fn eq(&self, other: &str) -> bool {
+        *self == CiString::new(other)
+    }
+}
+
+impl PartialEq<&str> for CiString {
+    fn eq(&self, other: &&str) -> bool {
+        *self == CiString::new(other)
+    }
+}
+
+impl PartialOrd for CiString {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CiString {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.folded().cmp(other.folded())
+    }
+}
+
+impl core::hash::Hash for CiString {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        for c in self.folded() {
+            c.hash(state);
+        }
+    }
+}
+
+impl NormalizedLabel for CiString {
+    fn normalized(&self) -> Cow<'_, str> {
+        normalize_label(&self.0)
+    }
+}
+
+impl fmt::Display for CiString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl core::str::FromStr for CiString {
+    type Err = core::convert::Infallible;
+
+    fn from_str(value: &str) -> core::result::Result<Self, Self::Err> {
+        Ok(Self::new(value))
+    }
+}
+
+impl From<&str> for CiString {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for CiString {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<CiString> for String {
+    fn from(value: CiString) -> Self {
+        value.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for CiString {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for CiString {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl FromSql for CiString {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_str().map(Self::new)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl ToSql for CiString {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_lowercase()))
+    }
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=======================  SENSITIVE  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+/// A key value that is masked when displayed or debugged
+///
+/// Meant for entities keyed by identifiers that must not leak into logs, such
+/// as emails or tokens, i.e. `Key<Sensitive<String>>`. `Display` and `Debug`
+/// print `***`, while comparisons, hashing, serde and SQL conversions use the
+/// real value, which [`expose`](Sensitive::expose) returns.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Sensitive<K>(K);
+
+impl<K> Sensitive<K> {
+    /// The text printed instead of the value
+    pub const MASK: &'static str = "***";
+
+    /// Wraps the value
+    pub const fn new(value: K) -> Self {
+        Self(value)
+    }
+
+    /// Returns the real value
+    pub fn expose(&self) -> &K {
+        &self.0
+    }
+
+    /// Returns the real value, consuming the wrapper
+    pub fn into_inner(self) -> K {
+        self.0
+    }
+}
+
+impl<K> From<K> for Sensitive<K> {
+    fn from(value: K) -> Self {
+        Self(value)
+    }
+}
+
+impl<K> fmt::Display for Sensitive<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(Self::MASK)
+    }
+}
+
+impl<K> fmt::Debug for Sensitive<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(Self::MASK)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl<K: FromSql> FromSql for Sensitive<K> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        K::column_result(value).map(Self)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl<K: ToSql> ToSql for Sensitive<K> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        if MASKED.with(std::cell::Cell::get) {
+            return Ok(ToSqlOutput::from(Self::MASK));
+        }
+        self.0.to_sql()
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+thread_local! {
+    /// Whether values are converted by [`to_sql_masked`], so [`Sensitive`]
+    /// values convert to their mask
+    static MASKED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Restores the masking state when dropped, even if converting panics
+#[cfg(feature = "rusqlite")]
+struct MaskGuard(bool);
+
+#[cfg(feature = "rusqlite")]
+impl Drop for MaskGuard {
+    fn drop(&mut self) {
+        MASKED.with(|masked| masked.set(self.0));
+    }
+}
+
+/// Converts the value to SQL for tracing, with any [`Sensitive`] value in it
+/// converted to [`Sensitive::MASK`]
+#[cfg(feature = "rusqlite")]
+pub(crate) fn to_sql_masked<K: ToSql + ?Sized>(value: &K) -> rusqlite::Result<ToSqlOutput<'_>> {
+    let _guard = MaskGuard(MASKED.with(|masked| masked.replace(true)));
+    value.to_sql()
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  ENTITY  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+/// Enum for defining a simple entity that will hold a Key
+/// or the created/fetched data
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Entity<K, T> {
+    /// Key of the entity
+    Key(Key<K>),
+    /// Created/Fetched data for the entity
+    Data(Box<T>),
+    #[default]
+    /// For when you have no data to fill or null from database
+    None,
+}
+
+impl<K, T> Keyed for Entity<K, T>
+where
+    T: Keyed<KeyType = K>,
+{
+    type KeyType = K;
+
+    fn key(&self) -> Result<&Key<Self::KeyType>> {
         match self {
             Entity::Key(key) => Ok(key),
             Entity::Data(data) => data.key(),
@@ -195,6 +1282,19 @@ where
     }
 }
 
+impl<K, T> KeyedMut for Entity<K, T>
+where
+    T: KeyedMut<KeyType = K>,
+{
+    fn key_mut(&mut self) -> Result<&mut Key<Self::KeyType>> {
+        match self {
+            Entity::Key(key) => Ok(key),
+            Entity::Data(data) => data.key_mut(),
+            Entity::None => Err(Error::EntityEmpty),
+        }
+    }
+}
+
 impl<K, T> Entity<K, T> {
     /// Returns the data if it exists and was fetched/created
     pub fn data(&self) -> Result<&T> {
@@ -205,18 +1305,1159 @@ impl<K, T> Entity<K, T> {
         }
     }
 
-    /// Returns the mutable data if it exists and was fetched/created
-    pub fn data_mut(&mut self) -> Result<&mut T> {
+    /// Returns the mutable data if it exists and was fetched/created
+    pub fn data_mut(&mut self) -> Result<&mut T> {
+        match self {
+            Entity::Data(ref mut data) => Ok(data),
+            Entity::Key(_) => Err(Error::EntityNotFetched),
+            Entity::None => Err(Error::EntityEmpty),
+        }
+    }
+
+    /// Creates an Entity from a nullable key column, None for a NULL key
+    pub fn from_key_nullable(key: Key<K>) -> Self {
+        match key.0 {
+            Some(_) => Entity::Key(key),
+            None => Entity::None,
+        }
+    }
+
+    /// Converts into the data if it exists and was fetched/created
+    pub fn into_data(self) -> Result<T> {
+        match self {
+            Entity::Data(data) => Ok(*data),
+            Entity::Key(_) => Err(Error::EntityNotFetched),
+            Entity::None => Err(Error::EntityEmpty),
+        }
+    }
+
+    /// Converts into the Key, taken from the data if it was fetched/created
+    pub fn into_key(self) -> Result<Key<K>>
+    where
+        K: Clone,
+        T: Keyed<KeyType = K>,
+    {
+        match self {
+            Entity::Key(key) => Ok(key),
+            Entity::Data(data) => data.key().cloned(),
+            Entity::None => Err(Error::EntityEmpty),
+        }
+    }
+
+    /// Returns the data, panicking with `msg`, the entity type and the variant if not fetched/created
+    #[track_caller]
+    pub fn expect_data(&self, msg: &str) -> &T {
+        match self {
+            Entity::Data(data) => data,
+            Entity::Key(_) => panic!("{msg}: Entity<{}> is Key", core::any::type_name::<T>()),
+            Entity::None => panic!("{msg}: Entity<{}> is None", core::any::type_name::<T>()),
+        }
+    }
+
+    /// Is this a Key variant?
+    pub fn is_key(&self) -> bool {
+        matches!(self, Self::Key(..))
+    }
+
+    /// Is this a Data variant?
+    pub fn is_data(&self) -> bool {
+        matches!(self, Self::Data(..))
+    }
+
+    /// Is this a None variant?
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+
+    /// Returns the name of the variant, for errors
+    fn state_name(&self) -> &'static str {
+        match self {
+            Entity::Key(_) => "Key",
+            Entity::Data(_) => "Data",
+            Entity::None => "None",
+        }
+    }
+
+    /// Replaces the entity with `to` applied to it, if `from` accepts its
+    /// current state
+    ///
+    /// Fails with `Error::InvalidState`, leaving the entity untouched, when
+    /// `from` rejects it.
+    ///
+    /// ```
+    /// use dbent::{Entity, Key};
+    ///
+    /// let mut user: Entity<i64, String> = Entity::Key(Key::new(1));
+    /// user.try_transition(Entity::is_key, |_| Entity::Data(Box::new("Ann".to_owned()))).unwrap();
+    /// assert!(user.try_transition(Entity::is_key, |_| Entity::None).is_err());
+    /// assert_eq!(user.data().unwrap(), "Ann");
+    /// ```
+    pub fn try_transition(&mut self, from: impl FnOnce(&Self) -> bool, to: impl FnOnce(Self) -> Self) -> Result<()> {
+        if !from(self) {
+            return Err(Error::InvalidState(self.state_name()));
+        }
+        let entity = core::mem::take(self);
+        *self = to(entity);
+        Ok(())
+    }
+
+    /// Converts into its data as a [`Fetched`], failing like [`into_data`](Self::into_data)
+    pub fn fetched(self) -> Result<Fetched<T>> {
+        match self {
+            Entity::Data(data) => Ok(Fetched(data)),
+            Entity::Key(_) => Err(Error::EntityNotFetched),
+            Entity::None => Err(Error::EntityEmpty),
+        }
+    }
+
+    /// Converts into its key as an [`Unfetched`]
+    ///
+    /// Fails with `Error::KeyEmpty` for an empty Key, `Error::EntityEmpty` for
+    /// None and `Error::InvalidState` for Data.
+    pub fn unfetched(self) -> Result<Unfetched<K>> {
+        match self {
+            Entity::Key(Key(Some(key))) => Ok(Unfetched(key)),
+            Entity::Key(Key(None)) => Err(Error::KeyEmpty),
+            Entity::Data(_) => Err(Error::InvalidState("Data")),
+            Entity::None => Err(Error::EntityEmpty),
+        }
+    }
+
+    /// Merges a newer state of the same entity into this one
+    ///
+    /// Data takes precedence over a Key, which takes precedence over None, so
+    /// merging never loses fetched data. When both are Data, `other` wins as
+    /// the most recent. Fails with `Error::KeyMismatch`, leaving this entity
+    /// untouched, when both have keys and they differ.
+    pub fn merge(&mut self, other: Entity<K, T>) -> Result<()>
+    where
+        K: PartialEq,
+        T: Keyed<KeyType = K>,
+    {
+        if self.is_none() {
+            *self = other;
+            return Ok(());
+        }
+        if other.is_none() {
+            return Ok(());
+        }
+        if self.key()? != other.key()? {
+            return Err(Error::KeyMismatch);
+        }
+
+        if other.is_data() {
+            *self = other;
+        }
+        Ok(())
+    }
+
+    /// Does the entity have the same key as `other`?
+    ///
+    /// Compares identities without comparing fetched data, which the derived
+    /// `PartialEq` does. False when either key is unset or missing, as unsaved
+    /// entities have no identity yet.
+    pub fn same_key(&self, other: &impl Keyed<KeyType = K>) -> bool
+    where
+        K: PartialEq,
+        T: Keyed<KeyType = K>,
+    {
+        match (self.key(), other.key()) {
+            (Ok(Key(Some(key))), Ok(Key(Some(other)))) => key == other,
+            _ => false,
+        }
+    }
+
+    /// Returns the data, loading it with `load` first if only the key is set
+    ///
+    /// Fails with `Error::EntityEmpty` for None and `Error::KeyEmpty` for an
+    /// empty Key, without calling `load`.
+    pub fn ensure_fetched(&mut self, load: impl FnOnce(&K) -> Result<T>) -> Result<&mut T> {
+        if let Entity::Key(key) = self {
+            let data = load(key.as_ref().ok_or(Error::KeyEmpty)?)?;
+            *self = Entity::Data(Box::new(data));
+        }
+        self.data_mut()
+    }
+}
+
+impl<K, T> TryFrom<Entity<K, T>> for Key<K>
+where
+    K: Clone,
+    T: Keyed<KeyType = K>,
+{
+    type Error = Error;
+
+    fn try_from(entity: Entity<K, T>) -> Result<Self> {
+        entity.into_key()
+    }
+}
+
+impl<K, T> From<Key<K>> for Entity<K, T> {
+    fn from(key: Key<K>) -> Self {
+        Self::Key(key)
+    }
+}
+
+impl<K, T: Keyed> From<T> for Entity<K, T> {
+    fn from(entity: T) -> Self {
+        Self::Data(Box::new(entity))
+    }
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=======================  FETCH STATE  ========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+/// The data of an `Entity` known to be fetched, its `Data` variant as a type
+///
+/// Code that needs a fetched entity takes a `Fetched` instead of checking the
+/// variant of an `Entity` at every layer, and converts it back with `into`:
+///
+/// ```
+/// use dbent::{Entity, Fetched, Key, Unfetched};
+///
+/// fn greet(user: &Fetched<String>) -> String {
+///     format!("Hello, {}", **user)
+/// }
+///
+/// let user: Entity<i64, String> = Entity::Key(Key::new(1));
+/// let user = user.unfetched().unwrap().fetch(|_| Ok("Ann".to_owned())).unwrap();
+/// assert_eq!(greet(&user), "Hello, Ann");
+///
+/// let user: Entity<i64, String> = user.into();
+/// assert!(user.is_data());
+/// assert!(Entity::<i64, String>::None.fetched().is_err());
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Fetched<T>(pub Box<T>);
+
+impl<T> Fetched<T> {
+    /// Wraps the data
+    pub fn new(data: T) -> Self {
+        Self(Box::new(data))
+    }
+
+    /// Returns the data
+    pub fn into_inner(self) -> T {
+        *self.0
+    }
+}
+
+impl<T> core::ops::Deref for Fetched<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> core::ops::DerefMut for Fetched<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Keyed> Keyed for Fetched<T> {
+    type KeyType = T::KeyType;
+
+    fn key(&self) -> Result<&Key<Self::KeyType>> {
+        self.0.key()
+    }
+}
+
+impl<K, T> From<Fetched<T>> for Entity<K, T> {
+    fn from(fetched: Fetched<T>) -> Self {
+        Self::Data(fetched.0)
+    }
+}
+
+impl<K, T> TryFrom<Entity<K, T>> for Fetched<T> {
+    type Error = Error;
+
+    fn try_from(entity: Entity<K, T>) -> Result<Self> {
+        entity.fetched()
+    }
+}
+
+/// The set key of an `Entity` whose data is not fetched, its `Key` variant
+/// as a type
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Unfetched<K>(pub K);
+
+impl<K> Unfetched<K> {
+    /// Returns the key
+    pub fn into_inner(self) -> K {
+        self.0
+    }
+
+    /// Fetches the data with `load`
+    pub fn fetch<T>(&self, load: impl FnOnce(&K) -> Result<T>) -> Result<Fetched<T>> {
+        load(&self.0).map(Fetched::new)
+    }
+}
+
+impl<K> core::ops::Deref for Unfetched<K> {
+    type Target = K;
+
+    fn deref(&self) -> &K {
+        &self.0
+    }
+}
+
+impl<K> From<Unfetched<K>> for Key<K> {
+    fn from(unfetched: Unfetched<K>) -> Self {
+        Key::new(unfetched.0)
+    }
+}
+
+impl<K, T> From<Unfetched<K>> for Entity<K, T> {
+    fn from(unfetched: Unfetched<K>) -> Self {
+        Self::Key(unfetched.into())
+    }
+}
+
+impl<K, T> TryFrom<Entity<K, T>> for Unfetched<K> {
+    type Error = Error;
+
+    fn try_from(entity: Entity<K, T>) -> Result<Self> {
+        entity.unfetched()
+    }
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  KEY EQ  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+/// A wrapper comparing and hashing entities by key only
+///
+/// Collections of `KeyEq` deduplicate and compare entities by identity, e.g.
+/// in a `HashSet`, without comparing their data. Entities without a key are
+/// all equal to each other, unlike with [`Entity::same_key`].
+///
+/// ```
+/// use std::collections::HashSet;
+/// use dbent::{Key, KeyEq, Keyed};
+///
+/// struct User {
+///     id: Key<u32>,
+///     name: String,
+/// }
+///
+/// impl Keyed for User {
+///     type KeyType = u32;
+///
+///     fn key(&self) -> dbent::Result<&Key<u32>> {
+///         Ok(&self.id)
+///     }
+/// }
+///
+/// let user = |id, name: &str| KeyEq(User { id: Key::new(id), name: name.to_owned() });
+/// let users: HashSet<_> = [user(1, "Ann"), user(2, "Bob"), user(1, "Ann B.")].into_iter().collect();
+/// assert_eq!(users.len(), 2);
+/// ```
+#[derive(Clone, Copy, Default, Debug)]
+pub struct KeyEq<T>(pub T);
+
+impl<T> KeyEq<T> {
+    /// Returns the wrapped entity
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Keyed> KeyEq<T> {
+    /// The key compared and hashed, None when the entity has no key
+    fn identity(&self) -> Option<&T::KeyType> {
+        self.0.key().ok().and_then(|key| key.as_ref())
+    }
+}
+
+impl<T> core::ops::Deref for KeyEq<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> core::ops::DerefMut for KeyEq<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for KeyEq<T> {
+    fn from(entity: T) -> Self {
+        Self(entity)
+    }
+}
+
+impl<T: Keyed> PartialEq for KeyEq<T>
+where
+    T::KeyType: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.identity() == other.identity()
+    }
+}
+
+impl<T: Keyed> Eq for KeyEq<T> where T::KeyType: Eq {}
+
+impl<T: Keyed> core::hash::Hash for KeyEq<T>
+where
+    T::KeyType: core::hash::Hash,
+{
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.identity().hash(state);
+    }
+}
+
+impl<T: Keyed> Keyed for KeyEq<T> {
+    type KeyType = T::KeyType;
+
+    fn key(&self) -> Result<&Key<Self::KeyType>> {
+        self.0.key()
+    }
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  PARENT  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+/// A child to parent reference that is always serialized as the parent key
+///
+/// It behaves like an `Entity`, which it derefs to, but serializing a child
+/// never embeds its parent data, so a child fetched inside its parent can be
+/// serialized without recursing back into the parent.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Parent<K, T>(pub Entity<K, T>);
+
+impl<K, T> Default for Parent<K, T> {
+    fn default() -> Self {
+        Parent(Entity::None)
+    }
+}
+
+impl<K, T> Parent<K, T> {
+    /// Converts this Parent into the Entity it holds
+    pub fn into_entity(self) -> Entity<K, T> {
+        self.0
+    }
+}
+
+impl<K, T> core::ops::Deref for Parent<K, T> {
+    type Target = Entity<K, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<K, T> core::ops::DerefMut for Parent<K, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<K, T> Keyed for Parent<K, T>
+where
+    T: Keyed<KeyType = K>,
+{
+    type KeyType = K;
+
+    fn key(&self) -> Result<&Key<Self::KeyType>> {
+        self.0.key()
+    }
+}
+
+impl<K, T> From<Entity<K, T>> for Parent<K, T> {
+    fn from(entity: Entity<K, T>) -> Self {
+        Self(entity)
+    }
+}
+
+impl<K, T> From<Key<K>> for Parent<K, T> {
+    fn from(key: Key<K>) -> Self {
+        Self(Entity::Key(key))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, T> Serialize for Parent<K, T>
+where
+    K: Serialize,
+    T: Keyed<KeyType = K>,
+{
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        match &self.0 {
+            Entity::None => serializer.serialize_none(),
+            entity => entity.key().map_err(::serde::ser::Error::custom)?.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, T> Deserialize<'de> for Parent<K, T>
+where
+    K: Deserialize<'de>,
+{
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        Ok(
+            match Option::<K>::deserialize(deserializer)? {
+                Some(key) => Self(Entity::Key(Key::new(key))),
+                None => Self(Entity::None),
+            }
+        )
+    }
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><======================  WEAK ENTITY  =========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+/// A non-owning reference to an entity shared behind an `Arc`
+///
+/// Graphs holding `Arc`s in both directions, like children in
+/// `Entity<K, Arc<T>>` pointing back at their parent, never free their
+/// entities. Holding one direction as a `WeakEntity` breaks the cycle: it
+/// keeps the key, and [`upgrade`](WeakEntity::upgrade) gives the shared data
+/// while it's alive, or falls back to `Entity::Key` to fetch it again. Like
+/// [`Parent`], it's serialized as the key.
+///
+/// ```
+/// use std::sync::Arc;
+/// use dbent::{Entity, Key, Keyed, WeakEntity};
+///
+/// struct Folder {
+///     id: Key<u32>,
+/// }
+///
+/// impl Keyed for Folder {
+///     type KeyType = u32;
+///
+///     fn key(&self) -> dbent::Result<&Key<u32>> {
+///         Ok(&self.id)
+///     }
+/// }
+///
+/// let folder = Arc::new(Folder { id: Key::new(7) });
+/// let weak = WeakEntity::new(&folder).unwrap();
+/// assert!(weak.upgrade().is_data());
+///
+/// drop(folder);
+/// assert!(matches!(weak.upgrade(), Entity::Key(key) if key == Key::new(7)));
+/// ```
+pub struct WeakEntity<K, T> {
+    key: Key<K>,
+    data: std::sync::Weak<T>,
+}
+
+impl<K, T> WeakEntity<K, T> {
+    /// Creates a reference to the shared entity, keeping a copy of its key
+    pub fn new(data: &Arc<T>) -> Result<Self>
+    where
+        K: Clone,
+        T: Keyed<KeyType = K>,
+    {
+        Ok(Self { key: data.key()?.clone(), data: Arc::downgrade(data) })
+    }
+
+    /// Creates a reference holding only the key, which never upgrades to data
+    pub fn from_key(key: Key<K>) -> Self {
+        Self { key, data: std::sync::Weak::new() }
+    }
+
+    /// Returns the shared data if the entity is still alive
+    pub fn data(&self) -> Option<Arc<T>> {
+        self.data.upgrade()
+    }
+
+    /// Is the shared entity still alive?
+    pub fn is_alive(&self) -> bool {
+        self.data.strong_count() > 0
+    }
+
+    /// Returns an Entity with the shared data if still alive, or its key
+    ///
+    /// Gives `Entity::None` for a dead reference without a key value.
+    pub fn upgrade(&self) -> Entity<K, Arc<T>>
+    where
+        K: Clone,
+    {
+        match self.data.upgrade() {
+            Some(data) => Entity::Data(Box::new(data)),
+            None => Entity::from_key_nullable(self.key.clone()),
+        }
+    }
+}
+
+impl<K, T> Keyed for WeakEntity<K, T> {
+    type KeyType = K;
+
+    fn key(&self) -> Result<&Key<Self::KeyType>> {
+        Ok(&self.key)
+    }
+}
+
+impl<K: Clone, T> Clone for WeakEntity<K, T> {
+    fn clone(&self) -> Self {
+        Self { key: self.key.clone(), data: self.data.clone() }
+    }
+}
+
+impl<K, T> Default for WeakEntity<K, T> {
+    fn default() -> Self {
+        Self::from_key(Key(None))
+    }
+}
+
+impl<K: fmt::Debug, T> fmt::Debug for WeakEntity<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WeakEntity").field("key", &self.key).field("alive", &self.is_alive()).finish()
+    }
+}
+
+/// Compares the keys only
+impl<K: PartialEq, T> PartialEq for WeakEntity<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Eq, T> Eq for WeakEntity<K, T> {}
+
+impl<K, T> From<Key<K>> for WeakEntity<K, T> {
+    fn from(key: Key<K>) -> Self {
+        Self::from_key(key)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K: Serialize, T> Serialize for WeakEntity<K, T> {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        self.key.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K: Deserialize<'de>, T> Deserialize<'de> for WeakEntity<K, T> {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        Key::deserialize(deserializer).map(Self::from_key)
+    }
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><======================  ENTITY LABEL  ========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+/// Enum for situations when you want to define not just a Key but also a Label
+///
+/// This may be useful for faster access to avoid an extra LEFT JOIN or because
+/// you may have missing data, thus no Key, and having the Label makes the entity
+/// still valid
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum EntityLabel<K, T, L> {
+    /// Key and Label for this entity
+    KeyLabel(Key<K>, L),
+    /// Created/Fetched data for the entity
+    Data(Box<T>),
+    /// For when you have no data to fill or null from database
+    #[default]
+    None,
+}
+
+impl<K, T, L> Keyed for EntityLabel<K, T, L>
+where
+    T: Keyed<KeyType = K>,
+{
+    type KeyType = K;
+
+    fn key(&self) -> Result<&Key<Self::KeyType>> {
+        match self {
+            EntityLabel::KeyLabel(key, _) => Ok(key),
+            EntityLabel::Data(data) => data.key(),
+            EntityLabel::None => Err(Error::EntityLabelEmpty),
+        }
+    }
+}
+
+impl<K, T, L> Label for EntityLabel<K, T, L>
+where
+    T: Label<LabelType = L>,
+{
+    type LabelType = L;
+
+    fn label(&self) -> Result<&Self::LabelType> {
+        match self {
+            EntityLabel::KeyLabel(_, label) => Ok(label),
+            EntityLabel::Data(data) => data.label(),
+            EntityLabel::None => Err(Error::EntityLabelEmpty),
+        }
+    }
+
+    fn tag_label(&self) -> Result<Cow<'static, str>>
+    where
+        Self::LabelType: fmt::Display,
+    {
+        match self {
+            EntityLabel::Data(data) => data.tag_label(),
+            _ => self.label().map(|label| Cow::Owned(label.to_string())),
+        }
+    }
+}
+
+impl<K, T, L> EntityLabel<K, T, L> {
+    /// Creates an EntityLabel from nullable key and label columns, as read from a LEFT JOIN
+    ///
+    /// Both must be set for a KeyLabel. A NULL key, or a key whose joined label
+    /// is NULL because the row it points to is missing, gives None.
+    pub fn from_columns(key: Key<K>, label: Option<L>) -> Self {
+        match (key.0, label) {
+            (Some(key), Some(label)) => EntityLabel::KeyLabel(Key::new(key), label),
+            _ => EntityLabel::None,
+        }
+    }
+
+    /// Returns the data if it exists and was fetched/created
+    pub fn data(&self) -> Result<&T> {
+        match self {
+            EntityLabel::Data(data) => Ok(data),
+            EntityLabel::KeyLabel(..) => Err(Error::EntityLabelNotFetched),
+            EntityLabel::None => Err(Error::EntityLabelEmpty),
+        }
+    }
+
+    /// Returns the mutable data if it exists and was fetched/created
+    pub fn data_mut(&mut self) -> Result<&mut T> {
+        match self {
+            EntityLabel::Data(ref mut data) => Ok(data),
+            EntityLabel::KeyLabel(..) => Err(Error::EntityLabelNotFetched),
+            EntityLabel::None => Err(Error::EntityLabelEmpty),
+        }
+    }
+
+    /// Is this a KeyLabel variant?
+    pub fn is_keylabel(&self) -> bool {
+        matches!(self, Self::KeyLabel(..))
+    }
+
+    /// Is this a Data variant?
+    pub fn is_data(&self) -> bool {
+        matches!(self, Self::Data(..))
+    }
+
+    /// Is this a None variant?
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+}
+
+impl<K, T, L> From<(Key<K>, L)> for EntityLabel<K, T, L> {
+    fn from((key, label): (Key<K>, L)) -> Self {
+        Self::KeyLabel(key, label)
+    }
+}
+
+/// Converts nullable key and label columns, as read with `row.get::<_, Option<_>>`
+///
+/// A key without a label keeps the key with a default label, so the entity
+/// can still be fetched, unlike [`EntityLabel::from_columns`] which treats it
+/// as a dangling LEFT JOIN. A label without a key gives None, as there is no
+/// key to refer to.
+impl<K, T, L: Default> From<(Option<K>, Option<L>)> for EntityLabel<K, T, L> {
+    fn from(columns: (Option<K>, Option<L>)) -> Self {
+        match columns {
+            (Some(key), label) => Self::KeyLabel(Key::new(key), label.unwrap_or_default()),
+            (None, _) => Self::None,
+        }
+    }
+}
+
+impl<K, T: Keyed, L> From<T> for EntityLabel<K, T, L> {
+    fn from(entity: T) -> Self {
+        Self::Data(Box::new(entity))
+    }
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><==========================  MANY  ============================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+/// Enum for defining one-to-many or many-to-many relationships
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Many<T> {
+    /// This holds the created/fetched data in a vector
+    Data(Vec<T>),
+    /// For when the data exists but is not fetched
+    NotFetched,
+    /// For when you have no data to fill or fetch from the DB
+    #[default]
+    None,
+}
+
+impl<T> Many<T> {
+    /// Returns the `Vec` of data if they exist and were fetched/created
+    pub fn data(&self) -> Result<&Vec<T>> {
+        match self {
+            Many::Data(data) => Ok(data),
+            Many::NotFetched => Err(Error::ManyNotFetched),
+            Many::None => Err(Error::ManyEmpty),
+        }
+    }
+
+    /// Returns the mutable `Vec` of data if they exist and were fetched/created
+    pub fn data_mut(&mut self) -> Result<&mut Vec<T>> {
+        match self {
+            Many::Data(ref mut data) => Ok(data),
+            Many::NotFetched => Err(Error::ManyNotFetched),
+            Many::None => Err(Error::ManyEmpty),
+        }
+    }
+
+    /// Returns the `Vec` of data, panicking with `msg`, the entity type and the variant if not fetched/created
+    #[track_caller]
+    pub fn expect_data(&self, msg: &str) -> &Vec<T> {
+        match self {
+            Many::Data(data) => data,
+            Many::NotFetched => panic!("{msg}: Many<{}> is NotFetched", core::any::type_name::<T>()),
+            Many::None => panic!("{msg}: Many<{}> is None", core::any::type_name::<T>()),
+        }
+    }
+
+    /// Returns the Tags of the data if they exist and were fetched/created
+    pub fn tags(&self) -> Result<Vec<Tag>> where T: Tagged {
+        self.data()?.tags()
+    }
+
+    /// Returns the first entity whose label matches `label` once both are
+    /// normalized, if the data exist and were fetched/created
+    pub fn find_by_label(&self, label: &str) -> Result<Option<&T>> where T: NormalizedLabel {
+        let label = normalize_label(label);
+        Ok(self.data()?.iter().find(|entity| entity.normalized() == label))
+    }
+
+    /// Returns an iterator over the labels of the data if they exist and were fetched/created
+    pub fn labels(&self) -> Result<impl Iterator<Item = Result<&T::LabelType>>> where T: Label {
+        Ok(self.data()?.iter().map(Label::label))
+    }
+
+    /// Sorts the data by label, if they exist and were fetched/created
+    ///
+    /// The sort is stable, and entities whose label can't be retrieved come first.
+    pub fn sort_by_label(&mut self) -> Result<()> where T: Label, T::LabelType: Ord {
+        self.data_mut()?.sort_by(|a, b| a.label().ok().cmp(&b.label().ok()));
+        Ok(())
+    }
+
+    /// Sorts the data by normalized label, if they exist and were fetched/created
+    ///
+    /// The sort is stable, so entities with the same normalized label keep their order.
+    pub fn sort_by_normalized_label(&mut self) -> Result<()> where T: NormalizedLabel {
+        self.data_mut()?.sort_by_cached_key(|entity| entity.normalized().into_owned());
+        Ok(())
+    }
+
+    /// Returns the sum of `value` over the data if they exist and were fetched/created
+    pub fn sum_by<S: core::iter::Sum<S>>(&self, value: impl FnMut(&T) -> S) -> Result<S> {
+        Ok(self.data()?.iter().map(value).sum())
+    }
+
+    /// Returns the entity with the minimum `key`, or None if there is no
+    /// entity, if the data exist and were fetched/created
+    ///
+    /// The first entity is returned when several have the minimum key.
+    pub fn min_by_key_field<B: Ord>(&self, mut key: impl FnMut(&T) -> B) -> Result<Option<&T>> {
+        Ok(self.data()?.iter().min_by_key(|entity| key(entity)))
+    }
+
+    /// Returns the entity with the maximum `key`, or None if there is no
+    /// entity, if the data exist and were fetched/created
+    ///
+    /// The last entity is returned when several have the maximum key.
+    pub fn max_by_key_field<B: Ord>(&self, mut key: impl FnMut(&T) -> B) -> Result<Option<&T>> {
+        Ok(self.data()?.iter().max_by_key(|entity| key(entity)))
+    }
+
+    /// Returns the number of entities matching `predicate`, if the data exist and were fetched/created
+    pub fn count_where(&self, mut predicate: impl FnMut(&T) -> bool) -> Result<usize> {
+        Ok(self.data()?.iter().filter(|entity| predicate(entity)).count())
+    }
+
+    /// Is this a Data variant?
+    pub fn is_data(&self) -> bool {
+        matches!(self, Self::Data(..))
+    }
+
+    /// Is this a NotFetched variant?
+    pub fn is_not_fetched(&self) -> bool {
+        matches!(self, Self::NotFetched)
+    }
+
+    /// Is this a None variant?
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+
+    /// Returns the data, loading it with `load` first if it was not fetched
+    ///
+    /// Fails with `Error::ManyEmpty` for None, without calling `load`, and
+    /// with `Error::TooManyRows` if `load` returns more rows than the limit of
+    /// [`limits::set_max_many_rows`].
+    pub fn ensure_fetched(&mut self, load: impl FnOnce() -> Result<Vec<T>>) -> Result<&mut Vec<T>> {
+        self.fetch_limited(|_| load(), usize::MAX)
+    }
+
+    /// Returns the data, loading at most `max` rows with `load` first if it
+    /// was not fetched
+    ///
+    /// `load` is given one more than the limit, the lower of `max` and the
+    /// limit of [`limits::set_max_many_rows`], so it can read no further than
+    /// needed to tell the limit was exceeded. Fails with `Error::TooManyRows`,
+    /// leaving the Many NotFetched, if it returns more rows than the limit.
+    pub fn fetch_limited(&mut self, load: impl FnOnce(usize) -> Result<Vec<T>>, max: usize) -> Result<&mut Vec<T>> {
+        if self.is_not_fetched() {
+            let limit = limits::effective_limit(max);
+            let data = load(limit.saturating_add(1))?;
+            limits::check_rows(data.len(), limit)?;
+            *self = Many::Data(data);
+        }
+        self.data_mut()
+    }
+
+    /// Returns the entity at `index`, or None if out of bounds or there is no data
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.data().ok()?.get(index)
+    }
+
+    /// Returns the mutable entity at `index`, or None if out of bounds or there is no data
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.data_mut().ok()?.get_mut(index)
+    }
+
+    /// Returns the first entity, or None if empty or there is no data
+    pub fn first(&self) -> Option<&T> {
+        self.data().ok()?.first()
+    }
+
+    /// Returns the last entity, or None if empty or there is no data
+    pub fn last(&self) -> Option<&T> {
+        self.data().ok()?.last()
+    }
+
+    /// Returns the number of entities, or None if there is no data
+    pub fn len(&self) -> Option<usize> {
+        self.data().ok().map(Vec::len)
+    }
+
+    /// Returns true if there are no entities, or None if there is no data
+    pub fn is_empty(&self) -> Option<bool> {
+        self.data().ok().map(Vec::is_empty)
+    }
+}
+
+impl<T: Keyed> Many<T> {
+    /// Returns the keys of the entities in `snapshot` that are no longer in
+    /// the data, if both exist and were fetched/created
+    ///
+    /// Entities with an empty key are skipped, as they were never stored.
+    pub fn removed_since(&self, snapshot: &snapshot::Snapshot<Many<T>>) -> Result<Vec<T::KeyType>>
+    where
+        T::KeyType: Eq + core::hash::Hash + Clone,
+    {
+        let mut current = std::collections::HashSet::new();
+        for entity in self.data()? {
+            if let Some(key) = entity.key()?.as_ref() {
+                current.insert(key);
+            }
+        }
+
+        let mut removed = Vec::new();
+        for entity in snapshot.data().data()? {
+            if let Some(key) = entity.key()?.as_ref() {
+                if !current.contains(key) {
+                    removed.push(key.clone());
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Merges newer entities into the fetched ones, matching them by key
+    ///
+    /// Entities of `other` replace the ones with the same key and the others
+    /// are appended. Merging into a Many without data takes `other` as is,
+    /// and merging a Many without data changes nothing.
+    pub fn merge_by_key(&mut self, other: Many<T>) -> Result<()>
+    where
+        T::KeyType: Eq + core::hash::Hash + Clone,
+    {
+        let other = match other {
+            Many::Data(other) => other,
+            _ => return Ok(()),
+        };
+        let data = match self {
+            Many::Data(data) => data,
+            _ => {
+                *self = Many::Data(other);
+                return Ok(());
+            },
+        };
+
+        let mut positions = std::collections::HashMap::new();
+        for (i, entity) in data.iter().enumerate() {
+            if let Some(key) = entity.key()?.as_ref() {
+                positions.insert(key.clone(), i);
+            }
+        }
+
+        for entity in other {
+            let position = entity.key()?.as_ref().and_then(|key| positions.get(key).copied());
+            match position {
+                Some(i) => data[i] = entity,
+                None => {
+                    if let Some(key) = entity.key()?.as_ref() {
+                        positions.insert(key.clone(), data.len());
+                    }
+                    data.push(entity);
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Indexes into the data, panicking for the NotFetched and None variants
+impl<T> core::ops::Index<usize> for Many<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self {
+            Many::Data(data) => &data[index],
+            Many::NotFetched => panic!("cannot index into Many::NotFetched at {index}, fetch it first"),
+            Many::None => panic!("cannot index into Many::None at {index}"),
+        }
+    }
+}
+
+/// Indexes into the data, panicking for the NotFetched and None variants
+impl<T> core::ops::IndexMut<usize> for Many<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        match self {
+            Many::Data(data) => &mut data[index],
+            Many::NotFetched => panic!("cannot index into Many::NotFetched at {index}, fetch it first"),
+            Many::None => panic!("cannot index into Many::None at {index}"),
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for Many<T> {
+    fn from(entities: Vec<T>) -> Self {
+        Self::Data(entities)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Sync> Many<T> {
+    /// Returns a parallel iterator over the data if they exist and were fetched/created
+    pub fn par_iter(&self) -> Result<::rayon::slice::Iter<'_, T>> {
+        use ::rayon::iter::IntoParallelRefIterator;
+        Ok(self.data()?.par_iter())
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T: Send> Many<T> {
+    /// Returns a mutable parallel iterator over the data if they exist and were fetched/created
+    pub fn par_iter_mut(&mut self) -> Result<::rayon::slice::IterMut<'_, T>> {
+        use ::rayon::iter::IntoParallelRefMutIterator;
+        Ok(self.data_mut()?.par_iter_mut())
+    }
+}
+
+/// Iterates over the data in parallel, yielding nothing for the NotFetched and None variants
+#[cfg(feature = "rayon")]
+impl<T: Send> ::rayon::iter::IntoParallelIterator for Many<T> {
+    type Iter = ::rayon::vec::IntoIter<T>;
+    type Item = T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        match self {
+            Many::Data(data) => data.into_par_iter(),
+            _ => Vec::new().into_par_iter(),
+        }
+    }
+}
+
+/// Iterates over the data in parallel, yielding nothing for the NotFetched and None variants
+#[cfg(feature = "rayon")]
+impl<'a, T: Sync> ::rayon::iter::IntoParallelIterator for &'a Many<T> {
+    type Iter = ::rayon::slice::Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        match self {
+            Many::Data(data) => data.into_par_iter(),
+            _ => (&[]).into_par_iter(),
+        }
+    }
+}
+
+/// Iterates over the data in parallel, yielding nothing for the NotFetched and None variants
+#[cfg(feature = "rayon")]
+impl<'a, T: Send> ::rayon::iter::IntoParallelIterator for &'a mut Many<T> {
+    type Iter = ::rayon::slice::IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        match self {
+            Many::Data(data) => data.into_par_iter(),
+            _ => (&mut []).into_par_iter(),
+        }
+    }
+}
+
+/// A `Many` whose data are shared behind an `Arc`
+///
+/// Cloning it only bumps a reference count, so a large fetched collection can
+/// be handed to worker threads or several views without copying the `Vec`.
+/// The data are read-only; turn it back into a `Many` to edit them.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Hash, Debug, Default)]
+pub enum SharedMany<T> {
+    /// This holds the created/fetched data in a shared slice
+    Data(Arc<[T]>),
+    /// For when the data exists but is not fetched
+    NotFetched,
+    /// For when you have no data to fill or fetch from the DB
+    #[default]
+    None,
+}
+
+impl<T> SharedMany<T> {
+    /// Returns the slice of data if they exist and were fetched/created
+    pub fn data(&self) -> Result<&[T]> {
         match self {
-            Entity::Data(ref mut data) => Ok(data),
-            Entity::Key(_) => Err(Error::EntityNotFetched),
-            Entity::None => Err(Error::EntityEmpty),
+            SharedMany::Data(data) => Ok(data),
+            SharedMany::NotFetched => Err(Error::ManyNotFetched),
+            SharedMany::None => Err(Error::ManyEmpty),
         }
     }
 
-    /// Is this a Key variant?
-    pub fn is_key(&self) -> bool {
-        matches!(self, Self::Key(..))
+    /// Returns the entity at `index`, or None if out of bounds or there is no data
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.data().ok()?.get(index)
+    }
+
+    /// Returns the number of entities, or None if there is no data
+    pub fn len(&self) -> Option<usize> {
+        self.data().ok().map(<[T]>::len)
+    }
+
+    /// Returns true if there are no entities, or None if there is no data
+    pub fn is_empty(&self) -> Option<bool> {
+        self.data().ok().map(<[T]>::is_empty)
     }
 
     /// Is this a Data variant?
@@ -224,165 +2465,312 @@ impl<K, T> Entity<K, T> {
         matches!(self, Self::Data(..))
     }
 
+    /// Is this a NotFetched variant?
+    pub fn is_not_fetched(&self) -> bool {
+        matches!(self, Self::NotFetched)
+    }
+
     /// Is this a None variant?
     pub fn is_none(&self) -> bool {
         matches!(self, Self::None)
     }
+
+    /// Returns a `Many` with a copy of the data
+    pub fn to_many(&self) -> Many<T> where T: Clone {
+        match self {
+            SharedMany::Data(data) => Many::Data(data.to_vec()),
+            SharedMany::NotFetched => Many::NotFetched,
+            SharedMany::None => Many::None,
+        }
+    }
 }
 
-impl<K, T> From<T> for Entity<K, T> {
-    fn from(entity: T) -> Self {
-        Self::Data(Box::new(entity))
+/// Shares the data instead of cloning them
+impl<T> Clone for SharedMany<T> {
+    fn clone(&self) -> Self {
+        match self {
+            SharedMany::Data(data) => SharedMany::Data(Arc::clone(data)),
+            SharedMany::NotFetched => SharedMany::NotFetched,
+            SharedMany::None => SharedMany::None,
+        }
     }
 }
 
-//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
-//<<>><======================  ENTITY LABEL  ========================><<>>//
-//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+/// Indexes into the data, panicking for the NotFetched and None variants
+impl<T> core::ops::Index<usize> for SharedMany<T> {
+    type Output = T;
 
-/// Enum for situations when you want to define not just a Key but also a Label
-///
-/// This may be useful for faster access to avoid an extra LEFT JOIN or because
-/// you may have missing data, thus no Key, and having the Label makes the entity
-/// still valid
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
-pub enum EntityLabel<K, T, L> {
-    /// Key and Label for this entity
-    KeyLabel(Key<K>, L),
-    /// Created/Fetched data for the entity
-    Data(Box<T>),
-    /// For when you have no data to fill or null from database
-    #[default]
-    None,
+    fn index(&self, index: usize) -> &T {
+        match self {
+            SharedMany::Data(data) => &data[index],
+            SharedMany::NotFetched => panic!("cannot index into SharedMany::NotFetched at {index}, fetch it first"),
+            SharedMany::None => panic!("cannot index into SharedMany::None at {index}"),
+        }
+    }
 }
 
-impl<K, T, L> Keyed for EntityLabel<K, T, L>
+impl<T> From<Many<T>> for SharedMany<T> {
+    fn from(many: Many<T>) -> Self {
+        match many {
+            Many::Data(data) => SharedMany::Data(data.into()),
+            Many::NotFetched => SharedMany::NotFetched,
+            Many::None => SharedMany::None,
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for SharedMany<T> {
+    fn from(entities: Vec<T>) -> Self {
+        Self::Data(entities.into())
+    }
+}
+
+impl<T> Many<T> {
+    /// Moves the data behind an `Arc` for sharing them without copies
+    pub fn into_shared(self) -> SharedMany<T> {
+        self.into()
+    }
+}
+
+/// Returns the keys of the entities that were not fetched yet, without duplicates
+///
+/// The keys come in the order they are first found, ready for a batched
+/// `WHERE key IN (...)` fetch.
+pub fn collect_keys<'a, K, T>(entities: impl IntoIterator<Item = &'a Entity<K, T>>) -> Vec<&'a K>
 where
-    T: Keyed<KeyType = K>,
+    K: Eq + core::hash::Hash + 'a,
+    T: 'a,
 {
-    type KeyType = K;
+    let mut seen = std::collections::HashSet::new();
+    entities
+        .into_iter()
+        .filter_map(|entity| match entity {
+            Entity::Key(Key(Some(key))) => Some(key),
+            _ => None,
+        })
+        .filter(|key| seen.insert(*key))
+        .collect()
+}
 
-    fn key(&self) -> Result<&Key<Self::KeyType>> {
-        match self {
-            EntityLabel::KeyLabel(key, _) => Ok(key),
-            EntityLabel::Data(data) => data.key(),
-            EntityLabel::None => Err(Error::EntityLabelEmpty),
+/// Returns the keys of the parents whose `Many` returned by `many` was not
+/// fetched yet, without duplicates
+///
+/// The keys come in the order they are first found, ready for a batched
+/// `WHERE parent IN (...)` fetch of the children.
+pub fn collect_many_keys<'a, P, T>(parents: impl IntoIterator<Item = &'a P>, many: impl Fn(&'a P) -> &'a Many<T>) -> Result<Vec<&'a P::KeyType>>
+where
+    P: Keyed + 'a,
+    P::KeyType: Eq + core::hash::Hash,
+    T: 'a,
+{
+    let mut seen = std::collections::HashSet::new();
+    let mut keys = Vec::new();
+    for parent in parents {
+        if many(parent).is_not_fetched() {
+            if let Some(key) = parent.key()?.as_ref() {
+                if seen.insert(key) {
+                    keys.push(key);
+                }
+            }
         }
     }
+    Ok(keys)
 }
 
-impl<K, T, L> Label for EntityLabel<K, T, L>
+/// Distributes fetched entities to the `Entity` fields of `parents` that
+/// hold their keys, returning the keys that were not found
+///
+/// The companion of [`collect_keys`]: fields returned by `field` that hold
+/// an `Entity::Key` present in `fetched` are replaced by its data, cloned
+/// when several parents share it. The other fields are left untouched.
+pub fn hydrate_entities<P, K, T>(parents: &mut [P], fetched: Vec<T>, mut field: impl FnMut(&mut P) -> &mut Entity<K, T>) -> Result<Vec<K>>
 where
-    T: Label<LabelType = L>,
+    K: Eq + core::hash::Hash + Clone,
+    T: Keyed<KeyType = K> + Clone,
 {
-    type LabelType = L;
+    let mut rows = std::collections::HashMap::new();
+    for data in fetched {
+        if let Some(key) = data.key()?.as_ref() {
+            rows.insert(key.clone(), data);
+        }
+    }
 
-    fn label(&self) -> Result<&Self::LabelType> {
-        match self {
-            EntityLabel::KeyLabel(_, label) => Ok(label),
-            EntityLabel::Data(data) => data.label(),
-            EntityLabel::None => Err(Error::EntityLabelEmpty),
+    let mut missing = Vec::new();
+    for parent in parents {
+        let entity = field(parent);
+        if let Entity::Key(Key(Some(key))) = entity {
+            match rows.get(key) {
+                Some(data) => *entity = Entity::Data(Box::new(data.clone())),
+                None if !missing.contains(key) => missing.push(key.clone()),
+                None => {},
+            }
         }
     }
+    Ok(missing)
 }
 
-impl<K, T, L> EntityLabel<K, T, L> {
-    /// Returns the data if it exists and was fetched/created
-    pub fn data(&self) -> Result<&T> {
-        match self {
-            EntityLabel::Data(data) => Ok(data),
-            EntityLabel::KeyLabel(..) => Err(Error::EntityLabelNotFetched),
-            EntityLabel::None => Err(Error::EntityLabelEmpty),
+/// Distributes fetched children to the `Many` fields of `parents` by the key
+/// each child holds, returning the children whose key matches no parent
+///
+/// The in-memory half of a two-query hydration: after fetching the parents
+/// and then the children `WHERE parent IN (...)` of the keys from
+/// [`collect_many_keys`], the `Many` returned by `field` is set to the
+/// children whose `fk` is the key of the parent, in their fetched order, or
+/// to an empty Many if there are none. Parents without a key are left
+/// untouched, and a parent whose key is repeated gets no children.
+pub fn join_by_key<P, C, K>(
+    parents: &mut [P],
+    children: Vec<C>,
+    fk: impl Fn(&C) -> &Key<K>,
+    mut field: impl FnMut(&mut P) -> &mut Many<C>,
+) -> Result<Vec<C>>
+where
+    P: Keyed<KeyType = K>,
+    K: Eq + core::hash::Hash + Clone,
+{
+    let mut groups = std::collections::HashMap::<K, Vec<C>>::new();
+    let mut orphans = Vec::new();
+    for child in children {
+        match fk(&child).as_ref() {
+            Some(key) => groups.entry(key.clone()).or_default().push(child),
+            None => orphans.push(child),
         }
     }
 
-    /// Returns the mutable data if it exists and was fetched/created
-    pub fn data_mut(&mut self) -> Result<&mut T> {
-        match self {
-            EntityLabel::Data(ref mut data) => Ok(data),
-            EntityLabel::KeyLabel(..) => Err(Error::EntityLabelNotFetched),
-            EntityLabel::None => Err(Error::EntityLabelEmpty),
+    for parent in parents {
+        if let Some(key) = parent.key()?.as_ref() {
+            let children = groups.remove(key).unwrap_or_default();
+            *field(parent) = Many::Data(children);
         }
     }
+    orphans.extend(groups.into_values().flatten());
+    Ok(orphans)
+}
 
-    /// Is this a KeyLabel variant?
-    pub fn is_keylabel(&self) -> bool {
-        matches!(self, Self::KeyLabel(..))
-    }
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><==========================  LINK  ============================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
 
-    /// Is this a Data variant?
-    pub fn is_data(&self) -> bool {
-        matches!(self, Self::Data(..))
+/// The composite key of a many-to-many join table row
+///
+/// `left` and `right` hold the keys of the two linked entities. Entities
+/// using `Key<LinkKey<A, B>>` as their key, including the ones generated
+/// by `#[derive(Entity)]`, get the far-side key helpers on `Many`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct LinkKey<A, B> {
+    /// Key of the left side entity
+    pub left: A,
+    /// Key of the right side entity
+    pub right: B,
+}
+
+impl<A, B> LinkKey<A, B> {
+    /// Creates a new LinkKey
+    pub fn new(left: A, right: B) -> Self {
+        Self { left, right }
     }
+}
 
-    /// Is this a None variant?
-    pub fn is_none(&self) -> bool {
-        matches!(self, Self::None)
+impl<A: fmt::Display, B: fmt::Display> fmt::Display for LinkKey<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.left, self.right)
     }
 }
 
-impl<K, T, L> From<T> for EntityLabel<K, T, L> {
-    fn from(entity: T) -> Self {
-        Self::Data(Box::new(entity))
+#[cfg(feature = "rusqlite")]
+impl<A: ToSql, B: ToSql> Key<LinkKey<A, B>> {
+    /// Returns the left and right keys as parameters for two SQL columns
+    pub fn to_sql_pair(&self) -> [&dyn ToSql; 2] {
+        match &self.0 {
+            Some(link) => [&link.left, &link.right],
+            None => [&rusqlite::types::Null, &rusqlite::types::Null],
+        }
     }
 }
 
-//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
-//<<>><==========================  MANY  ============================><<>>//
-//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+#[cfg(feature = "rusqlite")]
+impl<A: FromSql, B: FromSql> Key<LinkKey<A, B>> {
+    /// Reads the Key from the left and right key columns of a row
+    ///
+    /// The Key is empty if any of the columns is NULL.
+    pub fn from_sql_pair<I: rusqlite::RowIndex>(row: &rusqlite::Row<'_>, left: I, right: I) -> rusqlite::Result<Self> {
+        let left: Option<A> = row.get(left)?;
+        let right: Option<B> = row.get(right)?;
+        Ok(Self(left.zip(right).map(|(left, right)| LinkKey::new(left, right))))
+    }
+}
 
-/// Enum for defining one-to-many or many-to-many relationships
+/// A row of a many-to-many join table, linking two entities by their keys
+/// with an optional payload for extra columns
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
-pub enum Many<T> {
-    /// This holds the created/fetched data in a vector
-    Data(Vec<T>),
-    /// For when the data exists but is not fetched
-    NotFetched,
-    /// For when you have no data to fill or fetch from the DB
-    #[default]
-    None,
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Link<A, B, P = ()> {
+    /// The composite key of the link
+    pub key: Key<LinkKey<A, B>>,
+    /// Extra data stored in the link
+    pub payload: P,
 }
 
-impl<T> Many<T> {
-    /// Returns the `Vec` of data if they exist and were fetched/created
-    pub fn data(&self) -> Result<&Vec<T>> {
-        match self {
-            Many::Data(data) => Ok(data),
-            Many::NotFetched => Err(Error::ManyNotFetched),
-            Many::None => Err(Error::ManyEmpty),
-        }
+impl<A, B> Link<A, B> {
+    /// Creates a new Link between the `left` and `right` keys
+    pub fn new(left: A, right: B) -> Self {
+        Self::with_payload(left, right, ())
     }
+}
 
-    /// Returns the mutable `Vec` of data if they exist and were fetched/created
-    pub fn data_mut(&mut self) -> Result<&mut Vec<T>> {
-        match self {
-            Many::Data(ref mut data) => Ok(data),
-            Many::NotFetched => Err(Error::ManyNotFetched),
-            Many::None => Err(Error::ManyEmpty),
-        }
+impl<A, B, P> Link<A, B, P> {
+    /// Creates a new Link between the `left` and `right` keys with a payload
+    pub fn with_payload(left: A, right: B, payload: P) -> Self {
+        Self { key: Key::new(LinkKey::new(left, right)), payload }
     }
 
-    /// Is this a Data variant?
-    pub fn is_data(&self) -> bool {
-        matches!(self, Self::Data(..))
+    /// Returns the key of the left side entity
+    pub fn left(&self) -> Option<&A> {
+        self.key.as_ref().map(|link| &link.left)
     }
 
-    /// Is this a NotFetched variant?
-    pub fn is_not_fetched(&self) -> bool {
-        matches!(self, Self::NotFetched)
+    /// Returns the key of the right side entity
+    pub fn right(&self) -> Option<&B> {
+        self.key.as_ref().map(|link| &link.right)
     }
+}
 
-    /// Is this a None variant?
-    pub fn is_none(&self) -> bool {
-        matches!(self, Self::None)
+impl<A, B, P> Keyed for Link<A, B, P> {
+    type KeyType = LinkKey<A, B>;
+
+    fn key(&self) -> Result<&Key<Self::KeyType>> {
+        Ok(&self.key)
     }
 }
 
-impl<T> From<Vec<T>> for Many<T> {
-    fn from(entities: Vec<T>) -> Self {
-        Self::Data(entities)
+impl<A, B, P> KeyedMut for Link<A, B, P> {
+    fn key_mut(&mut self) -> Result<&mut Key<Self::KeyType>> {
+        Ok(&mut self.key)
+    }
+}
+
+impl<T> Many<T> {
+    /// Returns the set of left side keys of fetched links
+    pub fn left_keys<'a, A: Ord, B: 'a>(&'a self) -> Result<BTreeSet<&'a A>> where T: Keyed<KeyType = LinkKey<A, B>> {
+        let mut keys = BTreeSet::new();
+        for link in self.data()? {
+            if let Some(link) = link.key()?.as_ref() {
+                keys.insert(&link.left);
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Returns the set of right side keys of fetched links
+    pub fn right_keys<'a, A: 'a, B: Ord>(&'a self) -> Result<BTreeSet<&'a B>> where T: Keyed<KeyType = LinkKey<A, B>> {
+        let mut keys = BTreeSet::new();
+        for link in self.data()? {
+            if let Some(link) = link.key()?.as_ref() {
+                keys.insert(&link.right);
+            }
+        }
+        Ok(keys)
     }
 }
 
@@ -405,17 +2793,340 @@ pub enum Error {
     /// for an entity label that was not fetched
     #[error("data was not fetched from the database for this EntityLabel")]
     EntityLabelNotFetched,
+    /// for a label without any text, like an empty `LocalizedLabel`
+    #[error("no text set for this label")]
+    LabelEmpty,
     /// for a Many that has no data
     #[error("no data set for this Many")]
     ManyEmpty,
     /// for a Many that has no data fetched
     #[error("data were not fetched from the database for this Many")]
     ManyNotFetched,
+    /// for an empty key
+    #[error("no value set for this Key")]
+    KeyEmpty,
+    /// for a Tag, external id or audit entry of an entity whose Key is unset
+    #[error("the Key is unset, so the entity can't be identified")]
+    KeyMissing,
+    /// for an Entity in a state the operation doesn't accept, like a failed
+    /// transition
+    #[error("the Entity is in the {0} state, which the operation doesn't accept")]
+    InvalidState(&'static str),
+    /// for merging entities with different keys
+    #[error("the entities have different Keys")]
+    KeyMismatch,
+    /// for a key value of another type than the key type it is converted to
+    #[error("the Key value is not of the target key type")]
+    KeyTypeMismatch,
+    /// for a key value that doesn't fit in the key type it is converted to
+    #[error("the Key value is out of range for the target key type")]
+    KeyOutOfRange,
+    /// for an unsigned integer above the largest integer SQLite can store
+    #[error("the integer {0} is too large for SQLite")]
+    IntegerOutOfRange(u64),
+    /// for text that is not a valid hex binary key
+    #[error("invalid hex key {0:?}")]
+    InvalidHex(String),
+    /// for text that is not an external id of the entity type
+    #[error("invalid external id {0:?}")]
+    InvalidExternalId(String),
+    /// for a string that doesn't fit in a fixed capacity string
+    #[error("{len} bytes don't fit in a FixedStr<{capacity}>")]
+    CapacityExceeded {
+        /// Length of the string in bytes
+        len: usize,
+        /// Capacity of the fixed string in bytes
+        capacity: usize,
+    },
+    /// for a stored value that matches no variant of a `sql::DbEnum`
+    #[error("the value {value:?} matches no variant of {name}")]
+    UnknownVariant {
+        /// Name of the enum
+        name: &'static str,
+        /// Value that was read
+        value: sql::Value,
+    },
+    /// for a scoped key that belongs to a different scope
+    #[error("the Key belongs to a different scope")]
+    ScopeMismatch,
+    /// for a `Many` loaded with more rows than its limit
+    #[error("more than {limit} rows for a Many")]
+    TooManyRows {
+        /// Most rows allowed
+        limit: usize,
+    },
+    /// for a delete of an entity referenced through a relation with
+    /// `on_delete = "restrict"`
+    #[error("can't delete from {table} while referenced by {referenced_by}")]
+    DeleteRestricted {
+        /// Table of the entity
+        table: String,
+        /// The referencing table and column, as `table.column`
+        referenced_by: String,
+    },
+    /// for a key held by more than one entity where keys must be unique
+    #[error("duplicate key {key}")]
+    DuplicateKey {
+        /// The key, formatted with Debug
+        key: String,
+    },
+    /// for an entity that failed validation
+    #[error("validation failed: {0}")]
+    Invalid(#[from] validate::ValidationErrors),
+    /// for an entity that was changed by someone else since it was fetched
+    #[error("stale version {expected}, the stored version is {found}")]
+    StaleVersion {
+        /// Version held by the entity
+        expected: version::Version,
+        /// Version found in the database
+        found: version::Version,
+    },
+    /// for an upsert of a versioned entity, which could overwrite a row
+    /// without checking its version
+    #[error("versioned entities can't be upserted, their version wouldn't be checked")]
+    VersionedUpsert,
+    /// for an entity that was not found in the database
+    #[error("entity not found")]
+    NotFound,
+    /// for an encrypted column read or written without a cipher installed
+    #[error("no FieldCipher installed for the encrypted column {0:?}")]
+    CipherMissing(String),
+    /// for a value the field cipher failed to encrypt or decrypt
+    #[error("field cipher failed: {0}")]
+    Cipher(String),
+    /// for an operation aborted by an entity hook
+    #[error("operation aborted: {0}")]
+    Aborted(String),
+    /// for errors returned by rusqlite
+    #[cfg(feature = "rusqlite")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    /// for JSON that doesn't match the entity it is deserialized into
+    #[cfg(feature = "json")]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// for CSV that is malformed or doesn't match the entity it is read into
+    #[cfg(feature = "csv")]
+    #[error("invalid CSV: {0}")]
+    Csv(String),
+    /// for errors reading or writing CSV, or talking to Redis
+    #[cfg(any(feature = "csv", feature = "redis"))]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// for wire bytes that are malformed or don't encode the value they are
+    /// read into, and for values the wire encoding can't write
+    #[cfg(feature = "wire")]
+    #[error("invalid wire data: {0}")]
+    Wire(String),
+    /// for error replies of Redis and replies that don't match the command
+    #[cfg(feature = "redis")]
+    #[error("redis error: {0}")]
+    Redis(String),
+    /// for a JS value that doesn't match the type it is converted into
+    #[cfg(feature = "wasm")]
+    #[error("JS value conversion failed: {0}")]
+    Js(String),
+    /// for serialized data of a format version newer than the supported one
+    #[error("format version {found} is newer than the supported version {supported}")]
+    UnsupportedFormatVersion {
+        /// Version of the serialized data
+        found: u32,
+        /// Current format version of the type
+        supported: u32,
+    },
+    /// for a snowflake node id that doesn't fit in the id layout
+    #[error("snowflake node id {0} is out of range")]
+    InvalidNodeId(u16),
+    /// for an `Int` key past the largest one that can be represented
+    #[error("no key follows the largest Int key")]
+    KeyOverflow,
 }
 
 /// The result typedef for this crate for convenience
 pub type Result<T> = core::result::Result<T, Error>;
 
+/// An [`Error`] with the backtrace of where it was converted into a
+/// TracedError, captured when `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` is set
+///
+/// Error keeps its variants plain for matching, so the backtrace is taken by
+/// converting into this type with `?` in application code, which makes the
+/// first function returning a [`TracedResult`] the capture point:
+///
+/// ```
+/// use dbent::prelude::*;
+/// use dbent::{TracedResult, Error};
+///
+/// fn customer_name(customer: &Entity<Int, String>) -> TracedResult<String> {
+///     Ok(customer.data()?.clone())
+/// }
+///
+/// let err = customer_name(&Entity::Key(Key::new(1))).unwrap_err();
+/// assert!(matches!(err.error(), Error::EntityNotFetched));
+/// assert_eq!(err.to_string(), "data was not fetched from the database for this Entity");
+/// ```
+///
+/// Its `Debug` output appends the backtrace when one was captured, and the
+/// sources of the Error, like the rusqlite error behind a failed query, are
+/// chained through its `source`.
+pub struct TracedError {
+    error: Error,
+    backtrace: std::backtrace::Backtrace,
+}
+
+impl TracedError {
+    /// Returns the Error
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Returns the Error, dropping the backtrace
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+
+    /// Returns the backtrace, whose `status` tells whether it was captured
+    pub fn backtrace(&self) -> &std::backtrace::Backtrace {
+        &self.backtrace
+    }
+
+    /// Returns the Error followed by its sources, outermost first
+    pub fn chain(&self) -> impl Iterator<Item = &(dyn std::error::Error + 'static)> {
+        core::iter::successors(Some(&self.error as &(dyn std::error::Error + 'static)), |err| err.source())
+    }
+}
+
+impl From<Error> for TracedError {
+    fn from(error: Error) -> Self {
+        Self { error, backtrace: std::backtrace::Backtrace::capture() }
+    }
+}
+
+impl fmt::Display for TracedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl fmt::Debug for TracedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.error, f)?;
+        for source in self.chain().skip(1) {
+            write!(f, "\n\nCaused by: {source}")?;
+        }
+        if self.backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            write!(f, "\n\nStack backtrace:\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TracedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        std::error::Error::source(&self.error)
+    }
+}
+
+/// The result typedef capturing backtraces, see [`TracedError`]
+pub type TracedResult<T> = core::result::Result<T, TracedError>;
+
+/// Extension trait for results of entity accessors
+///
+/// Mirrors rusqlite's `OptionalExtension` for code that treats an entity that
+/// is empty or not fetched as absent.
+pub trait EntityResultExt<T> {
+    /// Maps `EntityEmpty` and `EntityNotFetched` errors to `Ok(None)`,
+    /// passing other errors through
+    fn optional(self) -> Result<Option<T>>;
+}
+
+impl<T> EntityResultExt<T> for Result<T> {
+    fn optional(self) -> Result<Option<T>> {
+        match self {
+            Ok(value) => Ok(Some(value)),
+            Err(Error::EntityEmpty | Error::EntityNotFetched) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Extension trait for iterators of keyed entities, or references to them
+///
+/// Entities without a key, like ones not inserted yet or empty relations, are
+/// skipped, so fetched collections turn into lookup structures in one call:
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use dbent::prelude::*;
+///
+/// #[derive(Entity)]
+/// struct User {
+///     id: Key<Int>,
+///     name: String,
+/// }
+///
+/// let user = |id, name: &str| User { id: Key(id), name: name.to_owned() };
+/// let users = vec![user(Some(1), "Ann"), user(None, "Bob"), user(Some(2), "Cid")];
+/// assert_eq!(users.iter().keys().collect::<Vec<_>>(), [1, 2]);
+/// assert!(users.iter().key_set().contains(&2));
+/// assert_eq!(users.iter().by_key_map()[&1].name, "Ann");
+/// assert!(users.iter().chain(&users).try_by_key_map().is_err());
+/// # }
+/// ```
+pub trait KeyedIteratorExt: Iterator + Sized
+where
+    Self::Item: Keyed,
+{
+    /// Returns the keys of the entities, in order
+    fn keys(self) -> impl Iterator<Item = <Self::Item as Keyed>::KeyType>
+    where
+        <Self::Item as Keyed>::KeyType: Clone,
+    {
+        self.filter_map(|entity| entity.key().ok()?.0.clone())
+    }
+
+    /// Returns the keys of the entities, without duplicates
+    fn key_set(self) -> HashSet<<Self::Item as Keyed>::KeyType>
+    where
+        <Self::Item as Keyed>::KeyType: Clone + Eq + Hash,
+    {
+        self.keys().collect()
+    }
+
+    /// Returns the entities by their keys, the last one winning on duplicates
+    fn by_key_map(self) -> HashMap<<Self::Item as Keyed>::KeyType, Self::Item>
+    where
+        <Self::Item as Keyed>::KeyType: Clone + Eq + Hash,
+    {
+        self.filter_map(|entity| Some((entity.key().ok()?.0.clone()?, entity))).collect()
+    }
+
+    /// Returns the entities by their keys, failing with `Error::DuplicateKey`
+    /// on the first key held by two entities
+    ///
+    /// For input where repeated keys mean corrupt data that must be reported
+    /// instead of silently overwritten, like natural keys of imported rows.
+    fn try_by_key_map(self) -> Result<HashMap<<Self::Item as Keyed>::KeyType, Self::Item>>
+    where
+        <Self::Item as Keyed>::KeyType: Clone + Eq + Hash + fmt::Debug,
+    {
+        let mut map = HashMap::new();
+        for entity in self {
+            let Some(key) = entity.key().ok().and_then(|key| key.0.clone()) else { continue };
+            match map.entry(key) {
+                std::collections::hash_map::Entry::Occupied(entry) => {
+                    return Err(Error::DuplicateKey { key: format!("{:?}", entry.key()) });
+                },
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(entity);
+                },
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl<I: Iterator> KeyedIteratorExt for I where I::Item: Keyed {}
+
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
 //<<>><======================  CONVENIENCE  =========================><<>>//
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
@@ -426,8 +3137,12 @@ pub type Int = usize;
 pub type EntityInt<T> = Entity<Int, T>;
 /// An Entity that has a String as key
 pub type EntityString<T> = Entity<String, T>;
+/// An Entity that has an i64 as key, like SQLite rowids
+pub type EntityI64<T> = Entity<i64, T>;
 /// An EntityLabel that has an Int as key
 pub type EntityLabelInt<T> = EntityLabel<Int, T, String>;
+/// An EntityLabel that has an i64 as key
+pub type EntityLabelI64<T> = EntityLabel<i64, T, String>;
 /// An EntityLabel that has a String as key
 pub type EntityLabelString<T> = EntityLabel<String, T, String>;
 
@@ -440,27 +3155,113 @@ pub mod prelude {
     //! ```
     //! use dbent::prelude::*;
     //! ```
+    //!
+    //! Crates where names like `Entity` or `Label` are taken by other libraries
+    //! can import the [traits] and [types] separately, or the [aliases] that
+    //! rename the clashing items:
+    //!
+    //! ```
+    //! use dbent::prelude::traits::*;
+    //! use dbent::prelude::aliases::{DbEntity, DbLabel};
+    //!
+    //! fn label<L: DbLabel>(entity: &DbEntity<i64, L>) -> Option<&L::LabelType> {
+    //!     entity.data().ok()?.label().ok()
+    //! }
+    //! ```
 
     #[cfg(feature = "derive")]
     pub use dbent_derive::{
+        DbEnum,
+        Denormalized,
         Entity,
+        IntoDto,
         Label,
+        Projection,
+        Validate,
     };
 
+    #[cfg(all(feature = "derive", feature = "serde"))]
+    pub use dbent_derive::serde_relations;
+
+    #[cfg(all(feature = "derive", feature = "rusqlite"))]
+    pub use dbent_derive::FromRow;
+
+    pub use self::traits::*;
+    pub use self::types::*;
+
     pub use crate::{
-        Key,
-        Keyed,
-        Label,
-        Tagged,
-        Tag,
-        Entity,
-        EntityLabel,
-        Many,
-        Int,
-        EntityInt,
-        EntityString,
-        EntityLabelInt,
-        EntityLabelString,
+        collect_keys,
+        collect_many_keys,
+        hydrate_entities,
+        join_by_key,
     };
+
+    pub mod traits {
+        //! Re-export of the common traits only
+
+        pub use crate::{
+            Keyed,
+            KeyedMut,
+            KeyedInfallible,
+            Label,
+            NormalizedLabel,
+            Tagged,
+            TagMeta,
+            Tags,
+            AnyKeyed,
+            AnyTagged,
+            EntityResultExt,
+            KeyedIteratorExt,
+            validate::Validate,
+            hooks::EntityHooks,
+            repository::{Repository, Fetch},
+            timestamps::Timestamps,
+            version::Versioned,
+            position::Positioned,
+        };
+    }
+
+    pub mod types {
+        //! Re-export of the common types only
+
+        pub use crate::{
+            Key,
+            Tag,
+            TagExt,
+            Entity,
+            Fetched,
+            Unfetched,
+            Parent,
+            WeakEntity,
+            poly::PolyEntity,
+            EntityLabel,
+            Many,
+            SharedMany,
+            Link,
+            LinkKey,
+            Int,
+            KeyAllocator,
+            ScopedKey,
+            KeyEq,
+            CiString,
+            version::Version,
+            EntityInt,
+            EntityI64,
+            EntityString,
+            EntityLabelInt,
+            EntityLabelI64,
+            EntityLabelString,
+        };
+    }
+
+    pub mod aliases {
+        //! Re-export of the items whose names often clash with other crates,
+        //! renamed with a `Db` prefix
+
+        pub use crate::{
+            Entity as DbEntity,
+            Label as DbLabel,
+        };
+    }
 }
 
@@ -0,0 +1,63 @@
+//! Guard rails on `Many` hydration
+//!
+//! A lazy load of a `Many` reads every row of the relation, which is fine
+//! for a dozen and freezes an interactive app for a million. The limit set
+//! with [`set_max_many_rows`] makes [`Many::ensure_fetched`] and
+//! [`Many::fetch_limited`] fail with `Error::TooManyRows` for relations
+//! holding more rows, leaving the `Many` NotFetched. No limit is set by
+//! default.
+//!
+//! [`Many::fetch_limited`] also takes a limit of its own and hands the loader
+//! one more than the limit, so it can stop reading there with a `LIMIT`:
+//!
+//! ```
+//! use dbent::prelude::*;
+//! use dbent::Error;
+//!
+//! let mut lines = Many::<i64>::NotFetched;
+//! let result = lines.fetch_limited(|limit| Ok((0..1_000_000).take(limit).collect()), 100);
+//! assert!(matches!(result, Err(Error::TooManyRows { limit: 100 })));
+//! assert!(lines.is_not_fetched());
+//!
+//! assert_eq!(lines.fetch_limited(|limit| Ok((0..10).take(limit).collect()), 100).unwrap().len(), 10);
+//! ```
+//!
+//! [`Many::ensure_fetched`]: crate::Many::ensure_fetched
+//! [`Many::fetch_limited`]: crate::Many::fetch_limited
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Error, Result};
+
+static MAX_MANY_ROWS: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Sets the most rows a `Many` can be loaded with, replacing the previous limit
+pub fn set_max_many_rows(max: usize) {
+    MAX_MANY_ROWS.store(max, Ordering::Relaxed);
+}
+
+/// Removes the limit on the rows of a `Many`
+pub fn clear_max_many_rows() {
+    MAX_MANY_ROWS.store(usize::MAX, Ordering::Relaxed);
+}
+
+/// Returns the most rows a `Many` can be loaded with, if limited
+pub fn max_many_rows() -> Option<usize> {
+    match MAX_MANY_ROWS.load(Ordering::Relaxed) {
+        usize::MAX => None,
+        max => Some(max),
+    }
+}
+
+/// Returns the lower of `max` and the global limit
+pub(crate) fn effective_limit(max: usize) -> usize {
+    max.min(MAX_MANY_ROWS.load(Ordering::Relaxed))
+}
+
+/// Fails with `Error::TooManyRows` if `rows` exceeds `limit`
+pub(crate) fn check_rows(rows: usize, limit: usize) -> Result<()> {
+    if rows > limit {
+        return Err(Error::TooManyRows { limit });
+    }
+    Ok(())
+}
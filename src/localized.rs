@@ -0,0 +1,212 @@
+//! Labels in several languages
+//!
+//! A [`LocalizedLabel`] maps locales to the text of a label, so reference
+//! data like countries or categories can use it as their `LabelType` and
+//! still be tagged. `Tagged` uses the default text, the one of the first
+//! locale added, and [`LocalizedLabels`] picks the text of the locales a
+//! user prefers, falling back from a regional locale like `pt-BR` to its
+//! language `pt`, and then to the default. With serde, LocalizedLabels are
+//! maps from locales to texts, in order:
+//!
+//! ```
+//! use dbent::localized::{LocalizedLabel, LocalizedLabels};
+//! use dbent::prelude::*;
+//!
+//! struct Color {
+//!     id: Key<Int>,
+//!     name: LocalizedLabel,
+//! }
+//!
+//! impl Keyed for Color {
+//!     type KeyType = Int;
+//!
+//!     fn key(&self) -> dbent::Result<&Key<Int>> {
+//!         Ok(&self.id)
+//!     }
+//! }
+//!
+//! impl Label for Color {
+//!     type LabelType = LocalizedLabel;
+//!
+//!     fn label(&self) -> dbent::Result<&LocalizedLabel> {
+//!         Ok(&self.name)
+//!     }
+//! }
+//!
+//! let red = Color { id: Key::new(1), name: LocalizedLabel::new().with("en", "Red").with("pt", "Vermelho") };
+//! assert_eq!(red.tag().unwrap(), Tag::new("1", "Red"));
+//! assert_eq!(red.tag_in(&["pt-BR", "en"]).unwrap(), Tag::new("1", "Vermelho"));
+//! assert_eq!(red.label_in(&["fr"]).unwrap(), "Red");
+//!
+//! # #[cfg(feature = "serde")]
+//! assert_eq!(serde_json::to_string(&red.name).unwrap(), r#"{"en":"Red","pt":"Vermelho"}"#);
+//! ```
+
+use core::fmt;
+use std::borrow::Cow;
+
+use crate::{normalize_label, Error, Key, Keyed, Label, NormalizedLabel, Result, Tag};
+
+/// The texts of a label by locale, in the order they were added
+///
+/// The first text is the default one, shown by `Display` and used for Tags
+/// and lookups by normalized label.
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct LocalizedLabel {
+    texts: Vec<(String, String)>,
+}
+
+impl LocalizedLabel {
+    /// Creates a LocalizedLabel without texts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the LocalizedLabel with the text of the locale set
+    pub fn with(mut self, locale: impl Into<String>, text: impl Into<String>) -> Self {
+        self.insert(locale, text);
+        self
+    }
+
+    /// Sets the text of the locale, returning the previous one
+    pub fn insert(&mut self, locale: impl Into<String>, text: impl Into<String>) -> Option<String> {
+        let (locale, text) = (locale.into(), text.into());
+        match self.texts.iter_mut().find(|(name, _)| *name == locale) {
+            Some((_, previous)) => Some(core::mem::replace(previous, text)),
+            None => {
+                self.texts.push((locale, text));
+                None
+            },
+        }
+    }
+
+    /// Returns the text of the locale, without fallbacks
+    pub fn get(&self, locale: &str) -> Option<&str> {
+        self.texts.iter().find(|(name, _)| name == locale).map(|(_, text)| text.as_str())
+    }
+
+    /// Returns the default text, the one of the first locale added
+    pub fn default_text(&self) -> Option<&str> {
+        self.texts.first().map(|(_, text)| text.as_str())
+    }
+
+    /// Returns the text of the first of the `locales` it has, trying the
+    /// language of each regional locale after it, or the default text
+    pub fn resolve(&self, locales: &[&str]) -> Option<&str> {
+        locales
+            .iter()
+            .find_map(|locale| self.get(locale).or_else(|| self.get(language(locale)?)))
+            .or_else(|| self.default_text())
+    }
+
+    /// Returns the locales and their texts, in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.texts.iter().map(|(locale, text)| (locale.as_str(), text.as_str()))
+    }
+
+    /// Returns the number of locales
+    pub fn len(&self) -> usize {
+        self.texts.len()
+    }
+
+    /// Returns true if there are no texts
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+}
+
+/// Returns the language of a regional locale, like `pt` for `pt-BR` or `pt_BR`
+fn language(locale: &str) -> Option<&str> {
+    locale.split_once(['-', '_']).map(|(language, _)| language)
+}
+
+impl<L: Into<String>, T: Into<String>> FromIterator<(L, T)> for LocalizedLabel {
+    fn from_iter<I: IntoIterator<Item = (L, T)>>(iter: I) -> Self {
+        let mut label = Self::new();
+        for (locale, text) in iter {
+            label.insert(locale, text);
+        }
+        label
+    }
+}
+
+/// Displays the default text
+impl fmt::Display for LocalizedLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.default_text().unwrap_or_default())
+    }
+}
+
+/// LocalizedLabels are looked up by their default text
+impl NormalizedLabel for LocalizedLabel {
+    fn normalized(&self) -> Cow<'_, str> {
+        normalize_label(self.default_text().unwrap_or_default())
+    }
+}
+
+/// Accessors for the label of entities labeled with a [`LocalizedLabel`],
+/// in the locales a user prefers
+///
+/// There is a blanket implementation for all entities whose `LabelType` is
+/// `LocalizedLabel`.
+pub trait LocalizedLabels {
+    /// Returns the text of the label for the first of the `locales` it has,
+    /// like [`LocalizedLabel::resolve`], failing with `Error::LabelEmpty`
+    /// when the label has no texts
+    fn label_in(&self, locales: &[&str]) -> Result<&str>;
+
+    /// Returns the Tag for the Entity with the label text of `label_in`,
+    /// failing with `Error::KeyMissing` when the Key is unset
+    fn tag_in(&self, locales: &[&str]) -> Result<Tag>
+    where
+        Self: Keyed,
+        <Self as Keyed>::KeyType: fmt::Display;
+}
+
+impl<T: Label<LabelType = LocalizedLabel> + ?Sized> LocalizedLabels for T {
+    fn label_in(&self, locales: &[&str]) -> Result<&str> {
+        self.label()?.resolve(locales).ok_or(Error::LabelEmpty)
+    }
+
+    fn tag_in(&self, locales: &[&str]) -> Result<Tag>
+    where
+        Self: Keyed,
+        <Self as Keyed>::KeyType: fmt::Display,
+    {
+        let key: &Key<<Self as Keyed>::KeyType> = self.key()?;
+        let key = key.0.as_ref().ok_or(Error::KeyMissing)?;
+        Ok(Tag::new(key.to_string(), self.label_in(locales)?.to_owned()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LocalizedLabel {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LocalizedLabel {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = LocalizedLabel;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map of locales to texts")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> core::result::Result<Self::Value, A::Error> {
+                let mut label = LocalizedLabel::new();
+                while let Some((locale, text)) = map.next_entry::<String, String>()? {
+                    label.insert(locale, text);
+                }
+                Ok(label)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
@@ -0,0 +1,175 @@
+//! Fetch instrumentation
+//!
+//! [`set_observer`] installs a process-wide [`FetchObserver`] that is told
+//! about every fetch made through [`Fetch`](crate::repository::Fetch), with
+//! the entity type and whether the entities were fetched one by one or in a
+//! batch. Nothing is recorded until an observer is set.
+//!
+//! [`FetchCounter`] counts fetches per entity type to find N+1 patterns, like
+//! orders fetched one by one in a loop instead of with a single `fetch_all`:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use std::sync::Arc;
+//!
+//! use dbent::prelude::*;
+//! use dbent::observe::{self, FetchCounter};
+//! use dbent::repository::MemoryRepository;
+//!
+//! #[derive(Entity, Clone)]
+//! struct Order {
+//!     id: Key<Int>,
+//! }
+//!
+//! let orders = MemoryRepository::new();
+//! for _ in 0..50 {
+//!     orders.insert(&mut Order { id: Key(None) }).unwrap();
+//! }
+//!
+//! let counter = Arc::new(FetchCounter::new());
+//! observe::set_observer(counter.clone());
+//!
+//! for id in 1..=50 {
+//!     orders.fetch(&mut Entity::<Int, Order>::Key(Key::new(id))).unwrap();
+//! }
+//! let suspects = counter.one_by_one(10);
+//! assert_eq!(suspects.len(), 1);
+//! assert_eq!(suspects[0].1, 50);
+//!
+//! observe::clear_observer();
+//! # }
+//! ```
+
+use core::any::type_name;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// How entities were fetched
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FetchKind {
+    /// A single entity by key
+    One,
+    /// The given number of entities by key, at once
+    Batch(usize),
+    /// The data of a `Many`
+    Many,
+}
+
+/// A fetch made through [`Fetch`](crate::repository::Fetch)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FetchEvent {
+    /// The type name of the fetched entities
+    pub entity: &'static str,
+    /// How they were fetched
+    pub kind: FetchKind,
+}
+
+/// Receives the fetches made while it is installed with [`set_observer`]
+pub trait FetchObserver: Send + Sync {
+    /// Called after entities were fetched
+    fn on_fetch(&self, event: &FetchEvent);
+}
+
+impl<O: FetchObserver + ?Sized> FetchObserver for Arc<O> {
+    fn on_fetch(&self, event: &FetchEvent) {
+        (**self).on_fetch(event)
+    }
+}
+
+static OBSERVED: AtomicBool = AtomicBool::new(false);
+static OBSERVER: RwLock<Option<Box<dyn FetchObserver>>> = RwLock::new(None);
+
+/// Installs the observer, replacing the previous one
+pub fn set_observer(observer: impl FetchObserver + 'static) {
+    *OBSERVER.write().unwrap_or_else(|err| err.into_inner()) = Some(Box::new(observer));
+    OBSERVED.store(true, Ordering::Release);
+}
+
+/// Removes the observer, if any
+pub fn clear_observer() {
+    OBSERVED.store(false, Ordering::Release);
+    *OBSERVER.write().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+/// Tells the observer, if any, that entities of type `T` were fetched
+pub(crate) fn notify<T>(kind: FetchKind) {
+    if !OBSERVED.load(Ordering::Acquire) {
+        return;
+    }
+    if let Some(observer) = &*OBSERVER.read().unwrap_or_else(|err| err.into_inner()) {
+        observer.on_fetch(&FetchEvent { entity: type_name::<T>(), kind });
+    }
+}
+
+/// The fetches of an entity type counted by a [`FetchCounter`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct FetchCounts {
+    /// Entities fetched one by one
+    pub one: usize,
+    /// Batches fetched
+    pub batches: usize,
+    /// Entities fetched in batches
+    pub batched: usize,
+    /// `Many`s fetched
+    pub many: usize,
+}
+
+/// A [`FetchObserver`] counting fetches per entity type
+///
+/// The counts cover a scope, from its creation or last [`reset`](Self::reset)
+/// on, such as a request or a test.
+#[derive(Default, Debug)]
+pub struct FetchCounter {
+    counts: Mutex<BTreeMap<&'static str, FetchCounts>>,
+}
+
+impl FetchCounter {
+    /// Creates a counter with no fetches
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the counts of each entity type fetched
+    pub fn counts(&self) -> BTreeMap<&'static str, FetchCounts> {
+        self.counts.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Returns the counts of the entity type `T`
+    pub fn counts_of<T>(&self) -> FetchCounts {
+        self.counts().get(type_name::<T>()).copied().unwrap_or_default()
+    }
+
+    /// Returns the entity types fetched one by one at least `threshold`
+    /// times, the likely N+1 queries, most fetched first
+    pub fn one_by_one(&self, threshold: usize) -> Vec<(&'static str, usize)> {
+        let mut suspects = self
+            .counts()
+            .into_iter()
+            .filter(|(_, counts)| counts.one >= threshold)
+            .map(|(entity, counts)| (entity, counts.one))
+            .collect::<Vec<_>>();
+        suspects.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        suspects
+    }
+
+    /// Forgets the fetches counted so far, starting a new scope
+    pub fn reset(&self) {
+        self.counts.lock().unwrap_or_else(|err| err.into_inner()).clear();
+    }
+}
+
+impl FetchObserver for FetchCounter {
+    fn on_fetch(&self, event: &FetchEvent) {
+        let mut counts = self.counts.lock().unwrap_or_else(|err| err.into_inner());
+        let counts = counts.entry(event.entity).or_default();
+        match event.kind {
+            FetchKind::One => counts.one += 1,
+            FetchKind::Batch(len) => {
+                counts.batches += 1;
+                counts.batched += len;
+            },
+            FetchKind::Many => counts.many += 1,
+        }
+    }
+}
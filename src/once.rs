@@ -0,0 +1,141 @@
+//! Entities loaded once and shared between threads
+//!
+//! An `Entity` is loaded through `&mut`, so reference data read everywhere,
+//! like currencies or settings, has to be loaded up front or put behind a
+//! lock. A [`OnceEntity`] holds the key of such an entity and loads its data
+//! on first use through `&`, from any number of threads: the first one to
+//! call [`get_or_load`](OnceEntity::get_or_load) runs the loader while the
+//! others wait for it, and once loaded the data is read without locking. A
+//! failed load leaves it unloaded, so the next call tries again.
+//!
+//! ```
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! use dbent::once::OnceEntity;
+//! use dbent::prelude::*;
+//!
+//! static EURO: OnceEntity<i64, String> = OnceEntity::new(Key(Some(978)));
+//! static LOADS: AtomicUsize = AtomicUsize::new(0);
+//!
+//! fn euro() -> dbent::Result<&'static String> {
+//!     EURO.get_or_load(|&code| {
+//!         LOADS.fetch_add(1, Ordering::Relaxed);
+//!         Ok(format!("Euro ({code})"))
+//!     })
+//! }
+//!
+//! std::thread::scope(|scope| {
+//!     for _ in 0..4 {
+//!         scope.spawn(|| assert_eq!(euro().unwrap(), "Euro (978)"));
+//!     }
+//! });
+//! assert_eq!(LOADS.load(Ordering::Relaxed), 1);
+//! assert!(EURO.is_loaded());
+//! ```
+
+use core::fmt;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use crate::{Entity, Error, Key, Keyed, Result};
+
+/// The key of an entity with its data loaded at most once, shared between threads
+pub struct OnceEntity<K, T> {
+    key: Key<K>,
+    data: OnceLock<T>,
+    loading: Mutex<()>,
+}
+
+impl<K, T> OnceEntity<K, T> {
+    /// Creates a OnceEntity with the key, not loaded yet, which can be a `static`
+    pub const fn new(key: Key<K>) -> Self {
+        Self { key, data: OnceLock::new(), loading: Mutex::new(()) }
+    }
+
+    /// Creates a OnceEntity already loaded with the data, with its key
+    pub fn with_data(data: T) -> Result<Self>
+    where
+        K: Clone,
+        T: Keyed<KeyType = K>,
+    {
+        let entity = Self::new(data.key()?.clone());
+        let _ = entity.data.set(data);
+        Ok(entity)
+    }
+
+    /// Returns the data if it was loaded, failing with `Error::EntityNotFetched`
+    pub fn data(&self) -> Result<&T> {
+        self.data.get().ok_or(Error::EntityNotFetched)
+    }
+
+    /// Is the data loaded?
+    pub fn is_loaded(&self) -> bool {
+        self.data.get().is_some()
+    }
+
+    /// Returns the data, loading it with `load` first if no thread did yet
+    ///
+    /// Calls racing to load it wait for the one running `load`, which runs
+    /// once unless it fails. Fails with `Error::KeyEmpty` for an unset Key,
+    /// without calling `load`.
+    pub fn get_or_load(&self, load: impl FnOnce(&K) -> Result<T>) -> Result<&T> {
+        if let Some(data) = self.data.get() {
+            return Ok(data);
+        }
+        let key = self.key.as_ref().ok_or(Error::KeyEmpty)?;
+
+        let _loading = self.loading.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(data) = self.data.get() {
+            return Ok(data);
+        }
+        let data = load(key)?;
+        Ok(self.data.get_or_init(|| data))
+    }
+
+    /// Converts into an `Entity`, Data if loaded or Key otherwise
+    pub fn into_entity(self) -> Entity<K, T> {
+        match self.data.into_inner() {
+            Some(data) => Entity::Data(Box::new(data)),
+            None => Entity::Key(self.key),
+        }
+    }
+}
+
+impl<K, T> Keyed for OnceEntity<K, T> {
+    type KeyType = K;
+
+    fn key(&self) -> Result<&Key<K>> {
+        Ok(&self.key)
+    }
+}
+
+impl<K, T> From<Key<K>> for OnceEntity<K, T> {
+    fn from(key: Key<K>) -> Self {
+        Self::new(key)
+    }
+}
+
+/// Takes the key of a Key, and the key and data of a Data, failing with
+/// `Error::EntityEmpty` for None
+impl<K: Clone, T: Keyed<KeyType = K>> TryFrom<Entity<K, T>> for OnceEntity<K, T> {
+    type Error = Error;
+
+    fn try_from(entity: Entity<K, T>) -> Result<Self> {
+        match entity {
+            Entity::Key(key) => Ok(Self::new(key)),
+            Entity::Data(data) => Self::with_data(*data),
+            Entity::None => Err(Error::EntityEmpty),
+        }
+    }
+}
+
+impl<K: Clone, T: Clone> Clone for OnceEntity<K, T> {
+    fn clone(&self) -> Self {
+        Self { key: self.key.clone(), data: self.data.clone(), loading: Mutex::new(()) }
+    }
+}
+
+impl<K: fmt::Debug, T: fmt::Debug> fmt::Debug for OnceEntity<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnceEntity").field("key", &self.key).field("data", &self.data.get()).finish()
+    }
+}
@@ -0,0 +1,221 @@
+//! Outbox of domain events
+//!
+//! An [`EventMapper`] turns the [`AuditEntry`] of a write, with its
+//! [`Changeset`](crate::changeset::Changeset), into the [`OutboxEvent`]s
+//! downstream consumers are notified of. With the `rusqlite` feature,
+//! [`SqliteOutbox`] is an [`AuditSink`] writing them to an `outbox` table, so
+//! an [`AuditedRepository`](crate::audit::AuditedRepository) over a
+//! [`TxContext`](crate::sqlite::TxContext) commits the events in the same
+//! transaction as the writes, and a relay publishes the
+//! [pending](SqliteOutbox::pending) ones and
+//! [acknowledges](SqliteOutbox::acknowledge) them.
+//!
+//! ```
+//! # #[cfg(all(feature = "derive", feature = "rusqlite"))] {
+//! # mod example {
+//! use dbent::prelude::*;
+//! use dbent::audit::{AuditAction, AuditEntry, AuditedRepository};
+//! use dbent::outbox::{OutboxEvent, SqliteOutbox};
+//! use dbent::schema::Schema;
+//! use dbent::sql::Value;
+//! use dbent::sqlite::{FromRow, TxContext};
+//!
+//! #[derive(Entity, FromRow, Clone)]
+//! #[entity(fields)]
+//! pub struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! fn renames(entry: &AuditEntry) -> Vec<OutboxEvent> {
+//!     match entry.changes.get("name").map(|change| &change.new) {
+//!         Some(Value::Text(name)) if entry.action == AuditAction::Update => {
+//!             vec![OutboxEvent::new("user.renamed", entry).with_payload(name.clone())]
+//!         },
+//!         _ => Vec::new(),
+//!     }
+//! }
+//!
+//! # pub fn main() -> dbent::Result<()> {
+//! let mut conn = rusqlite::Connection::open_in_memory()?;
+//! conn.execute(&User::entity_meta().create_table_sql(), [])?;
+//!
+//! let tx = TxContext::begin(&mut conn)?;
+//! let outbox = SqliteOutbox::new(&tx, renames);
+//! outbox.create_table()?;
+//! let users = AuditedRepository::new(&tx, &outbox, "admin");
+//! let mut user = User { id: Key(None), name: "Ann".to_owned() };
+//! users.insert(&mut user)?;
+//! user.name = "Anne".to_owned();
+//! users.update(&mut user)?;
+//! drop(users);
+//! tx.commit()?;
+//!
+//! let outbox = SqliteOutbox::new(&conn, renames);
+//! let pending = outbox.pending(10)?;
+//! assert_eq!(pending[0].1.topic, "user.renamed");
+//! assert_eq!(pending[0].1.payload, "Anne");
+//! outbox.acknowledge(&[pending[0].0])?;
+//! assert!(outbox.pending(10)?.is_empty());
+//! # Ok(())
+//! # }
+//! # }
+//! # example::main().unwrap();
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+use crate::audit::AuditEntry;
+#[cfg(feature = "rusqlite")]
+use crate::audit::AuditSink;
+#[cfg(feature = "rusqlite")]
+use crate::sql::quote_ident;
+#[cfg(feature = "rusqlite")]
+use crate::Result;
+
+/// A domain event to notify downstream consumers of
+#[derive(Clone, PartialEq, Debug)]
+pub struct OutboxEvent {
+    /// The kind of event, like `"user.renamed"`
+    pub topic: String,
+    /// The table of the changed entity
+    pub entity: Cow<'static, str>,
+    /// The key of the changed entity as text, if set
+    pub key: Option<String>,
+    /// The body of the event, in the encoding consumers expect
+    pub payload: String,
+    /// When the change was made
+    pub at: SystemTime,
+}
+
+impl OutboxEvent {
+    /// Creates an event about the entity of the entry, with an empty payload
+    pub fn new(topic: impl Into<String>, entry: &AuditEntry) -> Self {
+        Self {
+            topic: topic.into(),
+            entity: entry.entity.clone(),
+            key: entry.key.clone(),
+            payload: String::new(),
+            at: entry.at,
+        }
+    }
+
+    /// Sets the payload
+    pub fn with_payload(mut self, payload: impl Into<String>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+}
+
+/// Maps the writes recorded as audit entries to domain events
+///
+/// Implemented by functions and closures taking an entry.
+pub trait EventMapper {
+    /// Returns the events of the write, none if consumers don't care about it
+    fn events(&self, entry: &AuditEntry) -> Vec<OutboxEvent>;
+}
+
+impl<F: Fn(&AuditEntry) -> Vec<OutboxEvent>> EventMapper for F {
+    fn events(&self, entry: &AuditEntry) -> Vec<OutboxEvent> {
+        self(entry)
+    }
+}
+
+/// An [`AuditSink`] writing the events mapped from the entries to a table,
+/// with the `rusqlite` feature
+///
+/// Each event is a row holding an increasing id, the topic, entity, key,
+/// payload and time in milliseconds since the Unix epoch.
+#[cfg(feature = "rusqlite")]
+pub struct SqliteOutbox<'c, M> {
+    conn: &'c rusqlite::Connection,
+    table: Cow<'static, str>,
+    mapper: M,
+}
+
+#[cfg(feature = "rusqlite")]
+impl<'c, M: EventMapper> SqliteOutbox<'c, M> {
+    /// Creates an outbox writing to the `outbox` table
+    pub fn new(conn: &'c rusqlite::Connection, mapper: M) -> Self {
+        Self::with_table(conn, "outbox", mapper)
+    }
+
+    /// Creates an outbox writing to `table`
+    pub fn with_table(conn: &'c rusqlite::Connection, table: impl Into<Cow<'static, str>>, mapper: M) -> Self {
+        Self { conn, table: table.into(), mapper }
+    }
+
+    /// Returns the name of the table
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Returns the mapper of the entries
+    pub fn mapper(&self) -> &M {
+        &self.mapper
+    }
+
+    /// Creates the table if it doesn't exist
+    pub fn create_table(&self) -> Result<()> {
+        self.conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (\
+                    id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                    topic TEXT NOT NULL, \
+                    entity TEXT NOT NULL, \
+                    key TEXT, \
+                    payload TEXT NOT NULL, \
+                    at INTEGER NOT NULL\
+                )",
+                quote_ident(&self.table),
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Returns up to `limit` events not acknowledged yet with their ids,
+    /// oldest first
+    pub fn pending(&self, limit: usize) -> Result<Vec<(i64, OutboxEvent)>> {
+        use std::time::Duration;
+
+        let sql = format!("SELECT id, topic, entity, key, payload, at FROM {} ORDER BY id LIMIT ?", quote_ident(&self.table));
+        let mut statement = self.conn.prepare_cached(&sql)?;
+        let rows = statement.query_map([i64::try_from(limit).unwrap_or(i64::MAX)], |row| {
+            let at = Duration::from_millis(row.get::<_, i64>(5)?.try_into().unwrap_or_default());
+            Ok((row.get(0)?, OutboxEvent {
+                topic: row.get(1)?,
+                entity: Cow::Owned(row.get(2)?),
+                key: row.get(3)?,
+                payload: row.get(4)?,
+                at: SystemTime::UNIX_EPOCH + at,
+            }))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Removes the events with the given ids, once they are published
+    pub fn acknowledge(&self, ids: &[i64]) -> Result<()> {
+        let sql = format!("DELETE FROM {} WHERE id = ?", quote_ident(&self.table));
+        let mut statement = self.conn.prepare_cached(&sql)?;
+        for id in ids {
+            statement.execute([id])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl<M: EventMapper> AuditSink for SqliteOutbox<'_, M> {
+    fn record(&self, entry: AuditEntry) -> Result<()> {
+        let sql = format!("INSERT INTO {} (topic, entity, key, payload, at) VALUES (?, ?, ?, ?, ?)", quote_ident(&self.table));
+        let mut statement = self.conn.prepare_cached(&sql)?;
+        for event in self.mapper.events(&entry) {
+            let at = event.at.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as i64);
+            statement.execute(rusqlite::params![event.topic, event.entity.as_ref(), event.key, event.payload, at])?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,396 @@
+//! Relations to one of several entity types
+//!
+//! A [`PolyEntity`] references an entity of one of the types of a tuple, like
+//! the post or the photo a comment was made on. It keeps the table of the
+//! referenced entity as its tag, next to the Key, so it maps to two columns:
+//! `{column}` with the key and `{column}_type` with the table name.
+//!
+//! With `#[entity(fields)]`, both columns are part of the metadata and the
+//! values of the entity, and `#[derive(FromRow)]` reads them back. Serde
+//! writes the tag as the key of a map holding the serialized `Entity`, like
+//! `{"post":{"Key":1}}`, and `"None"` for no reference:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::poly::{OneOf2, PolyEntity};
+//! use dbent::prelude::*;
+//! use dbent::schema::Schema;
+//! use dbent::sql::{Columns, Value};
+//!
+//! #[derive(Entity)]
+//! #[entity(fields)]
+//! pub struct Post {
+//!     id: Key<Int>,
+//! }
+//!
+//! #[derive(Entity)]
+//! #[entity(fields)]
+//! pub struct Photo {
+//!     id: Key<Int>,
+//! }
+//!
+//! #[derive(Entity)]
+//! #[entity(fields)]
+//! pub struct Comment {
+//!     id: Key<Int>,
+//!     commentable: PolyEntity<(Post, Photo)>,
+//! }
+//!
+//! # pub fn main() {
+//! let columns = Comment::entity_meta().columns.into_iter().map(|column| column.name).collect::<Vec<_>>();
+//! assert_eq!(columns, ["id", "commentable", "commentable_type"]);
+//!
+//! let comment = Comment { id: Key::new(1), commentable: PolyEntity::from_key::<Photo>(Key::new(7)) };
+//! assert_eq!(comment.column_value("commentable_type"), Some(Value::Text("photo".to_owned())));
+//!
+//! let post = PolyEntity::<(Post, Photo)>::from(OneOf2::A(Post { id: Key::new(3) }));
+//! assert_eq!(post.tag().unwrap(), "post");
+//! assert_eq!(post.key().unwrap(), &Key::new(3));
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use ::serde::de::{self, Deserialize, Deserializer, MapAccess, Unexpected, Visitor};
+#[cfg(feature = "serde")]
+use ::serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::graph::Graph;
+use crate::schema::Schema;
+use crate::{sql, Error, Key, Keyed, Result};
+#[cfg(feature = "serde")]
+use crate::Entity;
+
+use self::__private::DataGraph;
+#[cfg(feature = "serde")]
+use self::__private::PolySerde;
+
+/// Trait for the tuples of entity types a [`PolyEntity`] can reference
+///
+/// Implemented for tuples of two to four types deriving `Entity` with
+/// `#[entity(fields)]`, which all share the same key type.
+pub trait PolyTargets {
+    /// The type of the key shared by the entity types
+    type KeyType;
+    /// The enum holding the data of one of the entity types
+    type Data;
+
+    /// Returns the tags of the entity types, their table names, in order
+    fn tags() -> Vec<Cow<'static, str>>;
+
+    /// Returns the tag of the entity type of the data
+    fn tag_of(data: &Self::Data) -> Cow<'static, str>;
+
+    /// Returns the Key of the data
+    fn key_of(data: &Self::Data) -> Result<&Key<Self::KeyType>>;
+}
+
+/// Enum for a reference to an entity of one of the types of `T`, holding its
+/// tag and Key or the created/fetched data
+#[derive(Default)]
+pub enum PolyEntity<T: PolyTargets> {
+    /// Tag and Key of the entity
+    Key(Cow<'static, str>, Key<T::KeyType>),
+    /// Created/Fetched data for the entity
+    Data(Box<T::Data>),
+    #[default]
+    /// For when you have no data to fill or null from database
+    None,
+}
+
+impl<T: PolyTargets> PolyEntity<T> {
+    /// Creates a reference to the entity of type `E` with the key
+    ///
+    /// `E` must be one of the types of `T`.
+    pub fn from_key<E: Schema>(key: Key<T::KeyType>) -> Self {
+        debug_assert!(T::tags().contains(&E::table()), "{} isn't a target of the PolyEntity", E::table());
+        PolyEntity::Key(E::table(), key)
+    }
+
+    /// Creates a PolyEntity from its key and tag columns, None for a NULL key
+    ///
+    /// Fails with `Error::UnknownVariant` for a tag that isn't the table of
+    /// one of the types of `T`, or a missing tag.
+    pub fn from_columns(key: Key<T::KeyType>, tag: Option<String>) -> Result<Self> {
+        if key.0.is_none() {
+            return Ok(PolyEntity::None);
+        }
+        let unknown = |value| Error::UnknownVariant { name: "PolyEntity", value };
+        let tag = tag.ok_or_else(|| unknown(sql::Value::Null))?;
+        match T::tags().into_iter().find(|target| *target == tag) {
+            Some(target) => Ok(PolyEntity::Key(target, key)),
+            None => Err(unknown(sql::Value::Text(tag))),
+        }
+    }
+
+    /// Returns the tag, the table of the referenced entity, unless None
+    pub fn tag(&self) -> Option<Cow<'static, str>> {
+        match self {
+            PolyEntity::Key(tag, _) => Some(tag.clone()),
+            PolyEntity::Data(data) => Some(T::tag_of(data)),
+            PolyEntity::None => None,
+        }
+    }
+
+    /// Returns the data if it exists and was fetched/created
+    pub fn data(&self) -> Result<&T::Data> {
+        match self {
+            PolyEntity::Data(data) => Ok(data),
+            PolyEntity::Key(..) => Err(Error::EntityNotFetched),
+            PolyEntity::None => Err(Error::EntityEmpty),
+        }
+    }
+
+    /// Returns the mutable data if it exists and was fetched/created
+    pub fn data_mut(&mut self) -> Result<&mut T::Data> {
+        match self {
+            PolyEntity::Data(ref mut data) => Ok(data),
+            PolyEntity::Key(..) => Err(Error::EntityNotFetched),
+            PolyEntity::None => Err(Error::EntityEmpty),
+        }
+    }
+
+    /// Is this a Key variant?
+    pub fn is_key(&self) -> bool {
+        matches!(self, Self::Key(..))
+    }
+
+    /// Is this a Data variant?
+    pub fn is_data(&self) -> bool {
+        matches!(self, Self::Data(..))
+    }
+
+    /// Is this a None variant?
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+}
+
+impl<T: PolyTargets> Keyed for PolyEntity<T> {
+    type KeyType = T::KeyType;
+
+    fn key(&self) -> Result<&Key<Self::KeyType>> {
+        match self {
+            PolyEntity::Key(_, key) => Ok(key),
+            PolyEntity::Data(data) => T::key_of(data),
+            PolyEntity::None => Err(Error::EntityEmpty),
+        }
+    }
+}
+
+impl<T: PolyTargets> Clone for PolyEntity<T>
+where
+    T::KeyType: Clone,
+    T::Data: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            PolyEntity::Key(tag, key) => PolyEntity::Key(tag.clone(), key.clone()),
+            PolyEntity::Data(data) => PolyEntity::Data(data.clone()),
+            PolyEntity::None => PolyEntity::None,
+        }
+    }
+}
+
+impl<T: PolyTargets> PartialEq for PolyEntity<T>
+where
+    T::KeyType: PartialEq,
+    T::Data: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PolyEntity::Key(tag, key), PolyEntity::Key(other_tag, other_key)) => tag == other_tag && key == other_key,
+            (PolyEntity::Data(data), PolyEntity::Data(other)) => data == other,
+            (PolyEntity::None, PolyEntity::None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: PolyTargets> fmt::Debug for PolyEntity<T>
+where
+    T::KeyType: fmt::Debug,
+    T::Data: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolyEntity::Key(tag, key) => f.debug_tuple("Key").field(tag).field(key).finish(),
+            PolyEntity::Data(data) => f.debug_tuple("Data").field(data).finish(),
+            PolyEntity::None => f.write_str("None"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: PolySerde> Serialize for PolyEntity<T>
+where
+    T::KeyType: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        match self {
+            PolyEntity::Key(tag, key) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(tag, &Entity::<&T::KeyType, ()>::Key(Key(key.0.as_ref())))?;
+                map.end()
+            },
+            PolyEntity::Data(data) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                T::serialize_data(data, &mut map)?;
+                map.end()
+            },
+            PolyEntity::None => serializer.serialize_unit_variant("PolyEntity", 2, "None"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: PolySerde> Deserialize<'de> for PolyEntity<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        struct PolyVisitor<T>(core::marker::PhantomData<T>);
+
+        impl<'de, T: PolySerde> Visitor<'de> for PolyVisitor<T> {
+            type Value = PolyEntity<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("\"None\" or a map from the table of an entity to its Key or Data")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> core::result::Result<Self::Value, E> {
+                match value {
+                    "None" => Ok(PolyEntity::None),
+                    _ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+                }
+            }
+
+            fn visit_unit<E: de::Error>(self) -> core::result::Result<Self::Value, E> {
+                Ok(PolyEntity::None)
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> core::result::Result<Self::Value, M::Error> {
+                let tag: String = map.next_key()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let index = T::tags()
+                    .iter()
+                    .position(|target| *target == tag)
+                    .ok_or_else(|| de::Error::custom(format_args!("unknown entity type `{tag}`")))?;
+                T::deserialize_entry(index, &mut map)
+            }
+        }
+
+        deserializer.deserialize_any(PolyVisitor(core::marker::PhantomData))
+    }
+}
+
+macro_rules! impl_poly_targets {
+    ($one_of:ident, $($index:tt => $target:ident),+) => {
+        /// The data of a [`PolyEntity`], one variant per entity type
+        #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+        pub enum $one_of<$($target),+> {
+            $(
+                #[doc = concat!("Data of the entity type at index ", stringify!($index))]
+                $target($target),
+            )+
+        }
+
+        impl<K, $($target),+> PolyTargets for ($($target,)+)
+        where
+            $($target: Schema + Keyed<KeyType = K>),+
+        {
+            type KeyType = K;
+            type Data = $one_of<$($target),+>;
+
+            fn tags() -> Vec<Cow<'static, str>> {
+                vec![$($target::table()),+]
+            }
+
+            fn tag_of(data: &Self::Data) -> Cow<'static, str> {
+                match data {
+                    $($one_of::$target(_) => $target::table(),)+
+                }
+            }
+
+            fn key_of(data: &Self::Data) -> Result<&Key<K>> {
+                match data {
+                    $($one_of::$target(data) => data.key(),)+
+                }
+            }
+        }
+
+        impl<K, $($target),+> From<$one_of<$($target),+>> for PolyEntity<($($target,)+)>
+        where
+            $($target: Schema + Keyed<KeyType = K>),+
+        {
+            fn from(data: $one_of<$($target),+>) -> Self {
+                PolyEntity::Data(Box::new(data))
+            }
+        }
+
+        impl<K, $($target),+> DataGraph for ($($target,)+)
+        where
+            $($target: Schema + Keyed<KeyType = K> + Graph),+
+        {
+            fn data_graph(data: &Self::Data) -> &dyn Graph {
+                match data {
+                    $($one_of::$target(data) => data,)+
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<K, $($target),+> PolySerde for ($($target,)+)
+        where
+            K: for<'de> Deserialize<'de>,
+            $($target: Schema + Keyed<KeyType = K> + Serialize + for<'de> Deserialize<'de>),+
+        {
+            fn serialize_data<M: SerializeMap>(data: &Self::Data, map: &mut M) -> core::result::Result<(), M::Error> {
+                match data {
+                    $($one_of::$target(data) => map.serialize_entry(&$target::table(), &Entity::<(), &$target>::Data(Box::new(data))),)+
+                }
+            }
+
+            fn deserialize_entry<'de, M: MapAccess<'de>>(index: usize, map: &mut M) -> core::result::Result<PolyEntity<Self>, M::Error> {
+                match index {
+                    $($index => Ok(match map.next_value::<Entity<K, $target>>()? {
+                        Entity::Key(key) => PolyEntity::Key($target::table(), key),
+                        Entity::Data(data) => PolyEntity::Data(Box::new($one_of::$target(*data))),
+                        Entity::None => PolyEntity::None,
+                    }),)+
+                    _ => Err(de::Error::custom(format_args!("no entity type at index {index}"))),
+                }
+            }
+        }
+    };
+}
+
+impl_poly_targets!(OneOf2, 0 => A, 1 => B);
+impl_poly_targets!(OneOf3, 0 => A, 1 => B, 2 => C);
+impl_poly_targets!(OneOf4, 0 => A, 1 => B, 2 => C, 3 => D);
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the graph and serde implementations of `PolyEntity`
+
+    use super::PolyTargets;
+    use crate::graph::Graph;
+
+    #[cfg(feature = "serde")]
+    use super::PolyEntity;
+    #[cfg(feature = "serde")]
+    use ::serde::{de::MapAccess, ser::SerializeMap};
+
+    pub trait DataGraph: PolyTargets {
+        fn data_graph(data: &Self::Data) -> &dyn Graph;
+    }
+
+    #[cfg(feature = "serde")]
+    pub trait PolySerde: PolyTargets + Sized {
+        fn serialize_data<M: SerializeMap>(data: &Self::Data, map: &mut M) -> Result<(), M::Error>;
+
+        fn deserialize_entry<'de, M: MapAccess<'de>>(index: usize, map: &mut M) -> Result<PolyEntity<Self>, M::Error>;
+    }
+}
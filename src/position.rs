@@ -0,0 +1,102 @@
+//! Ordered relations backed by a position column
+//!
+//! Entities that store their place in a list implement [`Positioned`], and
+//! the `Many` holding them can be sorted by it and reordered, for lists the
+//! user arranges by hand. `#[derive(Entity)]` implements [`Positioned`] when
+//! an `i64` field is marked with `#[position]`.
+//!
+//! The reordering helpers renumber the positions from zero in list order and
+//! return the indices of the entities whose position changed, the ones to
+//! write back:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use dbent::prelude::*;
+//!
+//! #[derive(Entity)]
+//! struct Track {
+//!     id: Key<Int>,
+//!     title: String,
+//!     #[position]
+//!     position: i64,
+//! }
+//!
+//! let track = |id, title: &str, position| Track { id: Key::new(id), title: title.to_owned(), position };
+//! let mut tracks: Many<Track> = vec![track(1, "Intro", 0), track(2, "Outro", 1), track(3, "Song", 2)].into();
+//!
+//! assert_eq!(tracks.move_up(2).unwrap(), [1, 2]);
+//! assert_eq!(tracks[1].title, "Song");
+//! assert_eq!(tracks[1].position, 1);
+//! assert_eq!(Track::order_by().to_sql(), " ORDER BY position ASC");
+//! # }
+//! ```
+
+use crate::sql::OrderBy;
+use crate::{Many, Result};
+
+/// Trait for entities that hold their position in an ordered relation
+pub trait Positioned {
+    /// The column holding the position
+    const POSITION_COLUMN: &'static str;
+
+    /// Returns the position of the entity
+    fn position(&self) -> i64;
+
+    /// Sets the position of the entity
+    fn set_position(&mut self, position: i64);
+
+    /// Returns the ordering by the position column, for fetching in order
+    fn order_by() -> OrderBy where Self: Sized {
+        OrderBy::new().asc(Self::POSITION_COLUMN)
+    }
+}
+
+impl<T: Positioned> Many<T> {
+    /// Sorts the data by position, if they exist and were fetched/created
+    ///
+    /// The sort is stable, so entities with the same position keep their order.
+    pub fn sort_by_position(&mut self) -> Result<()> {
+        self.data_mut()?.sort_by_key(Positioned::position);
+        Ok(())
+    }
+
+    /// Renumbers the positions from zero in the current order, returning the
+    /// indices of the entities whose position changed
+    pub fn reindex(&mut self) -> Result<Vec<usize>> {
+        let mut changed = Vec::new();
+        for (index, entity) in self.data_mut()?.iter_mut().enumerate() {
+            let position = index as i64;
+            if entity.position() != position {
+                entity.set_position(position);
+                changed.push(index);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Moves the entity at `index` one place up and renumbers the positions,
+    /// returning the indices of the entities whose position changed
+    ///
+    /// Moving the first entity up only renumbers. Panics if `index` is out of bounds.
+    pub fn move_up(&mut self, index: usize) -> Result<Vec<usize>> {
+        let data = self.data_mut()?;
+        assert!(index < data.len(), "cannot move up the entity at {index}, there are only {}", data.len());
+        if index > 0 {
+            data.swap(index - 1, index);
+        }
+        self.reindex()
+    }
+
+    /// Moves the entity at `index` one place down and renumbers the positions,
+    /// returning the indices of the entities whose position changed
+    ///
+    /// Moving the last entity down only renumbers. Panics if `index` is out of bounds.
+    pub fn move_down(&mut self, index: usize) -> Result<Vec<usize>> {
+        let data = self.data_mut()?;
+        assert!(index < data.len(), "cannot move down the entity at {index}, there are only {}", data.len());
+        if index + 1 < data.len() {
+            data.swap(index, index + 1);
+        }
+        self.reindex()
+    }
+}
@@ -0,0 +1,182 @@
+//! Read-only entities and repositories
+//!
+//! Views and reference tables are read by application code but never written.
+//! Wrapping their data in [`ReadOnly`] only gives shared access to it, so
+//! `Entity<K, ReadOnly<T>>::data_mut` can't be used to change the fields, and
+//! wrapping their repository in [`ReadOnlyRepository`] only exposes the
+//! reads, so inserts, updates and deletes don't compile:
+//!
+//! ```compile_fail
+//! # #[cfg(feature = "derive")] {
+//! use dbent::prelude::*;
+//! use dbent::readonly::ReadOnlyRepository;
+//! use dbent::repository::MemoryRepository;
+//!
+//! #[derive(Entity, Clone)]
+//! struct Country {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! let countries = ReadOnlyRepository::new(MemoryRepository::<Country>::new());
+//! countries.delete(&1).unwrap();
+//! # }
+//! # #[cfg(not(feature = "derive"))]
+//! # compile_error!("needs the derive feature");
+//! ```
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use dbent::prelude::*;
+//! use dbent::readonly::{ReadOnly, ReadOnlyRepository};
+//! use dbent::repository::MemoryRepository;
+//!
+//! #[derive(Entity, Clone)]
+//! struct Country {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! let repo = MemoryRepository::new();
+//! repo.insert(&mut Country { id: Key(None), name: "Brazil".to_owned() }).unwrap();
+//!
+//! let countries = ReadOnlyRepository::new(repo);
+//! let mut country = Entity::<Int, ReadOnly<Country>>::Key(Key::new(1));
+//! countries.fetch(&mut country).unwrap();
+//! assert_eq!(country.data().unwrap().name, "Brazil");
+//! # }
+//! ```
+
+use core::any::type_name;
+use core::fmt;
+use core::ops::Deref;
+use std::borrow::Cow;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::observe::{self, FetchKind};
+use crate::repository::Repository;
+use crate::trace;
+use crate::{Entity, Key, Keyed, Label, Result};
+
+/// A wrapper giving only shared access to an entity
+///
+/// It dereferences to the entity, and is `Keyed` and `Label` through it.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct ReadOnly<T>(T);
+
+impl<T> ReadOnly<T> {
+    /// Wraps the entity
+    pub fn new(entity: T) -> Self {
+        Self(entity)
+    }
+
+    /// Returns the wrapped entity, giving up the read-only guarantee
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ReadOnly<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> AsRef<T> for ReadOnly<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for ReadOnly<T> {
+    fn from(entity: T) -> Self {
+        Self(entity)
+    }
+}
+
+impl<T: Keyed> Keyed for ReadOnly<T> {
+    type KeyType = T::KeyType;
+
+    fn key(&self) -> Result<&Key<Self::KeyType>> {
+        self.0.key()
+    }
+}
+
+impl<T: Label> Label for ReadOnly<T> {
+    type LabelType = T::LabelType;
+
+    fn label(&self) -> Result<&Self::LabelType> {
+        self.0.label()
+    }
+
+    fn tag_label(&self) -> Result<Cow<'static, str>>
+    where
+        Self::LabelType: fmt::Display,
+    {
+        self.0.tag_label()
+    }
+}
+
+/// A [`Repository`] decorator exposing only the reads
+///
+/// It doesn't implement `Repository`, so writing through it fails to compile.
+/// Entities are returned wrapped in [`ReadOnly`].
+pub struct ReadOnlyRepository<R> {
+    inner: R,
+}
+
+impl<R> ReadOnlyRepository<R> {
+    /// Wraps `inner`
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the wrapped repository
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the entity with the given key, or `Error::NotFound`
+    pub fn get<T: Keyed>(&self, key: &T::KeyType) -> Result<ReadOnly<T>>
+    where
+        R: Repository<T>,
+    {
+        self.inner.get(key).map(ReadOnly)
+    }
+
+    /// Returns the entities with the given keys, skipping missing ones
+    pub fn get_many<T: Keyed>(&self, keys: &[&T::KeyType]) -> Result<Vec<ReadOnly<T>>>
+    where
+        R: Repository<T>,
+    {
+        Ok(self.inner.get_many(keys)?.into_iter().map(ReadOnly).collect())
+    }
+
+    /// Returns all the entities
+    pub fn list<T: Keyed>(&self) -> Result<Vec<ReadOnly<T>>>
+    where
+        R: Repository<T>,
+    {
+        Ok(self.inner.list()?.into_iter().map(ReadOnly).collect())
+    }
+
+    /// Replaces an `Entity::Key` with its data, returning the data
+    ///
+    /// Already fetched entities are left untouched.
+    pub fn fetch<'a, T: Keyed>(&self, entity: &'a mut Entity<T::KeyType, ReadOnly<T>>) -> Result<&'a ReadOnly<T>>
+    where
+        R: Repository<T>,
+    {
+        let data = entity.ensure_fetched(|key| {
+            let data = trace::traced("fetch", type_name::<T>(), None, || self.inner.get(key), |_| 1)?;
+            observe::notify::<T>(FetchKind::One);
+            Ok(ReadOnly(data))
+        })?;
+        Ok(data)
+    }
+}
@@ -0,0 +1,154 @@
+//! Field read tracking
+//!
+//! Entities derived with `#[entity(fields, track_reads)]` get a getter named
+//! after each column field, which tells the [`ReadTracker`] installed with
+//! [`set_tracker`] that the column was read. Nothing is recorded until a
+//! tracker is set, so the getters cost an atomic load otherwise.
+//!
+//! After running a scope, like a request or a test, with the getters in place
+//! of the fields, [`ReadTracker::projection`] suggests the columns to select
+//! instead of `SELECT *`:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use std::sync::Arc;
+//!
+//! use dbent::prelude::*;
+//! use dbent::reads::{self, ReadTracker};
+//!
+//! #[derive(Entity)]
+//! #[entity(fields, track_reads)]
+//! pub struct Product {
+//!     id: Key<Int>,
+//!     name: String,
+//!     description: String,
+//!     price: f64,
+//! }
+//!
+//! # pub fn main() {
+//! let tracker = Arc::new(ReadTracker::new());
+//! reads::set_tracker(tracker.clone());
+//!
+//! let product = Product { id: Key::new(1), name: "Pen".to_owned(), description: String::new(), price: 1.5 };
+//! assert_eq!(format!("{} {}", product.name(), product.price()), "Pen 1.5");
+//!
+//! assert_eq!(tracker.projection::<Product>(), ["id", "name", "price"]);
+//! assert_eq!(tracker.unread::<Product>(), ["description"]);
+//! assert_eq!(tracker.select_sql::<Product>(), "SELECT id, name, price FROM product");
+//!
+//! reads::clear_tracker();
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::schema::Schema;
+use crate::sql::{quote_ident, quote_list};
+
+static TRACKING: AtomicBool = AtomicBool::new(false);
+static TRACKER: RwLock<Option<Arc<ReadTracker>>> = RwLock::new(None);
+
+/// Installs the tracker, replacing the previous one
+pub fn set_tracker(tracker: Arc<ReadTracker>) {
+    *TRACKER.write().unwrap_or_else(|err| err.into_inner()) = Some(tracker);
+    TRACKING.store(true, Ordering::Release);
+}
+
+/// Removes the tracker, if any
+pub fn clear_tracker() {
+    TRACKING.store(false, Ordering::Release);
+    *TRACKER.write().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+/// Records the columns read per table, while installed with [`set_tracker`]
+///
+/// The reads cover a scope, from its creation or last [`reset`](Self::reset)
+/// on.
+#[derive(Default, Debug)]
+pub struct ReadTracker {
+    reads: Mutex<BTreeMap<&'static str, BTreeSet<&'static str>>>,
+}
+
+impl ReadTracker {
+    /// Creates a tracker with no reads
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the columns read of each table
+    pub fn reads(&self) -> BTreeMap<&'static str, BTreeSet<&'static str>> {
+        self.reads.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Returns whether `column` of the table of `T` was read
+    pub fn was_read<T: Schema>(&self, column: &str) -> bool {
+        let reads = self.reads.lock().unwrap_or_else(|err| err.into_inner());
+        reads.get(&*T::table()).is_some_and(|columns| columns.contains(column))
+    }
+
+    /// Returns the columns of `T` to select, in the order of its schema: the
+    /// primary key and the columns that were read
+    pub fn projection<T: Schema>(&self) -> Vec<Cow<'static, str>> {
+        self.columns::<T>(true)
+    }
+
+    /// Returns the columns of `T` that were never read, besides the primary
+    /// key, in the order of its schema
+    pub fn unread<T: Schema>(&self) -> Vec<Cow<'static, str>> {
+        self.columns::<T>(false)
+    }
+
+    /// Returns the SELECT of the [`projection`](Self::projection) of `T`
+    pub fn select_sql<T: Schema>(&self) -> String {
+        let meta = T::entity_meta();
+        format!("SELECT {} FROM {}", quote_list(&self.projection::<T>()), quote_ident(&meta.table))
+    }
+
+    /// Forgets the reads recorded so far, starting a new scope
+    pub fn reset(&self) {
+        self.reads.lock().unwrap_or_else(|err| err.into_inner()).clear();
+    }
+
+    /// Returns the columns of `T` that are projected, or the others
+    fn columns<T: Schema>(&self, projected: bool) -> Vec<Cow<'static, str>> {
+        let meta = T::entity_meta();
+        let reads = self.reads.lock().unwrap_or_else(|err| err.into_inner());
+        let read = reads.get(&*meta.table);
+        meta.columns
+            .into_iter()
+            .filter(|column| (column.primary_key || read.is_some_and(|read| read.contains(&*column.name))) == projected)
+            .map(|column| column.name)
+            .collect()
+    }
+
+    fn record(&self, table: &'static str, column: &'static str) {
+        self.reads.lock().unwrap_or_else(|err| err.into_inner()).entry(table).or_default().insert(column);
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the code generated by `#[entity(track_reads)]`
+
+    use core::sync::atomic::Ordering;
+
+    use super::{TRACKER, TRACKING};
+
+    /// Tells the tracker, if any, that `column` of `table` was read
+    #[inline]
+    pub fn record(table: &'static str, column: &'static str) {
+        if !TRACKING.load(Ordering::Acquire) {
+            return;
+        }
+        if let Some(tracker) = &*TRACKER.read().unwrap_or_else(|err| err.into_inner()) {
+            tracker.record(table, column);
+        }
+    }
+}
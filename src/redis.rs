@@ -0,0 +1,292 @@
+//! Entity cache shared through Redis
+//!
+//! [`RedisCache`] is a [`CacheStore`] keeping the entities of a
+//! [`CachedRepository`](crate::repository::CachedRepository) in Redis, so the
+//! instances of a deployment share their cache. Entities are stored under
+//! `"{table}:{key}"`, encoded with [`wire`](crate::wire), and expire with the
+//! TTL of the repository.
+//!
+//! The cache speaks the Redis protocol over a plain TCP connection, opened on
+//! first use and opened again after an I/O error. Connecting, sending and
+//! reading time out after [`DEFAULT_TIMEOUT`], or the timeout set with
+//! [`RedisCache::with_timeout`], so a stalled server fails the calls with
+//! `Error::Io` instead of blocking them. Since the [`wire`](crate::wire)
+//! encoding keeps the fields skipped by `#[serde_relations]`, such entities
+//! are cached with their relations in the same fetch state.
+//!
+//! ```no_run
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use std::time::Duration;
+//!
+//! use dbent::prelude::*;
+//! use dbent::redis::RedisCache;
+//! use dbent::repository::{CachedRepository, MemoryRepository};
+//!
+//! #[derive(Entity, Clone, serde::Serialize, serde::Deserialize)]
+//! #[entity(fields)]
+//! pub struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! # pub fn main() -> dbent::Result<()> {
+//! let users = CachedRepository::with_store(MemoryRepository::<User>::new(), Duration::from_secs(60), RedisCache::new("127.0.0.1:6379"));
+//! users.insert(&mut User { id: Key(None), name: "Bob".to_owned() })?;
+//! // cached under "user:1" for a minute
+//! assert_eq!(users.get(&1)?.name, "Bob");
+//! # Ok(())
+//! # }
+//! # }
+//! # example::main().unwrap();
+//! # }
+//! ```
+
+use core::fmt::Display;
+use core::marker::PhantomData;
+use std::borrow::Cow;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::repository::CacheStore;
+use crate::schema::Schema;
+use crate::{wire, Error, Keyed, Result};
+
+/// How long connecting to the server, sending a command or reading a reply
+/// takes before failing, unless set with [`RedisCache::with_timeout`]
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The longest bulk string read, the limit of Redis itself
+const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+/// How many bytes of a bulk string, and items of an array, are allocated
+/// ahead of reading them, so a bogus length doesn't allocate at once
+const PREALLOCATED_BYTES: usize = 64 * 1024;
+const PREALLOCATED_ITEMS: usize = 1024;
+
+/// A [`CacheStore`] keeping the entities of type `T` in Redis
+pub struct RedisCache<T> {
+    addr: String,
+    table: Cow<'static, str>,
+    timeout: Duration,
+    conn: Mutex<Option<BufReader<TcpStream>>>,
+    entity: PhantomData<fn() -> T>,
+}
+
+impl<T: Schema> RedisCache<T> {
+    /// Creates a cache on the Redis server at `addr`, like `"127.0.0.1:6379"`,
+    /// prefixing the keys with the table of `T`
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self { addr: addr.into(), table: T::entity_meta().table, timeout: DEFAULT_TIMEOUT, conn: Mutex::new(None), entity: PhantomData }
+    }
+}
+
+impl<T> RedisCache<T> {
+    /// Returns the address of the Redis server
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// Returns the prefix of the keys
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Sets how long connecting, sending a command or reading a reply takes
+    /// before failing, at least a millisecond
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout.max(Duration::from_millis(1));
+        self
+    }
+
+    /// Returns how long connecting, sending a command or reading a reply takes
+    /// before failing
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn redis_key(&self, key: &impl Display) -> String {
+        format!("{}:{key}", self.table)
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Option<BufReader<TcpStream>>> {
+        self.conn.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Connects to the first address of the server accepting the connection
+    fn connect(&self) -> std::io::Result<TcpStream> {
+        let mut last_err = None;
+        for addr in self.addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    return Ok(stream);
+                },
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| std::io::Error::from(std::io::ErrorKind::AddrNotAvailable)))
+    }
+
+    /// Sends the command and reads its reply, dropping the connection on
+    /// I/O errors
+    fn command(&self, args: &[&[u8]]) -> Result<Reply> {
+        let mut conn = self.conn();
+        let stream = match &mut *conn {
+            Some(stream) => stream,
+            None => conn.insert(BufReader::new(self.connect()?)),
+        };
+
+        let mut request = format!("*{}\r\n", args.len()).into_bytes();
+        for arg in args {
+            request.extend(format!("${}\r\n", arg.len()).as_bytes());
+            request.extend(*arg);
+            request.extend(b"\r\n");
+        }
+
+        let reply = stream.get_mut().write_all(&request).map_err(Error::from).and_then(|_| read_reply(stream));
+        if matches!(reply, Err(Error::Io(_))) {
+            *conn = None;
+        }
+        reply?
+    }
+}
+
+impl<T> CacheStore<T> for RedisCache<T>
+where
+    T: Keyed + Serialize + DeserializeOwned,
+    T::KeyType: Display,
+{
+    fn get(&self, key: &T::KeyType) -> Result<Option<T>> {
+        match self.command(&[b"GET", self.redis_key(key).as_bytes()])? {
+            Reply::Bulk(Some(bytes)) => wire::from_bytes(&bytes).map(Some),
+            Reply::Bulk(None) => Ok(None),
+            _ => Err(unexpected("GET")),
+        }
+    }
+
+    /// Does nothing for a zero `ttl`, as such entities would expire at once
+    fn set(&self, key: &T::KeyType, entity: &T, ttl: Duration) -> Result<()> {
+        if ttl.is_zero() {
+            return Ok(());
+        }
+        let millis = ttl.as_millis().clamp(1, i64::MAX as u128).to_string();
+        let bytes = wire::to_bytes(entity)?;
+        self.command(&[b"SET", self.redis_key(key).as_bytes(), &bytes, b"PX", millis.as_bytes()])?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &T::KeyType) -> Result<()> {
+        self.command(&[b"DEL", self.redis_key(key).as_bytes()])?;
+        Ok(())
+    }
+
+    /// Deletes the keys prefixed with the table, scanning them in batches
+    fn clear(&self) -> Result<()> {
+        let pattern = glob_escape(&self.table) + ":*";
+        let mut cursor = b"0".to_vec();
+        loop {
+            let reply = self.command(&[b"SCAN", &cursor, b"MATCH", pattern.as_bytes(), b"COUNT", b"100"])?;
+            let (next, keys) = match reply {
+                Reply::Array(mut items) if items.len() == 2 => match (items.remove(0), items.remove(0)) {
+                    (Reply::Bulk(Some(next)), Reply::Array(keys)) => (next, keys),
+                    _ => return Err(unexpected("SCAN")),
+                },
+                _ => return Err(unexpected("SCAN")),
+            };
+
+            let keys = keys.into_iter().filter_map(|key| match key {
+                Reply::Bulk(Some(key)) => Some(key),
+                _ => None,
+            }).collect::<Vec<_>>();
+            if !keys.is_empty() {
+                let mut args: Vec<&[u8]> = vec![b"DEL"];
+                args.extend(keys.iter().map(Vec::as_slice));
+                self.command(&args)?;
+            }
+
+            if next == b"0" {
+                return Ok(());
+            }
+            cursor = next;
+        }
+    }
+}
+
+/// Escapes the glob characters of a `MATCH` pattern
+fn glob_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '?' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A reply of the Redis protocol; error replies are read as `Error::Redis`
+enum Reply {
+    Status,
+    Integer,
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Reply>),
+}
+
+fn unexpected(command: &str) -> Error {
+    Error::Redis(format!("unexpected reply to {command}"))
+}
+
+/// Reads a reply, failing with `Error::Io` when the connection is broken and
+/// with `Error::Redis` for error replies, which leave the connection usable
+fn read_reply(stream: &mut impl BufRead) -> Result<Result<Reply>> {
+    let mut line = Vec::new();
+    stream.read_until(b'\n', &mut line)?;
+    if !line.ends_with(b"\r\n") {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    line.truncate(line.len() - 2);
+    let (kind, rest) = line.split_first().ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidData))?;
+    let rest = String::from_utf8_lossy(rest);
+    let length = || rest.parse::<i64>().map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidData));
+
+    Ok(Ok(match kind {
+        b'+' => Reply::Status,
+        b'-' => return Ok(Err(Error::Redis(rest.into_owned()))),
+        b':' => {
+            length()?;
+            Reply::Integer
+        },
+        b'$' => match usize::try_from(length()?) {
+            Ok(len) if len > MAX_BULK_LEN => return Err(std::io::Error::from(std::io::ErrorKind::InvalidData).into()),
+            Ok(len) => {
+                let mut bytes = Vec::with_capacity(len.min(PREALLOCATED_BYTES) + 2);
+                stream.take(len as u64 + 2).read_to_end(&mut bytes)?;
+                if !bytes.ends_with(b"\r\n") || bytes.len() != len + 2 {
+                    return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+                }
+                bytes.truncate(len);
+                Reply::Bulk(Some(bytes))
+            },
+            Err(_) => Reply::Bulk(None),
+        },
+        b'*' => {
+            let len = usize::try_from(length()?).unwrap_or(0);
+            let mut items = Vec::with_capacity(len.min(PREALLOCATED_ITEMS));
+            for _ in 0..len {
+                items.push(read_reply(stream)?);
+            }
+            match items.into_iter().collect() {
+                Ok(items) => Reply::Array(items),
+                Err(err) => return Ok(Err(err)),
+            }
+        },
+        _ => return Err(std::io::Error::from(std::io::ErrorKind::InvalidData).into()),
+    }))
+}
@@ -0,0 +1,767 @@
+//! Storage abstraction for entities
+//!
+//! [`Repository`] is the CRUD surface service code is written against, and
+//! [`Fetch`] builds on it to hydrate `Entity` and `Many` relations. Backends
+//! implement `Repository`; `Fetch` comes for free.
+//!
+//! [`MemoryRepository`] keeps entities in a `HashMap`, so unit tests and
+//! prototypes can exercise service logic without a database:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use dbent::prelude::*;
+//! use dbent::repository::MemoryRepository;
+//!
+//! #[derive(Entity, Clone)]
+//! struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! let users = MemoryRepository::new();
+//! let mut bob = User { id: Key(None), name: "Bob".to_owned() };
+//! users.insert(&mut bob).unwrap();
+//! assert_eq!(bob.id, Key::new(1));
+//!
+//! let mut entity = Entity::<Int, User>::Key(Key::new(1));
+//! users.fetch(&mut entity).unwrap();
+//! assert_eq!(entity.data().unwrap().name, "Bob");
+//! # }
+//! ```
+//!
+//! [`CachedRepository`] wraps another repository to memoize `get` results for
+//! a while, for read-heavy apps. It keeps them in a [`CacheStore`], in memory
+//! by default; with the `redis` feature,
+//! [`RedisCache`](crate::redis::RedisCache) shares them between instances.
+//!
+//! With the `async` feature, [`AsyncRepository`] offers the same CRUD surface
+//! for async backends such as sqlx, and is implemented by `MemoryRepository` too.
+//!
+//! With the `rusqlite` feature, [`TxContext`](crate::sqlite::TxContext)
+//! implements `Repository` over a transaction.
+
+use core::any::type_name;
+use core::fmt;
+#[cfg(feature = "async")]
+use core::future::Future;
+use core::hash::Hash;
+use core::marker::PhantomData;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::hooks::Lifecycle;
+use crate::observe::{self, FetchKind};
+use crate::snapshot::Snapshot;
+use crate::trace;
+use crate::{collect_keys, hydrate_entities, Entity, EntityLabel, Error, Key, KeyAllocator, Keyed, KeyedMut, Many, Result};
+
+/// CRUD operations over entities of type `T`
+///
+/// Methods take `&self` so repositories can be shared between services;
+/// backends use interior mutability or a connection that allows it.
+pub trait Repository<T: Keyed> {
+    /// Inserts the entity, writing back the key assigned by the backend
+    /// when its key is empty
+    fn insert(&self, entity: &mut T) -> Result<()>;
+
+    /// Returns the entity with the given key, or `Error::NotFound`
+    fn get(&self, key: &T::KeyType) -> Result<T>;
+
+    /// Returns the entities with the given keys, skipping missing ones
+    fn get_many(&self, keys: &[&T::KeyType]) -> Result<Vec<T>> {
+        let mut result = Vec::with_capacity(keys.len());
+        for key in keys {
+            match self.get(key) {
+                Ok(entity) => result.push(entity),
+                Err(Error::NotFound) => {},
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Returns the entity with the given key reading only the key and
+    /// `columns`, or `Error::NotFound`
+    ///
+    /// Lets wide tables, like ones with blob columns, be read cheaply and
+    /// fetched whole with `get` on demand. The other fields hold placeholder
+    /// values, so the entity must not be written back. The default
+    /// implementation reads the whole entity.
+    fn get_with(&self, key: &T::KeyType, columns: &[&str]) -> Result<T> {
+        let _ = columns;
+        self.get(key)
+    }
+
+    /// Returns all the entities
+    fn list(&self) -> Result<Vec<T>>;
+
+    /// Returns all the entities reading only their key and `columns`, like
+    /// [`get_with`](Repository::get_with)
+    fn list_with(&self, columns: &[&str]) -> Result<Vec<T>> {
+        let _ = columns;
+        self.list()
+    }
+
+    /// Replaces the stored entity with the same key, or fails with `Error::NotFound`
+    ///
+    /// Fields set by the backend while writing, like the timestamps touched
+    /// through `hooks::Lifecycle`, are written back to the entity. Versioned
+    /// entities are only written if they hold the stored version, which is
+    /// bumped, failing with `Error::StaleVersion` otherwise.
+    fn update(&self, entity: &mut T) -> Result<()>;
+
+    /// Inserts the entity, or replaces the stored one with the same key
+    ///
+    /// Entities with an empty key are inserted. The default implementation
+    /// tries `update` and inserts on `Error::NotFound`; backends can override
+    /// it with an atomic operation.
+    fn upsert(&self, entity: &mut T) -> Result<()> {
+        if entity.key()?.is_none() {
+            return self.insert(entity);
+        }
+
+        match self.update(entity) {
+            Err(Error::NotFound) => self.insert(entity),
+            result => result,
+        }
+    }
+
+    /// Deletes the entity with the given key, or fails with `Error::NotFound`
+    fn delete(&self, key: &T::KeyType) -> Result<()>;
+
+    /// Deletes the entities with the given keys, skipping missing ones, and
+    /// returns how many were deleted
+    ///
+    /// The default implementation calls `delete` for each key; backends can
+    /// override it with a single statement.
+    fn delete_many(&self, keys: &[&T::KeyType]) -> Result<usize> {
+        let mut deleted = 0;
+        for key in keys {
+            match self.delete(key) {
+                Ok(()) => deleted += 1,
+                Err(Error::NotFound) => {},
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Deletes the entities dropped from `many` since `snapshot` was taken,
+    /// and returns how many were deleted
+    ///
+    /// For saving edited collections, along with upserting the entities left.
+    /// See [`Many::removed_since`].
+    fn delete_orphans(&self, many: &Many<T>, snapshot: &Snapshot<Many<T>>) -> Result<usize>
+    where
+        T::KeyType: Eq + Hash + Clone,
+    {
+        let removed = many.removed_since(snapshot)?;
+        self.delete_many(&removed.iter().collect::<Vec<_>>())
+    }
+}
+
+impl<T: Keyed, R: Repository<T> + ?Sized> Repository<T> for &R {
+    fn insert(&self, entity: &mut T) -> Result<()> {
+        (**self).insert(entity)
+    }
+
+    fn get(&self, key: &T::KeyType) -> Result<T> {
+        (**self).get(key)
+    }
+
+    fn get_many(&self, keys: &[&T::KeyType]) -> Result<Vec<T>> {
+        (**self).get_many(keys)
+    }
+
+    fn get_with(&self, key: &T::KeyType, columns: &[&str]) -> Result<T> {
+        (**self).get_with(key, columns)
+    }
+
+    fn list(&self) -> Result<Vec<T>> {
+        (**self).list()
+    }
+
+    fn list_with(&self, columns: &[&str]) -> Result<Vec<T>> {
+        (**self).list_with(columns)
+    }
+
+    fn update(&self, entity: &mut T) -> Result<()> {
+        (**self).update(entity)
+    }
+
+    fn upsert(&self, entity: &mut T) -> Result<()> {
+        (**self).upsert(entity)
+    }
+
+    fn delete(&self, key: &T::KeyType) -> Result<()> {
+        (**self).delete(key)
+    }
+
+    fn delete_many(&self, keys: &[&T::KeyType]) -> Result<usize> {
+        (**self).delete_many(keys)
+    }
+}
+
+/// Async CRUD operations over entities of type `T`
+///
+/// Mirrors [`Repository`] for async backends. The returned futures are `Send`
+/// so they can be spawned on multithreaded runtimes; implementations can be
+/// written with `async fn`.
+#[cfg(feature = "async")]
+pub trait AsyncRepository<T: Keyed>: Sync {
+    /// Inserts the entity, writing back the key assigned by the backend
+    /// when its key is empty
+    fn insert(&self, entity: &mut T) -> impl Future<Output = Result<()>> + Send;
+
+    /// Returns the entity with the given key, or `Error::NotFound`
+    fn get(&self, key: &T::KeyType) -> impl Future<Output = Result<T>> + Send;
+
+    /// Returns the entities with the given keys, skipping missing ones
+    fn get_many(&self, keys: &[&T::KeyType]) -> impl Future<Output = Result<Vec<T>>> + Send
+    where
+        T: Send,
+        T::KeyType: Sync,
+    {
+        async move {
+            let mut result = Vec::with_capacity(keys.len());
+            for key in keys {
+                match self.get(key).await {
+                    Ok(entity) => result.push(entity),
+                    Err(Error::NotFound) => {},
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(result)
+        }
+    }
+
+    /// Returns all the entities
+    fn list(&self) -> impl Future<Output = Result<Vec<T>>> + Send;
+
+    /// Replaces the stored entity with the same key, or fails with `Error::NotFound`
+    fn update(&self, entity: &mut T) -> impl Future<Output = Result<()>> + Send;
+
+    /// Inserts the entity, or replaces the stored one with the same key
+    ///
+    /// Entities with an empty key are inserted. The default implementation
+    /// tries `update` and inserts on `Error::NotFound`.
+    fn upsert(&self, entity: &mut T) -> impl Future<Output = Result<()>> + Send
+    where
+        T: Send,
+    {
+        async move {
+            if entity.key()?.is_none() {
+                return self.insert(entity).await;
+            }
+
+            match self.update(entity).await {
+                Err(Error::NotFound) => self.insert(entity).await,
+                result => result,
+            }
+        }
+    }
+
+    /// Deletes the entity with the given key, or fails with `Error::NotFound`
+    fn delete(&self, key: &T::KeyType) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Hydration of relations through a [`Repository`]
+///
+/// Implemented for every repository. Fetches are reported to the
+/// [observer](crate::observe), if any, and traced with the `tracing` feature.
+pub trait Fetch<T: Keyed>: Repository<T> {
+    /// Replaces an `Entity::Key` with its data, returning the data
+    ///
+    /// Already fetched entities are left untouched.
+    fn fetch<'a>(&self, entity: &'a mut Entity<T::KeyType, T>) -> Result<&'a mut T> {
+        entity.ensure_fetched(|key| {
+            let data = trace::traced("fetch", type_name::<T>(), None, || self.get(key), |_| 1)?;
+            observe::notify::<T>(FetchKind::One);
+            Ok(data)
+        })
+    }
+
+    /// Replaces an `EntityLabel::KeyLabel` with its data, returning the data
+    ///
+    /// Already fetched entities are left untouched.
+    fn fetch_label<'a, L>(&self, entity: &'a mut EntityLabel<T::KeyType, T, L>) -> Result<&'a mut T> {
+        if let EntityLabel::KeyLabel(key, _) = entity {
+            let key = key.as_ref().ok_or(Error::KeyEmpty)?;
+            let data = trace::traced("fetch", type_name::<T>(), None, || self.get(key), |_| 1)?;
+            observe::notify::<T>(FetchKind::One);
+            *entity = EntityLabel::Data(Box::new(data));
+        }
+        entity.data_mut()
+    }
+
+    /// Fetches all the `Entity::Key`s in `entities` with a single `get_many`
+    fn fetch_all(&self, entities: &mut [Entity<T::KeyType, T>]) -> Result<()>
+    where
+        T: Clone,
+        T::KeyType: Eq + Hash + Clone,
+    {
+        let keys = collect_keys(entities.iter());
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let fetched = trace::traced("fetch_all", type_name::<T>(), None, || self.get_many(&keys), Vec::len)?;
+        observe::notify::<T>(FetchKind::Batch(keys.len()));
+        if hydrate_entities(entities, fetched, |entity| entity)?.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    /// Fills a `Many::NotFetched` with the entities matching `predicate`
+    ///
+    /// Already fetched data is left untouched.
+    fn fetch_many_where<'a>(&self, many: &'a mut Many<T>, predicate: impl FnMut(&T) -> bool) -> Result<&'a mut Vec<T>> {
+        let mut predicate = predicate;
+        many.ensure_fetched(|| {
+            let list = || Ok(self.list()?.into_iter().filter(|entity| predicate(entity)).collect());
+            let data = trace::traced("fetch_many", type_name::<T>(), None, list, Vec::len)?;
+            observe::notify::<T>(FetchKind::Many);
+            Ok(data)
+        })
+    }
+}
+
+impl<T: Keyed, R: Repository<T> + ?Sized> Fetch<T> for R {}
+
+struct MemoryState<K, T> {
+    /// Rows with their insertion sequence, so `list` is deterministic
+    rows: HashMap<K, (usize, T)>,
+    sequence: usize,
+    keys: KeyAllocator,
+}
+
+impl<K, T> MemoryState<K, T> {
+    fn new() -> Self {
+        Self { rows: HashMap::new(), sequence: 0, keys: KeyAllocator::new() }
+    }
+}
+
+/// Turns the next counter value into a key
+type AssignKey<K> = fn(usize) -> Option<K>;
+
+/// Turns an inserted key into a counter value
+type ObserveKey<K> = fn(&K) -> Option<usize>;
+
+/// A [`Repository`] keeping entities in memory
+///
+/// Created with [`MemoryRepository::new`], integer keys are assigned on insert
+/// from a counter, as a database would do. [`MemoryRepository::manual`]
+/// accepts any key type but requires keys to be set before inserting.
+/// Inserting a key already stored fails with `Error::DuplicateKey`, as a
+/// primary key constraint would.
+///
+/// [`MemoryRepository::transaction`] emulates transactions by restoring a
+/// snapshot of the data when the closure fails.
+pub struct MemoryRepository<T: Keyed> {
+    state: Mutex<MemoryState<T::KeyType, T>>,
+    assign: Option<AssignKey<T::KeyType>>,
+    observe: Option<ObserveKey<T::KeyType>>,
+}
+
+impl<T> MemoryRepository<T>
+where
+    T: Keyed + KeyedMut + Clone,
+    T::KeyType: Eq + Hash + Clone + TryFrom<usize> + TryInto<usize>,
+{
+    /// Creates an empty repository assigning integer keys on insert
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(MemoryState::new()),
+            assign: Some(|next| T::KeyType::try_from(next).ok()),
+            observe: Some(|key| key.clone().try_into().ok()),
+        }
+    }
+}
+
+impl<T> Default for MemoryRepository<T>
+where
+    T: Keyed + KeyedMut + Clone,
+    T::KeyType: Eq + Hash + Clone + TryFrom<usize> + TryInto<usize>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> MemoryRepository<T>
+where
+    T: Keyed + KeyedMut + Clone,
+    T::KeyType: Eq + Hash + Clone,
+{
+    /// Creates an empty repository that requires keys to be set on insert
+    pub fn manual() -> Self {
+        Self {
+            state: Mutex::new(MemoryState::new()),
+            assign: None,
+            observe: None,
+        }
+    }
+
+    /// Returns how many entities are stored
+    pub fn len(&self) -> usize {
+        self.state().rows.len()
+    }
+
+    /// Returns true if no entities are stored
+    pub fn is_empty(&self) -> bool {
+        self.state().rows.is_empty()
+    }
+
+    /// Runs `f`, restoring the data and the key counter as they were before
+    /// if it returns an error
+    ///
+    /// Other threads see the changes made by `f` before it returns.
+    pub fn transaction<R>(&self, f: impl FnOnce(&Self) -> Result<R>) -> Result<R> {
+        let (rows, keys) = {
+            let state = self.state();
+            (state.rows.clone(), state.keys.clone())
+        };
+        let result = f(self);
+        if result.is_err() {
+            let mut state = self.state();
+            state.rows = rows;
+            state.keys = keys;
+        }
+        result
+    }
+
+    fn state(&self) -> MutexGuard<'_, MemoryState<T::KeyType, T>> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<T> Repository<T> for MemoryRepository<T>
+where
+    T: Keyed + KeyedMut + Lifecycle + Clone,
+    T::KeyType: Eq + Hash + Clone + fmt::Debug,
+{
+    fn insert(&self, entity: &mut T) -> Result<()> {
+        let duplicate = |key: &T::KeyType| Error::DuplicateKey { key: format!("{key:?}") };
+        // checked before the hooks too, so a rejected entity is left untouched
+        if let Some(key) = entity.key()?.as_ref().filter(|key| self.state().rows.contains_key(key)) {
+            return Err(duplicate(key));
+        }
+
+        entity.before_insert()?;
+        let mut state = self.state();
+
+        let key = match (entity.key()?.as_ref(), self.assign) {
+            (Some(key), _) => {
+                if let Some(value) = self.observe.and_then(|observe| observe(key)) {
+                    state.keys.observe(&Key::new(value));
+                }
+                key.clone()
+            },
+            (None, Some(assign)) => {
+                let key = assign(state.keys.next_key()?.0.ok_or(Error::KeyEmpty)?).ok_or(Error::KeyEmpty)?;
+                *entity.key_mut()? = Key::new(key.clone());
+                key
+            },
+            (None, None) => return Err(Error::KeyEmpty),
+        };
+
+        if state.rows.contains_key(&key) {
+            return Err(duplicate(&key));
+        }
+        state.sequence += 1;
+        let sequence = state.sequence;
+        state.rows.insert(key, (sequence, entity.clone()));
+        Ok(())
+    }
+
+    fn get(&self, key: &T::KeyType) -> Result<T> {
+        let mut entity = self.state().rows.get(key).map(|(_, row)| row.clone()).ok_or(Error::NotFound)?;
+        entity.after_fetch()?;
+        Ok(entity)
+    }
+
+    fn list(&self) -> Result<Vec<T>> {
+        let mut entities = {
+            let state = self.state();
+            let mut rows = state.rows.values().collect::<Vec<_>>();
+            rows.sort_by_key(|(sequence, _)| *sequence);
+            rows.into_iter().map(|(_, row)| row.clone()).collect::<Vec<_>>()
+        };
+        entities.iter_mut().try_for_each(Lifecycle::after_fetch)?;
+        Ok(entities)
+    }
+
+    fn update(&self, entity: &mut T) -> Result<()> {
+        let key = entity.key()?.as_ref().ok_or(Error::KeyEmpty)?;
+        let current = match self.state().rows.get_mut(key) {
+            Some((_, row)) => row.versioned().map(|row| row.version()),
+            None => return Err(Error::NotFound),
+        };
+        // checked before the hooks too, so a stale entity is left untouched
+        if let (Some(current), Some(versioned)) = (current, entity.versioned()) {
+            versioned.check_version(current)?;
+        }
+
+        entity.before_update()?;
+        let mut state = self.state();
+        let key = entity.key()?.as_ref().ok_or(Error::KeyEmpty)?;
+        let (_, row) = state.rows.get_mut(key).ok_or(Error::NotFound)?;
+        if let Some(current) = row.versioned().map(|row| row.version()) {
+            if let Some(versioned) = entity.versioned() {
+                versioned.check_version(current)?;
+                versioned.bump_version();
+            }
+        }
+        *row = entity.clone();
+        Ok(())
+    }
+
+    fn upsert(&self, entity: &mut T) -> Result<()> {
+        let exists = match entity.key()?.as_ref() {
+            Some(key) => self.state().rows.contains_key(key),
+            None => false,
+        };
+        if exists {
+            self.update(entity)
+        } else {
+            self.insert(entity)
+        }
+    }
+
+    fn delete(&self, key: &T::KeyType) -> Result<()> {
+        let row = self.state().rows.get(key).map(|(_, row)| row.clone()).ok_or(Error::NotFound)?;
+        row.before_delete()?;
+        self.state().rows.remove(key).map(|_| ()).ok_or(Error::NotFound)
+    }
+}
+
+/// The storage of the entities cached by a [`CachedRepository`]
+///
+/// Stores are shared, so they use interior mutability like repositories.
+pub trait CacheStore<T: Keyed> {
+    /// Returns the entity cached under the key, unless missing or expired
+    fn get(&self, key: &T::KeyType) -> Result<Option<T>>;
+
+    /// Caches the entity under the key for `ttl`
+    fn set(&self, key: &T::KeyType, entity: &T, ttl: Duration) -> Result<()>;
+
+    /// Removes the entity cached under the key, if any
+    fn remove(&self, key: &T::KeyType) -> Result<()>;
+
+    /// Removes all the cached entities
+    fn clear(&self) -> Result<()>;
+}
+
+/// The entities of a [`MemoryCache`] with their expiry, if any
+type CacheEntries<T> = HashMap<<T as Keyed>::KeyType, (Option<Instant>, T)>;
+
+/// A [`CacheStore`] keeping entities in a `HashMap`, the default of
+/// [`CachedRepository`]
+pub struct MemoryCache<T: Keyed> {
+    entries: Mutex<CacheEntries<T>>,
+}
+
+impl<T: Keyed> MemoryCache<T> {
+    /// Creates an empty cache
+    pub fn new() -> Self {
+        Self { entries: Mutex::new(HashMap::new()) }
+    }
+
+    fn entries(&self) -> MutexGuard<'_, CacheEntries<T>> {
+        self.entries.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<T: Keyed> Default for MemoryCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> CacheStore<T> for MemoryCache<T>
+where
+    T: Keyed + Clone,
+    T::KeyType: Eq + Hash + Clone,
+{
+    fn get(&self, key: &T::KeyType) -> Result<Option<T>> {
+        Ok(match self.entries().get(key) {
+            Some((expires_at, entity)) if expires_at.is_none_or(|expires_at| Instant::now() < expires_at) => Some(entity.clone()),
+            _ => None,
+        })
+    }
+
+    fn set(&self, key: &T::KeyType, entity: &T, ttl: Duration) -> Result<()> {
+        self.entries().insert(key.clone(), (Instant::now().checked_add(ttl), entity.clone()));
+        Ok(())
+    }
+
+    fn remove(&self, key: &T::KeyType) -> Result<()> {
+        self.entries().remove(key);
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.entries().clear();
+        Ok(())
+    }
+}
+
+/// A [`Repository`] decorator caching `get` results
+///
+/// Fetched entities are kept in a [`CacheStore`] for `ttl`, so repeated `get`s
+/// of the same key are served without reaching the wrapped repository. The
+/// wrapped repository calls the `hooks::Lifecycle` of the entities, so cached
+/// entities are returned as they were after `after_fetch`.
+/// `update`, `upsert` and `delete` through the cache invalidate the key after
+/// writing, so a `get` in between doesn't cache the old entity; changes made
+/// to the wrapped repository directly are only seen once the entry expires
+/// or is [invalidated](CachedRepository::invalidate).
+pub struct CachedRepository<R, T: Keyed, S = MemoryCache<T>> {
+    inner: R,
+    ttl: Duration,
+    store: S,
+    entity: PhantomData<T>,
+}
+
+impl<R, T> CachedRepository<R, T>
+where
+    R: Repository<T>,
+    T: Keyed + Clone,
+    T::KeyType: Eq + Hash + Clone,
+{
+    /// Wraps `inner`, caching the entities it returns in memory for `ttl`
+    pub fn new(inner: R, ttl: Duration) -> Self {
+        Self::with_store(inner, ttl, MemoryCache::new())
+    }
+}
+
+impl<R, T, S> CachedRepository<R, T, S>
+where
+    R: Repository<T>,
+    T: Keyed,
+    S: CacheStore<T>,
+{
+    /// Wraps `inner`, caching the entities it returns in `store` for `ttl`
+    pub fn with_store(inner: R, ttl: Duration, store: S) -> Self {
+        Self { inner, ttl, store, entity: PhantomData }
+    }
+
+    /// Returns the wrapped repository
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the wrapped repository, dropping the cache
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the cache store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns how long entities are cached
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Removes the cached entity with the given key, if any
+    pub fn invalidate(&self, key: &T::KeyType) -> Result<()> {
+        self.store.remove(key)
+    }
+
+    /// Removes all the cached entities
+    pub fn clear(&self) -> Result<()> {
+        self.store.clear()
+    }
+}
+
+impl<R, T, S> Repository<T> for CachedRepository<R, T, S>
+where
+    R: Repository<T>,
+    T: Keyed,
+    S: CacheStore<T>,
+{
+    fn insert(&self, entity: &mut T) -> Result<()> {
+        self.inner.insert(entity)
+    }
+
+    fn get(&self, key: &T::KeyType) -> Result<T> {
+        if let Some(entity) = self.store.get(key)? {
+            return Ok(entity);
+        }
+
+        let entity = self.inner.get(key)?;
+        self.store.set(key, &entity, self.ttl)?;
+        Ok(entity)
+    }
+
+    /// Reads through, as partial entities aren't cached
+    fn get_with(&self, key: &T::KeyType, columns: &[&str]) -> Result<T> {
+        self.inner.get_with(key, columns)
+    }
+
+    fn list(&self) -> Result<Vec<T>> {
+        self.inner.list()
+    }
+
+    fn list_with(&self, columns: &[&str]) -> Result<Vec<T>> {
+        self.inner.list_with(columns)
+    }
+
+    fn update(&self, entity: &mut T) -> Result<()> {
+        self.inner.update(entity)?;
+        match entity.key()?.as_ref() {
+            Some(key) => self.invalidate(key),
+            None => Ok(()),
+        }
+    }
+
+    fn upsert(&self, entity: &mut T) -> Result<()> {
+        self.inner.upsert(entity)?;
+        match entity.key()?.as_ref() {
+            Some(key) => self.invalidate(key),
+            None => Ok(()),
+        }
+    }
+
+    fn delete(&self, key: &T::KeyType) -> Result<()> {
+        self.inner.delete(key)?;
+        self.invalidate(key)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T> AsyncRepository<T> for MemoryRepository<T>
+where
+    T: Keyed + KeyedMut + Lifecycle + Clone + Send + Sync,
+    T::KeyType: Eq + Hash + Clone + fmt::Debug + Send + Sync,
+{
+    async fn insert(&self, entity: &mut T) -> Result<()> {
+        Repository::insert(self, entity)
+    }
+
+    async fn get(&self, key: &T::KeyType) -> Result<T> {
+        Repository::get(self, key)
+    }
+
+    async fn list(&self) -> Result<Vec<T>> {
+        Repository::list(self)
+    }
+
+    async fn update(&self, entity: &mut T) -> Result<()> {
+        Repository::update(self, entity)
+    }
+
+    async fn upsert(&self, entity: &mut T) -> Result<()> {
+        Repository::upsert(self, entity)
+    }
+
+    async fn delete(&self, key: &T::KeyType) -> Result<()> {
+        Repository::delete(self, key)
+    }
+}
@@ -0,0 +1,737 @@
+//! Table metadata for entities and migrations between schema versions
+//!
+//! `#[derive(Entity)]` with `#[entity(fields)]` implements [`Schema`],
+//! describing the table of the struct as an [`EntityMeta`]. The table name
+//! defaults to the struct name in snake case and can be set with
+//! `#[entity(table = "...")]`.
+//!
+//! A [`SchemaSet`] collects the metadata of several entities. Comparing it
+//! with the set of a previous release, which can be kept around serialized
+//! with the `serde` feature, gives the statements to migrate an SQLite
+//! database:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::prelude::*;
+//! use dbent::schema::SchemaSet;
+//!
+//! #[derive(Entity)]
+//! #[entity(fields, table = "users")]
+//! pub struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//!     email: Option<String>,
+//! }
+//!
+//! # pub fn main() {
+//! let mut previous = SchemaSet::new();
+//! previous.add::<User>();
+//! previous.get_mut("users").unwrap().columns.retain(|column| column.name != "email");
+//!
+//! let mut current = SchemaSet::new();
+//! current.add::<User>();
+//! assert_eq!(current.migration_sql(&previous), vec!["ALTER TABLE users ADD COLUMN email TEXT"]);
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+//!
+//! Only added and removed tables and columns are detected; changes to the
+//! type of a column need a migration written by hand.
+//!
+//! A [`Registry`] keeps the entity types an application registers, for tools
+//! that enumerate every table, like schema generators or admin UIs. It can
+//! also draw the tables and their relations as a Mermaid or Graphviz diagram.
+
+use core::any::TypeId;
+use core::fmt;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+use crate::sql::{quote_ident, quote_list};
+use crate::{Entity, EntityLabel, Key, Many, Parent};
+
+/// The declared type of a column, following SQLite type affinities
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum SqlType {
+    /// Integers and booleans
+    Integer,
+    /// Floating point numbers
+    Real,
+    /// Strings and date times
+    Text,
+    /// Binary data
+    Blob,
+    /// No declared type, for types dbent doesn't know how to store
+    #[default]
+    Any,
+}
+
+impl SqlType {
+    /// Returns the type name used in SQL, empty for `Any`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SqlType::Integer => "INTEGER",
+            SqlType::Real => "REAL",
+            SqlType::Text => "TEXT",
+            SqlType::Blob => "BLOB",
+            SqlType::Any => "",
+        }
+    }
+
+    /// Returns the literal of the value used to fill NOT NULL columns added to existing rows
+    fn default_literal(&self) -> &'static str {
+        match self {
+            SqlType::Integer | SqlType::Real => "0",
+            SqlType::Text => "''",
+            SqlType::Blob | SqlType::Any => "x''",
+        }
+    }
+}
+
+impl fmt::Display for SqlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Types that can be stored in a column
+pub trait ColumnType {
+    /// The declared type of the column
+    const SQL_TYPE: SqlType;
+
+    /// Whether the column accepts NULL
+    const NULLABLE: bool = false;
+}
+
+macro_rules! impl_column_type {
+    ($sql_type:ident: $($ty:ty),*) => {
+        $(
+            impl ColumnType for $ty {
+                const SQL_TYPE: SqlType = SqlType::$sql_type;
+            }
+        )*
+    };
+}
+
+impl_column_type!(Integer: i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, bool);
+impl_column_type!(Real: f32, f64);
+impl_column_type!(Text: String, str, &str, Cow<'_, str>, char, crate::CiString);
+impl_column_type!(Blob: Vec<u8>);
+
+#[cfg(feature = "intern")]
+impl_column_type!(Text: crate::intern::InternedStr);
+
+#[cfg(feature = "decimal")]
+impl_column_type!(Text: crate::decimal::Decimal);
+
+#[cfg(feature = "chrono")]
+impl_column_type!(Text: chrono::DateTime<chrono::Utc>, chrono::NaiveDateTime, chrono::NaiveDate);
+
+#[cfg(feature = "time")]
+impl_column_type!(Text: time::OffsetDateTime, time::PrimitiveDateTime, time::Date);
+
+impl<const N: usize> ColumnType for [u8; N] {
+    const SQL_TYPE: SqlType = SqlType::Blob;
+}
+
+impl<T: ColumnType> ColumnType for Option<T> {
+    const SQL_TYPE: SqlType = T::SQL_TYPE;
+    const NULLABLE: bool = true;
+}
+
+impl<K: ColumnType> ColumnType for Key<K> {
+    const SQL_TYPE: SqlType = K::SQL_TYPE;
+}
+
+impl<const N: usize> ColumnType for crate::fixed_str::FixedStr<N> {
+    const SQL_TYPE: SqlType = SqlType::Text;
+}
+
+impl<K: ColumnType> ColumnType for crate::Sensitive<K> {
+    const SQL_TYPE: SqlType = K::SQL_TYPE;
+}
+
+/// The declared cardinality of a relation
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Cardinality {
+    /// A reference to one entity that must be set
+    RequiredOne,
+    /// A reference to one entity that may be None, marked `#[entity(optional)]`
+    OptionalOne,
+    /// A collection of entities, like `Many`
+    Many,
+}
+
+/// What deleting an entity does to the rows referencing it, set on a relation
+/// with `#[entity(on_delete = "...")]`
+///
+/// The generated tables have no FOREIGN KEY constraints, so the rules are
+/// enforced by [`sqlite::delete_cascading`](crate::sqlite::delete_cascading)
+/// and the repositories using it.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum OnDelete {
+    /// Deletes the referencing rows too
+    Cascade,
+    /// Sets the referencing column to NULL, for optional relations
+    SetNull,
+    /// Fails the delete while rows reference the entity
+    Restrict,
+}
+
+/// Metadata of a table column
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ColumnMeta {
+    /// Column name
+    pub name: Cow<'static, str>,
+    /// Declared type
+    pub sql_type: SqlType,
+    /// Whether the column accepts NULL
+    pub nullable: bool,
+    /// Whether the column is the primary key
+    pub primary_key: bool,
+    /// The table referenced by the column, for relations to other entities
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub references: Option<Cow<'static, str>>,
+    /// What deleting the referenced entity does to the row, not enforced if None
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub on_delete: Option<OnDelete>,
+}
+
+impl ColumnMeta {
+    /// Returns the cardinality of the relation held by the column, or None
+    /// if it doesn't reference a table
+    ///
+    /// Relations in NOT NULL columns are required.
+    pub fn cardinality(&self) -> Option<Cardinality> {
+        self.references.as_ref().map(|_| if self.nullable { Cardinality::OptionalOne } else { Cardinality::RequiredOne })
+    }
+
+    /// Returns the column definition used in CREATE TABLE
+    pub fn definition(&self) -> String {
+        let mut sql = quote_ident(&self.name).into_owned();
+        if self.sql_type != SqlType::Any {
+            sql.push(' ');
+            sql.push_str(self.sql_type.as_str());
+        }
+        if self.primary_key {
+            sql.push_str(" PRIMARY KEY");
+        } else if !self.nullable {
+            sql.push_str(" NOT NULL");
+        }
+        sql
+    }
+}
+
+/// Metadata of the table of an entity
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct EntityMeta {
+    /// Table name
+    pub table: Cow<'static, str>,
+    /// Columns in field order
+    pub columns: Vec<ColumnMeta>,
+    /// `Many` relations to other tables
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub many: Vec<ManyMeta>,
+    /// Sets of columns whose values must be unique together
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unique: Vec<Vec<Cow<'static, str>>>,
+    /// Prefix of the external ids of the entities, the table name if None
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id_prefix: Option<Cow<'static, str>>,
+}
+
+/// Metadata of a `Many` field, which has no column
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ManyMeta {
+    /// Field name
+    pub field: Cow<'static, str>,
+    /// The table of the entities in the field
+    pub table: Cow<'static, str>,
+}
+
+impl EntityMeta {
+    /// Returns the column with the given name
+    pub fn column(&self, name: &str) -> Option<&ColumnMeta> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Returns the primary key column
+    pub fn key(&self) -> Option<&ColumnMeta> {
+        self.columns.iter().find(|column| column.primary_key)
+    }
+
+    /// Returns the prefix of the external ids of the entities
+    pub fn id_prefix(&self) -> &str {
+        self.id_prefix.as_deref().unwrap_or(&self.table)
+    }
+
+    /// Returns the CREATE TABLE statement for the table, with its UNIQUE constraints
+    pub fn create_table_sql(&self) -> String {
+        let mut definitions = self.columns.iter().map(ColumnMeta::definition).collect::<Vec<_>>();
+        definitions.extend(self.unique.iter().map(|columns| format!("UNIQUE ({})", quote_list(columns))));
+        format!("CREATE TABLE {} ({})", quote_ident(&self.table), definitions.join(", "))
+    }
+
+    /// Returns the first UNIQUE constraint that includes `column`
+    pub fn unique_with(&self, column: &str) -> Option<&[Cow<'static, str>]> {
+        self.unique.iter().find(|columns| columns.iter().any(|name| name == column)).map(Vec::as_slice)
+    }
+}
+
+/// Trait for entities describing their table
+pub trait Schema {
+    /// Returns the table metadata
+    fn entity_meta() -> EntityMeta;
+
+    /// Returns the table name
+    ///
+    /// Used for the metadata of relations, so it must not call the
+    /// `entity_meta` of other entities.
+    fn table() -> Cow<'static, str> {
+        Self::entity_meta().table
+    }
+
+    /// Returns the prefix of the external ids of the entities
+    fn id_prefix() -> Cow<'static, str> {
+        let meta = Self::entity_meta();
+        meta.id_prefix.unwrap_or(meta.table)
+    }
+}
+
+/// Types relating an entity to entities of another type
+pub trait Relation {
+    /// The type of the related entities
+    type Target;
+}
+
+impl<K, T> Relation for Entity<K, T> {
+    type Target = T;
+}
+
+impl<K, T, L> Relation for EntityLabel<K, T, L> {
+    type Target = T;
+}
+
+impl<K, T> Relation for Parent<K, T> {
+    type Target = T;
+}
+
+impl<T> Relation for Many<T> {
+    type Target = T;
+}
+
+/// A change between two versions of a [`SchemaSet`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SchemaChange {
+    /// A new table
+    CreateTable(EntityMeta),
+    /// A table that no longer exists
+    DropTable(Cow<'static, str>),
+    /// A new column in an existing table
+    AddColumn {
+        /// Table name
+        table: Cow<'static, str>,
+        /// The new column
+        column: ColumnMeta,
+    },
+    /// A column that no longer exists in an existing table
+    DropColumn {
+        /// Table name
+        table: Cow<'static, str>,
+        /// Column name
+        column: Cow<'static, str>,
+    },
+}
+
+impl SchemaChange {
+    /// Returns the SQLite statement applying the change
+    ///
+    /// Added NOT NULL columns get a zero or empty default, as SQLite requires
+    /// for existing rows.
+    pub fn to_sql(&self) -> String {
+        match self {
+            SchemaChange::CreateTable(meta) => meta.create_table_sql(),
+            SchemaChange::DropTable(table) => format!("DROP TABLE {}", quote_ident(table)),
+            SchemaChange::AddColumn { table, column } => {
+                let mut sql = format!("ALTER TABLE {} ADD COLUMN {}", quote_ident(table), column.definition());
+                if !column.nullable {
+                    sql.push_str(" DEFAULT ");
+                    sql.push_str(column.sql_type.default_literal());
+                }
+                sql
+            },
+            SchemaChange::DropColumn { table, column } => format!("ALTER TABLE {} DROP COLUMN {}", quote_ident(table), quote_ident(column)),
+        }
+    }
+}
+
+/// The tables of a set of entities, keyed by table name
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct SchemaSet {
+    tables: BTreeMap<Cow<'static, str>, EntityMeta>,
+}
+
+impl SchemaSet {
+    /// Creates an empty SchemaSet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the table of the entity `T`
+    pub fn add<T: Schema>(&mut self) -> &mut Self {
+        self.insert(T::entity_meta())
+    }
+
+    /// Adds a table, replacing any with the same name
+    pub fn insert(&mut self, meta: EntityMeta) -> &mut Self {
+        self.tables.insert(meta.table.clone(), meta);
+        self
+    }
+
+    /// Returns the table with the given name
+    pub fn get(&self, table: &str) -> Option<&EntityMeta> {
+        self.tables.get(table)
+    }
+
+    /// Returns the table with the given name, mutably
+    pub fn get_mut(&mut self, table: &str) -> Option<&mut EntityMeta> {
+        self.tables.get_mut(table)
+    }
+
+    /// Returns the tables ordered by name
+    pub fn iter(&self) -> impl Iterator<Item = &EntityMeta> {
+        self.tables.values()
+    }
+
+    /// Returns the number of tables
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Returns true if there are no tables
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Returns the changes needed to go from `previous` to this set
+    pub fn diff(&self, previous: &SchemaSet) -> Vec<SchemaChange> {
+        let mut changes = Vec::new();
+
+        for (table, meta) in &self.tables {
+            let old = match previous.tables.get(table) {
+                Some(old) => old,
+                None => {
+                    changes.push(SchemaChange::CreateTable(meta.clone()));
+                    continue;
+                },
+            };
+
+            for column in &meta.columns {
+                if old.column(&column.name).is_none() {
+                    changes.push(SchemaChange::AddColumn { table: table.clone(), column: column.clone() });
+                }
+            }
+            for column in &old.columns {
+                if meta.column(&column.name).is_none() {
+                    changes.push(SchemaChange::DropColumn { table: table.clone(), column: column.name.clone() });
+                }
+            }
+        }
+
+        for table in previous.tables.keys() {
+            if !self.tables.contains_key(table) {
+                changes.push(SchemaChange::DropTable(table.clone()));
+            }
+        }
+
+        changes
+    }
+
+    /// Returns the SQLite statements migrating a database from `previous` to this set
+    pub fn migration_sql(&self, previous: &SchemaSet) -> Vec<String> {
+        self.diff(previous).iter().map(SchemaChange::to_sql).collect()
+    }
+}
+
+/// An entity type added to a [`Registry`]
+#[derive(Clone, Debug)]
+pub struct RegisteredEntity {
+    type_id: TypeId,
+    type_name: &'static str,
+    meta: EntityMeta,
+}
+
+impl RegisteredEntity {
+    /// Returns the `TypeId` of the entity
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Returns the Rust type name of the entity
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the table metadata of the entity
+    pub fn meta(&self) -> &EntityMeta {
+        &self.meta
+    }
+}
+
+/// Entity types registered by an application, in registration order
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # mod example {
+/// use dbent::prelude::*;
+/// use dbent::schema::Registry;
+///
+/// #[derive(Entity)]
+/// #[entity(fields)]
+/// pub struct User {
+///     id: Key<Int>,
+///     name: String,
+/// }
+///
+/// # pub fn main() {
+/// let mut registry = Registry::new();
+/// registry.register::<User>();
+/// assert_eq!(registry.get::<User>().unwrap().table, "user");
+/// assert_eq!(registry.create_tables_sql(), vec!["CREATE TABLE user (id INTEGER PRIMARY KEY, name TEXT NOT NULL)"]);
+/// # }
+/// # }
+/// # example::main();
+/// # }
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Registry {
+    entities: Vec<RegisteredEntity>,
+}
+
+impl Registry {
+    /// Creates an empty Registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the entity `T`, doing nothing if it already is
+    pub fn register<T: Schema + 'static>(&mut self) -> &mut Self {
+        if !self.contains::<T>() {
+            self.entities.push(RegisteredEntity {
+                type_id: TypeId::of::<T>(),
+                type_name: core::any::type_name::<T>(),
+                meta: T::entity_meta(),
+            });
+        }
+        self
+    }
+
+    /// Returns true if the entity `T` is registered
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    /// Returns the table metadata of the entity `T`
+    pub fn get<T: 'static>(&self) -> Option<&EntityMeta> {
+        self.entities
+            .iter()
+            .find(|entity| entity.type_id == TypeId::of::<T>())
+            .map(RegisteredEntity::meta)
+    }
+
+    /// Returns the entity registered for the given table
+    pub fn by_table(&self, table: &str) -> Option<&RegisteredEntity> {
+        self.entities.iter().find(|entity| entity.meta.table == table)
+    }
+
+    /// Returns the registered entities
+    pub fn iter(&self) -> impl Iterator<Item = &RegisteredEntity> {
+        self.entities.iter()
+    }
+
+    /// Returns the number of registered entities
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns true if no entities are registered
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Returns the tables of the registered entities as a SchemaSet
+    pub fn schema_set(&self) -> SchemaSet {
+        let mut set = SchemaSet::new();
+        for entity in &self.entities {
+            set.insert(entity.meta.clone());
+        }
+        set
+    }
+
+    /// Returns the CREATE TABLE statements of the registered entities, in registration order
+    pub fn create_tables_sql(&self) -> Vec<String> {
+        self.entities.iter().map(|entity| entity.meta.create_table_sql()).collect()
+    }
+
+    /// Returns a Mermaid `erDiagram` of the registered entities and their relations
+    pub fn to_mermaid(&self) -> String {
+        mermaid(self.entities.iter().map(RegisteredEntity::meta))
+    }
+
+    /// Returns a Graphviz DOT digraph of the registered entities and their relations
+    pub fn to_dot(&self) -> String {
+        dot(self.entities.iter().map(RegisteredEntity::meta))
+    }
+}
+
+/// Returns the relations between tables as (from, to, label, many) tuples
+///
+/// `Many` fields mirrored by a column referencing back their table are left
+/// out, so each relation is drawn once.
+fn relations<'a>(metas: &[&'a EntityMeta]) -> Vec<(&'a str, &'a str, &'a str, bool)> {
+    let mut result = Vec::new();
+    for meta in metas {
+        for column in &meta.columns {
+            if let Some(table) = &column.references {
+                result.push((&*meta.table, &**table, &*column.name, false));
+            }
+        }
+        for many in &meta.many {
+            let mirrored = metas.iter().any(|other| {
+                other.table == many.table
+                    && other.columns.iter().any(|column| column.references.as_deref() == Some(&*meta.table))
+            });
+            if !mirrored {
+                result.push((&*meta.table, &*many.table, &*many.field, true));
+            }
+        }
+    }
+    result
+}
+
+/// Returns a Mermaid `erDiagram` of the tables and their relations
+pub fn mermaid<'a>(metas: impl IntoIterator<Item = &'a EntityMeta>) -> String {
+    let metas = metas.into_iter().collect::<Vec<_>>();
+    let mut out = String::from("erDiagram\n");
+
+    for meta in &metas {
+        out.push_str(&format!("    {} {{\n", meta.table));
+        for column in &meta.columns {
+            let sql_type = match column.sql_type {
+                SqlType::Any => "ANY",
+                sql_type => sql_type.as_str(),
+            };
+            let key = match (column.primary_key, &column.references) {
+                (true, _) => " PK",
+                (false, Some(_)) => " FK",
+                (false, None) => "",
+            };
+            out.push_str(&format!("        {sql_type} {}{key}\n", column.name));
+        }
+        out.push_str("    }\n");
+    }
+
+    for (from, to, label, many) in relations(&metas) {
+        let cardinality = if many {
+            "||--o{"
+        } else if metas.iter().any(|meta| meta.table == from && meta.column(label).is_some_and(|column| column.nullable)) {
+            "}o--o|"
+        } else {
+            "}o--||"
+        };
+        out.push_str(&format!("    {from} {cardinality} {to} : {label}\n"));
+    }
+
+    out
+}
+
+/// Returns a Graphviz DOT digraph of the tables and their relations
+pub fn dot<'a>(metas: impl IntoIterator<Item = &'a EntityMeta>) -> String {
+    let metas = metas.into_iter().collect::<Vec<_>>();
+    let mut out = String::from("digraph {\n    node [shape=record];\n");
+
+    for meta in &metas {
+        let columns = meta
+            .columns
+            .iter()
+            .map(|column| format!("{} {}\\l", column.name, column.sql_type))
+            .collect::<String>();
+        out.push_str(&format!("    {table} [label=\"{{{table}|{columns}}}\"];\n", table = meta.table));
+    }
+
+    for (from, to, label, many) in relations(&metas) {
+        let arrowhead = if many { "crow" } else { "normal" };
+        out.push_str(&format!("    {from} -> {to} [label=\"{label}\", arrowhead={arrowhead}];\n"));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the code generated by `#[entity(fields)]`
+
+    use core::marker::PhantomData;
+    use std::borrow::Cow;
+
+    use super::{ColumnType, Schema, SqlType};
+
+    pub struct ColumnOf<T: ?Sized>(pub PhantomData<T>);
+
+    pub trait KnownColumn {
+        fn sql_type(&self) -> (SqlType, bool);
+    }
+
+    impl<T: ColumnType + ?Sized> KnownColumn for ColumnOf<T> {
+        fn sql_type(&self) -> (SqlType, bool) {
+            (T::SQL_TYPE, T::NULLABLE)
+        }
+    }
+
+    pub trait UnknownColumn {
+        fn sql_type(&self) -> (SqlType, bool);
+    }
+
+    impl<T: ?Sized> UnknownColumn for &ColumnOf<T> {
+        fn sql_type(&self) -> (SqlType, bool) {
+            (SqlType::Any, true)
+        }
+    }
+
+    pub struct TableOf<T: ?Sized>(pub PhantomData<T>);
+
+    pub trait KnownTable {
+        fn table(&self) -> Option<Cow<'static, str>>;
+    }
+
+    impl<T: Schema + ?Sized> KnownTable for TableOf<T> {
+        fn table(&self) -> Option<Cow<'static, str>> {
+            Some(T::table())
+        }
+    }
+
+    pub trait UnknownTable {
+        fn table(&self) -> Option<Cow<'static, str>>;
+    }
+
+    impl<T: ?Sized> UnknownTable for &TableOf<T> {
+        fn table(&self) -> Option<Cow<'static, str>> {
+            None
+        }
+    }
+}
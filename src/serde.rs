@@ -0,0 +1,687 @@
+//! Serde helpers for keys
+//!
+//! Meant for use with `#[serde(with = "...")]` on `Key` fields.
+//! [`key_string`] writes Key values as strings, and [`key_lenient`] keeps
+//! the default format but also accepts strings, as delivered by form data
+//! and query strings. Both read strings or values. [`key_hex`] writes binary
+//! keys as hex strings:
+//!
+//! ```
+//! use dbent::prelude::*;
+//! use serde::{Serialize, Deserialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Order {
+//!     #[serde(with = "dbent::serde::key_string")]
+//!     id: Key<i64>,
+//! }
+//!
+//! let order = Order { id: Key::new(9007199254740993) };
+//! assert_eq!(serde_json::to_string(&order).unwrap(), r#"{"id":"9007199254740993"}"#);
+//!
+//! let order: Order = serde_json::from_str(r#"{"id":9007199254740993}"#).unwrap();
+//! assert_eq!(order.id, Key::new(9007199254740993));
+//! ```
+//!
+//! [`ResolveEntity`] and [`ResolveEntities`] deserialize entities given only
+//! by their keys and resolve them into Data on the way, using a [`Resolve`]
+//! context like a cache of already loaded entities:
+//!
+//! ```
+//! use dbent::prelude::*;
+//! use dbent::serde::ResolveEntity;
+//! use serde::de::DeserializeSeed;
+//!
+//! let names = |key: &Int| (*key == 1).then(|| "Alice".to_owned());
+//! let mut json = serde_json::Deserializer::from_str("1");
+//! let entity: Entity<Int, String> = ResolveEntity::new(&names).deserialize(&mut json).unwrap();
+//! assert_eq!(entity.data().unwrap(), "Alice");
+//! ```
+//!
+//! [`MaybeKey`] tells a Key field left out from one set to `null`, for
+//! partial updates:
+//!
+//! ```
+//! use dbent::prelude::*;
+//! use dbent::serde::MaybeKey;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct OrderPatch {
+//!     #[serde(default)]
+//!     customer: MaybeKey<Int>,
+//! }
+//!
+//! let mut customer = Key::new(3);
+//! let patch: OrderPatch = serde_json::from_str("{}").unwrap();
+//! patch.customer.apply(&mut customer);
+//! assert_eq!(customer, Key::new(3));
+//!
+//! let patch: OrderPatch = serde_json::from_str(r#"{"customer":null}"#).unwrap();
+//! patch.customer.apply(&mut customer);
+//! assert_eq!(customer, Key(None));
+//! ```
+//!
+//! [`not_fetched`] and [`parent_is_none`] are the relation field defaults
+//! that `#[serde_relations]` sets up.
+//!
+//! [`DepthLimited`] caps how deep fetched entities are embedded, so a deep or
+//! cyclic graph doesn't end up whole in an API response. Relation fields
+//! opt in with [`entity_depth`] or [`entity_label_depth`], and beyond the
+//! limit their Data is written as a Key, or a KeyLabel:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use dbent::prelude::*;
+//! use dbent::serde::DepthLimited;
+//! use serde::Serialize;
+//!
+//! #[derive(Entity, Serialize)]
+//! struct Employee {
+//!     id: Key<Int>,
+//!     #[serde(with = "dbent::serde::entity_depth")]
+//!     manager: Entity<Int, Employee>,
+//! }
+//!
+//! let ceo = Employee { id: Key::new(1), manager: Entity::None };
+//! let cto = Employee { id: Key::new(2), manager: ceo.into() };
+//! let dev = Employee { id: Key::new(3), manager: cto.into() };
+//!
+//! let json = serde_json::to_string(&DepthLimited::new(&dev, 1)).unwrap();
+//! assert_eq!(json, r#"{"id":3,"manager":{"Data":{"id":2,"manager":{"Key":1}}}}"#);
+//! # }
+//! ```
+//!
+//! [`Redacting`] hides the [`Redacted`] fields of a value as a
+//! [`Redaction`] policy says, with placeholders or `null`, so one model
+//! serves both public and admin endpoints.
+
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::str::FromStr;
+
+use ::serde::de::{DeserializeSeed, SeqAccess, Visitor};
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Entity, Key, Many, Parent};
+
+/// Returns `Many::NotFetched`, for `#[serde(default = "dbent::serde::not_fetched")]`
+pub fn not_fetched<T>() -> Many<T> {
+    Many::NotFetched
+}
+
+/// Returns true if the parent is `Entity::None`, for `skip_serializing_if`
+pub fn parent_is_none<K, T>(parent: &Parent<K, T>) -> bool {
+    parent.is_none()
+}
+
+/// A Key field that tells a missing field from an explicit `null`, for
+/// partial updates
+///
+/// Fields need `#[serde(default)]` to be Missing when left out, and
+/// `#[serde(skip_serializing_if = "MaybeKey::is_missing")]` to be left out
+/// when serialized; otherwise Missing is written as `null`.
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub enum MaybeKey<K> {
+    /// The field was left out, keep the current Key
+    #[default]
+    Missing,
+    /// The field was `null`, unset the Key
+    Null,
+    /// The field holds a key value
+    Value(K),
+}
+
+impl<K> MaybeKey<K> {
+    /// Is the field left out?
+    pub fn is_missing(&self) -> bool {
+        matches!(self, MaybeKey::Missing)
+    }
+
+    /// Is the field explicitly `null`?
+    pub fn is_null(&self) -> bool {
+        matches!(self, MaybeKey::Null)
+    }
+
+    /// Returns the Key to set, or None if the field was left out
+    pub fn into_key(self) -> Option<Key<K>> {
+        match self {
+            MaybeKey::Missing => None,
+            MaybeKey::Null => Some(Key(None)),
+            MaybeKey::Value(value) => Some(Key::new(value)),
+        }
+    }
+
+    /// Sets `key` to the field unless it was left out, returning whether it was set
+    pub fn apply(self, key: &mut Key<K>) -> bool {
+        match self.into_key() {
+            Some(value) => {
+                *key = value;
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+/// Converts a set Key into Value and an unset one into Null
+impl<K> From<Key<K>> for MaybeKey<K> {
+    fn from(key: Key<K>) -> Self {
+        key.0.map_or(MaybeKey::Null, MaybeKey::Value)
+    }
+}
+
+impl<K: Serialize> Serialize for MaybeKey<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MaybeKey::Value(value) => serializer.serialize_some(value),
+            MaybeKey::Missing | MaybeKey::Null => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<'de, K: Deserialize<'de>> Deserialize<'de> for MaybeKey<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Option::<K>::deserialize(deserializer)?.map_or(MaybeKey::Null, MaybeKey::Value))
+    }
+}
+
+/// A key value given either as a string or as its own representation
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOr<K> {
+    String(String),
+    Value(K),
+}
+
+/// Deserializes a Key from `null`, its value or the value as a string
+fn deserialize_lenient<'de, D, K>(deserializer: D) -> Result<Key<K>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: FromStr + Deserialize<'de>,
+    K::Err: fmt::Display,
+{
+    match Option::<StringOr<K>>::deserialize(deserializer)? {
+        Some(StringOr::String(text)) => text.trim().parse().map(Key::new).map_err(::serde::de::Error::custom),
+        Some(StringOr::Value(value)) => Ok(Key::new(value)),
+        None => Ok(Key(None)),
+    }
+}
+
+/// Context for resolving keys into entities while deserializing
+///
+/// Implemented for closures taking a key and returning the entity, if known.
+pub trait Resolve<K, T> {
+    /// Returns the entity with `key`, or None to keep the Key
+    fn resolve(&self, key: &K) -> Option<T>;
+}
+
+impl<K, T, F: Fn(&K) -> Option<T>> Resolve<K, T> for F {
+    fn resolve(&self, key: &K) -> Option<T> {
+        self(key)
+    }
+}
+
+/// An Entity in its own representation or given only by its key
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EntityOrKey<K, T> {
+    Entity(Entity<K, T>),
+    Key(Option<K>),
+}
+
+/// Deserializes an Entity, resolving it into Data if it is a key
+fn deserialize_resolved<'de, D, R, K, T>(deserializer: D, resolver: &R) -> Result<Entity<K, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    R: Resolve<K, T>,
+    K: Deserialize<'de>,
+    T: Deserialize<'de>,
+{
+    let entity = match EntityOrKey::deserialize(deserializer)? {
+        EntityOrKey::Entity(entity) => entity,
+        EntityOrKey::Key(key) => key.map_or(Entity::None, |key| Entity::Key(Key::new(key))),
+    };
+    Ok(
+        match entity {
+            Entity::Key(Key(Some(key))) => match resolver.resolve(&key) {
+                Some(data) => Entity::Data(Box::new(data)),
+                None => Entity::Key(Key::new(key)),
+            },
+            entity => entity,
+        }
+    )
+}
+
+/// Deserializes an Entity, resolving keys into Data with a [`Resolve`] context
+///
+/// Reads the usual Entity representation or a bare key, with `null` for None.
+/// Keys the resolver doesn't know stay as Keys.
+pub struct ResolveEntity<'r, R, K, T> {
+    resolver: &'r R,
+    marker: PhantomData<fn() -> (K, T)>,
+}
+
+impl<'r, R, K, T> ResolveEntity<'r, R, K, T> {
+    /// Creates a seed resolving keys with `resolver`
+    pub fn new(resolver: &'r R) -> Self {
+        Self { resolver, marker: PhantomData }
+    }
+}
+
+impl<'de, R, K, T> DeserializeSeed<'de> for ResolveEntity<'_, R, K, T>
+where
+    R: Resolve<K, T>,
+    K: Deserialize<'de>,
+    T: Deserialize<'de>,
+{
+    type Value = Entity<K, T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserialize_resolved(deserializer, self.resolver)
+    }
+}
+
+/// Deserializes a sequence of Entities, resolving keys into Data with a
+/// [`Resolve`] context
+///
+/// Each element is read as with [`ResolveEntity`].
+pub struct ResolveEntities<'r, R, K, T> {
+    resolver: &'r R,
+    marker: PhantomData<fn() -> (K, T)>,
+}
+
+impl<'r, R, K, T> ResolveEntities<'r, R, K, T> {
+    /// Creates a seed resolving keys with `resolver`
+    pub fn new(resolver: &'r R) -> Self {
+        Self { resolver, marker: PhantomData }
+    }
+}
+
+impl<'de, R, K, T> DeserializeSeed<'de> for ResolveEntities<'_, R, K, T>
+where
+    R: Resolve<K, T>,
+    K: Deserialize<'de>,
+    T: Deserialize<'de>,
+{
+    type Value = Vec<Entity<K, T>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, R, K, T> Visitor<'de> for ResolveEntities<'_, R, K, T>
+where
+    R: Resolve<K, T>,
+    K: Deserialize<'de>,
+    T: Deserialize<'de>,
+{
+    type Value = Vec<Entity<K, T>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a sequence of entities or keys")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut entities = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(entity) = seq.next_element_seed(ResolveEntity::new(self.resolver))? {
+            entities.push(entity);
+        }
+        Ok(entities)
+    }
+}
+
+thread_local! {
+    /// The depth of the entity being serialized, and the limit set by the
+    /// innermost [`DepthLimited`]
+    static DEPTH: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// Restores the depth state when dropped, even if serializing panics
+struct DepthGuard(Option<(usize, usize)>);
+
+impl DepthGuard {
+    fn set(state: Option<(usize, usize)>) -> Self {
+        Self(DEPTH.with(|depth| depth.replace(state)))
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| depth.set(self.0));
+    }
+}
+
+/// Serializes a value embedding fetched relations at most `max_depth` levels
+/// deep
+///
+/// Only relation fields serialized with [`entity_depth`] or
+/// [`entity_label_depth`] are limited; beyond the limit they write their key.
+/// Without the adapter those fields serialize as usual.
+pub struct DepthLimited<'a, T: ?Sized> {
+    value: &'a T,
+    max_depth: usize,
+}
+
+impl<'a, T: ?Sized> DepthLimited<'a, T> {
+    /// Wraps `value`, embedding relation Data up to `max_depth` levels deep
+    pub fn new(value: &'a T, max_depth: usize) -> Self {
+        Self { value, max_depth }
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for DepthLimited<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let _guard = DepthGuard::set(Some((0, self.max_depth)));
+        self.value.serialize(serializer)
+    }
+}
+
+/// Serializes the Data of a relation one level deeper, or with `truncated`
+/// when the limit of the enclosing [`DepthLimited`] is reached
+fn serialize_depth<S, V>(value: &V, serializer: S, truncated: impl FnOnce(S) -> Result<S::Ok, S::Error>) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize + ?Sized,
+{
+    match DEPTH.with(Cell::get) {
+        Some((depth, max_depth)) if depth >= max_depth => truncated(serializer),
+        Some((depth, max_depth)) => {
+            let _guard = DepthGuard::set(Some((depth + 1, max_depth)));
+            value.serialize(serializer)
+        },
+        None => value.serialize(serializer),
+    }
+}
+
+pub mod entity_depth {
+    //! Serializes an Entity field as usual, but writes its Data as a Key
+    //! beyond the depth limit of a [`DepthLimited`](super::DepthLimited)
+
+    use ::serde::ser::Error;
+    use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{Entity, Keyed};
+
+    /// Serializes the Entity, as a Key if it's Data beyond the depth limit
+    pub fn serialize<S, K, T>(entity: &Entity<K, T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        K: Serialize,
+        T: Keyed<KeyType = K> + Serialize,
+    {
+        match entity {
+            Entity::Data(data) => super::serialize_depth(entity, serializer, |serializer| {
+                let key = data.key().map_err(S::Error::custom)?;
+                serializer.serialize_newtype_variant("Entity", 0, "Key", key)
+            }),
+            entity => entity.serialize(serializer),
+        }
+    }
+
+    /// Deserializes the Entity as usual
+    pub fn deserialize<'de, D, K, T>(deserializer: D) -> Result<Entity<K, T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: Deserialize<'de>,
+        T: Deserialize<'de>,
+    {
+        Entity::deserialize(deserializer)
+    }
+}
+
+pub mod entity_label_depth {
+    //! Serializes an EntityLabel field as usual, but writes its Data as a
+    //! KeyLabel beyond the depth limit of a [`DepthLimited`](super::DepthLimited)
+
+    use ::serde::ser::{Error, SerializeTupleVariant};
+    use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{EntityLabel, Keyed, Label};
+
+    /// Serializes the EntityLabel, as a KeyLabel if it's Data beyond the depth limit
+    pub fn serialize<S, K, T, L>(entity: &EntityLabel<K, T, L>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        K: Serialize,
+        L: Serialize,
+        T: Keyed<KeyType = K> + Label<LabelType = L> + Serialize,
+    {
+        match entity {
+            EntityLabel::Data(data) => super::serialize_depth(entity, serializer, |serializer| {
+                let mut variant = serializer.serialize_tuple_variant("EntityLabel", 0, "KeyLabel", 2)?;
+                variant.serialize_field(data.key().map_err(S::Error::custom)?)?;
+                variant.serialize_field(data.label().map_err(S::Error::custom)?)?;
+                variant.end()
+            }),
+            entity => entity.serialize(serializer),
+        }
+    }
+
+    /// Deserializes the EntityLabel as usual
+    pub fn deserialize<'de, D, K, T, L>(deserializer: D) -> Result<EntityLabel<K, T, L>, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: Deserialize<'de>,
+        T: Deserialize<'de>,
+        L: Deserialize<'de>,
+    {
+        EntityLabel::deserialize(deserializer)
+    }
+}
+
+pub mod key_string {
+    //! Serializes Key values as strings, so 64-bit ids survive JavaScript
+    //! numbers, and deserializes them from strings or numbers
+
+    use core::fmt;
+    use core::str::FromStr;
+
+    use ::serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::Key;
+
+    /// Serializes the Key value as a string, or `null` for empty Keys
+    pub fn serialize<S, K>(key: &Key<K>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        K: fmt::Display,
+    {
+        match &key.0 {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes a Key from `null`, a string or the value itself
+    pub fn deserialize<'de, D, K>(deserializer: D) -> Result<Key<K>, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: FromStr + Deserialize<'de>,
+        K::Err: fmt::Display,
+    {
+        super::deserialize_lenient(deserializer)
+    }
+}
+
+pub mod key_lenient {
+    //! Serializes Keys as usual, and deserializes them from their value or
+    //! the value as a string
+
+    use core::fmt;
+    use core::str::FromStr;
+
+    use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::Key;
+
+    /// Serializes the Key as usual
+    pub fn serialize<S, K>(key: &Key<K>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        K: Serialize,
+    {
+        key.serialize(serializer)
+    }
+
+    /// Deserializes a Key from `null`, a string or the value itself
+    pub fn deserialize<'de, D, K>(deserializer: D) -> Result<Key<K>, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: FromStr + Deserialize<'de>,
+        K::Err: fmt::Display,
+    {
+        super::deserialize_lenient(deserializer)
+    }
+}
+
+pub mod key_hex {
+    //! Serializes binary Keys, like `Key<Vec<u8>>` or `Key<[u8; 16]>`, as
+    //! hex strings
+
+    use ::serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::Key;
+
+    /// Serializes the Key value as a lowercase hex string, or `null` for empty Keys
+    pub fn serialize<S, K>(key: &Key<K>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        K: AsRef<[u8]>,
+    {
+        match &key.0 {
+            Some(_) => serializer.collect_str(&key.hex()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes a Key from `null` or a hex string
+    pub fn deserialize<'de, D, K>(deserializer: D) -> Result<Key<K>, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: TryFrom<Vec<u8>>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(text) => Key::from_hex(&text).map_err(::serde::de::Error::custom),
+            None => Ok(Key(None)),
+        }
+    }
+}
+
+thread_local! {
+    /// The policy of the innermost [`Redacting`] being serialized
+    static REDACTION: Cell<Option<Redaction>> = const { Cell::new(None) };
+}
+
+/// How [`Redacted`] fields are written by a [`Redacting`] serialization
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Redaction {
+    /// Writes the values, for trusted readers like admin endpoints
+    Reveal,
+    /// Writes `null` instead of the values
+    Null,
+    /// Writes the placeholder string instead of the values
+    Placeholder(&'static str),
+}
+
+/// A field value hidden from the serializations that ask for it
+///
+/// Inside a [`Redacting`] serialization it is written as its policy says,
+/// and elsewhere as the value, so storage and trusted formats keep it.
+/// Deserializing reads the value.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Redacted<T>(pub T);
+
+impl<T> Redacted<T> {
+    /// Returns the value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Redacted<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> core::ops::Deref for Redacted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> core::ops::DerefMut for Redacted<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Serialize> Serialize for Redacted<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match REDACTION.with(Cell::get) {
+            None | Some(Redaction::Reveal) => self.0.serialize(serializer),
+            Some(Redaction::Null) => serializer.serialize_none(),
+            Some(Redaction::Placeholder(placeholder)) => serializer.serialize_str(placeholder),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Redacted<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+/// Restores the redaction policy when dropped, even if serializing panics
+struct RedactionGuard(Option<Redaction>);
+
+impl Drop for RedactionGuard {
+    fn drop(&mut self) {
+        REDACTION.with(|redaction| redaction.set(self.0));
+    }
+}
+
+/// Serializes a value writing its [`Redacted`] fields as the policy says
+///
+/// The same model can then serve a public endpoint with placeholders and an
+/// admin one with [`Redaction::Reveal`]:
+///
+/// ```
+/// use dbent::serde::{Redacted, Redacting, Redaction};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+///     email: Redacted<String>,
+/// }
+///
+/// let user = User { name: "Ann".to_owned(), email: Redacted("ann@example.com".to_owned()) };
+/// let json = serde_json::to_string(&Redacting::new(&user, Redaction::Placeholder("***"))).unwrap();
+/// assert_eq!(json, r#"{"name":"Ann","email":"***"}"#);
+/// let json = serde_json::to_string(&Redacting::new(&user, Redaction::Reveal)).unwrap();
+/// assert_eq!(json, r#"{"name":"Ann","email":"ann@example.com"}"#);
+/// ```
+pub struct Redacting<'a, T: ?Sized> {
+    value: &'a T,
+    redaction: Redaction,
+}
+
+impl<'a, T: ?Sized> Redacting<'a, T> {
+    /// Wraps `value`, writing its Redacted fields with `redaction`
+    pub fn new(value: &'a T, redaction: Redaction) -> Self {
+        Self { value, redaction }
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for Redacting<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let _guard = RedactionGuard(REDACTION.with(|redaction| redaction.replace(Some(self.redaction))));
+        self.value.serialize(serializer)
+    }
+}
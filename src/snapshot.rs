@@ -0,0 +1,204 @@
+//! Snapshots for reverting or inspecting entity edits
+//!
+//! A [`Snapshot`] keeps a copy of an entity as it was before editing, so an
+//! edit form can be canceled by reverting to it, or saved by writing only the
+//! columns that changed:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::prelude::*;
+//! use dbent::snapshot::TakeSnapshot;
+//!
+//! #[derive(Entity, Clone, PartialEq, Debug)]
+//! #[entity(fields)]
+//! pub struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//!     age: i32,
+//! }
+//!
+//! # pub fn main() {
+//! let mut bob = User { id: Key::new(1), name: "Bob".to_owned(), age: 30 };
+//! let snapshot = bob.snapshot();
+//!
+//! bob.age = 31;
+//! let changes = snapshot.diff(&bob);
+//! assert_eq!(changes.len(), 1);
+//! assert_eq!(changes[0].column, "age");
+//!
+//! snapshot.revert(&mut bob);
+//! assert!(!snapshot.is_changed(&bob));
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+//!
+//! [`Snapshot::diff`] compares the values of the columns listed by
+//! [`Columns`], which `#[derive(Entity)]` implements with `#[entity(fields)]`,
+//! returning a [`Changeset`].
+//!
+//! [`EditHistory`] stacks snapshots to undo and redo a series of edits.
+
+use std::collections::VecDeque;
+
+pub use crate::changeset::{Change, Changeset};
+
+use crate::sql::Columns;
+
+/// A copy of an entity taken before editing it
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Snapshot<T> {
+    data: T,
+}
+
+impl<T: Clone> Snapshot<T> {
+    /// Captures the current data of `entity`
+    pub fn new(entity: &T) -> Self {
+        Self { data: entity.clone() }
+    }
+
+    /// Restores `entity` to the captured data
+    pub fn revert(&self, entity: &mut T) {
+        entity.clone_from(&self.data);
+    }
+}
+
+impl<T> Snapshot<T> {
+    /// Returns the captured data
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    /// Returns the captured data, consuming the snapshot
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T: PartialEq> Snapshot<T> {
+    /// Returns true if `entity` differs from the captured data
+    pub fn is_changed(&self, entity: &T) -> bool {
+        self.data != *entity
+    }
+}
+
+impl<T: Columns> Snapshot<T> {
+    /// Returns the columns whose values differ between the captured data and `entity`
+    pub fn diff(&self, entity: &T) -> Changeset {
+        Changeset::between(&self.data, entity)
+    }
+}
+
+/// Trait for taking snapshots of entities with method syntax
+///
+/// Implemented for every `Clone` type.
+pub trait TakeSnapshot: Clone {
+    /// Captures the current data
+    fn snapshot(&self) -> Snapshot<Self> {
+        Snapshot::new(self)
+    }
+}
+
+impl<T: Clone> TakeSnapshot for T {}
+
+/// Undo and redo history of the edits made to an entity
+///
+/// Each recorded edit keeps a [`Snapshot`] of the data before it, so `T` can
+/// also be a small graph, like an entity with its `Many` children. Only the
+/// last `depth` edits can be undone.
+///
+/// ```
+/// use dbent::snapshot::EditHistory;
+///
+/// let mut name = "Bob".to_owned();
+/// let mut history = EditHistory::new(10);
+/// history.edit(&mut name, |name| name.push_str("by"));
+/// assert_eq!(name, "Bobby");
+///
+/// assert!(history.undo(&mut name));
+/// assert_eq!(name, "Bob");
+/// assert!(history.redo(&mut name));
+/// assert_eq!(name, "Bobby");
+/// ```
+#[derive(Clone, Debug)]
+pub struct EditHistory<T> {
+    undo: VecDeque<Snapshot<T>>,
+    redo: Vec<Snapshot<T>>,
+    depth: usize,
+}
+
+impl<T: Clone> EditHistory<T> {
+    /// Creates an empty history keeping up to `depth` edits
+    pub fn new(depth: usize) -> Self {
+        Self { undo: VecDeque::new(), redo: Vec::new(), depth }
+    }
+
+    /// Records the data of `entity` before an edit made by the caller
+    ///
+    /// Clears the edits that could be redone.
+    pub fn record(&mut self, entity: &T) {
+        self.redo.clear();
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(Snapshot::new(entity));
+    }
+
+    /// Records the data of `entity`, then edits it with `f`
+    pub fn edit<R>(&mut self, entity: &mut T, f: impl FnOnce(&mut T) -> R) -> R {
+        self.record(entity);
+        f(entity)
+    }
+
+    /// Reverts `entity` to before the last edit, returning false if there is none
+    pub fn undo(&mut self, entity: &mut T) -> bool {
+        match self.undo.pop_back() {
+            Some(snapshot) => {
+                self.redo.push(Snapshot::new(entity));
+                snapshot.revert(entity);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Applies again the last undone edit to `entity`, returning false if there is none
+    pub fn redo(&mut self, entity: &mut T) -> bool {
+        match self.redo.pop() {
+            Some(snapshot) => {
+                self.undo.push_back(Snapshot::new(entity));
+                snapshot.revert(entity);
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+impl<T> EditHistory<T> {
+    /// Returns true if there are edits to undo
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns true if there are edits to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Returns the maximum number of edits kept
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Forgets all the edits
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
@@ -0,0 +1,128 @@
+//! Snowflake-style distributed id generation
+//!
+//! Ids are 63-bit positive integers composed of, from the most significant bits:
+//!
+//! - 41 bits of milliseconds elapsed since the generator epoch
+//! - 10 bits of node id, unique for every process generating ids
+//! - 12 bits of sequence, for ids generated in the same millisecond
+//!
+//! As long as every process uses a distinct node id, ids never collide and
+//! are roughly ordered by creation time, which makes them suitable as primary
+//! keys for sharded writes that can't rely on the database AUTOINCREMENT.
+//!
+//! ```
+//! use dbent::snowflake::Snowflake;
+//!
+//! let generator = Snowflake::new(7).unwrap();
+//! let key = generator.next_key();
+//! let parts = generator.decompose(key.unwrap());
+//! assert_eq!(parts.node_id, 7);
+//! ```
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Error, Key, Result};
+
+/// Number of bits used for the node id
+pub const NODE_ID_BITS: u32 = 10;
+/// Number of bits used for the sequence
+pub const SEQUENCE_BITS: u32 = 12;
+/// The highest node id allowed
+pub const MAX_NODE_ID: u16 = (1 << NODE_ID_BITS) - 1;
+/// The default epoch, 2020-01-01T00:00:00Z, in milliseconds since the UNIX epoch
+pub const DEFAULT_EPOCH_MS: u64 = 1_577_836_800_000;
+
+const SEQUENCE_MASK: i64 = (1 << SEQUENCE_BITS) - 1;
+const TIMESTAMP_SHIFT: u32 = NODE_ID_BITS + SEQUENCE_BITS;
+
+/// Generator of snowflake ids for a single node
+///
+/// The generator is lock free and can be shared between threads. When the
+/// sequence for the current millisecond is exhausted, or the system clock
+/// moves backwards, ids keep increasing by borrowing from the next millisecond
+/// so they are always unique and monotonic for a node.
+#[derive(Debug)]
+pub struct Snowflake {
+    node_id: u16,
+    epoch: SystemTime,
+    /// Last used `timestamp << SEQUENCE_BITS | sequence`
+    state: AtomicI64,
+}
+
+/// The components of a snowflake id
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SnowflakeParts {
+    /// Milliseconds elapsed since the generator epoch
+    pub timestamp_ms: u64,
+    /// Id of the node that generated the id
+    pub node_id: u16,
+    /// Sequence within the millisecond
+    pub sequence: u16,
+}
+
+impl Snowflake {
+    /// Creates a generator for `node_id` using the default epoch
+    pub fn new(node_id: u16) -> Result<Self> {
+        Self::with_epoch(node_id, UNIX_EPOCH + Duration::from_millis(DEFAULT_EPOCH_MS))
+    }
+
+    /// Creates a generator for `node_id` counting time from `epoch`
+    pub fn with_epoch(node_id: u16, epoch: SystemTime) -> Result<Self> {
+        if node_id > MAX_NODE_ID {
+            return Err(Error::InvalidNodeId(node_id));
+        }
+
+        Ok(Self { node_id, epoch, state: AtomicI64::new(0) })
+    }
+
+    /// Returns the node id of this generator
+    pub fn node_id(&self) -> u16 {
+        self.node_id
+    }
+
+    /// Generates a new id
+    pub fn next_id(&self) -> i64 {
+        let now = self.elapsed_ms() << SEQUENCE_BITS;
+        let mut prev = self.state.load(Ordering::Relaxed);
+
+        loop {
+            let next = if now > prev { now } else { prev + 1 };
+
+            match self.state.compare_exchange_weak(prev, next, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {
+                    let timestamp = next >> SEQUENCE_BITS;
+                    let sequence = next & SEQUENCE_MASK;
+                    return (timestamp << TIMESTAMP_SHIFT) | (i64::from(self.node_id) << SEQUENCE_BITS) | sequence;
+                },
+                Err(current) => prev = current,
+            }
+        }
+    }
+
+    /// Generates a new id wrapped in a Key
+    pub fn next_key(&self) -> Key<i64> {
+        Key::new(self.next_id())
+    }
+
+    /// Splits an id into its components
+    pub fn decompose(&self, id: i64) -> SnowflakeParts {
+        SnowflakeParts {
+            timestamp_ms: (id >> TIMESTAMP_SHIFT) as u64,
+            node_id: ((id >> SEQUENCE_BITS) & i64::from(MAX_NODE_ID)) as u16,
+            sequence: (id & SEQUENCE_MASK) as u16,
+        }
+    }
+
+    /// Returns the time an id was generated at
+    pub fn timestamp(&self, id: i64) -> SystemTime {
+        self.epoch + Duration::from_millis(self.decompose(id).timestamp_ms)
+    }
+
+    fn elapsed_ms(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(self.epoch)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,899 @@
+//! Building blocks for composing SQL from entity fields
+//!
+//! `#[derive(Entity)]` with `#[entity(fields)]` generates a module named after
+//! the struct in snake case with a `fields` submodule holding a typed [`Field`]
+//! constant for every column:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::prelude::*;
+//!
+//! #[derive(Entity)]
+//! #[entity(fields)]
+//! pub struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//!     age: i32,
+//! }
+//!
+//! # pub fn main() {
+//! let filter = user::fields::NAME.eq("bob") & user::fields::AGE.gt(18);
+//! assert_eq!(filter.sql(), "(name = ?) AND (age > ?)");
+//! assert_eq!(filter.params().len(), 2);
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+//!
+//! Filters render to SQL fragments with `?` placeholders and the [`Value`]s to
+//! bind to them, in order. With the `rusqlite` feature, `Value` implements
+//! `ToSql`, so `rusqlite::params_from_iter(filter.params())` binds them.
+//!
+//! An [`OrderBy`] renders to an ORDER BY list and can also sort fetched data
+//! in memory the same way SQLite would, through the [`Columns`] trait that
+//! `#[entity(fields)]` implements too.
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{BitAnd, BitOr, Not};
+use std::borrow::Cow;
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::types::{ToSql, ToSqlOutput};
+
+use crate::{Key, Many, Result};
+
+/// A value that can be bound as an SQL parameter
+#[derive(Clone, PartialEq, Debug, Default)]
+pub enum Value {
+    /// SQL NULL
+    #[default]
+    Null,
+    /// An integer
+    Integer(i64),
+    /// An unsigned integer too large for an `Integer`, which SQLite can't
+    /// store, so binding it fails
+    Unsigned(u64),
+    /// A floating point number
+    Real(f64),
+    /// A string
+    Text(String),
+    /// Binary data
+    Blob(Vec<u8>),
+}
+
+macro_rules! impl_value_from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Value::Integer(i64::from(value))
+                }
+            }
+        )*
+    };
+}
+
+impl_value_from_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+/// Keeps integers above `i64::MAX` as `Unsigned` instead of wrapping around,
+/// so binding them fails
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        i64::try_from(value).map_or(Value::Unsigned(value), Value::Integer)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        // usize is at most 64 bits wide
+        Value::from(value as u64)
+    }
+}
+
+impl From<isize> for Value {
+    fn from(value: isize) -> Self {
+        // isize is at most 64 bits wide
+        Value::Integer(value as i64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Integer(i64::from(value))
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Real(value as f64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Real(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_owned())
+    }
+}
+
+impl From<Cow<'_, str>> for Value {
+    fn from(value: Cow<'_, str>) -> Self {
+        Value::Text(value.into_owned())
+    }
+}
+
+impl From<char> for Value {
+    fn from(value: char) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+#[cfg(feature = "intern")]
+impl From<crate::intern::InternedStr> for Value {
+    fn from(value: crate::intern::InternedStr) -> Self {
+        Value::Text(value.as_str().to_owned())
+    }
+}
+
+/// Lowercases the text, like the `ToSql` of `CiString`
+impl From<crate::CiString> for Value {
+    fn from(value: crate::CiString) -> Self {
+        Value::Text(value.to_lowercase())
+    }
+}
+
+impl<const N: usize> From<crate::fixed_str::FixedStr<N>> for Value {
+    fn from(value: crate::fixed_str::FixedStr<N>) -> Self {
+        Value::Text(value.into())
+    }
+}
+
+impl<K: Into<Value>> From<crate::Sensitive<K>> for Value {
+    fn from(value: crate::Sensitive<K>) -> Self {
+        value.into_inner().into()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::NaiveDate> for Value {
+    fn from(value: chrono::NaiveDate) -> Self {
+        Value::Text(value.format("%F").to_string())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::NaiveDateTime> for Value {
+    fn from(value: chrono::NaiveDateTime) -> Self {
+        Value::Text(value.format("%F %T%.f").to_string())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Value {
+    fn from(value: chrono::DateTime<chrono::Utc>) -> Self {
+        Value::Text(value.format("%F %T%.f%:z").to_string())
+    }
+}
+
+/// Formats a `time` date time like rusqlite does, with the shortest fraction of seconds
+#[cfg(feature = "time")]
+fn time_text(date: time::Date, time: Option<time::Time>) -> String {
+    let mut text = format!("{:04}-{:02}-{:02}", date.year(), u8::from(date.month()), date.day());
+    if let Some(time) = time {
+        let fraction = format!("{:09}", time.nanosecond());
+        let fraction = fraction.trim_end_matches('0');
+        let fraction = if fraction.is_empty() { "0" } else { fraction };
+        text.push_str(&format!(" {:02}:{:02}:{:02}.{fraction}", time.hour(), time.minute(), time.second()));
+    }
+    text
+}
+
+#[cfg(feature = "time")]
+impl From<time::Date> for Value {
+    fn from(value: time::Date) -> Self {
+        Value::Text(time_text(value, None))
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::PrimitiveDateTime> for Value {
+    fn from(value: time::PrimitiveDateTime) -> Self {
+        Value::Text(time_text(value.date(), Some(value.time())))
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Value {
+    fn from(value: time::OffsetDateTime) -> Self {
+        let utc = value.to_offset(time::UtcOffset::UTC);
+        Value::Text(time_text(utc.date(), Some(utc.time())) + "Z")
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<crate::decimal::Decimal> for Value {
+    fn from(value: crate::decimal::Decimal) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Blob(value)
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(value: &[u8]) -> Self {
+        Value::Blob(value.to_vec())
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Value {
+    fn from(value: [u8; N]) -> Self {
+        Value::Blob(value.to_vec())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<K: Into<Value>> From<Key<K>> for Value {
+    fn from(key: Key<K>) -> Self {
+        key.0.into()
+    }
+}
+
+impl From<crate::version::Version> for Value {
+    fn from(version: crate::version::Version) -> Self {
+        Value::Integer(version.0)
+    }
+}
+
+/// Trait for unit enums stored in a TEXT or INTEGER column as a value per
+/// variant, implemented by `#[derive(DbEnum)]`
+///
+/// The values are set on the variants, so renaming or reordering them
+/// doesn't change what is stored.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use dbent::prelude::*;
+/// use dbent::sql::{DbEnum, Value};
+///
+/// #[derive(DbEnum, Clone, Copy, PartialEq, Debug)]
+/// enum Status {
+///     #[db_enum(value = "open")]
+///     Active,
+///     Closed,
+/// }
+///
+/// assert_eq!(Status::Active.db_value(), Value::Text("open".to_owned()));
+/// assert_eq!(Status::from_db_value(&Value::Text("closed".to_owned())), Some(Status::Closed));
+/// assert_eq!(Status::from_db_value(&Value::Text("active".to_owned())), None);
+/// # }
+/// ```
+pub trait DbEnum: Sized + 'static {
+    /// The name of the enum, for errors
+    const NAME: &'static str;
+
+    /// Returns the value stored for the variant
+    fn db_value(&self) -> Value;
+
+    /// Returns the variant stored as `value`, or None if no variant matches
+    fn from_db_value(value: &Value) -> Option<Self>;
+
+    /// Returns the variant stored as `value`, failing with
+    /// `Error::UnknownVariant` if no variant matches
+    fn try_from_db_value(value: Value) -> Result<Self> {
+        Self::from_db_value(&value).ok_or(crate::Error::UnknownVariant { name: Self::NAME, value })
+    }
+}
+
+impl Value {
+    /// Compares two values like SQLite does with the BINARY collation
+    ///
+    /// NULLs sort before numbers, which sort before text, which sorts before blobs.
+    pub fn sql_cmp(&self, other: &Value) -> Ordering {
+        fn rank(value: &Value) -> u8 {
+            match value {
+                Value::Null => 0,
+                Value::Integer(_) | Value::Unsigned(_) | Value::Real(_) => 1,
+                Value::Text(_) => 2,
+                Value::Blob(_) => 3,
+            }
+        }
+
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Integer(a), Value::Real(b)) => (*a as f64).total_cmp(b),
+            (Value::Real(a), Value::Integer(b)) => a.total_cmp(&(*b as f64)),
+            (Value::Real(a), Value::Real(b)) => a.total_cmp(b),
+            (Value::Unsigned(a), Value::Unsigned(b)) => a.cmp(b),
+            (Value::Integer(a), Value::Unsigned(b)) => i128::from(*a).cmp(&i128::from(*b)),
+            (Value::Unsigned(a), Value::Integer(b)) => i128::from(*a).cmp(&i128::from(*b)),
+            (Value::Unsigned(a), Value::Real(b)) => (*a as f64).total_cmp(b),
+            (Value::Real(a), Value::Unsigned(b)) => a.total_cmp(&(*b as f64)),
+            (Value::Text(a), Value::Text(b)) => a.as_bytes().cmp(b.as_bytes()),
+            (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl ToSql for Value {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        use rusqlite::types::ValueRef;
+
+        Ok(
+            ToSqlOutput::Borrowed(
+                match self {
+                    Value::Null => ValueRef::Null,
+                    Value::Integer(value) => ValueRef::Integer(*value),
+                    Value::Unsigned(value) => ValueRef::Integer(unsigned_to_sql(*value)?),
+                    Value::Real(value) => ValueRef::Real(*value),
+                    Value::Text(value) => ValueRef::Text(value.as_bytes()),
+                    Value::Blob(value) => ValueRef::Blob(value),
+                }
+            )
+        )
+    }
+}
+
+/// Returns the unsigned integer as an SQL integer, failing with
+/// `Error::IntegerOutOfRange` if it doesn't fit
+#[cfg(feature = "rusqlite")]
+pub(crate) fn unsigned_to_sql(value: u64) -> rusqlite::Result<i64> {
+    i64::try_from(value).map_err(|_| rusqlite::Error::ToSqlConversionFailure(Box::new(crate::Error::IntegerOutOfRange(value))))
+}
+
+#[cfg(feature = "rusqlite")]
+impl rusqlite::types::FromSql for Value {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        use rusqlite::types::ValueRef;
+
+        Ok(
+            match value {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(value) => Value::Integer(value),
+                ValueRef::Real(value) => Value::Real(value),
+                ValueRef::Text(value) => Value::Text(String::from_utf8_lossy(value).into_owned()),
+                ValueRef::Blob(value) => Value::Blob(value.to_vec()),
+            }
+        )
+    }
+}
+
+/// Words SQLite doesn't accept as bare identifiers
+const RESERVED: &[&str] = &[
+    "ADD", "ALL", "ALTER", "AND", "AS", "AUTOINCREMENT", "BETWEEN", "CASE", "CHECK", "COLLATE",
+    "COMMIT", "CONSTRAINT", "CREATE", "CROSS", "DEFAULT", "DEFERRABLE", "DELETE", "DISTINCT",
+    "DROP", "ELSE", "ESCAPE", "EXCEPT", "EXISTS", "FOREIGN", "FROM", "FULL", "GROUP", "HAVING",
+    "IN", "INDEX", "INNER", "INSERT", "INTERSECT", "INTO", "IS", "ISNULL", "JOIN", "LEFT", "LIMIT",
+    "NATURAL", "NOT", "NOTHING", "NOTNULL", "NULL", "ON", "OR", "ORDER", "OUTER", "PRIMARY",
+    "REFERENCES", "RETURNING", "RIGHT", "SELECT", "SET", "TABLE", "THEN", "TO", "TRANSACTION",
+    "UNION", "UNIQUE", "UPDATE", "USING", "VALUES", "WHEN", "WHERE",
+];
+
+/// Returns true for names made of ASCII letters, digits and underscores,
+/// not starting with a digit, which need no quoting unless reserved
+pub fn is_plain_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Returns the name as an SQL identifier, in double quotes with its quotes
+/// doubled unless it is a plain identifier that isn't a reserved word
+///
+/// The SQL assembled from metadata, like the DDL of [`EntityMeta`] and the
+/// statements of the `sqlite` helpers, passes table and column names through
+/// it, so a name holding a quote can't break out of the statement.
+///
+/// ```
+/// use dbent::sql::quote_ident;
+///
+/// assert_eq!(quote_ident("user_id"), "user_id");
+/// assert_eq!(quote_ident("order"), r#""order""#);
+/// assert_eq!(quote_ident(r#"x" OR 1 --"#), r#""x"" OR 1 --""#);
+/// ```
+///
+/// [`EntityMeta`]: crate::schema::EntityMeta
+pub fn quote_ident(name: &str) -> Cow<'_, str> {
+    if is_plain_ident(name) && !RESERVED.iter().any(|word| word.eq_ignore_ascii_case(name)) {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("\"{}\"", name.replace('"', "\"\"")))
+    }
+}
+
+/// Returns the names passed through [`quote_ident`], separated by commas
+pub fn quote_list<S: AsRef<str>>(names: &[S]) -> String {
+    names.iter().map(|name| quote_ident(name.as_ref())).collect::<Vec<_>>().join(", ")
+}
+
+/// A typed token for the column of entity `E` holding values of type `V`
+pub struct Field<E, V> {
+    column: &'static str,
+    marker: PhantomData<fn() -> (E, V)>,
+}
+
+impl<E, V> Field<E, V> {
+    /// Creates a token for `column`
+    pub const fn new(column: &'static str) -> Self {
+        Self { column, marker: PhantomData }
+    }
+
+    /// Returns the column name
+    pub const fn column(&self) -> &'static str {
+        self.column
+    }
+
+    /// Filters rows where the column equals `value`, or is NULL for null values
+    pub fn eq(&self, value: impl Into<V>) -> Filter where V: Into<Value> {
+        match value.into().into() {
+            Value::Null => self.is_null(),
+            value => self.compare("=", value),
+        }
+    }
+
+    /// Filters rows where the column differs from `value`, or is not NULL for null values
+    pub fn ne(&self, value: impl Into<V>) -> Filter where V: Into<Value> {
+        match value.into().into() {
+            Value::Null => self.is_not_null(),
+            value => self.compare("<>", value),
+        }
+    }
+
+    /// Filters rows where the column is greater than `value`
+    pub fn gt(&self, value: impl Into<V>) -> Filter where V: Into<Value> {
+        self.compare(">", value.into().into())
+    }
+
+    /// Filters rows where the column is greater than or equal to `value`
+    pub fn ge(&self, value: impl Into<V>) -> Filter where V: Into<Value> {
+        self.compare(">=", value.into().into())
+    }
+
+    /// Filters rows where the column is less than `value`
+    pub fn lt(&self, value: impl Into<V>) -> Filter where V: Into<Value> {
+        self.compare("<", value.into().into())
+    }
+
+    /// Filters rows where the column is less than or equal to `value`
+    pub fn le(&self, value: impl Into<V>) -> Filter where V: Into<Value> {
+        self.compare("<=", value.into().into())
+    }
+
+    /// Filters rows where the column matches the LIKE `pattern`
+    pub fn like(&self, pattern: impl Into<String>) -> Filter {
+        self.compare("LIKE", Value::Text(pattern.into()))
+    }
+
+    /// Filters rows where the column is one of `values`
+    ///
+    /// An empty list matches no rows.
+    pub fn is_in<I>(&self, values: I) -> Filter
+    where
+        I: IntoIterator,
+        I::Item: Into<V>,
+        V: Into<Value>,
+    {
+        let params = values.into_iter().map(|value| value.into().into()).collect::<Vec<Value>>();
+        if params.is_empty() {
+            return Filter::new("1 = 0", Vec::new());
+        }
+
+        let placeholders = vec!["?"; params.len()].join(", ");
+        Filter::new(format!("{} IN ({placeholders})", quote_ident(self.column)), params)
+    }
+
+    /// Filters rows where the column is NULL
+    pub fn is_null(&self) -> Filter {
+        Filter::new(format!("{} IS NULL", quote_ident(self.column)), Vec::new())
+    }
+
+    /// Filters rows where the column is not NULL
+    pub fn is_not_null(&self) -> Filter {
+        Filter::new(format!("{} IS NOT NULL", quote_ident(self.column)), Vec::new())
+    }
+
+    /// Orders by this column ascending
+    pub fn asc(&self) -> OrderBy {
+        OrderBy::new().asc(*self)
+    }
+
+    /// Orders by this column descending
+    pub fn desc(&self) -> OrderBy {
+        OrderBy::new().desc(*self)
+    }
+
+    fn compare(&self, operator: &str, value: Value) -> Filter {
+        Filter::new(format!("{} {operator} ?", quote_ident(self.column)), vec![value])
+    }
+}
+
+impl<E, V> Clone for Field<E, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E, V> Copy for Field<E, V> {}
+
+impl<E, V> fmt::Debug for Field<E, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Field").field(&self.column).finish()
+    }
+}
+
+impl<E, V> From<Field<E, V>> for Cow<'static, str> {
+    fn from(field: Field<E, V>) -> Self {
+        Cow::Borrowed(field.column)
+    }
+}
+
+/// A WHERE clause fragment with its parameters
+#[derive(Clone, PartialEq, Debug)]
+pub struct Filter {
+    sql: String,
+    params: Vec<Value>,
+}
+
+impl Filter {
+    /// Creates a filter from raw SQL using `?` placeholders for `params`
+    pub fn new(sql: impl Into<String>, params: Vec<Value>) -> Self {
+        Self { sql: sql.into(), params }
+    }
+
+    /// Returns the SQL fragment
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Returns the parameters in placeholder order
+    pub fn params(&self) -> &[Value] {
+        &self.params
+    }
+
+    /// Returns the SQL fragment and its parameters
+    pub fn into_parts(self) -> (String, Vec<Value>) {
+        (self.sql, self.params)
+    }
+
+    /// Combines both filters with AND
+    pub fn and(self, other: Filter) -> Filter {
+        self.combine("AND", other)
+    }
+
+    /// Combines both filters with OR
+    pub fn or(self, other: Filter) -> Filter {
+        self.combine("OR", other)
+    }
+
+    fn combine(mut self, operator: &str, other: Filter) -> Filter {
+        self.params.extend(other.params);
+        Filter::new(format!("({}) {operator} ({})", self.sql, other.sql), self.params)
+    }
+}
+
+impl BitAnd for Filter {
+    type Output = Filter;
+
+    fn bitand(self, other: Filter) -> Filter {
+        self.and(other)
+    }
+}
+
+impl BitOr for Filter {
+    type Output = Filter;
+
+    fn bitor(self, other: Filter) -> Filter {
+        self.or(other)
+    }
+}
+
+impl Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter::new(format!("NOT ({})", self.sql), self.params)
+    }
+}
+
+/// Restricts the rows of `T` a repository reads and writes, like the rows of
+/// the current tenant
+///
+/// The repositories that support it, like
+/// [`sqlite::ScopedRepository`](crate::sqlite::ScopedRepository), append the
+/// [`filter`](Self::filter) to the WHERE clause of every statement, so rows out
+/// of the scope are neither found, listed, updated nor deleted, and check
+/// every entity written with [`check_write`](Self::check_write).
+pub trait ScopePolicy<T> {
+    /// Returns the predicate of the rows in the scope, or None for all of them
+    fn filter(&self) -> Option<Filter>;
+
+    /// Checks that the entity about to be inserted or updated belongs to the
+    /// scope, failing with `Error::ScopeMismatch` usually
+    fn check_write(&self, entity: &T) -> Result<()> {
+        let _ = entity;
+        Ok(())
+    }
+}
+
+impl<T, P: ScopePolicy<T> + ?Sized> ScopePolicy<T> for &P {
+    fn filter(&self) -> Option<Filter> {
+        (**self).filter()
+    }
+
+    fn check_write(&self, entity: &T) -> Result<()> {
+        (**self).check_write(entity)
+    }
+}
+
+/// Entities whose column values can be read by name
+///
+/// Implemented by `#[derive(Entity)]` with `#[entity(fields)]` for every
+/// column whose type converts into a [`Value`]; the other columns return
+/// `None` and compare as NULL.
+pub trait Columns {
+    /// Returns the value of `column`, if the entity has it
+    ///
+    /// Columns that fail to be read, like encrypted ones without a cipher,
+    /// return `None`.
+    fn column_value(&self, column: &str) -> Option<Value>;
+
+    /// Returns the value of `column` to write to the database, if the entity
+    /// has it, failing if it can't be read
+    ///
+    /// Used by the SQL helpers, so `#[entity(encrypt)]` columns fail with
+    /// `Error::CipherMissing` or the error of the cipher instead of being
+    /// written as NULL. Defaults to [`column_value`](Self::column_value).
+    fn try_column_value(&self, column: &str) -> Result<Option<Value>> {
+        Ok(self.column_value(column))
+    }
+
+    /// Returns the names of the columns, in field order
+    ///
+    /// Defaults to none, for implementations that only support lookups.
+    fn columns(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Returns the values of the columns named as `:column` placeholders,
+    /// for binding the whole entity to SQL using named parameters
+    #[cfg(feature = "rusqlite")]
+    fn named_params(&self) -> Result<crate::sqlite::NamedParams> {
+        crate::sqlite::NamedParams::new(self)
+    }
+}
+
+/// The direction of an ORDER BY term
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum Direction {
+    /// Smallest values first
+    #[default]
+    Asc,
+    /// Largest values first
+    Desc,
+}
+
+/// Where NULLs go in an ORDER BY term
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum Nulls {
+    /// The database default, first when ascending and last when descending in SQLite
+    #[default]
+    Default,
+    /// NULLs before any other value
+    First,
+    /// NULLs after any other value
+    Last,
+}
+
+/// A term of an [`OrderBy`]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct OrderTerm {
+    /// The column to sort by
+    pub column: Cow<'static, str>,
+    /// The sort direction
+    pub direction: Direction,
+    /// Where NULLs go
+    pub nulls: Nulls,
+}
+
+impl fmt::Display for OrderTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&quote_ident(&self.column))?;
+        f.write_str(match self.direction {
+            Direction::Asc => " ASC",
+            Direction::Desc => " DESC",
+        })?;
+        f.write_str(match self.nulls {
+            Nulls::Default => "",
+            Nulls::First => " NULLS FIRST",
+            Nulls::Last => " NULLS LAST",
+        })
+    }
+}
+
+/// A sort specification shared by SQL queries and fetched data
+///
+/// ```
+/// use dbent::sql::OrderBy;
+///
+/// let order = OrderBy::new().desc("age").nulls_last().asc("name");
+/// assert_eq!(order.to_string(), "age DESC NULLS LAST, name ASC");
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct OrderBy {
+    terms: Vec<OrderTerm>,
+}
+
+impl OrderBy {
+    /// Creates an empty OrderBy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an ascending term for a column name or field token
+    pub fn asc(self, column: impl Into<Cow<'static, str>>) -> Self {
+        self.push(column.into(), Direction::Asc)
+    }
+
+    /// Adds a descending term for a column name or field token
+    pub fn desc(self, column: impl Into<Cow<'static, str>>) -> Self {
+        self.push(column.into(), Direction::Desc)
+    }
+
+    /// Puts NULLs first in the last added term
+    pub fn nulls_first(self) -> Self {
+        self.nulls(Nulls::First)
+    }
+
+    /// Puts NULLs last in the last added term
+    pub fn nulls_last(self) -> Self {
+        self.nulls(Nulls::Last)
+    }
+
+    /// Appends the terms of `other`, to break ties
+    pub fn then(mut self, other: OrderBy) -> Self {
+        self.terms.extend(other.terms);
+        self
+    }
+
+    /// Returns the terms in priority order
+    pub fn terms(&self) -> &[OrderTerm] {
+        &self.terms
+    }
+
+    /// Returns true if there are no terms
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Returns the ` ORDER BY ...` clause to append to a query, or an empty string
+    pub fn to_sql(&self) -> String {
+        if self.is_empty() {
+            String::new()
+        } else {
+            format!(" ORDER BY {self}")
+        }
+    }
+
+    /// Compares two entities by the terms, like the database would
+    pub fn compare<T: Columns>(&self, a: &T, b: &T) -> Ordering {
+        for term in &self.terms {
+            let a = a.column_value(&term.column).unwrap_or_default();
+            let b = b.column_value(&term.column).unwrap_or_default();
+
+            let nulls_first = match term.nulls {
+                Nulls::Default => term.direction == Direction::Asc,
+                Nulls::First => true,
+                Nulls::Last => false,
+            };
+
+            let ordering = match (a == Value::Null, b == Value::Null) {
+                (true, true) => Ordering::Equal,
+                (true, false) if nulls_first => Ordering::Less,
+                (true, false) => Ordering::Greater,
+                (false, true) if nulls_first => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => match term.direction {
+                    Direction::Asc => a.sql_cmp(&b),
+                    Direction::Desc => b.sql_cmp(&a),
+                },
+            };
+
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+
+        Ordering::Equal
+    }
+
+    /// Sorts a slice of entities by the terms; the sort is stable
+    pub fn sort<T: Columns>(&self, data: &mut [T]) {
+        data.sort_by(|a, b| self.compare(a, b));
+    }
+
+    fn push(mut self, column: Cow<'static, str>, direction: Direction) -> Self {
+        self.terms.push(OrderTerm { column, direction, nulls: Nulls::Default });
+        self
+    }
+
+    fn nulls(mut self, nulls: Nulls) -> Self {
+        if let Some(term) = self.terms.last_mut() {
+            term.nulls = nulls;
+        }
+        self
+    }
+}
+
+impl fmt::Display for OrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, term) in self.terms.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{term}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Columns> Many<T> {
+    /// Sorts the fetched data by `order`
+    pub fn sort_by_order(&mut self, order: &OrderBy) -> Result<()> {
+        order.sort(self.data_mut()?);
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the code generated by `#[entity(fields)]`
+
+    use super::Value;
+
+    pub struct ColumnRef<'a, T>(pub &'a T);
+
+    pub trait ToValue {
+        fn to_value(&self) -> Option<Value>;
+    }
+
+    impl<T: Clone + Into<Value>> ToValue for ColumnRef<'_, T> {
+        fn to_value(&self) -> Option<Value> {
+            Some(self.0.clone().into())
+        }
+    }
+
+    pub trait NoValue {
+        fn to_value(&self) -> Option<Value>;
+    }
+
+    impl<T> NoValue for &ColumnRef<'_, T> {
+        fn to_value(&self) -> Option<Value> {
+            None
+        }
+    }
+}
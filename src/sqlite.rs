@@ -0,0 +1,1901 @@
+//! Writing entities with rusqlite
+//!
+//! These helpers build their statements from the [`Schema`] and [`Columns`]
+//! that `#[derive(Entity)]` implements with `#[entity(fields)]`. Entities
+//! with an empty key are inserted without it, and the key SQLite assigns is
+//! written back:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::prelude::*;
+//! use dbent::schema::Schema;
+//!
+//! #[derive(Entity)]
+//! #[entity(fields)]
+//! pub struct User {
+//!     id: Key<Int>,
+//!     name: String,
+//! }
+//!
+//! # pub fn main() -> dbent::Result<()> {
+//! let conn = rusqlite::Connection::open_in_memory()?;
+//! conn.execute(&User::entity_meta().create_table_sql(), [])?;
+//!
+//! let mut users: Many<User> = vec![
+//!     User { id: Key(None), name: "Ann".to_owned() },
+//!     User { id: Key(None), name: "Bob".to_owned() },
+//! ].into();
+//! dbent::sqlite::insert_many(&conn, &mut users)?;
+//! assert_eq!(users[1].id, Key::new(2));
+//! # Ok(())
+//! # }
+//! # }
+//! # example::main().unwrap();
+//! # }
+//! ```
+
+use core::any::type_name;
+use core::fmt;
+use core::hash::Hash;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use rusqlite::types::{FromSql, ToSql, ToSqlOutput, ValueRef};
+
+use crate::hooks::Lifecycle;
+use crate::key_value::KeyValue;
+use crate::repository::Repository;
+use crate::schema::{OnDelete, RegisteredEntity, Registry, Schema};
+use crate::sql::{quote_ident, quote_list, Columns, Filter, OrderBy, ScopePolicy, Value};
+use crate::trace;
+use crate::validate::{FieldErrorKind, ValidationErrors};
+use crate::{Error, Keyed, KeyedMut, Many, Result, Tag};
+
+/// Returns the names of the columns of the table, and the key column if any
+fn column_names(meta: &crate::schema::EntityMeta) -> (Vec<&str>, Option<&str>) {
+    let columns = meta.columns.iter().map(|column| column.name.as_ref()).collect();
+    (columns, meta.key().map(|column| column.name.as_ref()))
+}
+
+/// The column values of an entity named as `:column` placeholders
+///
+/// Returned by [`Columns::named_params`], it binds a whole entity to
+/// hand-written SQL, complementing the positional parameters of the other
+/// helpers. Columns without a value are bound as NULL.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # mod example {
+/// use dbent::prelude::*;
+/// use dbent::schema::Schema;
+/// use dbent::sql::Columns;
+///
+/// #[derive(Entity)]
+/// #[entity(fields)]
+/// pub struct User {
+///     id: Key<Int>,
+///     name: String,
+/// }
+///
+/// # pub fn main() -> dbent::Result<()> {
+/// let conn = rusqlite::Connection::open_in_memory()?;
+/// conn.execute(&User::entity_meta().create_table_sql(), [])?;
+///
+/// let user = User { id: Key::new(7), name: "Ann".to_owned() };
+/// let params = user.named_params()?;
+/// conn.execute("INSERT INTO user (id, name) VALUES (:id, :name)", &*params.as_params())?;
+/// # Ok(())
+/// # }
+/// # }
+/// # example::main().unwrap();
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct NamedParams(Vec<(String, Value)>);
+
+impl NamedParams {
+    /// Reads the values of all the columns of the entity
+    ///
+    /// Fails like [`Columns::try_column_value`], for encrypted columns.
+    pub fn new<T: Columns + ?Sized>(entity: &T) -> Result<Self> {
+        entity
+            .columns()
+            .iter()
+            .map(|column| Ok((format!(":{column}"), entity.try_column_value(column)?.unwrap_or_default())))
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    /// Returns the value of the `:name` placeholder, with or without the colon
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        let name = name.strip_prefix(':').unwrap_or(name);
+        self.0.iter().find(|(param, _)| &param[1..] == name).map(|(_, value)| value)
+    }
+
+    /// Returns the parameters in the form rusqlite binds by name
+    pub fn as_params(&self) -> Vec<(&str, &dyn ToSql)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value as &dyn ToSql)).collect()
+    }
+
+    /// Returns the placeholder names and values
+    pub fn into_inner(self) -> Vec<(String, Value)> {
+        self.0
+    }
+}
+
+impl Deref for NamedParams {
+    type Target = [(String, Value)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Returns the key as a [`KeyValue`] for tracing, if it converts
+///
+/// [`Sensitive`](crate::Sensitive) keys are masked.
+fn key_value<K: ToSql>(key: &K) -> Option<KeyValue> {
+    let output = crate::to_sql_masked(key).ok()?;
+    let value = match &output {
+        ToSqlOutput::Borrowed(value) => *value,
+        ToSqlOutput::Owned(value) => value.into(),
+        _ => return None,
+    };
+    KeyValue::column_result(value).ok()
+}
+
+/// Returns whether the linked SQLite supports `RETURNING`, added in 3.35
+fn supports_returning() -> bool {
+    rusqlite::version_number() >= 3_035_000
+}
+
+/// Inserts the entity, writing back the key assigned to it if it has none
+///
+/// See [`insert_all`].
+pub fn insert<T>(conn: &rusqlite::Connection, entity: &mut T) -> Result<()>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle,
+    T::KeyType: FromSql,
+{
+    insert_all(conn, std::slice::from_mut(entity))
+}
+
+/// Inserts the entities of `many`, writing back the keys assigned to the ones
+/// without a key
+///
+/// Fails with `Error::ManyNotFetched` or `Error::ManyEmpty` if `many` has no data.
+pub fn insert_many<T>(conn: &rusqlite::Connection, many: &mut Many<T>) -> Result<()>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle,
+    T::KeyType: FromSql,
+{
+    insert_all(conn, many.data_mut()?)
+}
+
+/// Inserts the entities, writing back the keys assigned to the ones without a key
+///
+/// The rows are inserted with cached prepared statements inside a
+/// transaction, or inside the current one if there is one, so either all of
+/// them are inserted or none. The keys are only written back once all the
+/// rows are inserted and committed, so on failure no entity holds the key
+/// of a row that was rolled back.
+///
+/// The keys are read with `RETURNING` on SQLite 3.35 and later, so keys
+/// filled by column defaults are written back too, and with
+/// `last_insert_rowid()` on older versions.
+pub fn insert_all<T>(conn: &rusqlite::Connection, entities: &mut [T]) -> Result<()>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle,
+    T::KeyType: FromSql,
+{
+    if entities.is_empty() {
+        return Ok(());
+    }
+
+    let meta = T::entity_meta();
+    let rows = entities.len();
+    trace::traced("insert", &meta.table, None, || insert_rows(conn, &meta, entities), |_| rows)
+}
+
+/// Inserts the entities as rows of the table described by `meta`
+fn insert_rows<T>(conn: &rusqlite::Connection, meta: &crate::schema::EntityMeta, entities: &mut [T]) -> Result<()>
+where
+    T: Columns + KeyedMut + Lifecycle,
+    T::KeyType: FromSql,
+{
+    let (columns, key_column) = column_names(meta);
+    let returning = key_column.filter(|_| supports_returning());
+    let insert_sql = |with_key: bool| {
+        let columns = columns.iter().copied().filter(|&column| with_key || Some(column) != key_column).collect::<Vec<_>>();
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut sql = format!("INSERT INTO {} ({}) VALUES ({placeholders})", quote_ident(&meta.table), quote_list(&columns));
+        if let Some(key_column) = returning.filter(|_| !with_key) {
+            sql.push_str(" RETURNING ");
+            sql.push_str(&quote_ident(key_column));
+        }
+        sql
+    };
+    let (with_key_sql, without_key_sql) = (insert_sql(true), insert_sql(false));
+
+    let tx = conn.is_autocommit().then(|| conn.unchecked_transaction()).transpose()?;
+    let mut keys = Vec::new();
+    for (index, entity) in entities.iter_mut().enumerate() {
+        entity.before_insert()?;
+        let has_key = entity.key()?.is_some();
+        let params = columns
+            .iter()
+            .filter(|&&column| has_key || Some(column) != key_column)
+            .map(|column| Ok(entity.try_column_value(column)?.unwrap_or_default()))
+            .collect::<Result<Vec<Value>>>()?;
+
+        let params = rusqlite::params_from_iter(params);
+        if has_key {
+            conn.prepare_cached(&with_key_sql)?.execute(params)?;
+            continue;
+        }
+
+        let key = if returning.is_some() {
+            conn.prepare_cached(&without_key_sql)?.query_row(params, |row| row.get(0))?
+        } else {
+            conn.prepare_cached(&without_key_sql)?.execute(params)?;
+            T::KeyType::column_result(ValueRef::Integer(conn.last_insert_rowid())).map_err(|_| Error::KeyOutOfRange)?
+        };
+        keys.push((index, key));
+    }
+    if let Some(tx) = tx {
+        tx.commit()?;
+    }
+    for (index, key) in keys {
+        entities[index].key_mut()?.0 = Some(key);
+    }
+    Ok(())
+}
+
+/// Inserts the entity, or updates the row with the same key
+///
+/// Entities without a key conflict on the first UNIQUE constraint of the
+/// table instead, if any, so they update the row holding the same values.
+/// See [`upsert_on`].
+pub fn upsert<T>(conn: &rusqlite::Connection, entity: &mut T) -> Result<()>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle,
+    T::KeyType: FromSql,
+{
+    let meta = T::entity_meta();
+    let conflict = match meta.unique.first() {
+        Some(columns) if entity.key()?.is_none() => columns.iter().map(AsRef::as_ref).collect(),
+        _ => Vec::new(),
+    };
+    upsert_on(conn, entity, &conflict)
+}
+
+/// Inserts the entity, or updates the row conflicting with it on the
+/// `conflict` columns, the key column if empty
+///
+/// Runs `INSERT ... ON CONFLICT (conflict) DO UPDATE SET ... RETURNING key`,
+/// which needs SQLite 3.35, and writes back the key of the inserted or
+/// updated row. The conflict columns need a unique index. The `created_at`
+/// column is kept on update, and versioned entities fail with
+/// `Error::VersionedUpsert`, as the update would skip their version check.
+pub fn upsert_on<T>(conn: &rusqlite::Connection, entity: &mut T, conflict: &[&str]) -> Result<()>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle,
+    T::KeyType: FromSql,
+{
+    let meta = T::entity_meta();
+    let (columns, key_column) = column_names(&meta);
+    let key_column = key_column.ok_or(Error::KeyEmpty)?;
+    let conflict = if conflict.is_empty() { vec![key_column] } else { conflict.to_vec() };
+
+    if entity.versioned().is_some() {
+        return Err(Error::VersionedUpsert);
+    }
+
+    let has_key = entity.key()?.is_some();
+    if has_key {
+        entity.before_update()?;
+    } else {
+        entity.before_insert()?;
+    }
+    let columns = columns.into_iter().filter(|&column| has_key || column != key_column).collect::<Vec<_>>();
+    let mut updates = columns
+        .iter()
+        .filter(|&&column| column != key_column && column != "created_at" && !conflict.contains(&column))
+        .map(|column| format!("{0} = excluded.{0}", quote_ident(column)))
+        .collect::<Vec<_>>();
+    if updates.is_empty() {
+        // DO NOTHING wouldn't return the conflicting row
+        updates.push(format!("{0} = excluded.{0}", quote_ident(conflict[0])));
+    }
+
+    let sql = format!(
+        "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) DO UPDATE SET {} RETURNING {}",
+        quote_ident(&meta.table),
+        quote_list(&columns),
+        vec!["?"; columns.len()].join(", "),
+        quote_list(&conflict),
+        updates.join(", "),
+        quote_ident(key_column),
+    );
+    let params = columns
+        .iter()
+        .map(|column| Ok(entity.try_column_value(column)?.unwrap_or_default()))
+        .collect::<Result<Vec<Value>>>()?;
+
+    let upsert = || Ok(conn.prepare_cached(&sql)?.query_row(rusqlite::params_from_iter(params), |row| row.get(0))?);
+    let key = trace::traced("upsert", &meta.table, None, upsert, |_| 1)?;
+    entity.key_mut()?.0 = Some(key);
+    Ok(())
+}
+
+/// Turns the failure of a UNIQUE constraint of the table of `T` into
+/// `Error::Invalid`, with a `FieldErrorKind::Unique` error for each of its
+/// columns; other errors are returned as is
+///
+/// The errors are named after the columns, which are the field names unless
+/// renamed with `#[entity(column = "...")]`.
+pub fn unique_violation<T: Schema + Columns>(entity: &T, err: Error) -> Error {
+    let message = match &err {
+        Error::Sqlite(rusqlite::Error::SqliteFailure(failure, Some(message)))
+            if failure.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE => message,
+        _ => return err,
+    };
+    let Some(columns) = message.strip_prefix("UNIQUE constraint failed: ") else {
+        return err;
+    };
+
+    let table = T::table();
+    let mut errors = ValidationErrors::new();
+    for column in columns.split(", ") {
+        let field = column
+            .split_once('.')
+            .filter(|(column_table, _)| *column_table == table)
+            .and_then(|(_, column)| entity.columns().iter().find(|&&name| name == column));
+        match field {
+            Some(field) => errors.add(field, FieldErrorKind::Unique),
+            None => return err,
+        }
+    }
+    Error::Invalid(errors)
+}
+
+/// Returns whether the table of `T` has a row with the key
+pub fn exists<T>(conn: &rusqlite::Connection, key: &T::KeyType) -> Result<bool>
+where
+    T: Schema + Keyed,
+    T::KeyType: ToSql,
+{
+    let meta = T::entity_meta();
+    let (_, key_column) = column_names(&meta);
+    let key_column = quote_ident(key_column.ok_or(Error::KeyEmpty)?);
+    let sql = format!("SELECT EXISTS (SELECT 1 FROM {} WHERE {key_column} = ?)", quote_ident(&meta.table));
+    let exists = || Ok(conn.prepare_cached(&sql)?.query_row([key], |row| row.get(0))?);
+    trace::traced("exists", &meta.table, key_value(key).as_ref().map(|key| key as _), exists, |&found| usize::from(found))
+}
+
+/// Returns the number of rows of the table of `T`
+pub fn count<T: Schema>(conn: &rusqlite::Connection) -> Result<usize> {
+    count_rows::<T>(conn, None)
+}
+
+/// Returns the number of rows of the table of `T` matching `filter`
+pub fn count_where<T: Schema>(conn: &rusqlite::Connection, filter: &Filter) -> Result<usize> {
+    count_rows::<T>(conn, Some(filter))
+}
+
+/// Counts the rows of the table of `T`, only the ones matching `filter` if set
+fn count_rows<T: Schema>(conn: &rusqlite::Connection, filter: Option<&Filter>) -> Result<usize> {
+    let meta = T::entity_meta();
+    let (filter, params) = scope_clause("WHERE", filter);
+    let sql = format!("SELECT COUNT(*) FROM {}{filter}", quote_ident(&meta.table));
+    let count = || Ok(conn.prepare_cached(&sql)?.query_row(rusqlite::params_from_iter(params), |row| row.get(0))?);
+    trace::traced("count", &meta.table, None, count, |&count| count)
+}
+
+/// Starts a [`Select`] of the rows of the table of `T`
+pub fn select<T: Schema + FromRow>() -> Select<T> {
+    Select::new()
+}
+
+/// A SELECT of the rows of the table of `T`, composed from filters, an
+/// order and a page, for the queries simple enough not to write by hand
+///
+/// `#[entity(queries)]` generates a `select()` function starting one:
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # mod example {
+/// use dbent::prelude::*;
+/// use dbent::sqlite::FromRow;
+///
+/// #[derive(Entity, FromRow)]
+/// #[entity(fields, queries)]
+/// pub struct User {
+///     id: Key<Int>,
+///     name: String,
+///     age: i32,
+/// }
+///
+/// # pub fn main() -> dbent::Result<()> {
+/// let conn = rusqlite::Connection::open_in_memory()?;
+/// conn.execute_batch(
+///     "CREATE TABLE user (id INTEGER PRIMARY KEY, name TEXT, age INTEGER);
+///      INSERT INTO user VALUES (1, 'Ann', 34), (2, 'Bob', 17), (3, 'Cid', 25), (4, 'Dee', 41);",
+/// )?;
+///
+/// let adults = User::select()
+///     .filter(user::fields::AGE.gt(18))
+///     .order(user::fields::NAME.desc())
+///     .limit(2)
+///     .fetch(&conn)?;
+/// assert_eq!(adults.iter().map(|user| user.name.as_str()).collect::<Vec<_>>(), ["Dee", "Cid"]);
+/// # Ok(())
+/// # }
+/// # }
+/// # example::main().unwrap();
+/// # }
+/// ```
+pub struct Select<T> {
+    filter: Option<Filter>,
+    order: OrderBy,
+    limit: Option<usize>,
+    offset: usize,
+    entity: PhantomData<fn() -> T>,
+}
+
+impl<T> Select<T> {
+    /// Creates a Select of all the rows, in no particular order
+    pub fn new() -> Self {
+        Self { filter: None, order: OrderBy::new(), limit: None, offset: 0, entity: PhantomData }
+    }
+
+    /// Keeps the rows matching `filter`, besides the filters added before
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(match self.filter {
+            Some(previous) => previous & filter,
+            None => filter,
+        });
+        self
+    }
+
+    /// Orders the rows by `order`, after the orders added before
+    pub fn order(mut self, order: OrderBy) -> Self {
+        self.order = self.order.then(order);
+        self
+    }
+
+    /// Returns at most `limit` rows
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skips the first `offset` rows
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Returns the rows of page `page`, counting from 0, of `size` rows each
+    pub fn page(self, page: usize, size: usize) -> Self {
+        self.offset(page.saturating_mul(size)).limit(size)
+    }
+
+    /// Returns the parameters of the query, in order
+    pub fn params(&self) -> &[Value] {
+        self.filter.as_ref().map_or(&[], Filter::params)
+    }
+}
+
+impl<T: Schema + FromRow> Select<T> {
+    /// Returns the SQL of the query, with `?` placeholders for its parameters
+    pub fn sql(&self) -> Result<String> {
+        let meta = T::entity_meta();
+        let (filter, _) = scope_clause("WHERE", self.filter.as_ref());
+        let mut sql = format!("SELECT {} FROM {}{filter}{}", select_list(&meta, None)?, quote_ident(&meta.table), self.order.to_sql());
+        match (self.limit, self.offset) {
+            (None, 0) => {},
+            (Some(limit), 0) => sql += &format!(" LIMIT {limit}"),
+            (limit, offset) => sql += &format!(" LIMIT {} OFFSET {offset}", limit.map_or(-1, |limit| limit as i64)),
+        }
+        Ok(sql)
+    }
+
+    /// Runs the query, returning the rows
+    pub fn fetch(&self, conn: &rusqlite::Connection) -> Result<Vec<T>> {
+        let sql = self.sql()?;
+        let fetch = || {
+            let mut statement = conn.prepare_cached(&sql)?;
+            let rows = statement.query_map(rusqlite::params_from_iter(self.params()), T::from_row)?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        };
+        trace::traced("select", &T::table(), None, fetch, Vec::len)
+    }
+
+    /// Runs the query, returning its first row if any
+    pub fn first(self, conn: &rusqlite::Connection) -> Result<Option<T>> {
+        Ok(self.limit(1).fetch(conn)?.pop())
+    }
+
+    /// Returns the number of rows matching the filters, ignoring the order
+    /// and the page
+    pub fn count(&self, conn: &rusqlite::Connection) -> Result<usize> {
+        count_rows::<T>(conn, self.filter.as_ref())
+    }
+}
+
+impl<T> Default for Select<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for Select<T> {
+    fn clone(&self) -> Self {
+        Self { filter: self.filter.clone(), order: self.order.clone(), limit: self.limit, offset: self.offset, entity: PhantomData }
+    }
+}
+
+impl<T> fmt::Debug for Select<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Select")
+            .field("entity", &type_name::<T>())
+            .field("filter", &self.filter)
+            .field("order", &self.order)
+            .field("limit", &self.limit)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// A write a delete makes to follow the `on_delete` rules of the relations to
+/// the deleted entity
+#[derive(Clone, PartialEq, Debug)]
+pub enum DeleteEffect {
+    /// Deletes the row of `table` with the key
+    Delete {
+        /// Table name
+        table: String,
+        /// Key of the row
+        key: Value,
+    },
+    /// Sets `column` of the row of `table` with the key to NULL
+    SetNull {
+        /// Table name
+        table: String,
+        /// The column referencing the deleted entity
+        column: String,
+        /// Key of the row
+        key: Value,
+    },
+}
+
+/// Returns the writes deleting the entity `T` with the key would make,
+/// without making them, ending with the delete of the entity itself
+///
+/// The writes to the rows referencing a row come before its delete, so they
+/// can be made in order with foreign keys enforced.
+///
+/// The relations to it are looked up in the entities of `registry`, and
+/// followed by their `on_delete` rule: cascades are listed recursively, and
+/// a `"restrict"` relation held by any row fails with
+/// `Error::DeleteRestricted`. Fails with `Error::NotFound` if there is no
+/// such entity.
+pub fn delete_plan<T>(conn: &rusqlite::Connection, registry: &Registry, key: &T::KeyType) -> Result<Vec<DeleteEffect>>
+where
+    T: Schema + Keyed,
+    T::KeyType: ToSql,
+{
+    let meta = T::entity_meta();
+    let key_column = quote_ident(&meta.key().ok_or(Error::KeyEmpty)?.name);
+    let sql = format!("SELECT {key_column} FROM {} WHERE {key_column} = ?", quote_ident(&meta.table));
+    let key = match conn.prepare_cached(&sql)?.query_row([key], |row| row.get(0)) {
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(Error::NotFound),
+        key => key?,
+    };
+
+    let mut plan = Vec::new();
+    plan_delete(conn, registry, &meta.table, key, &mut plan, &mut Vec::new())?;
+    Ok(plan)
+}
+
+/// Adds the writes of the rows referencing the row of `table` with the key to
+/// the plan, followed by its delete
+///
+/// `visiting` holds the deletes being planned, which rows referencing each
+/// other in a cycle lead back to.
+fn plan_delete(
+    conn: &rusqlite::Connection,
+    registry: &Registry,
+    table: &str,
+    key: Value,
+    plan: &mut Vec<DeleteEffect>,
+    visiting: &mut Vec<DeleteEffect>,
+) -> Result<()> {
+    let effect = DeleteEffect::Delete { table: table.to_owned(), key: key.clone() };
+    if plan.contains(&effect) || visiting.contains(&effect) {
+        return Ok(());
+    }
+    visiting.push(effect);
+
+    for meta in registry.iter().map(RegisteredEntity::meta) {
+        let Some(key_column) = meta.key() else { continue };
+        for column in meta.columns.iter().filter(|column| column.references.as_deref() == Some(table)) {
+            let Some(on_delete) = column.on_delete else { continue };
+            let sql = format!(
+                "SELECT {} FROM {} WHERE {} = ?",
+                quote_ident(&key_column.name),
+                quote_ident(&meta.table),
+                quote_ident(&column.name),
+            );
+            let keys = conn
+                .prepare_cached(&sql)?
+                .query_map([&key], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<Value>>>()?;
+            match on_delete {
+                _ if keys.is_empty() => {},
+                OnDelete::Restrict => {
+                    return Err(Error::DeleteRestricted { table: table.to_owned(), referenced_by: format!("{}.{}", meta.table, column.name) });
+                },
+                OnDelete::SetNull => plan.extend(keys.into_iter().map(|key| DeleteEffect::SetNull {
+                    table: meta.table.to_string(),
+                    column: column.name.to_string(),
+                    key,
+                })),
+                OnDelete::Cascade => {
+                    for key in keys {
+                        plan_delete(conn, registry, &meta.table, key, plan, visiting)?;
+                    }
+                },
+            }
+        }
+    }
+    plan.extend(visiting.pop());
+    Ok(())
+}
+
+/// Deletes the entity `T` with the key, and makes the writes the `on_delete`
+/// rules of the relations to it call for, returning them
+///
+/// Nothing is written if the delete is restricted, as the writes are planned
+/// with [`delete_plan`] first. They are made one by one, so run it in a
+/// transaction, like [`TxContext`] does when given a registry, to undo the
+/// ones made before an error. No `Lifecycle` hook is called; [`TxContext`]
+/// calls the `before_delete` of the entity itself first.
+pub fn delete_cascading<T>(conn: &rusqlite::Connection, registry: &Registry, key: &T::KeyType) -> Result<Vec<DeleteEffect>>
+where
+    T: Schema + Keyed,
+    T::KeyType: ToSql,
+{
+    let meta = T::entity_meta();
+    let plan = delete_plan::<T>(conn, registry, key)?;
+    for effect in &plan {
+        let (DeleteEffect::Delete { table, key } | DeleteEffect::SetNull { table, key, .. }) = effect;
+        let table_meta = match registry.by_table(table) {
+            Some(entity) => entity.meta(),
+            None => &meta,
+        };
+        let key_column = quote_ident(&table_meta.key().ok_or(Error::KeyEmpty)?.name);
+        let sql = match effect {
+            DeleteEffect::Delete { .. } => format!("DELETE FROM {} WHERE {key_column} = ?", quote_ident(table)),
+            DeleteEffect::SetNull { column, .. } => {
+                format!("UPDATE {} SET {} = NULL WHERE {key_column} = ?", quote_ident(table), quote_ident(column))
+            },
+        };
+        conn.prepare_cached(&sql)?.execute([key])?;
+    }
+    Ok(plan)
+}
+
+/// Entities that can be read from a row holding their columns, in the order
+/// of their [`Schema`]
+///
+/// With the `derive` feature, `#[derive(FromRow)]` implements it by reading
+/// the columns by name. `Many` fields are left `NotFetched`, and `Entity` and
+/// `Parent` fields read their key column, None when NULL. `EntityLabel`
+/// fields need the label column of a join with `#[entity(join(label = "..."))]`,
+/// so the row must come from a query selecting it:
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use dbent::prelude::*;
+/// use dbent::sqlite::FromRow;
+///
+/// #[derive(Entity, Label)]
+/// struct User {
+///     id: Key<Int>,
+///     #[label]
+///     name: String,
+/// }
+///
+/// #[derive(Entity, FromRow)]
+/// struct Post {
+///     id: Key<Int>,
+///     title: String,
+///     #[entity(column = "author_id", join(label = "author_label"))]
+///     author: EntityLabelInt<User>,
+/// }
+///
+/// let conn = rusqlite::Connection::open_in_memory().unwrap();
+/// let post = conn.query_row(
+///     "SELECT 1 AS id, 'Hello' AS title, 2 AS author_id, 'Ann' AS author_label",
+///     [],
+///     Post::from_row,
+/// ).unwrap();
+/// assert_eq!(post.author.key().unwrap(), &Key::new(2));
+/// assert_eq!(post.author.label().unwrap(), "Ann");
+/// # }
+/// ```
+pub trait FromRow: Sized {
+    /// Reads the entity from the row
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self>;
+
+    /// Reads the entity from the columns named with `prefix`, like `line_id`
+    /// for the `id` column read as `line_`
+    ///
+    /// Used to read several entities from the row of a JOIN, as
+    /// [`query_with_many`] does. The derive supports it, while the default
+    /// only reads unprefixed columns, failing with `InvalidColumnName` otherwise.
+    fn from_row_prefixed(row: &rusqlite::Row<'_>, prefix: &str) -> rusqlite::Result<Self> {
+        if prefix.is_empty() {
+            Self::from_row(row)
+        } else {
+            Err(rusqlite::Error::InvalidColumnName(format!("{prefix}*")))
+        }
+    }
+}
+
+/// Runs a JOIN query selecting parent columns and prefixed child columns,
+/// returning the parents with their `Many` children
+///
+/// Each row holds a parent, read with `FromRow::from_row`, and one of its
+/// children, read with `FromRow::from_row_prefixed` and pushed into the field
+/// returned by `many`. Rows of the same parent are merged by key, keeping the
+/// order of their first row, so the query should be ordered by parent. Rows
+/// whose child key column is NULL, as a LEFT JOIN gives for parents without
+/// children, only add the parent, whose `Many` is then empty but fetched.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # mod example {
+/// use dbent::prelude::*;
+/// use dbent::sqlite::{self, FromRow};
+///
+/// #[derive(Entity, FromRow)]
+/// #[entity(fields)]
+/// struct Line {
+///     id: Key<Int>,
+///     product: String,
+/// }
+///
+/// #[derive(Entity, FromRow)]
+/// struct Order {
+///     id: Key<Int>,
+///     lines: Many<Line>,
+/// }
+///
+/// # pub fn main() {
+/// let conn = rusqlite::Connection::open_in_memory().unwrap();
+/// conn.execute_batch(
+///     "CREATE TABLE orders (id INTEGER PRIMARY KEY);
+///      CREATE TABLE lines (id INTEGER PRIMARY KEY, order_id INTEGER, product TEXT);
+///      INSERT INTO orders VALUES (1), (2);
+///      INSERT INTO lines VALUES (1, 1, 'Tea'), (2, 1, 'Milk');",
+/// ).unwrap();
+///
+/// let orders: Vec<Order> = sqlite::query_with_many(
+///     &conn,
+///     "SELECT o.id, l.id AS line_id, l.product AS line_product
+///      FROM orders o LEFT JOIN lines l ON l.order_id = o.id ORDER BY o.id, l.id",
+///     [],
+///     "line_",
+///     |order: &mut Order| &mut order.lines,
+/// ).unwrap();
+/// assert_eq!(orders[0].lines.len(), Some(2));
+/// assert_eq!(orders[1].lines.is_empty(), Some(true));
+/// # }
+/// # }
+/// # example::main();
+/// # }
+/// ```
+pub fn query_with_many<P, C, F>(
+    conn: &rusqlite::Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+    child_prefix: &str,
+    mut many: F,
+) -> Result<Vec<P>>
+where
+    P: Keyed + FromRow,
+    P::KeyType: Eq + Hash + Clone,
+    C: Schema + FromRow,
+    F: FnMut(&mut P) -> &mut Many<C>,
+{
+    let meta = C::entity_meta();
+    let child_key = meta.key().map(|column| format!("{child_prefix}{}", column.name)).ok_or(Error::KeyEmpty)?;
+
+    let query = || {
+        let mut statement = conn.prepare(sql)?;
+        let mut rows = statement.query(params)?;
+        let mut parents = Vec::<P>::new();
+        let mut positions = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let parent = P::from_row(row)?;
+            let key = parent.key()?.as_ref().cloned().ok_or(Error::KeyEmpty)?;
+            let position = *positions.entry(key).or_insert_with(|| {
+                parents.push(parent);
+                parents.len() - 1
+            });
+            let children = many(&mut parents[position]);
+            if children.is_not_fetched() {
+                *children = Many::Data(Vec::new());
+            }
+            if row.get_ref(child_key.as_str())? != ValueRef::Null {
+                children.data_mut()?.push(C::from_row_prefixed(row, child_prefix)?);
+            }
+        }
+        Ok(parents)
+    };
+    trace::traced("query_with_many", type_name::<P>(), None, query, Vec::len)
+}
+
+#[cfg(feature = "derive")]
+pub use dbent_derive::FromRow;
+
+/// A [`Repository`] over a transaction
+///
+/// Entities read through the context, including the ones hydrated with
+/// [`Fetch`](crate::repository::Fetch), see the snapshot of the transaction,
+/// and everything written through it is committed or rolled back together.
+/// Dropping the context without committing rolls back.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # mod example {
+/// use dbent::prelude::*;
+/// use dbent::repository::Repository;
+/// use dbent::schema::Schema;
+/// use dbent::sqlite::{FromRow, TxContext};
+///
+/// #[derive(Entity)]
+/// #[entity(fields)]
+/// pub struct User {
+///     id: Key<Int>,
+///     name: String,
+/// }
+///
+/// impl FromRow for User {
+///     fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+///         Ok(User { id: row.get(0)?, name: row.get(1)? })
+///     }
+/// }
+///
+/// # pub fn main() -> dbent::Result<()> {
+/// let mut conn = rusqlite::Connection::open_in_memory()?;
+/// conn.execute(&User::entity_meta().create_table_sql(), [])?;
+///
+/// let tx = TxContext::begin(&mut conn)?;
+/// tx.insert(&mut User { id: Key(None), name: "Ann".to_owned() })?;
+/// tx.rollback()?;
+///
+/// let tx = TxContext::begin(&mut conn)?;
+/// assert!(Repository::<User>::list(&tx)?.is_empty());
+/// # Ok(())
+/// # }
+/// # }
+/// # example::main().unwrap();
+/// # }
+/// ```
+pub struct TxContext<'tx> {
+    tx: rusqlite::Transaction<'tx>,
+    registry: Option<Registry>,
+}
+
+impl<'tx> TxContext<'tx> {
+    /// Wraps the transaction
+    pub fn new(tx: rusqlite::Transaction<'tx>) -> Self {
+        Self { tx, registry: None }
+    }
+
+    /// Makes deletes follow the `on_delete` rules of the relations held by
+    /// the entities of `registry`, with [`delete_cascading`]
+    pub fn with_registry(mut self, registry: Registry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Begins a deferred transaction on the connection
+    pub fn begin(conn: &'tx mut rusqlite::Connection) -> Result<Self> {
+        Ok(Self::new(conn.transaction()?))
+    }
+
+    /// Commits everything written through the context
+    pub fn commit(self) -> Result<()> {
+        Ok(self.tx.commit()?)
+    }
+
+    /// Rolls back everything written through the context
+    pub fn rollback(self) -> Result<()> {
+        Ok(self.tx.rollback()?)
+    }
+
+    /// Returns the wrapped transaction
+    pub fn into_inner(self) -> rusqlite::Transaction<'tx> {
+        self.tx
+    }
+
+    /// Starts a nested unit of work on a savepoint of the transaction
+    pub fn savepoint(&mut self) -> Result<SavepointContext<'_>> {
+        Ok(SavepointContext::new(self.tx.savepoint()?))
+    }
+}
+
+impl<'tx> Deref for TxContext<'tx> {
+    type Target = rusqlite::Transaction<'tx>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+impl<T> Repository<T> for TxContext<'_>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle + FromRow,
+    T::KeyType: FromSql + ToSql,
+{
+    fn insert(&self, entity: &mut T) -> Result<()> {
+        insert(&self.tx, entity)
+    }
+
+    fn get(&self, key: &T::KeyType) -> Result<T> {
+        get_row(&self.tx, key, None, None)
+    }
+
+    fn get_with(&self, key: &T::KeyType, columns: &[&str]) -> Result<T> {
+        get_row(&self.tx, key, Some(columns), None)
+    }
+
+    fn list(&self) -> Result<Vec<T>> {
+        list_rows(&self.tx, None, None)
+    }
+
+    fn list_with(&self, columns: &[&str]) -> Result<Vec<T>> {
+        list_rows(&self.tx, Some(columns), None)
+    }
+
+    fn update(&self, entity: &mut T) -> Result<()> {
+        update_row(&self.tx, entity, None)
+    }
+
+    fn upsert(&self, entity: &mut T) -> Result<()> {
+        upsert_row(&self.tx, entity)
+    }
+
+    /// With a registry, only the hooks of the entity itself are called, not
+    /// the ones of the rows deleted along with it
+    fn delete(&self, key: &T::KeyType) -> Result<()> {
+        match &self.registry {
+            Some(registry) => {
+                fetch_before_delete::<T>(&self.tx, key, None)?;
+                delete_cascading::<T>(&self.tx, registry, key).map(drop)
+            },
+            None => delete_row::<T>(&self.tx, key, None),
+        }
+    }
+
+    fn delete_many(&self, keys: &[&T::KeyType]) -> Result<usize> {
+        let Some(registry) = &self.registry else {
+            return delete_rows::<T>(&self.tx, keys, None);
+        };
+
+        for key in keys {
+            match fetch_before_delete::<T>(&self.tx, key, None) {
+                Ok(()) | Err(Error::NotFound) => {},
+                Err(err) => return Err(err),
+            }
+        }
+        let mut deleted = 0;
+        for key in keys {
+            match delete_cascading::<T>(&self.tx, registry, key) {
+                Ok(_) => deleted += 1,
+                Err(Error::NotFound) => {},
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Returns the SELECT list of the columns, with placeholders for the ones
+/// left out of `selected`, or all of them when None
+///
+/// Placeholders are NULL for nullable and relation columns, and the zero
+/// value of their type otherwise, so `FromRow` can still read them.
+fn select_list(meta: &crate::schema::EntityMeta, selected: Option<&[&str]>) -> Result<String> {
+    use crate::schema::SqlType;
+
+    let Some(selected) = selected else {
+        return Ok(quote_list(&column_names(meta).0));
+    };
+    if let Some(unknown) = selected.iter().find(|&&name| meta.column(name).is_none()) {
+        return Err(rusqlite::Error::InvalidColumnName((*unknown).to_owned()).into());
+    }
+
+    let columns = meta.columns.iter().map(|column| {
+        if column.primary_key || selected.contains(&column.name.as_ref()) {
+            return quote_ident(&column.name).into_owned();
+        }
+        let placeholder = match column.sql_type {
+            _ if column.nullable || column.references.is_some() => "NULL",
+            SqlType::Integer => "0",
+            SqlType::Real => "0.0",
+            SqlType::Text => "''",
+            SqlType::Blob => "X''",
+            SqlType::Any => "NULL",
+        };
+        format!("{placeholder} AS {}", quote_ident(&column.name))
+    });
+    Ok(columns.collect::<Vec<_>>().join(", "))
+}
+
+/// Returns the condition restricting the rows to the `scope`, after `keyword`,
+/// and its parameters
+fn scope_clause<'f>(keyword: &str, scope: Option<&'f Filter>) -> (String, &'f [Value]) {
+    match scope {
+        Some(filter) => (format!(" {keyword} ({})", filter.sql()), filter.params()),
+        None => (String::new(), &[]),
+    }
+}
+
+/// Selects the row with the given key, reading only the `selected` columns if set
+fn get_row<T>(conn: &rusqlite::Connection, key: &T::KeyType, selected: Option<&[&str]>, scope: Option<&Filter>) -> Result<T>
+where
+    T: Schema + Keyed + Lifecycle + FromRow,
+    T::KeyType: ToSql,
+{
+    let meta = T::entity_meta();
+    let (_, key_column) = column_names(&meta);
+    let (scope, scope_params) = scope_clause("AND", scope);
+    let key_column = quote_ident(key_column.ok_or(Error::KeyEmpty)?);
+    let sql = format!("SELECT {} FROM {} WHERE {key_column} = ?{scope}", select_list(&meta, selected)?, quote_ident(&meta.table));
+    let params = [key as &dyn ToSql].into_iter().chain(scope_params.iter().map(|value| value as &dyn ToSql));
+    let get = || match conn.prepare_cached(&sql)?.query_row(rusqlite::params_from_iter(params), T::from_row) {
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(Error::NotFound),
+        result => {
+            let mut entity = result?;
+            entity.after_fetch()?;
+            Ok(entity)
+        },
+    };
+    trace::traced("get", &meta.table, key_value(key).as_ref().map(|key| key as _), get, |_| 1)
+}
+
+/// Selects all the rows of the table, reading only the `selected` columns if set
+fn list_rows<T: Schema + Lifecycle + FromRow>(conn: &rusqlite::Connection, selected: Option<&[&str]>, scope: Option<&Filter>) -> Result<Vec<T>> {
+    let meta = T::entity_meta();
+    let (scope, scope_params) = scope_clause("WHERE", scope);
+    let sql = format!("SELECT {} FROM {}{scope}", select_list(&meta, selected)?, quote_ident(&meta.table));
+    let list = || {
+        let mut statement = conn.prepare_cached(&sql)?;
+        let rows = statement.query_map(rusqlite::params_from_iter(scope_params), T::from_row)?;
+        let mut entities = rows.collect::<rusqlite::Result<Vec<T>>>()?;
+        entities.iter_mut().try_for_each(Lifecycle::after_fetch)?;
+        Ok(entities)
+    };
+    trace::traced("list", &meta.table, None, list, Vec::len)
+}
+
+/// Updates the row with the key of the entity, or `Error::NotFound`
+///
+/// Versioned entities are updated with
+/// [`update_versioned`](crate::version::update_versioned), bumping their
+/// version. Their version is checked before `before_update`, so a stale
+/// entity is left untouched.
+fn update_row<T>(conn: &rusqlite::Connection, entity: &mut T, scope: Option<&Filter>) -> Result<()>
+where
+    T: Schema + Columns + Keyed + Lifecycle,
+    T::KeyType: ToSql,
+{
+    let meta = T::entity_meta();
+    let (columns, key_column) = column_names(&meta);
+    let key_column = key_column.ok_or(Error::KeyEmpty)?;
+    let version_column = match entity.versioned().map(|versioned| versioned.version_column()) {
+        Some(version_column) => {
+            check_stored_version(conn, &meta.table, key_column, version_column, entity, scope)?;
+            Some(version_column)
+        },
+        None => None,
+    };
+    entity.before_update()?;
+
+    let columns = columns
+        .into_iter()
+        .filter(|&column| column != key_column && Some(column) != version_column)
+        .collect::<Vec<_>>();
+    let values = columns
+        .iter()
+        .map(|column| Ok(entity.try_column_value(column)?.unwrap_or_default()))
+        .collect::<Result<Vec<Value>>>()?;
+    let traced_key = key_value(entity.key()?.as_ref().ok_or(Error::KeyEmpty)?);
+
+    if let Some(version_column) = version_column {
+        let key = entity.column_value(key_column).unwrap_or_default();
+        let assignments = columns.iter().zip(&values).map(|(&column, value)| (column, value as &dyn ToSql)).collect::<Vec<_>>();
+        if let Some(versioned) = entity.versioned() {
+            let update = || crate::version::update_versioned(conn, versioned, &meta.table, key_column, &key, version_column, &assignments);
+            return trace::traced("update", &meta.table, traced_key.as_ref().map(|key| key as _), update, |_| 1);
+        }
+    }
+
+    let key = entity.key()?.as_ref().ok_or(Error::KeyEmpty)?;
+    let assignments = columns.iter().map(|column| format!("{} = ?", quote_ident(column))).collect::<Vec<_>>();
+    let (scope, scope_params) = scope_clause("AND", scope);
+    let sql = format!("UPDATE {} SET {} WHERE {} = ?{scope}", quote_ident(&meta.table), assignments.join(", "), quote_ident(key_column));
+    let params = values
+        .iter()
+        .map(|value| value as &dyn ToSql)
+        .chain([key as &dyn ToSql])
+        .chain(scope_params.iter().map(|value| value as &dyn ToSql));
+
+    let update = || match conn.prepare_cached(&sql)?.execute(rusqlite::params_from_iter(params))? {
+        0 => Err(Error::NotFound),
+        _ => Ok(()),
+    };
+    trace::traced("update", &meta.table, traced_key.as_ref().map(|key| key as _), update, |_| 1)
+}
+
+/// Upserts the entity with [`upsert`], or for versioned entities, updates
+/// the row guarded by the version and inserts if missing or without a key
+fn upsert_row<T>(conn: &rusqlite::Connection, entity: &mut T) -> Result<()>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle,
+    T::KeyType: FromSql + ToSql,
+{
+    if entity.versioned().is_none() {
+        return upsert(conn, entity);
+    }
+    match entity.key()?.as_ref() {
+        Some(key) if exists::<T>(conn, key)? => update_row(conn, entity, None),
+        _ => insert(conn, entity),
+    }
+}
+
+/// Fails with `Error::StaleVersion` unless the row with the key of the
+/// versioned entity holds its version, or with `Error::NotFound` if there is
+/// no such row in the `scope`
+fn check_stored_version<T>(
+    conn: &rusqlite::Connection,
+    table: &str,
+    key_column: &str,
+    version_column: &str,
+    entity: &mut T,
+    scope: Option<&Filter>,
+) -> Result<()>
+where
+    T: Columns + Lifecycle,
+{
+    let key = entity.column_value(key_column).unwrap_or_default();
+    let (scope, scope_params) = scope_clause("AND", scope);
+    let sql = format!("SELECT {} FROM {} WHERE {} = ?{scope}", quote_ident(version_column), quote_ident(table), quote_ident(key_column));
+    let params = [&key as &dyn ToSql].into_iter().chain(scope_params.iter().map(|value| value as &dyn ToSql));
+    let found = match conn.prepare_cached(&sql)?.query_row(rusqlite::params_from_iter(params), |row| row.get(0)) {
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err(Error::NotFound),
+        result => result?,
+    };
+    match entity.versioned() {
+        Some(versioned) => versioned.check_version(found),
+        None => Ok(()),
+    }
+}
+
+/// Reads the entity with the key to call `before_delete`, if it has
+/// `Lifecycle::FETCH_BEFORE_DELETE`
+fn fetch_before_delete<T>(conn: &rusqlite::Connection, key: &T::KeyType, scope: Option<&Filter>) -> Result<()>
+where
+    T: Schema + Keyed + Lifecycle + FromRow,
+    T::KeyType: ToSql,
+{
+    if T::FETCH_BEFORE_DELETE {
+        get_row::<T>(conn, key, None, scope)?.before_delete()?;
+    }
+    Ok(())
+}
+
+/// Deletes the row with the given key, or `Error::NotFound`
+///
+/// Entities with `Lifecycle::FETCH_BEFORE_DELETE` are read first to call
+/// `before_delete`.
+fn delete_row<T>(conn: &rusqlite::Connection, key: &T::KeyType, scope: Option<&Filter>) -> Result<()>
+where
+    T: Schema + Keyed + Lifecycle + FromRow,
+    T::KeyType: ToSql,
+{
+    fetch_before_delete::<T>(conn, key, scope)?;
+
+    let meta = T::entity_meta();
+    let (_, key_column) = column_names(&meta);
+    let (scope, scope_params) = scope_clause("AND", scope);
+    let sql = format!("DELETE FROM {} WHERE {} = ?{scope}", quote_ident(&meta.table), quote_ident(key_column.ok_or(Error::KeyEmpty)?));
+    let params = [key as &dyn ToSql].into_iter().chain(scope_params.iter().map(|value| value as &dyn ToSql));
+    let delete = || match conn.prepare_cached(&sql)?.execute(rusqlite::params_from_iter(params))? {
+        0 => Err(Error::NotFound),
+        _ => Ok(()),
+    };
+    trace::traced("delete", &meta.table, key_value(key).as_ref().map(|key| key as _), delete, |_| 1)
+}
+
+/// Deletes the rows with the given keys, returning how many were deleted
+///
+/// Entities with `Lifecycle::FETCH_BEFORE_DELETE` are read first to call
+/// `before_delete`.
+fn delete_rows<T>(conn: &rusqlite::Connection, keys: &[&T::KeyType], scope: Option<&Filter>) -> Result<usize>
+where
+    T: Schema + Keyed + Lifecycle + FromRow,
+    T::KeyType: ToSql,
+{
+    if keys.is_empty() {
+        return Ok(0);
+    }
+
+    for key in keys {
+        match fetch_before_delete::<T>(conn, key, scope) {
+            Ok(()) | Err(Error::NotFound) => {},
+            Err(err) => return Err(err),
+        }
+    }
+
+    let meta = T::entity_meta();
+    let (_, key_column) = column_names(&meta);
+    let placeholders = vec!["?"; keys.len()].join(", ");
+    let (scope, scope_params) = scope_clause("AND", scope);
+    let sql = format!("DELETE FROM {} WHERE {} IN ({placeholders}){scope}", quote_ident(&meta.table), quote_ident(key_column.ok_or(Error::KeyEmpty)?));
+    let params = keys.iter().map(|key| key as &dyn ToSql).chain(scope_params.iter().map(|value| value as &dyn ToSql));
+    let delete = || Ok(conn.prepare(&sql)?.execute(rusqlite::params_from_iter(params))?);
+    trace::traced("delete_many", &meta.table, None, delete, |&deleted| deleted)
+}
+
+/// A [`Repository`] over a savepoint, nested in a [`TxContext`] or another
+/// savepoint
+///
+/// Committing releases the savepoint, keeping what was written through it as
+/// part of the enclosing unit of work, which can still roll it back. Rolling
+/// back, or dropping the context without committing, only undoes what was
+/// written since the savepoint, so an edit dialog can be canceled without
+/// losing the rest of the session.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # mod example {
+/// use dbent::prelude::*;
+/// use dbent::schema::Schema;
+/// use dbent::sqlite::{FromRow, TxContext};
+///
+/// #[derive(Entity, FromRow)]
+/// #[entity(fields)]
+/// pub struct User {
+///     id: Key<Int>,
+///     name: String,
+/// }
+///
+/// # pub fn main() -> dbent::Result<()> {
+/// let mut conn = rusqlite::Connection::open_in_memory()?;
+/// conn.execute(&User::entity_meta().create_table_sql(), [])?;
+///
+/// let mut tx = TxContext::begin(&mut conn)?;
+/// tx.insert(&mut User { id: Key(None), name: "Ann".to_owned() })?;
+///
+/// let dialog = tx.savepoint()?;
+/// dialog.insert(&mut User { id: Key(None), name: "Bob".to_owned() })?;
+/// dialog.rollback()?;
+///
+/// assert_eq!(Repository::<User>::list(&tx)?.len(), 1);
+/// tx.commit()?;
+/// # Ok(())
+/// # }
+/// # }
+/// # example::main().unwrap();
+/// # }
+/// ```
+pub struct SavepointContext<'sp> {
+    savepoint: rusqlite::Savepoint<'sp>,
+}
+
+impl<'sp> SavepointContext<'sp> {
+    /// Wraps the savepoint
+    pub fn new(savepoint: rusqlite::Savepoint<'sp>) -> Self {
+        Self { savepoint }
+    }
+
+    /// Starts a unit of work nested in this one
+    pub fn savepoint(&mut self) -> Result<SavepointContext<'_>> {
+        Ok(SavepointContext::new(self.savepoint.savepoint()?))
+    }
+
+    /// Releases the savepoint, merging its writes into the enclosing unit of work
+    pub fn commit(self) -> Result<()> {
+        Ok(self.savepoint.commit()?)
+    }
+
+    /// Undoes everything written since the savepoint, and releases it
+    pub fn rollback(mut self) -> Result<()> {
+        self.savepoint.rollback()?;
+        Ok(self.savepoint.commit()?)
+    }
+
+    /// Returns the wrapped savepoint
+    pub fn into_inner(self) -> rusqlite::Savepoint<'sp> {
+        self.savepoint
+    }
+}
+
+impl<'sp> Deref for SavepointContext<'sp> {
+    type Target = rusqlite::Savepoint<'sp>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.savepoint
+    }
+}
+
+impl<T> Repository<T> for SavepointContext<'_>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle + FromRow,
+    T::KeyType: FromSql + ToSql,
+{
+    fn insert(&self, entity: &mut T) -> Result<()> {
+        insert(&self.savepoint, entity)
+    }
+
+    fn get(&self, key: &T::KeyType) -> Result<T> {
+        get_row(&self.savepoint, key, None, None)
+    }
+
+    fn get_with(&self, key: &T::KeyType, columns: &[&str]) -> Result<T> {
+        get_row(&self.savepoint, key, Some(columns), None)
+    }
+
+    fn list(&self) -> Result<Vec<T>> {
+        list_rows(&self.savepoint, None, None)
+    }
+
+    fn list_with(&self, columns: &[&str]) -> Result<Vec<T>> {
+        list_rows(&self.savepoint, Some(columns), None)
+    }
+
+    fn update(&self, entity: &mut T) -> Result<()> {
+        update_row(&self.savepoint, entity, None)
+    }
+
+    fn upsert(&self, entity: &mut T) -> Result<()> {
+        upsert_row(&self.savepoint, entity)
+    }
+
+    fn delete(&self, key: &T::KeyType) -> Result<()> {
+        delete_row::<T>(&self.savepoint, key, None)
+    }
+
+    fn delete_many(&self, keys: &[&T::KeyType]) -> Result<usize> {
+        delete_rows::<T>(&self.savepoint, keys, None)
+    }
+}
+
+/// A source of connections, like a connection pool
+///
+/// [`PooledContext`] checks out a connection for each operation and gives it
+/// back when done, so any pool can back it by implementing this trait for its
+/// handle: an `r2d2::Pool<SqliteConnectionManager>` returns its
+/// `PooledConnection` from `get`, and a deadpool `Pool` its `Object`. A
+/// `Mutex<Connection>` shares a single connection between threads.
+pub trait ConnectionPool {
+    /// The checked out connection, given back to the pool when dropped
+    type Connection<'a>: Deref<Target = rusqlite::Connection>
+    where
+        Self: 'a;
+
+    /// Checks out a connection, waiting for one to be available
+    fn connection(&self) -> Result<Self::Connection<'_>>;
+}
+
+impl ConnectionPool for Mutex<rusqlite::Connection> {
+    type Connection<'a> = MutexGuard<'a, rusqlite::Connection>;
+
+    fn connection(&self) -> Result<Self::Connection<'_>> {
+        // A panic while holding the lock doesn't leave the connection unusable
+        Ok(self.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+impl<P: ConnectionPool + ?Sized> ConnectionPool for Arc<P> {
+    type Connection<'a> = P::Connection<'a> where Self: 'a;
+
+    fn connection(&self) -> Result<Self::Connection<'_>> {
+        (**self).connection()
+    }
+}
+
+/// A [`Repository`] over a pool of connections
+///
+/// Each operation, including the hydration with
+/// [`Fetch`](crate::repository::Fetch), checks out its own connection, so the
+/// context can be shared between the threads of a server instead of passing
+/// connections around. Operations autocommit; use a [`TxContext`] on a
+/// checked out connection to write atomically.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # mod example {
+/// use std::sync::{Arc, Mutex};
+///
+/// use dbent::prelude::*;
+/// use dbent::schema::Schema;
+/// use dbent::sqlite::{FromRow, PooledContext};
+///
+/// #[derive(Entity, FromRow)]
+/// #[entity(fields)]
+/// pub struct User {
+///     id: Key<Int>,
+///     name: String,
+/// }
+///
+/// # pub fn main() -> dbent::Result<()> {
+/// let conn = rusqlite::Connection::open_in_memory()?;
+/// conn.execute(&User::entity_meta().create_table_sql(), [])?;
+///
+/// let users = PooledContext::new(Arc::new(Mutex::new(conn)));
+/// let worker = users.clone();
+/// std::thread::spawn(move || worker.insert(&mut User { id: Key(None), name: "Ann".to_owned() }))
+///     .join()
+///     .unwrap()?;
+///
+/// let mut user = EntityInt::<User>::Key(Key::new(1));
+/// assert_eq!(users.fetch(&mut user)?.name, "Ann");
+/// # Ok(())
+/// # }
+/// # }
+/// # example::main().unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PooledContext<P> {
+    pool: P,
+}
+
+impl<P> PooledContext<P> {
+    /// Wraps the pool
+    pub fn new(pool: P) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the wrapped pool
+    pub fn pool(&self) -> &P {
+        &self.pool
+    }
+
+    /// Returns the wrapped pool, consuming the context
+    pub fn into_inner(self) -> P {
+        self.pool
+    }
+}
+
+impl<P: ConnectionPool> PooledContext<P> {
+    /// Checks out a connection from the pool
+    pub fn connection(&self) -> Result<P::Connection<'_>> {
+        self.pool.connection()
+    }
+}
+
+impl<T, P> Repository<T> for PooledContext<P>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle + FromRow,
+    T::KeyType: FromSql + ToSql,
+    P: ConnectionPool,
+{
+    fn insert(&self, entity: &mut T) -> Result<()> {
+        insert(&*self.connection()?, entity)
+    }
+
+    fn get(&self, key: &T::KeyType) -> Result<T> {
+        get_row(&*self.connection()?, key, None, None)
+    }
+
+    fn get_with(&self, key: &T::KeyType, columns: &[&str]) -> Result<T> {
+        get_row(&*self.connection()?, key, Some(columns), None)
+    }
+
+    fn list(&self) -> Result<Vec<T>> {
+        list_rows(&*self.connection()?, None, None)
+    }
+
+    fn list_with(&self, columns: &[&str]) -> Result<Vec<T>> {
+        list_rows(&*self.connection()?, Some(columns), None)
+    }
+
+    fn update(&self, entity: &mut T) -> Result<()> {
+        update_row(&*self.connection()?, entity, None)
+    }
+
+    fn upsert(&self, entity: &mut T) -> Result<()> {
+        upsert_row(&*self.connection()?, entity)
+    }
+
+    fn delete(&self, key: &T::KeyType) -> Result<()> {
+        delete_row::<T>(&*self.connection()?, key, None)
+    }
+
+    fn delete_many(&self, keys: &[&T::KeyType]) -> Result<usize> {
+        delete_rows::<T>(&*self.connection()?, keys, None)
+    }
+}
+
+/// A [`Repository`] over a connection, restricted by a [`ScopePolicy`]
+///
+/// Every statement is restricted to the rows matching the filter of the
+/// policy, so rows out of the scope behave as if they didn't exist: getting,
+/// updating or deleting them fails with `Error::NotFound`. Inserted and
+/// updated entities are checked by the policy first. It borrows any
+/// connection, so it can wrap a [`TxContext`], a [`SavepointContext`] or a
+/// connection checked out of a [`ConnectionPool`].
+///
+/// Upserting an entity with a key updates its row in the scope, or inserts
+/// it otherwise, which fails if the key is taken out of the scope.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # mod example {
+/// use dbent::prelude::*;
+/// use dbent::schema::Schema;
+/// use dbent::sql::{Filter, ScopePolicy};
+/// use dbent::sqlite::{FromRow, ScopedRepository};
+///
+/// #[derive(Entity, FromRow)]
+/// #[entity(fields)]
+/// pub struct Invoice {
+///     id: Key<Int>,
+///     tenant: i64,
+/// }
+///
+/// pub struct Tenant(i64);
+///
+/// impl ScopePolicy<Invoice> for Tenant {
+///     fn filter(&self) -> Option<Filter> {
+///         Some(invoice::fields::TENANT.eq(self.0))
+///     }
+///
+///     fn check_write(&self, invoice: &Invoice) -> dbent::Result<()> {
+///         match invoice.tenant == self.0 {
+///             true => Ok(()),
+///             false => Err(dbent::Error::ScopeMismatch),
+///         }
+///     }
+/// }
+///
+/// # pub fn main() -> dbent::Result<()> {
+/// let conn = rusqlite::Connection::open_in_memory()?;
+/// conn.execute(&Invoice::entity_meta().create_table_sql(), [])?;
+///
+/// let acme = ScopedRepository::new(&conn, Tenant(1));
+/// acme.insert(&mut Invoice { id: Key(None), tenant: 1 })?;
+/// assert!(acme.insert(&mut Invoice { id: Key(None), tenant: 2 }).is_err());
+///
+/// let initech = ScopedRepository::new(&conn, Tenant(2));
+/// assert!(matches!(initech.get(&1), Err(dbent::Error::NotFound)));
+/// assert!(initech.list()?.is_empty());
+/// # Ok(())
+/// # }
+/// # }
+/// # example::main().unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ScopedRepository<'c, P> {
+    conn: &'c rusqlite::Connection,
+    policy: P,
+}
+
+impl<'c, P> ScopedRepository<'c, P> {
+    /// Restricts the connection with the policy
+    pub fn new(conn: &'c rusqlite::Connection, policy: P) -> Self {
+        Self { conn, policy }
+    }
+
+    /// Returns the policy
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+}
+
+impl<T, P> Repository<T> for ScopedRepository<'_, P>
+where
+    T: Schema + Columns + KeyedMut + Lifecycle + FromRow,
+    T::KeyType: FromSql + ToSql,
+    P: ScopePolicy<T>,
+{
+    fn insert(&self, entity: &mut T) -> Result<()> {
+        self.policy.check_write(entity)?;
+        insert(self.conn, entity)
+    }
+
+    fn get(&self, key: &T::KeyType) -> Result<T> {
+        get_row(self.conn, key, None, self.policy.filter().as_ref())
+    }
+
+    fn get_with(&self, key: &T::KeyType, columns: &[&str]) -> Result<T> {
+        get_row(self.conn, key, Some(columns), self.policy.filter().as_ref())
+    }
+
+    fn list(&self) -> Result<Vec<T>> {
+        list_rows(self.conn, None, self.policy.filter().as_ref())
+    }
+
+    fn list_with(&self, columns: &[&str]) -> Result<Vec<T>> {
+        list_rows(self.conn, Some(columns), self.policy.filter().as_ref())
+    }
+
+    fn update(&self, entity: &mut T) -> Result<()> {
+        self.policy.check_write(entity)?;
+        update_row(self.conn, entity, self.policy.filter().as_ref())
+    }
+
+    fn upsert(&self, entity: &mut T) -> Result<()> {
+        self.policy.check_write(entity)?;
+        if entity.key()?.is_some() {
+            match update_row(self.conn, entity, self.policy.filter().as_ref()) {
+                Err(Error::NotFound) => (),
+                result => return result,
+            }
+        }
+        insert(self.conn, entity)
+    }
+
+    fn delete(&self, key: &T::KeyType) -> Result<()> {
+        delete_row::<T>(self.conn, key, self.policy.filter().as_ref())
+    }
+
+    fn delete_many(&self, keys: &[&T::KeyType]) -> Result<usize> {
+        delete_rows::<T>(self.conn, keys, self.policy.filter().as_ref())
+    }
+}
+
+/// A full-text index over the labels of entities, for type-ahead search
+///
+/// It keeps an FTS5 table with the key and label of the rows of the entity
+/// types registered with [`register`](Self::register), which installs
+/// triggers on their tables, so the index follows every insert, update and
+/// delete, however they are made. [`search_tags`](Self::search_tags) returns
+/// the [`Tag`]s of the labels matching a query as typed.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// # mod example {
+/// use dbent::prelude::*;
+/// use dbent::schema::Schema;
+/// use dbent::sqlite::LabelIndex;
+///
+/// #[derive(Entity)]
+/// #[entity(fields)]
+/// pub struct City {
+///     id: Key<Int>,
+///     name: String,
+/// }
+///
+/// # pub fn main() -> dbent::Result<()> {
+/// let conn = rusqlite::Connection::open_in_memory()?;
+/// conn.execute(&City::entity_meta().create_table_sql(), [])?;
+///
+/// let index = LabelIndex::default();
+/// index.create(&conn)?;
+/// index.register::<City>(&conn, "name")?;
+///
+/// dbent::sqlite::insert(&conn, &mut City { id: Key(None), name: "São Paulo".to_owned() })?;
+/// dbent::sqlite::insert(&conn, &mut City { id: Key(None), name: "Porto Alegre".to_owned() })?;
+/// assert_eq!(index.search_tags(&conn, "port")?, [Tag::new("2", "Porto Alegre")]);
+/// # Ok(())
+/// # }
+/// # }
+/// # example::main().unwrap();
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LabelIndex {
+    table: Cow<'static, str>,
+}
+
+impl Default for LabelIndex {
+    fn default() -> Self {
+        Self::new("label_index")
+    }
+}
+
+impl LabelIndex {
+    /// Uses the FTS5 table named `table`, `label_index` by default
+    pub fn new(table: impl Into<Cow<'static, str>>) -> Self {
+        Self { table: table.into() }
+    }
+
+    /// Returns the name of the FTS5 table
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Creates the FTS5 table if it doesn't exist
+    ///
+    /// Fails if the linked SQLite is built without FTS5.
+    pub fn create(&self, conn: &rusqlite::Connection) -> Result<()> {
+        let sql = format!("CREATE VIRTUAL TABLE IF NOT EXISTS {} USING fts5(label, entity UNINDEXED, key UNINDEXED)", quote_ident(&self.table));
+        conn.execute(&sql, [])?;
+        Ok(())
+    }
+
+    /// Indexes the `label_column` of the rows of `T`, the ones already in its
+    /// table and, through triggers, the ones written from now on
+    ///
+    /// Registering a type again replaces its triggers and reindexes it, inside
+    /// a transaction, or inside the current one if there is one, so a failure
+    /// leaves the index as it was. Fails with
+    /// `rusqlite::Error::InvalidColumnName` if the table of `T` has no such
+    /// column.
+    pub fn register<T: Schema>(&self, conn: &rusqlite::Connection, label_column: &str) -> Result<()> {
+        let meta = T::entity_meta();
+        if meta.column(label_column).is_none() {
+            return Err(rusqlite::Error::InvalidColumnName(label_column.to_owned()).into());
+        }
+        let (_, key_column) = column_names(&meta);
+        let (key_column, label_column) = (quote_ident(key_column.ok_or(Error::KeyEmpty)?), quote_ident(label_column));
+
+        let (index, table) = (quote_ident(&self.table), quote_ident(&meta.table));
+        let trigger = |event| quote_ident(&format!("{}_{}_{event}", self.table, meta.table)).into_owned();
+        let (on_insert, on_update, on_delete) = (trigger("insert"), trigger("update"), trigger("delete"));
+        let entity = format!("'{}'", meta.table.replace('\'', "''"));
+        let insert = format!("INSERT INTO {index} (label, entity, key) VALUES (new.{label_column}, {entity}, CAST(new.{key_column} AS TEXT));");
+        let delete = format!("DELETE FROM {index} WHERE entity = {entity} AND key = CAST(old.{key_column} AS TEXT);");
+        let sql = format!(
+            "DROP TRIGGER IF EXISTS {on_insert};
+             DROP TRIGGER IF EXISTS {on_update};
+             DROP TRIGGER IF EXISTS {on_delete};
+             DELETE FROM {index} WHERE entity = {entity};
+             INSERT INTO {index} (label, entity, key) SELECT {label_column}, {entity}, CAST({key_column} AS TEXT) FROM {table};
+             CREATE TRIGGER {on_insert} AFTER INSERT ON {table} BEGIN {insert} END;
+             CREATE TRIGGER {on_update} AFTER UPDATE OF {key_column}, {label_column} ON {table} BEGIN {delete} {insert} END;
+             CREATE TRIGGER {on_delete} AFTER DELETE ON {table} BEGIN {delete} END;"
+        );
+        let tx = conn.is_autocommit().then(|| conn.unchecked_transaction()).transpose()?;
+        conn.execute_batch(&sql)?;
+        if let Some(tx) = tx {
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the Tags of the labels matching `query`, best matches first
+    ///
+    /// Every word of the query has to start a word of the label, so
+    /// `"port ale"` finds "Porto Alegre". The query is taken as typed, so
+    /// FTS5 operators and quotes in it are searched as text.
+    pub fn search_tags(&self, conn: &rusqlite::Connection, query: &str) -> Result<Vec<Tag>> {
+        let query = query
+            .split_whitespace()
+            .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let sql = format!("SELECT key, label FROM {0} WHERE {0} MATCH ? ORDER BY rank", quote_ident(&self.table));
+        let search = || {
+            let mut statement = conn.prepare_cached(&sql)?;
+            let rows = statement.query_map([query], |row| Ok(Tag::new(row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        };
+        trace::traced("search_tags", &self.table, None, search, Vec::len)
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the code generated by `#[derive(FromRow)]` and `#[derive(DbEnum)]`
+
+    use std::borrow::Cow;
+
+    pub use rusqlite::{Connection, Result, Row};
+    pub use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+    use rusqlite::types::FromSqlError;
+
+    use crate::poly::{PolyEntity, PolyTargets};
+    use crate::sql::{DbEnum, Value};
+    use crate::{Entity, Key, Parent};
+
+    /// Returns the column name with the prefix of `FromRow::from_row_prefixed`
+    pub fn prefixed(prefix: &str, name: &'static str) -> Cow<'static, str> {
+        if prefix.is_empty() {
+            Cow::Borrowed(name)
+        } else {
+            Cow::Owned(format!("{prefix}{name}"))
+        }
+    }
+
+    /// Reads the `#[entity(encrypt)]` column, decrypting it with the installed cipher
+    pub fn decrypted<T: FromSql>(row: &Row<'_>, column: &str) -> Result<T> {
+        let index = row.as_ref().column_index(column)?;
+        let stored = row.get_ref(index)?;
+        let failure = |err: crate::Error| rusqlite::Error::FromSqlConversionFailure(index, stored.data_type(), Box::new(err));
+
+        let value = match stored {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(value) => Value::Integer(value),
+            ValueRef::Real(value) => Value::Real(value),
+            ValueRef::Text(_) => Value::Text(stored.as_str()?.to_owned()),
+            ValueRef::Blob(value) => Value::Blob(value.to_vec()),
+        };
+        let value = crate::cipher::decrypt(column, value).map_err(failure)?;
+        let value = match &value {
+            Value::Null => ValueRef::Null,
+            Value::Integer(value) => ValueRef::Integer(*value),
+            Value::Unsigned(value) => ValueRef::Integer(crate::sql::unsigned_to_sql(*value)?),
+            Value::Real(value) => ValueRef::Real(*value),
+            Value::Text(value) => ValueRef::Text(value.as_bytes()),
+            Value::Blob(value) => ValueRef::Blob(value),
+        };
+        T::column_result(value).map_err(|err| rusqlite::Error::FromSqlConversionFailure(index, value.data_type(), Box::new(err)))
+    }
+
+    pub trait FromKeyColumn {
+        type KeyType: FromSql;
+
+        fn from_key_column(key: Key<Self::KeyType>) -> Self;
+    }
+
+    impl<K: FromSql, T> FromKeyColumn for Entity<K, T> {
+        type KeyType = K;
+
+        fn from_key_column(key: Key<K>) -> Self {
+            Entity::from_key_nullable(key)
+        }
+    }
+
+    impl<K: FromSql, T> FromKeyColumn for Parent<K, T> {
+        type KeyType = K;
+
+        fn from_key_column(key: Key<K>) -> Self {
+            Parent(Entity::from_key_nullable(key))
+        }
+    }
+
+    /// Reads a `PolyEntity` from its key and tag columns, failing for tags of
+    /// no entity type of the PolyEntity
+    pub fn poly_entity<T>(row: &Row<'_>, column: &str, tag_column: &str) -> Result<PolyEntity<T>>
+    where
+        T: PolyTargets,
+        T::KeyType: FromSql,
+    {
+        let index = row.as_ref().column_index(tag_column)?;
+        let tag = row.get::<_, Option<String>>(index)?;
+        PolyEntity::from_columns(row.get(column)?, tag)
+            .map_err(|err| rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(err)))
+    }
+
+    /// Binds the value of the variant
+    pub fn db_enum_to_sql<E: DbEnum>(variant: &E) -> Result<ToSqlOutput<'static>> {
+        Ok(ToSqlOutput::Owned(match variant.db_value() {
+            Value::Null => rusqlite::types::Value::Null,
+            Value::Integer(value) => rusqlite::types::Value::Integer(value),
+            Value::Unsigned(value) => rusqlite::types::Value::Integer(crate::sql::unsigned_to_sql(value)?),
+            Value::Real(value) => rusqlite::types::Value::Real(value),
+            Value::Text(value) => rusqlite::types::Value::Text(value),
+            Value::Blob(value) => rusqlite::types::Value::Blob(value),
+        }))
+    }
+
+    /// Reads the variant stored in a column, failing for values of no variant
+    pub fn db_enum_column_result<E: DbEnum>(stored: ValueRef<'_>) -> FromSqlResult<E> {
+        let value = match stored {
+            ValueRef::Integer(value) => Value::Integer(value),
+            ValueRef::Text(_) => Value::Text(stored.as_str()?.to_owned()),
+            _ => return Err(FromSqlError::InvalidType),
+        };
+        E::try_from_db_value(value).map_err(|err| FromSqlError::Other(Box::new(err)))
+    }
+}
@@ -0,0 +1,166 @@
+//! Hashes of keys that are the same everywhere
+//!
+//! `std::hash` gives no guarantee that a value hashes the same across
+//! processes, platforms or Rust versions, and `HashMap` seeds it randomly,
+//! so it can't route entities to cache shards or consistent-hash rings
+//! shared between instances. [`Key::stable_hash`](crate::Key::stable_hash)
+//! can: it is the 64-bit FNV-1a hash of a canonical encoding of the key,
+//! finished with the SplitMix64 mixer so its low bits spread well for
+//! `hash % shards`. The algorithm and the encoding are part of the API and
+//! won't change between versions:
+//!
+//! - integers are written as 8 little-endian bytes of their value widened to
+//!   64 bits, so `7u32` and `7i64` hash the same
+//! - strings as their UTF-8 bytes, `CiString`s case folded
+//! - binary keys and UUIDs as their bytes
+//! - `LinkKey`s as their left key, a `0xff` byte and their right key
+//! - unset Keys as nothing
+//!
+//! ```
+//! use dbent::prelude::*;
+//!
+//! assert_eq!(Key::new(42u32).stable_hash(), Key::new(42i64).stable_hash());
+//! assert_eq!(Key::new("user".to_owned()).stable_hash(), 0xff9a_e3c5_5ae5_bee1);
+//! let shard = Key::new(42).stable_hash() % 16;
+//! # assert!(shard < 16);
+//! ```
+
+use std::borrow::Cow;
+
+/// Key values with a canonical encoding for [`StableHasher`]
+pub trait StableHash {
+    /// Writes the canonical encoding of the value
+    fn stable_hash(&self, hasher: &mut StableHasher);
+}
+
+/// The 64-bit FNV-1a hasher behind `Key::stable_hash`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    /// Creates a hasher with nothing written
+    pub fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    /// Writes the bytes
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Writes an integer as 8 little-endian bytes
+    pub fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    /// Returns the hash of what was written, mixed with the SplitMix64 finalizer
+    pub fn finish(&self) -> u64 {
+        let mut hash = self.0;
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^ (hash >> 31)
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+macro_rules! impl_stable_hash_int {
+    ($($ty:ty),*) => {
+        $(
+            impl StableHash for $ty {
+                fn stable_hash(&self, hasher: &mut StableHasher) {
+                    hasher.write_u64(*self as u64);
+                }
+            }
+        )*
+    };
+}
+
+impl_stable_hash_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl StableHash for str {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write(self.as_bytes());
+    }
+}
+
+impl StableHash for String {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_str().stable_hash(hasher);
+    }
+}
+
+impl StableHash for Cow<'_, str> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (**self).stable_hash(hasher);
+    }
+}
+
+impl StableHash for [u8] {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write(self);
+    }
+}
+
+impl StableHash for Vec<u8> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write(self);
+    }
+}
+
+impl<const N: usize> StableHash for [u8; N] {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write(self);
+    }
+}
+
+impl<T: StableHash + ?Sized> StableHash for &T {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (**self).stable_hash(hasher);
+    }
+}
+
+impl StableHash for crate::CiString {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        let mut buffer = [0; 4];
+        for c in self.folded() {
+            hasher.write(c.encode_utf8(&mut buffer).as_bytes());
+        }
+    }
+}
+
+impl<const N: usize> StableHash for crate::fixed_str::FixedStr<N> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_str().stable_hash(hasher);
+    }
+}
+
+impl<K: StableHash> StableHash for crate::Sensitive<K> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.expose().stable_hash(hasher);
+    }
+}
+
+impl<A: StableHash, B: StableHash> StableHash for crate::LinkKey<A, B> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.left.stable_hash(hasher);
+        hasher.write(&[0xff]);
+        self.right.stable_hash(hasher);
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl StableHash for uuid::Uuid {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write(self.as_bytes());
+    }
+}
@@ -0,0 +1,88 @@
+//! Incremental hydration of `Many` from async streams
+//!
+//! Async backends return large result sets as streams of rows.
+//! [`Many::from_stream`] wraps one in a [`ManyStream`], which hands out the
+//! entities as they arrive and keeps them, so they can be committed to a
+//! `Many::Data` once done, or earlier with what arrived so far.
+//!
+//! The stream must be `Unpin`; pin other streams with `Box::pin` first.
+
+use core::future::poll_fn;
+use core::pin::Pin;
+
+use futures_core::Stream;
+
+use crate::Many;
+
+impl<T> Many<T> {
+    /// Wraps a stream of entities for incremental hydration
+    pub fn from_stream<S, E>(stream: S) -> ManyStream<T, S>
+    where
+        S: Stream<Item = Result<T, E>> + Unpin,
+    {
+        ManyStream { stream, data: Vec::new(), done: false }
+    }
+}
+
+/// A stream of entities being hydrated into a `Many`
+///
+/// Created with [`Many::from_stream`].
+pub struct ManyStream<T, S> {
+    stream: S,
+    data: Vec<T>,
+    done: bool,
+}
+
+impl<T, S, E> ManyStream<T, S>
+where
+    S: Stream<Item = Result<T, E>> + Unpin,
+{
+    /// Waits for the next entity, returning it after keeping it
+    ///
+    /// Returns `None` once the stream ends. Errors are returned as they come
+    /// and don't end the stream.
+    pub async fn next(&mut self) -> Option<Result<&T, E>> {
+        if self.done {
+            return None;
+        }
+
+        match poll_fn(|cx| Pin::new(&mut self.stream).poll_next(cx)).await {
+            Some(Ok(entity)) => {
+                self.data.push(entity);
+                self.data.last().map(Ok)
+            },
+            Some(Err(err)) => Some(Err(err)),
+            None => {
+                self.done = true;
+                None
+            },
+        }
+    }
+
+    /// Consumes the rest of the stream, returning all the entities
+    ///
+    /// Stops at the first error.
+    pub async fn collect(mut self) -> Result<Many<T>, E> {
+        while let Some(result) = self.next().await {
+            result?;
+        }
+        Ok(Many::Data(self.data))
+    }
+}
+
+impl<T, S> ManyStream<T, S> {
+    /// Returns the entities received so far
+    pub fn received(&self) -> &[T] {
+        &self.data
+    }
+
+    /// Returns true if the stream has ended
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Returns the entities received so far, dropping the rest of the stream
+    pub fn into_many(self) -> Many<T> {
+        Many::Data(self.data)
+    }
+}
@@ -0,0 +1,256 @@
+//! Deterministic test fixtures, with the `testing` feature
+//!
+//! [`seeded`] builds an entity from a seed, with the same keys, labels and
+//! nested relations on every machine and run, so snapshot tests don't need
+//! fixture files. Entities derived with `#[entity(seeded)]` implement
+//! [`Seeded`], building each field in turn from a [`SeedRng`]:
+//!
+//! - keys are set, and numbers are small and positive
+//! - strings are two words, like `"amber falcon"`
+//! - `Entity`, `EntityLabel` and `Many` fields hold data down to the depth
+//!   given to [`seeded_with_depth`], [`DEFAULT_DEPTH`] for [`seeded`], and
+//!   keys or `NotFetched` below it
+//! - `Parent` fields only hold a key, as they point back up the graph
+//! - fields of types that don't implement `Seeded` are set to their default
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! # mod example {
+//! use dbent::prelude::*;
+//! use dbent::testing::{seeded, seeded_with_depth};
+//!
+//! #[derive(Entity, Label, PartialEq, Debug)]
+//! #[entity(seeded)]
+//! pub struct Author {
+//!     id: Key<Int>,
+//!     #[label]
+//!     name: String,
+//!     books: Many<Book>,
+//! }
+//!
+//! #[derive(Entity, PartialEq, Debug)]
+//! #[entity(seeded)]
+//! pub struct Book {
+//!     id: Key<Int>,
+//!     title: String,
+//!     author: Parent<Int, Author>,
+//! }
+//!
+//! # pub fn main() {
+//! let author = seeded::<Author>(42);
+//! assert_eq!(author, seeded::<Author>(42));
+//! assert_ne!(author, seeded::<Author>(43));
+//! assert!(author.id.is_some());
+//! assert!(author.books.data().unwrap().iter().all(|book| book.author.is_key()));
+//!
+//! assert!(seeded_with_depth::<Author>(42, 0).books.is_not_fetched());
+//! # }
+//! # }
+//! # example::main();
+//! # }
+//! ```
+
+use std::borrow::Cow;
+
+use crate::{CiString, Entity, EntityLabel, Key, Many, Parent};
+
+/// The depth of the relations holding data built by [`seeded`]
+pub const DEFAULT_DEPTH: usize = 2;
+
+/// Returns the entity built from `seed`, with relations holding data down to
+/// [`DEFAULT_DEPTH`]
+pub fn seeded<T: Seeded>(seed: u64) -> T {
+    seeded_with_depth(seed, DEFAULT_DEPTH)
+}
+
+/// Returns the entity built from `seed`, with relations holding data down to
+/// `depth`
+pub fn seeded_with_depth<T: Seeded>(seed: u64, depth: usize) -> T {
+    T::seeded(&mut SeedRng::new(seed), depth)
+}
+
+/// A small deterministic random number generator, the same on every platform
+///
+/// It is SplitMix64, which is fast and good enough for fixtures but not for
+/// anything needing unpredictable numbers.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SeedRng {
+    state: u64,
+}
+
+impl SeedRng {
+    /// Creates a generator starting from `seed`
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next number
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number below `bound`, or 0 if `bound` is 0
+    pub fn below(&mut self, bound: u64) -> u64 {
+        match bound {
+            0 => 0,
+            bound => self.next_u64() % bound,
+        }
+    }
+
+    /// Returns true half of the time
+    pub fn coin(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    /// Returns one of the items, which must not be empty
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+/// Types built from a [`SeedRng`] by [`seeded`], implemented by
+/// `#[entity(seeded)]`
+pub trait Seeded: Sized {
+    /// Returns a value built from the numbers of `rng`, with relations holding
+    /// data down to `depth`
+    fn seeded(rng: &mut SeedRng, depth: usize) -> Self;
+}
+
+macro_rules! impl_seeded_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl Seeded for $ty {
+                fn seeded(rng: &mut SeedRng, _depth: usize) -> Self {
+                    1 + rng.below((<$ty>::MAX as u64).min(10_000) - 1) as $ty
+                }
+            }
+        )*
+    };
+}
+
+impl_seeded_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl Seeded for bool {
+    fn seeded(rng: &mut SeedRng, _depth: usize) -> Self {
+        rng.coin()
+    }
+}
+
+impl Seeded for f32 {
+    fn seeded(rng: &mut SeedRng, _depth: usize) -> Self {
+        rng.below(100_000) as f32 / 100.0
+    }
+}
+
+impl Seeded for f64 {
+    fn seeded(rng: &mut SeedRng, _depth: usize) -> Self {
+        rng.below(100_000) as f64 / 100.0
+    }
+}
+
+const ADJECTIVES: &[&str] = &["amber", "brisk", "calm", "dusty", "eager", "fuzzy", "gentle", "hollow", "ivory", "jolly", "keen", "lucky"];
+const NOUNS: &[&str] = &["falcon", "harbor", "meadow", "lantern", "otter", "pebble", "quill", "river", "saddle", "thistle", "violet", "willow"];
+
+impl Seeded for String {
+    fn seeded(rng: &mut SeedRng, _depth: usize) -> Self {
+        format!("{} {}", rng.pick(ADJECTIVES), rng.pick(NOUNS))
+    }
+}
+
+impl Seeded for Cow<'static, str> {
+    fn seeded(rng: &mut SeedRng, depth: usize) -> Self {
+        Cow::Owned(String::seeded(rng, depth))
+    }
+}
+
+impl Seeded for CiString {
+    fn seeded(rng: &mut SeedRng, depth: usize) -> Self {
+        CiString::new(String::seeded(rng, depth))
+    }
+}
+
+impl<T: Seeded> Seeded for Option<T> {
+    fn seeded(rng: &mut SeedRng, depth: usize) -> Self {
+        rng.coin().then(|| T::seeded(rng, depth))
+    }
+}
+
+impl<K: Seeded> Seeded for Key<K> {
+    fn seeded(rng: &mut SeedRng, depth: usize) -> Self {
+        Key(Some(K::seeded(rng, depth)))
+    }
+}
+
+impl<K: Seeded, T: Seeded> Seeded for Entity<K, T> {
+    fn seeded(rng: &mut SeedRng, depth: usize) -> Self {
+        match depth {
+            0 => Entity::Key(Key::seeded(rng, 0)),
+            depth => Entity::Data(Box::new(T::seeded(rng, depth - 1))),
+        }
+    }
+}
+
+impl<K: Seeded, T: Seeded, L: Seeded> Seeded for EntityLabel<K, T, L> {
+    fn seeded(rng: &mut SeedRng, depth: usize) -> Self {
+        match depth {
+            0 => {
+                let key = Key::seeded(rng, 0);
+                EntityLabel::KeyLabel(key, L::seeded(rng, 0))
+            },
+            depth => EntityLabel::Data(Box::new(T::seeded(rng, depth - 1))),
+        }
+    }
+}
+
+impl<K: Seeded, T> Seeded for Parent<K, T> {
+    fn seeded(rng: &mut SeedRng, _depth: usize) -> Self {
+        Parent(Entity::Key(Key::seeded(rng, 0)))
+    }
+}
+
+impl<T: Seeded> Seeded for Many<T> {
+    fn seeded(rng: &mut SeedRng, depth: usize) -> Self {
+        match depth {
+            0 => Many::NotFetched,
+            depth => {
+                let len = 1 + rng.below(3);
+                Many::Data((0..len).map(|_| T::seeded(rng, depth - 1)).collect())
+            },
+        }
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Helpers for the code generated by `#[entity(seeded)]`
+
+    use core::marker::PhantomData;
+
+    use super::{SeedRng, Seeded};
+
+    pub struct SeedOf<T>(pub PhantomData<T>);
+
+    pub trait KnownSeed<T> {
+        fn seeded(&self, rng: &mut SeedRng, depth: usize) -> T;
+    }
+
+    impl<T: Seeded> KnownSeed<T> for SeedOf<T> {
+        fn seeded(&self, rng: &mut SeedRng, depth: usize) -> T {
+            T::seeded(rng, depth)
+        }
+    }
+
+    pub trait DefaultSeed<T> {
+        fn seeded(&self, rng: &mut SeedRng, depth: usize) -> T;
+    }
+
+    impl<T: Default> DefaultSeed<T> for &SeedOf<T> {
+        fn seeded(&self, _rng: &mut SeedRng, _depth: usize) -> T {
+            T::default()
+        }
+    }
+}
@@ -1,7 +1,8 @@
 
 use super::*;
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "redis", derive(Serialize, Deserialize))]
 struct Model {
     id: Key<Int>,
     label: String,
@@ -15,6 +16,14 @@ impl Keyed for Model {
     }
 }
 
+impl KeyedMut for Model {
+    fn key_mut(&mut self) -> Result<&mut Key<Self::KeyType>> {
+        Ok(&mut self.id)
+    }
+}
+
+impl hooks::Lifecycle for Model {}
+
 impl Label for Model {
     type LabelType = String;
 
@@ -23,6 +32,44 @@ impl Label for Model {
     }
 }
 
+impl sql::Columns for Model {
+    fn column_value(&self, column: &str) -> Option<sql::Value> {
+        match column {
+            "id" => Some(self.id.clone().into()),
+            "label" => Some(self.label.as_str().into()),
+            _ => None,
+        }
+    }
+
+    fn columns(&self) -> &'static [&'static str] {
+        &["id", "label"]
+    }
+}
+
+impl schema::Schema for Model {
+    fn entity_meta() -> schema::EntityMeta {
+        use schema::{ColumnMeta, SqlType};
+
+        schema::EntityMeta {
+            table: "model".into(),
+            columns: vec![
+                ColumnMeta { name: "id".into(), sql_type: SqlType::Integer, nullable: true, primary_key: true, references: None, on_delete: None },
+                ColumnMeta { name: "label".into(), sql_type: SqlType::Text, nullable: false, primary_key: false, references: None, on_delete: None },
+            ],
+            many: vec![],
+            unique: vec![vec!["label".into()]],
+            id_prefix: None,
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl sqlite::FromRow for Model {
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Model { id: row.get(0)?, label: row.get(1)? })
+    }
+}
+
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
 //<<>><=========================  TRAITS  ===========================><<>>//
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
@@ -76,6 +123,308 @@ fn test_tagged() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_custom_tag_label() -> Result<()> {
+    struct Country {
+        id: Key<Int>,
+        code: &'static str,
+        name: String,
+    }
+
+    impl Keyed for Country {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.id)
+        }
+    }
+
+    impl Label for Country {
+        type LabelType = String;
+
+        fn label(&self) -> Result<&Self::LabelType> {
+            Ok(&self.name)
+        }
+
+        fn tag_label(&self) -> Result<Cow<'static, str>> {
+            Ok(format!("{} ({})", self.name, self.code).into())
+        }
+    }
+
+    let country = Country { id: Key::new(1), code: "BR", name: "Brazil".to_owned() };
+    assert_eq!(country.tag()?, Tag::new("1", "Brazil (BR)"));
+    assert_eq!(country.tag_parts().1.as_deref(), Some("Brazil (BR)"));
+    assert_eq!(Box::new(&country).tag()?.label, "Brazil (BR)");
+    assert_eq!(country.label()?, "Brazil");
+    Ok(())
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_tag_strict() -> Result<()> {
+    let entity = Model { id: Key::new(1), label: "Entity".to_owned() };
+    assert_eq!(entity.tag_strict()?, Tag::new("1", "Entity"));
+
+    let entity = Model { id: Key(None), label: "New".to_owned() };
+    assert!(matches!(entity.tag(), Err(Error::KeyMissing)));
+    assert!(matches!(entity.tag_lossy(), Err(Error::KeyMissing)));
+    assert!(matches!(entity.tag_strict(), Err(Error::KeyMissing)));
+    Ok(())
+}
+
+#[test]
+fn test_tagged_fallback() -> Result<()> {
+    struct Partial {
+        id: Key<Int>,
+        label: Option<String>,
+    }
+
+    impl Keyed for Partial {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.id)
+        }
+    }
+
+    impl Label for Partial {
+        type LabelType = String;
+
+        fn label(&self) -> Result<&Self::LabelType> {
+            self.label.as_ref().ok_or(Error::EntityNotFetched)
+        }
+    }
+
+    let entity = Partial { id: Key::new(1), label: None };
+    assert!(entity.tag().is_err());
+    assert_eq!(entity.tag_or("Unknown")?.label, "Unknown");
+    assert_eq!(entity.tag_lossy()?, Tag::new("1", "1"));
+
+    let entity = Partial { id: Key::new(1), label: Some("Entity".to_owned()) };
+    assert_eq!(entity.tag_or("Unknown")?.label, "Entity");
+    assert_eq!(entity.tag_lossy()?.label, "Entity");
+
+    let entity_label = EntityLabelInt::<Model>::None;
+    assert!(entity_label.tag_lossy().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_write_tag() -> Result<()> {
+    let model = |id: Option<Int>, label: &str| Model { id: Key(id), label: label.to_owned() };
+    let mut tag = model(Some(1234), "a").tag()?;
+    let buffer = tag.key.as_ptr();
+
+    model(Some(56), "b").write_tag(&mut tag)?;
+    assert_eq!(tag, Tag::new("56", "b"));
+    assert_eq!(tag.key.as_ptr(), buffer);
+
+    assert!(matches!(model(None, "c").write_tag(&mut tag), Err(Error::KeyMissing)));
+    assert!(EntityLabelInt::<Model>::None.write_tag(&mut tag).is_err());
+
+    let models = [model(Some(1), "a"), model(Some(2), "b")];
+    let mut tags = vec![Tag::new(String::from("10"), "x"), Tag::new(String::from("20"), "y"), Tag::default()];
+    let buffer = tags[1].key.as_ptr();
+    models.tags_into(&mut tags)?;
+    assert_eq!(tags, models.tags()?);
+    assert_eq!(tags[1].key.as_ptr(), buffer);
+
+    let partial = [EntityLabelInt::from(model(Some(3), "c")), EntityLabelInt::None];
+    assert!(partial.tags_into(&mut tags).is_err());
+    assert_eq!(tags, [Tag::new("3", "c")]);
+    Ok(())
+}
+
+impl TagMeta for Model {
+    type Payload = usize;
+
+    fn tag_payload(&self) -> Option<usize> {
+        (!self.label.is_empty()).then_some(self.label.len())
+    }
+}
+
+#[test]
+fn test_tag_meta() -> Result<()> {
+    let tag = Model { id: Key::new(1), label: "Entity".to_owned() }.tag_ext()?;
+    assert_eq!(tag, TagExt::new(Tag::new("1", "Entity"), Some(6)));
+    assert_eq!(tag.key, "1");
+    assert_eq!(Model { id: Key::new(2), label: String::new() }.tag_ext()?.payload, None);
+    assert!(matches!(Model::default().tag_ext(), Err(Error::KeyMissing)));
+    assert_eq!(TagExt::<usize>::from(Tag::new("3", "c")).into_tag(), Tag::new("3", "c"));
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&tag).unwrap();
+        assert_eq!(json, r#"{"key":"1","label":"Entity","payload":6}"#);
+        assert_eq!(serde_json::from_str::<TagExt<usize>>(&json).unwrap(), tag);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_tag_parts() {
+    let entity = Model { id: Key::new(1), label: "Entity".to_owned() };
+    assert_eq!(entity.tag_parts(), (Some("1".to_owned()), Some("Entity".to_owned())));
+    assert!(entity.has_label());
+
+    let entity = Model { id: Key(None), label: "New".to_owned() };
+    assert!(!entity.has_tag());
+    assert!(entity.has_label());
+    assert_eq!(entity.tag_parts(), (None, Some("New".to_owned())));
+
+    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key(None), "Label only".to_owned());
+    assert_eq!(entity_label.tag_parts(), (None, Some("Label only".to_owned())));
+
+    let entity_label = EntityLabelInt::<Model>::None;
+    assert!(!entity_label.has_label());
+    assert_eq!(entity_label.tag_parts(), (None, None));
+}
+
+#[test]
+fn test_keyed_label_smart_pointers() -> Result<()> {
+    fn tag_of(entity: impl Keyed<KeyType = Int> + Label<LabelType = String>) -> Result<Tag> {
+        entity.tag()
+    }
+
+    let model = Model { id: Key::new(1), label: "Entity".to_owned() };
+    let tag = Tag::new("1", "Entity");
+    assert_eq!(tag_of(&model)?, tag);
+    assert_eq!(tag_of(std::rc::Rc::new(&model))?, tag);
+    assert_eq!(tag_of(std::sync::Arc::new(&model))?, tag);
+    assert_eq!(tag_of(Box::new(model))?, tag);
+    Ok(())
+}
+
+#[test]
+fn test_normalized_label() -> Result<()> {
+    assert!(matches!(normalize_label("  brazil "), Cow::Borrowed("brazil")));
+    assert_eq!(normalize_label(" United States\n"), "united states");
+    assert_eq!("ÅLAND".normalized(), "åland".normalized());
+    assert_eq!(CiString::new(" US ").normalized(), "us");
+    assert_eq!(Tag::new("1", " Label ").normalized(), "label");
+
+    let model = Model { id: Key::new(1), label: " First ".to_owned() };
+    assert_eq!(model.normalized(), "first");
+    let labels: Vec<EntityLabel<Int, Model, String>> = vec![EntityLabel::KeyLabel(Key::new(1), "A".to_owned()), EntityLabel::None];
+    assert_eq!(labels[0].normalized(), "a");
+    assert_eq!(labels[1].normalized(), "");
+
+    let many: Many<Model> = vec![model, Model { id: Key::new(2), label: "Second".to_owned() }].into();
+    assert_eq!(many.find_by_label(" SECOND")?.map(|model| model.id.clone()), Some(Key::new(2)));
+    assert!(many.find_by_label("third")?.is_none());
+    assert!(matches!(Many::<Model>::NotFetched.find_by_label("first"), Err(Error::ManyNotFetched)));
+    Ok(())
+}
+
+#[cfg(feature = "unaccent")]
+#[test]
+fn test_normalized_label_unaccent() {
+    assert_eq!(normalize_label(" São Paulo "), "sao paulo");
+    assert_eq!("Ångström".normalized(), "angstrom");
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+fn relations_are_send_sync<K: Send + Sync, T: Send + Sync, L: Send + Sync>() {
+    assert_send_sync::<Key<K>>();
+    assert_send_sync::<Entity<K, T>>();
+    assert_send_sync::<Parent<K, T>>();
+    assert_send_sync::<EntityLabel<K, T, L>>();
+    assert_send_sync::<Many<T>>();
+    assert_send_sync::<SharedMany<T>>();
+    assert_send_sync::<Link<K, L, T>>();
+    assert_send_sync::<snapshot::Snapshot<T>>();
+    assert_send_sync::<snapshot::EditHistory<T>>();
+}
+
+fn repositories_are_send_sync<R: Send + Sync, T: Keyed + Send + Sync>()
+where
+    T::KeyType: Send + Sync,
+{
+    assert_send_sync::<repository::MemoryRepository<T>>();
+    assert_send_sync::<repository::CachedRepository<R, T>>();
+}
+
+#[test]
+fn test_send_sync() {
+    assert_send_sync::<Error>();
+    assert_send_sync::<Tag>();
+    assert_send_sync::<CiString>();
+    assert_send_sync::<Sensitive<String>>();
+    assert_send_sync::<fixed_str::FixedStr<8>>();
+    assert_send_sync::<key_value::KeyValue>();
+    assert_send_sync::<observe::FetchCounter>();
+    assert_send_sync::<reads::ReadTracker>();
+    assert_send_sync::<Box<dyn AnyTagged + Send + Sync>>();
+    relations_are_send_sync::<Int, Model, String>();
+    repositories_are_send_sync::<repository::MemoryRepository<Model>, Model>();
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_repository_futures_are_send() {
+    use repository::AsyncRepository;
+
+    fn assert_send<F: Send>(_: &F) {}
+
+    let repo = repository::MemoryRepository::<Model>::new();
+    let mut model = Model::default();
+    assert_send(&repo.insert(&mut model));
+    assert_send(&repo.get(&1));
+    assert_send(&repo.list());
+    assert_send(&repo.get_many(&[]));
+}
+
+#[cfg(feature = "async")]
+#[allow(dead_code)]
+fn streams_are_send_sync<T: Send + Sync, S: Send + Sync>() {
+    assert_send_sync::<stream::ManyStream<T, S>>();
+}
+
+#[test]
+fn test_tagged_manual_defaults() -> Result<()> {
+    struct Manual(Option<&'static str>);
+
+    impl Tagged for Manual {
+        fn tag(&self) -> Result<Tag> {
+            Err(Error::LabelEmpty)
+        }
+
+        fn has_tag(&self) -> bool {
+            self.0.is_some()
+        }
+
+        fn tag_parts(&self) -> (Option<String>, Option<String>) {
+            (self.0.map(str::to_owned), None)
+        }
+    }
+
+    assert_eq!(Manual(Some("1")).tag_or("Unknown")?, Tag::new("1", "Unknown"));
+    assert_eq!(Manual(Some("1")).tag_lossy()?, Tag::new("1", "1"));
+    assert!(matches!(Manual(None).tag_or("Unknown"), Err(Error::LabelEmpty)));
+    assert!(matches!(Manual(None).tag_lossy(), Err(Error::LabelEmpty)));
+    assert!(!Manual(Some("1")).has_label());
+
+    struct Tagless;
+
+    impl Tagged for Tagless {
+        fn tag(&self) -> Result<Tag> {
+            Ok(Tag::new("2", "Two"))
+        }
+
+        fn has_tag(&self) -> bool {
+            true
+        }
+    }
+
+    assert_eq!(Tagless.tag_parts(), (Some("2".to_owned()), Some("Two".to_owned())));
+    assert!(Tagless.has_label());
+    let mut tags = vec![Tag::default()];
+    [Tagless].tags_into(&mut tags)?;
+    assert_eq!(tags, [Tag::new("2", "Two")]);
+    Ok(())
+}
+
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
 //<<>><==========================  KEY  =============================><<>>//
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
@@ -93,6 +442,19 @@ fn test_key() {
     let id = Key::from(&Some(3));
     assert_eq!(*id, Some(3));
     assert_eq!(format!("{}", id), "3");
+
+    let id: Key<Int> = 4.into();
+    assert_eq!(id, Key::new(4));
+}
+
+#[test]
+fn test_key_const() {
+    const ADMIN: Key<i64> = Key::new(1);
+    static ROLES: [Entity<i64, Model>; 2] = [Entity::Key(ADMIN), Entity::Key(Key::NONE)];
+
+    assert_eq!(ADMIN, Key::new(1));
+    assert_eq!(Key::<i64>::NONE, Key(None));
+    assert!(matches!(&ROLES, [Entity::Key(Key(Some(1))), Entity::Key(Key(None))]));
 }
 
 #[test]
@@ -103,6 +465,22 @@ fn test_key_display() {
     assert_eq!(format!("{}", id), "None");
 }
 
+#[test]
+fn test_key_try_display() {
+    assert_eq!(Key::new(7).try_display().map(|key| format!("/users/{key}")).as_deref(), Some("/users/7"));
+    assert!(Key::<i32>(None).try_display().is_none());
+}
+
+#[test]
+fn test_key_display_or() {
+    let id = Key::new(7);
+    assert_eq!(id.display_or("-").to_string(), "7");
+    assert_eq!(format!("{:>3}", id.display_or("-")), "  7");
+    let id = Key::<i32>(None);
+    assert_eq!(id.display_or("").to_string(), "");
+    assert_eq!(format!("#{}", id.display_or("").placeholder("new")), "#new");
+}
+
 #[test]
 fn test_key_from_option() {
     let id = Key::from(Some(1));
@@ -119,11 +497,117 @@ fn test_key_from_reference() {
     assert_eq!(id, Key::<i32>(None));
 }
 
+#[test]
+fn test_key_borrow_lookup() {
+    use std::collections::{HashMap, BTreeSet};
+
+    let mut map = HashMap::new();
+    map.insert(Key::new(5), "five");
+    map.insert(Key(None), "none");
+    assert_eq!(map.get(&Some(5)), Some(&"five"));
+    assert_eq!(map.get(&None), Some(&"none"));
+    assert_eq!(map.get(&Some(6)), None);
+
+    let set: BTreeSet<Key<String>> = [Key::new("a".to_owned())].into_iter().collect();
+    assert!(set.contains(&Some("a".to_owned())));
+}
+
+#[test]
+fn test_key_try_from() -> Result<()> {
+    assert_eq!(Key::<Int>::try_from(Key::new(5i64))?, Key::new(5));
+    assert_eq!(Key::<i64>::try_from(Key::<Int>(None))?, Key(None));
+    assert_eq!(Key::<u32>::try_from(Key::new(7i32))?, Key::new(7));
+    assert!(matches!(Key::<Int>::try_from(Key::new(-1i64)), Err(Error::KeyOutOfRange)));
+    assert!(matches!(Key::<i32>::try_from(Key::new(u64::MAX)), Err(Error::KeyOutOfRange)));
+    Ok(())
+}
+
+#[test]
+fn test_key_hex() -> Result<()> {
+    let key = Key::new(vec![0x00, 0xab, 0x10]);
+    assert_eq!(key.hex().to_string(), "00ab10");
+    assert_eq!(Key::<Vec<u8>>(None).hex().to_string(), "None");
+    assert_eq!(Key::<Vec<u8>>::from_hex("00AB10")?, key);
+
+    let hash = Key::<[u8; 4]>::from_hex("deadbeef")?;
+    assert_eq!(hash, Key::new([0xde, 0xad, 0xbe, 0xef]));
+    assert!(matches!(Key::<[u8; 4]>::from_hex("dead"), Err(Error::InvalidHex(_))));
+    assert!(matches!(Key::<Vec<u8>>::from_hex("abc"), Err(Error::InvalidHex(_))));
+    assert!(matches!(Key::<Vec<u8>>::from_hex("zz"), Err(Error::InvalidHex(_))));
+    assert!(matches!(Key::<Vec<u8>>::from_hex("é1"), Err(Error::InvalidHex(_))));
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_key_hex_serde() {
+    #[derive(::serde::Serialize, ::serde::Deserialize, PartialEq, Debug)]
+    struct Content {
+        #[serde(with = "crate::serde::key_hex")]
+        hash: Key<[u8; 2]>,
+    }
+
+    let content = Content { hash: Key::new([0x0f, 0xf0]) };
+    assert_eq!(serde_json::to_string(&content).unwrap(), r#"{"hash":"0ff0"}"#);
+    assert_eq!(serde_json::from_str::<Content>(r#"{"hash":"0FF0"}"#).unwrap(), content);
+    assert_eq!(serde_json::from_str::<Content>(r#"{"hash":null}"#).unwrap(), Content { hash: Key(None) });
+    assert!(serde_json::from_str::<Content>(r#"{"hash":"0ff"}"#).is_err());
+}
+
+#[test]
+fn test_key_next() {
+    assert_eq!(Key::<Int>(None).next().unwrap(), Key::new(1));
+    assert_eq!(Key::<Int>::new(1).next().unwrap(), Key::new(2));
+    assert!(matches!(Key::<Int>::new(Int::MAX).next(), Err(Error::KeyOverflow)));
+}
+
+#[test]
+fn test_key_allocator() {
+    let allocator = KeyAllocator::new();
+    assert_eq!(allocator.next_key().unwrap(), Key::new(1));
+    assert_eq!(allocator.next_key().unwrap(), Key::new(2));
+    assert_eq!(allocator.peek(), Some(Key::new(3)));
+
+    allocator.observe(&Key::new(10));
+    assert_eq!(allocator.next_key().unwrap(), Key::new(11));
+    allocator.observe(&Key::new(5));
+    allocator.observe(&Key(None));
+    assert_eq!(allocator.next_key().unwrap(), Key::new(12));
+
+    let allocator = std::sync::Arc::new(KeyAllocator::starting_at(100));
+    let handles = (0..4)
+        .map(|_| {
+            let allocator = allocator.clone();
+            std::thread::spawn(move || (0..25).map(|_| allocator.next_key().unwrap()).collect::<Vec<_>>())
+        })
+        .collect::<Vec<_>>();
+    let mut keys = handles.into_iter().flat_map(|h| h.join().unwrap()).collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), 100);
+    assert_eq!(allocator.peek(), Some(Key::new(200)));
+
+    let allocator = KeyAllocator::starting_at(Int::MAX - 1);
+    assert_eq!(allocator.next_key().unwrap(), Key::new(Int::MAX - 1));
+    assert!(matches!(allocator.next_key(), Err(Error::KeyOverflow)));
+    assert_eq!(allocator.peek(), None);
+    let allocator = KeyAllocator::new();
+    allocator.observe(&Key::new(Int::MAX));
+    assert!(matches!(allocator.next_key(), Err(Error::KeyOverflow)));
+    assert_eq!(allocator.peek(), None);
+    assert!(matches!(allocator.clone().next_key(), Err(Error::KeyOverflow)));
+
+    let allocator = KeyAllocator::starting_at(7);
+    let copy = allocator.clone();
+    assert_eq!(allocator.next_key().unwrap(), Key::new(7));
+    assert_eq!(copy.next_key().unwrap(), Key::new(7));
+}
+
 #[cfg(feature = "rusqlite")]
 #[test]
 fn test_key_to_from_sql() {
     let id = Key::new(1);
-    let result: Result<Key<i32>, _> = ValueRef::from(&Some(1)).from_sql();
+    let result = Key::<i32>::column_result(ValueRef::Integer(1));
     assert_eq!(result.unwrap(), id);
 }
 
@@ -132,10 +616,10 @@ fn test_key_to_from_sql() {
 fn test_key_from_sql() {
     use rusqlite::types::Value;
     let value = Value::from(1);
-    let id = Key::<i32>::column_result(value).unwrap();
+    let id = Key::<i32>::column_result((&value).into()).unwrap();
     assert_eq!(id, Key::new(1));
     let value = Value::Null;
-    let id = Key::<i32>::column_result(value).unwrap();
+    let id = Key::<i32>::column_result((&value).into()).unwrap();
     assert_eq!(id, Key::<i32>(None));
 }
 
@@ -148,74 +632,1013 @@ fn test_key_to_sql() {
     assert_eq!(value, ToSqlOutput::from(1));
     let id = Key::<i32>(None);
     let value = id.to_sql().unwrap();
-    assert_eq!(value, ToSqlOutput::from(rusqlite::types::Value::Null));
+    assert_eq!(value, ToSqlOutput::from(rusqlite::types::Null));
 }
 
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
-//<<>><=========================  ENTITY  ===========================><<>>//
+//<<>><======================  SCOPED KEY  ==========================><<>>//
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
 
 #[test]
-fn test_entity_key() -> Result<()> {
-    let entity = Model { id: Key::new(1), label: "Entity".to_owned() };
-    assert_eq!(entity.key()?.unwrap(), 1);
-    assert_eq!(**entity.key()?, Some(1));
+fn test_scoped_key() -> Result<()> {
+    struct Invoice {
+        id: Key<ScopedKey<u32, Int>>,
+    }
 
-    let entity: Entity<Int, Model> = Key::new(1).into_entity();
-    assert_eq!(entity.key()?.unwrap(), 1);
-    assert_eq!(**entity.key()?, Some(1));
+    impl Keyed for Invoice {
+        type KeyType = ScopedKey<u32, Int>;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.id)
+        }
+    }
+
+    let invoice = Invoice { id: Key::scoped(7, 1) };
+    let entity: Entity<ScopedKey<u32, Int>, Invoice> = invoice.into();
+    assert_eq!(entity.key()?.scope(), Some(&7));
+    assert_eq!(entity.key()?.in_scope(&7)?, &1);
+    assert!(matches!(entity.key()?.in_scope(&8), Err(Error::ScopeMismatch)));
+    assert!(matches!(Key::<ScopedKey<u32, Int>>(None).in_scope(&7), Err(Error::KeyEmpty)));
+    assert_eq!(entity.key()?.to_string(), "7/1");
     Ok(())
 }
 
+#[cfg(feature = "serde")]
 #[test]
-fn test_entity_key_none() -> Result<()> {
-    let entity = Model::default();
-    assert!(entity.key()?.is_none());
-    Ok(())
+fn test_key_string_serde() {
+    #[derive(::serde::Serialize, ::serde::Deserialize, PartialEq, Debug)]
+    struct Row {
+        #[serde(with = "crate::serde::key_string")]
+        id: Key<u64>,
+    }
+
+    let row = Row { id: Key::new(u64::MAX) };
+    assert_eq!(serde_json::to_string(&row).unwrap(), format!(r#"{{"id":"{}"}}"#, u64::MAX));
+    assert_eq!(serde_json::to_string(&Row { id: Key(None) }).unwrap(), r#"{"id":null}"#);
+    assert_eq!(serde_json::from_str::<Row>(&format!(r#"{{"id":"{}"}}"#, u64::MAX)).unwrap(), row);
+    assert_eq!(serde_json::from_str::<Row>(r#"{"id":5}"#).unwrap(), Row { id: Key::new(5) });
+    assert_eq!(serde_json::from_str::<Row>(r#"{"id":null}"#).unwrap(), Row { id: Key(None) });
+    assert!(serde_json::from_str::<Row>(r#"{"id":"five"}"#).is_err());
 }
 
+#[cfg(feature = "serde")]
 #[test]
-fn test_entity_data() -> Result<()> {
-    let entity: EntityInt<Model> = Model { id: Key::new(1), label: "Entity".to_owned() }.into();
-    let result = entity.data()?;
-    assert_eq!(entity.key()?.unwrap(), 1);
-    assert_eq!(result.key()?.unwrap(), 1);
-    assert_eq!(result.label()?, "Entity");
-    Ok(())
+fn test_key_lenient_serde() {
+    #[derive(::serde::Serialize, ::serde::Deserialize, PartialEq, Debug)]
+    struct Form {
+        #[serde(with = "crate::serde::key_lenient")]
+        country: Key<Int>,
+    }
+
+    assert_eq!(serde_json::to_string(&Form { country: Key::new(5) }).unwrap(), r#"{"country":5}"#);
+    assert_eq!(serde_json::from_str::<Form>(r#"{"country":"5"}"#).unwrap(), Form { country: Key::new(5) });
+    assert_eq!(serde_json::from_str::<Form>(r#"{"country":" 5 "}"#).unwrap(), Form { country: Key::new(5) });
+    assert_eq!(serde_json::from_str::<Form>(r#"{"country":5}"#).unwrap(), Form { country: Key::new(5) });
+    assert!(serde_json::from_str::<Form>(r#"{"country":"-5"}"#).is_err());
 }
 
+#[cfg(feature = "serde")]
 #[test]
-fn test_entity_data_none() {
-    let entity = Entity::<Int, Model>::None;
-    assert!(entity.data().is_err());
-}
+fn test_depth_limited_serde() {
+    use crate::serde::DepthLimited;
 
-//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
-//<<>><======================  ENTITY LABEL  ========================><<>>//
-//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+    #[derive(Serialize)]
+    struct Category {
+        id: Key<Int>,
+        name: String,
+        #[serde(with = "crate::serde::entity_label_depth")]
+        parent: EntityLabel<Int, Category, String>,
+    }
 
-#[test]
-fn test_entity_label_label() -> Result<()> {
-    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
-    assert_eq!(entity_label.label()?, &String::from("Label"));
-    Ok(())
-}
+    impl Keyed for Category {
+        type KeyType = Int;
 
-#[test]
-fn test_entity_label_key() -> Result<()>{
-    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
-    assert_eq!(entity_label.key()?.unwrap(), 1);
-    Ok(())
+        fn key(&self) -> Result<&Key<Int>> {
+            Ok(&self.id)
+        }
+    }
+
+    impl Label for Category {
+        type LabelType = String;
+
+        fn label(&self) -> Result<&String> {
+            Ok(&self.name)
+        }
+    }
+
+    let category = |id, name: &str, parent| Category { id: Key::new(id), name: name.to_owned(), parent };
+    let root = category(1, "Root", EntityLabel::None);
+    let books = category(2, "Books", root.into());
+    let novels = category(3, "Novels", books.into());
+
+    let json = |depth| serde_json::to_string(&DepthLimited::new(&novels, depth)).unwrap();
+    assert_eq!(json(0), r#"{"id":3,"name":"Novels","parent":{"KeyLabel":[2,"Books"]}}"#);
+    assert_eq!(
+        json(1),
+        r#"{"id":3,"name":"Novels","parent":{"Data":{"id":2,"name":"Books","parent":{"KeyLabel":[1,"Root"]}}}}"#,
+    );
+    assert_eq!(json(2), serde_json::to_string(&novels).unwrap());
+    assert!(serde_json::to_string(&novels).unwrap().contains(r#""parent":"None""#));
+
+    let levels = [DepthLimited::new(&novels, 0), DepthLimited::new(&novels, 5)];
+    assert_eq!(serde_json::to_value(&levels).unwrap()[0]["parent"]["KeyLabel"][0], 2);
 }
 
+#[cfg(feature = "serde")]
 #[test]
-fn test_entity_label_data() -> Result<()> {
-    let entity_label: EntityLabelInt<Model> = Model { id: Key::new(1), label: "Entity".to_owned() }.into();
-    let result = entity_label.data()?;
-    assert_eq!(entity_label.key()?.unwrap(), 1);
-    assert_eq!(result.key()?.unwrap(), 1);
-    assert_eq!(result.label()?, "Entity");
-    Ok(())
+fn test_redacted_serde() {
+    use crate::serde::{Redacted, Redacting, Redaction};
+
+    #[derive(::serde::Serialize, ::serde::Deserialize, PartialEq, Debug)]
+    struct Customer {
+        name: String,
+        phone: Redacted<Option<String>>,
+        notes: Vec<Redacted<String>>,
+    }
+
+    let customer = Customer { name: "Ann".to_owned(), phone: Redacted(Some("555".to_owned())), notes: vec![Redacted("vip".to_owned())] };
+    let json = |redaction| serde_json::to_string(&Redacting::new(&customer, redaction)).unwrap();
+    assert_eq!(json(Redaction::Placeholder("[hidden]")), r#"{"name":"Ann","phone":"[hidden]","notes":["[hidden]"]}"#);
+    assert_eq!(json(Redaction::Null), r#"{"name":"Ann","phone":null,"notes":[null]}"#);
+    assert_eq!(json(Redaction::Reveal), serde_json::to_string(&customer).unwrap());
+
+    let stored = serde_json::to_string(&customer).unwrap();
+    assert_eq!(stored, r#"{"name":"Ann","phone":"555","notes":["vip"]}"#);
+    assert_eq!(serde_json::from_str::<Customer>(&stored).unwrap(), customer);
+
+    let nested = [Redacting::new(&customer, Redaction::Null), Redacting::new(&customer, Redaction::Reveal)];
+    assert_eq!(serde_json::to_value(&nested).unwrap()[1]["phone"], "555");
+    assert_eq!(serde_json::to_string(&customer.phone).unwrap(), r#""555""#);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_maybe_key_serde() {
+    use crate::serde::MaybeKey;
+
+    #[derive(::serde::Serialize, ::serde::Deserialize, PartialEq, Debug)]
+    struct Patch {
+        #[serde(default, skip_serializing_if = "MaybeKey::is_missing")]
+        country: MaybeKey<Int>,
+    }
+
+    let patch = |country| Patch { country };
+    assert_eq!(serde_json::from_str::<Patch>("{}").unwrap(), patch(MaybeKey::Missing));
+    assert_eq!(serde_json::from_str::<Patch>(r#"{"country":null}"#).unwrap(), patch(MaybeKey::Null));
+    assert_eq!(serde_json::from_str::<Patch>(r#"{"country":5}"#).unwrap(), patch(MaybeKey::Value(5)));
+
+    assert_eq!(serde_json::to_string(&patch(MaybeKey::Missing)).unwrap(), "{}");
+    assert_eq!(serde_json::to_string(&patch(MaybeKey::Null)).unwrap(), r#"{"country":null}"#);
+    assert_eq!(serde_json::to_string(&patch(MaybeKey::from(Key::new(5)))).unwrap(), r#"{"country":5}"#);
+
+    let mut key = Key::new(1);
+    assert!(!MaybeKey::Missing.apply(&mut key));
+    assert_eq!(key, Key::new(1));
+    assert!(MaybeKey::Value(2).apply(&mut key));
+    assert_eq!(key, Key::new(2));
+    assert!(MaybeKey::Null.apply(&mut key));
+    assert!(key.is_none());
+    assert!(MaybeKey::<Int>::from(Key(None)).is_null());
+}
+
+#[test]
+fn test_ci_string() {
+    use std::collections::HashMap;
+
+    let code = CiString::new(" US ");
+    assert_eq!(code.as_str(), "US");
+    assert_eq!(code.to_string(), "US");
+    assert_eq!(code, CiString::from("us"));
+    assert_eq!(code, "uS");
+    assert_eq!(CiString::from("BR").cmp(&code), core::cmp::Ordering::Less);
+
+    let mut countries = HashMap::new();
+    countries.insert(Key::new(code), "United States");
+    assert_eq!(countries.get(&Key::new(CiString::from("us\n"))), Some(&"United States"));
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_ci_string_sql() -> Result<()> {
+    use crate::sql::Value;
+
+    assert_eq!(Value::from(CiString::from(" ÉtÉ ")), Value::Text("été".to_owned()));
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch("CREATE TABLE country (code TEXT PRIMARY KEY, name TEXT NOT NULL)")?;
+    conn.execute("INSERT INTO country VALUES (?, 'Åland')", [CiString::from("ÅX")])?;
+    let find = |code: &str| conn.query_row("SELECT name FROM country WHERE code = ?", [CiString::from(code)], |row| row.get::<_, String>(0));
+    assert_eq!(find("åx")?, "Åland");
+    assert_eq!(find(" Åx ")?, "Åland");
+    let code: CiString = conn.query_row("SELECT code FROM country", [], |row| row.get(0))?;
+    assert_eq!(code, CiString::from("ÅX"));
+    assert_eq!(code.as_str(), "åx");
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_ci_string_serde() {
+    assert_eq!(serde_json::to_string(&CiString::from(" Us")).unwrap(), r#""Us""#);
+    assert_eq!(serde_json::from_str::<CiString>(r#"" us ""#).unwrap().as_str(), "us");
+}
+
+#[test]
+fn test_fixed_str() {
+    use crate::schema::{ColumnType, SqlType};
+    use crate::sql::Value;
+    use fixed_str::FixedStr;
+    use std::collections::HashMap;
+
+    let code = FixedStr::<5>::new("en-US").unwrap();
+    assert_eq!(code, "en-US");
+    assert_eq!(code.len(), 5);
+    assert_eq!(format!("{code:?}"), r#""en-US""#);
+    assert!(matches!(FixedStr::<4>::new("en-US"), Err(Error::CapacityExceeded { len: 5, capacity: 4 })));
+    assert_eq!(FixedStr::<4>::default(), "");
+    assert!("BRL".parse::<FixedStr<3>>().unwrap() < "USD".parse().unwrap());
+    assert_eq!(Value::from(Key::new(code)), Value::Text("en-US".to_owned()));
+    assert_eq!(<Key<FixedStr<5>> as ColumnType>::SQL_TYPE, SqlType::Text);
+
+    let mut rates = HashMap::new();
+    rates.insert(FixedStr::<3>::new("USD").unwrap(), 1.0);
+    assert_eq!(rates.get("USD"), Some(&1.0));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_fixed_str_serde() {
+    use fixed_str::FixedStr;
+
+    let code: Key<FixedStr<3>> = serde_json::from_str(r#""EUR""#).unwrap();
+    assert_eq!(serde_json::to_string(&code).unwrap(), r#""EUR""#);
+    assert!(serde_json::from_str::<FixedStr<3>>(r#""EURO""#).is_err());
+}
+
+#[test]
+fn test_external_id() {
+    use external_id::ExternalId;
+    use schema::Schema;
+
+    assert_eq!(Model::id_prefix(), "model");
+    let model = Model { id: Key::new(123), label: String::new() };
+    let id = ExternalId::of(&model).unwrap();
+    assert_eq!(id.to_string(), "model_123");
+    assert_eq!(format!("{id:?}"), r#"ExternalId(model_123)"#);
+    assert_eq!("model_123".parse::<ExternalId<Model>>().unwrap(), id);
+    assert_eq!(id.into_key(), Key::new(123));
+
+    for invalid in ["model123", "user_123", "model_", "model_x1", "_123"] {
+        assert!(matches!(invalid.parse::<ExternalId<Model>>(), Err(Error::InvalidExternalId(value)) if value == invalid));
+    }
+    assert!(matches!(ExternalId::<Model>::from_key(Key(None)), Err(Error::KeyMissing)));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_external_id_serde() {
+    use external_id::ExternalId;
+
+    assert_eq!(serde_json::to_string(&ExternalId::<Model>::new(5)).unwrap(), r#""model_5""#);
+    assert_eq!(serde_json::from_str::<ExternalId<Model>>(r#""model_5""#).unwrap().key(), &5);
+    assert!(serde_json::from_str::<ExternalId<Model>>("5").is_err());
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_fixed_str_sqlite() -> Result<()> {
+    use fixed_str::FixedStr;
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    let code: FixedStr<3> = conn.query_row("SELECT ?", [FixedStr::<3>::new("EUR")?], |row| row.get(0))?;
+    assert_eq!(code, "EUR");
+    assert!(conn.query_row("SELECT 'EURO'", [], |row| row.get::<_, FixedStr<3>>(0)).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_sensitive() {
+    use crate::schema::{ColumnType, SqlType};
+    use crate::sql::Value;
+    use std::collections::HashSet;
+
+    let key = Key::new(Sensitive::new("ann@example.com".to_owned()));
+    assert_eq!(key.to_string(), "***");
+    assert_eq!(format!("{key:?}"), "Key(Some(***))");
+    assert_eq!(key.as_ref().unwrap().expose(), "ann@example.com");
+    assert_eq!(Value::from(key.clone()), Value::Text("ann@example.com".to_owned()));
+    assert_eq!(<Key<Sensitive<String>> as ColumnType>::SQL_TYPE, SqlType::Text);
+
+    let keys = HashSet::from([key]);
+    assert!(keys.contains(&Some(Sensitive::from("ann@example.com".to_owned()))));
+    assert!(!keys.contains(&Some(Sensitive::from("bob@example.com".to_owned()))));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_sensitive_serde() {
+    let key: Key<Sensitive<String>> = serde_json::from_str(r#""tok""#).unwrap();
+    assert_eq!(key.0.as_ref().map(Sensitive::expose).map(String::as_str), Some("tok"));
+    assert_eq!(serde_json::to_string(&key).unwrap(), r#""tok""#);
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_sensitive_sqlite() -> Result<()> {
+    use rusqlite::types::{ToSqlOutput, Value};
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    let key: Key<Sensitive<String>> = conn.query_row("SELECT ?", [Sensitive::new("tok")], |row| row.get(0))?;
+    assert_eq!(key.expect("key").into_inner(), "tok");
+
+    // Aliases and wrappers are masked too, as masking doesn't go by type name
+    type Token = Sensitive<String>;
+    let masked = |output: rusqlite::Result<ToSqlOutput<'_>>| match output.unwrap() {
+        ToSqlOutput::Borrowed(ValueRef::Text(text)) => String::from_utf8(text.to_vec()).unwrap(),
+        ToSqlOutput::Owned(Value::Text(text)) => text,
+        output => panic!("{output:?}"),
+    };
+    assert_eq!(masked(crate::to_sql_masked(&Token::new("tok".to_owned()))), Sensitive::<String>::MASK);
+    assert_eq!(masked(crate::to_sql_masked(&Key::new(Token::new("tok".to_owned())))), Sensitive::<String>::MASK);
+    assert_eq!(masked(crate::to_sql_masked(&"tok".to_owned())), "tok");
+    assert_eq!(masked(Token::new("tok".to_owned()).to_sql()), "tok");
+    Ok(())
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_chrono_keys() {
+    use crate::schema::{ColumnType, SqlType};
+    use crate::sql::Value;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    let day = Key::new(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+    assert_eq!(day.to_string(), "2024-02-29");
+    assert_eq!(Value::from(day.clone()), Value::Text("2024-02-29".to_owned()));
+    assert_eq!(<Key<NaiveDate> as ColumnType>::SQL_TYPE, SqlType::Text);
+
+    let instant = Utc.with_ymd_and_hms(2024, 2, 29, 12, 30, 0).unwrap();
+    assert_eq!(Value::from(instant), Value::Text("2024-02-29 12:30:00+00:00".to_owned()));
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&day).unwrap();
+        assert_eq!(json, r#""2024-02-29""#);
+        assert_eq!(serde_json::from_str::<Key<NaiveDate>>(&json).unwrap(), day);
+    }
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_time_keys() {
+    use crate::sql::Value;
+    use time::{Date, Month, PrimitiveDateTime, Time};
+
+    let date = Date::from_calendar_date(2024, Month::February, 29).unwrap();
+    assert_eq!(Value::from(Key::new(date)), Value::Text("2024-02-29".to_owned()));
+
+    let at = PrimitiveDateTime::new(date, Time::from_hms_milli(8, 5, 1, 250).unwrap());
+    assert_eq!(Value::from(at), Value::Text("2024-02-29 08:05:01.25".to_owned()));
+    assert_eq!(Value::from(at.assume_utc()), Value::Text("2024-02-29 08:05:01.25Z".to_owned()));
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn test_decimal_keys() {
+    use crate::decimal::Decimal;
+    use crate::sql::Value;
+
+    let price: Key<Decimal> = Key::new("19.90".parse().unwrap());
+    assert_eq!(price.to_string(), "19.90");
+    assert_eq!(Value::from(price.clone()), Value::Text("19.90".to_owned()));
+    assert_eq!(price, Key::new("19.9".parse().unwrap()));
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&price).unwrap();
+        assert_eq!(json, r#""19.90""#);
+        assert_eq!(serde_json::from_str::<Key<Decimal>>(&json).unwrap(), price);
+        assert_eq!(serde_json::from_str::<Key<Decimal>>("19.9").unwrap(), price);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_scoped_key_serde() {
+    let key = Key::scoped("acme".to_owned(), 5);
+    let json = serde_json::to_string(&key).unwrap();
+    assert_eq!(json, r#"{"scope":"acme","key":5}"#);
+    assert_eq!(serde_json::from_str::<Key<ScopedKey<String, i32>>>(&json).unwrap(), key);
+    assert_eq!(serde_json::from_str::<Key<ScopedKey<String, i32>>>("null").unwrap(), Key(None));
+}
+
+#[test]
+fn test_key_value() {
+    use crate::key_value::KeyValue;
+
+    assert_eq!(KeyValue::from(Key::new(7i32)), KeyValue::Int(7));
+    assert_eq!(KeyValue::from(Key::new("a".to_owned())), KeyValue::Text("a".to_owned()));
+    assert_eq!(KeyValue::from(Key::<i64>(None)), KeyValue::Null);
+    assert_eq!(KeyValue::try_from(Key::<Int>::new(3)).unwrap(), KeyValue::Int(3));
+    assert!(matches!(KeyValue::try_from(Key::new(u64::MAX)), Err(Error::KeyOutOfRange)));
+
+    assert_eq!(Key::<Int>::try_from(KeyValue::Int(3)).unwrap(), Key::new(3));
+    assert_eq!(Key::<Int>::try_from(KeyValue::Null).unwrap(), Key(None));
+    assert!(matches!(Key::<u8>::try_from(KeyValue::Int(300)), Err(Error::KeyOutOfRange)));
+    assert!(matches!(Key::<Int>::try_from(KeyValue::from("a")), Err(Error::KeyTypeMismatch)));
+
+    assert_eq!(KeyValue::Bytes(vec![0xab, 0x01]).to_string(), "ab01");
+    assert_eq!(KeyValue::Null.to_string(), "None");
+
+    #[cfg(feature = "serde")]
+    {
+        let values = [KeyValue::Int(1), KeyValue::from("a"), KeyValue::Bytes(vec![1, 2]), KeyValue::Null];
+        let json = serde_json::to_string(&values).unwrap();
+        assert_eq!(json, r#"[1,"a",[1,2],null]"#);
+        assert_eq!(serde_json::from_str::<Vec<KeyValue>>(&json).unwrap(), values);
+    }
+
+    #[cfg(feature = "uuid")]
+    {
+        let uuid = uuid::Uuid::from_u128(1);
+        assert_eq!(KeyValue::from(Key::new(uuid)), KeyValue::Uuid(uuid));
+        assert_eq!(KeyValue::Uuid(uuid).to_string(), "00000000-0000-0000-0000-000000000001");
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_key_value_sqlite() -> Result<()> {
+    use crate::key_value::KeyValue;
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    for value in [KeyValue::Int(1), KeyValue::from("a"), KeyValue::Bytes(vec![1, 2]), KeyValue::Null] {
+        let read: KeyValue = conn.query_row("SELECT ?1", [&value], |row| row.get(0))?;
+        assert_eq!(read, value);
+    }
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  ENTITY  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_entity_key() -> Result<()> {
+    let entity = Model { id: Key::new(1), label: "Entity".to_owned() };
+    assert_eq!(entity.key()?.unwrap(), 1);
+    assert_eq!(**entity.key()?, Some(1));
+
+    let entity: Entity<Int, Model> = Key::new(1).into_entity();
+    assert_eq!(entity.key()?.unwrap(), 1);
+    assert_eq!(**entity.key()?, Some(1));
+
+    let entity: Entity<Int, Model> = Key::new(2).into();
+    assert!(entity.is_key());
+    assert_eq!(entity.key()?, &Key::new(2));
+    Ok(())
+}
+
+#[test]
+fn test_entity_key_none() -> Result<()> {
+    let entity = Model::default();
+    assert!(entity.key()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_entity_data() -> Result<()> {
+    let entity: EntityInt<Model> = Model { id: Key::new(1), label: "Entity".to_owned() }.into();
+    let result = entity.data()?;
+    assert_eq!(entity.key()?.unwrap(), 1);
+    assert_eq!(result.key()?.unwrap(), 1);
+    assert_eq!(result.label()?, "Entity");
+    Ok(())
+}
+
+#[test]
+fn test_entity_into_data_and_key() -> Result<()> {
+    let entity: EntityInt<Model> = Model { id: Key::new(1), label: "Entity".to_owned() }.into();
+    assert_eq!(entity.clone().into_key()?, Key::new(1));
+    assert_eq!(Key::try_from(entity.clone())?, Key::new(1));
+    assert_eq!(entity.into_data()?.label, "Entity");
+
+    let entity: EntityInt<Model> = Key::new(2).into();
+    assert!(matches!(entity.clone().into_data(), Err(Error::EntityNotFetched)));
+    let key: Key<Int> = entity.try_into()?;
+    assert_eq!(key, Key::new(2));
+
+    assert!(matches!(EntityInt::<Model>::None.into_data(), Err(Error::EntityEmpty)));
+    assert!(matches!(Key::<Int>::try_from(EntityInt::<Model>::None), Err(Error::EntityEmpty)));
+    Ok(())
+}
+
+#[test]
+fn test_entity_from_key_nullable() -> Result<()> {
+    let entity = EntityInt::<Model>::from_key_nullable(Key::new(1));
+    assert_eq!(entity.key()?, &Key::new(1));
+    assert!(EntityInt::<Model>::from_key_nullable(Key(None)).is_none());
+    Ok(())
+}
+
+#[test]
+fn test_entity_data_none() {
+    let entity = Entity::<Int, Model>::None;
+    assert!(entity.data().is_err());
+}
+
+#[test]
+fn test_entity_same_key() {
+    let model = |id: Option<Int>, label: &str| Model { id: Key(id), label: label.to_owned() };
+    let fetched = EntityInt::from(model(Some(1), "a"));
+
+    assert!(fetched.same_key(&EntityInt::<Model>::Key(Key::new(1))));
+    assert!(fetched.same_key(&model(Some(1), "b")));
+    assert!(!fetched.same_key(&model(Some(2), "a")));
+    assert!(!fetched.same_key(&EntityInt::<Model>::None));
+    assert!(!EntityInt::from(model(None, "a")).same_key(&model(None, "a")));
+}
+
+#[test]
+fn test_key_eq() {
+    use std::collections::HashSet;
+
+    let model = |id: Option<Int>, label: &str| KeyEq(Model { id: Key(id), label: label.to_owned() });
+    assert!(model(Some(1), "a") == model(Some(1), "b"));
+    assert!(model(Some(1), "a") != model(Some(2), "a"));
+    assert!(model(None, "a") == model(None, "b"));
+
+    let set = [model(Some(1), "a"), model(Some(2), "b"), model(Some(1), "c")].into_iter().collect::<HashSet<_>>();
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&model(Some(2), "z")));
+
+    let entities = [EntityInt::Key(Key::new(3)), EntityInt::from(Model { id: Key::new(3), label: "d".to_owned() })];
+    assert!(KeyEq(&entities[0]) == KeyEq(&entities[1]));
+    assert!(KeyEq(&entities[0]) != KeyEq(&EntityInt::<Model>::None));
+    assert_eq!(model(Some(4), "e").into_inner().label, "e");
+}
+
+#[test]
+fn test_entity_fetch_state() -> Result<()> {
+    let model = || Model { id: Key::new(1), label: "a".to_owned() };
+
+    let fetched = Entity::<Int, Model>::from(model()).fetched()?;
+    assert_eq!((fetched.key()?, fetched.label.as_str()), (&Key::new(1), "a"));
+    assert!(Entity::<Int, Model>::from(fetched).is_data());
+    assert!(matches!(Entity::<Int, Model>::Key(Key::new(1)).fetched(), Err(Error::EntityNotFetched)));
+    assert!(matches!(Fetched::try_from(Entity::<Int, Model>::None), Err(Error::EntityEmpty)));
+
+    let unfetched = Unfetched::try_from(Entity::<Int, Model>::Key(Key::new(2)))?;
+    assert_eq!(*unfetched, 2);
+    assert_eq!(Key::from(unfetched), Key::new(2));
+    assert!(matches!(Entity::<Int, Model>::Key(Key(None)).unfetched(), Err(Error::KeyEmpty)));
+    assert!(matches!(Entity::<Int, Model>::from(model()).unfetched(), Err(Error::InvalidState("Data"))));
+
+    let fetched = unfetched.fetch(|&id| Ok(Model { id: Key::new(id), label: "b".to_owned() }))?;
+    assert_eq!(fetched.into_inner().label, "b");
+    assert!(matches!(unfetched.fetch::<Model>(|_| Err(Error::NotFound)), Err(Error::NotFound)));
+
+    let mut entity = Entity::<Int, Model>::Key(Key::new(1));
+    entity.try_transition(Entity::is_key, |_| Entity::from(model()))?;
+    assert!(entity.is_data());
+    assert!(matches!(entity.try_transition(Entity::is_key, |_| Entity::None), Err(Error::InvalidState("Data"))));
+    assert!(entity.is_data());
+    entity.try_transition(|entity| entity.is_data(), |entity| entity.into_key().map_or(Entity::None, Entity::Key))?;
+    assert!(matches!(entity, Entity::Key(Key(Some(1)))));
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  PARENT  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_parent() -> Result<()> {
+    let parent: Parent<Int, Model> = Entity::from(Model { id: Key::new(1), label: "Parent".to_owned() }).into();
+    assert_eq!(parent.key()?, &Key::new(1));
+    assert_eq!(parent.data()?.label, "Parent");
+    assert!(parent.is_data());
+
+    let parent: Parent<Int, Model> = Key::new(2).into();
+    assert!(parent.is_key());
+    assert!(parent.into_entity().data().is_err());
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_resolve_entity_serde() {
+    use crate::serde::{ResolveEntity, ResolveEntities};
+    use ::serde::de::DeserializeSeed;
+
+    let names = |key: &Int| (*key < 3).then(|| format!("name {key}"));
+    let resolve = |json: &str| -> Entity<Int, String> {
+        ResolveEntity::new(&names).deserialize(&mut serde_json::Deserializer::from_str(json)).unwrap()
+    };
+
+    assert_eq!(resolve("1"), Entity::Data(Box::new("name 1".to_owned())));
+    assert_eq!(resolve(r#"{"Key":2}"#), Entity::Data(Box::new("name 2".to_owned())));
+    assert_eq!(resolve("5"), Entity::Key(Key::new(5)));
+    assert_eq!(resolve(r#"{"Data":"given"}"#), Entity::Data(Box::new("given".to_owned())));
+    assert_eq!(resolve("null"), Entity::None);
+
+    let entities: Vec<Entity<Int, String>> = ResolveEntities::new(&names)
+        .deserialize(&mut serde_json::Deserializer::from_str("[1, 7, null]"))
+        .unwrap();
+    assert_eq!(entities, [Entity::Data(Box::new("name 1".to_owned())), Entity::Key(Key::new(7)), Entity::None]);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_from_json_tree() -> Result<()> {
+    use crate::json::from_json_tree;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Status {
+        Open,
+        Closed { reason: String },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Order {
+        id: Key<Int>,
+        customer: Entity<Int, Customer>,
+        lines: Many<Line>,
+        status: Status,
+        note: Option<String>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Customer {
+        id: Key<Int>,
+        name: String,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Line {
+        id: Key<Int>,
+        product: Entity<Int, Customer>,
+    }
+
+    let json = serde_json::json!({
+        "id": 1,
+        "customer": { "id": 2, "name": "Ann" },
+        "lines": [{ "id": 3, "product": 4 }, { "id": 5, "product": null }],
+        "status": { "Closed": { "reason": "paid" } },
+        "note": null,
+    });
+    let order: Order = from_json_tree(json)?;
+    assert_eq!(order.customer, Entity::Data(Box::new(Customer { id: Key::new(2), name: "Ann".to_owned() })));
+    assert_eq!(order.lines.data()?[0].product, Entity::Key(Key::new(4)));
+    assert_eq!(order.lines.data()?[1].product, Entity::None);
+    assert_eq!(order.status, Status::Closed { reason: "paid".to_owned() });
+    assert_eq!(order.note, None);
+
+    let json = serde_json::json!({ "id": 1, "customer": null, "lines": null, "status": "Open", "note": "x" });
+    let order: Order = from_json_tree(json)?;
+    assert_eq!((order.customer, order.lines, order.status), (Entity::None, Many::None, Status::Open));
+
+    let json = serde_json::json!({ "id": 1, "customer": 2, "lines": 3, "status": "Open", "note": null });
+    assert!(matches!(from_json_tree::<Order>(json), Err(Error::Json(_))));
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_format_version() -> Result<()> {
+    use crate::format::{upgrade, FormatVersion, WithVersion};
+    use serde_json::{json, Value};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Note {
+        title: String,
+        tags: Vec<String>,
+    }
+
+    impl FormatVersion for Note {
+        const FORMAT_VERSION: u32 = 3;
+
+        fn migrate(version: u32, mut value: Value) -> Value {
+            match version {
+                1 => value["title"] = value["name"].take(),
+                _ => value["tags"] = json!([]),
+            }
+            value
+        }
+    }
+
+    let note = Note { title: "Todo".to_owned(), tags: vec!["work".to_owned()] };
+    let stored = serde_json::to_string(&WithVersion(&note)).map_err(Error::Json)?;
+    assert_eq!(stored, r#"{"version":3,"entity":{"title":"Todo","tags":["work"]}}"#);
+    let WithVersion(read) = serde_json::from_str::<WithVersion<Note>>(&stored).map_err(Error::Json)?;
+    assert_eq!(read, note);
+
+    let note: Note = upgrade(1, json!({ "name": "Old" }))?;
+    assert_eq!(note, Note { title: "Old".to_owned(), tags: vec![] });
+
+    assert!(matches!(
+        upgrade::<Note>(4, json!({})),
+        Err(Error::UnsupportedFormatVersion { found: 4, supported: 3 })
+    ));
+    assert!(serde_json::from_str::<WithVersion<Note>>(r#"{"version":5,"entity":{}}"#).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_parent_serde() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Order {
+        id: Key<Int>,
+        lines: Many<Line>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Line {
+        id: Key<Int>,
+        order: Parent<Int, Order>,
+    }
+
+    impl Keyed for Order {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.id)
+        }
+    }
+
+    let order = Order { id: Key::new(1), lines: Many::NotFetched };
+    let line = Line { id: Key::new(2), order: Entity::from(order).into() };
+    let json = serde_json::to_string(&line).unwrap();
+    assert_eq!(json, r#"{"id":2,"order":1}"#);
+
+    let line: Line = serde_json::from_str(&json).unwrap();
+    assert_eq!(line.order, Parent(Entity::Key(Key::new(1))));
+
+    let line: Line = serde_json::from_str(r#"{"id":2,"order":null}"#).unwrap();
+    assert!(line.order.is_none());
+}
+
+#[test]
+fn test_weak_entity() -> Result<()> {
+    use std::sync::{Arc, Mutex};
+
+    struct Folder {
+        id: Key<Int>,
+        files: Mutex<Vec<Arc<File>>>,
+    }
+
+    impl Keyed for Folder {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Int>> {
+            Ok(&self.id)
+        }
+    }
+
+    struct File {
+        folder: WeakEntity<Int, Folder>,
+    }
+
+    let folder = Arc::new(Folder { id: Key::new(1), files: Mutex::new(Vec::new()) });
+    let file = Arc::new(File { folder: WeakEntity::new(&folder)? });
+    folder.files.lock().unwrap().push(Arc::clone(&file));
+
+    assert!(file.folder.is_alive());
+    assert_eq!(file.folder.key()?, &Key::new(1));
+    assert!(Arc::ptr_eq(file.folder.upgrade().data()?, &folder));
+    assert_eq!(Arc::strong_count(&folder), 1);
+
+    drop(folder);
+    assert!(!file.folder.is_alive());
+    assert!(file.folder.data().is_none());
+    assert_eq!(file.folder.upgrade().key()?, &Key::new(1));
+    assert_eq!(Arc::strong_count(&file), 1);
+
+    assert!(WeakEntity::<Int, Folder>::default().upgrade().is_none());
+    assert!(WeakEntity::<Int, Folder>::from(Key::new(2)) == WeakEntity::from_key(Key::new(2)));
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_weak_entity_serde() -> Result<()> {
+    let model = std::sync::Arc::new(Model { id: Key::new(3), label: "a".to_owned() });
+    let weak = WeakEntity::new(&model)?;
+    assert_eq!(serde_json::to_string(&weak).unwrap(), "3");
+
+    let weak: WeakEntity<Int, Model> = serde_json::from_str("4").unwrap();
+    assert!(!weak.is_alive());
+    assert_eq!(weak.key()?, &Key::new(4));
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><======================  ENTITY LABEL  ========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_entity_label_from_columns() -> Result<()> {
+    let entity = EntityLabelInt::<Model>::from_columns(Key::new(1), Some("Entity".to_owned()));
+    assert!(entity.is_keylabel());
+    assert_eq!(entity.label()?, "Entity");
+
+    assert!(EntityLabelInt::<Model>::from_columns(Key::new(1), None).is_none());
+    assert!(EntityLabelInt::<Model>::from_columns(Key(None), Some("Entity".to_owned())).is_none());
+    assert!(EntityLabelInt::<Model>::from_columns(Key(None), None).is_none());
+    Ok(())
+}
+
+#[test]
+fn test_entity_label_from_key_label() -> Result<()> {
+    let entity: EntityLabelInt<Model> = (Key::new(1), "Entity".to_owned()).into();
+    assert!(entity.is_keylabel());
+    assert_eq!(entity.key()?, &Key::new(1));
+    assert_eq!(entity.label()?, "Entity");
+    Ok(())
+}
+
+#[test]
+fn test_entity_label_from_nullable_columns() -> Result<()> {
+    let entity: EntityLabelInt<Model> = (Some(1), Some("Entity".to_owned())).into();
+    assert_eq!(entity.key()?, &Key::new(1));
+    assert_eq!(entity.label()?, "Entity");
+
+    let entity: EntityLabelInt<Model> = (Some(2), None).into();
+    assert_eq!(entity.key()?, &Key::new(2));
+    assert_eq!(entity.label()?, "");
+
+    assert!(EntityLabelInt::<Model>::from((None, Some("Entity".to_owned()))).is_none());
+    assert!(EntityLabelInt::<Model>::from((None, None)).is_none());
+    Ok(())
+}
+
+#[test]
+fn test_collect_keys() -> Result<()> {
+    let entities: Vec<Entity<Int, Model>> = vec![
+        Entity::Key(Key::new(2)),
+        Entity::None,
+        Entity::Key(Key::new(1)),
+        Entity::Data(Box::new(Model { id: Key::new(3), label: String::new() })),
+        Entity::Key(Key::new(2)),
+    ];
+    assert_eq!(collect_keys(&entities), vec![&2, &1]);
+
+    struct Parent {
+        id: Key<Int>,
+        children: Many<Model>,
+    }
+
+    impl Keyed for Parent {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Int>> {
+            Ok(&self.id)
+        }
+    }
+
+    let parents = [
+        Parent { id: Key::new(1), children: Many::NotFetched },
+        Parent { id: Key::new(2), children: Many::Data(vec![]) },
+        Parent { id: Key::new(1), children: Many::NotFetched },
+        Parent { id: Key::new(3), children: Many::NotFetched },
+    ];
+    assert_eq!(collect_many_keys(&parents, |parent| &parent.children)?, vec![&1, &3]);
+    Ok(())
+}
+
+#[test]
+fn test_hydrate_entities() -> Result<()> {
+    struct Order {
+        customer: Entity<Int, Model>,
+    }
+
+    let mut orders = [
+        Order { customer: Entity::Key(Key::new(1)) },
+        Order { customer: Entity::Key(Key::new(2)) },
+        Order { customer: Entity::None },
+        Order { customer: Entity::Key(Key::new(1)) },
+        Order { customer: Entity::Key(Key::new(2)) },
+    ];
+    let fetched = vec![Model { id: Key::new(1), label: "first".to_owned() }];
+
+    let missing = hydrate_entities(&mut orders, fetched, |order| &mut order.customer)?;
+    assert_eq!(missing, vec![2]);
+    assert_eq!(orders[0].customer.data()?.label, "first");
+    assert_eq!(orders[3].customer.data()?.label, "first");
+    assert_eq!(orders[1].customer.key()?, &Key::new(2));
+    assert!(orders[2].customer.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_join_by_key() -> Result<()> {
+    struct Line {
+        order: Key<Int>,
+        label: &'static str,
+    }
+
+    struct Order {
+        id: Key<Int>,
+        lines: Many<Line>,
+    }
+
+    impl Keyed for Order {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Int>> {
+            Ok(&self.id)
+        }
+    }
+
+    let order = |id: Option<Int>| Order { id: Key(id), lines: Many::NotFetched };
+    let mut orders = [order(Some(1)), order(Some(2)), order(None)];
+    let line = |order: Option<Int>, label| Line { order: Key(order), label };
+    let lines = vec![line(Some(1), "a"), line(Some(3), "b"), line(Some(1), "c"), line(None, "d")];
+
+    let orphans = join_by_key(&mut orders, lines, |line| &line.order, |order| &mut order.lines)?;
+    assert_eq!(orders[0].lines.data()?.iter().map(|line| line.label).collect::<Vec<_>>(), ["a", "c"]);
+    assert!(orders[1].lines.data()?.is_empty());
+    assert!(orders[2].lines.is_not_fetched());
+    let mut orphans = orphans.iter().map(|line| line.label).collect::<Vec<_>>();
+    orphans.sort_unstable();
+    assert_eq!(orphans, ["b", "d"]);
+    Ok(())
+}
+
+#[test]
+fn test_localized_label() -> Result<()> {
+    use localized::{LocalizedLabel, LocalizedLabels};
+
+    struct Country {
+        id: Key<Int>,
+        name: LocalizedLabel,
+    }
+
+    impl Keyed for Country {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Int>> {
+            Ok(&self.id)
+        }
+    }
+
+    impl Label for Country {
+        type LabelType = LocalizedLabel;
+
+        fn label(&self) -> Result<&LocalizedLabel> {
+            Ok(&self.name)
+        }
+    }
+
+    let name: LocalizedLabel = [("en", "Germany"), ("de", "Deutschland"), ("pt-BR", "Alemanha")].into_iter().collect();
+    assert_eq!((name.len(), name.to_string(), name.normalized()), (3, "Germany".to_owned(), "germany".into()));
+    assert_eq!(name.resolve(&["fr", "de-AT"]), Some("Deutschland"));
+    assert_eq!(name.resolve(&["pt-BR"]), Some("Alemanha"));
+    assert_eq!(name.resolve(&["pt"]), Some("Germany"));
+    assert_eq!(name.clone().with("en", "Deutschland").get("en"), Some("Deutschland"));
+
+    let countries = [Country { id: Key::new(1), name }, Country { id: Key(None), name: LocalizedLabel::new() }];
+    assert_eq!(countries[..1].tags()?, [Tag::new("1", "Germany")]);
+    assert_eq!(countries[0].tag_in(&["de"])?, Tag::new("1", "Deutschland"));
+    assert!(matches!(countries[1].label_in(&["en"]), Err(Error::LabelEmpty)));
+    assert!(matches!(countries[1].tag_in(&["en"]), Err(Error::KeyMissing)));
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_localized_label_serde() {
+    use localized::LocalizedLabel;
+
+    let label: LocalizedLabel = serde_json::from_str(r#"{"pt":"Azul","en":"Blue"}"#).unwrap();
+    assert_eq!((label.default_text(), label.get("en")), (Some("Azul"), Some("Blue")));
+    assert_eq!(serde_json::to_string(&label).unwrap(), r#"{"pt":"Azul","en":"Blue"}"#);
+    assert!(serde_json::from_str::<LocalizedLabel>(r#""Blue""#).is_err());
+}
+
+#[test]
+fn test_entity_label_label() -> Result<()> {
+    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
+    assert_eq!(entity_label.label()?, &String::from("Label"));
+    Ok(())
+}
+
+#[test]
+fn test_entity_label_key() -> Result<()>{
+    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
+    assert_eq!(entity_label.key()?.unwrap(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_entity_label_data() -> Result<()> {
+    let entity_label: EntityLabelInt<Model> = Model { id: Key::new(1), label: "Entity".to_owned() }.into();
+    let result = entity_label.data()?;
+    assert_eq!(entity_label.key()?.unwrap(), 1);
+    assert_eq!(result.key()?.unwrap(), 1);
+    assert_eq!(result.label()?, "Entity");
+    Ok(())
 }
 
 #[test]
@@ -225,56 +1648,2540 @@ fn test_entity_label_data_none() {
 }
 
 #[test]
-fn test_entity_label_missing_data() {
-    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
-    assert!(entity_label.data().is_err());
+fn test_entity_label_missing_data() {
+    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
+    assert!(entity_label.data().is_err());
+}
+
+#[test]
+fn test_entity_label_tag() -> Result<()> {
+    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
+    let tag = entity_label.tag()?;
+    assert_eq!(tag.key, "1");
+    assert_eq!(tag.label, "Label");
+    Ok(())
+}
+
+#[test]
+fn test_entity_label_has_tag() {
+    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
+    assert!(entity_label.has_tag());
+}
+
+#[test]
+fn test_entity_label_eq() {
+    let entity_label_1 = EntityLabelInt::<i32>::KeyLabel(Key::new(1), String::from("Label"));
+    let entity_label_2 = EntityLabelInt::<i32>::KeyLabel(Key::new(1), String::from("Label"));
+    assert_eq!(entity_label_1, entity_label_2);
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><==========================  MANY  ============================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_many_labels() -> Result<()> {
+    let model = |id, label: &str| Model { id: Key::new(id), label: label.to_owned() };
+    let mut many: Many<Model> = vec![model(1, "beta"), model(2, "Alpha"), model(3, "alpha"), model(4, "Gamma")].into();
+
+    assert_eq!(many.labels()?.collect::<Result<Vec<_>>>()?, ["beta", "Alpha", "alpha", "Gamma"]);
+    assert_eq!(many.find_by_label("ALPHA")?.map(|model| model.id.clone()), Some(Key::new(2)));
+
+    many.sort_by_label()?;
+    assert_eq!(many.labels()?.collect::<Result<Vec<_>>>()?, ["Alpha", "Gamma", "alpha", "beta"]);
+    many.sort_by_normalized_label()?;
+    assert_eq!(many.labels()?.collect::<Result<Vec<_>>>()?, ["Alpha", "alpha", "beta", "Gamma"]);
+
+    let mut labels: Many<EntityLabel<Int, Model, String>> = vec![EntityLabel::KeyLabel(Key::new(1), "b".to_owned()), EntityLabel::None].into();
+    labels.sort_by_label()?;
+    assert!(labels[0].is_none());
+    assert!(matches!(Many::<Model>::NotFetched.sort_by_label(), Err(Error::ManyNotFetched)));
+    assert!(Many::<Model>::None.labels().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_many_statistics() -> Result<()> {
+    let model = |id, label: &str| Model { id: Key::new(id), label: label.to_owned() };
+    let many: Many<Model> = vec![model(1, "three"), model(2, "one"), model(3, "five"), model(4, "two")].into();
+
+    assert_eq!(many.sum_by(|model| model.label.len())?, 15);
+    assert_eq!(many.min_by_key_field(|model| model.label.len())?.map(|model| model.id.clone()), Some(Key::new(2)));
+    assert_eq!(many.max_by_key_field(|model| model.label.len())?.map(|model| model.id.clone()), Some(Key::new(1)));
+    assert_eq!(many.count_where(|model| model.label.contains('o'))?, 2);
+
+    let empty = Many::<Model>::Data(vec![]);
+    assert_eq!(empty.sum_by(|model| model.label.len())?, 0);
+    assert!(empty.min_by_key_field(|model| model.id.clone())?.is_none());
+    assert!(matches!(Many::<Model>::NotFetched.sum_by(|_| 1), Err(Error::ManyNotFetched)));
+    assert!(matches!(Many::<Model>::None.count_where(|_| true), Err(Error::ManyEmpty)));
+    Ok(())
+}
+
+#[test]
+fn test_shared_many() -> Result<()> {
+    let model = |id, label: &str| Model { id: Key::new(id), label: label.to_owned() };
+    let shared = Many::from(vec![model(1, "one"), model(2, "two")]).into_shared();
+    let clone = shared.clone();
+    assert!(std::ptr::eq(shared.data()?, clone.data()?));
+
+    let worker = std::thread::spawn(move || clone.data().map(|data| data.iter().map(|model| model.label.clone()).collect::<Vec<_>>()));
+    assert_eq!(worker.join().unwrap()?, ["one", "two"]);
+
+    assert_eq!(shared[1].label, "two");
+    assert_eq!(shared.len(), Some(2));
+    assert_eq!(shared.to_many().labels()?.collect::<Result<Vec<_>>>()?, ["one", "two"]);
+
+    let not_fetched: SharedMany<Model> = Many::NotFetched.into();
+    assert!(not_fetched.is_not_fetched());
+    assert!(matches!(not_fetched.data(), Err(Error::ManyNotFetched)));
+    assert!(matches!(SharedMany::<Model>::None.data(), Err(Error::ManyEmpty)));
+    assert!(SharedMany::<Model>::default().to_many().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_entity_merge() -> Result<()> {
+    let model = |id, label: &str| Model { id: Key::new(id), label: label.to_owned() };
+
+    let mut entity = Entity::<Int, Model>::None;
+    entity.merge(Entity::Key(Key::new(1)))?;
+    assert_eq!(entity.key()?, &Key::new(1));
+
+    entity.merge(Entity::None)?;
+    entity.merge(model(1, "old").into())?;
+    assert_eq!(entity.data()?.label, "old");
+
+    entity.merge(Entity::Key(Key::new(1)))?;
+    assert_eq!(entity.data()?.label, "old");
+    entity.merge(model(1, "new").into())?;
+    assert_eq!(entity.data()?.label, "new");
+
+    assert!(matches!(entity.merge(Entity::Key(Key::new(2))), Err(Error::KeyMismatch)));
+    assert!(matches!(entity.merge(model(2, "other").into()), Err(Error::KeyMismatch)));
+    assert_eq!(entity.data()?.label, "new");
+    Ok(())
+}
+
+#[test]
+fn test_entity_ensure_fetched() -> Result<()> {
+    let mut loads = 0;
+    let mut load = |key: &Int| {
+        loads += 1;
+        Ok(Model { id: Key::new(*key), label: "loaded".to_owned() })
+    };
+
+    let mut entity = Entity::<Int, Model>::Key(Key::new(3));
+    assert_eq!(entity.ensure_fetched(&mut load)?.label, "loaded");
+    assert_eq!(entity.ensure_fetched(&mut load)?.id, Key::new(3));
+    assert!(matches!(Entity::<Int, Model>::None.ensure_fetched(&mut load), Err(Error::EntityEmpty)));
+    assert!(matches!(Entity::<Int, Model>::Key(Key(None)).ensure_fetched(&mut load), Err(Error::KeyEmpty)));
+    assert_eq!(loads, 1);
+
+    let mut many = Many::<Model>::NotFetched;
+    assert_eq!(many.ensure_fetched(|| Ok(vec![Model::default()]))?.len(), 1);
+    assert!(many.ensure_fetched(|| Err(Error::NotFound)).is_ok());
+    assert!(matches!(Many::<Model>::None.ensure_fetched(|| Ok(vec![])), Err(Error::ManyEmpty)));
+    assert!(matches!(Many::<Model>::NotFetched.ensure_fetched(|| Err(Error::NotFound)), Err(Error::NotFound)));
+    Ok(())
+}
+
+#[test]
+fn test_many_fetch_limited() -> Result<()> {
+    let models = |count: usize| (0..count).map(|_| Model::default()).collect::<Vec<_>>();
+
+    let mut many = Many::<Model>::NotFetched;
+    assert!(matches!(many.fetch_limited(|limit| Ok(models(limit)), 2), Err(Error::TooManyRows { limit: 2 })));
+    assert!(many.is_not_fetched());
+    assert_eq!(many.fetch_limited(|limit| { assert_eq!(limit, 3); Ok(models(2)) }, 2)?.len(), 2);
+    assert_eq!(many.fetch_limited(|_| Ok(models(5)), 2)?.len(), 2);
+    assert!(Many::<Model>::NotFetched.fetch_limited(|limit| Ok(models(limit.min(10))), usize::MAX).is_ok());
+
+    limits::set_max_many_rows(1000);
+    assert_eq!(limits::max_many_rows(), Some(1000));
+    let mut many = Many::<Model>::NotFetched;
+    let result = many.ensure_fetched(|| Ok(models(1001))).map(|data| data.len());
+    let limited = Many::<Model>::NotFetched.fetch_limited(|limit| Ok(models(limit)), 5000).map(|data| data.len());
+    limits::clear_max_many_rows();
+    assert!(matches!(result, Err(Error::TooManyRows { limit: 1000 })));
+    assert!(matches!(limited, Err(Error::TooManyRows { limit: 1000 })));
+    assert!(many.is_not_fetched());
+    assert_eq!(limits::max_many_rows(), None);
+    assert_eq!(many.ensure_fetched(|| Ok(models(1001)))?.len(), 1001);
+    Ok(())
+}
+
+#[test]
+fn test_many_merge_by_key() -> Result<()> {
+    let model = |id, label: &str| Model { id: Key::new(id), label: label.to_owned() };
+
+    let mut many = Many::NotFetched;
+    many.merge_by_key(Many::None)?;
+    assert!(many.is_not_fetched());
+    many.merge_by_key(vec![model(1, "a"), model(2, "b")].into())?;
+
+    many.merge_by_key(vec![model(2, "B"), model(3, "c"), model(3, "C")].into())?;
+    let labels = many.data()?.iter().map(|model| model.label.as_str()).collect::<Vec<_>>();
+    assert_eq!(labels, vec!["a", "B", "C"]);
+    Ok(())
+}
+
+#[test]
+fn test_many_not_fetched() {
+    let mut many = Many::<Model>::NotFetched;
+    assert!(many.data().is_err());
+    assert!(many.data_mut().is_err());
+}
+
+#[test]
+fn test_many_none() {
+    let mut many = Many::<Model>::None;
+    assert!(many.data().is_err());
+    assert!(many.data_mut().is_err());
+}
+
+#[test]
+fn test_many_data() -> Result<()> {
+    let mut data = vec![1, 2, 3];
+    let mut many = Many::Data(data.clone());
+    assert_eq!(many.data()?, &data);
+    assert_eq!(many.data_mut()?, &mut data);
+    Ok(())
+}
+
+#[test]
+fn test_many_tags() -> Result<()> {
+    let many = Many::Data(vec![
+        Model { id: Key::new(1), label: "One".to_owned() },
+        Model { id: Key::new(2), label: "Two".to_owned() },
+    ]);
+    let tags = many.tags()?;
+    assert_eq!(tags, vec![
+        Tag::new("1", "One"),
+        Tag::new("2", "Two"),
+    ]);
+    assert_eq!(many.data()?.tags()?, tags);
+    assert!(Many::<Model>::NotFetched.tags().is_err());
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=======================  SNOWFLAKE  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_snowflake_node_id() {
+    use snowflake::{Snowflake, MAX_NODE_ID};
+
+    assert!(Snowflake::new(MAX_NODE_ID).is_ok());
+    assert!(matches!(Snowflake::new(MAX_NODE_ID + 1), Err(Error::InvalidNodeId(_))));
+}
+
+#[test]
+fn test_snowflake_decompose() {
+    use snowflake::Snowflake;
+
+    let generator = Snowflake::new(42).unwrap();
+    let id = generator.next_id();
+    assert!(id > 0);
+    let parts = generator.decompose(id);
+    assert_eq!(parts.node_id, 42);
+    let elapsed = std::time::SystemTime::now().duration_since(generator.timestamp(id)).unwrap();
+    assert!(elapsed.as_secs() < 60);
+}
+
+#[test]
+fn test_snowflake_unique_and_ordered() {
+    use snowflake::Snowflake;
+
+    let generator = std::sync::Arc::new(Snowflake::new(1).unwrap());
+    let handles = (0..4)
+        .map(|_| {
+            let generator = generator.clone();
+            std::thread::spawn(move || (0..5000).map(|_| generator.next_key()).collect::<Vec<_>>())
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        let keys = handle.join().unwrap();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    let mut ids = (0..10_000).map(|_| generator.next_id()).collect::<Vec<_>>();
+    let len = ids.len();
+    ids.dedup();
+    assert_eq!(ids.len(), len);
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><========================  VALIDATE  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_validation_errors() {
+    use validate::{FieldErrorKind, ValidationErrors};
+
+    assert!(ValidationErrors::new().into_result().is_ok());
+
+    let mut errors = ValidationErrors::new();
+    errors.add("name", FieldErrorKind::Length { min: Some(1), max: None, actual: 0 });
+    errors.add("org", FieldErrorKind::Required);
+    assert_eq!(errors.to_string(), "name: length 0 is out of bounds [1, ..]; org: is required");
+    assert_eq!(errors.field("org").count(), 1);
+
+    let error = Error::from(errors.into_result().unwrap_err());
+    assert_eq!(error.to_string(), "validation failed: name: length 0 is out of bounds [1, ..]; org: is required");
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  VERSION  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+struct VersionedModel {
+    version: version::Version,
+}
+
+impl version::Versioned for VersionedModel {
+    fn version(&self) -> version::Version {
+        self.version
+    }
+
+    fn set_version(&mut self, version: version::Version) {
+        self.version = version;
+    }
+}
+
+#[test]
+fn test_versioned() {
+    use version::{Version, Versioned};
+
+    let mut model = VersionedModel { version: Version(1) };
+    assert!(model.check_version(Version(1)).is_ok());
+    assert_eq!(model.bump_version(), Version(2));
+    assert!(matches!(
+        model.check_version(Version(3)),
+        Err(Error::StaleVersion { expected: Version(2), found: Version(3) })
+    ));
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_update_versioned() -> Result<()> {
+    use version::{update_versioned, Version, Versioned};
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch("CREATE TABLE model (id INTEGER PRIMARY KEY, version INTEGER, label TEXT);
+                        INSERT INTO model VALUES (1, 1, 'Label');")?;
+
+    let key = Key::<Int>::new(1);
+    let mut model = VersionedModel { version: Version(1) };
+    update_versioned(&conn, &mut model, "model", "id", &key, "version", &[("label", &"Changed")])?;
+    assert_eq!(model.version(), Version(2));
+    let (version, label): (Version, String) = conn.query_row("SELECT version, label FROM model", [], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    assert_eq!((version, label.as_str()), (Version(2), "Changed"));
+
+    let mut stale = VersionedModel { version: Version(1) };
+    let result = update_versioned(&conn, &mut stale, "model", "id", &key, "version", &[("label", &"Stale")]);
+    assert!(matches!(result, Err(Error::StaleVersion { expected: Version(1), found: Version(2) })));
+    assert_eq!(stale.version(), Version(1));
+
+    let mut missing = VersionedModel { version: Version(1) };
+    let result = update_versioned(&conn, &mut missing, "model", "id", &Key::<Int>::new(2), "version", &[]);
+    assert!(matches!(result, Err(Error::NotFound)));
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  POSITION  =========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[derive(Debug)]
+struct Step {
+    label: &'static str,
+    position: i64,
+}
+
+impl position::Positioned for Step {
+    const POSITION_COLUMN: &'static str = "position";
+
+    fn position(&self) -> i64 {
+        self.position
+    }
+
+    fn set_position(&mut self, position: i64) {
+        self.position = position;
+    }
+}
+
+fn steps(many: &Many<Step>) -> Vec<(&'static str, i64)> {
+    many.data().unwrap().iter().map(|step| (step.label, step.position)).collect()
+}
+
+#[test]
+fn test_positioned_many() -> Result<()> {
+    use position::Positioned;
+
+    let step = |label, position| Step { label, position };
+    let mut many: Many<Step> = vec![step("c", 7), step("a", 2), step("b", 5)].into();
+
+    many.sort_by_position()?;
+    assert_eq!(steps(&many), [("a", 2), ("b", 5), ("c", 7)]);
+    assert_eq!(many.reindex()?, [0, 1, 2]);
+    assert_eq!(steps(&many), [("a", 0), ("b", 1), ("c", 2)]);
+    assert!(many.reindex()?.is_empty());
+
+    assert_eq!(many.move_down(0)?, [0, 1]);
+    assert_eq!(steps(&many), [("b", 0), ("a", 1), ("c", 2)]);
+    assert_eq!(many.move_up(2)?, [1, 2]);
+    assert_eq!(steps(&many), [("b", 0), ("c", 1), ("a", 2)]);
+    assert!(many.move_up(0)?.is_empty());
+    assert!(many.move_down(2)?.is_empty());
+
+    assert_eq!(Step::order_by().to_string(), "position ASC");
+    assert!(matches!(Many::<Step>::NotFetched.reindex(), Err(Error::ManyNotFetched)));
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "cannot move up the entity at 3")]
+fn test_positioned_many_out_of_bounds() {
+    let mut many: Many<Step> = vec![Step { label: "a", position: 0 }].into();
+    let _ = many.move_up(3);
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><==========================  HOOKS  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_entity_hooks() {
+    use hooks::EntityHooks;
+
+    #[derive(Debug, PartialEq)]
+    struct Country {
+        code: String,
+        locked: bool,
+    }
+
+    impl EntityHooks for Country {
+        fn before_save(&mut self) -> Result<()> {
+            self.code = self.code.trim().to_uppercase();
+            Ok(())
+        }
+
+        fn before_delete(&self) -> Result<()> {
+            if self.locked {
+                return Err(Error::Aborted(format!("{} is locked", self.code)));
+            }
+            Ok(())
+        }
+    }
+
+    let mut entity: Entity<Int, Country> = Entity::Data(Box::new(Country { code: " us ".to_owned(), locked: false }));
+    entity.before_save().unwrap();
+    assert_eq!(entity.data().unwrap().code, "US");
+    assert!(entity.after_fetch().is_ok());
+
+    let mut many = Many::Data(vec![
+        Country { code: "br".to_owned(), locked: false },
+        Country { code: "pt".to_owned(), locked: true },
+    ]);
+    many.before_save().unwrap();
+    assert_eq!(many.data().unwrap()[0].code, "BR");
+    assert_eq!(many.before_delete().unwrap_err().to_string(), "operation aborted: PT is locked");
+
+    let mut not_fetched = EntityLabelInt::<Country>::KeyLabel(Key::new(1), "US".to_owned());
+    assert!(not_fetched.before_save().is_ok());
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  INTERN  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[cfg(feature = "intern")]
+#[test]
+fn test_interned_label() -> Result<()> {
+    use intern::InternedStr;
+
+    struct Country {
+        id: Key<Int>,
+        name: InternedStr,
+    }
+
+    impl Keyed for Country {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.id)
+        }
+    }
+
+    impl Label for Country {
+        type LabelType = InternedStr;
+
+        fn label(&self) -> Result<&Self::LabelType> {
+            Ok(&self.name)
+        }
+    }
+
+    let country: EntityLabel<Int, Country, InternedStr> = Country { id: Key::new(0), name: "Shared label".into() }.into();
+    let labels = (1..3)
+        .map(|i| EntityLabel::<Int, Country, InternedStr>::KeyLabel(Key::new(i), "Shared label".into()))
+        .collect::<Vec<_>>();
+
+    assert!(InternedStr::ptr_eq(country.label()?, labels[1].label()?));
+    assert_eq!(labels[0].label()?, "Shared label");
+    assert_eq!(labels[0].tag()?, Tag::new("1", "Shared label"));
+    Ok(())
+}
+
+#[cfg(feature = "intern")]
+#[test]
+fn test_interned_purge() {
+    use intern::InternedStr;
+
+    let unique = InternedStr::new("test_interned_purge label");
+    InternedStr::purge();
+    assert!(InternedStr::ptr_eq(&unique, &InternedStr::new("test_interned_purge label")));
+    assert!(InternedStr::pool_size() > 0);
+}
+
+#[cfg(all(feature = "intern", feature = "serde"))]
+#[test]
+fn test_interned_serde() {
+    use intern::InternedStr;
+
+    let label: InternedStr = serde_json::from_str(r#""Serde label""#).unwrap();
+    assert!(InternedStr::ptr_eq(&label, &InternedStr::new("Serde label")));
+    assert_eq!(serde_json::to_string(&label).unwrap(), r#""Serde label""#);
+}
+
+#[test]
+fn test_tag_static_labels() -> Result<()> {
+    #[derive(Clone, Copy)]
+    enum Currency {
+        Usd,
+        Eur,
+    }
+
+    struct CurrencyEntity {
+        id: Key<&'static str>,
+        name: &'static str,
+    }
+
+    impl Keyed for CurrencyEntity {
+        type KeyType = &'static str;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.id)
+        }
+    }
+
+    impl Label for CurrencyEntity {
+        type LabelType = &'static str;
+
+        fn label(&self) -> Result<&Self::LabelType> {
+            Ok(&self.name)
+        }
+
+        fn tag_label(&self) -> Result<Cow<'static, str>> {
+            Ok(Cow::Borrowed(self.name))
+        }
+    }
+
+    impl From<Currency> for CurrencyEntity {
+        fn from(currency: Currency) -> Self {
+            match currency {
+                Currency::Usd => Self { id: Key::new("USD"), name: "US Dollar" },
+                Currency::Eur => Self { id: Key::new("EUR"), name: "Euro" },
+            }
+        }
+    }
+
+    let tag = CurrencyEntity::from(Currency::Usd).tag()?;
+    assert_eq!(tag.key, "USD");
+    assert!(matches!(tag.label, Cow::Borrowed("US Dollar")));
+
+    let entity_label = EntityLabel::<&'static str, CurrencyEntity, &'static str>::Data(Box::new(Currency::Eur.into()));
+    assert!(matches!(entity_label.tag()?.label, Cow::Borrowed("Euro")));
+    let entity_label = EntityLabel::<&'static str, CurrencyEntity, &'static str>::KeyLabel(Key::new("EUR"), "Euro");
+    assert_eq!(entity_label.tag()?, CurrencyEntity::from(Currency::Eur).tag()?);
+
+    let tag = Model { id: Key::new(1), label: "Owned".to_owned() }.tag()?;
+    assert!(matches!(tag.label, Cow::Owned(_)));
+    Ok(())
+}
+
+#[test]
+fn test_tag_borrowed_labels() -> Result<()> {
+    struct View<'a> {
+        id: Key<&'a str>,
+        name: &'a str,
+    }
+
+    impl<'a> Keyed for View<'a> {
+        type KeyType = &'a str;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.id)
+        }
+    }
+
+    impl<'a> Label for View<'a> {
+        type LabelType = &'a str;
+
+        fn label(&self) -> Result<&Self::LabelType> {
+            Ok(&self.name)
+        }
+    }
+
+    let (code, name) = ("BR".to_owned(), "Brazil".to_owned());
+    let view = View { id: Key::new(&code), name: &name };
+    assert_eq!(view.tag()?, Tag::new("BR", "Brazil"));
+    let mut tag = Tag::default();
+    view.write_tag(&mut tag)?;
+    assert_eq!(tag, Tag::new("BR", "Brazil"));
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><==========================  LINK  ============================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_link() -> Result<()> {
+    let link = Link::new(1, "admin");
+    assert_eq!(link.left(), Some(&1));
+    assert_eq!(link.right(), Some(&"admin"));
+    assert_eq!(link.key()?.to_string(), "1:admin");
+
+    let links: Many<Link<Int, Int, bool>> = vec![
+        Link::with_payload(1, 10, true),
+        Link::with_payload(1, 11, false),
+        Link::with_payload(2, 10, true),
+    ].into();
+    assert_eq!(links.left_keys()?.into_iter().collect::<Vec<_>>(), vec![&1, &2]);
+    assert_eq!(links.right_keys()?.into_iter().collect::<Vec<_>>(), vec![&10, &11]);
+    assert!(Many::<Link<Int, Int>>::NotFetched.right_keys().is_err());
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><===========================  WIRE  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[cfg(feature = "wire")]
+#[test]
+fn test_wire() -> Result<()> {
+    use crate::wire::{from_bytes, to_bytes};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Order {
+        id: Key<Int>,
+        customer: EntityLabel<Int, Customer, String>,
+        parent: Parent<Int, Order>,
+        lines: Many<Entity<Int, Order>>,
+        total: f64,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Customer {
+        id: Key<i64>,
+        name: String,
+    }
+
+    impl Keyed for Order {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.id)
+        }
+    }
+
+    let order = Order {
+        id: Key::new(1),
+        customer: EntityLabel::KeyLabel(Key::new(2), "Ann".to_owned()),
+        parent: Parent::from(Key::new(3)),
+        lines: vec![Entity::Key(Key::new(4)), Entity::None].into(),
+        total: 1.5,
+    };
+    let bytes = to_bytes(&order)?;
+    assert_eq!(&bytes[..4], b"DBW\x01");
+    assert_eq!(from_bytes::<Order>(&bytes)?, order);
+    assert_eq!(to_bytes(&order)?, bytes);
+
+    let order = Order {
+        id: Key(None),
+        customer: EntityLabel::Data(Box::new(Customer { id: Key::new(-2), name: "Bo".to_owned() })),
+        parent: Parent::default(),
+        lines: Many::NotFetched,
+        total: 0.0,
+    };
+    assert_eq!(from_bytes::<Order>(&to_bytes(&order)?)?, order);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Event {
+        Moved {
+            #[serde(skip_serializing_if = "Option::is_none", default)]
+            from: Option<u8>,
+            to: u8,
+        },
+    }
+
+    let bytes = to_bytes(&Event::Moved { from: None, to: 2 })?;
+    assert_eq!(bytes, b"DBW\x01\x00\x01\x02");
+    assert_eq!(from_bytes::<Event>(&bytes)?, Event::Moved { from: None, to: 2 });
+    assert_eq!(from_bytes::<Event>(&to_bytes(&Event::Moved { from: Some(1), to: 2 })?)?, Event::Moved { from: Some(1), to: 2 });
+
+    assert_eq!(to_bytes(&Entity::<i64, String>::Key(Key::new(-1)))?, b"DBW\x01\x00\x01\x01");
+    assert_eq!(to_bytes(&Many::<Entity<i64, String>>::NotFetched)?, b"DBW\x01\x01");
+    assert_eq!(to_bytes(&Key::<u64>::new(300))?, b"DBW\x01\x01\xac\x02");
+
+    assert!(matches!(from_bytes::<Key<u64>>(b"DBW\x02\x00"), Err(Error::UnsupportedFormatVersion { found: 2, supported: 1 })));
+    assert!(matches!(from_bytes::<Key<u64>>(b"XYZ\x01\x00"), Err(Error::Wire(_))));
+    assert!(matches!(from_bytes::<Key<u64>>(b"DBW\x01\x00\x00"), Err(Error::Wire(_))));
+    assert!(matches!(from_bytes::<Entity<u64, String>>(b"DBW\x01\x03"), Err(Error::Wire(_))));
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><===========================  SQL  ============================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_sql_filter() {
+    use crate::sql::{Field, Value};
+
+    const ID: Field<Model, Key<Int>> = Field::new("id");
+    const LABEL: Field<Model, Option<String>> = Field::new("label");
+
+    let filter = ID.ge(Key::new(2)) & !LABEL.like("a%");
+    assert_eq!(filter.sql(), "(id >= ?) AND (NOT (label LIKE ?))");
+    assert_eq!(filter.params(), &[Value::Integer(2), Value::Text("a%".to_owned())]);
+
+    let filter = LABEL.eq(None::<String>) | LABEL.ne("x".to_owned());
+    assert_eq!(filter.sql(), "(label IS NULL) OR (label <> ?)");
+    assert_eq!(filter.params(), &[Value::Text("x".to_owned())]);
+
+    let filter = ID.is_in([1, 2, 3].map(Key::new));
+    assert_eq!(filter.sql(), "id IN (?, ?, ?)");
+    assert_eq!(filter.params().len(), 3);
+    assert_eq!(ID.is_in(Vec::<Key<Int>>::new()).sql(), "1 = 0");
+}
+
+#[test]
+fn test_sql_value_from_integer() {
+    use crate::sql::Value;
+
+    assert_eq!(Value::from(-3i8), Value::Integer(-3));
+    assert_eq!(Value::from(u32::MAX), Value::Integer(u32::MAX.into()));
+    assert_eq!(Value::from(true), Value::Integer(1));
+    assert_eq!(Value::from(i64::MAX as u64), Value::Integer(i64::MAX));
+    assert_eq!(Value::from(u64::MAX), Value::Unsigned(u64::MAX));
+    assert_eq!(Value::from(usize::MAX), Value::Unsigned(usize::MAX as u64));
+    assert_eq!(Value::from(u64::MAX).sql_cmp(&Value::Integer(i64::MAX)), core::cmp::Ordering::Greater);
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_sql_value_unsigned_to_sql() -> Result<()> {
+    use crate::sql::Value;
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    let bound: i64 = conn.query_row("SELECT ?", [Value::from(i64::MAX as u64)], |row| row.get(0))?;
+    assert_eq!(bound, i64::MAX);
+    let err = conn.query_row("SELECT ?", [Value::from(u64::MAX)], |row| row.get::<_, i64>(0)).unwrap_err();
+    assert!(matches!(err, rusqlite::Error::ToSqlConversionFailure(err) if err.to_string() == "the integer 18446744073709551615 is too large for SQLite"));
+    Ok(())
+}
+
+#[test]
+fn test_sql_quote_ident() -> Result<()> {
+    use crate::schema::{ColumnMeta, EntityMeta, SchemaChange, SqlType};
+    use crate::sql::{quote_ident, quote_list, Field};
+
+    assert_eq!(quote_ident("_id2"), "_id2");
+    assert_eq!(quote_ident("Select"), r#""Select""#);
+    assert_eq!(quote_ident("2nd"), r#""2nd""#);
+    assert_eq!(quote_ident(""), r#""""#);
+    assert_eq!(quote_list(&["id", "group"]), r#"id, "group""#);
+
+    const GROUP: Field<Model, String> = Field::new("group");
+    assert_eq!(GROUP.eq("a".to_owned()).sql(), r#""group" = ?"#);
+    assert_eq!(GROUP.desc().to_sql(), r#" ORDER BY "group" DESC"#);
+
+    let column = |name: &'static str, primary_key| ColumnMeta { name: name.into(), sql_type: SqlType::Text, nullable: true, primary_key, references: None, on_delete: None };
+    let meta = EntityMeta {
+        table: "order".into(),
+        columns: vec![column("id", true), column(r#"na"me"#, false)],
+        many: vec![],
+        unique: vec![vec![r#"na"me"#.into()]],
+        id_prefix: None,
+    };
+    let create = meta.create_table_sql();
+    assert_eq!(create, r#"CREATE TABLE "order" (id TEXT PRIMARY KEY, "na""me" TEXT, UNIQUE ("na""me"))"#);
+    let add = SchemaChange::AddColumn { table: "order".into(), column: column("from", false) }.to_sql();
+    assert_eq!(add, r#"ALTER TABLE "order" ADD COLUMN "from" TEXT"#);
+
+    #[cfg(feature = "rusqlite")]
+    {
+        let conn = rusqlite::Connection::open_in_memory()?;
+        conn.execute_batch(&create)?;
+        conn.execute_batch(&add)?;
+        conn.execute_batch(&SchemaChange::DropTable("order".into()).to_sql())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_sql_order_by() -> Result<()> {
+    use crate::sql::{Columns, Field, OrderBy, Value};
+
+    #[derive(Debug)]
+    struct Row(Option<i64>, &'static str);
+
+    impl Columns for Row {
+        fn column_value(&self, column: &str) -> Option<Value> {
+            match column {
+                "score" => Some(self.0.into()),
+                "name" => Some(self.1.into()),
+                _ => None,
+            }
+        }
+    }
+
+    const SCORE: Field<Row, Option<i64>> = Field::new("score");
+
+    let order = SCORE.desc().nulls_last().then(OrderBy::new().asc("name"));
+    assert_eq!(order.to_sql(), " ORDER BY score DESC NULLS LAST, name ASC");
+    assert_eq!(OrderBy::new().to_sql(), "");
+
+    let mut rows: Many<Row> = vec![Row(None, "a"), Row(Some(1), "b"), Row(Some(3), "d"), Row(Some(3), "c")].into();
+    rows.sort_by_order(&order)?;
+    assert_eq!(rows.data()?.iter().map(|row| row.1).collect::<String>(), "cdba");
+
+    rows.sort_by_order(&SCORE.asc())?;
+    assert_eq!(rows.data()?.iter().map(|row| row.1).collect::<String>(), "abcd");
+
+    assert!(Many::<Row>::NotFetched.sort_by_order(&order).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_snapshot() {
+    use crate::snapshot::{Change, Changeset, TakeSnapshot};
+    use crate::sql::{Columns, Value};
+
+    #[derive(Clone, PartialEq, Debug)]
+    struct Row(i64, &'static str);
+
+    impl Columns for Row {
+        fn column_value(&self, column: &str) -> Option<Value> {
+            match column {
+                "score" => Some(self.0.into()),
+                "name" => Some(self.1.into()),
+                _ => None,
+            }
+        }
+
+        fn columns(&self) -> &'static [&'static str] {
+            &["score", "name"]
+        }
+    }
+
+    let mut row = Row(1, "a");
+    let snapshot = row.snapshot();
+    assert!(!snapshot.is_changed(&row));
+    assert!(snapshot.diff(&row).is_empty());
+
+    row.1 = "b";
+    assert!(snapshot.is_changed(&row));
+    assert_eq!(snapshot.diff(&row), Changeset::from(vec![Change { column: "name", old: "a".into(), new: "b".into() }]));
+
+    snapshot.revert(&mut row);
+    assert_eq!(row, Row(1, "a"));
+    assert_eq!(snapshot.into_inner(), row);
+}
+
+#[test]
+fn test_changeset() {
+    use crate::changeset::Changeset;
+    use crate::sql::Value;
+
+    let mut changeset = Changeset::new();
+    assert_eq!(changeset.to_update_sql("model", "id"), None);
+
+    changeset.push("label", "a", "b");
+    changeset.push("rank", 1, 2);
+    changeset.push("label", "b", "c");
+    assert_eq!(changeset.len(), 2);
+    assert_eq!(changeset.get("label").map(|change| (&change.old, &change.new)), Some((&"a".into(), &"c".into())));
+    assert_eq!(changeset.columns().collect::<Vec<_>>(), ["label", "rank"]);
+    assert_eq!(changeset.to_update_sql("model", "id").unwrap(), "UPDATE model SET label = ?, rank = ? WHERE id = ?");
+    assert_eq!(changeset.params(Key::<Int>::new(5)), [Value::from("c"), Value::Integer(2), Value::Integer(5)]);
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_changeset_update() -> Result<()> {
+    use crate::changeset::Changeset;
+
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch("CREATE TABLE model (id INTEGER PRIMARY KEY, version INTEGER, label TEXT);
+                        INSERT INTO model VALUES (1, 1, 'a');")?;
+
+    let mut changeset = Changeset::new();
+    changeset.update(&conn, "model", "id", 2)?;
+    changeset.push("label", "a", "b");
+    changeset.update(&conn, "model", "id", 1)?;
+    let label: String = conn.query_row("SELECT label FROM model", [], |row| row.get(0))?;
+    assert_eq!(label, "b");
+    assert!(matches!(changeset.update(&conn, "model", "id", 2), Err(Error::NotFound)));
+
+    let mut model = VersionedModel { version: version::Version(1) };
+    version::update_versioned(&conn, &mut model, "model", "id", &1, "version", &changeset.assignments())?;
+    assert_eq!(model.version, version::Version(2));
+    Ok(())
+}
+
+#[test]
+fn test_edit_history() {
+    use crate::snapshot::EditHistory;
+
+    let mut model = Model { id: Key::new(1), label: "a".to_owned() };
+    let mut history = EditHistory::new(2);
+    assert!(!history.undo(&mut model));
+
+    for label in ["b", "c", "d"] {
+        history.edit(&mut model, |model| model.label = label.to_owned());
+    }
+    assert!(history.undo(&mut model));
+    assert!(history.undo(&mut model));
+    assert_eq!(model.label, "b");
+    assert!(!history.can_undo());
+
+    assert!(history.redo(&mut model));
+    assert_eq!(model.label, "c");
+    history.record(&model);
+    model.label = "e".to_owned();
+    assert!(!history.can_redo());
+
+    assert!(history.undo(&mut model));
+    assert_eq!(model.label, "c");
+    history.clear();
+    assert!(!history.can_undo());
+}
+
+#[test]
+fn test_schema_migration() {
+    use crate::schema::{ColumnMeta, EntityMeta, SchemaChange, SchemaSet, SqlType};
+
+    fn column(name: &'static str, sql_type: SqlType, nullable: bool) -> ColumnMeta {
+        ColumnMeta { name: name.into(), sql_type, nullable, primary_key: name == "id", references: None, on_delete: None }
+    }
+
+    let mut previous = SchemaSet::new();
+    previous.insert(EntityMeta { table: "users".into(), columns: vec![column("id", SqlType::Integer, false), column("nick", SqlType::Text, true)], many: vec![], unique: vec![], id_prefix: None });
+    previous.insert(EntityMeta { table: "legacy".into(), columns: vec![column("id", SqlType::Integer, false)], many: vec![], unique: vec![], id_prefix: None });
+
+    let mut current = SchemaSet::new();
+    current.insert(EntityMeta {
+        table: "users".into(),
+        columns: vec![column("id", SqlType::Integer, false), column("name", SqlType::Text, false), column("age", SqlType::Integer, true)],
+        many: vec![],
+        unique: vec![],
+        id_prefix: None,
+    });
+    current.insert(EntityMeta { table: "posts".into(), columns: vec![column("id", SqlType::Integer, false), column("body", SqlType::Text, false)], many: vec![], unique: vec![], id_prefix: None });
+
+    assert_eq!(current.diff(&current), vec![]);
+    assert!(matches!(current.diff(&previous)[0], SchemaChange::CreateTable(_)));
+    assert_eq!(current.migration_sql(&previous), vec![
+        "CREATE TABLE posts (id INTEGER PRIMARY KEY, body TEXT NOT NULL)",
+        "ALTER TABLE users ADD COLUMN name TEXT NOT NULL DEFAULT ''",
+        "ALTER TABLE users ADD COLUMN age INTEGER",
+        "ALTER TABLE users DROP COLUMN nick",
+        "DROP TABLE legacy",
+    ]);
+}
+
+#[test]
+fn test_schema_registry() {
+    use crate::schema::{ColumnMeta, EntityMeta, Registry, Schema, SqlType};
+
+    struct User;
+    struct Post;
+
+    impl Schema for User {
+        fn entity_meta() -> EntityMeta {
+            EntityMeta {
+                table: "users".into(),
+                columns: vec![ColumnMeta { name: "id".into(), sql_type: SqlType::Integer, nullable: false, primary_key: true, references: None, on_delete: None }],
+                many: vec![],
+                unique: vec![],
+                id_prefix: None,
+            }
+        }
+    }
+
+    impl Schema for Post {
+        fn entity_meta() -> EntityMeta {
+            EntityMeta { table: "posts".into(), columns: vec![], many: vec![], unique: vec![], id_prefix: None }
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<User>().register::<Post>().register::<User>();
+    assert_eq!(registry.len(), 2);
+    assert!(registry.contains::<Post>());
+    assert!(!registry.contains::<Model>());
+    assert_eq!(registry.get::<User>().map(|meta| &*meta.table), Some("users"));
+    assert!(registry.by_table("posts").unwrap().type_name().ends_with("Post"));
+    assert_eq!(registry.iter().map(|entity| &*entity.meta().table).collect::<Vec<_>>(), vec!["users", "posts"]);
+    assert_eq!(registry.schema_set().iter().map(|meta| &*meta.table).collect::<Vec<_>>(), vec!["posts", "users"]);
+    assert_eq!(registry.create_tables_sql()[0], "CREATE TABLE users (id INTEGER PRIMARY KEY)");
+}
+
+#[test]
+fn test_schema_diagrams() {
+    use crate::schema::{ColumnMeta, EntityMeta, ManyMeta, Registry, Schema, SqlType};
+
+    struct Author;
+    struct Post;
+
+    fn column(name: &'static str, sql_type: SqlType, references: Option<&'static str>) -> ColumnMeta {
+        ColumnMeta { name: name.into(), sql_type, nullable: false, primary_key: name == "id", references: references.map(Into::into), on_delete: None }
+    }
+
+    impl Schema for Author {
+        fn entity_meta() -> EntityMeta {
+            EntityMeta {
+                table: "author".into(),
+                columns: vec![column("id", SqlType::Integer, None), column("name", SqlType::Text, None)],
+                many: vec![
+                    ManyMeta { field: "posts".into(), table: "post".into() },
+                    ManyMeta { field: "drafts".into(), table: "draft".into() },
+                ],
+                unique: vec![],
+                id_prefix: None,
+            }
+        }
+    }
+
+    impl Schema for Post {
+        fn entity_meta() -> EntityMeta {
+            EntityMeta {
+                table: "post".into(),
+                columns: vec![column("id", SqlType::Integer, None), column("author", SqlType::Integer, Some("author"))],
+                many: vec![],
+                unique: vec![],
+                id_prefix: None,
+            }
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<Author>().register::<Post>();
+    assert_eq!(registry.to_mermaid(), "\
+erDiagram
+    author {
+        INTEGER id PK
+        TEXT name
+    }
+    post {
+        INTEGER id PK
+        INTEGER author FK
+    }
+    author ||--o{ draft : drafts
+    post }o--|| author : author
+");
+    assert_eq!(registry.to_dot(), "\
+digraph {
+    node [shape=record];
+    author [label=\"{author|id INTEGER\\lname TEXT\\l}\"];
+    post [label=\"{post|id INTEGER\\lauthor INTEGER\\l}\"];
+    author -> draft [label=\"drafts\", arrowhead=crow];
+    post -> author [label=\"author\", arrowhead=normal];
+}
+");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_schema_serde() {
+    use crate::schema::{ColumnMeta, EntityMeta, SchemaSet, SqlType};
+
+    let mut schema = SchemaSet::new();
+    schema.insert(EntityMeta {
+        table: "users".into(),
+        columns: vec![ColumnMeta { name: "id".into(), sql_type: SqlType::Integer, nullable: false, primary_key: true, references: None, on_delete: None }],
+        many: vec![],
+        unique: vec![],
+        id_prefix: None,
+    });
+    let json = serde_json::to_string(&schema).unwrap();
+    assert_eq!(serde_json::from_str::<SchemaSet>(&json).unwrap(), schema);
+}
+
+/// Collects the exported tables as text cells
+#[derive(Default)]
+struct Sheets(Vec<(String, Vec<Vec<String>>)>);
+
+impl export::TabularExport for Sheets {
+    type Error = Error;
+
+    fn begin(&mut self, meta: &schema::EntityMeta) -> Result<()> {
+        let header = meta.columns.iter().map(|column| column.name.to_string()).collect();
+        self.0.push((meta.table.to_string(), vec![header]));
+        Ok(())
+    }
+
+    fn row(&mut self, cells: &[sql::Value]) -> Result<()> {
+        let row = cells.iter().map(|cell| format!("{cell:?}")).collect();
+        self.0.last_mut().ok_or(Error::NotFound)?.1.push(row);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_tabular_export() -> Result<()> {
+    use export::TabularExport;
+
+    let models = [Model { id: Key::new(1), label: "a".to_owned() }, Model { id: Key(None), label: "b, c".to_owned() }];
+    let mut sheets = Sheets::default();
+    <&mut Sheets as TabularExport>::export(&mut &mut sheets, &models)?;
+    sheets.export(&models[1..])?;
+    assert_eq!(sheets.0.len(), 2);
+    assert_eq!(sheets.0[0].0, "model");
+    assert_eq!(sheets.0[0].1, [["id", "label"], ["Integer(1)", "Text(\"a\")"], ["Null", "Text(\"b, c\")"]]);
+    assert_eq!(sheets.0[1].1.len(), 2);
+
+    #[cfg(feature = "csv")]
+    {
+        let mut csv = crate::csv::CsvExport::new(Vec::new());
+        csv.export(&models)?;
+        csv.export(&models[..1])?;
+        assert_eq!(csv.into_inner(), b"id,label\r\n1,a\r\n,\"b, c\"\r\nid,label\r\n1,a\r\n");
+    }
+    Ok(())
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><==========================  GRAPH  ===========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+impl graph::Graph for Model {
+    fn graph_table(&self) -> Cow<'static, str> {
+        "model".into()
+    }
+
+    fn graph_relations(&self) -> Vec<graph::GraphRelation<'_>> {
+        Vec::new()
+    }
+
+    fn graph_clear_keys(&mut self, _child: bool) {
+        self.id = Key(None);
+    }
+}
+
+#[test]
+fn test_graph_manual_defaults() {
+    use graph::Graph;
+
+    let model = Model { id: Key::new(1), label: "a".to_owned() };
+    assert_eq!(model.graph_key(), sql::Value::Integer(1));
+    assert_eq!(model.clone_as_new().id, Key(None));
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=======================  REPOSITORY  =========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+#[test]
+fn test_memory_repository() -> Result<()> {
+    use crate::repository::{Fetch, MemoryRepository, Repository};
+
+    let repo = MemoryRepository::new();
+    let mut first = Model { id: Key(None), label: "first".to_owned() };
+    repo.insert(&mut first)?;
+    repo.insert(&mut Model { id: Key::new(5), label: "fifth".to_owned() })?;
+    let mut next = Model { id: Key(None), label: "next".to_owned() };
+    repo.insert(&mut next)?;
+    assert_eq!(first.id, Key::new(1));
+    assert_eq!(next.id, Key::new(6));
+
+    next.label = "sixth".to_owned();
+    repo.update(&mut next)?;
+    assert_eq!(repo.get(&6)?.label, "sixth");
+    assert_eq!(repo.list()?.iter().map(|model| model.id.0).collect::<Vec<_>>(), vec![Some(1), Some(5), Some(6)]);
+
+    repo.delete(&5)?;
+    assert!(matches!(repo.get(&5), Err(Error::NotFound)));
+    assert!(matches!(repo.delete(&5), Err(Error::NotFound)));
+    assert_eq!(repo.get_many(&[&1, &5, &6])?.len(), 2);
+
+    let mut entities = vec![Entity::Key(Key::new(6)), Entity::None, Entity::Key(Key::new(1))];
+    repo.fetch_all(&mut entities)?;
+    assert_eq!(entities[0].data()?.label, "sixth");
+    assert!(entities[1].is_none());
+    assert_eq!(entities[2].data()?.label, "first");
+
+    let mut entity_label = EntityLabel::<Int, Model, String>::KeyLabel(Key::new(1), "first".to_owned());
+    assert_eq!(repo.fetch_label(&mut entity_label)?.label, "first");
+
+    let mut many = Many::NotFetched;
+    assert_eq!(repo.fetch_many_where(&mut many, |model| model.label.starts_with('s'))?.len(), 1);
+
+    let manual = MemoryRepository::<Model>::manual();
+    assert!(matches!(manual.insert(&mut Model { id: Key(None), label: String::new() }), Err(Error::KeyEmpty)));
+    Ok(())
+}
+
+#[test]
+fn test_memory_repository_duplicate_key() -> Result<()> {
+    use crate::repository::{MemoryRepository, Repository};
+
+    let repo = MemoryRepository::new();
+    repo.insert(&mut Model { id: Key::new(2), label: "second".to_owned() })?;
+    let duplicate = repo.insert(&mut Model { id: Key::new(2), label: "other".to_owned() });
+    assert!(matches!(duplicate, Err(Error::DuplicateKey { key }) if key == "2"));
+    assert_eq!(repo.get(&2)?.label, "second");
+    assert_eq!(repo.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_memory_repository_transaction() -> Result<()> {
+    use crate::repository::{MemoryRepository, Repository};
+
+    let repo = MemoryRepository::new();
+    repo.insert(&mut Model { id: Key(None), label: "kept".to_owned() })?;
+
+    let result = repo.transaction(|repo| {
+        repo.insert(&mut Model { id: Key(None), label: "dropped".to_owned() })?;
+        repo.delete(&1)?;
+        Err::<(), _>(Error::Aborted("rollback".to_owned()))
+    });
+    assert!(result.is_err());
+    assert_eq!(repo.len(), 1);
+    assert_eq!(repo.get(&1)?.label, "kept");
+
+    // the key taken by the rolled back insert is handed out again
+    let mut model = Model { id: Key(None), label: "next".to_owned() };
+    repo.insert(&mut model)?;
+    assert_eq!(model.id, Key::new(2));
+    repo.delete(&2)?;
+
+    repo.transaction(|repo| repo.delete(&1))?;
+    assert!(repo.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_repository_upsert() -> Result<()> {
+    use crate::repository::{CachedRepository, MemoryRepository, Repository};
+    use std::time::Duration;
+
+    let repo = MemoryRepository::new();
+    let mut model = Model { id: Key(None), label: "first".to_owned() };
+    repo.upsert(&mut model)?;
+    assert_eq!(model.id, Key::new(1));
+
+    model.label = "changed".to_owned();
+    repo.upsert(&mut model)?;
+    repo.upsert(&mut Model { id: Key::new(7), label: "seventh".to_owned() })?;
+    assert_eq!(repo.list()?.iter().map(|model| model.label.as_str()).collect::<Vec<_>>(), ["changed", "seventh"]);
+
+    let cached = CachedRepository::new(repo, Duration::from_secs(60));
+    assert_eq!(cached.get(&7)?.label, "seventh");
+    cached.upsert(&mut Model { id: Key::new(7), label: "through".to_owned() })?;
+    assert_eq!(cached.get(&7)?.label, "through");
+    Ok(())
+}
+
+#[test]
+fn test_fetch_observer() -> Result<()> {
+    use observe::{FetchCounter, FetchCounts};
+    use repository::{Fetch, MemoryRepository, Repository};
+    use std::sync::Arc;
+
+    #[derive(Clone)]
+    struct Observed {
+        id: Key<Int>,
+    }
+
+    impl Keyed for Observed {
+        type KeyType = Int;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.id)
+        }
+    }
+
+    impl KeyedMut for Observed {
+        fn key_mut(&mut self) -> Result<&mut Key<Self::KeyType>> {
+            Ok(&mut self.id)
+        }
+    }
+
+    impl hooks::Lifecycle for Observed {}
+
+    let repository = MemoryRepository::new();
+    for _ in 0..5 {
+        repository.insert(&mut Observed { id: Key(None) })?;
+    }
+
+    let counter = Arc::new(FetchCounter::new());
+    observe::set_observer(counter.clone());
+    for id in 1..=3 {
+        repository.fetch(&mut Entity::<Int, Observed>::Key(Key::new(id)))?;
+    }
+    let mut fetched = Entity::Data(Box::new(Observed { id: Key::new(1) }));
+    repository.fetch(&mut fetched)?;
+    repository.fetch_all(&mut [Entity::Key(Key::new(4)), Entity::Key(Key::new(5))])?;
+    repository.fetch_many_where(&mut Many::NotFetched, |_| true)?;
+    observe::clear_observer();
+    repository.fetch(&mut Entity::<Int, Observed>::Key(Key::new(1)))?;
+
+    assert_eq!(counter.counts_of::<Observed>(), FetchCounts { one: 3, batches: 1, batched: 2, many: 1 });
+    assert!(counter.one_by_one(3).iter().any(|(entity, count)| entity.ends_with("Observed") && *count == 3));
+    assert!(!counter.one_by_one(4).iter().any(|(entity, _)| entity.ends_with("Observed")));
+
+    counter.reset();
+    assert_eq!(counter.counts_of::<Observed>(), FetchCounts::default());
+    Ok(())
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn test_seeded() {
+    use testing::{seeded, seeded_with_depth, SeedRng, Seeded};
+
+    let mut rng = SeedRng::new(0);
+    assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+    assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+    assert!((0..100).all(|_| rng.below(3) < 3));
+    assert_eq!(rng.below(0), 0);
+
+    assert!((0..100).all(|seed| (1..127).contains(&seeded::<i8>(seed))));
+    assert_eq!(seeded::<String>(7), seeded::<String>(7));
+    assert!(seeded::<Key<Int>>(1).is_some());
+    assert!(seeded_with_depth::<Entity<Int, String>>(1, 0).is_key());
+    assert_eq!(seeded_with_depth::<Entity<Int, String>>(1, 1).data().ok(), Some(&seeded::<String>(1)));
+    assert!(seeded_with_depth::<Many<String>>(1, 0).is_not_fetched());
+    let many = Many::<String>::seeded(&mut SeedRng::new(1), 1);
+    assert!((1..=3).contains(&many.data().unwrap().len()));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing() -> Result<()> {
+    use repository::{Fetch, MemoryRepository, Repository};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata};
+
+    #[derive(Default)]
+    struct Recorded(Mutex<Vec<String>>);
+
+    impl Visit for &Recorded {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.lock().unwrap().push(format!("{}={value:?}", field.name()));
+        }
+    }
+
+    struct Recorder(Arc<Recorded>);
+
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            span.record(&mut &*self.0);
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut &*self.0);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    let repository = MemoryRepository::new();
+    repository.insert(&mut Model { id: Key::new(1), label: "a".to_owned() })?;
+
+    let recorded = Arc::new(Recorded::default());
+    tracing::subscriber::with_default(Recorder(recorded.clone()), || {
+        repository.fetch(&mut Entity::<Int, Model>::Key(Key::new(1)))?;
+        repository.fetch(&mut Entity::<Int, Model>::Key(Key::new(2))).err().unwrap();
+        Ok::<_, Error>(())
+    })?;
+
+    let recorded = recorded.0.lock().unwrap();
+    assert!(recorded.contains(&"operation=\"fetch\"".to_owned()));
+    assert!(recorded.iter().any(|field| field.starts_with("entity=") && field.ends_with("Model\"")));
+    assert!(recorded.contains(&"rows=1".to_owned()));
+    assert!(recorded.contains(&"message=fetch done".to_owned()));
+    assert!(recorded.contains(&"message=fetch failed".to_owned()));
+    Ok(())
+}
+
+#[test]
+fn test_cached_repository() -> Result<()> {
+    use crate::repository::{CachedRepository, MemoryRepository, Repository};
+    use std::time::Duration;
+
+    let repo = CachedRepository::new(MemoryRepository::new(), Duration::from_secs(60));
+    repo.insert(&mut Model { id: Key(None), label: "first".to_owned() })?;
+    assert_eq!(repo.get(&1)?.label, "first");
+
+    repo.inner().update(&mut Model { id: Key::new(1), label: "behind".to_owned() })?;
+    assert_eq!(repo.get(&1)?.label, "first");
+    repo.invalidate(&1)?;
+    assert_eq!(repo.get(&1)?.label, "behind");
+
+    repo.update(&mut Model { id: Key::new(1), label: "through".to_owned() })?;
+    assert_eq!(repo.get(&1)?.label, "through");
+    repo.delete(&1)?;
+    assert!(matches!(repo.get(&1), Err(Error::NotFound)));
+
+    let uncached = CachedRepository::new(MemoryRepository::new(), Duration::ZERO);
+    uncached.insert(&mut Model { id: Key(None), label: "first".to_owned() })?;
+    assert_eq!(uncached.get(&1)?.label, "first");
+    uncached.inner().update(&mut Model { id: Key::new(1), label: "expired".to_owned() })?;
+    assert_eq!(uncached.get(&1)?.label, "expired");
+    Ok(())
+}
+
+#[cfg(feature = "redis")]
+#[test]
+fn test_redis_cache() -> Result<()> {
+    use crate::redis::RedisCache;
+    use crate::repository::{CacheStore, CachedRepository, MemoryRepository, Repository};
+    use std::collections::BTreeMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    fn line(reader: &mut impl BufRead) -> Option<String> {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok().filter(|read| *read > 0)?;
+        Some(line.trim_end().to_owned())
+    }
+
+    fn bulk(bytes: &[u8]) -> Vec<u8> {
+        [format!("${}\r\n", bytes.len()).as_bytes(), bytes, b"\r\n"].concat()
+    }
+
+    // A fake server knowing the commands of the cache, recording their keys
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?.to_string();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut data = BTreeMap::<Vec<u8>, Vec<u8>>::new();
+        let mut commands = Vec::new();
+        while let Some(header) = line(&mut reader) {
+            let mut args = Vec::new();
+            for _ in 0..header[1..].parse::<usize>().unwrap() {
+                let len = line(&mut reader).unwrap()[1..].parse::<usize>().unwrap();
+                let mut arg = vec![0; len + 2];
+                reader.read_exact(&mut arg).unwrap();
+                arg.truncate(len);
+                args.push(arg);
+            }
+
+            let command = String::from_utf8(args[0].clone()).unwrap();
+            let reply = match command.as_str() {
+                "GET" => data.get(&args[1]).map_or(b"$-1\r\n".to_vec(), |value| bulk(value)),
+                "SET" => {
+                    assert_eq!(args[3..], [b"PX".to_vec(), b"60000".to_vec()]);
+                    data.insert(args[1].clone(), args[2].clone());
+                    b"+OK\r\n".to_vec()
+                },
+                "DEL" => {
+                    let deleted = args[1..].iter().filter(|key| data.remove(*key).is_some()).count();
+                    format!(":{deleted}\r\n").into_bytes()
+                },
+                "SCAN" => {
+                    let prefix = &args[3][..args[3].len() - 1];
+                    let keys = data.keys().filter(|key| key.starts_with(prefix)).collect::<Vec<_>>();
+                    let mut reply = [b"*2\r\n".to_vec(), bulk(b"0"), format!("*{}\r\n", keys.len()).into_bytes()].concat();
+                    keys.into_iter().for_each(|key| reply.extend(bulk(key)));
+                    reply
+                },
+                _ => b"-ERR unknown command\r\n".to_vec(),
+            };
+            commands.push(format!("{command} {}", String::from_utf8_lossy(args.get(1).map_or(&[][..], |arg| arg))));
+            writer.write_all(&reply).unwrap();
+        }
+        commands
+    });
+
+    let repo = CachedRepository::with_store(MemoryRepository::new(), Duration::from_secs(60), RedisCache::<Model>::new(addr));
+    repo.insert(&mut Model { id: Key(None), label: "first".to_owned() })?;
+    repo.insert(&mut Model { id: Key(None), label: "second".to_owned() })?;
+    assert_eq!(repo.get(&1)?.label, "first");
+    repo.inner().update(&mut Model { id: Key::new(1), label: "behind".to_owned() })?;
+    assert_eq!(repo.get(&1)?.label, "first");
+
+    repo.update(&mut Model { id: Key::new(1), label: "through".to_owned() })?;
+    assert_eq!(repo.get(&1)?.label, "through");
+    assert_eq!(repo.get(&2)?.label, "second");
+    repo.clear()?;
+    assert!(repo.store().get(&2)?.is_none());
+
+    drop(repo);
+    assert_eq!(server.join().unwrap(), [
+        "GET model:1", "SET model:1", "GET model:1",
+        "DEL model:1", "GET model:1", "SET model:1",
+        "GET model:2", "SET model:2",
+        "SCAN 0", "DEL model:1", "GET model:2",
+    ]);
+    Ok(())
+}
+
+#[cfg(feature = "redis")]
+#[test]
+fn test_redis_cache_bad_server() -> Result<()> {
+    use crate::redis::RedisCache;
+    use crate::repository::CacheStore;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    // Claims a bulk string of 4 GiB, then stalls without replying
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?.to_string();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0; 64];
+        let _ = stream.read(&mut request).unwrap();
+        stream.write_all(b"$4294967296\r\nabc").unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = stream.read(&mut request).unwrap();
+        let _ = stream.read(&mut request);
+    });
+
+    let cache = RedisCache::<Model>::new(addr).with_timeout(Duration::from_millis(200));
+    assert_eq!(cache.timeout(), Duration::from_millis(200));
+    assert!(matches!(cache.get(&1), Err(Error::Io(err)) if err.kind() == std::io::ErrorKind::InvalidData));
+
+    let start = Instant::now();
+    assert!(matches!(cache.get(&1), Err(Error::Io(_))));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    drop(cache);
+    server.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_delete_orphans() -> Result<()> {
+    use crate::repository::{MemoryRepository, Repository};
+    use crate::snapshot::TakeSnapshot;
+
+    let repo = MemoryRepository::new();
+    let mut many: Many<Model> = (1..=4).map(|_| Model { id: Key(None), label: String::new() }).collect::<Vec<_>>().into();
+    for model in many.data_mut()? {
+        repo.insert(model)?;
+    }
+
+    let snapshot = many.snapshot();
+    many.data_mut()?.retain(|model| model.id != Key::new(2) && model.id != Key::new(4));
+    many.data_mut()?.push(Model { id: Key(None), label: "new".to_owned() });
+    assert_eq!(many.removed_since(&snapshot)?, [2, 4]);
+
+    assert_eq!(repo.delete_orphans(&many, &snapshot)?, 2);
+    assert_eq!(repo.list()?.iter().map(|model| model.id.clone()).collect::<Vec<_>>(), [Key::new(1), Key::new(3)]);
+    assert_eq!(repo.delete_orphans(&many, &snapshot)?, 0);
+    assert_eq!(repo.delete_many(&[&1, &5])?, 1);
+    assert!(matches!(Many::<Model>::NotFetched.removed_since(&snapshot), Err(Error::ManyNotFetched)));
+    Ok(())
+}
+
+#[test]
+fn test_audited_repository() -> Result<()> {
+    use crate::audit::{AuditAction, AuditLog, AuditedRepository};
+    use crate::repository::{MemoryRepository, Repository};
+
+    let log = AuditLog::new();
+    let mut repo = AuditedRepository::new(MemoryRepository::new(), &log, "ann");
+    let mut model = Model { id: Key(None), label: "first".to_owned() };
+    repo.insert(&mut model)?;
+    model.label = "second".to_owned();
+    repo.update(&mut model)?;
+    repo.set_actor("bob");
+    repo.upsert(&mut Model { id: Key::new(1), label: "third".to_owned() })?;
+    repo.upsert(&mut Model { id: Key::new(5), label: "fifth".to_owned() })?;
+    repo.delete(&1)?;
+    assert!(matches!(repo.delete(&1), Err(Error::NotFound)));
+    assert!(matches!(repo.update(&mut Model { id: Key::new(9), label: String::new() }), Err(Error::NotFound)));
+
+    let entries = log.take();
+    let summary = entries
+        .iter()
+        .map(|entry| (entry.action, entry.key.as_deref().unwrap_or_default(), entry.actor.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(summary, [
+        (AuditAction::Insert, "1", "ann"),
+        (AuditAction::Update, "1", "ann"),
+        (AuditAction::Update, "1", "bob"),
+        (AuditAction::Insert, "5", "bob"),
+        (AuditAction::Delete, "1", "bob"),
+    ]);
+    assert!(entries.iter().all(|entry| entry.entity == "model"));
+
+    let label = |entry: &audit::AuditEntry| entry.changes.get("label").map(|change| (change.old.clone(), change.new.clone()));
+    let text = |text: &str| sql::Value::Text(text.to_owned());
+    assert_eq!(label(&entries[0]), Some((sql::Value::Null, text("first"))));
+    assert_eq!(label(&entries[1]), Some((text("first"), text("second"))));
+    assert_eq!(entries[1].changes.len(), 1);
+    assert_eq!(label(&entries[4]), Some((text("third"), sql::Value::Null)));
+    assert!(log.entries().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_read_only_repository() -> Result<()> {
+    use crate::readonly::{ReadOnly, ReadOnlyRepository};
+    use crate::repository::{MemoryRepository, Repository};
+
+    let repo = MemoryRepository::new();
+    repo.insert(&mut Model { id: Key(None), label: "first".to_owned() })?;
+    repo.insert(&mut Model { id: Key(None), label: "second".to_owned() })?;
+
+    let models = ReadOnlyRepository::new(repo);
+    assert_eq!(models.get::<Model>(&2)?.label, "second");
+    assert!(matches!(models.get::<Model>(&3), Err(Error::NotFound)));
+    assert_eq!(models.get_many::<Model>(&[&1, &3])?.len(), 1);
+    assert_eq!(models.list::<Model>()?.iter().map(|model| model.label.as_str()).collect::<Vec<_>>(), ["first", "second"]);
+
+    let mut entity = Entity::<Int, ReadOnly<Model>>::Key(Key::new(1));
+    assert_eq!(models.fetch(&mut entity)?.label, "first");
+    assert_eq!(entity.key()?, &Key::new(1));
+    assert_eq!(entity.data()?.label()?, "first");
+    assert_eq!(entity.into_data()?.into_inner().label, "first");
+    Ok(())
+}
+
+/// Polls a future to completion, for futures that don't wait on anything
+#[cfg(feature = "async")]
+fn block_on<F: core::future::Future>(future: F) -> F::Output {
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    // a no-op waker by hand, as `Waker::noop` needs a newer Rust
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RAW, |_| {}, |_| {}, |_| {});
+    const RAW: RawWaker = RawWaker::new(core::ptr::null(), &VTABLE);
+
+    // SAFETY: the vtable functions ignore the data pointer and do nothing
+    let waker = unsafe { Waker::from_raw(RAW) };
+    let mut future = core::pin::pin!(future);
+    let mut context = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_repository() -> Result<()> {
+    use crate::repository::{AsyncRepository, MemoryRepository};
+
+    async fn rename<R: AsyncRepository<Model>>(repo: &R, key: Int, label: &str) -> Result<()> {
+        let mut model = repo.get(&key).await?;
+        model.label = label.to_owned();
+        repo.update(&mut model).await
+    }
+
+    let repo = MemoryRepository::new();
+    block_on(async {
+        let mut model = Model { id: Key(None), label: "first".to_owned() };
+        AsyncRepository::insert(&repo, &mut model).await?;
+        assert_eq!(model.id, Key::new(1));
+
+        rename(&repo, 1, "renamed").await?;
+        assert_eq!(AsyncRepository::get(&repo, &1).await?.label, "renamed");
+        assert_eq!(AsyncRepository::get_many(&repo, &[&1, &2]).await?.len(), 1);
+        assert_eq!(AsyncRepository::list(&repo).await?.len(), 1);
+
+        AsyncRepository::delete(&repo, &1).await?;
+        assert!(matches!(AsyncRepository::get(&repo, &1).await, Err(Error::NotFound)));
+        Ok(())
+    })
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_many_from_stream() {
+    use core::pin::Pin;
+    use core::task::{Context, Poll};
+    use futures_core::Stream;
+
+    struct Rows(std::vec::IntoIter<Result<Model>>);
+
+    impl Stream for Rows {
+        type Item = Result<Model>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.next())
+        }
+    }
+
+    let rows = |labels: &[&str]| Rows(labels.iter().map(|label| Ok(Model { id: Key(None), label: label.to_string() })).collect::<Vec<_>>().into_iter());
+
+    block_on(async {
+        let mut stream = Many::from_stream(rows(&["a", "b", "c"]));
+        assert_eq!(stream.next().await.unwrap().unwrap().label, "a");
+        assert_eq!(stream.received().len(), 1);
+        assert!(!stream.is_done());
+        assert_eq!(stream.into_many().data().unwrap().len(), 1);
+
+        let many = Many::from_stream(rows(&["a", "b", "c"])).collect().await.unwrap();
+        assert_eq!(many.data().unwrap().iter().map(|model| model.label.as_str()).collect::<String>(), "abc");
+
+        let failing = Rows(vec![Ok(Model::default()), Err(Error::NotFound)].into_iter());
+        assert!(matches!(Many::from_stream(failing).collect().await, Err(Error::NotFound)));
+    });
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_many_par_iter() -> Result<()> {
+    use rayon::prelude::*;
+
+    let mut many: Many<Int> = (1..=100).collect::<Vec<_>>().into();
+    assert_eq!(many.par_iter()?.sum::<Int>(), 5050);
+
+    many.par_iter_mut()?.for_each(|n| *n *= 2);
+    assert_eq!((&many).into_par_iter().sum::<Int>(), 10100);
+    assert_eq!(many.into_par_iter().filter(|n| n % 4 == 0).count(), 50);
+
+    let mut not_fetched: Many<Int> = Many::NotFetched;
+    assert!(matches!(not_fetched.par_iter(), Err(Error::ManyNotFetched)));
+    assert!(matches!(not_fetched.par_iter_mut(), Err(Error::ManyNotFetched)));
+    assert_eq!((&mut not_fetched).into_par_iter().count(), 0);
+    assert_eq!(Many::<Int>::None.into_par_iter().count(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_many_index() {
+    let mut many: Many<Int> = vec![1, 2, 3].into();
+    assert_eq!(many[1], 2);
+    many[1] = 20;
+    assert_eq!(many.get(1), Some(&20));
+    assert_eq!(many.get(3), None);
+    *many.get_mut(0).unwrap() = 10;
+    assert_eq!(many.first(), Some(&10));
+    assert_eq!(many.last(), Some(&3));
+    assert_eq!(many.len(), Some(3));
+    assert_eq!(many.is_empty(), Some(false));
+
+    let empty: Many<Int> = Vec::new().into();
+    assert_eq!(empty.first(), None);
+    assert_eq!(empty.is_empty(), Some(true));
+
+    let not_fetched: Many<Int> = Many::NotFetched;
+    assert_eq!(not_fetched.get(0), None);
+    assert_eq!(not_fetched.len(), None);
+    assert_eq!(not_fetched.is_empty(), None);
 }
 
 #[test]
-fn test_entity_label_tag() -> Result<()> {
-    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
-    let tag = entity_label.tag()?;
-    assert_eq!(tag.key, "1");
-    assert_eq!(tag.label, "Label");
+#[should_panic(expected = "cannot index into Many::NotFetched at 0")]
+fn test_many_index_not_fetched() {
+    let many: Many<Int> = Many::NotFetched;
+    let _ = many[0];
+}
+
+#[test]
+fn test_expect_data() {
+    let entity: Entity<Int, Model> = Model { id: Key::new(1), label: "a".into() }.into();
+    assert_eq!(entity.expect_data("model").label, "a");
+    let many: Many<Int> = vec![1, 2].into();
+    assert_eq!(many.expect_data("numbers").len(), 2);
+    assert_eq!(Key::new(5).expect("id"), 5);
+}
+
+#[test]
+#[should_panic(expected = "model: Entity<dbent::tests::Model> is Key")]
+fn test_entity_expect_data_key() {
+    let entity: Entity<Int, Model> = Entity::Key(Key::new(1));
+    entity.expect_data("model");
+}
+
+#[test]
+#[should_panic(expected = "numbers: Many<usize> is NotFetched")]
+fn test_many_expect_data_not_fetched() {
+    let many: Many<Int> = Many::NotFetched;
+    many.expect_data("numbers");
+}
+
+#[test]
+#[should_panic(expected = "id: Key<usize> is unset")]
+fn test_key_expect_unset() {
+    Key::<Int>(None).expect("id");
+}
+
+#[test]
+fn test_entity_result_optional() -> Result<()> {
+    let data: Entity<Int, Model> = Model { id: Key::new(1), label: "a".into() }.into();
+    assert_eq!(data.data().optional()?.map(|model| model.label.as_str()), Some("a"));
+
+    let key: Entity<Int, Model> = Entity::Key(Key::new(1));
+    assert!(key.data().optional()?.is_none());
+    assert!(Entity::<Int, Model>::None.data().optional()?.is_none());
+
+    assert!(matches!(Many::<Int>::NotFetched.data().optional(), Err(Error::ManyNotFetched)));
     Ok(())
 }
 
 #[test]
-fn test_entity_label_has_tag() {
-    let entity_label = EntityLabelInt::<Model>::KeyLabel(Key::new(1), String::from("Label"));
-    assert!(entity_label.has_tag());
+fn test_traced_error() {
+    fn label(entity: &Entity<Int, Model>) -> TracedResult<&str> {
+        Ok(&entity.data()?.label)
+    }
+
+    let err = label(&Entity::Key(Key::new(1))).unwrap_err();
+    assert!(matches!(err.error(), Error::EntityNotFetched));
+    assert_eq!(err.to_string(), Error::EntityNotFetched.to_string());
+    assert!(format!("{err:?}").starts_with("EntityNotFetched"));
+    assert_eq!(err.chain().count(), 1);
+    assert!(matches!(err.into_error(), Error::EntityNotFetched));
+
+    #[cfg(feature = "rusqlite")]
+    {
+        let cause = std::io::Error::other("disk on fire");
+        let sqlite = rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(cause));
+        let err = TracedError::from(Error::Sqlite(sqlite));
+        assert_eq!(err.chain().map(ToString::to_string).last().as_deref(), Some("disk on fire"));
+        assert!(format!("{err:?}").contains("\n\nCaused by: disk on fire"));
+        assert_eq!(std::error::Error::source(&err).map(ToString::to_string).as_deref(), Some("disk on fire"));
+    }
 }
 
 #[test]
-fn test_entity_label_eq() {
-    let entity_label_1 = EntityLabelInt::<i32>::KeyLabel(Key::new(1), String::from("Label"));
-    let entity_label_2 = EntityLabelInt::<i32>::KeyLabel(Key::new(1), String::from("Label"));
-    assert_eq!(entity_label_1, entity_label_2);
+fn test_key_stable_hash() {
+    // pinned, as the hashes must not change between versions
+    assert_eq!(Key::<Int>(None).stable_hash(), 0xf52a_15e9_a9b5_e89b);
+    assert_eq!(Key::new(42usize).stable_hash(), 0xe15f_07fe_f55b_9454);
+    assert_eq!(Key::new(42u8).stable_hash(), Key::new(42i64).stable_hash());
+    assert_eq!(Key::new("user").stable_hash(), 0xff9a_e3c5_5ae5_bee1);
+    assert_eq!(Key::new("user".to_owned()).stable_hash(), Key::new(Cow::Borrowed("user")).stable_hash());
+    assert_eq!(Key::new(CiString::new("USER")).stable_hash(), Key::new("user").stable_hash());
+    assert_eq!(Key::new(Sensitive::from("user")).stable_hash(), Key::new("user").stable_hash());
+    assert_eq!(Key::new(*b"user").stable_hash(), Key::new(b"user".to_vec()).stable_hash());
+    assert_ne!(Key::new(LinkKey::new(1, 23)).stable_hash(), Key::new(LinkKey::new(12, 3)).stable_hash());
+
+    let shards = (1..=1000usize).fold([0; 8], |mut shards, key| {
+        shards[(Key::new(key).stable_hash() % 8) as usize] += 1;
+        shards
+    });
+    assert!(shards.iter().all(|&count| (90..160).contains(&count)), "{shards:?}");
+}
+
+#[test]
+fn test_denormalized_refresh() -> Result<()> {
+    use denormalized::{Denormalized, SourceChanges};
+
+    struct ModelTotals;
+
+    impl Denormalized for ModelTotals {
+        fn sources() -> Vec<Cow<'static, str>> {
+            vec!["model".into(), "other".into()]
+        }
+    }
+
+    let mut changes = SourceChanges::new();
+    assert!(changes.is_empty());
+    changes.record::<Model>(2);
+    changes.record::<Model>(1);
+    changes.record::<Model>(2);
+    changes.record_table("unrelated", 5);
+    assert_eq!(changes.keys("model"), [sql::Value::Integer(2), sql::Value::Integer(1)]);
+    assert_eq!(changes.tables().collect::<Vec<_>>(), ["model", "unrelated"]);
+    assert_eq!(ModelTotals::refresh_sql("model"), None);
+
+    let mut calls = Vec::new();
+    let rows = denormalized::refresh::<ModelTotals>(&changes, |source, keys| {
+        calls.push(source.to_owned());
+        Ok(keys.len() * 10)
+    })?;
+    assert_eq!((rows, calls), (20, vec!["model".to_owned()]));
+
+    let err = denormalized::refresh::<ModelTotals>(&changes, |_, _| Err(Error::KeyMissing));
+    assert!(matches!(err, Err(Error::KeyMissing)));
+    Ok(())
+}
+
+#[test]
+fn test_once_entity() -> Result<()> {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use once::OnceEntity;
+
+    let model: OnceEntity<Int, Model> = OnceEntity::new(Key::new(1));
+    assert!(matches!(model.data(), Err(Error::EntityNotFetched)));
+    assert!(matches!(model.get_or_load(|_| Err(Error::KeyMissing)), Err(Error::KeyMissing)));
+    assert!(!model.is_loaded());
+
+    let loads = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                let data = model.get_or_load(|&id| {
+                    loads.fetch_add(1, Ordering::Relaxed);
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                    Ok(Model { id: Key::new(id), label: "loaded".to_owned() })
+                });
+                assert_eq!(data.unwrap().label, "loaded");
+            });
+        }
+    });
+    assert_eq!(loads.load(Ordering::Relaxed), 1);
+    assert_eq!(model.get_or_load(|_| unreachable!())?.label, "loaded");
+    assert_eq!(model.key()?, &Key::new(1));
+    assert!(model.clone().into_entity().is_data());
+
+    let empty: OnceEntity<Int, Model> = OnceEntity::new(Key(None));
+    assert!(matches!(empty.get_or_load(|_| unreachable!()), Err(Error::KeyEmpty)));
+
+    let entity: Entity<Int, Model> = Entity::Data(Box::new(Model { id: Key::new(2), label: "b".to_owned() }));
+    let model = OnceEntity::try_from(entity)?;
+    assert_eq!((model.key()?, model.data()?.label.as_str()), (&Key::new(2), "b"));
+    assert!(OnceEntity::<Int, Model>::try_from(Entity::Key(Key::new(3)))?.into_entity().is_key());
+    assert!(matches!(OnceEntity::<Int, Model>::try_from(Entity::None), Err(Error::EntityEmpty)));
+    Ok(())
+}
+
+#[cfg(feature = "deterministic")]
+#[test]
+fn test_deterministic_serialize() {
+    use deterministic::DeterministicSerialize;
+    use localized::LocalizedLabel;
+    use observe::{FetchCounter, FetchEvent, FetchKind, FetchObserver};
+
+    fn snapshot<T: DeterministicSerialize + ?Sized>(value: &T) -> String {
+        serde_json::to_string(value).unwrap()
+    }
+
+    let label: LocalizedLabel = [("pt", "Vermelho"), ("en", "Red"), ("de", "Rot")].into_iter().collect();
+    assert_eq!(snapshot(&label), r#"{"pt":"Vermelho","en":"Red","de":"Rot"}"#);
+
+    let many: Many<Tag> = Many::Data(vec![Tag::new("2", "b"), Tag::new("1", "a")]);
+    let entity: Entity<Int, Model> = Entity::Key(Key::new(3));
+    assert_eq!(snapshot(&(&many, Key::new(1), entity.key().unwrap())), r#"[{"Data":[{"key":"2","label":"b"},{"key":"1","label":"a"}]},1,3]"#);
+
+    let counter = FetchCounter::new();
+    for entity in ["zeta", "alpha", "mid"] {
+        counter.on_fetch(&FetchEvent { entity, kind: FetchKind::One });
+    }
+    let counts = counter.counts();
+    assert_eq!(counts.keys().copied().collect::<Vec<_>>(), ["alpha", "mid", "zeta"]);
+}
+
+#[test]
+fn test_keyed_iterator_ext() {
+    let models = vec![
+        Model { id: Key::new(2), label: "b".to_owned() },
+        Model { id: Key(None), label: "new".to_owned() },
+        Model { id: Key::new(1), label: "a".to_owned() },
+        Model { id: Key::new(2), label: "c".to_owned() },
+    ];
+    assert_eq!(models.iter().keys().collect::<Vec<_>>(), [2, 1, 2]);
+    assert_eq!(models.iter().key_set(), HashSet::from([1, 2]));
+
+    let by_key = models.iter().by_key_map();
+    assert_eq!((by_key.len(), by_key[&2].label.as_str()), (2, "c"));
+    assert!(matches!(models.iter().try_by_key_map(), Err(Error::DuplicateKey { key }) if key == "2"));
+    assert_eq!(models.iter().skip(1).take(2).try_by_key_map().unwrap().len(), 1);
+    let by_key = models.into_iter().by_key_map();
+    assert_eq!(by_key[&1].label, "a");
+
+    let entities: Vec<Entity<Int, Model>> = vec![Entity::Key(Key::new(3)), Entity::None, Entity::Key(Key(None))];
+    assert_eq!(entities.iter().keys().collect::<Vec<_>>(), [3]);
+}
+
+#[test]
+fn test_containers_hash() {
+    use std::collections::HashSet;
+
+    let entities: HashSet<Entity<Int, Int>> = [Entity::Key(Key::new(1)), Entity::Data(Box::new(1)), Entity::Key(Key::new(1)), Entity::None].into();
+    assert_eq!(entities.len(), 3);
+
+    let labels: HashSet<EntityLabel<Int, Int, String>> = [EntityLabel::KeyLabel(Key::new(1), "a".into()), EntityLabel::KeyLabel(Key::new(1), "a".into())].into();
+    assert_eq!(labels.len(), 1);
+
+    let many: HashSet<Many<Int>> = [vec![1, 2].into(), vec![1, 2].into(), Many::NotFetched].into();
+    assert_eq!(many.len(), 2);
+}
+
+#[test]
+fn test_containers_default() {
+    struct NoDefault;
+
+    assert!(Entity::<Int, NoDefault>::default().is_none());
+    assert!(Parent::<Int, NoDefault>::default().is_none());
+    assert!(EntityLabel::<Int, NoDefault, String>::default().is_none());
+    assert!(Many::<NoDefault>::default().is_none());
+}
+
+#[test]
+fn test_any_tagged() -> Result<()> {
+    struct Category {
+        code: Key<String>,
+        name: String,
+    }
+
+    impl Keyed for Category {
+        type KeyType = String;
+
+        fn key(&self) -> Result<&Key<Self::KeyType>> {
+            Ok(&self.code)
+        }
+    }
+
+    impl Label for Category {
+        type LabelType = String;
+
+        fn label(&self) -> Result<&Self::LabelType> {
+            Ok(&self.name)
+        }
+    }
+
+    let rows: Vec<Box<dyn AnyTagged>> = vec![
+        Box::new(Model { id: Key::new(1), label: "one".into() }),
+        Box::new(Category { code: Key::new("c".into()), name: "Cat".into() }),
+        Box::new(Model { id: Key(None), label: "new".into() }),
+    ];
+
+    let keys = rows.iter().map(|row| Ok(row.any_key()?.map(|key| key.to_string()))).collect::<Result<Vec<_>>>()?;
+    assert_eq!(keys, [Some("1".to_owned()), Some("c".to_owned()), None]);
+    assert_eq!(rows[1].tag()?, Tag::new("c", "Cat"));
+    assert!(!rows[2].has_tag());
+
+    let mut rows = rows;
+    assert!(rows[0].is::<Model>());
+    assert!(rows[1].downcast_ref::<Model>().is_none());
+    rows[2].downcast_mut::<Model>().unwrap().id = Key::new(3);
+    assert_eq!(rows[2].any_key()?.unwrap().to_string(), "3");
+
+    let category = rows.remove(1);
+    let category = match category.downcast::<Model>() {
+        Ok(_) => panic!("downcast to the wrong type"),
+        Err(category) => category,
+    };
+    assert_eq!(category.downcast::<Category>().ok().unwrap().name, "Cat");
+    Ok(())
 }
 
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
-//<<>><==========================  MANY  ============================><<>>//
+//<<>><=========================  SQLITE  ===========================><<>>//
 //<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
 
+#[cfg(feature = "rusqlite")]
+fn model_table() -> Result<rusqlite::Connection> {
+    let conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch(&<Model as schema::Schema>::entity_meta().create_table_sql())?;
+    Ok(conn)
+}
+
+#[cfg(feature = "rusqlite")]
 #[test]
-fn test_many_not_fetched() {
-    let mut many = Many::<Model>::NotFetched;
-    assert!(many.data().is_err());
-    assert!(many.data_mut().is_err());
+fn test_insert_many() -> Result<()> {
+    let conn = model_table()?;
+    let model = |id: Option<Int>, label: &str| Model { id: Key(id), label: label.to_owned() };
+
+    let mut many: Many<Model> = vec![model(None, "a"), model(Some(10), "b"), model(None, "c")].into();
+    sqlite::insert_many(&conn, &mut many)?;
+    assert_eq!(many.data()?.iter().map(|model| model.id.unwrap()).collect::<Vec<_>>(), [1, 10, 11]);
+
+    let mut failing = vec![model(None, "d"), model(None, "e"), model(None, "a")];
+    assert!(matches!(sqlite::insert_all(&conn, &mut failing), Err(Error::Sqlite(_))));
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM model", [], |row| row.get(0))?;
+    assert_eq!(count, 3);
+    assert!(failing.iter().all(|model| model.id.is_none()));
+
+    assert!(matches!(sqlite::insert_many(&conn, &mut Many::<Model>::NotFetched), Err(Error::ManyNotFetched)));
+
+    let mut single = model(None, "e");
+    sqlite::insert(&conn, &mut single)?;
+    assert_eq!(single.id, Key::new(12));
+    assert!(single.key()?.is_some());
+    Ok(())
 }
 
+#[cfg(feature = "rusqlite")]
 #[test]
-fn test_many_none() {
-    let mut many = Many::<Model>::None;
-    assert!(many.data().is_err());
-    assert!(many.data_mut().is_err());
+fn test_sqlite_upsert() -> Result<()> {
+    let conn = model_table()?;
+    let mut model = Model { id: Key(None), label: "a".to_owned() };
+    sqlite::upsert(&conn, &mut model)?;
+    assert_eq!(model.id, Key::new(1));
+
+    model.label = "b".to_owned();
+    sqlite::upsert(&conn, &mut model)?;
+    let label: String = conn.query_row("SELECT label FROM model WHERE id = 1", [], |row| row.get(0))?;
+    assert_eq!(label, "b");
+
+    let mut same_label = Model { id: Key(None), label: "b".to_owned() };
+    sqlite::upsert_on(&conn, &mut same_label, &["label"])?;
+    assert_eq!(same_label.id, Key::new(1));
+
+    let mut unique_label = Model { id: Key(None), label: "b".to_owned() };
+    sqlite::upsert(&conn, &mut unique_label)?;
+    assert_eq!(unique_label.id, Key::new(1));
+    Ok(())
 }
 
+#[cfg(feature = "rusqlite")]
 #[test]
-fn test_many_data() -> Result<()> {
-    let mut data = vec![1, 2, 3];
-    let mut many = Many::Data(data.clone());
-    assert_eq!(many.data()?, &data);
-    assert_eq!(many.data_mut()?, &mut data);
+fn test_sqlite_exists_count() -> Result<()> {
+    let conn = model_table()?;
+    assert!(!sqlite::exists::<Model>(&conn, &1)?);
+    assert_eq!(sqlite::count::<Model>(&conn)?, 0);
+
+    for label in ["a", "b", "ab"] {
+        sqlite::insert(&conn, &mut Model { id: Key(None), label: label.to_owned() })?;
+    }
+    assert!(sqlite::exists::<Model>(&conn, &3)? && !sqlite::exists::<Model>(&conn, &4)?);
+    assert_eq!(sqlite::count::<Model>(&conn)?, 3);
+    assert_eq!(sqlite::count_where::<Model>(&conn, &sql::Filter::new("label LIKE ?", vec!["a%".into()]))?, 2);
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_sqlite_select() -> Result<()> {
+    use sql::OrderBy;
+
+    let conn = model_table()?;
+    for label in ["b", "a", "ab", "c"] {
+        sqlite::insert(&conn, &mut Model { id: Key(None), label: label.to_owned() })?;
+    }
+    let labels = |models: Vec<Model>| models.into_iter().map(|model| model.label).collect::<Vec<_>>();
+
+    assert_eq!(labels(sqlite::select::<Model>().fetch(&conn)?), ["b", "a", "ab", "c"]);
+
+    let select = sqlite::select::<Model>()
+        .filter(sql::Filter::new("label LIKE ?", vec!["%b%".into()]))
+        .filter(sql::Filter::new("id > ?", vec![1.into()]))
+        .order(OrderBy::new().desc("label"));
+    assert_eq!(select.sql()?, "SELECT id, label FROM model WHERE ((label LIKE ?) AND (id > ?)) ORDER BY label DESC");
+    assert_eq!(select.params(), [sql::Value::Text("%b%".to_owned()), sql::Value::Integer(1)]);
+    assert_eq!(labels(select.fetch(&conn)?), ["ab"]);
+    assert_eq!(select.count(&conn)?, 1);
+
+    let ordered = sqlite::select::<Model>().order(OrderBy::new().asc("label"));
+    assert_eq!(labels(ordered.clone().limit(2).fetch(&conn)?), ["a", "ab"]);
+    assert_eq!(labels(ordered.clone().offset(3).fetch(&conn)?), ["c"]);
+    assert_eq!(labels(ordered.clone().page(1, 3).fetch(&conn)?), ["c"]);
+    assert!(ordered.clone().page(1, 3).sql()?.ends_with(" LIMIT 3 OFFSET 3"));
+    assert_eq!(ordered.clone().first(&conn)?.map(|model| model.label).as_deref(), Some("a"));
+    assert!(ordered.filter(sql::Filter::new("label = ?", vec!["z".into()])).first(&conn)?.is_none());
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_tx_context() -> Result<()> {
+    use repository::{Fetch, Repository};
+
+    let mut conn = model_table()?;
+    let model = |label: &str| Model { id: Key(None), label: label.to_owned() };
+
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    let mut a = model("a");
+    tx.insert(&mut a)?;
+    tx.insert(&mut model("b"))?;
+    assert_eq!(a.id, Key::new(1));
+
+    a.label = "c".to_owned();
+    tx.update(&mut a)?;
+    let mut entity = Entity::<Int, Model>::Key(Key::new(1));
+    assert_eq!(tx.fetch(&mut entity)?.label, "c");
+    assert!(matches!(Repository::<Model>::get(&tx, &3), Err(Error::NotFound)));
+    assert!(matches!(tx.update(&mut Model { id: Key::new(3), label: "d".to_owned() }), Err(Error::NotFound)));
+
+    Repository::<Model>::delete(&tx, &2)?;
+    assert!(matches!(Repository::<Model>::delete(&tx, &2), Err(Error::NotFound)));
+    tx.commit()?;
+
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    tx.insert(&mut model("d"))?;
+    tx.upsert(&mut model("c"))?;
+    assert_eq!(Repository::<Model>::list(&tx)?.len(), 2);
+    tx.rollback()?;
+
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    let labels = Repository::<Model>::list(&tx)?.into_iter().map(|model| model.label).collect::<Vec<_>>();
+    assert_eq!(labels, ["c"]);
+
+    tx.insert(&mut model("e"))?;
+    tx.insert(&mut model("f"))?;
+    assert_eq!(Repository::<Model>::delete_many(&tx, &[&1, &3, &9])?, 2);
+    assert_eq!(Repository::<Model>::delete_many(&tx, &[])?, 0);
+    assert_eq!(Repository::<Model>::list(&tx)?.len(), 1);
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_get_with() -> Result<()> {
+    use repository::Repository;
+
+    let mut conn = model_table()?;
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    tx.insert(&mut Model { id: Key(None), label: "a".to_owned() })?;
+    tx.insert(&mut Model { id: Key(None), label: "b".to_owned() })?;
+
+    let model: Model = tx.get_with(&1, &["label"])?;
+    assert_eq!((model.id, model.label), (Key::new(1), "a".to_owned()));
+    let model: Model = tx.get_with(&2, &[])?;
+    assert_eq!((model.id, model.label), (Key::new(2), String::new()));
+    assert!(matches!(Repository::<Model>::get_with(&tx, &3, &[]), Err(Error::NotFound)));
+    assert!(matches!(
+        Repository::<Model>::get_with(&tx, &1, &["name"]),
+        Err(Error::Sqlite(rusqlite::Error::InvalidColumnName(name))) if name == "name"
+    ));
+
+    let labels = Repository::<Model>::list_with(&tx, &["id"])?.into_iter().map(|model| model.label).collect::<Vec<_>>();
+    assert_eq!(labels, ["", ""]);
+    Ok(())
+}
+
+/// Scopes `Model`s to the labels starting with a prefix
+#[cfg(feature = "rusqlite")]
+struct LabelPrefix(&'static str);
+
+#[cfg(feature = "rusqlite")]
+impl sql::ScopePolicy<Model> for LabelPrefix {
+    fn filter(&self) -> Option<sql::Filter> {
+        Some(sql::Filter::new("label LIKE ? || '%'", vec![self.0.into()]))
+    }
+
+    fn check_write(&self, model: &Model) -> Result<()> {
+        match model.label.starts_with(self.0) {
+            true => Ok(()),
+            false => Err(Error::ScopeMismatch),
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_scoped_repository() -> Result<()> {
+    use repository::Repository;
+
+    let conn = model_table()?;
+    let model = |id: Option<Int>, label: &str| Model { id: Key(id), label: label.to_owned() };
+    let a = sqlite::ScopedRepository::new(&conn, LabelPrefix("a"));
+    let b = sqlite::ScopedRepository::new(&conn, LabelPrefix("b"));
+
+    a.insert(&mut model(None, "a1"))?;
+    b.insert(&mut model(None, "b2"))?;
+    b.insert(&mut model(None, "b3"))?;
+    assert!(matches!(a.insert(&mut model(None, "b4")), Err(Error::ScopeMismatch)));
+
+    assert_eq!(Repository::<Model>::list(&a)?.len(), 1);
+    assert_eq!(Repository::<Model>::list(&b)?.len(), 2);
+    assert_eq!(Repository::<Model>::get(&b, &2)?.label, "b2");
+    assert!(matches!(Repository::<Model>::get(&a, &2), Err(Error::NotFound)));
+    assert_eq!(Repository::<Model>::get_with(&a, &1, &[])?.label, "");
+
+    assert!(matches!(a.update(&mut model(Some(2), "a2")), Err(Error::NotFound)));
+    assert!(matches!(b.update(&mut model(Some(2), "a2")), Err(Error::ScopeMismatch)));
+    b.update(&mut model(Some(2), "bb"))?;
+    b.upsert(&mut model(Some(3), "bc"))?;
+    assert!(a.upsert(&mut model(Some(3), "ac")).is_err());
+    a.upsert(&mut model(Some(4), "ad"))?;
+    let labels = Repository::<Model>::list(&b)?.into_iter().map(|model| model.label).collect::<Vec<_>>();
+    assert_eq!(labels, ["bb", "bc"]);
+
+    assert!(matches!(Repository::<Model>::delete(&a, &2), Err(Error::NotFound)));
+    assert_eq!(Repository::<Model>::delete_many(&a, &[&1, &2, &3, &4])?, 2);
+    assert_eq!(Repository::<Model>::list(&b)?.len(), 2);
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_label_index() -> Result<()> {
+    let conn = model_table()?;
+    let insert = |label: &str| sqlite::insert(&conn, &mut Model { id: Key(None), label: label.to_owned() });
+    insert("Porto Alegre")?;
+
+    let index = sqlite::LabelIndex::new("labels");
+    assert!(matches!(index.register::<Model>(&conn, "label"), Err(Error::Sqlite(_))));
+    assert!(conn.is_autocommit());
+    index.create(&conn)?;
+    assert!(matches!(
+        index.register::<Model>(&conn, "name"),
+        Err(Error::Sqlite(rusqlite::Error::InvalidColumnName(name))) if name == "name"
+    ));
+    index.register::<Model>(&conn, "label")?;
+    insert("Porto Velho")?;
+    insert("São Paulo")?;
+
+    assert_eq!(index.search_tags(&conn, "port")?.len(), 2);
+    assert_eq!(index.search_tags(&conn, "por ale")?, [Tag::new("1", "Porto Alegre")]);
+    assert_eq!(index.search_tags(&conn, "sao")?, [Tag::new("3", "São Paulo")]);
+    assert!(index.search_tags(&conn, "  ")?.is_empty());
+    assert!(index.search_tags(&conn, "\"port OR")?.is_empty());
+
+    conn.execute("UPDATE model SET label = 'Curitiba' WHERE id = 2", [])?;
+    conn.execute("DELETE FROM model WHERE id = 1", [])?;
+    assert!(index.search_tags(&conn, "port")?.is_empty());
+    assert_eq!(index.search_tags(&conn, "curi")?, [Tag::new("2", "Curitiba")]);
+
+    index.register::<Model>(&conn, "label")?;
+    assert_eq!(index.search_tags(&conn, "curi")?.len(), 1);
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_savepoint_context() -> Result<()> {
+    use repository::Repository;
+
+    let mut conn = model_table()?;
+    let model = |label: &str| Model { id: Key(None), label: label.to_owned() };
+    let labels = |repo: &dyn Repository<Model>| -> Result<Vec<String>> {
+        Ok(repo.list()?.into_iter().map(|model| model.label).collect())
+    };
+
+    let mut tx = sqlite::TxContext::begin(&mut conn)?;
+    tx.insert(&mut model("a"))?;
+
+    let mut outer = tx.savepoint()?;
+    outer.insert(&mut model("b"))?;
+    let inner = outer.savepoint()?;
+    inner.insert(&mut model("c"))?;
+    inner.rollback()?;
+    let inner = outer.savepoint()?;
+    inner.update(&mut Model { id: Key::new(1), label: "d".to_owned() })?;
+    inner.commit()?;
+    assert_eq!(labels(&outer)?, ["d", "b"]);
+    outer.commit()?;
+
+    let dropped = tx.savepoint()?;
+    dropped.insert(&mut model("e"))?;
+    drop(dropped);
+
+    let rolled_back = tx.savepoint()?;
+    Repository::<Model>::delete(&rolled_back, &2)?;
+    rolled_back.rollback()?;
+    tx.commit()?;
+
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    assert_eq!(labels(&tx)?, ["d", "b"]);
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_pooled_context() -> Result<()> {
+    use std::sync::{Arc, Mutex};
+
+    use repository::{Fetch, Repository};
+
+    let context = sqlite::PooledContext::new(Arc::new(Mutex::new(model_table()?)));
+    let workers = (0..4)
+        .map(|worker| {
+            let context = context.clone();
+            std::thread::spawn(move || context.insert(&mut Model { id: Key(None), label: format!("w{worker}") }))
+        })
+        .collect::<Vec<_>>();
+    for worker in workers {
+        worker.join().unwrap()?;
+    }
+    assert_eq!(Repository::<Model>::list(&context)?.len(), 4);
+
+    let mut entity = Entity::<Int, Model>::Key(Key::new(2));
+    assert!(context.fetch(&mut entity)?.label.starts_with('w'));
+
+    context.update(&mut Model { id: Key::new(2), label: "b".to_owned() })?;
+    assert_eq!(Repository::<Model>::get(&context, &2)?.label, "b");
+    assert_eq!(Repository::<Model>::delete_many(&context, &[&1, &2, &9])?, 2);
+    assert!(matches!(Repository::<Model>::delete(&context, &1), Err(Error::NotFound)));
+
+    let conn = context.connection()?;
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM model", [], |row| row.get(0))?;
+    assert_eq!(count, 2);
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_sqlite_audit_sink() -> Result<()> {
+    use audit::{AuditedRepository, SqliteAuditSink};
+    use repository::Repository;
+
+    let mut conn = model_table()?;
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    let sink = SqliteAuditSink::new(&tx);
+    sink.create_table()?;
+
+    let repo = AuditedRepository::new(&tx, &sink, "ann");
+    let mut model = Model { id: Key(None), label: "a".to_owned() };
+    repo.insert(&mut model)?;
+    model.label = "b".to_owned();
+    repo.update(&mut model)?;
+    repo.update(&mut model)?;
+    drop(repo);
+    tx.commit()?;
+
+    let mut statement = conn.prepare("SELECT entity, key, action, actor, field, old, new FROM audit_log ORDER BY id")?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, rusqlite::types::Value>(5)?,
+                row.get::<_, rusqlite::types::Value>(6)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    use rusqlite::types::Value::{Integer, Null, Text};
+    let text = |text: &str| Some(text.to_owned());
+    let row = |action: &str, field, old, new| ("model".to_owned(), text("1"), action.to_owned(), "ann".to_owned(), field, old, new);
+    assert_eq!(rows, [
+        row("insert", text("id"), Null, Integer(1)),
+        row("insert", text("label"), Null, Text("a".to_owned())),
+        row("update", text("label"), Text("a".to_owned()), Text("b".to_owned())),
+        row("update", None, Null, Null),
+    ]);
+
+    drop(statement);
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    let sink = SqliteAuditSink::with_table(&tx, "group");
+    sink.create_table()?;
+    Repository::<Model>::delete(&AuditedRepository::new(&tx, &sink, "ann"), &1)?;
+    tx.commit()?;
+    assert_eq!(conn.query_row(r#"SELECT action FROM "group""#, [], |row| row.get::<_, String>(0))?, "delete");
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_sqlite_outbox() -> Result<()> {
+    use audit::{AuditAction, AuditEntry, AuditedRepository};
+    use outbox::{OutboxEvent, SqliteOutbox};
+    use repository::Repository;
+
+    fn events(entry: &AuditEntry) -> Vec<OutboxEvent> {
+        let topic = format!("model.{}", entry.action);
+        match entry.action {
+            AuditAction::Update if entry.changes.is_empty() => Vec::new(),
+            AuditAction::Update => entry.changes.iter().map(|change| OutboxEvent::new(&topic, entry).with_payload(change.column)).collect(),
+            _ => vec![OutboxEvent::new(topic, entry)],
+        }
+    }
+
+    let mut conn = model_table()?;
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    let outbox = SqliteOutbox::new(&tx, events);
+    outbox.create_table()?;
+    let repo = AuditedRepository::new(&tx, &outbox, "ann");
+    let mut model = Model { id: Key(None), label: "a".to_owned() };
+    repo.insert(&mut model)?;
+    drop(repo);
+    tx.commit()?;
+
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    let outbox = SqliteOutbox::new(&tx, events);
+    let repo = AuditedRepository::new(&tx, &outbox, "ann");
+    model.label = "b".to_owned();
+    repo.update(&mut model)?;
+    Repository::<Model>::delete(&repo, &1)?;
+    drop(repo);
+    tx.rollback()?;
+
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    let outbox = SqliteOutbox::new(&tx, events);
+    let repo = AuditedRepository::new(&tx, &outbox, "ann");
+    repo.update(&mut model)?;
+    repo.update(&mut model)?;
+    drop(repo);
+    tx.commit()?;
+
+    let outbox = SqliteOutbox::new(&conn, events);
+    let pending = outbox.pending(10)?;
+    let summary = pending.iter().map(|(_, event)| (event.topic.as_str(), event.key.as_deref(), event.payload.as_str())).collect::<Vec<_>>();
+    assert_eq!(summary, [("model.insert", Some("1"), ""), ("model.update", Some("1"), "label")]);
+    assert_eq!(pending[0].1.entity, "model");
+
+    outbox.acknowledge(&[pending[0].0])?;
+    assert_eq!(outbox.pending(1)?, pending[1..]);
+    outbox.acknowledge(&[pending[1].0])?;
+    assert!(outbox.pending(10)?.is_empty());
+
+    let tx = sqlite::TxContext::begin(&mut conn)?;
+    let outbox = SqliteOutbox::with_table(&tx, "order", events);
+    outbox.create_table()?;
+    AuditedRepository::new(&tx, &outbox, "ann").insert(&mut Model { id: Key(None), label: "c".to_owned() })?;
+    let pending = outbox.pending(10)?;
+    assert_eq!(pending.len(), 1);
+    outbox.acknowledge(&[pending[0].0])?;
+    assert!(outbox.pending(10)?.is_empty());
+    drop(outbox);
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_named_params() -> Result<()> {
+    use sql::Columns;
+
+    let conn = model_table()?;
+    let model = Model { id: Key::new(3), label: "a".to_owned() };
+    let params = model.named_params()?;
+    assert_eq!(params.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), [":id", ":label"]);
+    assert_eq!(params.get(":label"), Some(&sql::Value::Text("a".to_owned())));
+    assert_eq!(params.get("id"), Some(&sql::Value::Integer(3)));
+    assert_eq!(params.get("missing"), None);
+
+    conn.execute("INSERT INTO model (label, id) VALUES (:label, :id)", &*params.as_params())?;
+    let label: String = conn.query_row("SELECT label FROM model WHERE id = 3", [], |row| row.get(0))?;
+    assert_eq!(label, "a");
+
+    let unset = Model { id: Key(None), label: "b".to_owned() }.named_params()?;
+    assert_eq!(unset.get("id"), Some(&sql::Value::Null));
+    Ok(())
+}
+
+#[cfg(feature = "rusqlite")]
+#[test]
+fn test_unique_violation() -> Result<()> {
+    let meta = <Model as schema::Schema>::entity_meta();
+    assert_eq!(meta.unique_with("label"), Some(&["label".into()][..]));
+    assert_eq!(meta.unique_with("id"), None);
+
+    let conn = model_table()?;
+    let mut models = [Model { id: Key(None), label: "a".to_owned() }, Model { id: Key(None), label: "a".to_owned() }];
+    let err = sqlite::insert_all(&conn, &mut models).unwrap_err();
+    match sqlite::unique_violation(&models[1], err) {
+        Error::Invalid(errors) => assert_eq!(errors.to_string(), "label: is already taken"),
+        err => panic!("unexpected error: {err}"),
+    }
+
+    let err = Error::KeyEmpty;
+    assert!(matches!(sqlite::unique_violation(&models[1], err), Error::KeyEmpty));
     Ok(())
 }
@@ -0,0 +1,92 @@
+//! Creation and modification timestamps for entities
+//!
+//! `#[derive(Entity)]` implements [`Timestamps`] for structs marked with
+//! `#[entity(timestamps)]`, which must have `created_at` and `updated_at`
+//! fields of type `Option<T>`, where `T` is one of the [`Timestamp`] types:
+//!
+//! - `std::time::SystemTime`
+//! - `chrono::DateTime<Utc>` and `chrono::NaiveDateTime` with the `chrono` feature
+//! - `time::OffsetDateTime` and `time::PrimitiveDateTime` with the `time` feature
+//!
+//! Repositories call [`Timestamps::touch_insert`] and
+//! [`Timestamps::touch_update`] through the `hooks::Lifecycle` the derive
+//! implements, right before writing the entity.
+
+use std::time::SystemTime;
+
+/// Types that can be used as entity timestamps
+pub trait Timestamp: Sized {
+    /// Returns the current time
+    fn now() -> Self;
+}
+
+impl Timestamp for SystemTime {
+    fn now() -> Self {
+        SystemTime::now()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Timestamp for chrono::DateTime<chrono::Utc> {
+    fn now() -> Self {
+        chrono::Utc::now()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Timestamp for chrono::NaiveDateTime {
+    fn now() -> Self {
+        chrono::Utc::now().naive_utc()
+    }
+}
+
+#[cfg(feature = "time")]
+impl Timestamp for time::OffsetDateTime {
+    fn now() -> Self {
+        time::OffsetDateTime::now_utc()
+    }
+}
+
+#[cfg(feature = "time")]
+impl Timestamp for time::PrimitiveDateTime {
+    fn now() -> Self {
+        let now = time::OffsetDateTime::now_utc();
+        time::PrimitiveDateTime::new(now.date(), now.time())
+    }
+}
+
+/// Trait for entities that keep track of when they were created and updated
+pub trait Timestamps {
+    /// The type of the timestamps
+    type Timestamp: Timestamp;
+
+    /// Returns when the entity was created, if it was saved already
+    fn created_at(&self) -> Option<&Self::Timestamp>;
+
+    /// Returns when the entity was last updated, if it was saved already
+    fn updated_at(&self) -> Option<&Self::Timestamp>;
+
+    /// Sets when the entity was created
+    fn set_created_at(&mut self, timestamp: Option<Self::Timestamp>);
+
+    /// Sets when the entity was last updated
+    fn set_updated_at(&mut self, timestamp: Option<Self::Timestamp>);
+
+    /// Sets both timestamps to the current time, for inserting the entity
+    fn touch_insert(&mut self) where Self::Timestamp: Clone {
+        let now = Self::Timestamp::now();
+        self.set_created_at(Some(now.clone()));
+        self.set_updated_at(Some(now));
+    }
+
+    /// Sets the updated timestamp to the current time, for updating the entity
+    ///
+    /// The created timestamp is also set if it is missing.
+    fn touch_update(&mut self) where Self::Timestamp: Clone {
+        let now = Self::Timestamp::now();
+        if self.created_at().is_none() {
+            self.set_created_at(Some(now.clone()));
+        }
+        self.set_updated_at(Some(now));
+    }
+}
@@ -0,0 +1,34 @@
+//! Tracing of database access, with the `tracing` feature
+//!
+//! Every fetch and write runs inside a `dbent` span at DEBUG level with the
+//! operation, entity and key when known, and ends with an event holding the
+//! number of rows and the duration, or the error. Without the feature the
+//! operations run untraced.
+
+use core::fmt::Debug;
+
+use crate::Result;
+
+/// Runs `operation` on `entity` inside a span, recording the rows it returned
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn traced<R>(
+    operation: &'static str,
+    entity: &str,
+    key: Option<&dyn Debug>,
+    run: impl FnOnce() -> Result<R>,
+    rows: impl FnOnce(&R) -> usize,
+) -> Result<R> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("dbent", operation, entity, key = key.map(tracing::field::debug)).entered();
+    #[cfg(feature = "tracing")]
+    let started = std::time::Instant::now();
+
+    let result = run();
+
+    #[cfg(feature = "tracing")]
+    match &result {
+        Ok(value) => tracing::debug!(rows = rows(value), elapsed = ?started.elapsed(), "{operation} done"),
+        Err(err) => tracing::debug!(error = %err, elapsed = ?started.elapsed(), "{operation} failed"),
+    }
+    result
+}
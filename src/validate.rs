@@ -0,0 +1,234 @@
+//! Validation of entities before they are saved
+//!
+//! The [`Validate`] trait can be implemented by hand or generated with
+//! `#[derive(Validate)]`, which supports the following field attributes:
+//!
+//! - `#[validate(length(min = 1, max = 50))]`: for strings, vectors and fetched `Many`s
+//! - `#[validate(range(min = 0, max = 100))]`: for numbers
+//! - `#[validate(required)]`: the field can't be `None`, for `Entity`, `EntityLabel`, `Many`, `Key` and `Option`
+//! - `#[validate(required_key)]`: the `Keyed` field must have a key set
+//! - `#[validate(custom = "path::to::function")]`: calls `fn(&FieldType) -> Result<(), String>`
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use dbent::prelude::*;
+//! use dbent::validate::{Validate, FieldErrorKind};
+//!
+//! #[derive(Entity, Validate)]
+//! struct User {
+//!     id: Key<Int>,
+//!     #[validate(length(min = 1))]
+//!     name: String,
+//! }
+//!
+//! let user = User { id: Key(None), name: String::new() };
+//! let errors = user.validate().unwrap_err();
+//! assert_eq!(errors.errors()[0].field, "name");
+//! assert!(matches!(errors.errors()[0].kind, FieldErrorKind::Length { .. }));
+//! # }
+//! ```
+
+use core::fmt;
+
+use crate::{Entity, EntityLabel, Key, Many};
+
+/// Trait for entities that can check their own fields before being saved
+pub trait Validate {
+    /// Returns all the field errors found, if any
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// The kind of a field validation error
+#[derive(Clone, PartialEq, Debug)]
+pub enum FieldErrorKind {
+    /// for a length outside the allowed bounds
+    Length {
+        /// Minimum length allowed
+        min: Option<usize>,
+        /// Maximum length allowed
+        max: Option<usize>,
+        /// The length found
+        actual: usize,
+    },
+    /// for a number outside the allowed bounds
+    Range {
+        /// Minimum value allowed
+        min: Option<f64>,
+        /// Maximum value allowed
+        max: Option<f64>,
+    },
+    /// for a required field that is not set
+    Required,
+    /// for a required key that is not set
+    RequiredKey,
+    /// for a value already taken by another entity, violating a UNIQUE constraint
+    Unique,
+    /// for an error returned by a custom validation function
+    Custom(String),
+}
+
+/// A validation error for a single field
+#[derive(Clone, PartialEq, Debug)]
+pub struct FieldError {
+    /// Name of the struct field
+    pub field: &'static str,
+    /// What went wrong
+    pub kind: FieldErrorKind,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            FieldErrorKind::Length { min, max, actual } => {
+                write!(f, "{}: length {actual} is out of bounds", self.field)?;
+                write_bounds(f, min, max)
+            },
+            FieldErrorKind::Range { min, max } => {
+                write!(f, "{}: value is out of range", self.field)?;
+                write_bounds(f, min, max)
+            },
+            FieldErrorKind::Required => write!(f, "{}: is required", self.field),
+            FieldErrorKind::RequiredKey => write!(f, "{}: a key is required", self.field),
+            FieldErrorKind::Unique => write!(f, "{}: is already taken", self.field),
+            FieldErrorKind::Custom(message) => write!(f, "{}: {message}", self.field),
+        }
+    }
+}
+
+fn write_bounds<T: fmt::Display>(f: &mut fmt::Formatter<'_>, min: &Option<T>, max: &Option<T>) -> fmt::Result {
+    match (min, max) {
+        (Some(min), Some(max)) => write!(f, " [{min}, {max}]"),
+        (Some(min), None) => write!(f, " [{min}, ..]"),
+        (None, Some(max)) => write!(f, " [.., {max}]"),
+        (None, None) => Ok(()),
+    }
+}
+
+/// The collection of field errors found while validating an entity
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct ValidationErrors(Vec<FieldError>);
+
+impl ValidationErrors {
+    /// Creates an empty collection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an error for `field`
+    pub fn add(&mut self, field: &'static str, kind: FieldErrorKind) {
+        self.0.push(FieldError { field, kind });
+    }
+
+    /// Returns all the errors in the order they were found
+    pub fn errors(&self) -> &[FieldError] {
+        &self.0
+    }
+
+    /// Returns the errors for a single field
+    pub fn field(&self, field: &str) -> impl Iterator<Item = &FieldError> {
+        let field = field.to_owned();
+        self.0.iter().filter(move |error| error.field == field)
+    }
+
+    /// Is this collection empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns `Ok` if there are no errors or `Err` with self otherwise
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Types that have a length to validate
+///
+/// Returns `None` when there is nothing to check, like a `Many` that was not fetched.
+pub trait HasLength {
+    /// Returns the length
+    fn length(&self) -> Option<usize>;
+}
+
+impl HasLength for String {
+    fn length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl HasLength for &str {
+    fn length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl<T> HasLength for Vec<T> {
+    fn length(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<T> HasLength for Many<T> {
+    fn length(&self) -> Option<usize> {
+        self.data().ok().map(Vec::len)
+    }
+}
+
+impl<T: HasLength> HasLength for Option<T> {
+    fn length(&self) -> Option<usize> {
+        self.as_ref().and_then(HasLength::length)
+    }
+}
+
+/// Types that can be checked for a required value
+pub trait Required {
+    /// Is the value set?
+    fn is_present(&self) -> bool;
+}
+
+impl<K, T> Required for Entity<K, T> {
+    fn is_present(&self) -> bool {
+        !self.is_none()
+    }
+}
+
+impl<K, T, L> Required for EntityLabel<K, T, L> {
+    fn is_present(&self) -> bool {
+        !self.is_none()
+    }
+}
+
+impl<T> Required for Many<T> {
+    fn is_present(&self) -> bool {
+        !self.is_none()
+    }
+}
+
+impl<K> Required for Key<K> {
+    fn is_present(&self) -> bool {
+        self.is_some()
+    }
+}
+
+impl<T> Required for Option<T> {
+    fn is_present(&self) -> bool {
+        self.is_some()
+    }
+}
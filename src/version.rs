@@ -0,0 +1,137 @@
+//! Optimistic locking with version numbers
+//!
+//! Entities that store a [`Version`] are only updated when the version in the
+//! database matches the one they were fetched with; the version is bumped on
+//! every successful update. `#[derive(Entity)]` implements [`Versioned`] when
+//! a field is marked with `#[version]`, and repositories then route their
+//! updates through [`update_versioned`].
+
+use core::fmt;
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+#[cfg(feature = "rusqlite")]
+use crate::sql::quote_ident;
+use crate::{Error, Result};
+
+/// A newtype holding the version number of an entity
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct Version(pub i64);
+
+impl Version {
+    /// Returns the version that follows this one
+    pub fn next(self) -> Self {
+        Self(self.0 + 1)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<i64> for Version {
+    fn from(value: i64) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl FromSql for Version {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(Version)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl ToSql for Version {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        self.0.to_sql()
+    }
+}
+
+/// Trait for entities that hold a Version for optimistic locking
+pub trait Versioned {
+    /// Returns the current version of the entity
+    fn version(&self) -> Version;
+
+    /// Sets the version of the entity
+    fn set_version(&mut self, version: Version);
+
+    /// Returns the name of the column holding the version, `version` by default
+    fn version_column(&self) -> &'static str {
+        "version"
+    }
+
+    /// Increments the version of the entity, returning the new one
+    fn bump_version(&mut self) -> Version {
+        let version = self.version().next();
+        self.set_version(version);
+        version
+    }
+
+    /// Fails with `Error::StaleVersion` if `current`, the version stored in
+    /// the database, is not the one this entity holds
+    fn check_version(&self, current: Version) -> Result<()> {
+        if self.version() == current {
+            Ok(())
+        } else {
+            Err(Error::StaleVersion { expected: self.version(), found: current })
+        }
+    }
+}
+
+/// Updates a versioned row only if its version wasn't changed since it was read
+///
+/// Runs `UPDATE table SET col = ?, ..., version_column = ? WHERE key_column = ? AND version_column = ?`
+/// and bumps the entity version on success. If no row is updated the entity
+/// is left untouched and `Error::StaleVersion` is returned.
+#[cfg(feature = "rusqlite")]
+pub fn update_versioned<T: Versioned + ?Sized>(
+    conn: &rusqlite::Connection,
+    entity: &mut T,
+    table: &str,
+    key_column: &str,
+    key: &dyn ToSql,
+    version_column: &str,
+    assignments: &[(&str, &dyn ToSql)],
+) -> Result<()> {
+    let current = entity.version();
+    let next = current.next();
+
+    let (table, key_column, version_column) = (quote_ident(table), quote_ident(key_column), quote_ident(version_column));
+    let mut sql = format!("UPDATE {table} SET ");
+    for (column, _) in assignments {
+        sql.push_str(&format!("{} = ?, ", quote_ident(column)));
+    }
+    sql.push_str(&format!("{version_column} = ? WHERE {key_column} = ? AND {version_column} = ?"));
+
+    let mut params = assignments.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+    params.extend([&next as &dyn ToSql, key, &current]);
+
+    match conn.execute(&sql, params.as_slice())? {
+        0 => {
+            let found = conn
+                .query_row(
+                    &format!("SELECT {version_column} FROM {table} WHERE {key_column} = ?"),
+                    [key],
+                    |row| row.get(0),
+                )
+                .map_err(|err| match err {
+                    rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+                    err => err.into(),
+                })?;
+            Err(Error::StaleVersion { expected: current, found })
+        },
+        _ => {
+            entity.set_version(next);
+            Ok(())
+        },
+    }
+}
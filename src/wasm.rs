@@ -0,0 +1,65 @@
+//! Conversions to and from JavaScript values, with the `wasm` feature
+//!
+//! Shared model crates compiled for `wasm32-unknown-unknown` can hand their
+//! entities to browser code as plain JS objects through serde, with
+//! [`to_js`] and [`from_js`]. [`Tag`] and [`Key`] convert with `From` and
+//! `TryFrom` too, an unset Key being `null`. `TryFrom` is implemented for
+//! Keys of integers and strings; other keys convert with [`from_js`].
+//!
+//! The conversions call into the JS runtime, so they only work on wasm32
+//! targets.
+
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::JsValue;
+
+use crate::{Error, Key, Result, Tag};
+
+/// Converts a serializable value, like an entity, into a JS value
+pub fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue> {
+    serde_wasm_bindgen::to_value(value).map_err(|err| Error::Js(err.to_string()))
+}
+
+/// Converts a JS value into a deserializable value, like an entity
+pub fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T> {
+    serde_wasm_bindgen::from_value(value).map_err(|err| Error::Js(err.to_string()))
+}
+
+/// Converts into a `{ key, label }` object
+impl From<Tag> for JsValue {
+    fn from(tag: Tag) -> Self {
+        // a pair of strings always converts
+        to_js(&tag).unwrap_or(JsValue::NULL)
+    }
+}
+
+impl TryFrom<JsValue> for Tag {
+    type Error = Error;
+
+    fn try_from(value: JsValue) -> Result<Self> {
+        from_js(value)
+    }
+}
+
+/// Converts into the key value, or `null` if unset
+impl<K: Into<JsValue>> From<Key<K>> for JsValue {
+    fn from(key: Key<K>) -> Self {
+        key.0.map_or(JsValue::NULL, Into::into)
+    }
+}
+
+macro_rules! impl_key_try_from_js {
+    ($($key:ty),*) => {
+        $(
+            /// Converts from the key value, or `null` or `undefined` for an unset Key
+            impl TryFrom<JsValue> for Key<$key> {
+                type Error = Error;
+
+                fn try_from(value: JsValue) -> Result<Self> {
+                    from_js(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_key_try_from_js!(i32, u32, i64, u64, usize, String);
@@ -0,0 +1,651 @@
+//! Compact binary encoding of entity graphs
+//!
+//! [`to_bytes`] and [`from_bytes`] encode any serde type, so two processes
+//! sharing dbent models can exchange partially fetched graphs: the fetch
+//! state of every relation is kept, even for the fields skipped with
+//! `#[serde(skip_serializing_if)]` like the ones of `#[serde_relations]`,
+//! and a value always encodes to the same bytes, as long as it has no hash
+//! maps. The encoding is not self-describing, so both sides need the same
+//! types.
+//!
+//! The bytes start with a header, the magic `DBW` and the version of the
+//! encoding, [`VERSION`]. The value follows:
+//!
+//! | Value | Encoding |
+//! |-------|----------|
+//! | `bool` | a byte, 0 or 1 |
+//! | unsigned integers | LEB128 varint |
+//! | signed integers | zigzag, then LEB128 varint |
+//! | `f32`, `f64` | little endian IEEE 754 |
+//! | `char` | varint of the scalar value |
+//! | strings, bytes | varint length, then the UTF-8 or raw bytes |
+//! | `Option` | 0 for None, 1 then the value for Some |
+//! | unit, unit structs | nothing |
+//! | newtype structs | the value |
+//! | sequences, maps | varint length, then the items, keys before values |
+//! | tuples | the fields in order |
+//! | structs | varint with a bit set per skipped field, then the other fields in order |
+//! | enums | varint variant index, then the content |
+//!
+//! So the dbent types are encoded as:
+//!
+//! | Type | Encoding |
+//! |------|----------|
+//! | `Key<K>` | 0 when unset, 1 then the key |
+//! | `Entity<K, T>` | 0 then the `Key`, 1 then the data, 2 for None |
+//! | `EntityLabel<K, T, L>` | 0 then the `Key` and the label, 1 then the data, 2 for None |
+//! | `Many<T>` | 0 then the length and the entities, 1 for NotFetched, 2 for None |
+//! | `Parent<K, T>` | the `Option` of the key |
+//!
+//! ```
+//! use dbent::prelude::*;
+//!
+//! let many: Many<Entity<i64, String>> = vec![Entity::Key(Key::new(-1)), Entity::Data(Box::new("a".to_owned()))].into();
+//! let bytes = dbent::wire::to_bytes(&many).unwrap();
+//! assert_eq!(bytes, [b'D', b'B', b'W', 1, 0, 2, 0, 1, 1, 1, 1, b'a']);
+//! assert_eq!(dbent::wire::from_bytes::<Many<Entity<i64, String>>>(&bytes).unwrap(), many);
+//! ```
+
+use core::fmt;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::{ser, Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// The bytes starting the encoding
+pub const MAGIC: [u8; 3] = *b"DBW";
+
+/// The version of the encoding written by [`to_bytes`]
+pub const VERSION: u8 = 1;
+
+/// Encodes the value, with the header
+///
+/// Fails with `Error::Wire` for sequences and maps of unknown length, and for
+/// structs skipping a field after their 128th.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut encoder = Encoder { out: MAGIC.to_vec(), structs: Vec::new() };
+    encoder.out.push(VERSION);
+    value.serialize(&mut encoder).map_err(|err| Error::Wire(err.0))?;
+    Ok(encoder.out)
+}
+
+/// Decodes a value encoded by [`to_bytes`]
+///
+/// Fails with `Error::UnsupportedFormatVersion` for bytes of a newer version
+/// of the encoding, and with `Error::Wire` for bytes that don't encode a `T`.
+pub fn from_bytes<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T> {
+    let body = bytes.strip_prefix(&MAGIC).ok_or_else(|| Error::Wire("missing header".to_owned()))?;
+    let (&version, body) = body.split_first().ok_or_else(|| Error::Wire("missing version".to_owned()))?;
+    if version > VERSION {
+        return Err(Error::UnsupportedFormatVersion { found: version.into(), supported: VERSION.into() });
+    }
+
+    let mut decoder = Decoder { input: body };
+    let value = T::deserialize(&mut decoder).map_err(|err| Error::Wire(err.0))?;
+    match decoder.input.len() {
+        0 => Ok(value),
+        left => Err(Error::Wire(format!("{left} bytes left after the value"))),
+    }
+}
+
+/// The error of encoding or decoding, wrapped in `Error::Wire`
+#[derive(Debug)]
+struct WireError(String);
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl ser::Error for WireError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        WireError(msg.to_string())
+    }
+}
+
+impl de::Error for WireError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        WireError(msg.to_string())
+    }
+}
+
+type WireResult<T> = core::result::Result<T, WireError>;
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  ENCODER  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+struct Encoder {
+    out: Vec<u8>,
+    /// The structs being encoded, innermost last
+    structs: Vec<StructState>,
+}
+
+/// Where a struct starts, the index of its next field and its skipped fields
+struct StructState {
+    start: usize,
+    index: u32,
+    skipped: u128,
+}
+
+impl Encoder {
+    fn varint(&mut self, mut value: u128) {
+        while value >= 0x80 {
+            self.out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn zigzag(&mut self, value: i128) {
+        self.varint(((value << 1) ^ (value >> 127)) as u128)
+    }
+
+    fn len(&mut self, len: Option<usize>) -> WireResult<()> {
+        let len = len.ok_or_else(|| WireError("sequences and maps need a known length".to_owned()))?;
+        self.varint(len as u128);
+        Ok(())
+    }
+
+    fn begin_struct(&mut self) {
+        self.structs.push(StructState { start: self.out.len(), index: 0, skipped: 0 });
+    }
+
+    fn next_field(&mut self, skip: bool) -> WireResult<()> {
+        let state = self.structs.last_mut().expect("inside a struct");
+        if skip {
+            let bit = 1u128.checked_shl(state.index).ok_or_else(|| WireError("only the first 128 fields of a struct can be skipped".to_owned()))?;
+            state.skipped |= bit;
+        }
+        state.index += 1;
+        Ok(())
+    }
+
+    /// Writes the skipped fields of the struct before its fields
+    fn end_struct(&mut self) {
+        let state = self.structs.pop().expect("inside a struct");
+        let fields = self.out.split_off(state.start);
+        self.varint(state.skipped);
+        self.out.extend(fields);
+    }
+}
+
+macro_rules! serialize_varint {
+    ($($method:ident($ty:ty) => $encode:ident,)*) => {
+        $(
+            fn $method(self, value: $ty) -> WireResult<()> {
+                self.$encode(value.into());
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for &mut Encoder {
+    type Ok = ();
+    type Error = WireError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, value: bool) -> WireResult<()> {
+        self.out.push(value.into());
+        Ok(())
+    }
+
+    serialize_varint! {
+        serialize_i8(i8) => zigzag,
+        serialize_i16(i16) => zigzag,
+        serialize_i32(i32) => zigzag,
+        serialize_i64(i64) => zigzag,
+        serialize_i128(i128) => zigzag,
+        serialize_u8(u8) => varint,
+        serialize_u16(u16) => varint,
+        serialize_u32(u32) => varint,
+        serialize_u64(u64) => varint,
+        serialize_u128(u128) => varint,
+        serialize_char(char) => varint,
+    }
+
+    fn serialize_f32(self, value: f32) -> WireResult<()> {
+        self.out.extend(value.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, value: f64) -> WireResult<()> {
+        self.out.extend(value.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_str(self, value: &str) -> WireResult<()> {
+        self.serialize_bytes(value.as_bytes())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> WireResult<()> {
+        self.varint(value.len() as u128);
+        self.out.extend(value);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> WireResult<()> {
+        self.out.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> WireResult<()> {
+        self.out.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> WireResult<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> WireResult<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, index: u32, _variant: &'static str) -> WireResult<()> {
+        self.varint(index.into());
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> WireResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, index: u32, _variant: &'static str, value: &T) -> WireResult<()> {
+        self.varint(index.into());
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> WireResult<Self> {
+        self.len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> WireResult<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> WireResult<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, index: u32, _variant: &'static str, _len: usize) -> WireResult<Self> {
+        self.varint(index.into());
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> WireResult<Self> {
+        self.len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> WireResult<Self> {
+        self.begin_struct();
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, index: u32, _variant: &'static str, _len: usize) -> WireResult<Self> {
+        self.varint(index.into());
+        self.begin_struct();
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! serialize_compound {
+    ($($trait:ident::$method:ident,)*) => {
+        $(
+            impl ser::$trait for &mut Encoder {
+                type Ok = ();
+                type Error = WireError;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> WireResult<()> {
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> WireResult<()> {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+serialize_compound! {
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field,
+}
+
+macro_rules! serialize_struct {
+    ($($trait:ident,)*) => {
+        $(
+            impl ser::$trait for &mut Encoder {
+                type Ok = ();
+                type Error = WireError;
+
+                fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> WireResult<()> {
+                    self.next_field(false)?;
+                    value.serialize(&mut **self)
+                }
+
+                fn skip_field(&mut self, _key: &'static str) -> WireResult<()> {
+                    self.next_field(true)
+                }
+
+                fn end(self) -> WireResult<()> {
+                    self.end_struct();
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+serialize_struct! {
+    SerializeStruct,
+    SerializeStructVariant,
+}
+
+impl ser::SerializeMap for &mut Encoder {
+    type Ok = ();
+    type Error = WireError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> WireResult<()> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> WireResult<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> WireResult<()> {
+        Ok(())
+    }
+}
+
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+//<<>><=========================  DECODER  ==========================><<>>//
+//<<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>><<>>//
+
+struct Decoder<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Decoder<'de> {
+    fn bytes(&mut self, len: usize) -> WireResult<&'de [u8]> {
+        if self.input.len() < len {
+            return Err(WireError("unexpected end of input".to_owned()));
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> WireResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varint(&mut self) -> WireResult<u128> {
+        let mut value = 0u128;
+        for shift in (0..128).step_by(7) {
+            let byte = self.byte()?;
+            value |= u128::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(WireError("varint too long".to_owned()))
+    }
+
+    fn zigzag(&mut self) -> WireResult<i128> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i128 ^ -((value & 1) as i128))
+    }
+
+    fn len(&mut self) -> WireResult<usize> {
+        usize::try_from(self.varint()?).map_err(|_| WireError("length out of range".to_owned()))
+    }
+}
+
+macro_rules! deserialize_varint {
+    ($($method:ident => $visit:ident($decode:ident),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+                let value = self.$decode()?;
+                visitor.$visit(value.try_into().map_err(|_| WireError(format!("{value} is out of range")))?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Decoder<'de> {
+    type Error = WireError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> WireResult<V::Value> {
+        Err(WireError("the wire encoding is not self-describing".to_owned()))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        match self.byte()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            byte => Err(WireError(format!("invalid bool {byte}"))),
+        }
+    }
+
+    deserialize_varint! {
+        deserialize_i8 => visit_i8(zigzag),
+        deserialize_i16 => visit_i16(zigzag),
+        deserialize_i32 => visit_i32(zigzag),
+        deserialize_i64 => visit_i64(zigzag),
+        deserialize_i128 => visit_i128(zigzag),
+        deserialize_u8 => visit_u8(varint),
+        deserialize_u16 => visit_u16(varint),
+        deserialize_u32 => visit_u32(varint),
+        deserialize_u64 => visit_u64(varint),
+        deserialize_u128 => visit_u128(varint),
+        deserialize_identifier => visit_u32(varint),
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        let bytes = self.bytes(4)?.try_into().expect("4 bytes");
+        visitor.visit_f32(f32::from_le_bytes(bytes))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        let bytes = self.bytes(8)?.try_into().expect("8 bytes");
+        visitor.visit_f64(f64::from_le_bytes(bytes))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        let value = self.varint()?;
+        let c = u32::try_from(value).ok().and_then(char::from_u32).ok_or_else(|| WireError(format!("invalid char {value}")))?;
+        visitor.visit_char(c)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        let len = self.len()?;
+        let text = core::str::from_utf8(self.bytes(len)?).map_err(|err| WireError(err.to_string()))?;
+        visitor.visit_borrowed_str(text)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        let len = self.len()?;
+        visitor.visit_borrowed_bytes(self.bytes(len)?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        match self.byte()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            byte => Err(WireError(format!("invalid option tag {byte}"))),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> WireResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> WireResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        let len = self.len()?;
+        visitor.visit_seq(Items { decoder: self, len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> WireResult<V::Value> {
+        visitor.visit_seq(Items { decoder: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> WireResult<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        let len = self.len()?;
+        visitor.visit_map(Items { decoder: self, len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> WireResult<V::Value> {
+        let skipped = self.varint()?;
+        visitor.visit_map(Fields { decoder: self, len: fields.len(), index: 0, skipped })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> WireResult<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> WireResult<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// The items of a sequence, tuple or map
+struct Items<'a, 'de> {
+    decoder: &'a mut Decoder<'de>,
+    len: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Items<'_, 'de> {
+    type Error = WireError;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> WireResult<Option<S::Value>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Items<'_, 'de> {
+    type Error = WireError;
+
+    fn next_key_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> WireResult<Option<S::Value>> {
+        de::SeqAccess::next_element_seed(self, seed)
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> WireResult<S::Value> {
+        seed.deserialize(&mut *self.decoder)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+/// The fields of a struct, keyed by their index, without the skipped ones
+struct Fields<'a, 'de> {
+    decoder: &'a mut Decoder<'de>,
+    len: usize,
+    index: usize,
+    skipped: u128,
+}
+
+impl<'de> de::MapAccess<'de> for Fields<'_, 'de> {
+    type Error = WireError;
+
+    fn next_key_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> WireResult<Option<S::Value>> {
+        while self.index < self.len && self.index < 128 && self.skipped & (1 << self.index) != 0 {
+            self.index += 1;
+        }
+        if self.index >= self.len {
+            return Ok(None);
+        }
+        self.index += 1;
+        seed.deserialize((self.index as u64 - 1).into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> WireResult<S::Value> {
+        seed.deserialize(&mut *self.decoder)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Decoder<'de> {
+    type Error = WireError;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> WireResult<(S::Value, Self)> {
+        let index = u32::try_from(self.varint()?).map_err(|_| WireError("variant index out of range".to_owned()))?;
+        let variant = seed.deserialize(index.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Decoder<'de> {
+    type Error = WireError;
+
+    fn unit_variant(self) -> WireResult<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> WireResult<S::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> WireResult<V::Value> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> WireResult<V::Value> {
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}